@@ -0,0 +1,105 @@
+//! Ingestion benchmarks (Path B4)
+//!
+//! Every benchmark runs on the same seeded synthetic repository
+//! (`vcr::testing::generate_repo`), so numbers are comparable across runs.
+//! Run `cargo bench --bench ingest --features parallel-execution` for a
+//! real parallel scan; without it `scan/parallel` falls back to serial.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::path::PathBuf;
+use tempfile::TempDir;
+use vcr::cpg::builder::CPGBuilder;
+use vcr::cpg::CPGEpoch;
+use vcr::io::{create_backend, IOMode};
+use vcr::pipeline::{Pipeline, PipelineOutput};
+use vcr::query::{QueryEngine, QuerySpec};
+use vcr::repo::RepoScanner;
+use vcr::testing::{edit_file, generate_repo};
+
+const FILES: usize = 200;
+const AVG_SIZE: usize = 2048;
+const SEED: u64 = 42;
+const SCAN_THREADS: usize = 4;
+const QUERY: &str = r#"{"pipeline": [{"find": "CfgNode"}, {"follow": "ControlFlow"}], "limit": 100}"#;
+
+/// Generated repository plus one full pipeline run over it
+struct Fixture {
+    dir: TempDir,
+    paths: Vec<PathBuf>,
+    output: PipelineOutput,
+}
+
+fn fixture() -> Fixture {
+    let dir = TempDir::new().unwrap();
+    let paths = generate_repo(dir.path(), FILES, AVG_SIZE, SEED).unwrap();
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    Fixture { dir, paths, output }
+}
+
+fn bench_io(c: &mut Criterion, f: &Fixture) {
+    let mut group = c.benchmark_group("io_read");
+    for mode in [IOMode::Hot, IOMode::Cold] {
+        let backend = create_backend(mode);
+        group.bench_function(backend.name(), |b| {
+            b.iter(|| {
+                f.paths.iter().map(|p| backend.read_file(p).unwrap().len()).sum::<usize>()
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_scan(c: &mut Criterion, f: &Fixture) {
+    let mut group = c.benchmark_group("scan");
+    for (name, threads) in [("serial", 1), ("parallel", SCAN_THREADS)] {
+        let scanner = RepoScanner::new(f.dir.path()).unwrap().with_extension("rs").with_threads(threads);
+        group.bench_function(name, |b| b.iter(|| black_box(scanner.scan().unwrap())));
+    }
+    group.finish();
+}
+
+fn bench_pipeline(c: &mut Criterion, f: &Fixture) {
+    let pipeline = Pipeline::default();
+    c.bench_function("pipeline_full_ingest", |b| {
+        b.iter(|| black_box(pipeline.run(f.dir.path()).unwrap()));
+    });
+
+    // A copy of the repo with one file edited; every iteration re-ingests it
+    // against the unedited run
+    let edited = TempDir::new().unwrap();
+    let paths = generate_repo(edited.path(), FILES, AVG_SIZE, SEED).unwrap();
+    let base = pipeline.run(edited.path()).unwrap();
+    edit_file(&paths[FILES / 2], SEED).unwrap();
+    c.bench_function("pipeline_incremental_one_file", |b| {
+        b.iter(|| black_box(pipeline.run_incremental(&base).unwrap()));
+    });
+}
+
+fn bench_fusion(c: &mut Criterion, f: &Fixture) {
+    c.bench_function("cpg_fusion", |b| {
+        b.iter(|| {
+            let mut epoch = CPGEpoch::new(f.output.semantic.epoch_id(), 0);
+            CPGBuilder::new().build(&f.output.semantic, &mut epoch).unwrap();
+            epoch.freeze()
+        });
+    });
+}
+
+fn bench_query(c: &mut Criterion, f: &Fixture) {
+    let spec = QuerySpec::from_json(QUERY).unwrap();
+    c.bench_function("query_cfg_successors", |b| {
+        b.iter(|| black_box(QueryEngine::new().execute(f.output.cpg_epoch.cpg(), &spec).unwrap()));
+    });
+}
+
+fn benches(c: &mut Criterion) {
+    let f = fixture();
+    bench_io(c, &f);
+    bench_scan(c, &f);
+    bench_pipeline(c, &f);
+    bench_fusion(c, &f);
+    bench_query(c, &f);
+}
+
+criterion_group!(ingest, benches);
+criterion_main!(ingest);
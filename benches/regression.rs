@@ -4,7 +4,8 @@
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use vcr::*;
-use vcr::cpg::model::{CPG, CPGNode, CPGNodeId, CPGNodeKind, OriginRef};
+use vcr::cpg::index::CPGIndices;
+use vcr::cpg::model::{CPG, CPGEdge, CPGEdgeId, CPGEdgeKind, CPGNode, CPGNodeId, CPGNodeKind, OriginRef};
 use vcr::types::ByteRange;
 use vcr::execution::{ExecutionPlan, Stage, Task, TaskId, WorkFragment, Scheduler, DeterministicOrder};
 
@@ -70,5 +71,90 @@ fn bench_cpg_hash(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_cpg_build, bench_query_execution, bench_cpg_hash);
+fn bench_invalidation(c: &mut Criterion) {
+    use semantic::invalidation::InvalidationTracker;
+
+    let file_id = FileId::new(1);
+    let mut tracker = InvalidationTracker::new();
+    for i in 0..50_000u64 {
+        let start = i as usize * 20;
+        tracker.track_ast_to_cfg(file_id, ByteRange::new(start, start + 15), NodeId(i));
+    }
+    tracker.invalidate(file_id, &[]);
+
+    c.bench_function("invalidate_50k_ranges", |b| {
+        b.iter(|| tracker.invalidate(file_id, black_box(&[ByteRange::new(500_000, 500_040)])));
+    });
+}
+
+fn bench_edges_from(c: &mut Criterion) {
+    // A chain of 1000 CFG nodes, each with a ControlFlow and a DataFlow edge
+    let mut cpg = CPG::new();
+    for i in 0..1000 {
+        cpg.add_node(CPGNode::new(
+            CPGNodeId(i),
+            CPGNodeKind::CfgNode,
+            OriginRef::Cfg { node_id: NodeId(i) },
+            ByteRange::new(0, 0),
+        ));
+    }
+    for i in 0..999 {
+        for kind in [CPGEdgeKind::ControlFlow, CPGEdgeKind::DataFlow] {
+            let id = CPGEdgeId(cpg.edges.len() as u64);
+            cpg.add_edge(CPGEdge::new(id, kind, CPGNodeId(i), CPGNodeId(i + 1)));
+        }
+    }
+    let indices = CPGIndices::build(&cpg);
+
+    c.bench_function("edges_from_collected_1k_nodes", |b| {
+        b.iter(|| (0..1000).map(|i| cpg.get_edges_from(CPGNodeId(i)).len()).sum::<usize>());
+    });
+    c.bench_function("edges_from_indexed_1k_nodes", |b| {
+        b.iter(|| (0..1000).map(|i| cpg.iter_edges_from(&indices, CPGNodeId(i)).count()).sum::<usize>());
+    });
+}
+
+fn bench_dfg_hash(c: &mut Criterion) {
+    use semantic::model::StringArena;
+    use semantic::{CanonicalHash, DFGValue, ValueId, ValueKind, DFG};
+    use sha2::{Digest, Sha256};
+
+    // 1000 variables over 50 names
+    let mut strings = StringArena::new();
+    let mut dfg = DFG::new(semantic::FunctionId(1));
+    for i in 0..1000 {
+        dfg.add_value(DFGValue {
+            id: ValueId(i),
+            kind: ValueKind::Variable { name: strings.intern(&format!("value_{}", i % 50)) },
+            source_range: ByteRange::new(0, 0),
+            origin: None,
+            occurrences: Vec::new(),
+        });
+    }
+
+    c.bench_function("dfg_hash_1k_values", |b| {
+        b.iter(|| black_box(dfg.compute_hash(&strings)));
+    });
+    // The kinds alone: canonical bytes vs the Debug text hashed before
+    c.bench_function("dfg_kinds_canonical_1k_values", |b| {
+        b.iter(|| {
+            let mut hasher = Sha256::new();
+            for value in &dfg.values {
+                value.kind.resolve(&strings).canonical_hash(&mut hasher);
+            }
+            black_box(hasher.finalize())
+        });
+    });
+    c.bench_function("dfg_kinds_debug_text_1k_values", |b| {
+        b.iter(|| {
+            let mut hasher = Sha256::new();
+            for value in &dfg.values {
+                hasher.update(format!("{:?}", value.kind.resolve(&strings)).as_bytes());
+            }
+            black_box(hasher.finalize())
+        });
+    });
+}
+
+criterion_group!(benches, bench_cpg_build, bench_query_execution, bench_cpg_hash, bench_invalidation, bench_edges_from, bench_dfg_hash);
 criterion_main!(benches);
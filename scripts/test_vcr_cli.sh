@@ -41,7 +41,7 @@ echo
 
 # Test 5: Query (placeholder)
 echo "Test 5: Query"
-echo '{"query": "find_functions"}' > /tmp/test_query.json
+echo '{"pipeline": [{"find": "Function"}], "limit": 10}' > /tmp/test_query.json
 RESULT=$($VTR query /tmp/test_query.json 2>&1)
 echo "$RESULT" | grep -q "\"status\":\"success\"" && echo "✓ Query executed"
 echo "$RESULT"
@@ -0,0 +1,194 @@
+//! Shadowed and unused bindings (`vcr lint shadowing`)
+//!
+//! Both passes read one file's SymbolTable and its recorded references.
+//! Only variables and parameters are considered; functions are left to
+//! dead function detection.
+//!
+//! - A binding shadows when an enclosing scope of the same function already
+//!   bound the name before it. Rebinding in the same scope (`let x = x;`
+//!   twice in one block) is idiomatic and not reported.
+//! - A binding is unused when no reference resolves to it. Names starting
+//!   with `_` and `self` are never reported.
+//!
+//! **Fails towards quiet**: names bound by patterns the table does not
+//! track (match arms, `if let`, closure parameters) resolve to an outer
+//! binding of the same name, which can hide an unused binding but never
+//! invents one.
+
+use crate::semantic::model::SymbolId;
+use crate::semantic::symbols::{ScopeKind, Symbol, SymbolKind, SymbolTable};
+use crate::types::ByteRange;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A binding hiding an outer one of the same name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowedBinding {
+    pub name: String,
+    pub kind: SymbolKind,
+
+    /// The shadowing binding
+    pub range: ByteRange,
+
+    /// Kind of the shadowed binding
+    pub shadowed_kind: SymbolKind,
+
+    /// The shadowed binding
+    pub shadowed_range: ByteRange,
+}
+
+/// A binding no reference resolves to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnusedBinding {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub range: ByteRange,
+}
+
+/// Both passes over every file of a repository
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BindingFindings {
+    /// (path, finding), sorted by path then range
+    pub shadowing: Vec<(PathBuf, ShadowedBinding)>,
+
+    /// (path, finding), sorted by path then range
+    pub unused: Vec<(PathBuf, UnusedBinding)>,
+}
+
+fn is_binding(symbol: &Symbol) -> bool {
+    matches!(symbol.kind, SymbolKind::Variable | SymbolKind::Parameter)
+}
+
+/// Bindings shadowing one in an enclosing scope of the same function,
+/// sorted by range
+pub fn find_shadowing(table: &SymbolTable) -> Vec<ShadowedBinding> {
+    let mut findings: Vec<ShadowedBinding> = table.symbols()
+        .filter(|symbol| is_binding(symbol))
+        .filter_map(|symbol| {
+            let shadowed = outer_binding(table, symbol)?;
+            Some(ShadowedBinding {
+                name: symbol.name.clone(),
+                kind: symbol.kind,
+                range: symbol.source_range,
+                shadowed_kind: shadowed.kind,
+                shadowed_range: shadowed.source_range,
+            })
+        })
+        .collect();
+    findings.sort_by_key(|f| (f.range.start, f.range.end, f.name.clone()));
+    findings
+}
+
+/// The latest binding of `symbol`'s name declared before it in an
+/// enclosing scope, up to the function scope
+fn outer_binding<'t>(table: &'t SymbolTable, symbol: &Symbol) -> Option<&'t Symbol> {
+    let mut scope = table.get_scope(symbol.scope)?;
+    while scope.kind != ScopeKind::Function {
+        scope = table.get_scope(scope.parent?)?;
+        let found = table.symbols()
+            .filter(|outer| outer.scope == scope.id && is_binding(outer))
+            .filter(|outer| outer.name == symbol.name && outer.id < symbol.id)
+            .max_by_key(|outer| outer.id);
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+/// Bindings no reference resolves to, sorted by range
+pub fn find_unused(table: &SymbolTable) -> Vec<UnusedBinding> {
+    let referenced: HashSet<SymbolId> = table.references().iter().map(|r| r.symbol).collect();
+    let mut findings: Vec<UnusedBinding> = table.symbols()
+        .filter(|symbol| is_binding(symbol) && !referenced.contains(&symbol.id))
+        .filter(|symbol| !symbol.name.starts_with('_') && symbol.name != "self")
+        .map(|symbol| UnusedBinding { name: symbol.name.clone(), kind: symbol.kind, range: symbol.source_range })
+        .collect();
+    findings.sort_by_key(|f| (f.range.start, f.range.end, f.name.clone()));
+    findings
+}
+
+/// Build a repository and run both passes on every file
+pub fn lint_repo(root: &Path, config: &crate::config::ValoriConfig) -> anyhow::Result<BindingFindings> {
+    let output = crate::pipeline::Pipeline::new(config).run(root)?;
+    let mut findings = BindingFindings::default();
+    for (file_id, meta) in &output.snapshot.files {
+        let Some(table) = output.semantic.get_symbols(*file_id) else { continue };
+        findings.shadowing.extend(find_shadowing(table).into_iter().map(|f| (meta.path.clone(), f)));
+        findings.unused.extend(find_unused(table).into_iter().map(|f| (meta.path.clone(), f)));
+    }
+    findings.shadowing.sort_by(|(a, x), (b, y)| (a, x.range.start, x.range.end).cmp(&(b, y.range.start, y.range.end)));
+    findings.unused.sort_by(|(a, x), (b, y)| (a, x.range.start, x.range.end).cmp(&(b, y.range.start, y.range.end)));
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::BufferedFile;
+    use crate::parse::IncrementalParser;
+    use crate::types::{FileId, Language};
+
+    fn table(source: &str) -> SymbolTable {
+        let file_id = FileId::new(1);
+        let file = BufferedFile::new(source.as_bytes().to_vec(), file_id);
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&file, None).unwrap();
+        let mut table = SymbolTable::new(file_id);
+        table.build(&parsed, source.as_bytes()).unwrap();
+        table
+    }
+
+    fn text(source: &str, range: ByteRange) -> &str {
+        &source[range.start..range.end]
+    }
+
+    #[test]
+    fn test_nested_block_shadowing() {
+        let source = "fn f() -> i32 {\n    let x = 1;\n    {\n        let x = x + 1;\n        x\n    }\n}\n";
+        let shadowing = find_shadowing(&table(source));
+
+        assert_eq!(shadowing.len(), 1);
+        assert_eq!(text(source, shadowing[0].range), "let x = x + 1;");
+        assert_eq!(text(source, shadowing[0].shadowed_range), "let x = 1;");
+        assert_eq!(shadowing[0].shadowed_kind, SymbolKind::Variable);
+        // The outer x is read by the inner initializer, the inner one by the tail
+        assert!(find_unused(&table(source)).is_empty());
+    }
+
+    #[test]
+    fn test_parameter_shadowing() {
+        let source = "fn f(n: u32) -> u32 {\n    let n = n * 2;\n    n\n}\n";
+        let shadowing = find_shadowing(&table(source));
+
+        assert_eq!(shadowing.len(), 1);
+        assert_eq!((shadowing[0].kind, shadowing[0].shadowed_kind), (SymbolKind::Variable, SymbolKind::Parameter));
+        assert_eq!(text(source, shadowing[0].shadowed_range), "n");
+    }
+
+    #[test]
+    fn test_same_scope_rebinding_and_later_outer_bindings_are_not_shadowing() {
+        let source = "fn f() -> i32 {\n    let a = 1;\n    let a = a + 1;\n    { let b = 2; b };\n    let b = a;\n    b\n}\n";
+        assert!(find_shadowing(&table(source)).is_empty());
+    }
+
+    #[test]
+    fn test_unused_bindings() {
+        let source = "fn f(used: u8, unused: u8, _ignored: u8) -> u8 {\n    let dead = 1;\n    let _quiet = 2;\n    let mut m = used;\n    m += 1;\n    m\n}\n";
+        let unused = find_unused(&table(source));
+
+        let names: Vec<_> = unused.iter().map(|u| (u.name.as_str(), u.kind)).collect();
+        assert_eq!(names, [("unused", SymbolKind::Parameter), ("dead", SymbolKind::Variable)]);
+    }
+
+    #[test]
+    fn test_binding_used_via_closure_is_not_unused() {
+        let source = "fn f() -> i32 {\n    let captured = 3;\n    let add = |v: i32| v + captured;\n    add(1)\n}\n";
+        assert!(find_unused(&table(source)).is_empty());
+    }
+
+    #[test]
+    fn test_functions_do_not_cross() {
+        let source = "fn outer(x: i32) -> i32 {\n    fn inner(x: i32) -> i32 { x }\n    inner(x)\n}\n";
+        assert!(find_shadowing(&table(source)).is_empty());
+    }
+}
@@ -0,0 +1,384 @@
+//! Name-resolved call graph (Step 3.6)
+//!
+//! Built from Tree-sitter parse trees, one file at a time. Functions are
+//! numbered exactly like CFGBuilder numbers them (parse order, nested
+//! functions belong to their enclosing function), so a `(FileId, FunctionId)`
+//! key names the same function in both.
+//!
+//! **Conservative, not precise**
+//! - Calls resolve by simple name to every function with that name
+//! - Method calls resolve by method name (no receiver types)
+//! - An identifier naming a function outside call position (function
+//!   pointers, `Type::method` values, macro arguments, imports) marks the
+//!   function as referenced
+//!
+//! Each function's local flows (see `summaries::FunctionFlows`) are
+//! extracted in the same walk.
+
+use super::summaries::FunctionFlows;
+use crate::semantic::model::FunctionId;
+use crate::types::{ByteRange, FileId, ParsedFile};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use tree_sitter::Node;
+
+/// Function key, shared with CFGs
+pub type FunctionKey = (FileId, FunctionId);
+
+/// One function in the call graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionInfo {
+    pub file_id: FileId,
+    pub function_id: FunctionId,
+    pub name: String,
+    pub source_range: ByteRange,
+
+    /// Declared plain `pub`
+    pub is_pub: bool,
+
+    /// Has a `#[test]` (or `#[<path>::test]`) attribute
+    pub is_test: bool,
+
+    /// Declared in a trait or a trait impl (callable through dispatch)
+    pub is_trait_method: bool,
+
+    /// Top-level item of a crate root file (lib.rs, main.rs, src/bin)
+    pub at_crate_root: bool,
+}
+
+/// Call graph over all added files
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    /// Functions by key (sorted)
+    functions: BTreeMap<FunctionKey, FunctionInfo>,
+
+    /// Callee names per caller
+    calls: BTreeMap<FunctionKey, BTreeSet<String>>,
+
+    /// Names referenced outside call position, per file
+    referenced: BTreeMap<FileId, BTreeSet<String>>,
+
+    /// Function keys by name
+    by_name: BTreeMap<String, Vec<FunctionKey>>,
+
+    /// Relative path per file
+    paths: BTreeMap<FileId, PathBuf>,
+
+    /// Local flows per function
+    flows: BTreeMap<FunctionKey, FunctionFlows>,
+}
+
+impl CallGraph {
+    /// Create an empty call graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the functions, calls and references of one parsed file
+    pub fn add_file(&mut self, path: &Path, parsed: &ParsedFile, source: &[u8]) {
+        let mut visitor = FileVisitor {
+            file_id: parsed.file_id,
+            source,
+            crate_root: is_crate_root(path),
+            next_function_id: 0,
+            skip: HashSet::new(),
+            graph: self,
+        };
+        visitor.visit(parsed.tree.root_node(), None);
+
+        self.paths.insert(parsed.file_id, path.to_path_buf());
+    }
+
+    /// All functions, ordered by key
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionInfo> {
+        self.functions.values()
+    }
+
+    /// Look up one function
+    pub fn function(&self, key: FunctionKey) -> Option<&FunctionInfo> {
+        self.functions.get(&key)
+    }
+
+    /// Every function a caller may call, ordered by key
+    pub fn callees(&self, caller: FunctionKey) -> Vec<FunctionKey> {
+        let mut callees: Vec<FunctionKey> = self.calls.get(&caller)
+            .into_iter()
+            .flatten()
+            .flat_map(|name| self.by_name.get(name).into_iter().flatten().copied())
+            .collect();
+        callees.sort();
+        callees.dedup();
+        callees
+    }
+
+    /// Every function a call by this name resolves to, ordered by key
+    pub fn resolve(&self, name: &str) -> &[FunctionKey] {
+        self.by_name.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Local flows of one function
+    pub fn flows(&self, key: FunctionKey) -> Option<&FunctionFlows> {
+        self.flows.get(&key)
+    }
+
+    /// Whether a function name is used outside call position
+    pub fn is_referenced(&self, name: &str) -> bool {
+        self.referenced.values().any(|names| names.contains(name))
+    }
+
+    /// Drop everything recorded for a file (before re-adding it)
+    pub fn remove_file(&mut self, file_id: FileId) {
+        self.functions.retain(|(f, _), _| *f != file_id);
+        self.calls.retain(|(f, _), _| *f != file_id);
+        self.flows.retain(|(f, _), _| *f != file_id);
+        self.referenced.remove(&file_id);
+        self.paths.remove(&file_id);
+        for keys in self.by_name.values_mut() {
+            keys.retain(|(f, _)| *f != file_id);
+        }
+        self.by_name.retain(|_, keys| !keys.is_empty());
+    }
+
+    /// Relative path of a file
+    pub fn path(&self, file_id: FileId) -> Option<&Path> {
+        self.paths.get(&file_id).map(PathBuf::as_path)
+    }
+
+    /// Number of functions
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Whether the graph has no functions
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+/// Walks one file in parse order
+struct FileVisitor<'a, 'g> {
+    file_id: FileId,
+    source: &'a [u8],
+    crate_root: bool,
+    next_function_id: u64,
+
+    /// Identifier nodes already accounted for (names and call targets)
+    skip: HashSet<usize>,
+
+    graph: &'g mut CallGraph,
+}
+
+impl FileVisitor<'_, '_> {
+    fn visit(&mut self, node: Node, current: Option<FunctionKey>) {
+        let mut current = current;
+
+        match node.kind() {
+            // Nested functions stay attributed to the outer one (as in CFGBuilder)
+            "function_item" if current.is_none() => {
+                current = self.add_function(node);
+            }
+            "call_expression" => {
+                if let Some(target) = node.child_by_field_name("function").and_then(call_target) {
+                    self.skip.insert(target.id());
+                    let name = self.text(target);
+                    match current {
+                        Some(caller) => {
+                            self.graph.calls.entry(caller).or_default().insert(name);
+                        }
+                        // Calls in consts and statics keep their callee live
+                        None => {
+                            self.graph.referenced.entry(self.file_id).or_default().insert(name);
+                        }
+                    }
+                }
+            }
+            "identifier" if !self.skip.contains(&node.id()) => {
+                let name = self.text(node);
+                self.graph.referenced.entry(self.file_id).or_default().insert(name);
+            }
+            _ => {}
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.visit(child, current);
+        }
+    }
+
+    /// Register a function_item and return its key
+    fn add_function(&mut self, node: Node) -> Option<FunctionKey> {
+        let function_id = FunctionId(self.next_function_id);
+        self.next_function_id += 1;
+
+        let name_node = node.child_by_field_name("name")?;
+        self.skip.insert(name_node.id());
+
+        let mut cursor = node.walk();
+        let is_pub = node.children(&mut cursor)
+            .any(|c| c.kind() == "visibility_modifier" && self.text(c) == "pub");
+
+        let key = (self.file_id, function_id);
+        let info = FunctionInfo {
+            file_id: self.file_id,
+            function_id,
+            name: self.text(name_node),
+            source_range: ByteRange::of_node(&node),
+            is_pub,
+            is_test: self.has_test_attribute(node),
+            is_trait_method: is_trait_method(node),
+            at_crate_root: self.crate_root
+                && node.parent().is_some_and(|p| p.kind() == "source_file"),
+        };
+
+        let keys = self.graph.by_name.entry(info.name.clone()).or_default();
+        keys.push(key);
+        keys.sort();
+        self.graph.functions.insert(key, info);
+        self.graph.flows.insert(key, FunctionFlows::extract(node, self.source));
+        Some(key)
+    }
+
+    /// Whether the attributes directly above a function include a test marker
+    fn has_test_attribute(&self, node: Node) -> bool {
+        let mut sibling = node.prev_named_sibling();
+        while let Some(attr) = sibling.filter(|s| s.kind() == "attribute_item") {
+            let text = self.text(attr);
+            let path = text.trim_start_matches("#[").trim_end_matches(']').trim();
+            if path == "test" || path.ends_with("::test") {
+                return true;
+            }
+            sibling = attr.prev_named_sibling();
+        }
+        false
+    }
+
+    fn text(&self, node: Node) -> String {
+        let range = ByteRange::of_node(&node);
+        String::from_utf8_lossy(&self.source[range.start..range.end]).to_string()
+    }
+}
+
+/// The name node a call resolves by (`f`, `a::f`, `x.f`, `f::<T>`)
+pub(crate) fn call_target(function: Node) -> Option<Node> {
+    match function.kind() {
+        "identifier" => Some(function),
+        "scoped_identifier" => function.child_by_field_name("name"),
+        "field_expression" => function.child_by_field_name("field"),
+        "generic_function" => function.child_by_field_name("function").and_then(call_target),
+        _ => None,
+    }
+}
+
+/// Whether a function is declared in a trait or a trait impl
+fn is_trait_method(node: Node) -> bool {
+    let mut parent = node.parent();
+    while let Some(p) = parent {
+        match p.kind() {
+            "trait_item" => return true,
+            "impl_item" => return p.child_by_field_name("trait").is_some(),
+            "function_item" | "mod_item" => return false,
+            _ => parent = p.parent(),
+        }
+    }
+    false
+}
+
+/// Whether a path is a crate root (lib.rs, main.rs, or a src/bin target)
+fn is_crate_root(path: &Path) -> bool {
+    let in_bin = path.parent()
+        .and_then(Path::file_name)
+        .is_some_and(|dir| dir == "bin");
+    match path.file_name().and_then(|n| n.to_str()) {
+        Some("lib.rs") | Some("main.rs") => true,
+        Some(name) => in_bin && name.ends_with(".rs"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::IncrementalParser;
+    use crate::memory::StringArena;
+    use crate::semantic::cfg::CFGBuilder;
+    use crate::types::Language;
+    use tempfile::NamedTempFile;
+
+    fn graph(path: &str, source: &[u8]) -> (CallGraph, ParsedFile) {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), source).unwrap();
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), FileId::new(1)).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+
+        let mut graph = CallGraph::new();
+        graph.add_file(Path::new(path), &parsed, source);
+        (graph, parsed)
+    }
+
+    fn key(id: u64) -> FunctionKey {
+        (FileId::new(1), FunctionId(id))
+    }
+
+    #[test]
+    fn test_function_ids_match_cfg_builder() {
+        let source = b"fn a() { fn inner() {} }\nimpl S { fn b(&self) {} }\nmod m { fn c() {} }\n";
+        let (graph, parsed) = graph("src/x.rs", source);
+        let cfgs = CFGBuilder::new(FileId::new(1), source).build_all(&parsed, &mut StringArena::new()).unwrap();
+
+        let names: Vec<_> = graph.functions().map(|f| (f.function_id, f.name.as_str())).collect();
+        assert_eq!(names, vec![(FunctionId(0), "a"), (FunctionId(1), "b"), (FunctionId(2), "c")]);
+        assert_eq!(cfgs.iter().map(|c| c.function_id).collect::<Vec<_>>(), vec![FunctionId(0), FunctionId(1), FunctionId(2)]);
+    }
+
+    #[test]
+    fn test_calls_resolve_by_name() {
+        let source = b"fn main() { helper(); m::other(); x.method(); id::<u8>(1); }\nfn helper() {}\nfn other() {}\nfn method() {}\nfn id<T>(t: T) {}\n";
+        let (graph, _) = graph("src/main.rs", source);
+
+        assert_eq!(graph.callees(key(0)), vec![key(1), key(2), key(3), key(4)]);
+        assert!(graph.callees(key(1)).is_empty());
+        assert!(!graph.is_referenced("helper"));
+    }
+
+    #[test]
+    fn test_references_outside_call_position() {
+        let source = b"fn main() { let f = helper; run(Self::other); println!(\"{}\", shown()); }\nfn helper() {}\nfn other() {}\nfn shown() {}\n";
+        let (graph, _) = graph("src/main.rs", source);
+
+        assert!(graph.is_referenced("helper"));
+        assert!(graph.is_referenced("other"));
+        assert!(graph.is_referenced("shown"));
+        assert!(!graph.is_referenced("main"));
+    }
+
+    #[test]
+    fn test_function_flags() {
+        let source = b"pub fn api() {}\npub(crate) fn internal() {}\n#[test]\nfn t() {}\nimpl Display for S { fn fmt(&self) {} }\nmod m { pub fn nested() {} }\n";
+        let (graph, _) = graph("src/lib.rs", source);
+        let f = |id| graph.function(key(id)).unwrap();
+
+        assert!(f(0).is_pub && f(0).at_crate_root);
+        assert!(!f(1).is_pub);
+        assert!(f(2).is_test);
+        assert!(f(3).is_trait_method);
+        assert!(f(4).is_pub && !f(4).at_crate_root);
+    }
+
+    #[test]
+    fn test_remove_file() {
+        let (mut graph, _) = graph("src/main.rs", b"fn main() { let f = helper; }\nfn helper() {}\n");
+        graph.remove_file(FileId::new(1));
+
+        assert!(graph.is_empty());
+        assert!(!graph.is_referenced("helper"));
+        assert!(graph.path(FileId::new(1)).is_none());
+    }
+
+    #[test]
+    fn test_crate_roots() {
+        assert!(is_crate_root(Path::new("src/lib.rs")));
+        assert!(is_crate_root(Path::new("main.rs")));
+        assert!(is_crate_root(Path::new("src/bin/vcr.rs")));
+        assert!(!is_crate_root(Path::new("src/api/mod.rs")));
+    }
+}
@@ -0,0 +1,184 @@
+//! Dead function detection (Step 3.6)
+//!
+//! A function is dead when no root reaches it through the call graph.
+//!
+//! **Fails towards live**
+//! - Roots: configured names (default `main`), `pub` items at a crate root,
+//!   `#[test]` functions
+//! - Trait methods are always live (dispatch is not resolved)
+//! - Functions whose name is referenced outside call position are live
+//!   (function pointers, `Type::method` values, macro arguments)
+
+use super::callgraph::{CallGraph, FunctionInfo, FunctionKey};
+use crate::config::AnalysisConfig;
+use crate::semantic::model::FunctionId;
+use crate::types::{ByteRange, FileId};
+use anyhow::Result;
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// Reason reported for every dead function
+pub const UNREACHABLE_REASON: &str = "not reachable from any root";
+
+/// Which functions count as roots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootSpec {
+    /// Function names that are always roots
+    pub names: Vec<String>,
+
+    /// `pub` functions at a crate root are roots
+    pub pub_items: bool,
+
+    /// `#[test]` functions are roots
+    pub tests: bool,
+}
+
+impl RootSpec {
+    /// Whether a function is a root
+    pub fn is_root(&self, function: &FunctionInfo) -> bool {
+        self.names.contains(&function.name)
+            || (self.pub_items && function.is_pub && function.at_crate_root)
+            || (self.tests && function.is_test)
+    }
+}
+
+impl Default for RootSpec {
+    fn default() -> Self {
+        Self::from(&AnalysisConfig::default())
+    }
+}
+
+impl From<&AnalysisConfig> for RootSpec {
+    fn from(config: &AnalysisConfig) -> Self {
+        Self {
+            names: config.dead_code_roots.clone(),
+            pub_items: config.pub_items_are_roots,
+            tests: config.tests_are_roots,
+        }
+    }
+}
+
+/// A function no root reaches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadFunction {
+    pub file_id: FileId,
+    pub path: PathBuf,
+    pub function_id: FunctionId,
+    pub name: String,
+    pub source_range: ByteRange,
+    pub reason: &'static str,
+}
+
+/// Find functions unreachable from the roots
+///
+/// **Deterministic**: Sorted by (path, FunctionId).
+pub fn find_dead_functions(callgraph: &CallGraph, roots: &RootSpec) -> Vec<DeadFunction> {
+    let mut live: BTreeSet<FunctionKey> = BTreeSet::new();
+    let mut queue: VecDeque<FunctionKey> = callgraph.functions()
+        .filter(|f| roots.is_root(f) || f.is_trait_method || callgraph.is_referenced(&f.name))
+        .map(|f| (f.file_id, f.function_id))
+        .collect();
+    live.extend(queue.iter().copied());
+
+    while let Some(caller) = queue.pop_front() {
+        for callee in callgraph.callees(caller) {
+            if live.insert(callee) {
+                queue.push_back(callee);
+            }
+        }
+    }
+
+    let mut dead: Vec<DeadFunction> = callgraph.functions()
+        .filter(|f| !live.contains(&(f.file_id, f.function_id)))
+        .map(|f| DeadFunction {
+            file_id: f.file_id,
+            path: callgraph.path(f.file_id).map(Path::to_path_buf).unwrap_or_default(),
+            function_id: f.function_id,
+            name: f.name.clone(),
+            source_range: f.source_range,
+            reason: UNREACHABLE_REASON,
+        })
+        .collect();
+    dead.sort_by(|a, b| (&a.path, a.function_id).cmp(&(&b.path, b.function_id)));
+    dead
+}
+
+/// Build a repository and find its dead functions
+pub fn lint_repo(root: &Path, roots: &RootSpec) -> Result<Vec<DeadFunction>> {
+    let output = crate::pipeline::Pipeline::default().run(root)?;
+    Ok(find_dead_functions(&output.call_graph, roots))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn repo(files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for (path, source) in files {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    fn dead_names(dir: &TempDir, roots: &RootSpec) -> Vec<String> {
+        lint_repo(dir.path(), roots).unwrap().into_iter().map(|d| d.name).collect()
+    }
+
+    #[test]
+    fn test_finds_obviously_dead_helper() {
+        let dir = repo(&[
+            ("src/main.rs", "fn main() { run(); }\nfn run() { util::used(); }\n"),
+            ("src/util.rs", "pub fn used() {}\nfn unused_helper() { used(); }\n"),
+        ]);
+
+        let dead = lint_repo(dir.path(), &RootSpec::default()).unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].name, "unused_helper");
+        assert_eq!(dead[0].path, PathBuf::from("src/util.rs"));
+        assert_eq!(dead[0].function_id, FunctionId(1));
+        assert_eq!(dead[0].reason, UNREACHABLE_REASON);
+    }
+
+    #[test]
+    fn test_default_roots() {
+        let dir = repo(&[(
+            "src/lib.rs",
+            "pub fn api() { step(); }\nfn step() {}\n#[test]\nfn t() { fixture(); }\nfn fixture() {}\nfn dead() {}\n",
+        )]);
+
+        assert_eq!(dead_names(&dir, &RootSpec::default()), vec!["dead"]);
+
+        let none = RootSpec { names: vec![], pub_items: false, tests: false };
+        assert_eq!(dead_names(&dir, &none), vec!["api", "step", "t", "fixture", "dead"]);
+    }
+
+    #[test]
+    fn test_uncertain_functions_are_live() {
+        let dir = repo(&[(
+            "src/main.rs",
+            "fn main() { let f: fn() = by_pointer; f(); }\nfn by_pointer() {}\nimpl Drop for S { fn drop(&mut self) { cleanup(); } }\nfn cleanup() {}\n",
+        )]);
+
+        assert!(dead_names(&dir, &RootSpec::default()).is_empty());
+    }
+
+    #[test]
+    fn test_dead_functions_deterministic() {
+        let dir = repo(&[
+            ("src/b.rs", "fn b_dead() {}\n"),
+            ("src/a.rs", "fn a_dead2() {}\nfn a_dead1() {}\n"),
+        ]);
+
+        let first = lint_repo(dir.path(), &RootSpec::default()).unwrap();
+        let second = lint_repo(dir.path(), &RootSpec::default()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            first.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(),
+            vec!["a_dead2", "a_dead1", "b_dead"]
+        );
+    }
+}
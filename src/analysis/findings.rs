@@ -0,0 +1,342 @@
+//! Taint findings (deduplicated taint paths)
+//!
+//! The same source often reaches the same sink along many paths. A `Finding`
+//! is one (source, sink) pair with all its paths collapsed into a count and
+//! one representative: the shortest path, ties broken by node IDs.
+//!
+//! ## Stable keys
+//!
+//! CPG node IDs and byte ranges shift whenever anything earlier in the
+//! repository changes, so findings are keyed by `StableKeys` instead:
+//!
+//! ```text
+//! <file id>/<function>#<n>/<node kind>/<label>#<m>
+//! ```
+//!
+//! `<function>` is the name of the innermost function whose span contains
+//! the node (`<file>` outside every function) and `<n>` counts earlier
+//! functions of the same name in the file. A node without a source range
+//! (a phi) belongs to the function of the first value flowing into it. `<m>` counts earlier nodes of the
+//! same kind and label in that function. A key only changes when its own
+//! function does.
+//!
+//! `FindingId` hashes the source key, the sink key and whether the finding
+//! is sanitized, so it is unchanged across rebuilds of an unchanged repo
+//! and unaffected by edits to unrelated functions.
+//!
+//! ## Baselines
+//!
+//! A `Baseline` is a set of FindingIds seen before, read from an earlier
+//! deduplicated `vcr query --taint` output. Findings it contains are
+//! marked `known`.
+
+use crate::analysis::taint::{TaintPath, TaintSink, TaintSource};
+use crate::cpg::index::CPGIndices;
+use crate::cpg::model::{CPGEdgeKind, CPGNode, CPGNodeId, CPGNodeKind, CPG};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+/// Stable identity of a finding: 16 hex digits
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FindingId(pub String);
+
+impl fmt::Display for FindingId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// One (source, sink) pair and the taint paths between them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    pub id: FindingId,
+    pub source_key: String,
+    pub sink_key: String,
+
+    /// Every collapsed path passes through a sanitizer
+    pub sanitized: bool,
+
+    /// Taint paths collapsed into this finding
+    pub path_count: usize,
+
+    /// Representative path (shortest; unsanitized ones first)
+    pub path: Vec<CPGNodeId>,
+
+    /// Listed in the baseline
+    pub known: bool,
+}
+
+/// Position-independent key of every CPG node (see module docs)
+#[derive(Debug, Clone, Default)]
+pub struct StableKeys {
+    keys: HashMap<CPGNodeId, String>,
+}
+
+impl StableKeys {
+    /// Keys for every node that belongs to a file
+    ///
+    /// **Panics** unless `indices` were built from `cpg` as it is now.
+    pub fn build(cpg: &CPG, indices: &CPGIndices) -> Self {
+        indices.assert_current(cpg);
+        let mut keys = HashMap::new();
+        for (file_id, range) in indices.file_ranges() {
+            let nodes = &cpg.nodes[range.clone()];
+
+            // Functions in node order, with their per-name occurrence
+            let mut seen_names: HashMap<&str, usize> = HashMap::new();
+            let functions: Vec<_> = nodes.iter()
+                .filter(|node| node.kind == CPGNodeKind::Function)
+                .map(|node| {
+                    let name = cpg.label(node).unwrap_or("");
+                    let occurrence = seen_names.entry(name).or_default();
+                    *occurrence += 1;
+                    (node.source_range, format!("{}#{}", name, *occurrence - 1))
+                })
+                .collect();
+
+            // Innermost containing function; the earliest on equal spans
+            let containing = |node: &CPGNode| {
+                functions.iter()
+                    .filter(|(span, _)| span.start <= node.source_range.start && node.source_range.end <= span.end)
+                    .min_by_key(|(span, _)| span.end - span.start)
+                    .map(|(_, name)| name.as_str())
+            };
+            let positioned: HashMap<CPGNodeId, &str> = nodes.iter()
+                .filter(|node| !node.source_range.is_empty())
+                .filter_map(|node| containing(node).map(|function| (node.id, function)))
+                .collect();
+
+            let mut seen: HashMap<String, usize> = HashMap::new();
+            for node in nodes {
+                // Unpositioned nodes (phis) follow the values flowing into them
+                let function = match node.source_range.is_empty() {
+                    false => positioned.get(&node.id).copied(),
+                    true => indices.get_sources_to(node.id, CPGEdgeKind::DataFlow).iter()
+                        .find_map(|pred| positioned.get(pred).copied()),
+                };
+                let prefix = format!(
+                    "{:016x}/{}/{:?}/{}",
+                    file_id.as_u64(),
+                    function.unwrap_or("<file>"),
+                    node.kind,
+                    cpg.label(node).unwrap_or(""),
+                );
+                let occurrence = seen.entry(prefix.clone()).or_default();
+                keys.insert(node.id, format!("{}#{}", prefix, occurrence));
+                *occurrence += 1;
+            }
+        }
+        Self { keys }
+    }
+
+    /// Key of a node (`node:<id>`, which is not stable, outside every file)
+    pub fn key(&self, node: CPGNodeId) -> String {
+        self.keys.get(&node).cloned().unwrap_or_else(|| format!("node:{}", node.0))
+    }
+
+    /// Node with a key, if any
+    pub fn find(&self, key: &str) -> Option<CPGNodeId> {
+        self.keys.iter().find(|(_, k)| k.as_str() == key).map(|(node, _)| *node)
+    }
+}
+
+/// Finding ID for a source/sink pair
+pub fn finding_id(source_key: &str, sink_key: &str, sanitized: bool) -> FindingId {
+    let mut hasher = Sha256::new();
+    for part in [source_key, sink_key] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher.update([sanitized as u8]);
+    let digest = hasher.finalize();
+    FindingId(digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// A path's nodes and whether a sanitizer lies on it
+type SanitizedPath<'a> = (bool, &'a [CPGNodeId]);
+
+/// Collapse taint paths into findings, sorted by ID
+///
+/// A path is sanitized if a node strictly between its source and sink is
+/// in `sanitizers`. A finding is sanitized only if all its paths are.
+pub fn dedupe(paths: &[TaintPath], keys: &StableKeys, sanitizers: &HashSet<CPGNodeId>) -> Vec<Finding> {
+    // (source key, sink key) → (sanitized, path) of each path
+    let mut groups: BTreeMap<(String, String), Vec<SanitizedPath>> = BTreeMap::new();
+    for path in paths {
+        let sanitized = path.path.len() > 2 && path.path[1..path.path.len() - 1].iter().any(|n| sanitizers.contains(n));
+        groups.entry((keys.key(source_node(path.source)), keys.key(sink_node(path.sink))))
+            .or_default()
+            .push((sanitized, &path.path));
+    }
+
+    let mut findings: Vec<Finding> = groups.into_iter()
+        .map(|((source_key, sink_key), paths)| {
+            let sanitized = paths.iter().all(|(sanitized, _)| *sanitized);
+            let representative = paths.iter()
+                .filter(|(path_sanitized, _)| *path_sanitized == sanitized)
+                .map(|(_, path)| *path)
+                .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+                .unwrap_or_default();
+            Finding {
+                id: finding_id(&source_key, &sink_key, sanitized),
+                source_key,
+                sink_key,
+                sanitized,
+                path_count: paths.len(),
+                path: representative.to_vec(),
+                known: false,
+            }
+        })
+        .collect();
+    findings.sort_by(|a, b| a.id.cmp(&b.id));
+    findings
+}
+
+fn source_node(source: TaintSource) -> CPGNodeId {
+    match source {
+        TaintSource::Parameter(node) | TaintSource::ExternalInput(node) => node,
+    }
+}
+
+fn sink_node(sink: TaintSink) -> CPGNodeId {
+    match sink {
+        TaintSink::FunctionCall(node) | TaintSink::Return(node) => node,
+    }
+}
+
+/// FindingIds seen before
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Baseline {
+    ids: BTreeSet<FindingId>,
+}
+
+/// What a baseline file must contain: `findings` entries with an `id`
+#[derive(Deserialize)]
+struct BaselineFile {
+    findings: Vec<BaselineEntry>,
+}
+
+#[derive(Deserialize)]
+struct BaselineEntry {
+    id: FindingId,
+}
+
+impl Baseline {
+    pub fn from_ids(ids: impl IntoIterator<Item = FindingId>) -> Self {
+        Self { ids: ids.into_iter().collect() }
+    }
+
+    /// Read the `findings` of an earlier deduplicated `vcr query --taint` output
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        let file: BaselineFile = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse baseline {}", path.display()))?;
+        Ok(Self::from_ids(file.findings.into_iter().map(|entry| entry.id)))
+    }
+
+    pub fn contains(&self, id: &FindingId) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Set `known` on every finding in the baseline
+    pub fn mark(&self, findings: &mut [Finding]) {
+        for finding in findings {
+            finding.known = self.contains(&finding.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::taint::TaintAnalysis;
+    use crate::pipeline::Pipeline;
+    use tempfile::TempDir;
+
+    /// Both definitions of `a` flow into a phi at the join
+    const HANDLER: &str = "fn handle(flag: bool) {\n    let mut a = 1;\n    if flag { a = 2; } else { a = 3; }\n    sink(a);\n}\n";
+
+    /// Findings from every DFG value to every DFG value of a one-file repo
+    fn findings(source: &str) -> Vec<Finding> {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), source).unwrap();
+        let output = Pipeline::default().run(dir.path()).unwrap();
+        let cpg = output.cpg_epoch.cpg();
+
+        let values: Vec<CPGNodeId> = cpg.get_nodes_of_kind(CPGNodeKind::DfgValue).iter().map(|n| n.id).collect();
+        let sources = values.iter().map(|id| TaintSource::ExternalInput(*id)).collect();
+        let sinks = values.iter().map(|id| TaintSink::FunctionCall(*id)).collect();
+        let analysis = TaintAnalysis::analyze(cpg, sources, sinks);
+
+        let keys = StableKeys::build(cpg, output.cpg_epoch.indices());
+        dedupe(analysis.paths(), &keys, &HashSet::new())
+    }
+
+    fn ids(findings: &[Finding]) -> BTreeSet<FindingId> {
+        findings.iter().map(|f| f.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_ids_identical_across_runs() {
+        let first = findings(HANDLER);
+        assert!(first.iter().any(|f| f.path.len() > 1), "{:?}", first);
+        assert_eq!(first, findings(HANDLER));
+        assert_eq!(ids(&first).len(), first.len());
+    }
+
+    #[test]
+    fn test_unrelated_function_keeps_existing_ids() {
+        let before = ids(&findings(HANDLER));
+        let with_helper = format!("fn helper(x: i32) -> i32 {{\n    let y = x;\n    y\n}}\n\n{}", HANDLER);
+        let after = ids(&findings(&with_helper));
+
+        assert!(after.len() > before.len());
+        assert!(before.is_subset(&after), "lost: {:?}", before.difference(&after).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_paths_collapse_to_shortest_and_sanitizers_change_ids() {
+        let keys = StableKeys::default();
+        let path = |nodes: &[u64]| TaintPath {
+            source: TaintSource::Parameter(CPGNodeId(1)),
+            path: nodes.iter().map(|n| CPGNodeId(*n)).collect(),
+            sink: TaintSink::Return(CPGNodeId(9)),
+        };
+        let paths = [path(&[1, 5, 6, 9]), path(&[1, 4, 9]), path(&[1, 3, 9])];
+
+        let plain = dedupe(&paths, &keys, &HashSet::new());
+        assert_eq!(plain.len(), 1);
+        assert_eq!(plain[0].path_count, 3);
+        assert_eq!(plain[0].path, [CPGNodeId(1), CPGNodeId(3), CPGNodeId(9)]);
+        assert!(!plain[0].sanitized);
+
+        // An unsanitized path remains: still a finding, represented by it
+        let partly = dedupe(&paths, &keys, &HashSet::from([CPGNodeId(3)]));
+        assert_eq!((partly[0].id.clone(), partly[0].path.len()), (plain[0].id.clone(), 3));
+        assert_eq!(partly[0].path[1], CPGNodeId(4));
+
+        let all = dedupe(&paths, &keys, &HashSet::from([CPGNodeId(3), CPGNodeId(4), CPGNodeId(5)]));
+        assert!(all[0].sanitized);
+        assert_ne!(all[0].id, plain[0].id);
+
+        let mut marked = plain.clone();
+        Baseline::from_ids([plain[0].id.clone()]).mark(&mut marked);
+        assert!(marked[0].known);
+    }
+
+    #[test]
+    #[should_panic(expected = "CPG indices are stale")]
+    fn test_keys_reject_stale_indices() {
+        let mut cpg = CPG::new();
+        let indices = CPGIndices::build(&cpg);
+        cpg.bump_generation();
+
+        StableKeys::build(&cpg, &indices);
+    }
+}
@@ -0,0 +1,90 @@
+//! Interprocedural taint over function flows
+//!
+//! A flow starts at a source call (`[analysis] taint_sources`) and ends at
+//! an argument of a sink call (`taint_sinks`), possibly through calls into
+//! functions of the repo. Every function is analyzed as a root.
+//!
+//! - `TaintMode::Summary` crosses a call by applying the callee's
+//!   `FunctionSummary`
+//! - `TaintMode::Inline` descends into the callee again on every call path;
+//!   a callee already on the path is not entered, so only summaries follow
+//!   recursion
+//!
+//! Without recursion both modes report the same flows.
+
+use super::callgraph::{CallGraph, FunctionKey};
+use super::summaries::{cross_call, solve, CallRef, FunctionSummaries, Label, Place, TaintSpec};
+use crate::config::TaintMode;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A source call whose result may reach a sink call's argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct TaintFlow {
+    pub source: CallRef,
+    pub sink: CallRef,
+}
+
+/// Every flow from a source call to a sink call of `summaries.spec()`
+///
+/// **Deterministic**: Sorted by (source, sink).
+pub fn find_taint_flows(graph: &CallGraph, summaries: &FunctionSummaries, mode: TaintMode) -> Vec<TaintFlow> {
+    let spec = summaries.spec();
+    let mut sunk = BTreeSet::new();
+    for function in graph.functions() {
+        let key = (function.file_id, function.function_id);
+        match mode {
+            TaintMode::Summary => with_summaries(graph, summaries, key, &mut sunk),
+            TaintMode::Inline => {
+                inline(graph, spec, key, BTreeMap::new(), &mut vec![key], &mut sunk);
+            }
+        }
+    }
+
+    sunk.into_iter()
+        .filter_map(|(label, sink)| match label {
+            Label::Source(source) => Some(TaintFlow { source, sink }),
+            Label::Param(_) => None,
+        })
+        .collect()
+}
+
+/// Taint from the source calls of one function, crossing calls by summary
+fn with_summaries(graph: &CallGraph, summaries: &FunctionSummaries, key: FunctionKey, sunk: &mut BTreeSet<(Label, CallRef)>) {
+    let Some(flows) = graph.flows(key) else { return };
+    solve(flows, BTreeMap::new(), |call, args| {
+        cross_call(graph, summaries.spec(), CallRef::new(key, call), &flows.calls[call], args, sunk, |callee, result, sunk| {
+            if let Some(summary) = summaries.get(callee) {
+                summary.apply(args, result, sunk);
+            }
+        })
+    });
+}
+
+/// Labels reaching the return value of `key`, given its parameters'
+/// labels, descending into every callee not on `path`
+fn inline(
+    graph: &CallGraph,
+    spec: &TaintSpec,
+    key: FunctionKey,
+    seeds: BTreeMap<Place, BTreeSet<Label>>,
+    path: &mut Vec<FunctionKey>,
+    sunk: &mut BTreeSet<(Label, CallRef)>,
+) -> BTreeSet<Label> {
+    let Some(flows) = graph.flows(key) else { return BTreeSet::new() };
+    let mut labels = solve(flows, seeds, |call, args| {
+        cross_call(graph, spec, CallRef::new(key, call), &flows.calls[call], args, sunk, |callee, result, sunk| {
+            if path.contains(&callee) {
+                return;
+            }
+            let seeds = args.iter().enumerate()
+                .filter(|(_, labels)| !labels.is_empty())
+                .map(|(index, labels)| (Place::Param(index), labels.clone()))
+                .collect();
+            path.push(callee);
+            result.extend(inline(graph, spec, callee, seeds, path, sunk));
+            path.pop();
+        })
+    });
+    labels.remove(&Place::Return).unwrap_or_default()
+}
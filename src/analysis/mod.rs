@@ -2,12 +2,28 @@
 //!
 //! Contains bounded, explainable analysis passes:
 //! - Pointer/alias analysis (Step 3.4)
-//! - Taint propagation (Step 3.5)
+//! - Taint propagation (Step 3.5) and deduplicated findings
 //! - Reachability queries (Step 3.6)
+//! - Call graph and dead function detection (Step 3.6)
+//! - Shadowed and unused bindings
+//! - Function summaries and interprocedural taint
 
 pub mod pointer;
 pub mod taint;
+pub mod findings;
 pub mod reachability;
+pub mod callgraph;
+pub mod deadcode;
+pub mod bindings;
+pub mod summaries;
+pub mod interprocedural;
 
-pub use pointer::{PointerAnalysis, PointsToSet};
-pub use taint::{TaintAnalysis, TaintPath};
+pub use pointer::{changed_values, AliasResult, PersistedPointsTo, PointerAnalysis, PointsToSet, PointsToUpdate, ValueFlowGraph};
+pub use taint::{TaintAnalysis, TaintPath, TaintSink, TaintSource};
+pub use findings::{Baseline, Finding, FindingId, StableKeys};
+pub use reachability::ReachabilityAnalysis;
+pub use callgraph::{CallGraph, FunctionInfo};
+pub use deadcode::{find_dead_functions, DeadFunction, RootSpec};
+pub use bindings::{find_shadowing, find_unused, ShadowedBinding, UnusedBinding};
+pub use summaries::{CallRef, FunctionFlows, FunctionSummaries, FunctionSummary, TaintSpec};
+pub use interprocedural::{find_taint_flows, TaintFlow};
@@ -1,6 +1,6 @@
 //! Bounded pointer/alias analysis (Step 3.4)
 //!
-//! **Algorithm**: Andersen-style, flow-insensitive
+//! **Algorithm**: Andersen-style, flow-insensitive, worklist-driven
 //! **No heap modeling initially**
 //! **No field sensitivity initially**
 //!
@@ -10,42 +10,195 @@
 //! - Capped growth (mark "unknown" if explodes)
 //! - Explainable results only
 //!
+//! ## Alias Queries
+//!
+//! Two values may alias when their points-to sets intersect
+//! (`may_alias`). An overflowed set aliases everything: the answer is
+//! `Unknown`, never a silent `NoAlias`.
+//!
+//! ## Set representation
+//!
+//! Known sets are sorted, duplicate-free `Vec`s: a third of a `HashSet`'s
+//! per-target footprint, and iteration (witnesses, alias groups,
+//! persistence) is ascending without sorting. Propagation merges sorted
+//! runs. `stats().set_bytes` reports what the sets hold.
+//!
+//! ## Persistence and incremental updates
+//!
+//! An analysis keeps the `ValueFlowGraph` it was solved on. `persist` turns
+//! it into a sorted `PersistedPointsTo` keyed by the CPG hash (see
+//! `SnapshotStore::save_points_to`). `update_from_roots` brings a previous
+//! solution up to date with a new CPG: if the new graph only adds values,
+//! edges and seed facts, monotonicity means the old sets are still lower
+//! bounds, so the worklist resumes from the values that gained something.
+//! Any removal (or an overflowed previous run) forces a full recompute.
+//!
 //! ## Not Trying To Be Clever
 //!
 //! This is **correct but incomplete** > fast and wrong
 
-use crate::cpg::model::{CPG, CPGNodeKind, CPGEdgeKind};
+use crate::cpg::model::{CPG, CPGEdgeKind, CPGNodeId, OriginRef};
+use crate::execution::cancel::{uninterrupted, CancellationToken, Checkpoint, Interrupted};
 use crate::semantic::model::ValueId;
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 /// Maximum points-to set size before marking "unknown"
 const MAX_POINTSTO_SIZE: usize = 100;
 
 /// Pointer analysis results
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PointerAnalysis {
     /// Points-to sets: ValueId → Set of ValueId it may point to
     points_to: HashMap<ValueId, PointsToSet>,
     
     /// Whether analysis completed without overflow
     completed: bool,
+
+    /// Graph the sets were solved on
+    graph: ValueFlowGraph,
 }
 
 /// Points-to set for a value
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PointsToSet {
-    /// Known set of targets
-    Known(HashSet<ValueId>),
+    /// Known set of targets, ascending
+    Known(Vec<ValueId>),
     
     /// Unknown (analysis overflow)
     Unknown,
 }
 
+/// Answer to an alias query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasResult {
+    /// The points-to sets are disjoint
+    NoAlias,
+
+    /// The points-to sets share these targets (ascending)
+    MayAlias { witnesses: Vec<ValueId> },
+
+    /// A set overflowed or the value was not analyzed
+    Unknown,
+}
+
+/// Value-level input of an analysis
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValueFlowGraph {
+    /// DFG values of the CPG
+    pub values: BTreeSet<ValueId>,
+
+    /// DataFlow edges between them
+    pub edges: BTreeSet<(ValueId, ValueId)>,
+
+    /// Initial facts: `(value, target)` means `value` may point to `target`
+    pub seeds: BTreeSet<(ValueId, ValueId)>,
+}
+
+impl ValueFlowGraph {
+    /// Values and value-to-value DataFlow edges of `cpg`, with `seeds`
+    pub fn build(cpg: &CPG, seeds: &[(ValueId, ValueId)]) -> Self {
+        let mut values: HashMap<CPGNodeId, ValueId> = HashMap::new();
+        for node in &cpg.nodes {
+            if let OriginRef::Dfg { value_id } = node.origin {
+                values.insert(node.id, value_id);
+            }
+        }
+
+        let edges = cpg.iter_edges_of_kind(CPGEdgeKind::DataFlow)
+            .filter_map(|edge| Some((*values.get(&edge.from)?, *values.get(&edge.to)?)))
+            .collect();
+        Self {
+            values: values.into_values().collect(),
+            edges,
+            seeds: seeds.iter().copied().collect(),
+        }
+    }
+
+    /// `build`, seeding every value with no incoming data flow with itself
+    pub fn from_roots(cpg: &CPG) -> Self {
+        let mut fed: HashSet<CPGNodeId> = HashSet::new();
+        fed.extend(cpg.iter_edges_of_kind(CPGEdgeKind::DataFlow).map(|edge| edge.to));
+
+        let seeds: Vec<_> = cpg.nodes.iter()
+            .filter(|node| !fed.contains(&node.id))
+            .filter_map(|node| match node.origin {
+                OriginRef::Dfg { value_id } => Some((value_id, value_id)),
+                _ => None,
+            })
+            .collect();
+        Self::build(cpg, &seeds)
+    }
+
+    /// Whether this graph keeps every value, edge and seed of `previous`
+    pub fn extends(&self, previous: &ValueFlowGraph) -> bool {
+        previous.values.is_subset(&self.values)
+            && previous.edges.is_subset(&self.edges)
+            && previous.seeds.is_subset(&self.seeds)
+    }
+
+    /// Successors of each value, in edge order
+    fn successors(&self) -> HashMap<ValueId, Vec<ValueId>> {
+        let mut successors: HashMap<ValueId, Vec<ValueId>> = HashMap::new();
+        for (from, to) in &self.edges {
+            successors.entry(*from).or_default().push(*to);
+        }
+        successors
+    }
+}
+
+/// How `update_from_roots` produced its result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointsToUpdate {
+    /// Same graph: the previous solution was reused as is
+    Unchanged,
+
+    /// Additions only: the worklist was seeded with `seeded` values
+    Incremental { seeded: usize },
+
+    /// Something was removed, or the previous run overflowed: solved
+    /// from scratch
+    Recomputed,
+}
+
+/// Sorted, serializable form of an analysis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedPointsTo {
+    /// Hash of the CPG the analysis ran on
+    pub cpg_hash: String,
+
+    pub completed: bool,
+
+    pub graph: ValueFlowGraph,
+
+    /// Points-to sets by value, targets ascending (None = overflowed)
+    pub sets: Vec<(ValueId, Option<Vec<ValueId>>)>,
+}
+
+impl From<PersistedPointsTo> for PointerAnalysis {
+    fn from(persisted: PersistedPointsTo) -> Self {
+        let points_to = persisted.sets.into_iter()
+            .map(|(value, set)| match set {
+                Some(mut targets) => {
+                    targets.sort();
+                    targets.dedup();
+                    (value, PointsToSet::Known(targets))
+                }
+                None => (value, PointsToSet::Unknown),
+            })
+            .collect();
+        Self { points_to, completed: persisted.completed, graph: persisted.graph }
+    }
+}
+
 impl PointerAnalysis {
     /// Create empty pointer analysis
     pub fn new() -> Self {
         Self {
             points_to: HashMap::new(),
             completed: true,
+            graph: ValueFlowGraph::default(),
         }
     }
 
@@ -53,83 +206,174 @@ impl PointerAnalysis {
     ///
     /// **Bounded**: Will mark "unknown" if growth explodes
     pub fn analyze(cpg: &CPG) -> Self {
+        Self::analyze_seeded(cpg, &[])
+    }
+
+    /// Run analysis with every value that has no incoming data flow as its
+    /// own abstract location (it points to itself)
+    ///
+    /// Values then point to the roots that flow into them, so two values
+    /// alias when they may carry the same root.
+    pub fn analyze_from_roots(cpg: &CPG) -> Self {
+        uninterrupted(Self::analyze_from_roots_cancellable(cpg, &CancellationToken::new()))
+    }
+
+    /// `analyze_from_roots`, stopping when `token` is cancelled or times out
+    pub fn analyze_from_roots_cancellable(cpg: &CPG, token: &CancellationToken) -> Result<Self, Interrupted> {
+        Self::solve(ValueFlowGraph::from_roots(cpg), token)
+    }
+
+    /// Run analysis with initial facts: each `(value, target)` means
+    /// `value` may point to `target`
+    ///
+    /// **Worklist**: Only values whose set changed are revisited, and only
+    /// along their own outgoing DataFlow edges, until nothing changes.
+    /// Termination follows from monotonicity plus the set size cap.
+    pub fn analyze_seeded(cpg: &CPG, seeds: &[(ValueId, ValueId)]) -> Self {
+        uninterrupted(Self::analyze_seeded_cancellable(cpg, seeds, &CancellationToken::new()))
+    }
+
+    /// `analyze_seeded`, checking `token` once per worklist item
+    pub fn analyze_seeded_cancellable(
+        cpg: &CPG,
+        seeds: &[(ValueId, ValueId)],
+        token: &CancellationToken,
+    ) -> Result<Self, Interrupted> {
+        Self::solve(ValueFlowGraph::build(cpg, seeds), token)
+    }
+
+    /// Bring `previous`, an `analyze_from_roots` result, up to date with `cpg`
+    ///
+    /// `changed` (the DFG values an edit invalidated, see `changed_values`)
+    /// are revisited along with every value that gained a seed fact or an
+    /// outgoing edge, so a `changed` set that misses something stays sound.
+    pub fn update_from_roots(previous: &Self, cpg: &CPG, changed: &[ValueId]) -> (Self, PointsToUpdate) {
+        uninterrupted(Self::update_from_roots_cancellable(previous, cpg, changed, &CancellationToken::new()))
+    }
+
+    /// `update_from_roots`, checking `token` once per worklist item
+    pub fn update_from_roots_cancellable(
+        previous: &Self,
+        cpg: &CPG,
+        changed: &[ValueId],
+        token: &CancellationToken,
+    ) -> Result<(Self, PointsToUpdate), Interrupted> {
+        let graph = ValueFlowGraph::from_roots(cpg);
+        if graph == previous.graph {
+            return Ok((previous.clone(), PointsToUpdate::Unchanged));
+        }
+        // Removals can shrink sets, and an overflow cut propagation short:
+        // neither leaves a valid lower bound to resume from
+        if !previous.completed || !graph.extends(&previous.graph) {
+            return Ok((Self::solve(graph, token)?, PointsToUpdate::Recomputed));
+        }
+
+        let mut analysis = previous.clone();
+        for value in graph.values.difference(&previous.graph.values) {
+            analysis.points_to.insert(*value, PointsToSet::Known(Vec::new()));
+        }
+        let mut seeded: Vec<ValueId> = changed.iter()
+            .filter(|value| graph.values.contains(value))
+            .copied()
+            .collect();
+        for (value, target) in graph.seeds.difference(&previous.graph.seeds) {
+            if analysis.add_target(*value, *target) {
+                seeded.push(*value);
+            }
+        }
+        seeded.extend(graph.edges.difference(&previous.graph.edges).map(|(from, _)| *from));
+
+        analysis.graph = graph;
+        let seeded = analysis.propagate(seeded, token)?;
+        Ok((analysis, PointsToUpdate::Incremental { seeded }))
+    }
+
+    /// Solve `graph` from empty sets
+    fn solve(graph: ValueFlowGraph, token: &CancellationToken) -> Result<Self, Interrupted> {
         let mut analysis = Self::new();
 
         // Step 1: Initialize points-to sets for all DFG values
-        for node in &cpg.nodes {
-            if node.kind == CPGNodeKind::DfgValue {
-                if let crate::cpg::model::OriginRef::Dfg { value_id } = node.origin {
-                    analysis.points_to.insert(value_id, PointsToSet::Known(HashSet::new()));
-                }
+        for value in &graph.values {
+            analysis.points_to.insert(*value, PointsToSet::Known(Vec::new()));
+        }
+
+        // Step 2: Apply the seed facts
+        let mut seeded: Vec<ValueId> = Vec::new();
+        for (value, target) in &graph.seeds {
+            if analysis.add_target(*value, *target) {
+                seeded.push(*value);
             }
         }
 
-        // Step 2: Propagate along DataFlow edges (simplified)
-        // In real Andersen's, would iterate to fixed point
-        // For now, single pass over edges
-        
-        let mut changed = true;
-        let mut iterations = 0;
-        const MAX_ITERATIONS: usize = 100;
-        
-        while changed && iterations < MAX_ITERATIONS {
-            changed = false;
-            iterations += 1;
-            
-            for edge in &cpg.edges {
-                if edge.kind == CPGEdgeKind::DataFlow {
-                    // Get source and target value IDs
-                    if let (Some(from_node), Some(to_node)) = (cpg.get_node(edge.from), cpg.get_node(edge.to)) {
-                        if let (
-                            crate::cpg::model::OriginRef::Dfg { value_id: from_id },
-                            crate::cpg::model::OriginRef::Dfg { value_id: to_id }
-                        ) = (from_node.origin, to_node.origin) {
-                            // Propagate: if x → y, then pts(y) ⊇ pts(x)
-                            if analysis.propagate_points_to(from_id, to_id) {
-                                changed = true;
-                            }
-                        }
-                    }
+        analysis.graph = graph;
+        analysis.propagate(seeded, token)?;
+        Ok(analysis)
+    }
+
+    /// Run the worklist from `seeded` (visited ascending) to a fixpoint;
+    /// returns the number of distinct seeded values
+    fn propagate(&mut self, mut seeded: Vec<ValueId>, token: &CancellationToken) -> Result<usize, Interrupted> {
+        let successors = self.graph.successors();
+        seeded.sort();
+        seeded.dedup();
+        let count = seeded.len();
+        let mut queued: HashSet<ValueId> = seeded.iter().copied().collect();
+        let mut worklist: VecDeque<ValueId> = seeded.into();
+
+        // Propagate: if x → y, then pts(y) ⊇ pts(x)
+        let mut checkpoint = Checkpoint::new(token);
+        while let Some(from) = worklist.pop_front() {
+            checkpoint.step()?;
+            queued.remove(&from);
+            for to in successors.get(&from).into_iter().flatten() {
+                if self.propagate_points_to(from, *to) && queued.insert(*to) {
+                    worklist.push_back(*to);
                 }
             }
         }
 
-        if iterations >= MAX_ITERATIONS {
-            analysis.completed = false;
-        }
+        Ok(count)
+    }
 
-        analysis
+    /// Add one target to a value's set
+    ///
+    /// Returns true if the set changed
+    fn add_target(&mut self, value: ValueId, target: ValueId) -> bool {
+        let set = self.points_to.entry(value).or_insert_with(|| PointsToSet::Known(Vec::new()));
+        match set {
+            PointsToSet::Known(targets) => {
+                let Err(at) = targets.binary_search(&target) else { return false };
+                targets.insert(at, target);
+                if targets.len() > MAX_POINTSTO_SIZE {
+                    *set = PointsToSet::Unknown;
+                    self.completed = false;
+                }
+                true
+            }
+            PointsToSet::Unknown => false,
+        }
     }
 
     /// Propagate points-to set from source to target
     ///
     /// Returns true if target set changed
     fn propagate_points_to(&mut self, from: ValueId, to: ValueId) -> bool {
-        // Get from set (clone to avoid borrow issues)
-        let from_set = match self.points_to.get(&from) {
-            Some(PointsToSet::Known(set)) => set.clone(),
-            Some(PointsToSet::Unknown) | None => return false,
+        let Some(PointsToSet::Known(from_set)) = self.points_to.get(&from) else { return false };
+        let merged = match self.points_to.get(&to) {
+            Some(PointsToSet::Known(to_set)) => sorted_union(to_set, from_set),
+            Some(PointsToSet::Unknown) => return false,
+            None => Some(from_set.clone()),
         };
+        let Some(merged) = merged else { return false };
 
-        // Get or create to set
-        let to_set = self.points_to.entry(to).or_insert_with(|| PointsToSet::Known(HashSet::new()));
-
-        match to_set {
-            PointsToSet::Known(set) => {
-                let old_size = set.len();
-                set.extend(&from_set);
-                
-                // Check for overflow
-                if set.len() > MAX_POINTSTO_SIZE {
-                    *to_set = PointsToSet::Unknown;
-                    self.completed = false;
-                    return true;
-                }
-                
-                set.len() > old_size
-            }
-            PointsToSet::Unknown => false,
+        // Check for overflow
+        if merged.len() > MAX_POINTSTO_SIZE {
+            self.points_to.insert(to, PointsToSet::Unknown);
+            self.completed = false;
+        } else {
+            self.points_to.insert(to, PointsToSet::Known(merged));
         }
+        true
     }
 
     /// Get points-to set for a value
@@ -137,6 +381,76 @@ impl PointerAnalysis {
         self.points_to.get(&value)
     }
 
+    /// Whether two values may point to the same target
+    pub fn may_alias(&self, a: ValueId, b: ValueId) -> AliasResult {
+        match (self.points_to.get(&a), self.points_to.get(&b)) {
+            (Some(PointsToSet::Known(a)), Some(PointsToSet::Known(b))) => {
+                let witnesses = sorted_intersection(a, b);
+                if witnesses.is_empty() {
+                    return AliasResult::NoAlias;
+                }
+                AliasResult::MayAlias { witnesses }
+            }
+            _ => AliasResult::Unknown,
+        }
+    }
+
+    /// Groups of two or more values that share a target, transitively
+    ///
+    /// Each group is ascending and groups are ordered by their first value.
+    /// Values with overflowed sets are left out (see `may_alias`).
+    pub fn alias_sets(&self) -> Vec<Vec<ValueId>> {
+        let mut values: Vec<ValueId> = self.points_to.iter()
+            .filter(|(_, set)| matches!(set, PointsToSet::Known(s) if !s.is_empty()))
+            .map(|(value, _)| *value)
+            .collect();
+        values.sort();
+
+        // Union-find over positions in `values`
+        let mut parent: Vec<usize> = (0..values.len()).collect();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let mut owner: HashMap<ValueId, usize> = HashMap::new();
+        for (i, value) in values.iter().enumerate() {
+            let Some(PointsToSet::Known(targets)) = self.points_to.get(value) else { continue };
+            for target in targets {
+                let first = *owner.entry(*target).or_insert(i);
+                let (a, b) = (find(&mut parent, first), find(&mut parent, i));
+                // Smaller root wins, so roots are each group's first value
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+
+        let mut groups: BTreeMap<usize, Vec<ValueId>> = BTreeMap::new();
+        for (i, value) in values.iter().enumerate() {
+            groups.entry(find(&mut parent, i)).or_default().push(*value);
+        }
+        groups.into_values().filter(|group| group.len() > 1).collect()
+    }
+
+    /// Graph the sets were solved on
+    pub fn graph(&self) -> &ValueFlowGraph {
+        &self.graph
+    }
+
+    /// Sorted form of this analysis of the CPG hashing to `cpg_hash`
+    pub fn persist(&self, cpg_hash: &str) -> PersistedPointsTo {
+        let mut sets: Vec<_> = self.points_to.iter()
+            .map(|(value, set)| match set {
+                PointsToSet::Known(targets) => (*value, Some(targets.clone())),
+                PointsToSet::Unknown => (*value, None),
+            })
+            .collect();
+        sets.sort_by_key(|(value, _)| *value);
+        PersistedPointsTo { cpg_hash: cpg_hash.to_string(), completed: self.completed, graph: self.graph.clone(), sets }
+    }
+
     /// Check if analysis completed without overflow
     pub fn is_complete(&self) -> bool {
         self.completed
@@ -163,11 +477,77 @@ impl PointerAnalysis {
             known_sets: known_count,
             unknown_sets: unknown_count,
             total_points_to_edges: total_edges,
+            set_bytes: self.points_to.len() * std::mem::size_of::<PointsToSet>()
+                + total_edges * std::mem::size_of::<ValueId>(),
             completed: self.completed,
         }
     }
 }
 
+impl Default for PointerAnalysis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Union of ascending `into` and `from`, or None if `from` adds nothing
+fn sorted_union(into: &[ValueId], from: &[ValueId]) -> Option<Vec<ValueId>> {
+    let mut rest = into;
+    let subset = from.iter().all(|target| match rest.binary_search(target) {
+        Ok(at) => {
+            rest = &rest[at + 1..];
+            true
+        }
+        Err(_) => false,
+    });
+    if subset {
+        return None;
+    }
+
+    let mut merged = Vec::with_capacity(into.len() + from.len());
+    let (mut i, mut j) = (0, 0);
+    while i < into.len() && j < from.len() {
+        match into[i].cmp(&from[j]) {
+            Ordering::Less => { merged.push(into[i]); i += 1; }
+            Ordering::Greater => { merged.push(from[j]); j += 1; }
+            Ordering::Equal => { merged.push(into[i]); i += 1; j += 1; }
+        }
+    }
+    merged.extend_from_slice(&into[i..]);
+    merged.extend_from_slice(&from[j..]);
+    Some(merged)
+}
+
+/// Targets of both ascending `a` and `b`, ascending
+fn sorted_intersection(a: &[ValueId], b: &[ValueId]) -> Vec<ValueId> {
+    let mut shared = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => { shared.push(a[i]); i += 1; j += 1; }
+        }
+    }
+    shared
+}
+
+/// DFG values of the invalidated CPG nodes `nodes` (see
+/// `InvalidationSet::cpg_nodes`), ascending
+pub fn changed_values(cpg: &CPG, nodes: &[CPGNodeId]) -> Vec<ValueId> {
+    let nodes: HashSet<CPGNodeId> = nodes.iter().copied().collect();
+    let mut values: Vec<ValueId> = cpg.nodes.iter()
+        .filter(|node| nodes.contains(&node.id))
+        .filter_map(|node| match node.origin {
+            OriginRef::Dfg { value_id } => Some(value_id),
+            _ => None,
+        })
+        .collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
 /// Statistics about pointer analysis
 #[derive(Debug, Clone)]
 pub struct PointerAnalysisStats {
@@ -175,6 +555,8 @@ pub struct PointerAnalysisStats {
     pub known_sets: usize,
     pub unknown_sets: usize,
     pub total_points_to_edges: usize,
+    /// Bytes held by the sets (`Known` targets plus one set per value)
+    pub set_bytes: usize,
     pub completed: bool,
 }
 
@@ -226,6 +608,249 @@ mod tests {
         assert_eq!(analysis.points_to.len(), 2);
     }
 
+    /// The previous whole-edge-set iteration, kept as a reference
+    fn analyze_by_rescanning(cpg: &CPG, seeds: &[(ValueId, ValueId)]) -> PointerAnalysis {
+        let mut analysis = PointerAnalysis::new();
+        for node in &cpg.nodes {
+            if let OriginRef::Dfg { value_id } = node.origin {
+                analysis.points_to.insert(value_id, PointsToSet::Known(Vec::new()));
+            }
+        }
+        for (value, target) in seeds {
+            analysis.add_target(*value, *target);
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for edge in &cpg.edges {
+                if edge.kind != CPGEdgeKind::DataFlow {
+                    continue;
+                }
+                if let (Some(from), Some(to)) = (cpg.get_node(edge.from), cpg.get_node(edge.to)) {
+                    if let (OriginRef::Dfg { value_id: from }, OriginRef::Dfg { value_id: to }) = (from.origin, to.origin) {
+                        changed |= analysis.propagate_points_to(from, to);
+                    }
+                }
+            }
+        }
+        analysis
+    }
+
+    /// DFG value nodes 0..count with the given DataFlow edges
+    fn value_graph(count: u64, edges: &[(u64, u64)]) -> CPG {
+        let mut cpg = CPG::new();
+        for id in 0..count {
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(id),
+                CPGNodeKind::DfgValue,
+                OriginRef::Dfg { value_id: ValueId(id) },
+                ByteRange::new(0, 0),
+            ));
+        }
+        for (i, (from, to)) in edges.iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), CPGEdgeKind::DataFlow, CPGNodeId(*from), CPGNodeId(*to)));
+        }
+        cpg
+    }
+
+    #[test]
+    fn test_worklist_matches_rescanning() {
+        // A diamond, a cycle and an unrelated pair, edges out of order
+        let edges = [(3, 4), (0, 1), (0, 2), (1, 3), (2, 3), (4, 5), (5, 3), (6, 7), (2, 8)];
+        let cpg = value_graph(9, &edges);
+        let seeds = [(ValueId(0), ValueId(100)), (ValueId(2), ValueId(101)), (ValueId(5), ValueId(102)), (ValueId(6), ValueId(103))];
+
+        let worklist = PointerAnalysis::analyze_seeded(&cpg, &seeds);
+        let reference = analyze_by_rescanning(&cpg, &seeds);
+
+        assert!(worklist.is_complete());
+        assert_eq!(worklist.points_to, reference.points_to);
+        let expected = vec![ValueId(100), ValueId(101), ValueId(102)];
+        assert_eq!(worklist.points_to(ValueId(4)), Some(&PointsToSet::Known(expected)));
+        assert_eq!(worklist.points_to(ValueId(1)), Some(&PointsToSet::Known(vec![ValueId(100)])));
+    }
+
+    #[test]
+    fn test_long_chain_converges() {
+        // Edges listed back to front: a rescan would need one pass per link
+        const LEN: u64 = 10_000;
+        let edges: Vec<_> = (0..LEN - 1).rev().map(|i| (i, i + 1)).collect();
+        let cpg = value_graph(LEN, &edges);
+
+        let analysis = PointerAnalysis::analyze_seeded(&cpg, &[(ValueId(0), ValueId(LEN))]);
+
+        assert!(analysis.is_complete());
+        let last = analysis.points_to(ValueId(LEN - 1));
+        assert_eq!(last, Some(&PointsToSet::Known(vec![ValueId(LEN)])));
+    }
+
+    #[test]
+    fn test_overflow_marks_unknown() {
+        let cpg = value_graph(2, &[(0, 1)]);
+        let seeds: Vec<_> = (0..=MAX_POINTSTO_SIZE as u64).map(|t| (ValueId(0), ValueId(1000 + t))).collect();
+
+        let analysis = PointerAnalysis::analyze_seeded(&cpg, &seeds);
+
+        assert!(!analysis.is_complete());
+        assert_eq!(analysis.points_to(ValueId(0)), Some(&PointsToSet::Unknown));
+        assert_eq!(analysis.stats().unknown_sets, 1);
+    }
+
+    #[test]
+    fn test_may_alias_and_alias_sets() {
+        // 1 and 2 are both copies of 0; 3 is unrelated
+        let cpg = value_graph(4, &[(0, 1), (0, 2)]);
+        let analysis = PointerAnalysis::analyze_from_roots(&cpg);
+
+        assert_eq!(analysis.may_alias(ValueId(1), ValueId(2)), AliasResult::MayAlias { witnesses: vec![ValueId(0)] });
+        assert_eq!(analysis.may_alias(ValueId(1), ValueId(3)), AliasResult::NoAlias);
+        assert_eq!(analysis.may_alias(ValueId(1), ValueId(99)), AliasResult::Unknown);
+        assert_eq!(analysis.alias_sets(), vec![vec![ValueId(0), ValueId(1), ValueId(2)]]);
+    }
+
+    #[test]
+    fn test_overflowed_set_aliases_unknown() {
+        let cpg = value_graph(3, &[(0, 1)]);
+        let seeds: Vec<_> = (0..=MAX_POINTSTO_SIZE as u64).map(|t| (ValueId(0), ValueId(1000 + t))).collect();
+        let analysis = PointerAnalysis::analyze_seeded(&cpg, &seeds);
+
+        assert_eq!(analysis.may_alias(ValueId(0), ValueId(2)), AliasResult::Unknown);
+        assert!(analysis.alias_sets().is_empty());
+    }
+
+    #[test]
+    fn test_update_after_additions_matches_full() {
+        // 3 is a root; the edit adds 3 → 1 and a new value 4 fed by 2
+        let previous = PointerAnalysis::analyze_from_roots(&value_graph(4, &[(0, 1), (1, 2)]));
+        let cpg = value_graph(5, &[(0, 1), (1, 2), (3, 1), (2, 4)]);
+
+        let (updated, how) = PointerAnalysis::update_from_roots(&previous, &cpg, &[]);
+
+        assert_eq!(how, PointsToUpdate::Incremental { seeded: 2 });
+        assert_eq!(updated, PointerAnalysis::analyze_from_roots(&cpg));
+        assert_eq!(updated.points_to(ValueId(4)), Some(&PointsToSet::Known(vec![ValueId(0), ValueId(3)])));
+    }
+
+    #[test]
+    fn test_update_recomputes_after_removal_or_overflow() {
+        let previous = PointerAnalysis::analyze_from_roots(&value_graph(3, &[(0, 1), (1, 2)]));
+        // Dropping 1 → 2 makes 2 a root that no longer points to 0
+        let cpg = value_graph(3, &[(0, 1)]);
+        let (updated, how) = PointerAnalysis::update_from_roots(&previous, &cpg, &[ValueId(1)]);
+        assert_eq!(how, PointsToUpdate::Recomputed);
+        assert_eq!(updated.points_to(ValueId(2)), Some(&PointsToSet::Known(vec![ValueId(2)])));
+
+        let seeds: Vec<_> = (0..=MAX_POINTSTO_SIZE as u64).map(|t| (ValueId(0), ValueId(1000 + t))).collect();
+        let overflowed = PointerAnalysis::analyze_seeded(&value_graph(2, &[(0, 1)]), &seeds);
+        let (_, how) = PointerAnalysis::update_from_roots(&overflowed, &value_graph(3, &[(0, 1)]), &[]);
+        assert_eq!(how, PointsToUpdate::Recomputed);
+    }
+
+    #[test]
+    fn test_persisted_round_trip() {
+        let seeds: Vec<_> = (0..=MAX_POINTSTO_SIZE as u64).map(|t| (ValueId(0), ValueId(1000 + t))).collect();
+        let analysis = PointerAnalysis::analyze_seeded(&value_graph(4, &[(0, 1), (2, 3)]), &[(ValueId(2), ValueId(7))]);
+        let overflowed = PointerAnalysis::analyze_seeded(&value_graph(2, &[(0, 1)]), &seeds);
+
+        for analysis in [analysis, overflowed] {
+            let persisted = analysis.persist("hash");
+            assert!(persisted.sets.windows(2).all(|w| w[0].0 < w[1].0));
+            let json = serde_json::to_string(&persisted).unwrap();
+            let restored = PointerAnalysis::from(serde_json::from_str::<PersistedPointsTo>(&json).unwrap());
+            assert_eq!(restored, analysis);
+        }
+    }
+
+    #[test]
+    fn test_changed_values_of_invalidated_nodes() {
+        let mut cpg = value_graph(3, &[]);
+        cpg.add_node(CPGNode::new(CPGNodeId(9), CPGNodeKind::Function, OriginRef::Function {
+            function_id: crate::semantic::model::FunctionId(0),
+        }, ByteRange::new(0, 0)));
+
+        assert_eq!(changed_values(&cpg, &[CPGNodeId(2), CPGNodeId(9), CPGNodeId(0), CPGNodeId(2)]), vec![ValueId(0), ValueId(2)]);
+    }
+
+    /// The previous `HashSet` worklist solve of `analyze_seeded`, kept as a
+    /// reference
+    fn solve_with_hash_sets(cpg: &CPG, seeds: &[(ValueId, ValueId)]) -> HashMap<ValueId, HashSet<ValueId>> {
+        let graph = ValueFlowGraph::build(cpg, seeds);
+        let successors = graph.successors();
+        let mut sets: HashMap<ValueId, HashSet<ValueId>> = graph.values.iter().map(|v| (*v, HashSet::new())).collect();
+        for (value, target) in &graph.seeds {
+            sets.get_mut(value).unwrap().insert(*target);
+        }
+        let mut worklist: VecDeque<ValueId> = graph.seeds.iter().map(|(value, _)| *value).collect();
+        while let Some(from) = worklist.pop_front() {
+            for to in successors.get(&from).into_iter().flatten() {
+                let from_set = sets[&from].clone();
+                let to_set = sets.get_mut(to).unwrap();
+                let before = to_set.len();
+                to_set.extend(from_set);
+                if to_set.len() > before {
+                    worklist.push_back(*to);
+                }
+            }
+        }
+        sets
+    }
+
+    #[test]
+    fn test_sorted_sets_against_hash_sets_at_100k_values() {
+        // Chains of 64 values, each seeding its own target: sets of 1..=64
+        const VALUES: u64 = 100_032;
+        const CHAIN: u64 = 64;
+        let edges: Vec<_> = (0..VALUES).filter(|v| v % CHAIN != CHAIN - 1).map(|v| (v, v + 1)).collect();
+        let cpg = value_graph(VALUES, &edges);
+        let seeds: Vec<_> = (0..VALUES).map(|v| (ValueId(v), ValueId(VALUES + v))).collect();
+
+        let started = std::time::Instant::now();
+        let analysis = PointerAnalysis::analyze_seeded(&cpg, &seeds);
+        let sorted_time = started.elapsed();
+        let started = std::time::Instant::now();
+        let reference = solve_with_hash_sets(&cpg, &seeds);
+        let hashed_time = started.elapsed();
+
+        assert!(analysis.is_complete());
+        for (value, targets) in &reference {
+            let mut expected: Vec<_> = targets.iter().copied().collect();
+            expected.sort();
+            assert_eq!(analysis.points_to(*value), Some(&PointsToSet::Known(expected)));
+        }
+
+        let stats = analysis.stats();
+        let targets = (VALUES / CHAIN * (CHAIN * (CHAIN + 1) / 2)) as usize;
+        assert_eq!(stats.total_points_to_edges, targets);
+        assert_eq!(stats.set_bytes, VALUES as usize * std::mem::size_of::<PointsToSet>() + targets * std::mem::size_of::<ValueId>());
+        // A hash set allocates a control byte per bucket on top of the slot
+        let hashed_bytes: usize = reference.values()
+            .map(|set| std::mem::size_of::<HashSet<ValueId>>() + set.capacity() * (std::mem::size_of::<ValueId>() + 1))
+            .sum();
+        assert!(stats.set_bytes < hashed_bytes, "{} vs {} bytes", stats.set_bytes, hashed_bytes);
+        // Generous: only a pathological merge (e.g. quadratic) should trip this
+        assert!(sorted_time < hashed_time * 4 + std::time::Duration::from_millis(200), "{:?} vs {:?}", sorted_time, hashed_time);
+    }
+
+    #[test]
+    fn test_alias_witnesses_stable_across_runs() {
+        // 4..8 each receive every root 0..4, in edge orders that differ per run
+        let mut edges: Vec<(u64, u64)> = (0..4).flat_map(|root| (4..8).map(move |v| (root, v))).collect();
+        let mut runs = Vec::new();
+        for _ in 0..8 {
+            edges.rotate_left(3);
+            let analysis = PointerAnalysis::analyze_from_roots(&value_graph(8, &edges));
+            let witnesses = analysis.may_alias(ValueId(4), ValueId(7));
+            let sets: Vec<_> = (0..8).map(|v| analysis.points_to(ValueId(v)).cloned()).collect();
+            runs.push((witnesses, sets, analysis.alias_sets()));
+        }
+
+        let roots: Vec<_> = (0..4).map(ValueId).collect();
+        assert_eq!(runs[0].0, AliasResult::MayAlias { witnesses: roots.clone() });
+        assert_eq!(runs[0].1[5], Some(PointsToSet::Known(roots)));
+        assert!(runs.iter().all(|run| *run == runs[0]));
+    }
+
     #[test]
     fn test_pointer_analysis_stats() {
         let cpg = CPG::new();
@@ -1,3 +1,120 @@
-//! Reachability queries (Step 3.6) - STUB
+//! Reachability queries (Step 3.6)
 //!
-//! Will be implemented in Step 3.6
+//! **Bounded BFS over chosen edge kinds**
+//! - Start nodes are reached at depth 0
+//! - Edges are followed in creation order
+//! - Results are sorted by node ID (a set, not a traversal order)
+//! - Stoppable between BFS steps (`analyze_cancellable`)
+
+use crate::cpg::model::{CPG, CPGEdgeKind, CPGNodeId};
+use crate::execution::cancel::{uninterrupted, CancellationToken, Checkpoint, Interrupted};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Nodes reachable from a start set
+#[derive(Debug, Clone, Default)]
+pub struct ReachabilityAnalysis {
+    /// Reached nodes, ascending
+    reached: Vec<CPGNodeId>,
+}
+
+impl ReachabilityAnalysis {
+    /// Nodes within `max_depth` edges of `from`, following only `kinds`
+    ///
+    /// An empty `kinds` follows every edge kind.
+    pub fn analyze(cpg: &CPG, from: &[CPGNodeId], kinds: &[CPGEdgeKind], max_depth: usize) -> Self {
+        uninterrupted(Self::analyze_cancellable(cpg, from, kinds, max_depth, &CancellationToken::new()))
+    }
+
+    /// `analyze`, stopping when `token` is cancelled or times out
+    pub fn analyze_cancellable(
+        cpg: &CPG,
+        from: &[CPGNodeId],
+        kinds: &[CPGEdgeKind],
+        max_depth: usize,
+        token: &CancellationToken,
+    ) -> Result<Self, Interrupted> {
+        // Out-edges of the requested kinds, built once
+        let mut successors: HashMap<CPGNodeId, Vec<CPGNodeId>> = HashMap::new();
+        for edge in &cpg.edges {
+            if kinds.is_empty() || kinds.contains(&edge.kind) {
+                successors.entry(edge.from).or_default().push(edge.to);
+            }
+        }
+
+        let mut visited: HashSet<CPGNodeId> = from.iter().copied().collect();
+        let mut queue: VecDeque<(CPGNodeId, usize)> = from.iter().map(|node| (*node, 0)).collect();
+
+        let mut checkpoint = Checkpoint::new(token);
+        while let Some((current, depth)) = queue.pop_front() {
+            checkpoint.step()?;
+            if depth >= max_depth {
+                continue;
+            }
+            for next in successors.get(&current).into_iter().flatten() {
+                if visited.insert(*next) {
+                    queue.push_back((*next, depth + 1));
+                }
+            }
+        }
+
+        let mut reached: Vec<_> = visited.into_iter().collect();
+        reached.sort();
+        Ok(Self { reached })
+    }
+
+    /// Reached nodes (including the start nodes), ascending
+    pub fn reached(&self) -> &[CPGNodeId] {
+        &self.reached
+    }
+
+    /// Consume into the reached nodes
+    pub fn into_nodes(self) -> Vec<CPGNodeId> {
+        self.reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGNode, CPGNodeKind, OriginRef};
+    use crate::semantic::model::NodeId;
+    use crate::types::ByteRange;
+
+    /// 0 → 1 → 2 → 3 by control flow, 0 → 4 by data flow
+    fn chain() -> CPG {
+        let mut cpg = CPG::new();
+        for i in 0..5 {
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(i),
+                CPGNodeKind::CfgNode,
+                OriginRef::Cfg { node_id: NodeId(i) },
+                ByteRange::new(0, 0),
+            ));
+        }
+        let edges = [
+            (CPGEdgeKind::ControlFlow, 0, 1),
+            (CPGEdgeKind::ControlFlow, 1, 2),
+            (CPGEdgeKind::ControlFlow, 2, 3),
+            (CPGEdgeKind::DataFlow, 0, 4),
+        ];
+        for (i, (kind, from, to)) in edges.into_iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), kind, CPGNodeId(from), CPGNodeId(to)));
+        }
+        cpg
+    }
+
+    #[test]
+    fn test_reachability_bounded_by_depth_and_kind() {
+        let cpg = chain();
+        let start = [CPGNodeId(0)];
+
+        let two = ReachabilityAnalysis::analyze(&cpg, &start, &[CPGEdgeKind::ControlFlow], 2);
+        assert_eq!(two.reached(), &[CPGNodeId(0), CPGNodeId(1), CPGNodeId(2)]);
+
+        let all = ReachabilityAnalysis::analyze(&cpg, &start, &[], 10);
+        assert_eq!(all.reached().len(), 5);
+
+        let none = ReachabilityAnalysis::analyze(&cpg, &start, &[CPGEdgeKind::ControlFlow], 0);
+        assert_eq!(none.into_nodes(), vec![CPGNodeId(0)]);
+    }
+}
@@ -0,0 +1,973 @@
+//! Function summaries for interprocedural analysis
+//!
+//! A `FunctionSummary` says where a function's parameters may flow: to its
+//! return value, to the arguments of the functions it calls, and to
+//! designated sink calls. It also lists the source calls (in the function
+//! or below it) its return value may carry. Interprocedural taint crosses a
+//! call by applying the callee's summary instead of descending into it
+//! (see `interprocedural`).
+//!
+//! **Flows**: `FunctionFlows` are extracted from the parse tree along with
+//! the call graph, by name and flow-insensitively: parameters, `let`,
+//! assignments, `for` and `match` bindings, `return`, trailing expressions
+//! and call arguments. A compound expression carries every name it reads.
+//! A call writes its result back into its receiver and `&mut` arguments.
+//! Calls that resolve to no function of the repo, and source and sink
+//! calls, pass every argument to their result.
+//!
+//! **Order**: Summaries are computed bottom-up over the strongly connected
+//! components of the call graph: Tarjan's algorithm from functions in key
+//! order, callees in key order, emits callees first. Each component is
+//! iterated to a fixpoint, its members in key order.
+//!
+//! **Invalidation**: A function's fingerprint hashes its CFG and DFG (taken
+//! from the previous summaries while its file's semantic fingerprint is
+//! unchanged), its flows and its resolved callees. A component is reused
+//! when every member's fingerprint is unchanged and its callees' summaries
+//! are equal to last time.
+
+use super::callgraph::{call_target, CallGraph, FunctionKey};
+use crate::config::AnalysisConfig;
+use crate::semantic::epoch::SemanticEpoch;
+use crate::semantic::model::FunctionId;
+use crate::types::{ByteRange, FileId};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use tree_sitter::Node;
+
+/// A value one function's flows connect
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Place {
+    /// Parameter by position (`self` first)
+    Param(usize),
+
+    /// Local variable or parameter binding, by name
+    Var(String),
+
+    Return,
+
+    /// Argument of the function's `call`-th call (a method call's receiver first)
+    Arg { call: usize, index: usize },
+
+    /// Result of the function's `call`-th call
+    Result { call: usize },
+}
+
+/// One call in a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    /// Name the call resolves by
+    pub callee: String,
+
+    /// Arguments, counting a method call's receiver
+    pub args: usize,
+
+    pub range: ByteRange,
+}
+
+/// Local flows of one function
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FunctionFlows {
+    /// Parameters, counting `self`
+    pub params: usize,
+
+    /// (from, to)
+    pub edges: BTreeSet<(Place, Place)>,
+
+    /// Calls in pre-order (outer calls before the calls in their arguments)
+    pub calls: Vec<CallSite>,
+}
+
+impl FunctionFlows {
+    /// Flows of a `function_item` (nested functions are skipped)
+    pub(crate) fn extract(function: Node, source: &[u8]) -> Self {
+        let mut extractor = FlowExtractor { source, flows: Self::default() };
+        extractor.function(function);
+        extractor.flows
+    }
+
+    /// Hash of everything but call ranges
+    fn hash_into(&self, hasher: &mut Sha256) {
+        hasher.update((self.params as u64).to_be_bytes());
+        for (from, to) in &self.edges {
+            hash_place(hasher, from);
+            hash_place(hasher, to);
+        }
+        for call in &self.calls {
+            hash_str(hasher, &call.callee);
+            hasher.update((call.args as u64).to_be_bytes());
+        }
+    }
+}
+
+/// Walks one function body, recording flows
+struct FlowExtractor<'a> {
+    source: &'a [u8],
+    flows: FunctionFlows,
+}
+
+impl FlowExtractor<'_> {
+    fn function(&mut self, node: Node) {
+        if let Some(parameters) = node.child_by_field_name("parameters") {
+            let mut cursor = parameters.walk();
+            for parameter in parameters.named_children(&mut cursor) {
+                let index = self.flows.params;
+                match parameter.kind() {
+                    "self_parameter" => self.edge(Place::Param(index), Place::Var("self".to_string())),
+                    "parameter" => {
+                        if let Some(pattern) = parameter.child_by_field_name("pattern") {
+                            self.bind(pattern, &[Place::Param(index)].into());
+                        }
+                    }
+                    _ => continue,
+                }
+                self.flows.params += 1;
+            }
+        }
+        if let Some(body) = node.child_by_field_name("body") {
+            let value = self.eval(body);
+            self.flow(&value, Place::Return);
+        }
+    }
+
+    /// Record what `node` does and return the places its value carries
+    fn eval(&mut self, node: Node) -> BTreeSet<Place> {
+        match node.kind() {
+            "identifier" | "self" => [Place::Var(self.text(node))].into(),
+            "function_item" | "scoped_identifier" | "line_comment" | "block_comment" => BTreeSet::new(),
+            "call_expression" => self.call(node),
+            "let_declaration" | "let_condition" => {
+                let value = self.eval_field(node, "value");
+                if let Some(pattern) = node.child_by_field_name("pattern") {
+                    self.bind(pattern, &value);
+                }
+                self.eval_field(node, "alternative");
+                // `if let` carries its value into the condition; `let` is a statement
+                if node.kind() == "let_condition" { value } else { BTreeSet::new() }
+            }
+            "assignment_expression" | "compound_assignment_expr" => {
+                let value = self.eval_field(node, "right");
+                if let Some(left) = node.child_by_field_name("left") {
+                    self.eval(left);
+                    if let Some(target) = self.assigned_name(left) {
+                        self.flow(&value, Place::Var(target));
+                    }
+                }
+                BTreeSet::new()
+            }
+            "return_expression" => {
+                let value = self.union(node);
+                self.flow(&value, Place::Return);
+                BTreeSet::new()
+            }
+            "expression_statement" => {
+                self.union(node);
+                BTreeSet::new()
+            }
+            "block" => {
+                let mut cursor = node.walk();
+                let statements: Vec<Node> = node.named_children(&mut cursor)
+                    .filter(|child| !child.kind().ends_with("comment"))
+                    .collect();
+                let mut value = BTreeSet::new();
+                for (i, statement) in statements.iter().enumerate() {
+                    let trailing = i + 1 == statements.len() && !ends_with_semicolon(*statement);
+                    value = match statement.named_child(0) {
+                        // A block-like trailing expression parses as a statement without `;`
+                        Some(expression) if trailing && statement.kind() == "expression_statement" => self.eval(expression),
+                        _ => self.eval(*statement),
+                    };
+                }
+                value
+            }
+            "field_expression" => self.eval_field(node, "value"),
+            "macro_invocation" => {
+                let name = node.child_by_field_name("macro").map(|m| m.id());
+                let mut value = BTreeSet::new();
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor).filter(|c| Some(c.id()) != name) {
+                    value.extend(self.eval(child));
+                }
+                value
+            }
+            "for_expression" => {
+                let value = self.eval_field(node, "value");
+                if let Some(pattern) = node.child_by_field_name("pattern") {
+                    self.bind(pattern, &value);
+                }
+                self.eval_field(node, "body");
+                BTreeSet::new()
+            }
+            "match_expression" => {
+                let scrutinee = self.eval_field(node, "value");
+                let mut value = BTreeSet::new();
+                if let Some(body) = node.child_by_field_name("body") {
+                    let mut cursor = body.walk();
+                    for arm in body.named_children(&mut cursor).filter(|c| c.kind() == "match_arm") {
+                        if let Some(pattern) = arm.child_by_field_name("pattern") {
+                            self.bind(pattern, &scrutinee);
+                            self.union(pattern);
+                        }
+                        value.extend(self.eval_field(arm, "value"));
+                    }
+                }
+                value
+            }
+            _ => self.union(node),
+        }
+    }
+
+    fn eval_field(&mut self, node: Node, field: &str) -> BTreeSet<Place> {
+        node.child_by_field_name(field).map(|child| self.eval(child)).unwrap_or_default()
+    }
+
+    /// Union of every named child's value
+    fn union(&mut self, node: Node) -> BTreeSet<Place> {
+        let mut value = BTreeSet::new();
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            value.extend(self.eval(child));
+        }
+        value
+    }
+
+    fn call(&mut self, node: Node) -> BTreeSet<Place> {
+        let mut function = node.child_by_field_name("function");
+        let Some(target) = function.and_then(call_target) else {
+            // Closures and other unnamed callees pass everything through
+            return self.union(node);
+        };
+        let call = self.flows.calls.len();
+        self.flows.calls.push(CallSite {
+            callee: self.text(target),
+            args: 0,
+            range: ByteRange::of_node(&node),
+        });
+
+        let mut args = Vec::new();
+        while let Some(f) = function.filter(|f| f.kind() == "generic_function") {
+            function = f.child_by_field_name("function");
+        }
+        if let Some(receiver) = function.filter(|f| f.kind() == "field_expression").and_then(|f| f.child_by_field_name("value")) {
+            args.push((receiver, true));
+        }
+        if let Some(arguments) = node.child_by_field_name("arguments") {
+            let mut cursor = arguments.walk();
+            args.extend(arguments.named_children(&mut cursor)
+                .filter(|a| !a.kind().ends_with("comment") && a.kind() != "attribute_item")
+                .map(|a| (a, false)));
+        }
+
+        self.flows.calls[call].args = args.len();
+        for (index, (arg, receiver)) in args.into_iter().enumerate() {
+            let value = self.eval(arg);
+            self.flow(&value, Place::Arg { call, index });
+            let written = if receiver { self.assigned_name(arg) } else { self.mut_borrowed(arg) };
+            if let Some(name) = written {
+                self.edge(Place::Result { call }, Place::Var(name));
+            }
+        }
+        [Place::Result { call }].into()
+    }
+
+    /// Bind every name `pattern` introduces to `value`
+    fn bind(&mut self, pattern: Node, value: &BTreeSet<Place>) {
+        let mut names = Vec::new();
+        self.bound_names(pattern, &mut names);
+        for name in names {
+            self.flow(value, Place::Var(name));
+        }
+    }
+
+    fn bound_names(&self, node: Node, names: &mut Vec<String>) {
+        match node.kind() {
+            "identifier" | "shorthand_field_identifier" => names.push(self.text(node)),
+            "scoped_identifier" | "type_identifier" => {}
+            _ => {
+                // Skip the path of `Some(x)` and `S { x }`
+                let path = node.child_by_field_name("type").map(|t| t.id());
+                let mut cursor = node.walk();
+                for child in node.named_children(&mut cursor).filter(|c| Some(c.id()) != path) {
+                    self.bound_names(child, names);
+                }
+            }
+        }
+    }
+
+    /// Variable an assignment target writes (`x`, `x.f`, `x[i]`, `*x`)
+    fn assigned_name(&self, node: Node) -> Option<String> {
+        match node.kind() {
+            "identifier" | "self" => Some(self.text(node)),
+            "field_expression" => self.assigned_name(node.child_by_field_name("value")?),
+            "index_expression" | "unary_expression" | "parenthesized_expression" | "reference_expression" => {
+                self.assigned_name(node.named_child(0)?)
+            }
+            _ => None,
+        }
+    }
+
+    /// Variable of a `&mut x` argument
+    fn mut_borrowed(&self, node: Node) -> Option<String> {
+        let mut cursor = node.walk();
+        let mutable = node.kind() == "reference_expression"
+            && node.children(&mut cursor).any(|c| c.kind() == "mutable_specifier");
+        if !mutable {
+            return None;
+        }
+        self.assigned_name(node.child_by_field_name("value")?)
+    }
+
+    fn flow(&mut self, from: &BTreeSet<Place>, to: Place) {
+        for place in from {
+            if *place != to {
+                self.edge(place.clone(), to.clone());
+            }
+        }
+    }
+
+    fn edge(&mut self, from: Place, to: Place) {
+        self.flows.edges.insert((from, to));
+    }
+
+    fn text(&self, node: Node) -> String {
+        let range = ByteRange::of_node(&node);
+        String::from_utf8_lossy(&self.source[range.start..range.end]).to_string()
+    }
+}
+
+fn ends_with_semicolon(node: Node) -> bool {
+    node.child(node.child_count().saturating_sub(1)).is_some_and(|last| last.kind() == ";")
+}
+
+/// Source and sink calls, by callee name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaintSpec {
+    /// Calls whose result is tainted
+    pub sources: BTreeSet<String>,
+
+    /// Calls whose arguments must not be tainted
+    pub sinks: BTreeSet<String>,
+}
+
+impl TaintSpec {
+    /// Functions a call by this name crosses into (none for sources and sinks)
+    pub fn resolve<'g>(&self, graph: &'g CallGraph, name: &str) -> &'g [FunctionKey] {
+        if self.sources.contains(name) || self.sinks.contains(name) {
+            return &[];
+        }
+        graph.resolve(name)
+    }
+}
+
+impl From<&AnalysisConfig> for TaintSpec {
+    fn from(config: &AnalysisConfig) -> Self {
+        Self {
+            sources: config.taint_sources.iter().cloned().collect(),
+            sinks: config.taint_sinks.iter().cloned().collect(),
+        }
+    }
+}
+
+/// One call, by function and position in `FunctionFlows::calls`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CallRef {
+    pub file_id: FileId,
+    pub function_id: FunctionId,
+    pub call: usize,
+}
+
+impl CallRef {
+    pub fn new((file_id, function_id): FunctionKey, call: usize) -> Self {
+        Self { file_id, function_id, call }
+    }
+
+    pub fn function(&self) -> FunctionKey {
+        (self.file_id, self.function_id)
+    }
+}
+
+/// One argument of a function of the repo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CalleeArg {
+    pub file_id: FileId,
+    pub function_id: FunctionId,
+    pub index: usize,
+}
+
+/// Where one function's parameters may flow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionSummary {
+    pub file_id: FileId,
+    pub function_id: FunctionId,
+
+    /// CFG and DFG hash
+    pub semantic_hash: String,
+
+    /// Semantic hash, flows and resolved callees
+    pub fingerprint: String,
+
+    /// Parameters the return value may carry
+    pub params_to_return: BTreeSet<usize>,
+
+    /// Callee arguments each parameter may reach (direct calls)
+    pub params_to_callees: BTreeMap<usize, BTreeSet<CalleeArg>>,
+
+    /// Sink calls each parameter may reach (here or in any callee)
+    pub params_to_sinks: BTreeMap<usize, BTreeSet<CallRef>>,
+
+    /// Source calls (here or in any callee) the return value may carry
+    pub return_sources: BTreeSet<CallRef>,
+}
+
+impl FunctionSummary {
+    fn empty(key: FunctionKey, semantic_hash: String, fingerprint: String) -> Self {
+        Self {
+            file_id: key.0,
+            function_id: key.1,
+            semantic_hash,
+            fingerprint,
+            params_to_return: BTreeSet::new(),
+            params_to_callees: BTreeMap::new(),
+            params_to_sinks: BTreeMap::new(),
+            return_sources: BTreeSet::new(),
+        }
+    }
+
+    pub fn key(&self) -> FunctionKey {
+        (self.file_id, self.function_id)
+    }
+
+    /// Whether both summarize the same flows (hashes aside)
+    pub fn same_flows(&self, other: &Self) -> bool {
+        self.params_to_return == other.params_to_return
+            && self.params_to_callees == other.params_to_callees
+            && self.params_to_sinks == other.params_to_sinks
+            && self.return_sources == other.return_sources
+    }
+
+    /// Cross a call to this function: extend `result` with what the call
+    /// returns and `sunk` with (label, sink) pairs its arguments reach
+    pub(crate) fn apply(&self, args: &[BTreeSet<Label>], result: &mut BTreeSet<Label>, sunk: &mut BTreeSet<(Label, CallRef)>) {
+        for (index, labels) in args.iter().enumerate() {
+            if self.params_to_return.contains(&index) {
+                result.extend(labels.iter().copied());
+            }
+            for sink in self.params_to_sinks.get(&index).into_iter().flatten() {
+                sunk.extend(labels.iter().map(|label| (*label, *sink)));
+            }
+        }
+        result.extend(self.return_sources.iter().map(|source| Label::Source(*source)));
+    }
+}
+
+/// What a place may carry: a parameter's value or a source call's result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Label {
+    Param(usize),
+    Source(CallRef),
+}
+
+/// Labels reaching each place of one function
+///
+/// `transfer(call, args)` gives the labels of a call's result from the
+/// labels of its arguments; it is called again whenever those grow.
+pub(crate) fn solve(
+    flows: &FunctionFlows,
+    mut seeds: BTreeMap<Place, BTreeSet<Label>>,
+    mut transfer: impl FnMut(usize, &[BTreeSet<Label>]) -> BTreeSet<Label>,
+) -> BTreeMap<Place, BTreeSet<Label>> {
+    let mut successors: BTreeMap<&Place, Vec<&Place>> = BTreeMap::new();
+    for (from, to) in &flows.edges {
+        successors.entry(from).or_default().push(to);
+    }
+    let mut crossed: Vec<Option<Vec<BTreeSet<Label>>>> = vec![None; flows.calls.len()];
+
+    loop {
+        let labels = flood(&successors, &seeds);
+        let mut changed = false;
+        for (call, site) in flows.calls.iter().enumerate() {
+            let args: Vec<BTreeSet<Label>> = (0..site.args)
+                .map(|index| labels.get(&Place::Arg { call, index }).cloned().unwrap_or_default())
+                .collect();
+            if crossed[call].as_ref() == Some(&args) {
+                continue;
+            }
+            let result = transfer(call, &args);
+            crossed[call] = Some(args);
+            let entry = seeds.entry(Place::Result { call }).or_default();
+            for label in result {
+                changed |= entry.insert(label);
+            }
+        }
+        if !changed {
+            return labels;
+        }
+    }
+}
+
+fn flood(
+    successors: &BTreeMap<&Place, Vec<&Place>>,
+    seeds: &BTreeMap<Place, BTreeSet<Label>>,
+) -> BTreeMap<Place, BTreeSet<Label>> {
+    let mut labels = seeds.clone();
+    let mut worklist: Vec<&Place> = seeds.keys().collect();
+    while let Some(place) = worklist.pop() {
+        let Some(carried) = labels.get(place).cloned() else { continue };
+        for next in successors.get(place).into_iter().flatten() {
+            let entry = labels.entry((*next).clone()).or_default();
+            let before = entry.len();
+            entry.extend(carried.iter().copied());
+            if entry.len() != before {
+                worklist.push(next);
+            }
+        }
+    }
+    labels
+}
+
+/// Summaries of every function in a call graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FunctionSummaries {
+    spec: TaintSpec,
+
+    /// Semantic fingerprint of each file, as of these summaries
+    files: BTreeMap<FileId, String>,
+
+    /// By key
+    summaries: Vec<FunctionSummary>,
+
+    /// Functions summarized again by the last `update`, by key
+    #[serde(skip)]
+    recomputed: Vec<FunctionKey>,
+}
+
+impl FunctionSummaries {
+    /// Summarize every function of `graph`
+    pub fn compute(graph: &CallGraph, semantic: &SemanticEpoch, spec: &TaintSpec) -> Self {
+        Self::update(None, graph, semantic, spec)
+    }
+
+    /// Summarize every function of `graph`, reusing the unchanged
+    /// components of `previous` (nothing is reused if its spec differs)
+    pub fn update(previous: Option<&Self>, graph: &CallGraph, semantic: &SemanticEpoch, spec: &TaintSpec) -> Self {
+        let previous = previous.filter(|p| p.spec == *spec);
+        let reusable: BTreeMap<FunctionKey, &FunctionSummary> = previous
+            .map(|p| p.summaries.iter().map(|s| (s.key(), s)).collect())
+            .unwrap_or_default();
+
+        let mut files = BTreeMap::new();
+        let mut semantic_hashes: BTreeMap<FunctionKey, String> = BTreeMap::new();
+        let file_ids: BTreeSet<FileId> = graph.functions().map(|f| f.file_id).collect();
+        for file_id in file_ids {
+            let fingerprint = semantic.fingerprint(file_id).map(str::to_string);
+            let unchanged = fingerprint.is_some() && previous.and_then(|p| p.files.get(&file_id)) == fingerprint.as_ref();
+            let mut fresh = None;
+            for function in graph.functions().filter(|f| f.file_id == file_id) {
+                let key = (file_id, function.function_id);
+                let hash = match reusable.get(&key).filter(|_| unchanged) {
+                    Some(summary) => summary.semantic_hash.clone(),
+                    None => fresh.get_or_insert_with(|| function_hashes(semantic, file_id))
+                        .get(&function.function_id)
+                        .cloned()
+                        .unwrap_or_else(|| semantic_hash(None, None)),
+                };
+                semantic_hashes.insert(key, hash);
+            }
+            if let Some(fingerprint) = fingerprint {
+                files.insert(file_id, fingerprint);
+            }
+        }
+
+        let empty = FunctionFlows::default();
+        let callees: BTreeMap<FunctionKey, Vec<FunctionKey>> = semantic_hashes.keys()
+            .map(|key| {
+                let flows = graph.flows(*key).unwrap_or(&empty);
+                let mut callees: Vec<FunctionKey> = flows.calls.iter()
+                    .flat_map(|site| spec.resolve(graph, &site.callee).iter().copied())
+                    .collect();
+                callees.sort();
+                callees.dedup();
+                (*key, callees)
+            })
+            .collect();
+
+        let mut summaries: BTreeMap<FunctionKey, FunctionSummary> = BTreeMap::new();
+        let mut recomputed = Vec::new();
+        for component in components(&callees) {
+            let fingerprints: Vec<String> = component.iter()
+                .map(|key| fingerprint(&semantic_hashes[key], graph.flows(*key).unwrap_or(&empty), &callees[key]))
+                .collect();
+            let members_unchanged = component.iter().zip(&fingerprints)
+                .all(|(key, fingerprint)| reusable.get(key).is_some_and(|s| s.fingerprint == *fingerprint));
+            let callees_unchanged = component.iter()
+                .flat_map(|key| &callees[key])
+                .filter(|callee| !component.contains(callee))
+                .all(|callee| reusable.get(callee).is_some_and(|old| summaries[callee].same_flows(old)));
+            if members_unchanged && callees_unchanged {
+                summaries.extend(component.iter().map(|key| (*key, reusable[key].clone())));
+                continue;
+            }
+
+            for (key, fingerprint) in component.iter().zip(fingerprints) {
+                summaries.insert(*key, FunctionSummary::empty(*key, semantic_hashes[key].clone(), fingerprint));
+            }
+            // Members start empty and only grow: iterate to the fixpoint
+            loop {
+                let mut changed = false;
+                for key in &component {
+                    let next = summarize(*key, graph.flows(*key).unwrap_or(&empty), graph, spec, &summaries);
+                    let current = summaries.get_mut(key).expect("member inserted above");
+                    if !next.same_flows(current) {
+                        *current = FunctionSummary { semantic_hash: current.semantic_hash.clone(), fingerprint: current.fingerprint.clone(), ..next };
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            recomputed.extend(component);
+        }
+        recomputed.sort();
+
+        Self {
+            spec: spec.clone(),
+            files,
+            summaries: summaries.into_values().collect(),
+            recomputed,
+        }
+    }
+
+    /// Summary of one function
+    pub fn get(&self, key: FunctionKey) -> Option<&FunctionSummary> {
+        self.summaries.binary_search_by_key(&key, FunctionSummary::key).ok().map(|i| &self.summaries[i])
+    }
+
+    /// All summaries, by key
+    pub fn summaries(&self) -> &[FunctionSummary] {
+        &self.summaries
+    }
+
+    /// Source and sink calls the summaries were computed for
+    pub fn spec(&self) -> &TaintSpec {
+        &self.spec
+    }
+
+    /// Functions the `update` that built these summarized again, by key
+    pub fn recomputed(&self) -> &[FunctionKey] {
+        &self.recomputed
+    }
+
+    pub fn len(&self) -> usize {
+        self.summaries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summaries.is_empty()
+    }
+}
+
+/// Labels of a call's result: sources start there, sinks add the labels of
+/// every argument to `sunk`, calls outside the repo pass every argument
+/// through and `callee` crosses into each function the call resolves to
+pub(crate) fn cross_call(
+    graph: &CallGraph,
+    spec: &TaintSpec,
+    at: CallRef,
+    site: &CallSite,
+    args: &[BTreeSet<Label>],
+    sunk: &mut BTreeSet<(Label, CallRef)>,
+    mut callee: impl FnMut(FunctionKey, &mut BTreeSet<Label>, &mut BTreeSet<(Label, CallRef)>),
+) -> BTreeSet<Label> {
+    let mut result = BTreeSet::new();
+    if spec.sinks.contains(&site.callee) {
+        sunk.extend(args.iter().flatten().map(|label| (*label, at)));
+    }
+    if spec.sources.contains(&site.callee) {
+        result.insert(Label::Source(at));
+    }
+    let callees = spec.resolve(graph, &site.callee);
+    if callees.is_empty() {
+        result.extend(args.iter().flatten().copied());
+    }
+    for key in callees {
+        callee(*key, &mut result, sunk);
+    }
+    result
+}
+
+/// Summary of one function from the current summaries of its callees
+fn summarize(
+    key: FunctionKey,
+    flows: &FunctionFlows,
+    graph: &CallGraph,
+    spec: &TaintSpec,
+    summaries: &BTreeMap<FunctionKey, FunctionSummary>,
+) -> FunctionSummary {
+    let seeds = (0..flows.params).map(|i| (Place::Param(i), [Label::Param(i)].into())).collect();
+    let mut sunk = BTreeSet::new();
+    let mut reached = BTreeSet::new();
+    let labels = solve(flows, seeds, |call, args| {
+        cross_call(graph, spec, CallRef::new(key, call), &flows.calls[call], args, &mut sunk, |callee, result, sunk| {
+            for (index, labels) in args.iter().enumerate() {
+                for label in labels {
+                    if let Label::Param(param) = label {
+                        reached.insert((*param, CalleeArg { file_id: callee.0, function_id: callee.1, index }));
+                    }
+                }
+            }
+            if let Some(summary) = summaries.get(&callee) {
+                summary.apply(args, result, sunk);
+            }
+        })
+    });
+
+    let mut summary = FunctionSummary::empty(key, String::new(), String::new());
+    for label in labels.get(&Place::Return).into_iter().flatten() {
+        match label {
+            Label::Param(param) => {
+                summary.params_to_return.insert(*param);
+            }
+            Label::Source(source) => {
+                summary.return_sources.insert(*source);
+            }
+        }
+    }
+    for (label, sink) in sunk {
+        if let Label::Param(param) = label {
+            summary.params_to_sinks.entry(param).or_default().insert(sink);
+        }
+    }
+    for (param, arg) in reached {
+        summary.params_to_callees.entry(param).or_default().insert(arg);
+    }
+    summary
+}
+
+/// Strongly connected components, callees before callers
+///
+/// Iterative Tarjan from keys in order, successors in order.
+fn components(callees: &BTreeMap<FunctionKey, Vec<FunctionKey>>) -> Vec<Vec<FunctionKey>> {
+    let none = Vec::new();
+    let successors = |key: &FunctionKey| callees.get(key).unwrap_or(&none);
+
+    let mut index: BTreeMap<FunctionKey, usize> = BTreeMap::new();
+    let mut low: BTreeMap<FunctionKey, usize> = BTreeMap::new();
+    let mut stack: Vec<FunctionKey> = Vec::new();
+    let mut on_stack: BTreeSet<FunctionKey> = BTreeSet::new();
+    let mut components = Vec::new();
+
+    for root in callees.keys() {
+        if index.contains_key(root) {
+            continue;
+        }
+        let mut work: Vec<(FunctionKey, usize)> = vec![(*root, 0)];
+        index.insert(*root, index.len());
+        low.insert(*root, low.len());
+        stack.push(*root);
+        on_stack.insert(*root);
+
+        while let Some((node, next)) = work.last_mut() {
+            let node = *node;
+            if let Some(successor) = successors(&node).get(*next).copied() {
+                *next += 1;
+                if !index.contains_key(&successor) {
+                    index.insert(successor, index.len());
+                    low.insert(successor, low.len());
+                    stack.push(successor);
+                    on_stack.insert(successor);
+                    work.push((successor, 0));
+                } else if on_stack.contains(&successor) {
+                    let lowest = low[&node].min(index[&successor]);
+                    low.insert(node, lowest);
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some((parent, _)) = work.last() {
+                let lowest = low[parent].min(low[&node]);
+                low.insert(*parent, lowest);
+            }
+            if low[&node] == index[&node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack.remove(&member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Semantic hash of every function with a CFG or DFG in one file
+fn function_hashes(semantic: &SemanticEpoch, file_id: FileId) -> BTreeMap<FunctionId, String> {
+    let mut hashes: BTreeMap<FunctionId, (Option<String>, Option<String>)> = BTreeMap::new();
+    for cfg in semantic.get_cfgs(file_id).into_iter().flatten() {
+        hashes.entry(cfg.function_id).or_default().0 = Some(cfg.compute_hash());
+    }
+    for dfg in semantic.get_dfgs(file_id).into_iter().flatten() {
+        hashes.entry(dfg.function_id).or_default().1 = Some(dfg.compute_hash(semantic.strings()));
+    }
+    hashes.into_iter()
+        .map(|(function_id, (cfg, dfg))| (function_id, semantic_hash(cfg.as_deref(), dfg.as_deref())))
+        .collect()
+}
+
+fn semantic_hash(cfg: Option<&str>, dfg: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    hash_str(&mut hasher, cfg.unwrap_or(""));
+    hash_str(&mut hasher, dfg.unwrap_or(""));
+    format!("{:x}", hasher.finalize())
+}
+
+fn fingerprint(semantic_hash: &str, flows: &FunctionFlows, callees: &[FunctionKey]) -> String {
+    let mut hasher = Sha256::new();
+    hash_str(&mut hasher, semantic_hash);
+    flows.hash_into(&mut hasher);
+    for (file_id, function_id) in callees {
+        hasher.update(file_id.as_u64().to_be_bytes());
+        hasher.update(function_id.0.to_be_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn hash_str(hasher: &mut Sha256, value: &str) {
+    hasher.update((value.len() as u64).to_be_bytes());
+    hasher.update(value.as_bytes());
+}
+
+fn hash_place(hasher: &mut Sha256, place: &Place) {
+    match place {
+        Place::Param(index) => {
+            hasher.update([0]);
+            hasher.update((*index as u64).to_be_bytes());
+        }
+        Place::Var(name) => {
+            hasher.update([1]);
+            hash_str(hasher, name);
+        }
+        Place::Return => hasher.update([2]),
+        Place::Arg { call, index } => {
+            hasher.update([3]);
+            hasher.update((*call as u64).to_be_bytes());
+            hasher.update((*index as u64).to_be_bytes());
+        }
+        Place::Result { call } => {
+            hasher.update([4]);
+            hasher.update((*call as u64).to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::IncrementalParser;
+    use crate::types::{Language, ParsedFile};
+    use std::path::Path;
+    use tempfile::NamedTempFile;
+
+    fn graph(source: &[u8]) -> CallGraph {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), source).unwrap();
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), FileId::new(1)).unwrap();
+        let parsed: ParsedFile = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+
+        let mut graph = CallGraph::new();
+        graph.add_file(Path::new("src/lib.rs"), &parsed, source);
+        graph
+    }
+
+    fn key(id: u64) -> FunctionKey {
+        (FileId::new(1), FunctionId(id))
+    }
+
+    fn var(name: &str) -> Place {
+        Place::Var(name.to_string())
+    }
+
+    #[test]
+    fn test_flows_of_statements() {
+        let graph = graph(b"fn f(a: i32, (b, c): (i32, i32)) -> i32 { let d = a + 1; g(d, b); return c; }\n");
+        let flows = graph.flows(key(0)).unwrap();
+
+        assert_eq!(flows.params, 2);
+        assert_eq!(flows.calls.len(), 1);
+        assert_eq!((flows.calls[0].callee.as_str(), flows.calls[0].args), ("g", 2));
+        for edge in [
+            (Place::Param(0), var("a")),
+            (Place::Param(1), var("b")),
+            (Place::Param(1), var("c")),
+            (var("a"), var("d")),
+            (var("d"), Place::Arg { call: 0, index: 0 }),
+            (var("b"), Place::Arg { call: 0, index: 1 }),
+            (var("c"), Place::Return),
+        ] {
+            assert!(flows.edges.contains(&edge), "missing {:?}", edge);
+        }
+        // `g(d, b);` is a statement: its result flows nowhere
+        assert!(!flows.edges.iter().any(|(from, _)| *from == Place::Result { call: 0 }));
+    }
+
+    #[test]
+    fn test_trailing_expressions_return() {
+        let graph = graph(b"fn f(a: i32, b: i32) -> i32 { if a > 0 { a } else { b } }\nfn g(a: i32) { let b = a; }\n");
+
+        let f = graph.flows(key(0)).unwrap();
+        assert!(f.edges.contains(&(var("a"), Place::Return)));
+        assert!(f.edges.contains(&(var("b"), Place::Return)));
+        // A trailing `let` is a statement
+        assert!(!graph.flows(key(1)).unwrap().edges.iter().any(|(_, to)| *to == Place::Return));
+    }
+
+    #[test]
+    fn test_calls_write_back_into_receiver_and_mut_args() {
+        let graph = graph(b"fn f(&self) { let mut buf = String::new(); stdin().read_line(&mut buf); self.items.push(buf); }\n");
+        let flows = graph.flows(key(0)).unwrap();
+
+        let callees: Vec<_> = flows.calls.iter().map(|c| c.callee.as_str()).collect();
+        assert_eq!(callees, vec!["new", "read_line", "stdin", "push"]);
+        assert_eq!(flows.params, 1);
+        assert!(flows.edges.contains(&(Place::Param(0), var("self"))));
+        assert!(flows.edges.contains(&(Place::Result { call: 1 }, var("buf"))));
+        assert!(flows.edges.contains(&(Place::Result { call: 3 }, var("self"))));
+        assert!(flows.edges.contains(&(var("buf"), Place::Arg { call: 3, index: 1 })));
+    }
+
+    #[test]
+    fn test_match_and_for_bind_their_value() {
+        let graph = graph(b"fn f(o: Option<i32>, v: Vec<i32>) { match o { Some(x) => sink(x), None => {} } for y in v { sink(y); } }\n");
+        let flows = graph.flows(key(0)).unwrap();
+
+        assert!(flows.edges.contains(&(var("o"), var("x"))));
+        assert!(flows.edges.contains(&(var("v"), var("y"))));
+        assert!(!flows.edges.contains(&(var("o"), var("Some"))));
+    }
+
+    #[test]
+    fn test_components_callees_first() {
+        // a → b ⇄ c → d, e alone
+        let callees: BTreeMap<FunctionKey, Vec<FunctionKey>> = [
+            (key(0), vec![key(1)]),
+            (key(1), vec![key(2)]),
+            (key(2), vec![key(1), key(3)]),
+            (key(3), vec![]),
+            (key(4), vec![]),
+        ].into();
+
+        assert_eq!(components(&callees), vec![
+            vec![key(3)],
+            vec![key(1), key(2)],
+            vec![key(0)],
+            vec![key(4)],
+        ]);
+    }
+}
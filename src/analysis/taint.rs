@@ -4,12 +4,18 @@
 //! - Deterministic BFS from sources
 //! - Bounded depth (no infinite loops)
 //! - Every taint must be traceable
+//! - Optionally crosses may-alias pairs (`analyze_with_aliases`)
+//! - Stoppable between BFS steps (`analyze_cancellable`)
 
-use crate::cpg::model::{CPG, CPGNodeId, CPGEdgeKind};
+use crate::analysis::pointer::{PointerAnalysis, PointsToSet};
+use crate::cpg::model::{CPG, CPGNodeId, CPGEdgeKind, OriginRef};
+use crate::cpg::index::CPGIndices;
+use crate::execution::cancel::{uninterrupted, CancellationToken, Checkpoint, Interrupted};
+use crate::semantic::model::ValueId;
 use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Maximum taint propagation depth
-const MAX_TAINT_DEPTH: usize = 50;
+pub const MAX_TAINT_DEPTH: usize = 50;
 
 /// Taint sources
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -61,22 +67,76 @@ impl TaintAnalysis {
     ///
     /// **Bounded BFS**: Max depth to prevent infinite loops
     pub fn analyze(cpg: &CPG, sources: Vec<TaintSource>, sinks: Vec<TaintSink>) -> Self {
+        Self::analyze_bounded(cpg, sources, sinks, MAX_TAINT_DEPTH)
+    }
+
+    /// Run taint analysis with an explicit depth bound
+    pub fn analyze_bounded(cpg: &CPG, sources: Vec<TaintSource>, sinks: Vec<TaintSink>, max_depth: usize) -> Self {
+        uninterrupted(Self::analyze_cancellable(cpg, sources, sinks, max_depth, &CancellationToken::new()))
+    }
+
+    /// Run bounded taint analysis, stopping when `token` is cancelled or
+    /// times out
+    pub fn analyze_cancellable(
+        cpg: &CPG,
+        sources: Vec<TaintSource>,
+        sinks: Vec<TaintSink>,
+        max_depth: usize,
+        token: &CancellationToken,
+    ) -> Result<Self, Interrupted> {
+        Self::analyze_with_neighbours(cpg, sources, sinks, max_depth, &HashMap::new(), token)
+    }
+
+    /// Run taint analysis that also crosses may-alias pairs
+    ///
+    /// **Conservative**: A tainted value taints every value in its alias
+    /// set. A value whose points-to set overflowed may alias anything, so
+    /// it is reachable from every value and reaches every value. Each alias
+    /// step counts as one step of depth and appears in the path.
+    pub fn analyze_with_aliases(
+        cpg: &CPG,
+        sources: Vec<TaintSource>,
+        sinks: Vec<TaintSink>,
+        max_depth: usize,
+        pointers: &PointerAnalysis,
+    ) -> Self {
+        let aliases = alias_neighbours(cpg, pointers);
+        uninterrupted(Self::analyze_with_neighbours(cpg, sources, sinks, max_depth, &aliases, &CancellationToken::new()))
+    }
+
+    fn analyze_with_neighbours(
+        cpg: &CPG,
+        sources: Vec<TaintSource>,
+        sinks: Vec<TaintSink>,
+        max_depth: usize,
+        aliases: &HashMap<CPGNodeId, Vec<CPGNodeId>>,
+        token: &CancellationToken,
+    ) -> Result<Self, Interrupted> {
         let mut analysis = Self::new();
+        let mut checkpoint = Checkpoint::new(token);
+        let indices = CPGIndices::build(cpg);
+        let flows = Flows { cpg, indices: &indices, aliases };
 
         // BFS from each source
         for source in sources {
-            let source_node = match source {
-                TaintSource::Parameter(node) | TaintSource::ExternalInput(node) => node,
-            };
-            
-            analysis.propagate_from_source(cpg, source, source_node, &sinks);
+            analysis.propagate_from_source(&flows, source, &sinks, max_depth, &mut checkpoint)?;
         }
 
-        analysis
+        Ok(analysis)
     }
 
     /// Propagate taint from a source using bounded BFS
-    fn propagate_from_source(&mut self, cpg: &CPG, source: TaintSource, start: CPGNodeId, sinks: &[TaintSink]) {
+    fn propagate_from_source(
+        &mut self,
+        flows: &Flows,
+        source: TaintSource,
+        sinks: &[TaintSink],
+        max_depth: usize,
+        checkpoint: &mut Checkpoint,
+    ) -> Result<(), Interrupted> {
+        let start = match source {
+            TaintSource::Parameter(node) | TaintSource::ExternalInput(node) => node,
+        };
         let mut queue = VecDeque::new();
         let mut visited = HashMap::new();
         
@@ -84,8 +144,10 @@ impl TaintAnalysis {
         visited.insert(start, 0);
 
         while let Some((current, path, depth)) = queue.pop_front() {
+            checkpoint.step()?;
+
             // Depth limit
-            if depth >= MAX_TAINT_DEPTH {
+            if depth >= max_depth {
                 continue;
             }
 
@@ -107,21 +169,19 @@ impl TaintAnalysis {
                 }
             }
 
-            // Follow DataFlow edges
-            for edge in &cpg.edges {
-                if edge.from == current && edge.kind == CPGEdgeKind::DataFlow {
-                    let next_depth = depth + 1;
-                    
-                    // Only visit if haven't seen or found shorter path
-                    if !visited.contains_key(&edge.to) || visited[&edge.to] > next_depth {
-                        visited.insert(edge.to, next_depth);
-                        let mut new_path = path.clone();
-                        new_path.push(edge.to);
-                        queue.push_back((edge.to, new_path, next_depth));
-                    }
+            for next in flows.from(current) {
+                let next_depth = depth + 1;
+
+                // Only visit if haven't seen or found shorter path
+                if !visited.contains_key(&next) || visited[&next] > next_depth {
+                    visited.insert(next, next_depth);
+                    let mut new_path = path.clone();
+                    new_path.push(next);
+                    queue.push_back((next, new_path, next_depth));
                 }
             }
         }
+        Ok(())
     }
 
     /// Get all taint paths
@@ -143,6 +203,75 @@ impl TaintAnalysis {
     }
 }
 
+impl Default for TaintAnalysis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where taint flows from a node
+struct Flows<'a> {
+    cpg: &'a CPG,
+    indices: &'a CPGIndices,
+    aliases: &'a HashMap<CPGNodeId, Vec<CPGNodeId>>,
+}
+
+impl Flows<'_> {
+    /// Targets of DataFlow edges, then aliases
+    fn from(&self, node: CPGNodeId) -> impl Iterator<Item = CPGNodeId> + '_ {
+        let flows = self.cpg.iter_edges_from(self.indices, node)
+            .of_kind(CPGEdgeKind::DataFlow)
+            .map(|edge| edge.to);
+        flows.chain(self.aliases.get(&node).into_iter().flatten().copied())
+    }
+}
+
+/// Extra taint neighbours per DFG value node, ascending
+fn alias_neighbours(cpg: &CPG, pointers: &PointerAnalysis) -> HashMap<CPGNodeId, Vec<CPGNodeId>> {
+    let mut nodes_of: HashMap<ValueId, Vec<CPGNodeId>> = HashMap::new();
+    for node in &cpg.nodes {
+        if let OriginRef::Dfg { value_id } = node.origin {
+            nodes_of.entry(value_id).or_default().push(node.id);
+        }
+    }
+    let nodes_for = |values: &[ValueId]| -> Vec<CPGNodeId> {
+        let mut nodes: Vec<_> = values.iter()
+            .flat_map(|value| nodes_of.get(value).into_iter().flatten().copied())
+            .collect();
+        nodes.sort();
+        nodes
+    };
+
+    let mut unknown: Vec<ValueId> = nodes_of.keys()
+        .filter(|value| matches!(pointers.points_to(**value), Some(PointsToSet::Unknown)))
+        .copied()
+        .collect();
+    unknown.sort();
+    let unknown_nodes = nodes_for(&unknown);
+    let all_values: Vec<ValueId> = nodes_of.keys().copied().collect();
+    let all_nodes = nodes_for(&all_values);
+
+    let mut neighbours: HashMap<CPGNodeId, Vec<CPGNodeId>> = HashMap::new();
+    for group in pointers.alias_sets() {
+        let group_nodes = nodes_for(&group);
+        for node in &group_nodes {
+            neighbours.insert(*node, group_nodes.iter().filter(|n| *n != node).copied().collect());
+        }
+    }
+    if !unknown_nodes.is_empty() {
+        for node in &all_nodes {
+            let list = neighbours.entry(*node).or_default();
+            if unknown_nodes.contains(node) {
+                *list = all_nodes.iter().filter(|n| *n != node).copied().collect();
+            } else {
+                list.extend(unknown_nodes.iter().copied());
+                list.sort();
+            }
+        }
+    }
+    neighbours
+}
+
 /// Taint analysis statistics
 #[derive(Debug, Clone)]
 pub struct TaintAnalysisStats {
@@ -202,5 +331,87 @@ mod tests {
         assert!(analysis.is_tainted(CPGNodeId(1)));
         assert!(analysis.is_tainted(CPGNodeId(2)));
     }
-}
 
+    #[test]
+    fn test_taint_crosses_aliases() {
+        use crate::semantic::model::ValueId;
+        let mut cpg = CPG::new();
+        // 0 is a root, 1 and 2 are both copies of it, 3 is unrelated
+        for id in 0..4 {
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(id),
+                CPGNodeKind::DfgValue,
+                OriginRef::Dfg { value_id: ValueId(id) },
+                ByteRange::new(0, 0),
+            ));
+        }
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(0), CPGEdgeKind::DataFlow, CPGNodeId(0), CPGNodeId(1)));
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(1), CPGEdgeKind::DataFlow, CPGNodeId(0), CPGNodeId(2)));
+        let pointers = PointerAnalysis::analyze_from_roots(&cpg);
+
+        let sources = vec![TaintSource::Parameter(CPGNodeId(1))];
+        let sinks = vec![TaintSink::FunctionCall(CPGNodeId(2)), TaintSink::FunctionCall(CPGNodeId(3))];
+
+        let plain = TaintAnalysis::analyze(&cpg, sources.clone(), sinks.clone());
+        assert!(plain.paths().is_empty());
+
+        let aliased = TaintAnalysis::analyze_with_aliases(&cpg, sources, sinks, MAX_TAINT_DEPTH, &pointers);
+        assert_eq!(aliased.paths().len(), 1);
+        assert_eq!(aliased.paths()[0].path, vec![CPGNodeId(1), CPGNodeId(2)]);
+        assert!(aliased.is_tainted(CPGNodeId(0)));
+        assert!(!aliased.is_tainted(CPGNodeId(3)));
+    }
+
+    /// DataFlow chain 0 → 1 → ... → n-1
+    fn chain(n: u64) -> CPG {
+        use crate::semantic::model::ValueId;
+        let mut cpg = CPG::new();
+        for id in 0..n {
+            cpg.add_node(CPGNode::new(CPGNodeId(id), CPGNodeKind::DfgValue,
+                OriginRef::Dfg { value_id: ValueId(id) }, ByteRange::new(0, 0)));
+            if id > 0 {
+                cpg.add_edge(CPGEdge::new(CPGEdgeId(id), CPGEdgeKind::DataFlow, CPGNodeId(id - 1), CPGNodeId(id)));
+            }
+        }
+        cpg
+    }
+
+    #[test]
+    fn test_deadline_stops_long_propagation() {
+        use crate::execution::cancel::Progress;
+        use std::time::{Duration, Instant};
+
+        // Each BFS step scans every edge: quadratic, minutes to finish
+        let cpg = chain(50_000);
+        let token = CancellationToken::new().with_timeout(Duration::from_millis(20));
+        let sources = vec![TaintSource::Parameter(CPGNodeId(0))];
+        let sinks = vec![TaintSink::Return(CPGNodeId(49_999))];
+
+        let started = Instant::now();
+        let result = TaintAnalysis::analyze_cancellable(&cpg, sources, sinks, usize::MAX, &token);
+
+        let Err(Interrupted::Timeout(Progress { steps, .. })) = result else { panic!("expected a timeout") };
+        assert!(steps > 0 && steps < 50_000);
+        assert!(started.elapsed() < Duration::from_secs(5), "took {:?}", started.elapsed());
+    }
+
+    #[test]
+    fn test_unexpired_token_does_not_change_results() {
+        use std::time::Duration;
+
+        let cpg = chain(300);
+        let sources = vec![TaintSource::Parameter(CPGNodeId(0)), TaintSource::ExternalInput(CPGNodeId(120))];
+        let sinks = vec![TaintSink::Return(CPGNodeId(40)), TaintSink::FunctionCall(CPGNodeId(299))];
+        let token = CancellationToken::new().with_timeout(Duration::from_secs(3600));
+
+        let plain = TaintAnalysis::analyze_bounded(&cpg, sources.clone(), sinks.clone(), 500);
+        let tokened = TaintAnalysis::analyze_cancellable(&cpg, sources, sinks, 500, &token).unwrap();
+
+        let paths = |analysis: &TaintAnalysis| -> Vec<_> {
+            analysis.paths().iter().map(|p| (p.source, p.path.clone(), p.sink)).collect()
+        };
+        assert_eq!(paths(&tokened), paths(&plain));
+        assert_eq!(paths(&plain).len(), 3);
+        assert_eq!(tokened.stats().tainted_nodes, plain.stats().tainted_nodes);
+    }
+}
@@ -0,0 +1,189 @@
+//! Epoch transition events for embedding tools
+//!
+//! `ValoriAPI::subscribe` hands out a receiver of one `EpochEvent` per
+//! commit of a repo's build (refresh, update_files, overlay changes), sent
+//! after the commit in commit order. Every subscriber gets the full stream.
+//!
+//! Each receiver is a bounded `std::sync::mpsc` channel of `[events]
+//! capacity` events. A dropped receiver is forgotten on the next commit and
+//! never blocks one. A full receiver is handled by `[events] on_full`:
+//! `fail` (default) refuses the commit before anything changes, so no
+//! subscriber ever misses an epoch; `unsubscribe` commits anyway and
+//! disconnects the lagging subscriber, which sees the disconnect once it
+//! has drained its queue.
+
+use crate::api::{RepoHandle, ValoriError};
+use crate::config::SubscriberFullPolicy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvError, RecvTimeoutError, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A committed build of a repo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpochEvent {
+    /// The repo's epoch after the commit
+    pub epoch_id: u64,
+
+    /// Hash of the committed CPG
+    pub cpg_hash: String,
+
+    /// Normalized paths added, modified or deleted by the commit, sorted
+    pub changed_files: Vec<String>,
+
+    /// Commit time (seconds since the Unix epoch)
+    pub timestamp: u64,
+}
+
+/// Receiving end of a subscription
+///
+/// `recv` fails once the subscription ended (unsubscribed for lagging, or
+/// the API was dropped) and every queued event was received.
+pub struct EpochEventReceiver {
+    receiver: Receiver<EpochEvent>,
+
+    /// Events sent but not yet received (shared with the sender)
+    pending: Arc<AtomicUsize>,
+}
+
+impl EpochEventReceiver {
+    /// Block for the next event
+    pub fn recv(&self) -> Result<EpochEvent, RecvError> {
+        self.receiver.recv().inspect(|_| self.received())
+    }
+
+    /// Next event if one is queued
+    pub fn try_recv(&self) -> Result<EpochEvent, TryRecvError> {
+        self.receiver.try_recv().inspect(|_| self.received())
+    }
+
+    /// Block for the next event, at most `timeout`
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<EpochEvent, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout).inspect(|_| self.received())
+    }
+
+    /// Every queued event, without blocking
+    pub fn drain(&self) -> Vec<EpochEvent> {
+        std::iter::from_fn(|| self.try_recv().ok()).collect()
+    }
+
+    fn received(&self) {
+        self.pending.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Sending end of one subscription
+struct Subscriber {
+    sender: SyncSender<EpochEvent>,
+    pending: Arc<AtomicUsize>,
+}
+
+impl Subscriber {
+    fn is_full(&self, capacity: usize) -> bool {
+        self.pending.load(Ordering::Acquire) >= capacity
+    }
+
+    /// Send without blocking; false if the receiver is gone or full
+    fn send(&self, event: EpochEvent) -> bool {
+        self.pending.fetch_add(1, Ordering::AcqRel);
+        match self.sender.try_send(event) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => {
+                self.pending.fetch_sub(1, Ordering::AcqRel);
+                false
+            }
+        }
+    }
+}
+
+/// Subscriptions of every repo
+pub(crate) struct Subscriptions {
+    capacity: usize,
+    on_full: SubscriberFullPolicy,
+    by_repo: HashMap<RepoHandle, Vec<Subscriber>>,
+}
+
+impl Subscriptions {
+    pub(crate) fn new(capacity: usize, on_full: SubscriberFullPolicy) -> Self {
+        Self { capacity: capacity.max(1), on_full, by_repo: HashMap::new() }
+    }
+
+    /// Add a subscriber to a repo's commits
+    pub(crate) fn subscribe(&mut self, handle: RepoHandle) -> EpochEventReceiver {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let pending = Arc::new(AtomicUsize::new(0));
+        self.by_repo.entry(handle).or_default().push(Subscriber { sender, pending: pending.clone() });
+        EpochEventReceiver { receiver, pending }
+    }
+
+    /// Fail before a commit that a full subscriber could not receive
+    ///
+    /// Only under `on_full = "fail"`; dropped receivers never count.
+    pub(crate) fn check(&self, handle: RepoHandle) -> Result<(), ValoriError> {
+        if self.on_full != SubscriberFullPolicy::Fail {
+            return Ok(());
+        }
+        let full = self.by_repo.get(&handle).into_iter().flatten()
+            .filter(|subscriber| subscriber.is_full(self.capacity))
+            .count();
+        match full {
+            0 => Ok(()),
+            full => Err(ValoriError::SubscriberFull(full)),
+        }
+    }
+
+    /// Send a committed epoch to every subscriber of the repo
+    ///
+    /// Subscribers whose receiver is gone (or full, past `check`) are
+    /// removed.
+    pub(crate) fn publish(&mut self, handle: RepoHandle, event: EpochEvent) {
+        if let Some(subscribers) = self.by_repo.get_mut(&handle) {
+            subscribers.retain(|subscriber| subscriber.send(event.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(epoch_id: u64) -> EpochEvent {
+        EpochEvent { epoch_id, cpg_hash: String::new(), changed_files: Vec::new(), timestamp: 0 }
+    }
+
+    #[test]
+    fn test_full_subscriber_fails_check() {
+        let handle = RepoHandle(1);
+        let mut subscriptions = Subscriptions::new(2, SubscriberFullPolicy::Fail);
+        let receiver = subscriptions.subscribe(handle);
+
+        subscriptions.publish(handle, event(2));
+        subscriptions.publish(handle, event(3));
+        assert_eq!(subscriptions.check(handle), Err(ValoriError::SubscriberFull(1)));
+
+        assert_eq!(receiver.recv().unwrap().epoch_id, 2);
+        assert!(subscriptions.check(handle).is_ok());
+    }
+
+    #[test]
+    fn test_dropped_and_lagging_subscribers_are_removed() {
+        let handle = RepoHandle(1);
+        let mut subscriptions = Subscriptions::new(1, SubscriberFullPolicy::Unsubscribe);
+        let lagging = subscriptions.subscribe(handle);
+        drop(subscriptions.subscribe(handle));
+        let current = subscriptions.subscribe(handle);
+
+        subscriptions.publish(handle, event(2));
+        assert_eq!(current.drain(), vec![event(2)]);
+        assert!(subscriptions.check(handle).is_ok());
+        subscriptions.publish(handle, event(3));
+
+        assert_eq!(subscriptions.by_repo[&handle].len(), 1);
+        assert_eq!(current.recv().unwrap(), event(3));
+        // The lagging subscriber keeps what it had, then sees the disconnect
+        assert_eq!(lagging.recv().unwrap(), event(2));
+        assert_eq!(lagging.recv(), Err(RecvError));
+    }
+}
@@ -0,0 +1,370 @@
+//! C ABI for the API operations (`ffi` feature)
+//!
+//! The five ValoriAPI operations as `extern "C"` functions over one
+//! process-wide ValoriAPI (default config), for Python, Go and other FFI
+//! callers. Build with `cargo build --features ffi`; the cdylib is
+//! `libvcr.so` / `libvcr.dylib` / `vcr.dll`.
+//!
+//! ## Conventions
+//!
+//! - Every function returns `VCR_OK` or a `VCR_ERR_*` code; the codes map
+//!   one-to-one onto `ValoriError` variants, plus invalid arguments and
+//!   caught panics
+//! - Outputs are written through pointers, and only on success
+//! - Returned strings are NUL-terminated UTF-8 JSON owned by the caller;
+//!   release them with `vcr_free_string`
+//! - `vcr_last_error` returns the calling thread's last error message
+//! - Panics never unwind into the caller; they become `VCR_ERR_PANIC`
+//!
+//! All pointer handling goes through the small unsafe helpers at the bottom
+//! of this file.
+
+#![deny(unsafe_op_in_unsafe_fn)]
+
+use super::{RepoHandle, ResultId, ValoriAPI, ValoriError};
+use crate::types::FileId;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::mem::MaybeUninit;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+/// Success
+pub const VCR_OK: i32 = 0;
+
+/// Null pointer or non-UTF-8 string argument
+pub const VCR_ERR_INVALID_ARGUMENT: i32 = 1;
+
+/// `ValoriError::LoadFailed`
+pub const VCR_ERR_LOAD_FAILED: i32 = 2;
+
+/// `ValoriError::UnknownRepo`
+pub const VCR_ERR_UNKNOWN_REPO: i32 = 3;
+
+/// `ValoriError::InvalidQuery`
+pub const VCR_ERR_INVALID_QUERY: i32 = 4;
+
+/// `ValoriError::QueryFailed`
+pub const VCR_ERR_QUERY_FAILED: i32 = 5;
+
+/// `ValoriError::UnknownResult`
+pub const VCR_ERR_UNKNOWN_RESULT: i32 = 6;
+
+/// `ValoriError::InvalidPath`
+pub const VCR_ERR_INVALID_PATH: i32 = 7;
+
+/// `ValoriError::Cancelled`
+pub const VCR_ERR_CANCELLED: i32 = 8;
+
+/// `ValoriError::Timeout`
+pub const VCR_ERR_TIMEOUT: i32 = 9;
+
+/// `ValoriError::UpdateFailed`
+pub const VCR_ERR_UPDATE_FAILED: i32 = 10;
+
+/// `ValoriError::SaveFailed`
+pub const VCR_ERR_SAVE_FAILED: i32 = 11;
+
+/// `ValoriError::SubscriberFull`
+pub const VCR_ERR_SUBSCRIBER_FULL: i32 = 12;
+
+/// `ValoriError::SourceUnavailable`
+pub const VCR_ERR_SOURCE_UNAVAILABLE: i32 = 13;
+
+/// `ValoriError::UnknownSnapshot`
+pub const VCR_ERR_UNKNOWN_SNAPSHOT: i32 = 14;
+
+/// The engine panicked; the call had no effect visible to the caller
+pub const VCR_ERR_PANIC: i32 = 99;
+
+/// Process-wide API instance
+static API: OnceLock<Mutex<ValoriAPI>> = OnceLock::new();
+
+thread_local! {
+    /// Message of this thread's last failed call
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Failure of one FFI call
+#[derive(Debug)]
+enum FfiError {
+    /// Invalid pointer or string argument
+    Argument(String),
+
+    /// Operation failed
+    Api(ValoriError),
+}
+
+impl FfiError {
+    fn code(&self) -> i32 {
+        match self {
+            FfiError::Argument(_) => VCR_ERR_INVALID_ARGUMENT,
+            FfiError::Api(error) => error_code(error),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            FfiError::Argument(message) => message.clone(),
+            FfiError::Api(error) => error.to_string(),
+        }
+    }
+}
+
+impl From<ValoriError> for FfiError {
+    fn from(error: ValoriError) -> Self {
+        FfiError::Api(error)
+    }
+}
+
+/// Status code for an API error
+pub fn error_code(error: &ValoriError) -> i32 {
+    match error {
+        ValoriError::LoadFailed(_) => VCR_ERR_LOAD_FAILED,
+        ValoriError::UnknownRepo(_) => VCR_ERR_UNKNOWN_REPO,
+        ValoriError::InvalidQuery(_) => VCR_ERR_INVALID_QUERY,
+        ValoriError::QueryFailed(_) => VCR_ERR_QUERY_FAILED,
+        ValoriError::UnknownResult(_) => VCR_ERR_UNKNOWN_RESULT,
+        ValoriError::InvalidPath(_) => VCR_ERR_INVALID_PATH,
+        ValoriError::Cancelled(_) => VCR_ERR_CANCELLED,
+        ValoriError::Timeout(_) => VCR_ERR_TIMEOUT,
+        ValoriError::UpdateFailed(_) => VCR_ERR_UPDATE_FAILED,
+        ValoriError::SaveFailed(_) => VCR_ERR_SAVE_FAILED,
+        ValoriError::SubscriberFull(_) => VCR_ERR_SUBSCRIBER_FULL,
+        ValoriError::SourceUnavailable(_) => VCR_ERR_SOURCE_UNAVAILABLE,
+        ValoriError::UnknownSnapshot(_) => VCR_ERR_UNKNOWN_SNAPSHOT,
+    }
+}
+
+/// Load and build a repository, writing its handle to `out_handle`.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated string, and `out_handle` null or
+/// valid for writing a `u64`.
+#[no_mangle]
+pub unsafe extern "C" fn vcr_load_repo(path: *const c_char, out_handle: *mut u64) -> i32 {
+    call(|| {
+        // SAFETY: forwarded from this function's contract
+        let path = unsafe { read_str(path, "path") }?;
+        let out = unsafe { out_slot(out_handle, "out_handle") }?;
+
+        let handle = api().load_repo(path)?;
+        out.write(handle.0);
+        Ok(())
+    })
+}
+
+/// Run a JSON query against a loaded repository, writing the result ID to
+/// `out_result`.
+///
+/// # Safety
+///
+/// `query` must be null or a NUL-terminated string, and `out_result` null or
+/// valid for writing a `u64`.
+#[no_mangle]
+pub unsafe extern "C" fn vcr_run_query(handle: u64, query: *const c_char, out_result: *mut u64) -> i32 {
+    call(|| {
+        // SAFETY: forwarded from this function's contract
+        let query = unsafe { read_str(query, "query") }?;
+        let out = unsafe { out_slot(out_result, "out_result") }?;
+
+        let result_id = api().run_query(RepoHandle(handle), query)?;
+        out.write(result_id.0);
+        Ok(())
+    })
+}
+
+/// Fetch a stored result as a JSON array of node IDs (strings).
+///
+/// # Safety
+///
+/// `out_json` must be null or valid for writing a pointer. The string
+/// written there must be released with `vcr_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn vcr_fetch_result(result_id: u64, out_json: *mut *mut c_char) -> i32 {
+    call(|| {
+        // SAFETY: forwarded from this function's contract
+        let out = unsafe { out_slot(out_json, "out_json") }?;
+
+        let nodes = api().fetch_result(ResultId(result_id))?;
+        out.write(json_string(&nodes)?);
+        Ok(())
+    })
+}
+
+/// Tell a loaded repository which files changed.
+///
+/// # Safety
+///
+/// `file_ids` must point to `len` readable `u64`s, or be null with `len` 0.
+#[no_mangle]
+pub unsafe extern "C" fn vcr_update_files(handle: u64, file_ids: *const u64, len: usize) -> i32 {
+    call(|| {
+        // SAFETY: forwarded from this function's contract
+        let ids = unsafe { read_slice(file_ids, len, "file_ids") }?;
+
+        api().update_files(RepoHandle(handle), ids.iter().copied().map(FileId::new).collect())?;
+        Ok(())
+    })
+}
+
+/// Explain a stored result, writing a JSON string to `out_json`.
+///
+/// # Safety
+///
+/// `out_json` must be null or valid for writing a pointer. The string
+/// written there must be released with `vcr_free_string`.
+#[no_mangle]
+pub unsafe extern "C" fn vcr_explain_result(result_id: u64, out_json: *mut *mut c_char) -> i32 {
+    call(|| {
+        // SAFETY: forwarded from this function's contract
+        let out = unsafe { out_slot(out_json, "out_json") }?;
+
+        let explanation = api().explain_result(ResultId(result_id))?;
+        out.write(json_string(&explanation)?);
+        Ok(())
+    })
+}
+
+/// Message of the calling thread's last failed call (null if the last call
+/// succeeded). Release it with `vcr_free_string`.
+#[no_mangle]
+pub extern "C" fn vcr_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow().as_ref().map_or(std::ptr::null_mut(), |message| message.clone().into_raw())
+    })
+}
+
+/// Release a string returned by this library. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or a pointer returned by this library that was not
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn vcr_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: `s` came from `CString::into_raw` per this function's contract
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+/// Run one call: catch panics, record the error message, return the code
+fn call(f: impl FnOnce() -> Result<(), FfiError>) -> i32 {
+    let (code, message) = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => (VCR_OK, None),
+        Ok(Err(error)) => (error.code(), Some(error.message())),
+        Err(payload) => {
+            let reason = payload.downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            (VCR_ERR_PANIC, Some(format!("Panic: {}", reason)))
+        }
+    };
+
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = message.map(|m| CString::new(m.replace('\0', " ")).expect("NULs replaced"));
+    });
+    code
+}
+
+/// The process-wide API (a panic while it was locked does not disable it)
+fn api() -> MutexGuard<'static, ValoriAPI> {
+    API.get_or_init(|| Mutex::new(ValoriAPI::default()))
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Serialize to a caller-owned JSON C string
+fn json_string<T: serde::Serialize + ?Sized>(value: &T) -> Result<*mut c_char, FfiError> {
+    let json = serde_json::to_string(value).map_err(|e| FfiError::Argument(e.to_string()))?;
+    // serde_json escapes NUL, so this cannot fail
+    Ok(CString::new(json).expect("JSON has no interior NUL").into_raw())
+}
+
+/// Borrow a C string argument
+///
+/// # Safety
+///
+/// `ptr` must be null or a NUL-terminated string that outlives the call.
+unsafe fn read_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if ptr.is_null() {
+        return Err(FfiError::Argument(format!("{} is null", name)));
+    }
+    // SAFETY: non-null and NUL-terminated per the contract
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| FfiError::Argument(format!("{} is not valid UTF-8", name)))
+}
+
+/// Borrow an array argument
+///
+/// # Safety
+///
+/// `ptr` must point to `len` readable values, or be null with `len` 0.
+unsafe fn read_slice<'a, T>(ptr: *const T, len: usize, name: &str) -> Result<&'a [T], FfiError> {
+    if ptr.is_null() {
+        return match len {
+            0 => Ok(&[]),
+            _ => Err(FfiError::Argument(format!("{} is null", name))),
+        };
+    }
+    // SAFETY: non-null and valid for `len` reads per the contract
+    Ok(unsafe { std::slice::from_raw_parts(ptr, len) })
+}
+
+/// Borrow an output slot (checked before any work is done)
+///
+/// # Safety
+///
+/// `ptr` must be null or valid for writing a `T` for the rest of the call.
+unsafe fn out_slot<'a, T>(ptr: *mut T, name: &str) -> Result<&'a mut MaybeUninit<T>, FfiError> {
+    // SAFETY: MaybeUninit<T> has T's layout; null is rejected by as_mut
+    unsafe { ptr.cast::<MaybeUninit<T>>().as_mut() }
+        .ok_or_else(|| FfiError::Argument(format!("{} is null", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        let ptr = vcr_last_error();
+        assert!(!ptr.is_null());
+        let message = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { vcr_free_string(ptr) };
+        message
+    }
+
+    #[test]
+    fn test_panics_become_error_codes() {
+        assert_eq!(call(|| panic!("boom")), VCR_ERR_PANIC);
+        assert_eq!(last_error(), "Panic: boom");
+
+        assert_eq!(call(|| Ok(())), VCR_OK);
+        assert!(vcr_last_error().is_null());
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let mut handle = 0u64;
+        assert_eq!(unsafe { vcr_load_repo(std::ptr::null(), &mut handle) }, VCR_ERR_INVALID_ARGUMENT);
+        assert_eq!(last_error(), "path is null");
+
+        let query = CString::new("{}").unwrap();
+        assert_eq!(unsafe { vcr_run_query(1, query.as_ptr(), std::ptr::null_mut()) }, VCR_ERR_INVALID_ARGUMENT);
+        assert_eq!(unsafe { vcr_update_files(1, std::ptr::null(), 3) }, VCR_ERR_INVALID_ARGUMENT);
+    }
+
+    #[test]
+    fn test_error_codes_follow_variants() {
+        let mut json = std::ptr::null_mut();
+        assert_eq!(unsafe { vcr_fetch_result(u64::MAX, &mut json) }, VCR_ERR_UNKNOWN_RESULT);
+        assert!(json.is_null());
+        assert_eq!(unsafe { vcr_update_files(u64::MAX, std::ptr::null(), 0) }, VCR_ERR_UNKNOWN_REPO);
+        assert_eq!(error_code(&ValoriError::InvalidPath(String::new())), VCR_ERR_INVALID_PATH);
+        assert_eq!(error_code(&ValoriError::Timeout(Default::default())), VCR_ERR_TIMEOUT);
+    }
+}
@@ -1,66 +1,968 @@
 //! API module (Phase 4 Step 4.6)
 //!
 //! External APIs (boring on purpose)
+//!
+//! Every operation fails with a typed `ValoriError`. The `ffi` feature
+//! exposes the same operations over a C ABI (see `ffi`).
+//!
+//! ## Overlays
+//!
+//! `update_file_content` analyzes an editor's unsaved buffer in place of a
+//! file on disk: the repo is rebuilt incrementally with the buffer as the
+//! file's contents, and the disk is never written. `clear_overlay` goes back
+//! to the file on disk. Overlaid files are marked in `report_files`, and
+//! `auto_save` skips a repo with overlays unless `[snapshot] save_overlays`.
+//!
+//! ## Refresh
+//!
+//! `refresh` picks up whatever changed on disk since the repo was last
+//! built: it rescans, and if the snapshot hash moved, rebuilds the changed
+//! files incrementally. A refresh that finds the same snapshot hash parses
+//! nothing. Every rebuild (refresh or overlay change) advances the repo's
+//! epoch; `RefreshReport` says what changed and what it cost.
+//!
+//! ## Time travel
+//!
+//! `run_query_at` answers a query from snapshot N of the `[snapshot]` store
+//! instead of a loaded repo: the snapshot is restored into a temporary
+//! frozen epoch, queried, and dropped. The result is tagged with the
+//! snapshot ID, which `explain_result` reports. The same snapshot file and
+//! query give the same result on every run and every machine.
+//!
+//! ## Events
+//!
+//! `subscribe` delivers an `EpochEvent` for every later commit of a repo's
+//! build, in commit order (see `events`).
+
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
+pub use events::{EpochEvent, EpochEventReceiver};
+
+use crate::analysis::StableKeys;
+use crate::api::events::Subscriptions;
+use crate::config::{SnapshotConfig, ValoriConfig};
+use crate::cpg::CPGEpoch;
+use crate::execution::{CancellationToken, Interrupted, Progress, Scheduler};
+use crate::metrics::MetricsCollector;
+use crate::query::cache::{CacheKey, CacheOutcome, ResultCache};
+use crate::query::dsl::QuerySpec;
+use crate::query::engine::QueryEngine;
+use crate::query::materialize::{MaterializedResult, RepoSources, ResultMaterializer};
+use crate::query::primitives::QueryPrimitives;
+use crate::query::scope::FileScope;
+use crate::pipeline::{Pipeline, PipelineOutput};
+use crate::report::{FileReport, ReportBuilder};
+use crate::repo::normalize_path;
+use crate::storage::{SnapshotId, SnapshotStore};
 use crate::types::FileId;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use thiserror::Error;
+
+pub use crate::query::engine::{Aggregate, ResultId};
+
+/// API error
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValoriError {
+    /// Repository could not be scanned or built
+    #[error("Failed to load repo: {0}")]
+    LoadFailed(String),
+
+    /// Handle does not name a loaded repository
+    #[error("Unknown repo handle: {0}")]
+    UnknownRepo(u64),
+
+    /// Query text is not a valid QuerySpec
+    #[error("{0}")]
+    InvalidQuery(String),
+
+    /// Query was valid but could not be evaluated
+    #[error("Query failed: {0}")]
+    QueryFailed(String),
+
+    /// Result ID does not name a stored result
+    #[error("Unknown result: {0}")]
+    UnknownResult(u64),
+
+    /// Path is not part of the repository snapshot
+    #[error("{0}")]
+    InvalidPath(String),
+
+    /// Query was cancelled before it finished
+    #[error("Query cancelled after {} task(s) and {} step(s)", .0.tasks_completed, .0.steps)]
+    Cancelled(Progress),
+
+    /// Query ran past its deadline
+    #[error("Query timed out after {} task(s) and {} step(s)", .0.tasks_completed, .0.steps)]
+    Timeout(Progress),
+
+    /// Repository could not be rebuilt after an overlay change
+    #[error("Failed to update repo: {0}")]
+    UpdateFailed(String),
+
+    /// Repository could not be written to the snapshot store
+    #[error("Failed to save snapshot: {0}")]
+    SaveFailed(String),
+
+    /// Commit refused: this many epoch subscribers hold `[events] capacity` events
+    #[error("Commit refused: {0} epoch event subscriber(s) full")]
+    SubscriberFull(usize),
+
+    /// A result's source could not be read, or changed since it was analyzed
+    #[error("Source unavailable: {0}")]
+    SourceUnavailable(String),
+
+    /// Snapshot ID does not name a snapshot in the `[snapshot]` store
+    #[error("Unknown snapshot: {0}")]
+    UnknownSnapshot(u64),
+}
+
+impl ValoriError {
+    /// Typed error for a failed query: interruptions keep their progress
+    fn query(error: anyhow::Error) -> Self {
+        match error.downcast_ref::<Interrupted>() {
+            Some(Interrupted::Cancelled(progress)) => ValoriError::Cancelled(*progress),
+            Some(Interrupted::Timeout(progress)) => ValoriError::Timeout(*progress),
+            None => ValoriError::QueryFailed(error.to_string()),
+        }
+    }
+}
 
 /// Repository handle
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RepoHandle(pub u64);
 
-/// Query result ID
-#[derive(Debug, Clone, Copy)]
-pub struct ResultId(pub u64);
+/// A loaded repository
+struct LoadedRepo {
+    /// Latest pipeline run (its CPG answers queries)
+    output: PipelineOutput,
+
+    /// Hash of the CPG (cache key component)
+    cpg_hash: String,
+
+    /// Paths of the snapshot the CPG was built from
+    files: FileScope,
+
+    /// Unsaved contents replacing files, by absolute path
+    overlays: BTreeMap<PathBuf, Vec<u8>>,
+
+    /// Builds so far (1 after the load)
+    epoch_id: u64,
+}
+
+impl LoadedRepo {
+    fn new(output: PipelineOutput, overlays: BTreeMap<PathBuf, Vec<u8>>, epoch_id: u64) -> Self {
+        Self {
+            cpg_hash: output.cpg_epoch.cpg_hash().to_string(),
+            files: FileScope::from_snapshot(&output.snapshot),
+            output,
+            overlays,
+            epoch_id,
+        }
+    }
+
+    /// Overlay key of a path that must name one file
+    fn overlay_path(&self, path: &str) -> Result<PathBuf, ValoriError> {
+        let file_id = self.files.resolve_file(path).map_err(|e| ValoriError::InvalidPath(e.to_string()))?;
+        let snapshot = &self.output.snapshot;
+        Ok(snapshot.root.join(&snapshot.files[&file_id].path))
+    }
+}
+
+/// What a `refresh` found and did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefreshReport {
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub renamed: usize,
+
+    /// Files parsed and analyzed again
+    pub rebuilt: usize,
+
+    /// Hash of the repo's CPG after the refresh
+    pub new_cpg_hash: String,
+
+    /// The repo's epoch after the refresh (unchanged by a no-op)
+    pub epoch_id: u64,
+
+    /// Rescan time (microseconds)
+    pub scan_us: u64,
+
+    /// Rebuild time (microseconds; 0 for a no-op)
+    pub build_us: u64,
+}
 
 /// API operations (5 only)
-pub struct ValoriAPI;
+pub struct ValoriAPI {
+    /// Loaded repositories
+    repos: HashMap<RepoHandle, LoadedRepo>,
+
+    /// Query engine (owns stored results)
+    engine: QueryEngine,
+
+    /// Path → CPG pipeline
+    pipeline: Pipeline,
+
+    /// Query result cache
+    cache: ResultCache,
+
+    /// Metrics
+    metrics: MetricsCollector,
+
+    /// Where `auto_save` writes
+    snapshot: SnapshotConfig,
+
+    /// Epoch event subscribers
+    subscriptions: Subscriptions,
+
+    /// Next repository handle
+    next_handle: u64,
+}
 
 impl ValoriAPI {
+    /// Create an API instance
+    pub fn new(config: &ValoriConfig) -> Self {
+        Self {
+            repos: HashMap::new(),
+            engine: QueryEngine::new()
+                .with_scheduler(Scheduler::from_config(&config.execution))
+                .with_epoch_verification(config.query.verify_epochs)
+                .with_points_to_store(&config.snapshot.path)
+                .with_incremental_points_to(config.query.incremental_points_to),
+            pipeline: Pipeline::new(config),
+            cache: ResultCache::new(config.query.cache_capacity)
+                .with_paranoid(config.query.cache_paranoid),
+            metrics: MetricsCollector::new(),
+            snapshot: config.snapshot.clone(),
+            subscriptions: Subscriptions::new(config.events.capacity, config.events.on_full),
+            next_handle: 1,
+        }
+    }
+
     /// Load a repository
-    pub fn load_repo(_path: &str) -> Result<RepoHandle, String> {
-        // Placeholder
-        Ok(RepoHandle(1))
+    pub fn load_repo(&mut self, path: &str) -> Result<RepoHandle, ValoriError> {
+        let started = Instant::now();
+        let output = self.pipeline.run_with_metrics(Path::new(path), &self.metrics)
+            .map_err(|e| ValoriError::LoadFailed(format!("{:#}", e)))?;
+        self.metrics.record_scan_duration(started.elapsed());
+        self.metrics.record_cpg_stats(output.cpg_epoch.stats().clone());
+
+        let handle = RepoHandle(self.next_handle);
+        self.next_handle += 1;
+        self.commit(handle, output, BTreeMap::new());
+
+        Ok(handle)
     }
 
-    /// Update files
-    pub fn update_files(_handle: RepoHandle, _files: Vec<FileId>) -> Result<(), String> {
-        // Placeholder
+    /// Analyze `content` in place of a file's contents on disk
+    ///
+    /// Replaces any earlier overlay of the file. The file's new content hash
+    /// is detected as an edit and rebuilt incrementally; the disk is not
+    /// touched. On failure the repo keeps its previous state.
+    pub fn update_file_content(&mut self, handle: RepoHandle, path: &str, content: Vec<u8>) -> Result<(), ValoriError> {
+        let repo = self.repo(handle)?;
+        let mut overlays = repo.overlays.clone();
+        overlays.insert(repo.overlay_path(path)?, content);
+        self.rebuild(handle, overlays)
+    }
+
+    /// Go back to a file's contents on disk (no-op if it has no overlay)
+    pub fn clear_overlay(&mut self, handle: RepoHandle, path: &str) -> Result<(), ValoriError> {
+        let repo = self.repo(handle)?;
+        let mut overlays = repo.overlays.clone();
+        if overlays.remove(&repo.overlay_path(path)?).is_none() {
+            return Ok(());
+        }
+        self.rebuild(handle, overlays)
+    }
+
+    /// Rebuild a repo incrementally with `overlays`
+    fn rebuild(&mut self, handle: RepoHandle, overlays: BTreeMap<PathBuf, Vec<u8>>) -> Result<(), ValoriError> {
+        self.subscriptions.check(handle)?;
+        let repo = self.repos.get(&handle).ok_or(ValoriError::UnknownRepo(handle.0))?;
+        let started = Instant::now();
+        let output = self.pipeline.clone()
+            .with_overlays(overlays.clone())
+            .run_incremental_with_metrics(&repo.output, &self.metrics)
+            .map_err(|e| ValoriError::UpdateFailed(format!("{:#}", e)))?;
+        self.metrics.record_scan_duration(started.elapsed());
+        self.metrics.record_cpg_stats(output.cpg_epoch.stats().clone());
+        self.commit(handle, output, overlays);
         Ok(())
     }
 
+    /// Replace a repo's build with the next epoch and notify its subscribers
+    fn commit(&mut self, handle: RepoHandle, output: PipelineOutput, overlays: BTreeMap<PathBuf, Vec<u8>>) -> &LoadedRepo {
+        let previous = self.repos.remove(&handle);
+        let epoch_id = previous.as_ref().map_or(1, |repo| repo.epoch_id + 1);
+        let repo = LoadedRepo::new(output, overlays, epoch_id);
+
+        let paths = |repo: &LoadedRepo| -> BTreeMap<String, String> {
+            repo.output.snapshot.files.values()
+                .map(|meta| (normalize_path(&meta.path), meta.content_hash.clone()))
+                .collect()
+        };
+        let (before, after) = (previous.as_ref().map(paths).unwrap_or_default(), paths(&repo));
+        let mut changed_files: Vec<String> = after.iter()
+            .filter(|(path, hash)| before.get(*path) != Some(*hash))
+            .map(|(path, _)| path.clone())
+            .chain(before.keys().filter(|path| !after.contains_key(*path)).cloned())
+            .collect();
+        changed_files.sort();
+
+        self.subscriptions.publish(handle, EpochEvent {
+            epoch_id,
+            cpg_hash: repo.cpg_hash.clone(),
+            changed_files,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        });
+        self.repos.entry(handle).or_insert(repo)
+    }
+
+    /// Deliver an `EpochEvent` for every later commit of a repo's build
+    ///
+    /// Events arrive in commit order; every subscriber gets all of them.
+    /// A full subscriber refuses or drops out of later commits per
+    /// `[events] on_full`; dropping the receiver ends the subscription.
+    pub fn subscribe(&mut self, handle: RepoHandle) -> Result<EpochEventReceiver, ValoriError> {
+        self.repo(handle)?;
+        Ok(self.subscriptions.subscribe(handle))
+    }
+
+    /// Rescan a repo and rebuild whatever changed since its last build
+    ///
+    /// Overlaid files keep their overlays. When the rescanned snapshot hash
+    /// matches, nothing is parsed and the epoch stays the same. On failure
+    /// the repo keeps its previous state.
+    pub fn refresh(&mut self, handle: RepoHandle) -> Result<RefreshReport, ValoriError> {
+        let repo = self.repos.get(&handle).ok_or(ValoriError::UnknownRepo(handle.0))?;
+        let failed = |e: anyhow::Error| ValoriError::UpdateFailed(format!("{:#}", e));
+        let pipeline = self.pipeline.clone().with_overlays(repo.overlays.clone());
+
+        let scan_started = Instant::now();
+        let snapshot = pipeline.scan(&repo.output.snapshot.root_paths()).map_err(failed)?;
+        let scan_us = scan_started.elapsed().as_micros() as u64;
+        if snapshot.snapshot_hash == repo.output.snapshot.snapshot_hash {
+            return Ok(RefreshReport {
+                added: 0,
+                modified: 0,
+                deleted: 0,
+                renamed: 0,
+                rebuilt: 0,
+                new_cpg_hash: repo.cpg_hash.clone(),
+                epoch_id: repo.epoch_id,
+                scan_us,
+                build_us: 0,
+            });
+        }
+
+        self.subscriptions.check(handle)?;
+        let started = Instant::now();
+        let output = pipeline.run_incremental_scanned(&repo.output, snapshot, &self.metrics).map_err(failed)?;
+        let build_us = started.elapsed().as_micros() as u64;
+        self.metrics.record_scan_duration(scan_started.elapsed());
+        self.metrics.record_cpg_stats(output.cpg_epoch.stats().clone());
+
+        let changes = output.changes.clone().unwrap_or_default();
+        let rebuilt = output.rebuilt.len();
+        let overlays = repo.overlays.clone();
+        let repo = self.commit(handle, output, overlays);
+        Ok(RefreshReport {
+            added: changes.added,
+            modified: changes.modified,
+            deleted: changes.deleted,
+            renamed: changes.renamed,
+            rebuilt,
+            new_cpg_hash: repo.cpg_hash.clone(),
+            epoch_id: repo.epoch_id,
+            scan_us,
+            build_us,
+        })
+    }
+
+    /// Per-file ingestion report of a repo (overlaid files are marked)
+    pub fn report_files(&self, handle: RepoHandle) -> Result<Vec<FileReport>, ValoriError> {
+        Ok(ReportBuilder::from_output(&self.repo(handle)?.output).build())
+    }
+
+    /// Save a repo to the `[snapshot]` store, as hosts do after a load or
+    /// update, then apply retention
+    ///
+    /// Saves nothing (None) when `auto_save` is off, or when files are
+    /// overlaid and `save_overlays` is off: unsaved buffers are not
+    /// persisted by default.
+    pub fn auto_save(&self, handle: RepoHandle) -> Result<Option<SnapshotId>, ValoriError> {
+        let output = &self.repo(handle)?.output;
+        if !self.snapshot.auto_save || (!output.overlaid.is_empty() && !self.snapshot.save_overlays) {
+            return Ok(None);
+        }
+
+        let failed = |e: std::io::Error| ValoriError::SaveFailed(e.to_string());
+        let mut store = SnapshotStore::open(&self.snapshot.path).map_err(failed)?
+            .with_compression(self.snapshot.compression);
+        let stats = ReportBuilder::from_output(output).build();
+        let cpg_epoch = &output.cpg_epoch;
+        let id = store.save_with_semantics(cpg_epoch, &output.snapshot, &output.semantic, &output.summaries, stats)
+            .map_err(failed)?;
+        store.prune(&self.snapshot.retention()).map_err(failed)?;
+        Ok(Some(id))
+    }
+
+    /// Update files
+    ///
+    /// Rebuilds through `refresh`, which finds every change itself; `files`
+    /// is not consulted.
+    pub fn update_files(&mut self, handle: RepoHandle, _files: Vec<FileId>) -> Result<(), ValoriError> {
+        self.refresh(handle).map(|_| ())
+    }
+
     /// Run query (returns result ID)
-    pub fn run_query(_handle: RepoHandle, _query: &str) -> Result<ResultId, String> {
-        // Placeholder
-        Ok(ResultId(1))
+    ///
+    /// Aggregate queries (`count`, `group_by`) bypass the result cache;
+    /// read them with `fetch_aggregate`.
+    pub fn run_query(&mut self, handle: RepoHandle, query: &str) -> Result<ResultId, ValoriError> {
+        self.run_query_cancellable(handle, query, CancellationToken::new())
+    }
+
+    /// Run query, failing with `Cancelled` or `Timeout` when `token` says stop
+    ///
+    /// An interrupted query is neither cached nor stored.
+    pub fn run_query_cancellable(
+        &mut self,
+        handle: RepoHandle,
+        query: &str,
+        token: CancellationToken,
+    ) -> Result<ResultId, ValoriError> {
+        self.engine.set_cancellation(token);
+        let result = self.run_query_with_engine(handle, query);
+        self.engine.set_cancellation(CancellationToken::new());
+        result
+    }
+
+    fn run_query_with_engine(&mut self, handle: RepoHandle, query: &str) -> Result<ResultId, ValoriError> {
+        let spec = QuerySpec::from_json(query).map_err(|e| ValoriError::InvalidQuery(format!("{:#}", e)))?;
+
+        let repo = self.repos.get(&handle).ok_or(ValoriError::UnknownRepo(handle.0))?;
+        if matches!(spec.split_aggregate(), Ok((_, Some(_)))) {
+            let aggregate = self.engine.aggregate_scoped_with_metrics(&repo.output.cpg_epoch, &repo.files, &spec, &self.metrics)
+                .map_err(ValoriError::query)?;
+            return Ok(self.engine.store_aggregate(aggregate));
+        }
+        let key = CacheKey::new(&repo.cpg_hash, &spec);
+
+        let (engine, metrics) = (&self.engine, &self.metrics);
+        let (nodes, outcome) = self.cache
+            .get_or_compute(key, || engine.compute_scoped_with_metrics(&repo.output.cpg_epoch, &repo.files, &spec, metrics))
+            .map_err(ValoriError::query)?;
+
+        match outcome {
+            CacheOutcome::Hit => self.metrics.record_query_cache_hit(),
+            CacheOutcome::Miss => self.metrics.record_query_cache_miss(),
+        }
+
+        Ok(self.engine.store(nodes, spec.options.order_by))
+    }
+
+    /// Run query against snapshot `snapshot_id` of the `[snapshot]` store
+    ///
+    /// The snapshot is restored (and checked against its hash) for this
+    /// query only; loaded repos are untouched. Stages that need file paths
+    /// (`in_file`) fail, as the snapshot records no repo scope.
+    pub fn run_query_at(&mut self, snapshot_id: SnapshotId, query: &str) -> Result<ResultId, ValoriError> {
+        let spec = QuerySpec::from_json(query).map_err(|e| ValoriError::InvalidQuery(format!("{:#}", e)))?;
+
+        let store = SnapshotStore::open(&self.snapshot.path)
+            .map_err(|e| ValoriError::LoadFailed(e.to_string()))?;
+        if store.get(snapshot_id).is_none() {
+            return Err(ValoriError::UnknownSnapshot(snapshot_id.0));
+        }
+        let epoch = CPGEpoch::from_store(&store, snapshot_id)
+            .map_err(|e| ValoriError::LoadFailed(format!("{:#}", e)))?;
+        self.metrics.record_cpg_epoch(&epoch);
+
+        let result_id = self.engine.run(epoch.cpg(), &spec).map_err(ValoriError::query)?;
+        self.engine.tag_snapshot(result_id, snapshot_id);
+        Ok(result_id)
     }
 
     /// Fetch result
-    pub fn fetch_result(_result_id: ResultId) -> Result<Vec<String>, String> {
-        // Placeholder
-        Ok(vec![])
+    pub fn fetch_result(&self, result_id: ResultId) -> Result<Vec<String>, ValoriError> {
+        let stored = self.engine.get_result(result_id)
+            .ok_or(ValoriError::UnknownResult(result_id.0))?;
+
+        Ok(stored.nodes.iter().map(|id| id.0.to_string()).collect())
+    }
+
+    /// Fetch a result's nodes with their file, line, column and source
+    /// excerpt (see `query::materialize`), read as the repo was built
+    /// (overlays included)
+    ///
+    /// The result must have been run against `handle`'s current epoch.
+    pub fn fetch_result_detailed(&self, handle: RepoHandle, result_id: ResultId) -> Result<Vec<MaterializedResult>, ValoriError> {
+        let repo = self.repo(handle)?;
+        let stored = self.engine.get_result(result_id)
+            .ok_or(ValoriError::UnknownResult(result_id.0))?;
+
+        let backend = self.pipeline.clone().with_overlays(repo.overlays.clone()).source_backend();
+        let sources = RepoSources::new(&repo.output.snapshot, backend)
+            .with_normalized_line_endings(self.pipeline.normalizes_line_endings());
+        ResultMaterializer::new(&repo.output.cpg_epoch, &sources)
+            .materialize(&stored.nodes)
+            .map_err(|e| ValoriError::SourceUnavailable(format!("{:#}", e)))
+    }
+
+    /// Stable keys (see `analysis::StableKeys`) of a result's nodes, in
+    /// result order
+    ///
+    /// The result must have been run against `handle`'s current epoch. A key
+    /// only changes when its own function does, so keys compare across
+    /// epochs where node IDs do not.
+    pub fn fetch_result_keys(&self, handle: RepoHandle, result_id: ResultId) -> Result<Vec<String>, ValoriError> {
+        let repo = self.repo(handle)?;
+        let stored = self.engine.get_result(result_id)
+            .ok_or(ValoriError::UnknownResult(result_id.0))?;
+
+        let epoch = &repo.output.cpg_epoch;
+        let keys = StableKeys::build(epoch.cpg(), epoch.indices());
+        Ok(stored.nodes.iter().map(|node| keys.key(*node)).collect())
     }
 
-    /// Explain result (provenance path)
-    pub fn explain_result(_result_id: ResultId) -> Result<String, String> {
-        // Placeholder
-        Ok("provenance path".to_string())
+    /// Aggregate of a stored result (None for node results)
+    pub fn fetch_aggregate(&self, result_id: ResultId) -> Result<Option<Aggregate>, ValoriError> {
+        let stored = self.engine.get_result(result_id)
+            .ok_or(ValoriError::UnknownResult(result_id.0))?;
+
+        Ok(stored.aggregate.as_ref().map(|aggregate| aggregate.value.clone()))
+    }
+
+    /// Nodes whose source range contains a byte offset (innermost first)
+    pub fn node_at(&self, handle: RepoHandle, path: &str, offset: usize) -> Result<Vec<String>, ValoriError> {
+        let repo = self.repo(handle)?;
+        let file_id = repo.files.resolve_file(path).map_err(|e| ValoriError::InvalidPath(e.to_string()))?;
+
+        let cpg_epoch = &repo.output.cpg_epoch;
+        Ok(QueryPrimitives::nodes_at(cpg_epoch.cpg(), cpg_epoch.indices(), file_id, offset)
+            .iter()
+            .map(|id| id.0.to_string())
+            .collect())
+    }
+
+    /// Explain result (provenance path; what was counted, for aggregates)
+    ///
+    /// Results of `run_query_at` also name the snapshot that answered.
+    pub fn explain_result(&self, result_id: ResultId) -> Result<String, ValoriError> {
+        let stored = self.engine.get_result(result_id);
+        let explanation = match stored.and_then(|stored| stored.aggregate.as_ref()) {
+            Some(aggregate) => aggregate.describe(),
+            // Placeholder
+            None => "provenance path".to_string(),
+        };
+        Ok(match stored.and_then(|stored| stored.snapshot) {
+            Some(id) => format!("{} (snapshot {})", explanation, id.0),
+            None => explanation,
+        })
+    }
+
+    /// Get metrics
+    pub fn metrics(&self) -> &MetricsCollector {
+        &self.metrics
+    }
+
+    /// Look up a loaded repository
+    fn repo(&self, handle: RepoHandle) -> Result<&LoadedRepo, ValoriError> {
+        self.repos.get(&handle).ok_or(ValoriError::UnknownRepo(handle.0))
+    }
+}
+
+impl Default for ValoriAPI {
+    fn default() -> Self {
+        Self::new(&ValoriConfig::default())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cpg::model::CPGNodeId;
+    use tempfile::TempDir;
+
+    fn temp_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() { let x = 1; }\nfn b() {}\n").unwrap();
+        dir
+    }
 
     #[test]
     fn test_api_load_repo() {
-        let handle = ValoriAPI::load_repo("/tmp/test").unwrap();
+        let dir = temp_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
         assert_eq!(handle.0, 1);
+
+        let cpg = &api.metrics().to_json()["cpg"];
+        assert_eq!(cpg["nodes_by_kind"]["Function"], 2);
+        assert_eq!(cpg["nodes_by_kind"]["File"], 1);
     }
 
     #[test]
     fn test_api_operations() {
-        let handle = RepoHandle(1);
-        assert!(ValoriAPI::update_files(handle, vec![]).is_ok());
-        assert!(ValoriAPI::run_query(handle, "test").is_ok());
+        let dir = temp_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+        assert!(api.update_files(handle, vec![]).is_ok());
+        let result_id = api.run_query(handle, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+        assert!(api.fetch_result(result_id).is_ok());
+    }
+
+    #[test]
+    fn test_api_rejects_unknown_handle() {
+        let mut api = ValoriAPI::default();
+        assert_eq!(api.run_query(RepoHandle(9), r#"{"pipeline": []}"#), Err(ValoriError::UnknownRepo(9)));
+        assert_eq!(api.update_files(RepoHandle(9), vec![]), Err(ValoriError::UnknownRepo(9)));
+        assert_eq!(api.fetch_result(ResultId(3)), Err(ValoriError::UnknownResult(3)));
+        assert!(matches!(api.run_query(RepoHandle(9), "not json"), Err(ValoriError::InvalidQuery(_))));
+    }
+
+    #[test]
+    fn test_aggregates_match_plain_query() {
+        let dir = multi_file_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        let all_id = api.run_query(handle, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+        let all = api.fetch_result(all_id).unwrap();
+
+        let count_id = api.run_query(handle, r#"{"pipeline": [{"find": "Function"}, {"count": true}]}"#).unwrap();
+        assert_eq!(api.fetch_aggregate(count_id).unwrap(), Some(Aggregate::Count(all.len())));
+        assert!(api.fetch_result(count_id).unwrap().is_empty());
+        assert_eq!(api.explain_result(count_id).unwrap(), "count over find: 4 nodes");
+
+        let by_file_id = api.run_query(handle, r#"{"pipeline": [{"find": "Function"}, {"group_by": "file"}]}"#).unwrap();
+        let Some(Aggregate::Groups(by_file)) = api.fetch_aggregate(by_file_id).unwrap() else { panic!() };
+        assert_eq!(by_file.into_iter().collect::<Vec<_>>(), vec![
+            ("src/handlers/login.rs".to_string(), 2),
+            ("src/handlers/logout.rs".to_string(), 1),
+            ("src/lib.rs".to_string(), 1),
+        ]);
+        assert_eq!(api.fetch_aggregate(all_id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_queries_record_task_timings() {
+        let dir = temp_repo();
+        let mut config = ValoriConfig::default();
+        config.execution.chunk_size = 2;
+        let mut api = ValoriAPI::new(&config);
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+        api.run_query(handle, r#"{"pipeline": [{"find": "CfgNode"}, {"filter": "CfgNode"}]}"#).unwrap();
+        api.run_query(handle, r#"{"pipeline": [{"find": "Function"}, {"count": true}]}"#).unwrap();
+        assert_eq!(api.metrics().tasks_executed(), 3);
+        assert!(api.metrics().chunks_executed() >= 3);
+        assert_eq!(api.metrics().to_json()["execution"]["tasks"], 3);
+    }
+
+    #[test]
+    fn test_cancelled_query_is_typed_and_not_cached() {
+        let dir = temp_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        let query = r#"{"pipeline": [{"find": "Function"}, {"follow": "ControlFlow"}]}"#;
+
+        let token = CancellationToken::new();
+        token.cancel();
+        assert_eq!(api.run_query_cancellable(handle, query, token), Err(ValoriError::Cancelled(Progress::default())));
+        let expired = CancellationToken::new().with_timeout(std::time::Duration::ZERO);
+        assert!(matches!(api.run_query_cancellable(handle, query, expired), Err(ValoriError::Timeout(_))));
+
+        let result_id = api.run_query(handle, query).unwrap();
+        assert!(api.fetch_result(result_id).is_ok());
+        assert_eq!(api.metrics().query_cache_misses(), 1);
+    }
+
+    fn multi_file_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let handlers = dir.path().join("src/handlers");
+        std::fs::create_dir_all(&handlers).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "fn root() {}\n").unwrap();
+        std::fs::write(handlers.join("login.rs"), "fn login() { let x = 1; }\nfn check() {}\n").unwrap();
+        std::fs::write(handlers.join("logout.rs"), "fn logout() {}\n").unwrap();
+        dir
+    }
+
+    fn functions_in(api: &mut ValoriAPI, handle: RepoHandle, path: &str) -> Result<Vec<String>, ValoriError> {
+        let query = format!(r#"{{"pipeline": [{{"in_file": "{}"}}, {{"filter": "Function"}}]}}"#, path);
+        let result_id = api.run_query(handle, &query)?;
+        api.fetch_result(result_id)
+    }
+
+    #[test]
+    fn test_in_file_restricts_to_file_and_prefix() {
+        let dir = multi_file_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+        let login = functions_in(&mut api, handle, "src/handlers/login.rs").unwrap();
+        let handlers = functions_in(&mut api, handle, "src/handlers").unwrap();
+        let all_id = api.run_query(handle, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+        let all = api.fetch_result(all_id).unwrap();
+
+        assert_eq!(login.len(), 2);
+        assert_eq!(handlers.len(), 3);
+        assert_eq!(all.len(), 4);
+        assert!(login.iter().all(|id| handlers.contains(id)));
+    }
+
+    #[test]
+    fn test_in_file_is_deterministic() {
+        let dir = multi_file_repo();
+        let first = {
+            let mut api = ValoriAPI::default();
+            let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+            functions_in(&mut api, handle, "src/handlers").unwrap()
+        };
+        let second = {
+            let mut api = ValoriAPI::default();
+            let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+            functions_in(&mut api, handle, "src/handlers").unwrap()
+        };
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_in_file_unknown_path() {
+        let dir = multi_file_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+        let err = functions_in(&mut api, handle, "src/handlers/missing.rs").unwrap_err();
+        assert!(matches!(err, ValoriError::QueryFailed(_)));
+        assert!(err.to_string().contains("Path not in snapshot: src/handlers/missing.rs"), "{}", err);
+    }
+
+    #[test]
+    fn test_node_at() {
+        let dir = multi_file_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+        // Inside `let x = 1;` of login()
+        let offset = "fn login() { let x".len();
+        let nodes = api.node_at(handle, "src/handlers/login.rs", offset).unwrap();
+        assert!(!nodes.is_empty());
+
+        // Innermost first: each range is no larger than the next
+        let cpg = api.repos[&handle].output.cpg_epoch.cpg();
+        let sizes: Vec<_> = nodes.iter()
+            .map(|id| cpg.get_node(CPGNodeId(id.parse().unwrap())).unwrap().source_range.len())
+            .collect();
+        assert!(sizes.windows(2).all(|w| w[0] <= w[1]), "{:?}", sizes);
+
+        assert!(api.node_at(handle, "src/handlers/login.rs", 10_000).unwrap().is_empty());
+        assert!(api.node_at(handle, "src/missing.rs", 0).is_err());
+        assert!(api.node_at(RepoHandle(9), "src/handlers/login.rs", 0).is_err());
+    }
+
+    fn function_count(api: &mut ValoriAPI, handle: RepoHandle, pattern: &str) -> usize {
+        let query = format!(r#"{{"pipeline": [{{"function_matches": "{}"}}]}}"#, pattern);
+        let result_id = api.run_query(handle, &query).unwrap();
+        api.fetch_result(result_id).unwrap().len()
+    }
+
+    #[test]
+    fn test_overlay_adds_and_clears_function() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let main = "fn main() { helper(); }\nfn helper() {}\n";
+        std::fs::write(dir.path().join("src/main.rs"), main).unwrap();
+        std::fs::write(dir.path().join("src/util.rs"), "fn util() {}\n").unwrap();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        let loaded_hash = api.repos[&handle].cpg_hash.clone();
+        assert_eq!(function_count(&mut api, handle, "unsaved"), 0);
+
+        let buffer = format!("{}fn unsaved() {{ let x = 1; }}\n", main);
+        api.update_file_content(handle, "src/main.rs", buffer.into_bytes()).unwrap();
+        assert_eq!(function_count(&mut api, handle, "unsaved"), 1);
+        assert_eq!(function_count(&mut api, handle, "*"), 4);
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/main.rs")).unwrap(), main);
+        assert_eq!(api.repos[&handle].output.rebuilt.len(), 1);
+
+        let marked: Vec<(String, bool)> = api.report_files(handle).unwrap().into_iter()
+            .map(|file| (file.path, file.overlay))
+            .collect();
+        assert_eq!(marked, [("src/main.rs".to_string(), true), ("src/util.rs".to_string(), false)]);
+
+        api.clear_overlay(handle, "src/main.rs").unwrap();
+        assert_eq!(function_count(&mut api, handle, "unsaved"), 0);
+        assert_eq!(api.repos[&handle].cpg_hash, loaded_hash);
+        assert!(api.report_files(handle).unwrap().iter().all(|file| !file.overlay));
+
+        assert!(matches!(api.update_file_content(handle, "src/missing.rs", vec![]), Err(ValoriError::InvalidPath(_))));
+        assert!(matches!(api.clear_overlay(handle, "src"), Err(ValoriError::InvalidPath(_))));
+    }
+
+    #[test]
+    fn test_fetch_result_detailed_reads_overlays_and_rejects_stale_files() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "// ñ\nfn café() {}\n").unwrap();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        let functions = r#"{"pipeline": [{"find": "Function"}]}"#;
+
+        let result_id = api.run_query(handle, functions).unwrap();
+        let detailed = api.fetch_result_detailed(handle, result_id).unwrap();
+        assert_eq!(detailed.len(), 1);
+        assert_eq!(detailed[0].node_id.to_string(), api.fetch_result(result_id).unwrap()[0]);
+        assert_eq!(detailed[0].file_path.as_deref(), Some("lib.rs"));
+        assert_eq!((detailed[0].line, detailed[0].column), (Some(2), Some(1)));
+        assert_eq!(detailed[0].excerpt.as_deref(), Some("fn café() {}"));
+
+        // Unsaved buffers are read from the overlay, not the disk
+        api.update_file_content(handle, "lib.rs", "fn naïve() {}\n".as_bytes().to_vec()).unwrap();
+        let result_id = api.run_query(handle, functions).unwrap();
+        let detailed = api.fetch_result_detailed(handle, result_id).unwrap();
+        assert_eq!((detailed[0].line, detailed[0].excerpt.as_deref()), (Some(1), Some("fn naïve() {}")));
+
+        api.clear_overlay(handle, "lib.rs").unwrap();
+        let result_id = api.run_query(handle, functions).unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn edited() {}\n").unwrap();
+        assert!(matches!(api.fetch_result_detailed(handle, result_id), Err(ValoriError::SourceUnavailable(_))));
+        assert_eq!(api.fetch_result_detailed(RepoHandle(9), result_id), Err(ValoriError::UnknownRepo(9)));
+        assert_eq!(api.fetch_result_detailed(handle, ResultId(999)), Err(ValoriError::UnknownResult(999)));
+    }
+
+    #[test]
+    fn test_auto_save_skips_overlays_unless_allowed() {
+        let dir = temp_repo();
+        let store = TempDir::new().unwrap();
+        let mut config = ValoriConfig::default();
+        config.snapshot.path = store.path().to_path_buf();
+        let saved = |config: &ValoriConfig| {
+            let mut api = ValoriAPI::new(config);
+            let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+            assert!(api.auto_save(handle).unwrap().is_some());
+            api.update_file_content(handle, "lib.rs", b"fn edited() {}\n".to_vec()).unwrap();
+            api.auto_save(handle).unwrap()
+        };
+
+        assert_eq!(saved(&config), None);
+        config.snapshot.save_overlays = true;
+        assert!(saved(&config).is_some());
+        assert_eq!(SnapshotStore::open(store.path()).unwrap().entries().len(), 3);
+    }
+
+    #[test]
+    fn test_refresh_reports_changes() {
+        let dir = temp_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        let counts = |report: &RefreshReport| (report.added, report.modified, report.deleted, report.renamed, report.rebuilt);
+
+        std::fs::write(dir.path().join("extra.rs"), "fn extra() {}\n").unwrap();
+        let created = api.refresh(handle).unwrap();
+        assert_eq!(counts(&created), (1, 0, 0, 0, 1));
+        assert_eq!(created.epoch_id, 2);
+        assert_eq!(function_count(&mut api, handle, "extra"), 1);
+
+        std::fs::write(dir.path().join("extra.rs"), "fn extra() {}\nfn more() {}\n").unwrap();
+        let edited = api.refresh(handle).unwrap();
+        assert_eq!(counts(&edited), (0, 1, 0, 0, 1));
+        assert_ne!(edited.new_cpg_hash, created.new_cpg_hash);
+        assert_eq!(edited.new_cpg_hash, api.repos[&handle].cpg_hash);
+
+        std::fs::remove_file(dir.path().join("extra.rs")).unwrap();
+        let deleted = api.refresh(handle).unwrap();
+        assert_eq!(counts(&deleted), (0, 0, 1, 0, 0));
+        assert_eq!(deleted.epoch_id, 4);
+        assert_eq!(function_count(&mut api, handle, "extra"), 0);
+
+        assert_eq!(api.refresh(RepoHandle(9)), Err(ValoriError::UnknownRepo(9)));
+    }
+
+    #[test]
+    fn test_noop_refresh_parses_nothing() {
+        let dir = temp_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        api.update_file_content(handle, "lib.rs", b"fn buffered() {}\n".to_vec()).unwrap();
+        let hash = api.repos[&handle].cpg_hash.clone();
+        let (reparses, parsed) = (api.metrics().reparse_count(), api.metrics().parse_time_stats().count);
+
+        let report = api.refresh(handle).unwrap();
+        assert_eq!((report.added, report.modified, report.deleted, report.renamed, report.rebuilt), (0, 0, 0, 0, 0));
+        assert_eq!((report.new_cpg_hash, report.epoch_id, report.build_us), (hash, 2, 0));
+        assert_eq!(api.metrics().reparse_count(), reparses);
+        assert_eq!(api.metrics().parse_time_stats().count, parsed);
+        assert_eq!(function_count(&mut api, handle, "buffered"), 1);
+    }
+
+    #[test]
+    fn test_subscribers_see_identical_ordered_events() {
+        let dir = temp_repo();
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        let first = api.subscribe(handle).unwrap();
+        let second = api.subscribe(handle).unwrap();
+
+        std::fs::write(dir.path().join("extra.rs"), "fn extra() {}\n").unwrap();
+        api.refresh(handle).unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        api.update_files(handle, Vec::new()).unwrap();
+        std::fs::remove_file(dir.path().join("extra.rs")).unwrap();
+        let last = api.refresh(handle).unwrap();
+
+        let events = first.drain();
+        assert_eq!(events, second.drain());
+        let epochs: Vec<u64> = events.iter().map(|event| event.epoch_id).collect();
+        let changed: Vec<&[String]> = events.iter().map(|event| event.changed_files.as_slice()).collect();
+        assert_eq!(epochs, vec![2, 3, 4]);
+        assert_eq!(changed, vec![&["extra.rs".to_string()][..], &["lib.rs".to_string()], &["extra.rs".to_string()]]);
+        assert_eq!(events[2].cpg_hash, last.new_cpg_hash);
+
+        assert!(matches!(api.subscribe(RepoHandle(9)), Err(ValoriError::UnknownRepo(9))));
+    }
+
+    #[test]
+    fn test_full_subscriber_refuses_commit() {
+        let dir = temp_repo();
+        let mut config = ValoriConfig::default();
+        config.events.capacity = 1;
+        let mut api = ValoriAPI::new(&config);
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        let receiver = api.subscribe(handle).unwrap();
+
+        std::fs::write(dir.path().join("extra.rs"), "fn extra() {}\n").unwrap();
+        api.refresh(handle).unwrap();
+        std::fs::write(dir.path().join("extra.rs"), "fn extra() {}\nfn more() {}\n").unwrap();
+        assert_eq!(api.refresh(handle), Err(ValoriError::SubscriberFull(1)));
+        assert_eq!(api.repos[&handle].epoch_id, 2);
+
+        assert_eq!(receiver.recv().unwrap().epoch_id, 2);
+        assert_eq!(api.refresh(handle).unwrap().epoch_id, 3);
+        assert_eq!(receiver.recv().unwrap().epoch_id, 3);
+    }
+
+    #[test]
+    fn test_dropped_receiver_does_not_block_commits() {
+        let dir = temp_repo();
+        let mut config = ValoriConfig::default();
+        config.events.capacity = 1;
+        let mut api = ValoriAPI::new(&config);
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        drop(api.subscribe(handle).unwrap());
+
+        for i in 0..3 {
+            std::fs::write(dir.path().join("extra.rs"), format!("fn extra{}() {{}}\n", i)).unwrap();
+            api.refresh(handle).unwrap();
+        }
+        assert_eq!(api.repos[&handle].epoch_id, 4);
     }
 }
@@ -1,35 +1,49 @@
 //! VTR CLI - wiring, not product
 //!
 //! Zero magic. Explicit config. Machine-readable output.
+//!
+//! Commands live in `vcr::cli`; this binary only parses arguments, loads
+//! config and prints.
 
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
-use std::fs;
-
-/// Load config from file or use defaults
-fn load_config(config_path: Option<PathBuf>) -> vcr::config::ValoriConfig {
-    if let Some(path) = config_path {
-        // Load from specified path
-        let content = fs::read_to_string(&path)
-            .unwrap_or_else(|e| {
-                eprintln!("{{\"status\":\"error\",\"message\":\"Failed to read config: {}\",\"fatal\":true}}", e);
-                process::exit(1);
-            });
-        
-        toml::from_str(&content)
-            .unwrap_or_else(|e| {
-                eprintln!("{{\"status\":\"error\",\"message\":\"Failed to parse config: {}\",\"fatal\":true}}", e);
-                process::exit(1);
-            })
-    } else if PathBuf::from("./vtr.toml").exists() {
-        // Try default location
-        let content = fs::read_to_string("./vtr.toml").unwrap();
-        toml::from_str(&content).unwrap_or_default()
-    } else {
-        // Use built-in defaults
-        vcr::config::ValoriConfig::default()
-    }
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use vcr::cli::output::{to_json, ErrorCode, ErrorOutput};
+use vcr::cli::{self, CommandError};
+use vcr::config::{ResolvedConfig, ValoriConfig};
+use vcr::metrics::{MetricsCollector, MetricsFormat, MetricsSink};
+use vcr::compare;
+use vcr::report::render_table;
+
+/// Load config (file → VCR_* env → validate), exiting with every error on failure
+fn load_config(config_path: Option<PathBuf>) -> ValoriConfig {
+    resolve_config(config_path).config
+}
+
+/// Resolve config with provenance, exiting with every error on failure
+fn resolve_config(config_path: Option<PathBuf>) -> ResolvedConfig {
+    cli::load_config(config_path.as_deref()).unwrap_or_else(|e| fail(&e))
+}
+
+/// Print an error to stderr and exit non-zero
+fn fail(error: &CommandError) -> ! {
+    eprintln!("{}", to_json(&ErrorOutput::from(error)));
+    process::exit(1);
+}
+
+/// Write `metrics` to `sink` (whether or not the command succeeded); a
+/// failed write fails a command that succeeded
+fn write_metrics(
+    sink: Option<&MetricsSink>,
+    metrics: &MetricsCollector,
+    result: Result<String, CommandError>,
+) -> Result<String, CommandError> {
+    let written = sink.map_or(Ok(()), |sink| sink.write(metrics));
+    result.and_then(|output| written.map(|()| output).map_err(|e| format!("{:#}", e).into()))
 }
 
 #[derive(Parser)]
@@ -37,20 +51,109 @@ fn load_config(config_path: Option<PathBuf>) -> vcr::config::ValoriConfig {
 #[command(about = "Valori Code Replay - deterministic code analysis")]
 #[command(version)]
 struct Cli {
+    /// Log output format (logs go to stderr)
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    log_format: LogFormat,
+    
+    /// Log level filter (e.g. "warn", "debug", "vcr=trace")
+    #[arg(long, global = true, default_value = "warn")]
+    log_level: String,
+    
+    /// Write metrics to this file after `ingest` and `query` (and
+    /// periodically during `serve`); replaced atomically
+    #[arg(long, global = true, value_name = "PATH")]
+    metrics_file: Option<PathBuf>,
+    
+    /// Format of the metrics file
+    #[arg(long, global = true, value_enum, default_value = "json", requires = "metrics_file")]
+    metrics_format: MetricsFileFormat,
+    
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum MetricsFileFormat {
+    Json,
+    Prometheus,
+}
+
+impl From<MetricsFileFormat> for MetricsFormat {
+    fn from(format: MetricsFileFormat) -> Self {
+        match format {
+            MetricsFileFormat::Json => MetricsFormat::Json,
+            MetricsFileFormat::Prometheus => MetricsFormat::Prometheus,
+        }
+    }
+}
+
+/// Install the global tracing subscriber (binary only, never the library)
+fn init_logging(format: LogFormat, level: &str) {
+    use tracing_subscriber::fmt::format::FmtSpan;
+    use tracing_subscriber::EnvFilter;
+    
+    let filter = EnvFilter::try_new(level).unwrap_or_else(|e| {
+        fail(&CommandError::new(ErrorCode::InvalidInput, format!("Invalid log level: {}", e)))
+    });
+    
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr);
+    
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Ingest repository and build CPG
     Ingest {
         /// Path to repository or file
-        path: PathBuf,
+        #[arg(required_unless_present_any = ["roots", "stdin"], conflicts_with_all = ["roots", "stdin"])]
+        path: Option<PathBuf>,
+
+        /// Workspace root (repeatable); all roots are ingested as one repository
+        #[arg(long = "root", value_name = "DIR", conflicts_with = "stdin")]
+        roots: Vec<PathBuf>,
         
         /// Config file (default: ./vtr.toml)
         #[arg(short, long)]
         config: Option<PathBuf>,
+        
+        /// Build twice and fail if any stage hash diverges
+        #[arg(long, conflicts_with = "stdin")]
+        verify_determinism: bool,
+
+        /// Only scan: report the files an ingest would parse and skip (with
+        /// reasons) and what changed since the latest stored snapshot
+        #[arg(long, conflicts_with_all = ["stdin", "verify_determinism"])]
+        dry_run: bool,
+
+        /// Analyze one buffer read from stdin (no snapshot or CPG); exits 2
+        /// on syntax errors
+        #[arg(long, requires = "language")]
+        stdin: bool,
+
+        /// Language of the stdin buffer
+        #[arg(long, value_enum, requires = "stdin")]
+        language: Option<BufferLanguage>,
+
+        /// Include shadowed and unused binding findings (stdin only)
+        #[arg(long, requires = "stdin")]
+        lint: bool,
+
+        /// Exit 1 if any finding of this category is reported (stdin only, repeatable)
+        #[arg(long, value_enum, requires = "stdin")]
+        deny: Vec<BindingLint>,
     },
     
     /// Snapshot operations
@@ -62,7 +165,86 @@ enum Commands {
     /// Run query on CPG
     Query {
         /// Path to query file (JSON)
-        query_file: PathBuf,
+        #[arg(required_unless_present_any = ["name", "list", "taint"], conflicts_with_all = ["name", "list", "taint"])]
+        query_file: Option<PathBuf>,
+
+        /// Run the saved query with this name from query.query_dir
+        #[arg(long, conflicts_with = "list")]
+        name: Option<String>,
+
+        /// List the saved queries in query.query_dir
+        #[arg(long)]
+        list: bool,
+
+        /// Report taint paths between the node sets of a taint query file
+        #[arg(long, conflicts_with_all = ["name", "list", "explain", "timeout_secs"])]
+        taint: Option<PathBuf>,
+
+        /// Collapse taint paths into findings with stable IDs
+        #[arg(long, requires = "taint")]
+        dedupe: bool,
+
+        /// Mark findings listed in an earlier `--dedupe` output as known
+        #[arg(long, requires = "dedupe")]
+        baseline: Option<PathBuf>,
+
+        /// Config file (default: ./vtr.toml)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Run against a CPG snapshot file (otherwise an empty CPG)
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+
+        /// Run against this snapshot of the [snapshot] store, as it was saved
+        #[arg(long, conflicts_with = "snapshot")]
+        snapshot_id: Option<u64>,
+
+        /// Also report the execution plan, estimates and timings
+        #[arg(long)]
+        explain: bool,
+
+        /// Fail with a timeout error if the query runs longer
+        #[arg(long)]
+        timeout_secs: Option<u64>,
+
+        /// Also resolve each result to its file, line, column and source excerpt
+        #[arg(long, conflicts_with_all = ["list", "taint"])]
+        materialize: bool,
+
+        /// Only validate the query file; exit 1 if it has problems
+        #[arg(long, requires = "query_file", conflicts_with_all = ["snapshot", "snapshot_id", "explain", "timeout_secs", "materialize"])]
+        check: bool,
+
+        /// Repository the snapshot was built from, read by --materialize (default: .)
+        #[arg(long, requires = "materialize")]
+        source_root: Option<PathBuf>,
+
+        /// Ingest PATH and re-run the query file whenever it changes, printing
+        /// what each change added and removed (Ctrl-C stops)
+        #[arg(long, requires_all = ["query_file", "path"], conflicts_with_all = [
+            "name", "list", "taint", "snapshot", "snapshot_id", "explain", "timeout_secs", "materialize", "check",
+        ])]
+        watch: bool,
+
+        /// Repository --watch ingests
+        #[arg(requires = "watch")]
+        path: Option<PathBuf>,
+
+        /// Time between --watch refreshes in milliseconds (default: 500)
+        #[arg(long, requires = "watch")]
+        poll_ms: Option<u64>,
+    },
+    
+    /// Answer line-delimited JSON requests on stdin until shutdown or EOF
+    Serve {
+        /// Config file (default: ./vtr.toml)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Fail each query that runs longer with a timeout error
+        #[arg(long)]
+        timeout_secs: Option<u64>,
     },
     
     /// Explain result provenance
@@ -70,12 +252,219 @@ enum Commands {
         /// Result ID to explain
         result_id: String,
     },
+    
+    /// Configuration operations
+    Config {
+        #[command(subcommand)]
+        operation: ConfigOp,
+    },
+    
+    /// Lint operations
+    Lint {
+        #[command(subcommand)]
+        operation: LintOp,
+    },
+    
+    /// Code-health reports
+    Report {
+        #[command(subcommand)]
+        operation: ReportOp,
+    },
+
+    /// Function-level semantic deltas between two checkouts
+    Compare {
+        /// Base and head paths, or only the head with --snapshot-id
+        #[arg(required = true, num_args = 1..=2)]
+        paths: Vec<PathBuf>,
+
+        /// Use this snapshot of the [snapshot] store as the base
+        #[arg(long)]
+        snapshot_id: Option<u64>,
+
+        /// Config file (default: ./vtr.toml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "json")]
+        format: ReportFormat,
+    },
+
+    /// Golden hash regression suite
+    Golden {
+        #[command(subcommand)]
+        operation: GoldenOp,
+    },
+
+    /// Print the graphs of one source file as a standalone graph file, or
+    /// save part of a repository's CPG as a snapshot file (--subgraph)
+    Export {
+        /// Source file (the repository, with --subgraph)
+        path: PathBuf,
+
+        /// What to export
+        #[arg(long, value_enum, required_unless_present = "subgraph", conflicts_with = "subgraph")]
+        format: Option<ExportFormat>,
+
+        /// Stable key of the node (as in taint findings) whose neighborhood to extract
+        #[arg(long, requires = "output")]
+        subgraph: Option<String>,
+
+        /// Edges to walk out from the --subgraph node (default: 3)
+        #[arg(long, requires = "subgraph")]
+        depth: Option<usize>,
+
+        /// Replace identifiers in --subgraph labels with hashed pseudonyms
+        #[arg(long, requires = "subgraph")]
+        anonymize: bool,
+
+        /// Snapshot file --subgraph writes
+        #[arg(long, requires = "subgraph")]
+        output: Option<PathBuf>,
+
+        /// Config file (default: ./vtr.toml)
+        #[arg(long, requires = "subgraph")]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReportOp {
+    /// Per-function cyclomatic complexity and CFG metrics
+    Complexity {
+        /// Path to repository
+        path: PathBuf,
+    },
+
+    /// Per-file ingestion status, counts and hashes
+    Files {
+        /// Path to repository (ingested now)
+        #[arg(required_unless_present = "snapshot_id", conflicts_with = "snapshot_id")]
+        path: Option<PathBuf>,
+
+        /// Read the stats saved with this snapshot of the [snapshot] store
+        #[arg(long)]
+        snapshot_id: Option<u64>,
+
+        /// Config file (default: ./vtr.toml)
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "json")]
+        format: ReportFormat,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ReportFormat {
+    Json,
+    Table,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ExportFormat {
+    /// All CFGs of the file (see `vcr::semantic::io`)
+    CfgJson,
+}
+
+#[derive(Subcommand)]
+enum GoldenOp {
+    /// Rebuild the fixtures and fail if any pinned hash changed
+    Check {
+        /// Golden directory (fixtures + manifest.toml)
+        #[arg(long, default_value = "tests/golden")]
+        dir: PathBuf,
+    },
+
+    /// Rebuild the fixtures and rewrite the manifest
+    Bless {
+        /// Golden directory (fixtures + manifest.toml)
+        #[arg(long, default_value = "tests/golden")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum LintOp {
+    /// Report functions not reachable from any root
+    DeadFunctions {
+        /// Path to repository
+        path: PathBuf,
+        
+        /// Config file (default: ./vtr.toml)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Report flows from source calls to sink calls, across calls
+    Taint {
+        /// Path to repository
+        path: PathBuf,
+
+        /// Config file (default: ./vtr.toml)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+
+    /// Report shadowed and unused variables and parameters
+    Shadowing {
+        /// Path to repository
+        path: PathBuf,
+
+        /// Config file (default: ./vtr.toml)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Exit 1 if any finding of this category is reported (repeatable)
+        #[arg(long, value_enum)]
+        deny: Vec<BindingLint>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum BindingLint {
+    Shadowing,
+    Unused,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum BufferLanguage {
+    Rust,
+}
+
+impl From<BufferLanguage> for vcr::types::Language {
+    fn from(language: BufferLanguage) -> Self {
+        match language {
+            BufferLanguage::Rust => vcr::types::Language::Rust,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum ConfigOp {
+    /// Print the effective config (TOML, annotated with each field's source)
+    Show {
+        /// Config file (default: ./vtr.toml)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    
+    /// Write a commented vtr.toml to the current directory
+    Init {
+        /// Overwrite an existing vtr.toml
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 #[derive(Subcommand)]
 enum SnapshotOp {
     /// Save current CPG snapshot
-    Save,
+    Save {
+        /// Repository to ingest and save (empty CPG if omitted)
+        path: Option<PathBuf>,
+    },
     
     /// Load CPG snapshot
     Load {
@@ -88,20 +477,174 @@ enum SnapshotOp {
         /// Snapshot path
         path: PathBuf,
     },
+    
+    /// Prune old snapshots per the retention policy in config
+    Prune,
+
+    /// Delete payload files no snapshot references
+    Gc {
+        /// Only report what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let args = Cli::parse();
+    init_logging(args.log_format, &args.log_level);
+    let sink = args.metrics_file.map(|path| MetricsSink::new(path, args.metrics_format.into()));
+    let mut metrics = MetricsCollector::new();
     
-    let result = match cli.command {
-        Commands::Ingest { path, config } => cmd_ingest(path, config),
+    let result = match args.command {
+        Commands::Ingest { stdin: true, language, config, lint, deny, .. } => {
+            use std::io::Read;
+            let config = load_config(config);
+            let mut source = Vec::new();
+            if let Err(e) = std::io::stdin().read_to_end(&mut source) {
+                fail(&CommandError::new(ErrorCode::InvalidInput, format!("Failed to read stdin: {}", e)));
+            }
+            let language = language.expect("clap requires --language with --stdin").into();
+            let (shadowing, unused) = (deny.contains(&BindingLint::Shadowing), deny.contains(&BindingLint::Unused));
+            let result = cli::ingest_buffer(source, language, &config, lint, shadowing, unused, &mut metrics);
+            let code = result.as_ref().map_or(0, |output| output.exit_code());
+            match write_metrics(sink.as_ref(), &metrics, result.map(|o| to_json(&o))) {
+                Ok(output) => {
+                    println!("{}", output);
+                    process::exit(code);
+                }
+                Err(e) => fail(&e),
+            }
+        }
+        Commands::Ingest { path, roots, config, dry_run: true, .. } => {
+            let roots = path.map_or(roots, |path| vec![path]);
+            let result = cli::ingest_plan(&roots, &load_config(config), &mut metrics).map(|o| to_json(&o));
+            write_metrics(sink.as_ref(), &metrics, result)
+        }
+        Commands::Ingest { path, roots, config, verify_determinism, .. } => {
+            let config = load_config(config);
+            let result = match path {
+                Some(path) => cli::ingest_with_metrics(&path, &config, verify_determinism, &mut metrics),
+                None => cli::ingest_workspace_with_metrics(&roots, &config, verify_determinism, &mut metrics),
+            }.map(|o| to_json(&o));
+            write_metrics(sink.as_ref(), &metrics, result)
+        }
         Commands::Snapshot { operation } => match operation {
-            SnapshotOp::Save => cmd_snapshot_save(),
-            SnapshotOp::Load { id } => cmd_snapshot_load(id),
-            SnapshotOp::Verify { path } => cmd_snapshot_verify(path),
+            SnapshotOp::Save { path } => cli::snapshot_save(&load_config(None), path.as_deref()),
+            SnapshotOp::Load { id } => cli::snapshot_load(&id),
+            SnapshotOp::Verify { path } => cli::snapshot_verify(&path),
+            SnapshotOp::Prune => cli::snapshot_prune(&load_config(None)),
+            SnapshotOp::Gc { dry_run } => cli::snapshot_gc(&load_config(None), dry_run),
+        }.map(|o| to_json(&o)),
+        Commands::Query {
+            query_file, name, list, taint, dedupe, baseline, config, snapshot, snapshot_id, explain, timeout_secs, materialize,
+            source_root, check, watch, path, poll_ms,
+        } => {
+            if let (true, Some(query_file), Some(path)) = (watch, &query_file, &path) {
+                let stop = Arc::new(AtomicBool::new(false));
+                let handler_stop = Arc::clone(&stop);
+                if let Err(e) = ctrlc::set_handler(move || handler_stop.store(true, Ordering::Relaxed)) {
+                    fail(&CommandError::from(format!("Failed to install Ctrl-C handler: {}", e)));
+                }
+                let poll = poll_ms.map_or(cli::watch::POLL_INTERVAL, Duration::from_millis);
+                match cli::query_watch(&load_config(config), query_file, path, poll, &stop, std::io::stdout().lock()) {
+                    Ok(()) => process::exit(0),
+                    Err(e) => fail(&e),
+                }
+            }
+            let store = snapshot_id.map(|id| (load_config(config.clone()).snapshot.path, id));
+            let snapshot = match &store {
+                Some((dir, id)) => Some(cli::QuerySnapshot::Stored { dir, id: *id }),
+                None => snapshot.as_deref().map(cli::QuerySnapshot::File),
+            };
+            let timeout = timeout_secs.map(Duration::from_secs);
+            let materialize = materialize.then(|| source_root.unwrap_or_else(|| PathBuf::from(".")));
+            let result = match (query_file, name, taint) {
+                _ if list => cli::query_list(&load_config(config)).map(|o| to_json(&o)),
+                (Some(query_file), _, _) if check => match cli::query_check(&query_file) {
+                    Ok(output) => {
+                        println!("{}", to_json(&output));
+                        process::exit(if output.valid { 0 } else { 1 });
+                    }
+                    Err(e) => fail(&e),
+                },
+                (_, _, Some(taint)) => {
+                    cli::query_taint_with_metrics(&taint, snapshot, dedupe, baseline.as_deref(), &mut metrics)
+                        .map(|o| to_json(&o))
+                }
+                (Some(query_file), _, None) => {
+                    cli::query_with_metrics(&query_file, snapshot, explain, timeout, materialize.as_deref(), &mut metrics)
+                        .map(|o| to_json(&o))
+                }
+                (None, Some(name), None) => {
+                    let config = load_config(config);
+                    cli::query_named_with_metrics(
+                        &config, &name, snapshot, explain, timeout, materialize.as_deref(), &mut metrics,
+                    )
+                        .map(|o| to_json(&o))
+                }
+                (None, None, None) => unreachable!("clap requires a query file, --name, --list or --taint"),
+            };
+            write_metrics(sink.as_ref(), &metrics, result)
+        }
+        Commands::Serve { config, timeout_secs } => {
+            let config = load_config(config);
+            let input = std::io::BufReader::new(std::io::stdin());
+            let timeout = timeout_secs.map(Duration::from_secs);
+            match cli::serve(&config, timeout, sink.as_ref(), input, std::io::stdout().lock()) {
+                Ok(()) => process::exit(0),
+                Err(e) => fail(&e),
+            }
+        }
+        Commands::Explain { result_id } => cli::explain(&result_id).map(|o| to_json(&o)),
+        Commands::Config { operation } => match operation {
+            ConfigOp::Show { config } => cli::config_show(&resolve_config(config)),
+            ConfigOp::Init { force } => cli::config_init(Path::new("."), force).map(|o| to_json(&o)),
+        },
+        Commands::Lint { operation } => match operation {
+            LintOp::DeadFunctions { path, config } => {
+                cli::lint_dead_functions(&path, &load_config(config)).map(|o| to_json(&o))
+            }
+            LintOp::Taint { path, config } => {
+                cli::lint_taint(&path, &load_config(config)).map(|o| to_json(&o))
+            }
+            LintOp::Shadowing { path, config, deny } => {
+                let (shadowing, unused) = (deny.contains(&BindingLint::Shadowing), deny.contains(&BindingLint::Unused));
+                match cli::lint_shadowing(&path, &load_config(config), shadowing, unused) {
+                    Ok(output) => {
+                        println!("{}", to_json(&output));
+                        process::exit(if output.denied.is_empty() { 0 } else { 1 });
+                    }
+                    Err(e) => fail(&e),
+                }
+            }
+        },
+        Commands::Report { operation } => match operation {
+            ReportOp::Complexity { path } => cli::report_complexity(&path).map(|o| to_json(&o)),
+            ReportOp::Files { path, snapshot_id, config, format } => {
+                cli::report_files(&load_config(config), path.as_deref(), snapshot_id).map(|o| match format {
+                    ReportFormat::Json => to_json(&o),
+                    ReportFormat::Table => render_table(&o.files).trim_end().to_string(),
+                })
+            }
+        },
+        Commands::Compare { paths, snapshot_id, config, format } => {
+            cli::compare(&load_config(config), &paths, snapshot_id).map(|o| match format {
+                ReportFormat::Json => to_json(&o),
+                ReportFormat::Table => compare::render_table(&o.comparison).trim_end().to_string(),
+            })
+        }
+        Commands::Golden { operation } => match operation {
+            GoldenOp::Check { dir } => cli::golden_check(&dir).map(|o| to_json(&o)),
+            GoldenOp::Bless { dir } => cli::golden_bless(&dir).map(|o| to_json(&o)),
+        },
+        Commands::Export { path, format, subgraph, depth, anonymize, output, config } => match (format, subgraph, output) {
+            (_, Some(key), Some(output)) => {
+                let depth = depth.unwrap_or(cli::SUBGRAPH_DEPTH);
+                cli::export_subgraph(&load_config(config), &path, &key, depth, anonymize, &output).map(|o| to_json(&o))
+            }
+            (Some(ExportFormat::CfgJson), _, _) => cli::export_cfgs(&path).map(|o| to_json(&o)),
+            _ => unreachable!("clap requires --format or --subgraph with --output"),
         },
-        Commands::Query { query_file } => cmd_query(query_file),
-        Commands::Explain { result_id } => cmd_explain(result_id),
     };
     
     match result {
@@ -109,130 +652,6 @@ fn main() {
             println!("{}", output);
             process::exit(0);
         }
-        Err(e) => {
-            eprintln!("{{\"status\":\"error\",\"message\":\"{}\",\"fatal\":true}}", e);
-            process::exit(1);
-        }
+        Err(e) => fail(&e),
     }
 }
-
-fn cmd_ingest(path: PathBuf, config: Option<PathBuf>) -> Result<String, String> {
-    use vcr::parse::IncrementalParser;
-    use vcr::types::{Language, FileId};
-    use vcr::io::MmappedFile;
-    
-    let _config = load_config(config);
-    
-    // For now: simple single-file ingestion
-    // Full repo traversal would go here
-    
-    if !path.exists() {
-        return Err(format!("Path not found: {}", path.display()));
-    }
-    
-    if path.is_file() {
-        // Single file ingestion
-        let file_id = FileId::new(1);
-        let mmap = MmappedFile::open(&path, file_id)
-            .map_err(|e| format!("Failed to open file: {}", e))?;
-        
-        let mut parser = IncrementalParser::new(Language::Rust)
-            .map_err(|e| format!("Failed to create parser: {}", e))?;
-        
-        let parsed = parser.parse(&mmap, None)
-            .map_err(|e| format!("Parse failed: {}", e))?;
-        
-        // Build CPG (simplified - full pipeline would include semantic analysis)
-        let cpg = vcr::cpg::model::CPG::new();
-        let hash = cpg.compute_hash();
-        
-        Ok(format!("{{\"status\":\"success\",\"epoch_id\":1,\"cpg_hash\":\"{}\",\"nodes\":{}}}", 
-            hash, parsed.tree.root_node().child_count()))
-    } else {
-        Err("Directory ingestion not yet implemented - TODO".to_string())
-    }
-}
-
-fn cmd_snapshot_save() -> Result<String, String> {
-    use vcr::storage::CPGSnapshot;
-    use vcr::cpg::model::CPG;
-    use std::path::PathBuf;
-    
-    // For now: save empty CPG as demo
-    // Full implementation would get current CPG from global state
-    let cpg = CPG::new();
-    
-    let temp_path = PathBuf::from("/tmp/vcr-snapshot-demo.bin");
-    
-    let snapshot_id = CPGSnapshot::save(&cpg, &temp_path)
-        .map_err(|e| format!("Snapshot save failed: {}", e))?;
-    
-    let hash = cpg.compute_hash();
-    
-    Ok(format!("{{\"status\":\"success\",\"snapshot_id\":{},\"hash\":\"{}\"}}", 
-        snapshot_id.0, hash))
-}
-
-fn cmd_snapshot_load(id: String) -> Result<String, String> {
-    use vcr::storage::CPGSnapshot;
-    use std::path::Path;
-    
-    // Load from path (id is treated as path for now)
-    let path = Path::new(&id);
-    
-    if !path.exists() {
-        return Err(format!("Snapshot not found: {}", id));
-    }
-    
-    // Verify first
-    let hash = CPGSnapshot::verify(path)
-        .map_err(|e| format!("Snapshot verification failed: {}", e))?;
-    
-    // Load
-    let _cpg = CPGSnapshot::load(path)
-        .map_err(|e| format!("Snapshot load failed: {}", e))?;
-    
-    Ok(format!("{{\"status\":\"success\",\"hash\":\"{}\",\"verified\":true}}", hash))
-}
-
-fn cmd_snapshot_verify(path: PathBuf) -> Result<String, String> {
-    // TODO: Wire to CPGSnapshot::verify
-    use vcr::storage::CPGSnapshot;
-    
-    match CPGSnapshot::verify(&path) {
-        Ok(hash) => Ok(format!("{{\"status\":\"success\",\"hash\":\"{}\",\"valid\":true}}", hash)),
-        Err(e) => Err(format!("Snapshot verification failed: {}", e)),
-    }
-}
-
-fn cmd_query(query_file: PathBuf) -> Result<String, String> {
-    use vcr::cpg::model::CPG;
-    use vcr::query::primitives::QueryPrimitives;
-    use vcr::cpg::model::CPGNodeKind;
-    
-    // For now: simple hardcoded query demo
-    // Full implementation would parse query file (JSON DSL)
-    
-    if !query_file.exists() {
-        return Err(format!("Query file not found: {}", query_file.display()));
-    }
-    
-    // Demo: empty CPG, find all functions
-    let cpg = CPG::new();
-    let results = QueryPrimitives::find_nodes(&cpg, CPGNodeKind::Function);
-    
-    Ok(format!("{{\"status\":\"success\",\"query\":\"{}\",\"results\":[],\"count\":{}}}", 
-        query_file.display(), results.len()))
-}
-
-fn cmd_explain(result_id: String) -> Result<String, String> {
-    // Deterministic provenance trace
-    // For now: placeholder implementation
-    // Full version would:
-    // 1. Load result metadata from store
-    // 2. Trace back through CPG to origin nodes
-    // 3. Output complete provenance chain
-    
-    Ok(format!("{{\"status\":\"success\",\"result_id\":\"{}\",\"provenance\":[\"TODO: trace origin\"]}}", 
-        result_id))
-}
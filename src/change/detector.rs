@@ -1,8 +1,13 @@
 //! Change detection (Step 1.5)
 //!
 //! Detects what changed between repository snapshots.
+//!
+//! A deleted file whose content reappears under an added path is reported
+//! once, as a rename. Changes are ordered by FileId, so the same pair of
+//! snapshots always yields the same Vec.
 
 use crate::types::{FileId, RepoSnapshot};
+use std::collections::{HashMap, HashSet};
 
 /// Type of file change detected.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -10,7 +15,7 @@ pub enum FileChange {
     /// File was added
     Added(FileId),
     
-    /// File was modified (content hash changed)
+    /// File was modified (content hash or `.vcr.toml` policy changed)
     Modified(FileId),
     
     /// File was deleted
@@ -18,6 +23,74 @@ pub enum FileChange {
     
     /// File unchanged
     Unchanged(FileId),
+
+    /// File moved without a content change (new path, new FileId)
+    Renamed { from: FileId, to: FileId },
+}
+
+impl FileChange {
+    /// The file this change is keyed on (the new ID for renames)
+    pub fn file_id(&self) -> FileId {
+        match self {
+            FileChange::Added(id)
+            | FileChange::Modified(id)
+            | FileChange::Deleted(id)
+            | FileChange::Unchanged(id) => *id,
+            FileChange::Renamed { to, .. } => *to,
+        }
+    }
+}
+
+/// Per-kind counts over a set of changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSummary {
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+    pub renamed: usize,
+
+    /// Files that must be re-analyzed (added, modified, rename targets), ascending
+    changed: Vec<FileId>,
+}
+
+impl ChangeSummary {
+    /// Files that must be re-analyzed, ascending
+    pub fn changed_file_ids(&self) -> &[FileId] {
+        &self.changed
+    }
+
+    /// Whether anything other than `Unchanged` was seen
+    pub fn has_changes(&self) -> bool {
+        self.added + self.modified + self.deleted + self.renamed > 0
+    }
+}
+
+impl From<&[FileChange]> for ChangeSummary {
+    fn from(changes: &[FileChange]) -> Self {
+        let mut summary = Self::default();
+        for change in changes {
+            match change {
+                FileChange::Added(id) => {
+                    summary.added += 1;
+                    summary.changed.push(*id);
+                }
+                FileChange::Modified(id) => {
+                    summary.modified += 1;
+                    summary.changed.push(*id);
+                }
+                FileChange::Renamed { to, .. } => {
+                    summary.renamed += 1;
+                    summary.changed.push(*to);
+                }
+                FileChange::Deleted(_) => summary.deleted += 1,
+                FileChange::Unchanged(_) => summary.unchanged += 1,
+            }
+        }
+        summary.changed.sort();
+        summary.changed.dedup();
+        summary
+    }
 }
 
 /// Change detector between snapshots.
@@ -32,6 +105,10 @@ impl ChangeDetector {
     }
 
     /// Detect changes between the previous and current snapshot.
+    ///
+    /// Changes are sorted by `FileChange::file_id`. Each deleted file is
+    /// paired with the first (lowest FileId) unpaired added file with the
+    /// same content hash, if any, and reported as `Renamed`.
     pub fn detect(&self, current: &RepoSnapshot) -> Vec<FileChange> {
         let mut changes = Vec::new();
 
@@ -43,8 +120,8 @@ impl ChangeDetector {
                     changes.push(FileChange::Added(*file_id));
                 }
                 Some(prev_meta) => {
-                    // File exists - check if content changed
-                    if prev_meta.content_hash != current_meta.content_hash {
+                    // File exists - check if content (or its .vcr.toml policy) changed
+                    if prev_meta.content_hash != current_meta.content_hash || prev_meta.policy != current_meta.policy {
                         changes.push(FileChange::Modified(*file_id));
                     } else {
                         changes.push(FileChange::Unchanged(*file_id));
@@ -60,20 +137,64 @@ impl ChangeDetector {
             }
         }
 
+        self.pair_renames(current, &mut changes);
+        changes.sort_by_key(FileChange::file_id);
         changes
     }
+
+    /// Replace matching Deleted/Added pairs with Renamed
+    fn pair_renames(&self, current: &RepoSnapshot, changes: &mut Vec<FileChange>) {
+        // Added files by content hash, lowest FileId first
+        let mut added: HashMap<&str, Vec<FileId>> = HashMap::new();
+        for change in changes.iter() {
+            if let FileChange::Added(id) = change {
+                added.entry(current.files[id].content_hash.as_str()).or_default().push(*id);
+            }
+        }
+        if added.is_empty() {
+            return;
+        }
+        for ids in added.values_mut() {
+            ids.sort_by(|a, b| b.cmp(a));
+        }
+
+        let mut renamed_to: HashMap<FileId, FileId> = HashMap::new();
+        let mut deleted: Vec<FileId> = changes.iter()
+            .filter_map(|change| match change {
+                FileChange::Deleted(id) => Some(*id),
+                _ => None,
+            })
+            .collect();
+        deleted.sort();
+        for from in deleted {
+            let hash = self.previous_snapshot.files[&from].content_hash.as_str();
+            if let Some(to) = added.get_mut(hash).and_then(Vec::pop) {
+                renamed_to.insert(from, to);
+            }
+        }
+
+        let targets: HashSet<FileId> = renamed_to.values().copied().collect();
+        changes.retain(|change| !matches!(change, FileChange::Added(id) if targets.contains(id)));
+        for change in changes.iter_mut() {
+            if let FileChange::Deleted(from) = change {
+                if let Some(to) = renamed_to.get(from) {
+                    *change = FileChange::Renamed { from: *from, to: *to };
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{FileMetadata, Language};
-    use std::collections::HashMap;
+    use crate::types::{FileMetadata, FilePolicy, Language};
+    use std::collections::BTreeMap;
     use std::path::PathBuf;
     use std::time::SystemTime;
 
     fn make_snapshot(files: Vec<(u64, &str, &str)>) -> RepoSnapshot {
-        let mut file_map = HashMap::new();
+        let mut file_map = BTreeMap::new();
         
         for (id, path, hash) in files {
             file_map.insert(
@@ -84,15 +205,19 @@ mod tests {
                     mtime: SystemTime::UNIX_EPOCH,
                     content_hash: hash.to_string(),
                     language: Some(Language::Rust),
+                    policy: FilePolicy::default(),
                 },
             );
         }
 
         RepoSnapshot {
             root: PathBuf::from("/test"),
+            roots: Vec::new(),
             files: file_map,
             created_at: SystemTime::UNIX_EPOCH,
             snapshot_hash: "test".to_string(),
+            vcs: None,
+            normalized_line_endings: false,
         }
     }
 
@@ -143,4 +268,55 @@ mod tests {
         assert_eq!(changes.len(), 1);
         assert!(matches!(changes[0], FileChange::Deleted(_)));
     }
+
+    #[test]
+    fn test_renamed_file() {
+        let prev = make_snapshot(vec![(1, "a.rs", "hash1"), (2, "b.rs", "hash2")]);
+        let curr = make_snapshot(vec![(2, "b.rs", "hash2"), (3, "c.rs", "hash1"), (4, "d.rs", "hash1")]);
+
+        let changes = ChangeDetector::new(prev).detect(&curr);
+
+        assert_eq!(changes, vec![
+            FileChange::Unchanged(FileId::new(2)),
+            FileChange::Renamed { from: FileId::new(1), to: FileId::new(3) },
+            FileChange::Added(FileId::new(4)),
+        ]);
+    }
+
+    #[test]
+    fn test_detect_order_is_deterministic() {
+        let prev_files = vec![(9, "i.rs", "h9"), (2, "b.rs", "h2"), (5, "e.rs", "h5"), (7, "g.rs", "h7")];
+        let curr_files = vec![(5, "e.rs", "h5x"), (1, "a.rs", "h1"), (9, "i.rs", "h9"), (3, "c.rs", "h3")];
+
+        let expected = ChangeDetector::new(make_snapshot(prev_files.clone())).detect(&make_snapshot(curr_files.clone()));
+        for rotation in 1..4 {
+            let mut prev = prev_files.clone();
+            let mut curr = curr_files.clone();
+            prev.rotate_left(rotation);
+            curr.rotate_right(rotation);
+            let changes = ChangeDetector::new(make_snapshot(prev)).detect(&make_snapshot(curr));
+            assert_eq!(changes, expected);
+        }
+
+        let ids: Vec<u64> = expected.iter().map(|c| c.file_id().as_u64()).collect();
+        assert_eq!(ids, vec![1, 2, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn test_change_summary() {
+        let changes = vec![
+            FileChange::Unchanged(FileId::new(1)),
+            FileChange::Modified(FileId::new(4)),
+            FileChange::Renamed { from: FileId::new(2), to: FileId::new(3) },
+            FileChange::Added(FileId::new(2)),
+            FileChange::Deleted(FileId::new(5)),
+        ];
+
+        let summary = ChangeSummary::from(changes.as_slice());
+
+        assert_eq!((summary.added, summary.modified, summary.deleted, summary.unchanged, summary.renamed), (1, 1, 1, 1, 1));
+        assert_eq!(summary.changed_file_ids(), &[FileId::new(2), FileId::new(3), FileId::new(4)]);
+        assert!(summary.has_changes());
+        assert!(!ChangeSummary::from(&changes[..1]).has_changes());
+    }
 }
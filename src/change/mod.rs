@@ -2,4 +2,4 @@
 
 pub mod detector;
 
-pub use detector::{ChangeDetector, FileChange};
+pub use detector::{ChangeDetector, ChangeSummary, FileChange};
@@ -0,0 +1,1969 @@
+//! CLI commands (Path B9)
+//!
+//! Every `vcr` subcommand as a plain function: explicit inputs in,
+//! structured output out. The binary only parses arguments, loads config and
+//! prints what comes back, so commands are testable without a process.
+//!
+//! **No exits here**: Failures are returned as `CommandError`.
+
+pub mod output;
+pub mod serve;
+pub mod watch;
+
+use crate::config::{ConfigError, ConfigLoader, ResolvedConfig, ValoriConfig};
+use crate::metrics::MetricsCollector;
+use output::*;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub use serve::serve;
+pub use watch::query_watch;
+
+/// A failed command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    /// Error category
+    pub code: ErrorCode,
+
+    /// Summary message
+    pub message: String,
+
+    /// Individual errors behind the summary (config validation)
+    pub errors: Vec<String>,
+}
+
+impl CommandError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), errors: Vec::new() }
+    }
+
+    /// Argument refers to a path that does not exist
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    /// Argument exists but is the wrong kind of input
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::new(ErrorCode::Failed, message)
+    }
+}
+
+impl From<crate::api::ValoriError> for CommandError {
+    fn from(error: crate::api::ValoriError) -> Self {
+        Self::new(serve::error_code(&error), error.to_string())
+    }
+}
+
+impl From<Vec<ConfigError>> for CommandError {
+    fn from(errors: Vec<ConfigError>) -> Self {
+        Self {
+            code: ErrorCode::InvalidConfig,
+            message: format!("Invalid configuration ({} error(s))", errors.len()),
+            errors: errors.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+}
+
+impl From<&CommandError> for ErrorOutput {
+    fn from(error: &CommandError) -> Self {
+        Self { errors: error.errors.clone(), ..Self::new(error.code, error.message.clone()) }
+    }
+}
+
+/// Result of one command
+pub type CommandResult<T> = Result<T, CommandError>;
+
+/// Snapshot a one-shot query runs against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuerySnapshot<'a> {
+    /// A snapshot file (`--snapshot`)
+    File(&'a Path),
+
+    /// Snapshot `id` of the store in `dir` (`--snapshot-id`)
+    Stored { dir: &'a Path, id: u64 },
+}
+
+impl QuerySnapshot<'_> {
+    /// Store snapshot ID, to tag results with
+    fn id(&self) -> Option<u64> {
+        match self {
+            QuerySnapshot::File(_) => None,
+            QuerySnapshot::Stored { id, .. } => Some(*id),
+        }
+    }
+}
+
+/// Restore the CPG a query runs against: the snapshot if given, otherwise
+/// an empty CPG (no live ingest in a one-shot command)
+fn restore_epoch(snapshot: Option<QuerySnapshot>) -> CommandResult<crate::cpg::FrozenCPGEpoch> {
+    use crate::cpg::CPGEpoch;
+    use crate::storage::{SnapshotId, SnapshotStore};
+
+    match snapshot {
+        Some(QuerySnapshot::File(path)) => {
+            if !path.exists() {
+                return Err(CommandError::not_found(format!("Snapshot not found: {}", path.display())));
+            }
+            Ok(CPGEpoch::from_snapshot(path, None).map_err(|e| format!("Snapshot load failed: {:#}", e))?)
+        }
+        Some(QuerySnapshot::Stored { dir, id }) => {
+            let store = SnapshotStore::open(dir)
+                .map_err(|e| format!("Snapshot store open failed: {}", e))?;
+            if store.get(SnapshotId(id)).is_none() {
+                return Err(CommandError::not_found(format!("Snapshot not found: {}", id)));
+            }
+            Ok(CPGEpoch::from_store(&store, SnapshotId(id)).map_err(|e| format!("Snapshot load failed: {:#}", e))?)
+        }
+        None => Ok(CPGEpoch::new(0, 0).freeze()),
+    }
+}
+
+/// Load config (file → VCR_* env → validate) with provenance
+pub fn load_config(config_path: Option<&Path>) -> CommandResult<ResolvedConfig> {
+    Ok(ConfigLoader::new().with_file(config_path).load()?)
+}
+
+/// `vcr ingest`: full pipeline for a directory, parse only for a file
+pub fn ingest(path: &Path, config: &ValoriConfig, verify_determinism: bool) -> CommandResult<IngestOutput> {
+    ingest_with_metrics(path, config, verify_determinism, &mut MetricsCollector::new())
+}
+
+/// `ingest`, recording scan and parse times and the CPG in `metrics`
+pub fn ingest_with_metrics(
+    path: &Path,
+    config: &ValoriConfig,
+    verify_determinism: bool,
+    metrics: &mut MetricsCollector,
+) -> CommandResult<IngestOutput> {
+    use crate::config::ParseErrorPolicy;
+    use crate::io::MmappedFile;
+    use crate::parse::IncrementalParser;
+    use crate::types::{FileId, Language};
+
+    if !path.exists() {
+        return Err(CommandError::not_found(format!("Path not found: {}", path.display())));
+    }
+
+    let verify_determinism = verify_determinism || config.verification.verify_determinism;
+
+    if path.is_dir() {
+        return ingest_workspace_with_metrics(&[path.to_path_buf()], config, verify_determinism, metrics);
+    }
+
+    if verify_determinism {
+        return Err(CommandError::invalid_input("Determinism verification requires a directory"));
+    }
+
+    if !path.is_file() {
+        return Err(CommandError::invalid_input(format!("Not a file or directory: {}", path.display())));
+    }
+
+    // Single file ingestion
+    let language = path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(Language::from_extension)
+        .ok_or_else(|| CommandError::invalid_input(format!("Unsupported language: {}", path.display())))?;
+
+    let file_id = FileId::new(1);
+    let mmap = MmappedFile::open(path, file_id)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    let mut parser = IncrementalParser::new(language)
+        .map_err(|e| format!("Failed to create parser: {}", e))?;
+
+    let started = std::time::Instant::now();
+    let parsed = parser.parse(&mmap, None)
+        .map_err(|e| format!("Parse failed: {}", e))?;
+    metrics.record_parse_time(file_id, started.elapsed().as_micros() as u64);
+    metrics.record_scan_duration(started.elapsed());
+
+    let quality = parsed.quality();
+    let policy = config.parse.on_parse_error;
+    if !quality.clean && policy == ParseErrorPolicy::Fail {
+        return Err(format!("Syntax errors in {}: {} error node(s)", path.display(), quality.error_count).into());
+    }
+    let parse_errors = (!quality.clean)
+        .then(|| ParseErrorRow {
+            file: path.display().to_string(),
+            error_count: quality.error_count,
+            error_ranges: quality.error_ranges,
+            skipped: policy == ParseErrorPolicy::Skip,
+        })
+        .into_iter()
+        .collect();
+
+    // Build CPG (simplified - full pipeline would include semantic analysis)
+    let cpg = crate::cpg::model::CPG::new();
+
+    Ok(IngestOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        epoch_id: 1,
+        cpg_hash: cpg.compute_hash(),
+        snapshot_hash: None,
+        files: None,
+        nodes: Some(parsed.tree.root_node().child_count()),
+        determinism_verified: None,
+        parse_errors,
+    })
+}
+
+/// `file` of the binding findings in a buffer report
+const STDIN_PATH: &str = "<stdin>";
+
+/// `vcr ingest --stdin`: parse, CFGs, symbols and DFGs of one buffer
+///
+/// Nothing is read from or written to disk: the buffer is held as a
+/// `BufferedFile` (as a `MemoryBackend` run holds its files) with FileId 1,
+/// and no snapshot or CPG epoch is built. Syntax errors are reported in
+/// `parse` rather than failing the command; see
+/// `BufferIngestOutput::exit_code`. Binding findings are included when
+/// `lint` is set or a category is denied.
+pub fn ingest_buffer(
+    source: Vec<u8>,
+    language: crate::types::Language,
+    config: &ValoriConfig,
+    lint: bool,
+    deny_shadowing: bool,
+    deny_unused: bool,
+    metrics: &mut MetricsCollector,
+) -> CommandResult<BufferIngestOutput> {
+    use crate::analysis::bindings::{find_shadowing, find_unused};
+    use crate::config::ParseErrorPolicy;
+    use crate::io::{BufferedFile, SourceFile};
+    use crate::parse::IncrementalParser;
+    use crate::semantic::cfg::metrics::cfg_metrics;
+    use crate::semantic::epoch::SEMANTIC_EPOCH_ID;
+    use crate::semantic::{profile_for, SemanticEpoch};
+    use crate::types::{ByteRange, FileId};
+
+    let file_id = FileId::new(1);
+    let size = source.len();
+    let file = BufferedFile::new(source, file_id);
+    let mut parser = IncrementalParser::new(language)
+        .map_err(|e| format!("Failed to create parser: {}", e))?;
+    let started = std::time::Instant::now();
+    let parsed = parser.parse(&file, None)
+        .map_err(|e| format!("Parse failed: {}", e))?;
+    metrics.record_parse_time(file_id, started.elapsed().as_micros() as u64);
+
+    let quality = parsed.quality();
+    let analyzed = quality.clean || config.parse.on_parse_error == ParseErrorPolicy::IncludeBestEffort;
+    let mut semantic = SemanticEpoch::builder(SEMANTIC_EPOCH_ID).build();
+    semantic.set_limits(config.limits);
+    if analyzed {
+        semantic.add_parsed_with_profile(file_id, &parsed, file.bytes(), profile_for(Some(language)))
+            .map_err(|e| format!("Analysis failed: {:#}", e))?;
+    }
+
+    let report = semantic.report();
+    let hashes = report.file(file_id).map(|file| file.function_hashes.as_slice()).unwrap_or_default();
+    let functions = semantic.get_cfgs(file_id).into_iter().flatten()
+        .map(|cfg| {
+            let range = cfg.get_node(cfg.entry).map_or(ByteRange::default(), |entry| entry.source_range);
+            let metrics = cfg_metrics(cfg);
+            let hashes = hashes.iter().find(|h| h.function_id == cfg.function_id);
+            BufferFunctionRow {
+                function_id: cfg.function_id.0,
+                name: cfg.name.clone(),
+                start: range.start,
+                end: range.end,
+                cyclomatic_complexity: metrics.cyclomatic_complexity,
+                node_count: metrics.node_count,
+                edge_count: metrics.edge_count,
+                max_loop_nesting: metrics.max_loop_nesting,
+                cfg_hash: hashes.and_then(|h| h.cfg_hash.clone()).unwrap_or_default(),
+                dfg_hash: hashes.and_then(|h| h.dfg_hash.clone()),
+            }
+        })
+        .collect();
+
+    let table = semantic.get_symbols(file_id);
+    let lint_rows = (lint || deny_shadowing || deny_unused).then(|| {
+        let kind = |kind| format!("{:?}", kind).to_lowercase();
+        BufferLintRows {
+            shadowing: table.map(find_shadowing).unwrap_or_default().into_iter().map(|f| ShadowingRow {
+                name: f.name,
+                kind: kind(f.kind),
+                file: STDIN_PATH.to_string(),
+                start: f.range.start,
+                end: f.range.end,
+                shadowed_kind: kind(f.shadowed_kind),
+                shadowed_start: f.shadowed_range.start,
+                shadowed_end: f.shadowed_range.end,
+            }).collect(),
+            unused: table.map(find_unused).unwrap_or_default().into_iter().map(|f| UnusedBindingRow {
+                name: f.name,
+                kind: kind(f.kind),
+                file: STDIN_PATH.to_string(),
+                start: f.range.start,
+                end: f.range.end,
+            }).collect(),
+        }
+    });
+    let mut denied = Vec::new();
+    if let Some(rows) = &lint_rows {
+        if deny_shadowing && !rows.shadowing.is_empty() {
+            denied.push("shadowing".to_string());
+        }
+        if deny_unused && !rows.unused.is_empty() {
+            denied.push("unused".to_string());
+        }
+    }
+
+    Ok(BufferIngestOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        language: language.name().to_string(),
+        size,
+        parse: quality,
+        analyzed,
+        semantic_status: semantic.status(file_id),
+        symbols: table.map_or(0, |table| table.symbols().count()),
+        functions,
+        lint: lint_rows,
+        denied,
+    })
+}
+
+/// `vcr ingest --root <dir> --root <dir>`: several roots as one workspace
+pub fn ingest_workspace(roots: &[PathBuf], config: &ValoriConfig, verify_determinism: bool) -> CommandResult<IngestOutput> {
+    ingest_workspace_with_metrics(roots, config, verify_determinism, &mut MetricsCollector::new())
+}
+
+/// `ingest_workspace`, recording scan and parse times, the CPG and the
+/// semantic epoch's per-file report in `metrics`
+pub fn ingest_workspace_with_metrics(
+    roots: &[PathBuf],
+    config: &ValoriConfig,
+    verify_determinism: bool,
+    metrics: &mut MetricsCollector,
+) -> CommandResult<IngestOutput> {
+    use crate::config::ParseErrorPolicy;
+    use crate::pipeline::Pipeline;
+
+    if let Some(missing) = roots.iter().find(|root| !root.exists()) {
+        return Err(CommandError::not_found(format!("Path not found: {}", missing.display())));
+    }
+    if let Some(file) = roots.iter().find(|root| !root.is_dir()) {
+        return Err(CommandError::invalid_input(format!("Workspace root is not a directory: {}", file.display())));
+    }
+
+    let verify_determinism = verify_determinism || config.verification.verify_determinism;
+    let pipeline = Pipeline::new(config).with_verify_determinism(verify_determinism);
+    let started = std::time::Instant::now();
+    let output = pipeline.run_workspace_with_metrics(roots, metrics)
+        .map_err(|e| format!("Ingest failed: {:#}", e))?;
+    metrics.record_scan_duration(started.elapsed());
+    metrics.record_cpg_epoch(&output.cpg_epoch);
+    metrics.record_semantic_epoch(&output.semantic);
+    let verified = pipeline.verifies_determinism();
+
+    Ok(IngestOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        epoch_id: 1,
+        cpg_hash: output.cpg_epoch.cpg_hash().to_string(),
+        snapshot_hash: Some(output.snapshot.snapshot_hash.clone()),
+        files: (!verified).then_some(output.snapshot.files.len()),
+        nodes: (!verified).then_some(output.cpg_epoch.cpg().nodes.len()),
+        determinism_verified: verified.then_some(true),
+        parse_errors: output.parse_errors.iter().map(|(file_id, quality)| ParseErrorRow {
+            file: output.snapshot.files[file_id].path.display().to_string(),
+            error_count: quality.error_count,
+            error_ranges: quality.error_ranges.clone(),
+            skipped: output.parse_error_policy == ParseErrorPolicy::Skip,
+        }).collect(),
+    })
+}
+
+/// `vcr ingest --dry-run`: scan only, reporting what an ingest would parse
+/// and skip
+///
+/// Changes are reported against the latest stored snapshot, if the store
+/// exists and that snapshot records its files (storage version 9 on). No
+/// parse, semantic or CPG work is done; only the scan time is recorded in
+/// `metrics`.
+pub fn ingest_plan(roots: &[PathBuf], config: &ValoriConfig, metrics: &mut MetricsCollector) -> CommandResult<IngestPlanOutput> {
+    use crate::pipeline::Pipeline;
+    use crate::storage::SnapshotStore;
+
+    if let Some(missing) = roots.iter().find(|root| !root.exists()) {
+        return Err(CommandError::not_found(format!("Path not found: {}", missing.display())));
+    }
+    if let Some(file) = roots.iter().find(|root| !root.is_dir()) {
+        return Err(CommandError::invalid_input(format!("Dry runs need a directory: {}", file.display())));
+    }
+
+    // Opening creates the store: only read one that exists
+    let previous = match config.snapshot.path.exists() {
+        true => {
+            let store = SnapshotStore::open(&config.snapshot.path)
+                .map_err(|e| format!("Snapshot store open failed: {}", e))?;
+            store.latest().and_then(|entry| Some((entry.id.0, entry.metadata.previous_repo()?)))
+        }
+        false => None,
+    };
+
+    let plan = Pipeline::new(config)
+        .plan_workspace(roots, previous.as_ref().map(|(_, repo)| repo), metrics)
+        .map_err(|e| format!("Scan failed: {:#}", e))?;
+    Ok(IngestPlanOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        since_snapshot: previous.map(|(id, _)| id),
+        plan,
+    })
+}
+
+/// `vcr snapshot save [path]`
+///
+/// With a repository path, ingests it and records the repo snapshot it was
+/// built from; without one, saves an empty CPG.
+pub fn snapshot_save(config: &ValoriConfig, path: Option<&Path>) -> CommandResult<SnapshotOutput> {
+    use crate::cpg::model::CPG;
+    use crate::pipeline::Pipeline;
+    use crate::report::ReportBuilder;
+    use crate::storage::SnapshotStore;
+
+    let mut store = SnapshotStore::open(&config.snapshot.path)
+        .map_err(|e| format!("Snapshot store open failed: {}", e))?;
+
+    let (snapshot_id, hash) = match path {
+        Some(path) => {
+            if !path.exists() {
+                return Err(CommandError::not_found(format!("Path not found: {}", path.display())));
+            }
+            let metrics = MetricsCollector::new();
+            let output = Pipeline::new(config).run_with_metrics(path, &metrics)
+                .map_err(|e| format!("Ingest failed: {:#}", e))?;
+            let stats = ReportBuilder::from_output(&output).with_metrics(&metrics).build();
+            let id = store.save_with_semantics(&output.cpg_epoch, &output.snapshot, &output.semantic, &output.summaries, stats)
+                .map_err(|e| format!("Snapshot save failed: {}", e))?;
+            (id, output.cpg_epoch.cpg_hash().to_string())
+        }
+        None => {
+            // No repository: an empty CPG (the CLI has no resident epoch)
+            let cpg = CPG::new();
+            let id = store.save(&cpg, 0)
+                .map_err(|e| format!("Snapshot save failed: {}", e))?;
+            (id, cpg.compute_hash())
+        }
+    };
+
+    // Retention runs after every auto-save
+    let pruned = if config.snapshot.auto_save {
+        store.prune(&config.snapshot.retention())
+            .map_err(|e| format!("Snapshot prune failed: {}", e))?
+            .removed
+            .len()
+    } else {
+        0
+    };
+
+    Ok(SnapshotOutput::new(SnapshotResult::Saved {
+        snapshot_id: snapshot_id.0,
+        hash,
+        pruned,
+    }))
+}
+
+/// `vcr snapshot prune`
+pub fn snapshot_prune(config: &ValoriConfig) -> CommandResult<SnapshotOutput> {
+    use crate::storage::SnapshotStore;
+
+    let mut store = SnapshotStore::open(&config.snapshot.path)
+        .map_err(|e| format!("Snapshot store open failed: {}", e))?;
+
+    let report = store.prune(&config.snapshot.retention())
+        .map_err(|e| format!("Snapshot prune failed: {}", e))?;
+
+    Ok(SnapshotOutput::new(SnapshotResult::Pruned {
+        removed: report.removed.iter().map(|id| id.0).collect(),
+        payloads_deleted: report.payloads_deleted.len(),
+        retained: store.entries().len(),
+    }))
+}
+
+/// `vcr snapshot gc [--dry-run]`
+pub fn snapshot_gc(config: &ValoriConfig, dry_run: bool) -> CommandResult<SnapshotOutput> {
+    use crate::storage::SnapshotStore;
+
+    let mut store = SnapshotStore::open(&config.snapshot.path)
+        .map_err(|e| format!("Snapshot store open failed: {}", e))?;
+
+    let report = store.gc(dry_run)
+        .map_err(|e| format!("Snapshot gc failed: {}", e))?;
+
+    Ok(SnapshotOutput::new(SnapshotResult::Collected {
+        dry_run,
+        scanned: report.scanned,
+        referenced: report.referenced,
+        orphaned: report.orphaned,
+        protected: report.protected,
+        bytes_reclaimable: report.bytes_reclaimable,
+        deleted: report.deleted,
+    }))
+}
+
+/// `vcr snapshot load` (id is treated as a path for now)
+pub fn snapshot_load(id: &str) -> CommandResult<SnapshotOutput> {
+    use crate::cpg::CPGEpoch;
+
+    let path = Path::new(id);
+
+    if !path.exists() {
+        return Err(CommandError::not_found(format!("Snapshot not found: {}", id)));
+    }
+
+    // Verifies the content hash and rebuilds indices
+    let epoch = CPGEpoch::from_snapshot(path, None)
+        .map_err(|e| format!("Snapshot load failed: {:#}", e))?;
+
+    Ok(SnapshotOutput::new(SnapshotResult::Loaded {
+        hash: epoch.cpg_hash().to_string(),
+        verified: true,
+        epoch_id: epoch.epoch_id(),
+        restored_from: epoch.restored_from().unwrap_or_default(),
+        nodes: epoch.cpg().nodes.len(),
+        warning: epoch.snapshot_metadata().and_then(|m| m.tool_version_warning()),
+    }))
+}
+
+/// `vcr snapshot verify`
+pub fn snapshot_verify(path: &Path) -> CommandResult<SnapshotOutput> {
+    use crate::storage::CPGSnapshot;
+
+    let metadata = CPGSnapshot::verify(path)
+        .map_err(|e| format!("Snapshot verification failed: {}", e))?;
+
+    Ok(SnapshotOutput::new(SnapshotResult::Verified {
+        hash: metadata.cpg_hash,
+        valid: true,
+        repo_snapshot_hash: metadata.repo_snapshot_hash,
+        tool_version: metadata.tool_version,
+        file_count: metadata.file_count,
+        language_counts: metadata.language_counts,
+    }))
+}
+
+/// `vcr query`: against a restored snapshot, or an empty CPG without one
+///
+/// A snapshot of the `[snapshot]` store answers as it was saved, and its ID
+/// is reported with the result (time travel).
+/// With `timeout`, fails with a `timeout` error once the query has run that long.
+/// With `materialize`, the page's nodes are also resolved to source excerpts
+/// read from that repository, which must still match the snapshot.
+pub fn query(
+    query_file: &Path,
+    snapshot: Option<QuerySnapshot>,
+    explain: bool,
+    timeout: Option<std::time::Duration>,
+    materialize: Option<&Path>,
+) -> CommandResult<QueryOutput> {
+    query_with_metrics(query_file, snapshot, explain, timeout, materialize, &mut MetricsCollector::new())
+}
+
+/// `query`, recording the CPG queried in `metrics`
+pub fn query_with_metrics(
+    query_file: &Path,
+    snapshot: Option<QuerySnapshot>,
+    explain: bool,
+    timeout: Option<std::time::Duration>,
+    materialize: Option<&Path>,
+    metrics: &mut MetricsCollector,
+) -> CommandResult<QueryOutput> {
+    let (_, spec) = read_query_file(query_file)?;
+    run_query(&query_file.display().to_string(), &spec, snapshot, explain, timeout, materialize, metrics)
+}
+
+/// Text and spec of a query file, rejecting it with every validation issue
+fn read_query_file(query_file: &Path) -> CommandResult<(String, crate::query::QuerySpec)> {
+    if !query_file.exists() {
+        return Err(CommandError::not_found(format!("Query file not found: {}", query_file.display())));
+    }
+
+    let text = std::fs::read_to_string(query_file)
+        .map_err(|e| format!("Failed to read query: {}", e))?;
+    let issues = crate::query::validate_query(&text);
+    if !issues.is_empty() {
+        let lines: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        return Err(CommandError::invalid_input(lines.join("\n")));
+    }
+    let spec = crate::query::QuerySpec::from_json(&text)
+        .map_err(|e| CommandError::invalid_input(format!("{:#}", e)))?;
+    Ok((text, spec))
+}
+
+/// `vcr query --check`: validate a query file without running it
+pub fn query_check(query_file: &Path) -> CommandResult<QueryCheckOutput> {
+    if !query_file.exists() {
+        return Err(CommandError::not_found(format!("Query file not found: {}", query_file.display())));
+    }
+
+    let text = std::fs::read_to_string(query_file)
+        .map_err(|e| format!("Failed to read query: {}", e))?;
+    let issues = crate::query::validate_query(&text);
+
+    Ok(QueryCheckOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        query: query_file.display().to_string(),
+        valid: issues.is_empty(),
+        issues,
+    })
+}
+
+/// `vcr query --name`: run the saved query `name` from `query.query_dir`
+pub fn query_named(
+    config: &ValoriConfig,
+    name: &str,
+    snapshot: Option<QuerySnapshot>,
+    explain: bool,
+    timeout: Option<std::time::Duration>,
+    materialize: Option<&Path>,
+) -> CommandResult<QueryOutput> {
+    query_named_with_metrics(config, name, snapshot, explain, timeout, materialize, &mut MetricsCollector::new())
+}
+
+/// `query_named`, recording the CPG queried in `metrics`
+pub fn query_named_with_metrics(
+    config: &ValoriConfig,
+    name: &str,
+    snapshot: Option<QuerySnapshot>,
+    explain: bool,
+    timeout: Option<std::time::Duration>,
+    materialize: Option<&Path>,
+    metrics: &mut MetricsCollector,
+) -> CommandResult<QueryOutput> {
+    let library = query_library(config.query.query_dir.as_deref())?;
+    let saved = library.get(name)
+        .ok_or_else(|| CommandError::not_found(format!("No saved query named '{}'", name)))?;
+
+    run_query(name, &saved.spec, snapshot, explain, timeout, materialize, metrics)
+}
+
+/// `vcr query --list`: saved queries in `query.query_dir`, by name
+pub fn query_list(config: &ValoriConfig) -> CommandResult<QueryListOutput> {
+    let library = query_library(config.query.query_dir.as_deref())?;
+
+    Ok(QueryListOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        queries: library.iter().map(SavedQueryInfo::from).collect(),
+    })
+}
+
+/// Load and validate the configured `query.query_dir`
+pub(crate) fn query_library(dir: Option<&Path>) -> CommandResult<crate::query::QueryLibrary> {
+    let dir = dir
+        .ok_or_else(|| CommandError::invalid_input("No query directory configured (set query.query_dir)"))?;
+    if !dir.is_dir() {
+        return Err(CommandError::not_found(format!("Query directory not found: {}", dir.display())));
+    }
+
+    crate::query::QueryLibrary::load(dir).map_err(|e| CommandError::invalid_input(format!("{:#}", e)))
+}
+
+/// `vcr query --taint`: taint paths between the nodes a taint query selects
+///
+/// With `dedupe`, paths are collapsed into findings; with `baseline`
+/// (an earlier deduplicated output), findings listed there are `known`.
+pub fn query_taint(
+    taint_file: &Path,
+    snapshot: Option<QuerySnapshot>,
+    dedupe: bool,
+    baseline: Option<&Path>,
+) -> CommandResult<TaintOutput> {
+    query_taint_with_metrics(taint_file, snapshot, dedupe, baseline, &mut MetricsCollector::new())
+}
+
+/// `query_taint`, recording the CPG queried in `metrics`
+pub fn query_taint_with_metrics(
+    taint_file: &Path,
+    snapshot: Option<QuerySnapshot>,
+    dedupe: bool,
+    baseline: Option<&Path>,
+    metrics: &mut MetricsCollector,
+) -> CommandResult<TaintOutput> {
+    use crate::analysis::findings::{self, Baseline, StableKeys};
+    use crate::analysis::{TaintAnalysis, TaintSink, TaintSource};
+    use crate::query::{QueryEngine, TaintQuery};
+    use std::collections::HashSet;
+
+    if !taint_file.exists() {
+        return Err(CommandError::not_found(format!("Taint query file not found: {}", taint_file.display())));
+    }
+    if baseline.is_some() && !dedupe {
+        return Err(CommandError::invalid_input("--baseline requires --dedupe"));
+    }
+    let baseline = match baseline {
+        Some(path) if !path.exists() => {
+            return Err(CommandError::not_found(format!("Baseline not found: {}", path.display())));
+        }
+        Some(path) => Some(Baseline::load(path).map_err(|e| CommandError::invalid_input(format!("{:#}", e)))?),
+        None => None,
+    };
+
+    let text = std::fs::read_to_string(taint_file)
+        .map_err(|e| format!("Failed to read taint query: {}", e))?;
+    let query = TaintQuery::from_json(&text)
+        .map_err(|e| CommandError::invalid_input(format!("{:#}", e)))?;
+
+    let epoch = restore_epoch(snapshot)?;
+    metrics.record_cpg_epoch(&epoch);
+    let cpg = epoch.cpg();
+    let engine = QueryEngine::new();
+    let select = |spec| engine.compute(cpg, spec).map_err(|e| format!("Query failed: {:#}", e));
+
+    let sources = select(&query.sources)?.into_iter().map(TaintSource::ExternalInput).collect();
+    let sinks = select(&query.sinks)?.into_iter().map(TaintSink::FunctionCall).collect();
+    let sanitizers: HashSet<_> = match &query.sanitizers {
+        Some(spec) => select(spec)?.into_iter().collect(),
+        None => HashSet::new(),
+    };
+    let analysis = TaintAnalysis::analyze_bounded(cpg, sources, sinks, query.max_depth);
+
+    let (paths, findings) = if dedupe {
+        let mut findings = findings::dedupe(analysis.paths(), &StableKeys::build(cpg, epoch.indices()), &sanitizers);
+        if let Some(baseline) = &baseline {
+            baseline.mark(&mut findings);
+        }
+        (None, Some(findings))
+    } else {
+        let rows = analysis.paths().iter()
+            .map(|path| TaintPathRow {
+                source: path.path.first().map_or(0, |id| id.0),
+                sink: path.path.last().map_or(0, |id| id.0),
+                path: path.path.iter().map(|id| id.0).collect(),
+            })
+            .collect();
+        (Some(rows), None)
+    };
+
+    Ok(TaintOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        query: taint_file.display().to_string(),
+        snapshot_id: snapshot.and_then(|snapshot| snapshot.id()),
+        total_paths: analysis.paths().len(),
+        paths,
+        findings,
+    })
+}
+
+/// Run a parsed query for `vcr query`; `label` names it in the output
+fn run_query(
+    label: &str,
+    spec: &crate::query::QuerySpec,
+    snapshot: Option<QuerySnapshot>,
+    explain: bool,
+    timeout: Option<std::time::Duration>,
+    materialize: Option<&Path>,
+    metrics: &mut MetricsCollector,
+) -> CommandResult<QueryOutput> {
+    use crate::execution::{CancellationToken, Interrupted};
+    use crate::pipeline::Pipeline;
+    use crate::query::{QueryEngine, RepoSources, ResultMaterializer};
+    use crate::storage::SnapshotId;
+
+    let epoch = restore_epoch(snapshot)?;
+    metrics.record_cpg_epoch(&epoch);
+    let token = match timeout {
+        Some(timeout) => CancellationToken::new().with_timeout(timeout),
+        None => CancellationToken::new(),
+    };
+    let mut engine = QueryEngine::new().with_cancellation(token);
+    let (result_id, explanation) = if explain {
+        engine.run_explained(epoch.cpg(), spec).map(|(id, explanation)| (id, Some(explanation)))
+    } else {
+        engine.run(epoch.cpg(), spec).map(|id| (id, None))
+    }.map_err(|e| {
+        let code = match e.downcast_ref::<Interrupted>() {
+            Some(Interrupted::Timeout(_)) => ErrorCode::Timeout,
+            Some(Interrupted::Cancelled(_)) => ErrorCode::Cancelled,
+            None => ErrorCode::Failed,
+        };
+        CommandError::new(code, format!("Query failed: {}", e))
+    })?;
+    let snapshot_id = snapshot.and_then(|snapshot| snapshot.id());
+    if let Some(id) = snapshot_id {
+        engine.tag_snapshot(result_id, SnapshotId(id));
+    }
+    let page = engine.fetch_with(result_id, &spec.options)
+        .map_err(|e| format!("Query failed: {}", e))?;
+    let aggregate = engine.get_result(result_id)
+        .and_then(|stored| stored.aggregate.as_ref())
+        .map(|aggregate| aggregate.value.clone());
+    // The repository is rescanned as the snapshot records it was and must
+    // be the one it was built from; without a snapshot the CPG is empty, so
+    // nothing is read
+    let materialized = match (materialize, epoch.snapshot_metadata()) {
+        (Some(root), Some(metadata)) => {
+            let pipeline = Pipeline::default().with_normalized_line_endings(metadata.normalize_line_endings);
+            let scanned = pipeline.scan(&[root.to_path_buf()])
+                .map_err(|e| format!("Materialization failed: {:#}", e))?;
+            if scanned.snapshot_hash != metadata.repo_snapshot_hash {
+                return Err(format!(
+                    "Materialization failed: {} is not the repository the snapshot was built from \
+                     (changed since it was analyzed, or the snapshot records no repository)",
+                    root.display()
+                ).into());
+            }
+            let sources = RepoSources::new(&scanned, pipeline.source_backend())
+                .with_normalized_line_endings(pipeline.normalizes_line_endings());
+            let results = ResultMaterializer::new(&epoch, &sources).materialize(&page.nodes)
+                .map_err(|e| format!("Materialization failed: {:#}", e))?;
+            Some(results)
+        }
+        (Some(_), None) => Some(Vec::new()),
+        (None, _) => None,
+    };
+
+    Ok(QueryOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        query: label.to_string(),
+        snapshot_id,
+        result_id: page.result_id.0,
+        results: page.nodes.iter().map(|id| id.0).collect(),
+        count: page.nodes.len(),
+        total: page.total,
+        offset: page.offset,
+        explain: explanation,
+        aggregate,
+        materialized,
+    })
+}
+
+/// `vcr explain`
+pub fn explain(result_id: &str) -> CommandResult<ExplainOutput> {
+    // Deterministic provenance trace
+    // For now: placeholder implementation
+    // Full version would:
+    // 1. Load result metadata from store
+    // 2. Trace back through CPG to origin nodes
+    // 3. Output complete provenance chain
+
+    Ok(ExplainOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        result_id: result_id.to_string(),
+        provenance: vec!["TODO: trace origin".to_string()],
+    })
+}
+
+/// `vcr config show`: annotated TOML, not JSON
+pub fn config_show(resolved: &ResolvedConfig) -> CommandResult<String> {
+    resolved.to_annotated_toml()
+        .map(|toml| toml.trim_end().to_string())
+        .map_err(|e| e.to_string().into())
+}
+
+/// `vcr config init`: write the starter vtr.toml into `dir`
+pub fn config_init(dir: &Path, force: bool) -> CommandResult<ConfigInitOutput> {
+    let path = crate::config::loader::write_template(dir, force).map_err(|e| match e {
+        ConfigError::AlreadyExists { .. } => CommandError::invalid_input(e.to_string()),
+        _ => e.to_string().into(),
+    })?;
+
+    Ok(ConfigInitOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.display().to_string(),
+    })
+}
+
+/// `vcr lint dead-functions`
+pub fn lint_dead_functions(path: &Path, config: &ValoriConfig) -> CommandResult<DeadFunctionsOutput> {
+    use crate::analysis::deadcode::{lint_repo, RootSpec};
+
+    if !path.is_dir() {
+        return Err(CommandError::invalid_input(format!("Not a directory: {}", path.display())));
+    }
+
+    let dead = lint_repo(path, &RootSpec::from(&config.analysis))
+        .map_err(|e| format!("Lint failed: {:#}", e))?;
+
+    Ok(DeadFunctionsOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.display().to_string(),
+        count: dead.len(),
+        dead_functions: dead.into_iter().map(|d| DeadFunctionRow {
+            name: d.name,
+            file: d.path.display().to_string(),
+            function_id: d.function_id.0,
+            start: d.source_range.start,
+            end: d.source_range.end,
+            reason: d.reason.to_string(),
+        }).collect(),
+    })
+}
+
+/// `vcr lint taint`: source-to-sink flows across calls, crossing calls as
+/// `[analysis] taint_mode` says
+pub fn lint_taint(path: &Path, config: &ValoriConfig) -> CommandResult<TaintLintOutput> {
+    use crate::analysis::{find_taint_flows, CallRef};
+    use crate::pipeline::Pipeline;
+
+    if !path.is_dir() {
+        return Err(CommandError::invalid_input(format!("Not a directory: {}", path.display())));
+    }
+
+    let output = Pipeline::new(config).run(path).map_err(|e| format!("Lint failed: {:#}", e))?;
+    let mode = config.analysis.taint_mode;
+    let graph = &output.call_graph;
+    let site = |at: CallRef| {
+        let function = graph.function(at.function()).expect("flows only name functions of the graph");
+        let call = &graph.flows(at.function()).expect("every function has flows").calls[at.call];
+        TaintSiteRow {
+            file: graph.path(at.file_id).map(|p| p.display().to_string()).unwrap_or_default(),
+            function: function.name.clone(),
+            function_id: at.function_id.0,
+            callee: call.callee.clone(),
+            start: call.range.start,
+            end: call.range.end,
+        }
+    };
+
+    let mut flows: Vec<TaintFlowRow> = find_taint_flows(graph, &output.summaries, mode).into_iter()
+        .map(|flow| TaintFlowRow { source: site(flow.source), sink: site(flow.sink) })
+        .collect();
+    flows.sort_by(|a, b| {
+        (&a.source.file, a.source.start, &a.sink.file, a.sink.start).cmp(&(&b.source.file, b.source.start, &b.sink.file, b.sink.start))
+    });
+
+    Ok(TaintLintOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.display().to_string(),
+        mode,
+        count: flows.len(),
+        flows,
+    })
+}
+
+/// `vcr lint shadowing`
+///
+/// `deny_shadowing` / `deny_unused` list those categories in `denied` when
+/// they have findings.
+pub fn lint_shadowing(
+    path: &Path,
+    config: &ValoriConfig,
+    deny_shadowing: bool,
+    deny_unused: bool,
+) -> CommandResult<BindingsLintOutput> {
+    use crate::analysis::bindings::lint_repo;
+
+    if !path.is_dir() {
+        return Err(CommandError::invalid_input(format!("Not a directory: {}", path.display())));
+    }
+
+    let findings = lint_repo(path, config)
+        .map_err(|e| format!("Lint failed: {:#}", e))?;
+    let kind = |kind| format!("{:?}", kind).to_lowercase();
+
+    let mut denied = Vec::new();
+    if deny_shadowing && !findings.shadowing.is_empty() {
+        denied.push("shadowing".to_string());
+    }
+    if deny_unused && !findings.unused.is_empty() {
+        denied.push("unused".to_string());
+    }
+
+    Ok(BindingsLintOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.display().to_string(),
+        shadowing: findings.shadowing.into_iter().map(|(file, f)| ShadowingRow {
+            name: f.name,
+            kind: kind(f.kind),
+            file: file.display().to_string(),
+            start: f.range.start,
+            end: f.range.end,
+            shadowed_kind: kind(f.shadowed_kind),
+            shadowed_start: f.shadowed_range.start,
+            shadowed_end: f.shadowed_range.end,
+        }).collect(),
+        unused: findings.unused.into_iter().map(|(file, f)| UnusedBindingRow {
+            name: f.name,
+            kind: kind(f.kind),
+            file: file.display().to_string(),
+            start: f.range.start,
+            end: f.range.end,
+        }).collect(),
+        denied,
+    })
+}
+
+/// `vcr export --format cfg-json`: every CFG of one source file as a graph file
+///
+/// The file is parsed on its own as FileId 1, like `vcr ingest <file>`.
+/// Files with syntax errors are refused.
+pub fn export_cfgs(path: &Path) -> CommandResult<CfgExportOutput> {
+    use crate::io::{MmappedFile, SourceFile};
+    use crate::memory::StringArena;
+    use crate::parse::IncrementalParser;
+    use crate::semantic::io::GraphFile;
+    use crate::semantic::CFGBuilder;
+    use crate::types::{FileId, Language};
+
+    if !path.is_file() {
+        return Err(CommandError::not_found(format!("File not found: {}", path.display())));
+    }
+    let language = path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(Language::from_extension)
+        .ok_or_else(|| CommandError::invalid_input(format!("Unsupported language: {}", path.display())))?;
+
+    let file_id = FileId::new(1);
+    let mmap = MmappedFile::open(path, file_id)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    let parsed = IncrementalParser::new(language)
+        .and_then(|mut parser| parser.parse(&mmap, None))
+        .map_err(|e| format!("Parse failed: {}", e))?;
+    let quality = parsed.quality();
+    if !quality.clean {
+        return Err(format!("Syntax errors in {}: {} error node(s)", path.display(), quality.error_count).into());
+    }
+
+    let mut strings = StringArena::new();
+    let cfgs = CFGBuilder::new(file_id, mmap.bytes())
+        .build_all(&parsed, &mut strings)
+        .map_err(|e| format!("CFG construction failed: {:#}", e))?;
+
+    Ok(CfgExportOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.display().to_string(),
+        graph_file: GraphFile::new(&cfgs, &strings),
+    })
+}
+
+/// Default `vcr export --subgraph --depth`
+pub const SUBGRAPH_DEPTH: usize = 3;
+
+/// `vcr export --subgraph`: ingest a repository and save the neighborhood
+/// of one node as a snapshot file (see `CPG::extract_subgraph`)
+///
+/// The root is named by its stable key (see `analysis::StableKeys`); with
+/// `anonymize`, identifiers are replaced by pseudonyms (`CPG::anonymized`).
+pub fn export_subgraph(
+    config: &ValoriConfig,
+    path: &Path,
+    root: &str,
+    depth: usize,
+    anonymize: bool,
+    output: &Path,
+) -> CommandResult<SubgraphExportOutput> {
+    use crate::analysis::StableKeys;
+    use crate::pipeline::Pipeline;
+    use crate::storage::CPGSnapshot;
+
+    if !path.is_dir() {
+        return Err(CommandError::invalid_input(format!("Not a directory: {}", path.display())));
+    }
+    let ingested = Pipeline::new(config).run(path).map_err(|e| format!("Ingest failed: {:#}", e))?;
+    let epoch = &ingested.cpg_epoch;
+    let node = StableKeys::build(epoch.cpg(), epoch.indices()).find(root)
+        .ok_or_else(|| CommandError::not_found(format!("No node with key {}", root)))?;
+
+    let mut subgraph = epoch.cpg().extract_subgraph(&[node], depth, None);
+    if anonymize {
+        subgraph = subgraph.anonymized();
+    }
+    CPGSnapshot::save(&subgraph, epoch.epoch_id(), output)
+        .map_err(|e| format!("Snapshot save failed: {:#}", e))?;
+
+    Ok(SubgraphExportOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.display().to_string(),
+        root: root.to_string(),
+        depth,
+        anonymized: anonymize,
+        output: output.display().to_string(),
+        nodes: subgraph.nodes.len(),
+        edges: subgraph.edges.len(),
+        hash: subgraph.compute_hash(),
+    })
+}
+
+/// `vcr report complexity`
+pub fn report_complexity(path: &Path) -> CommandResult<ComplexityOutput> {
+    use crate::semantic::cfg::metrics::{report_repo, MetricsSummary};
+
+    if !path.is_dir() {
+        return Err(CommandError::invalid_input(format!("Not a directory: {}", path.display())));
+    }
+
+    let report = report_repo(path).map_err(|e| format!("Report failed: {:#}", e))?;
+
+    let summary = |s: &MetricsSummary| ComplexitySummary {
+        functions: s.function_count,
+        total_complexity: s.total_complexity,
+        max_complexity: s.max_complexity,
+        max_loop_nesting: s.max_loop_nesting,
+    };
+
+    Ok(ComplexityOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.display().to_string(),
+        functions: report.functions.iter().map(|f| FunctionComplexityRow {
+            name: f.name.clone(),
+            file: f.path.display().to_string(),
+            function_id: f.function_id.0,
+            start: f.source_range.start,
+            end: f.source_range.end,
+            cyclomatic_complexity: f.metrics.cyclomatic_complexity,
+            node_count: f.metrics.node_count,
+            edge_count: f.metrics.edge_count,
+            max_loop_nesting: f.metrics.max_loop_nesting,
+        }).collect(),
+        files: report.files.iter().map(|(file, s)| FileComplexityRow {
+            file: file.display().to_string(),
+            summary: summary(s),
+        }).collect(),
+        repo: summary(&report.repo),
+    })
+}
+
+/// `vcr report files`
+///
+/// Ingests `path`, or reads the stats saved with snapshot `snapshot_id` of
+/// the `[snapshot]` store. Exactly one of the two must be given.
+pub fn report_files(
+    config: &ValoriConfig,
+    path: Option<&Path>,
+    snapshot_id: Option<u64>,
+) -> CommandResult<FilesReportOutput> {
+    use crate::pipeline::Pipeline;
+    use crate::report::ReportBuilder;
+    use crate::storage::{SnapshotId, SnapshotStore};
+
+    let (files, semantic) = match (path, snapshot_id) {
+        (Some(path), None) => {
+            if !path.is_dir() {
+                return Err(CommandError::invalid_input(format!("Not a directory: {}", path.display())));
+            }
+            let metrics = MetricsCollector::new();
+            let output = Pipeline::new(config).run_with_metrics(path, &metrics)
+                .map_err(|e| format!("Ingest failed: {:#}", e))?;
+            (ReportBuilder::from_output(&output).with_metrics(&metrics).build(), Some(output.semantic.report()))
+        }
+        (None, Some(id)) => {
+            let store = SnapshotStore::open(&config.snapshot.path)
+                .map_err(|e| format!("Snapshot store open failed: {}", e))?;
+            let entry = store.get(SnapshotId(id))
+                .ok_or_else(|| CommandError::not_found(format!("Snapshot not found: {}", id)))?;
+            if entry.metadata.file_stats.is_empty() && entry.metadata.file_count > 0 {
+                return Err(CommandError::invalid_input(format!(
+                    "Snapshot {} has no per-file stats (written by storage version {})",
+                    id, entry.metadata.version
+                )));
+            }
+            (entry.metadata.file_stats.clone(), None)
+        }
+        _ => return Err(CommandError::invalid_input("Give either a path or a snapshot ID")),
+    };
+
+    Ok(FilesReportOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: path.map(|p| p.display().to_string()),
+        snapshot_id,
+        files,
+        semantic,
+    })
+}
+
+/// `vcr compare`
+///
+/// Compares two ingested paths, or snapshot `snapshot_id` of the
+/// `[snapshot]` store (the base) against one ingested path.
+pub fn compare(
+    config: &ValoriConfig,
+    paths: &[PathBuf],
+    snapshot_id: Option<u64>,
+) -> CommandResult<CompareOutput> {
+    use crate::compare::{RepoComparison, RepoSummary};
+    use crate::pipeline::Pipeline;
+    use crate::storage::{SnapshotId, SnapshotStore};
+
+    let summarize = |path: &Path| -> CommandResult<RepoSummary> {
+        if !path.is_dir() {
+            return Err(CommandError::invalid_input(format!("Not a directory: {}", path.display())));
+        }
+        let output = Pipeline::new(config).run(path)
+            .map_err(|e| format!("Ingest of {} failed: {:#}", path.display(), e))?;
+        Ok(RepoSummary::from_output(&output))
+    };
+
+    let (base, head, before) = match (paths, snapshot_id) {
+        ([base, head], None) => (base.display().to_string(), head, summarize(base)?),
+        ([head], Some(id)) => {
+            let store = SnapshotStore::open(&config.snapshot.path)
+                .map_err(|e| format!("Snapshot store open failed: {}", e))?;
+            let entry = store.get(SnapshotId(id))
+                .ok_or_else(|| CommandError::not_found(format!("Snapshot not found: {}", id)))?;
+            let summary = entry.metadata.comparable_functions().cloned().ok_or_else(|| CommandError::invalid_input(format!(
+                "Snapshot {} has no comparable function summaries (written by storage version {})",
+                id, entry.metadata.version
+            )))?;
+            (format!("snapshot:{}", id), head, summary)
+        }
+        _ => return Err(CommandError::invalid_input("Give two paths, or a path and a snapshot ID")),
+    };
+    let after = summarize(head)?;
+
+    Ok(CompareOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        base,
+        head: head.display().to_string(),
+        comparison: RepoComparison::between(&before, &after),
+    })
+}
+
+/// `vcr golden check`: rebuild the fixtures and fail on any drift
+pub fn golden_check(dir: &Path) -> CommandResult<GoldenOutput> {
+    golden(dir, false)
+}
+
+/// `vcr golden bless`: rebuild the fixtures and rewrite the manifest
+pub fn golden_bless(dir: &Path) -> CommandResult<GoldenOutput> {
+    golden(dir, true)
+}
+
+fn golden(dir: &Path, bless: bool) -> CommandResult<GoldenOutput> {
+    use crate::verify::golden::{self, GoldenManifest};
+
+    if !dir.is_dir() {
+        return Err(CommandError::not_found(format!("Golden directory not found: {}", dir.display())));
+    }
+
+    let changes = if bless { golden::bless(dir) } else { golden::check(dir) }
+        .map_err(|e| format!("Golden {} failed: {:#}", if bless { "bless" } else { "check" }, e))?;
+
+    if !bless && !changes.is_empty() {
+        return Err(CommandError {
+            errors: changes.iter().map(|m| m.to_string()).collect(),
+            ..CommandError::new(
+                ErrorCode::Failed,
+                format!("Golden check failed: {} hash(es) diverged (run `vcr golden bless` if intended)", changes.len()),
+            )
+        });
+    }
+
+    let manifest = GoldenManifest::load(dir).map_err(|e| format!("{:#}", e))?;
+    Ok(GoldenOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        path: dir.display().to_string(),
+        fixtures: manifest.fixtures.into_keys().collect(),
+        changes: changes.into_iter().map(|m| GoldenChangeRow {
+            fixture: m.fixture,
+            artifact: m.artifact,
+            old: m.expected,
+            new: m.actual,
+        }).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// Serialize as the binary would and parse back
+    fn emitted<T: Serialize>(output: CommandResult<T>) -> Value {
+        let value: Value = serde_json::from_str(&to_json(&output.unwrap())).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        value
+    }
+
+    fn temp_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() { let x = 1; if x > 0 { used(); } }\nfn used() {}\nfn unused() {}\n").unwrap();
+        dir
+    }
+
+    fn snapshot_config(dir: &TempDir) -> ValoriConfig {
+        let mut config = ValoriConfig::default();
+        config.snapshot.path = dir.path().join("snapshots");
+        config
+    }
+
+    #[test]
+    fn test_ingest_directory() {
+        let dir = temp_repo();
+        let out = emitted(ingest(dir.path(), &ValoriConfig::default(), false));
+
+        assert_eq!(out["status"], "success");
+        assert_eq!(out["files"], 1);
+        assert!(out["nodes"].as_u64().unwrap() > 0);
+        assert_eq!(out["cpg_hash"].as_str().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_ingest_verified() {
+        let dir = temp_repo();
+        let plain = emitted(ingest(dir.path(), &ValoriConfig::default(), false));
+        let verified = emitted(ingest(dir.path(), &ValoriConfig::default(), true));
+
+        assert_eq!(verified["determinism_verified"], true);
+        assert_eq!(verified["cpg_hash"], plain["cpg_hash"]);
+        assert!(verified.get("nodes").is_none());
+        assert!(verified.get("files").is_none());
+    }
+
+    #[test]
+    fn test_ingest_single_file() {
+        let dir = temp_repo();
+        let out = emitted(ingest(&dir.path().join("main.rs"), &ValoriConfig::default(), false));
+
+        assert_eq!(out["nodes"], 3);
+        assert!(out.get("snapshot_hash").is_none());
+        assert!(ingest(&dir.path().join("main.rs"), &ValoriConfig::default(), true).is_err());
+
+        std::fs::write(dir.path().join("script.py"), "def main():\n    pass\n").unwrap();
+        let err = ingest(&dir.path().join("script.py"), &ValoriConfig::default(), false).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_ingest_reports_parse_errors() {
+        use crate::config::ParseErrorPolicy;
+
+        let dir = temp_repo();
+        std::fs::write(dir.path().join("broken.rs"), "fn b() { let y = ; }\n").unwrap();
+        let mut config = ValoriConfig::default();
+
+        let out = emitted(ingest(dir.path(), &config, false));
+        assert_eq!(out["parse_errors"][0]["file"], "broken.rs");
+        assert_eq!(out["parse_errors"][0]["skipped"], true);
+        assert!(out["parse_errors"][0]["error_count"].as_u64().unwrap() > 0);
+        assert!(emitted(ingest(&dir.path().join("main.rs"), &config, false)).get("parse_errors").is_none());
+
+        config.parse.on_parse_error = ParseErrorPolicy::IncludeBestEffort;
+        assert_eq!(emitted(ingest(dir.path(), &config, false))["parse_errors"][0]["skipped"], false);
+
+        config.parse.on_parse_error = ParseErrorPolicy::Fail;
+        assert_eq!(ingest(dir.path(), &config, false).unwrap_err().code, ErrorCode::Failed);
+        assert_eq!(ingest(&dir.path().join("broken.rs"), &config, false).unwrap_err().code, ErrorCode::Failed);
+    }
+
+    #[test]
+    fn test_ingest_missing_path() {
+        let err = ingest(Path::new("/nonexistent/repo"), &ValoriConfig::default(), false).unwrap_err();
+        assert_eq!(err.message, "Path not found: /nonexistent/repo");
+        assert_eq!(err.code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_ingest_workspace() {
+        let dir = TempDir::new().unwrap();
+        for root in ["a", "b"] {
+            std::fs::create_dir_all(dir.path().join(root)).unwrap();
+            std::fs::write(dir.path().join(root).join("lib.rs"), "fn f() {}\n").unwrap();
+        }
+        let roots = vec![dir.path().join("b"), dir.path().join("a")];
+        let config = ValoriConfig::default();
+
+        let out = emitted(ingest_workspace(&roots, &config, false));
+        assert_eq!(out["files"], 2);
+        let sorted = emitted(ingest_workspace(&[roots[1].clone(), roots[0].clone()], &config, false));
+        assert_eq!(out["snapshot_hash"], sorted["snapshot_hash"]);
+
+        let missing = vec![dir.path().join("a"), dir.path().join("missing")];
+        assert_eq!(ingest_workspace(&missing, &config, false).unwrap_err().code, ErrorCode::NotFound);
+        let file = vec![dir.path().join("a/lib.rs")];
+        assert_eq!(ingest_workspace(&file, &config, false).unwrap_err().code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_ingest_records_metrics() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn f() {}\nfn g() { f(); }\n").unwrap();
+        let mut metrics = MetricsCollector::new();
+
+        let out = emitted(ingest_with_metrics(dir.path(), &ValoriConfig::default(), false, &mut metrics));
+        assert!(metrics.scan_duration().is_some());
+        assert_eq!(metrics.parse_time_stats().count, 1);
+        assert_eq!(metrics.cpg_stats().unwrap().total_nodes, out["nodes"]);
+        assert!(metrics.total_epoch_memory() > 0);
+        let semantic = metrics.semantic_report().unwrap();
+        assert_eq!(semantic.files.len(), 1);
+        assert_eq!(semantic.files[0].functions, 2);
+        assert_eq!(metrics.to_json()["semantic"]["files"][0]["function_hashes"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_snapshot_save_and_prune() {
+        let dir = TempDir::new().unwrap();
+        let mut config = snapshot_config(&dir);
+        config.snapshot.auto_save = false;
+
+        let first = emitted(snapshot_save(&config, None));
+        let second = emitted(snapshot_save(&config, None));
+        assert_eq!(first["snapshot_id"], 1);
+        assert_eq!(second["snapshot_id"], 2);
+        assert_eq!(second["pruned"], 0);
+
+        config.snapshot.max_snapshots = Some(1);
+        let pruned = emitted(snapshot_prune(&config));
+        assert_eq!(pruned["removed"], json!([1]));
+        assert_eq!(pruned["retained"], 1);
+    }
+
+    #[test]
+    fn test_snapshot_gc_keeps_referenced_payloads() {
+        let dir = TempDir::new().unwrap();
+        let config = snapshot_config(&dir);
+        emitted(snapshot_save(&config, None));
+        std::fs::write(config.snapshot.path.join("payloads/orphan.cpg"), b"{}").unwrap();
+
+        // The orphan is newer than the safety window
+        let dry = emitted(snapshot_gc(&config, true));
+        assert_eq!(dry["dry_run"], true);
+        assert_eq!((dry["scanned"].as_u64(), dry["referenced"].as_u64()), (Some(2), Some(1)));
+        assert_eq!(dry["protected"], 1);
+        assert_eq!(dry["orphaned"], json!([]));
+
+        let collected = emitted(snapshot_gc(&config, false));
+        assert_eq!(collected["deleted"], json!([]));
+        assert!(config.snapshot.path.join("payloads/orphan.cpg").exists());
+    }
+
+    #[test]
+    fn test_snapshot_save_records_repo() {
+        use crate::storage::{SnapshotStore, TOOL_VERSION};
+
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::write(repo.join("a.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(repo.join("b.rs"), "fn b() {}\n").unwrap();
+        let config = snapshot_config(&dir);
+
+        emitted(snapshot_save(&config, Some(&repo)));
+        let store = SnapshotStore::open(&config.snapshot.path).unwrap();
+        let metadata = &store.latest().unwrap().metadata;
+        let ingested = emitted(ingest(&repo, &config, false));
+        assert_eq!(metadata.repo_snapshot_hash, ingested["snapshot_hash"]);
+        assert_eq!(metadata.tool_version, TOOL_VERSION);
+        assert_eq!(metadata.file_count, 2);
+        assert_eq!(metadata.language_counts, BTreeMap::from([("rust".to_string(), 2)]));
+
+        let missing = snapshot_save(&config, Some(&dir.path().join("missing"))).unwrap_err();
+        assert_eq!(missing.code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_snapshot_verify_prints_provenance_and_load_warns() {
+        use crate::pipeline::Pipeline;
+        use crate::storage::CPGSnapshot;
+
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        let path = dir.path().join("snapshot.cpg");
+        let output = Pipeline::default().run(dir.path()).unwrap();
+        CPGSnapshot::save_with_repo(output.cpg_epoch.cpg(), 1, &output.snapshot, &path).unwrap();
+
+        let verified = emitted(snapshot_verify(&path));
+        assert_eq!(verified["repo_snapshot_hash"], output.snapshot.snapshot_hash);
+        assert_eq!(verified["file_count"], 1);
+        assert_eq!(verified["language_counts"], json!({"rust": 1}));
+        assert!(emitted(snapshot_load(path.to_str().unwrap())).get("warning").is_none());
+
+        // Same content, written by a future major version
+        let mut file: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        file["metadata"]["tool_version"] = json!("999.0.0");
+        std::fs::write(&path, file.to_string()).unwrap();
+        let loaded = emitted(snapshot_load(path.to_str().unwrap()));
+        assert!(loaded["warning"].as_str().unwrap().contains("999.0.0"), "{}", loaded);
+    }
+
+    #[test]
+    fn test_snapshot_verify_and_load() {
+        use crate::cpg::model::CPG;
+        use crate::storage::CPGSnapshot;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("snapshot.cpg");
+        CPGSnapshot::save(&CPG::new(), 4, &path).unwrap();
+
+        let hash = CPG::new().compute_hash();
+        assert_eq!(emitted(snapshot_verify(&path))["hash"], hash);
+        let loaded = emitted(snapshot_load(path.to_str().unwrap()));
+        assert_eq!(loaded["verified"], true);
+        assert_eq!(loaded["restored_from"], 4);
+        assert_eq!(loaded["epoch_id"], 5);
+    }
+
+    #[test]
+    fn test_query_restored_snapshot() {
+        use crate::pipeline::Pipeline;
+        use crate::storage::CPGSnapshot;
+
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() { b(); }\nfn b() {}\n").unwrap();
+        let snapshot = dir.path().join("snapshot.cpg");
+        let query_file = dir.path().join("query.json");
+        std::fs::write(&query_file, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+
+        let output = Pipeline::default().run(dir.path()).unwrap();
+        let epoch_id = output.cpg_epoch.epoch_id();
+        CPGSnapshot::save(output.cpg_epoch.cpg(), epoch_id, &snapshot).unwrap();
+        drop(output);
+
+        let out = emitted(query(&query_file, Some(QuerySnapshot::File(&snapshot)), false, None, None));
+        assert_eq!(out["count"], 2);
+
+        let explained = emitted(query(&query_file, Some(QuerySnapshot::File(&snapshot)), true, None, None));
+        assert_eq!(explained["results"], out["results"]);
+        assert_eq!(explained["explain"]["stages"][0]["operator"], "find_nodes");
+        assert_eq!(explained["explain"]["stages"][0]["actual_rows"], 2);
+        assert_eq!(explained["explain"]["result_count"], 2);
+        assert_eq!(query(&query_file, Some(QuerySnapshot::File(&dir.path().join("missing.cpg"))), false, None, None).unwrap_err().code, ErrorCode::NotFound);
+
+        std::fs::write(&query_file, r#"{"pipeline": [{"find": "Function"}, {"count": true}]}"#).unwrap();
+        let counted = emitted(query(&query_file, Some(QuerySnapshot::File(&snapshot)), true, None, None));
+        assert_eq!(counted["aggregate"], json!({"count": 2}));
+        assert_eq!(counted["results"], json!([]));
+        assert_eq!(counted["explain"]["stages"][1]["operator"], "count");
+        assert_eq!(counted["explain"]["stages"][1]["input_rows"], 2);
+
+        let timed_out = query(&query_file, Some(QuerySnapshot::File(&snapshot)), false, Some(std::time::Duration::ZERO), None).unwrap_err();
+        assert_eq!(timed_out.code, ErrorCode::Timeout);
+    }
+
+    #[test]
+    fn test_query_materializes_against_repo() {
+        use crate::pipeline::Pipeline;
+        use crate::storage::CPGSnapshot;
+
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::write(repo.join("é.rs"), "/* ☃ */ fn snow() {}\n").unwrap();
+        let snapshot = dir.path().join("snapshot.cpg");
+        let output = Pipeline::default().run(&repo).unwrap();
+        CPGSnapshot::save_with_repo(output.cpg_epoch.cpg(), 1, &output.snapshot, &snapshot).unwrap();
+        let query_file = dir.path().join("query.json");
+        std::fs::write(&query_file, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+
+        let out = emitted(query(&query_file, Some(QuerySnapshot::File(&snapshot)), false, None, Some(&repo)));
+        let materialized = &out["materialized"][0];
+        assert_eq!(materialized["node_id"], out["results"][0]);
+        assert_eq!(materialized["file_path"], "é.rs");
+        assert_eq!((&materialized["line"], &materialized["column"]), (&json!(1), &json!(9)));
+        assert_eq!(materialized["excerpt"], "fn snow() {}");
+        assert_eq!(materialized["label"], "snow");
+        assert!(emitted(query(&query_file, Some(QuerySnapshot::File(&snapshot)), false, None, None)).get("materialized").is_none());
+
+        std::fs::write(repo.join("é.rs"), "fn snow() {}\n").unwrap();
+        let stale = query(&query_file, Some(QuerySnapshot::File(&snapshot)), false, None, Some(&repo)).unwrap_err();
+        assert!(stale.message.contains("not the repository the snapshot was built from"), "{}", stale.message);
+    }
+
+    #[test]
+    fn test_query_materializes_with_recorded_line_endings() {
+        use crate::pipeline::Pipeline;
+        use crate::storage::CPGSnapshot;
+
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(&repo).unwrap();
+        std::fs::write(repo.join("lib.rs"), "// crlf\r\nfn crlf() {}\r\n").unwrap();
+        let snapshot = dir.path().join("snapshot.cpg");
+        let output = Pipeline::default().with_normalized_line_endings(true).run(&repo).unwrap();
+        CPGSnapshot::save_with_repo(output.cpg_epoch.cpg(), 1, &output.snapshot, &snapshot).unwrap();
+        assert!(CPGSnapshot::verify(&snapshot).unwrap().normalize_line_endings);
+        let query_file = dir.path().join("query.json");
+        std::fs::write(&query_file, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+
+        let out = emitted(query(&query_file, Some(QuerySnapshot::File(&snapshot)), false, None, Some(&repo)));
+        let materialized = &out["materialized"][0];
+        assert_eq!((&materialized["line"], &materialized["column"]), (&json!(2), &json!(1)));
+        assert_eq!(materialized["excerpt"], "fn crlf() {}");
+    }
+
+    #[test]
+    fn test_snapshot_load_missing() {
+        assert_eq!(snapshot_load("/nonexistent/snapshot.cpg").unwrap_err().code, ErrorCode::NotFound);
+        assert!(snapshot_verify(Path::new("/nonexistent/snapshot.cpg")).is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let dir = TempDir::new().unwrap();
+        let query_file = dir.path().join("query.json");
+        std::fs::write(&query_file, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+
+        let out = emitted(query(&query_file, None, false, None, None));
+        assert_eq!(out["results"], json!([]));
+        assert_eq!(out["count"], 0);
+        assert!(out.get("explain").is_none());
+
+        std::fs::write(&query_file, "not json").unwrap();
+        assert_eq!(query(&query_file, None, false, None, None).unwrap_err().code, ErrorCode::InvalidInput);
+        assert_eq!(query(&dir.path().join("missing.json"), None, false, None, None).unwrap_err().code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_query_check() {
+        let dir = TempDir::new().unwrap();
+        let query_file = dir.path().join("query.json");
+        std::fs::write(&query_file, r#"{"pipeline": [{"find": "Function"}, {"count": true}]}"#).unwrap();
+        let valid = query_check(&query_file).unwrap();
+        assert!(valid.valid && valid.issues.is_empty());
+
+        std::fs::write(&query_file, r#"{"pipeline": [{"follow": "Calls"}, {"count": true}, {"filter": "Fn"}]}"#).unwrap();
+        let out = emitted(query_check(&query_file));
+        assert_eq!(out["valid"], false);
+        let codes: Vec<&str> = out["issues"].as_array().unwrap().iter().map(|i| i["code"].as_str().unwrap()).collect();
+        assert_eq!(codes, ["no_input", "misplaced_aggregate", "unknown_variant"]);
+        assert_eq!(out["issues"][2]["stage"], 2);
+        assert_eq!(out["issues"][2]["location"], "pipeline[2].filter");
+
+        // Running the same file fails with every issue
+        let error = query(&query_file, None, false, None, None).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidInput);
+        assert_eq!(error.message.lines().count(), 3, "{}", error.message);
+        assert_eq!(query_check(&dir.path().join("missing.json")).unwrap_err().code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_query_by_name_matches_query_by_file() {
+        use crate::pipeline::Pipeline;
+        use crate::storage::CPGSnapshot;
+
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() { b(); }\nfn b() {}\n").unwrap();
+        let snapshot = dir.path().join("snapshot.cpg");
+        let output = Pipeline::default().run(dir.path()).unwrap();
+        CPGSnapshot::save(output.cpg_epoch.cpg(), output.cpg_epoch.epoch_id(), &snapshot).unwrap();
+        drop(output);
+
+        let queries = dir.path().join("queries");
+        std::fs::create_dir(&queries).unwrap();
+        let saved = r#"{"name": "functions", "description": "All functions", "pipeline": [{"find": "Function"}], "limit": 1}"#;
+        std::fs::write(queries.join("functions.json"), saved).unwrap();
+        std::fs::write(queries.join("count.json"), r#"{"name": "count", "description": "How many functions", "pipeline": [{"find": "Function"}, {"count": true}]}"#).unwrap();
+
+        let mut config = ValoriConfig::default();
+        assert_eq!(query_list(&config).unwrap_err().code, ErrorCode::InvalidInput);
+        config.query.query_dir = Some(queries.clone());
+
+        let listed = emitted(query_list(&config));
+        assert_eq!(listed["queries"][0]["name"], "count");
+        assert_eq!(listed["queries"][1]["description"], "All functions");
+
+        let by_name = emitted(query_named(&config, "functions", Some(QuerySnapshot::File(&snapshot)), false, None, None));
+        let by_file = emitted(query(&queries.join("functions.json"), Some(QuerySnapshot::File(&snapshot)), false, None, None));
+        assert_eq!(by_name["query"], "functions");
+        for field in ["results", "count", "total", "offset"] {
+            assert_eq!(by_name[field], by_file[field], "{}", field);
+        }
+        assert_eq!(by_name["total"], 2);
+        assert_eq!(emitted(query_named(&config, "count", Some(QuerySnapshot::File(&snapshot)), false, None, None))["aggregate"], json!({"count": 2}));
+        assert_eq!(query_named(&config, "missing", None, false, None, None).unwrap_err().code, ErrorCode::NotFound);
+
+        std::fs::write(queries.join("broken.json"), "{").unwrap();
+        let err = query_list(&config).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert!(err.message.contains("broken.json"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_export_cfgs_loads_back() {
+        use crate::memory::StringArena;
+        use crate::semantic::io::load_cfgs;
+
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("a.rs");
+        std::fs::write(&source, "fn a() { let x = 1; b(x); }\nfn b(y: i32) { if y > 0 { a(); } }\n").unwrap();
+
+        let out = emitted(export_cfgs(&source));
+        let graph_file = &out["graph_file"];
+        assert_eq!(graph_file["header"]["kind"], "cfg");
+        assert_eq!(graph_file["header"]["functions"], 2);
+        assert_eq!(graph_file["functions"][1]["name"], "b");
+
+        // `graph_file` is a graph file as `save_cfgs` writes it
+        let exported = dir.path().join("cfgs.json");
+        std::fs::write(&exported, graph_file.to_string()).unwrap();
+        let cfgs = load_cfgs(&exported, &mut StringArena::new()).unwrap();
+        assert_eq!(cfgs.iter().map(|cfg| cfg.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+
+        std::fs::write(&source, "fn a( {").unwrap();
+        assert_eq!(export_cfgs(&source).unwrap_err().code, ErrorCode::Failed);
+        assert_eq!(export_cfgs(&dir.path().join("missing.rs")).unwrap_err().code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn test_query_taint_dedupe_with_baseline() {
+        use crate::pipeline::Pipeline;
+        use crate::storage::CPGSnapshot;
+
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        let handler = "fn handle(flag: bool) {\n    let mut a = 1;\n    if flag { a = 2; } else { a = 3; }\n    sink(a);\n}\n";
+        let snapshot_of = |source: &str, name: &str| {
+            std::fs::write(repo.join("lib.rs"), source).unwrap();
+            let output = Pipeline::default().run(&repo).unwrap();
+            let path = dir.path().join(name);
+            CPGSnapshot::save(output.cpg_epoch.cpg(), output.cpg_epoch.epoch_id(), &path).unwrap();
+            path
+        };
+        let before = snapshot_of(handler, "before.cpg");
+        let after = snapshot_of(&format!("fn helper(x: i32) -> i32 {{\n    let y = x;\n    y\n}}\n\n{}", handler), "after.cpg");
+        let taint_file = dir.path().join("taint.json");
+        let all_values = r#"{"pipeline": [{"find": "DfgValue"}]}"#;
+        std::fs::write(&taint_file, format!(r#"{{"sources": {all_values}, "sinks": {all_values}}}"#)).unwrap();
+
+        let paths = emitted(query_taint(&taint_file, Some(QuerySnapshot::File(&before)), false, None));
+        assert!(paths.get("findings").is_none());
+        let first = emitted(query_taint(&taint_file, Some(QuerySnapshot::File(&before)), true, None));
+        assert!(first.get("paths").is_none());
+        assert_eq!(first["total_paths"], paths["total_paths"]);
+        let findings = first["findings"].as_array().unwrap();
+        assert!(!findings.is_empty());
+        let collapsed: u64 = findings.iter().map(|f| f["path_count"].as_u64().unwrap()).sum();
+        assert_eq!(collapsed, paths["paths"].as_array().unwrap().len() as u64);
+        assert_eq!(emitted(query_taint(&taint_file, Some(QuerySnapshot::File(&before)), true, None)), first);
+
+        let baseline = dir.path().join("baseline.json");
+        std::fs::write(&baseline, first.to_string()).unwrap();
+        let rerun = emitted(query_taint(&taint_file, Some(QuerySnapshot::File(&after)), true, Some(&baseline)));
+        let known: Vec<&Value> = rerun["findings"].as_array().unwrap().iter().filter(|f| f["known"] == true).collect();
+        assert_eq!(known.len(), findings.len());
+        assert!(rerun["findings"].as_array().unwrap().iter().any(|f| f["known"] == false));
+
+        assert_eq!(query_taint(&taint_file, Some(QuerySnapshot::File(&before)), false, Some(&baseline)).unwrap_err().code, ErrorCode::InvalidInput);
+        std::fs::write(&taint_file, r#"{"sources": {"pipeline": [{"count": true}]}, "sinks": {"pipeline": []}}"#).unwrap();
+        assert_eq!(query_taint(&taint_file, None, true, None).unwrap_err().code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_explain() {
+        let out = emitted(explain("say \"hi\"\n"));
+        assert_eq!(out["result_id"], "say \"hi\"\n");
+        assert_eq!(out["provenance"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_config_init_and_show() {
+        let dir = TempDir::new().unwrap();
+        let out = emitted(config_init(dir.path(), false));
+        let path = PathBuf::from(out["path"].as_str().unwrap());
+        assert!(path.exists());
+        assert_eq!(config_init(dir.path(), false).unwrap_err().code, ErrorCode::InvalidInput);
+        assert!(config_init(dir.path(), true).is_ok());
+
+        let resolved = load_config(Some(&path)).unwrap();
+        let shown = config_show(&resolved).unwrap();
+        assert!(shown.starts_with("# Effective configuration"));
+    }
+
+    #[test]
+    fn test_load_config_collects_errors() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vtr.toml");
+        std::fs::write(&path, "[bogus]\nkey = 1\n").unwrap();
+
+        let err = load_config(Some(&path)).unwrap_err();
+        assert_eq!(err.message, "Invalid configuration (1 error(s))");
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.code, ErrorCode::InvalidConfig);
+
+        let value: Value = serde_json::from_str(&to_json(&ErrorOutput::from(&err))).unwrap();
+        assert_eq!(value["code"], "invalid_config");
+        assert_eq!(value["errors"].as_array().unwrap().len(), 1);
+        assert_eq!(value["fatal"], true);
+    }
+
+    #[test]
+    fn test_lint_taint() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() { let line = read_line(); run(line); }\nfn run(cmd: String) { exec(cmd); }\n").unwrap();
+        let mut config = ValoriConfig::default();
+
+        let out = emitted(lint_taint(dir.path(), &config));
+        assert_eq!(out["mode"], "summary");
+        assert_eq!(out["count"], 1);
+        assert_eq!(out["flows"][0]["source"]["callee"], "read_line");
+        assert_eq!(out["flows"][0]["source"]["function"], "main");
+        assert_eq!(out["flows"][0]["sink"]["callee"], "exec");
+        assert_eq!(out["flows"][0]["sink"]["function"], "run");
+
+        config.analysis.taint_mode = crate::config::TaintMode::Inline;
+        let inline = emitted(lint_taint(dir.path(), &config));
+        assert_eq!(inline["mode"], "inline");
+        assert_eq!(inline["flows"], out["flows"]);
+        assert!(lint_taint(&dir.path().join("main.rs"), &config).is_err());
+    }
+
+    #[test]
+    fn test_lint_dead_functions() {
+        let dir = temp_repo();
+        let out = emitted(lint_dead_functions(dir.path(), &ValoriConfig::default()));
+
+        assert_eq!(out["count"], 1);
+        assert_eq!(out["dead_functions"][0]["name"], "unused");
+        assert_eq!(out["dead_functions"][0]["file"], "main.rs");
+        assert!(lint_dead_functions(&dir.path().join("main.rs"), &ValoriConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_lint_shadowing_denies_by_category() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn f(n: u8) -> u8 { let n = n + 1; let spare = 0; n }\n").unwrap();
+        let config = ValoriConfig::default();
+
+        let out = emitted(lint_shadowing(dir.path(), &config, false, false));
+        assert_eq!(out["shadowing"][0]["name"], "n");
+        assert_eq!(out["shadowing"][0]["kind"], "variable");
+        assert_eq!(out["shadowing"][0]["shadowed_kind"], "parameter");
+        assert_eq!(out["unused"][0]["name"], "spare");
+        assert_eq!(out["denied"], json!([]));
+
+        let denied = emitted(lint_shadowing(dir.path(), &config, true, true));
+        assert_eq!(denied["denied"], json!(["shadowing", "unused"]));
+        assert!(lint_shadowing(&dir.path().join("lib.rs"), &config, false, false).is_err());
+    }
+
+    #[test]
+    fn test_ingest_buffer_exit_codes() {
+        use crate::types::Language;
+        let config = ValoriConfig::default();
+        let ingest = |source: &str, deny_unused: bool| {
+            ingest_buffer(source.as_bytes().to_vec(), Language::Rust, &config, false, false, deny_unused, &mut MetricsCollector::new())
+        };
+
+        let clean = ingest("fn f(n: u8) -> u8 { let spare = 0; if n > 0 { n } else { 1 } }\n", false).unwrap();
+        assert_eq!(clean.exit_code(), 0);
+        assert!(clean.lint.is_none());
+        let out = emitted(Ok(clean));
+        assert_eq!(out["functions"][0]["name"], "f");
+        assert_eq!(out["functions"][0]["cyclomatic_complexity"], 2);
+        assert_eq!(out["functions"][0]["cfg_hash"].as_str().unwrap().len(), 64);
+
+        let denied = ingest("fn f() { let spare = 0; }\n", true).unwrap();
+        assert_eq!(denied.denied, vec!["unused"]);
+        assert_eq!(denied.exit_code(), output::EXIT_DENIED);
+
+        // A syntax error outranks a denied finding
+        let broken = ingest("fn f() { let spare = 0; }\nfn g( {\n", true).unwrap();
+        assert!(!broken.parse.clean);
+        assert!(!broken.analyzed);
+        assert_eq!(broken.exit_code(), output::EXIT_PARSE_FAILED);
+    }
+
+    #[test]
+    fn test_report_complexity() {
+        let dir = temp_repo();
+        let out = emitted(report_complexity(dir.path()));
+
+        assert_eq!(out["functions"][0]["name"], "main");
+        assert_eq!(out["functions"][0]["cyclomatic_complexity"], 2);
+        assert_eq!(out["repo"]["functions"], 3);
+        assert_eq!(out["files"][0]["file"], "main.rs");
+        assert_eq!(out["files"][0]["total_complexity"], 4);
+    }
+
+    #[test]
+    fn test_report_files_live_and_from_snapshot() {
+        let dir = TempDir::new().unwrap();
+        let repo = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/calls");
+        let config = snapshot_config(&dir);
+
+        let live = emitted(report_files(&config, Some(&repo), None));
+        assert_eq!(live["files"][0]["path"], "src/main.rs");
+        assert_eq!(live["files"][1]["path"], "src/util.rs");
+        assert_eq!(live["files"][0]["parse_clean"], true);
+        assert!(live["files"][0]["parse_time_us"].is_u64());
+        assert!(live.get("snapshot_id").is_none());
+        assert_eq!(live["semantic"]["files"].as_array().unwrap().len(), 2);
+        assert_eq!(live["semantic"]["files"][0]["function_hashes"].as_array().unwrap().len(), 2);
+
+        emitted(snapshot_save(&config, Some(&repo)));
+        let saved = emitted(report_files(&config, None, Some(1)));
+        assert_eq!(saved["snapshot_id"], 1);
+        assert!(saved.get("semantic").is_none());
+        let strip = |out: &Value| -> Vec<Value> {
+            out["files"].as_array().unwrap().iter()
+                .map(|f| { let mut f = f.clone(); f.as_object_mut().unwrap().remove("parse_time_us"); f })
+                .collect()
+        };
+        assert_eq!(strip(&saved), strip(&live));
+
+        assert_eq!(report_files(&config, None, Some(9)).unwrap_err().code, ErrorCode::NotFound);
+        assert_eq!(report_files(&config, None, None).unwrap_err().code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_compare_paths_and_snapshot() {
+        let dir = TempDir::new().unwrap();
+        let golden = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let (calls, loops) = ([golden.join("calls")], [golden.join("loops")]);
+        let config = snapshot_config(&dir);
+
+        let live = emitted(compare(&config, &[calls[0].clone(), loops[0].clone()], None));
+        assert_eq!(live["base"], calls[0].display().to_string());
+        assert!(!live["files_added"].as_array().unwrap().is_empty());
+
+        emitted(snapshot_save(&config, Some(&calls[0])));
+        let saved = emitted(compare(&config, &loops, Some(1)));
+        assert_eq!(saved["base"], "snapshot:1");
+        let deltas = |out: &Value| { let mut out = out.clone(); out.as_object_mut().unwrap().remove("base"); out };
+        assert_eq!(deltas(&saved), deltas(&live));
+
+        let same = emitted(compare(&config, &calls, Some(1)));
+        assert_eq!(same["changed"], json!([]));
+        assert_eq!(same["added"], json!([]));
+
+        assert_eq!(compare(&config, &loops, Some(9)).unwrap_err().code, ErrorCode::NotFound);
+        assert_eq!(compare(&config, &loops, None).unwrap_err().code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_golden_bless_then_check() {
+        let dir = TempDir::new().unwrap();
+        let fixture = dir.path().join("basic");
+        std::fs::create_dir_all(&fixture).unwrap();
+        std::fs::write(fixture.join("main.rs"), "fn main() { let x = 1; }\n").unwrap();
+
+        let blessed = emitted(golden_bless(dir.path()));
+        assert_eq!(blessed["fixtures"], json!(["basic"]));
+        assert_eq!(blessed["changes"][0]["old"], "<missing>");
+        assert_eq!(emitted(golden_check(dir.path()))["changes"], json!([]));
+
+        std::fs::write(fixture.join("main.rs"), "fn main() { let y = 2; }\n").unwrap();
+        let err = golden_check(dir.path()).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Failed);
+        assert!(err.errors.iter().any(|e| e.starts_with("basic: cpg: ")));
+    }
+}
@@ -0,0 +1,794 @@
+//! CLI output types
+//!
+//! Every JSON document the CLI prints is one of these types, serialized at
+//! the edge. Each carries a top-level `schema_version` so downstream parsers
+//! can reject shapes they do not understand.
+//!
+//! **Bump `SCHEMA_VERSION`** when removing, renaming or retyping a field.
+//! Adding a field does not require a bump.
+
+use crate::api::RefreshReport;
+use crate::analysis::findings::Finding;
+use crate::compare::RepoComparison;
+use crate::config::TaintMode;
+use crate::pipeline::IngestPlan;
+use crate::query::{Aggregate, MaterializedResult, PlanExplanation, QueryIssue, SavedQuery};
+use crate::report::FileReport;
+use crate::semantic::io::GraphFile;
+use crate::semantic::{SemanticEpochReport, SemanticStatus, CFG};
+use crate::types::{ByteRange, ParseQuality};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Version of every output schema below
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Top-level `status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Success,
+    Error,
+}
+
+/// Machine-readable error category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Config file or overrides rejected
+    InvalidConfig,
+
+    /// Argument refers to a path that does not exist
+    NotFound,
+
+    /// Argument exists but is the wrong kind of input
+    InvalidInput,
+
+    /// Command ran and failed
+    Failed,
+
+    /// Query was cancelled before it finished
+    Cancelled,
+
+    /// Query ran past its deadline
+    Timeout,
+}
+
+/// `vcr ingest`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub epoch_id: u64,
+    pub cpg_hash: String,
+
+    /// Directories only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_hash: Option<String>,
+
+    /// Directories without verification only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<usize>,
+
+    /// CPG nodes (directory) or parse tree children (file); absent when verified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<usize>,
+
+    /// Present (and `true`) only with determinism verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub determinism_verified: Option<bool>,
+
+    /// Files with syntax errors (absent when there are none)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parse_errors: Vec<ParseErrorRow>,
+}
+
+/// `vcr ingest --dry-run`: what an ingest would scan, skip and parse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestPlanOutput {
+    pub schema_version: u32,
+    pub status: Status,
+
+    /// Stored snapshot the changes are relative to (absent without one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since_snapshot: Option<u64>,
+
+    #[serde(flatten)]
+    pub plan: IngestPlan,
+}
+
+/// One file Tree-sitter parsed with error recovery
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseErrorRow {
+    pub file: String,
+    pub error_count: usize,
+    pub error_ranges: Vec<ByteRange>,
+
+    /// No semantics were built (`on_parse_error = "skip"`)
+    pub skipped: bool,
+}
+
+/// `vcr ingest --stdin`: one buffer, analyzed without a snapshot or CPG
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferIngestOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub language: String,
+
+    /// Buffer size in bytes
+    pub size: usize,
+
+    pub parse: ParseQuality,
+
+    /// CFGs, symbols and DFGs were built (a buffer with syntax errors only
+    /// with `on_parse_error = "include_best_effort"`)
+    pub analyzed: bool,
+
+    /// Whether a budget left functions out (omitted when complete)
+    #[serde(default, skip_serializing_if = "SemanticStatus::is_complete")]
+    pub semantic_status: SemanticStatus,
+
+    pub symbols: usize,
+
+    /// One row per CFG, by FunctionId
+    pub functions: Vec<BufferFunctionRow>,
+
+    /// Binding findings (with `--lint` or `--deny`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lint: Option<BufferLintRows>,
+
+    /// Denied categories with findings (`"shadowing"`, `"unused"`)
+    pub denied: Vec<String>,
+}
+
+impl BufferIngestOutput {
+    /// Process exit code: `EXIT_PARSE_FAILED` for syntax errors, else
+    /// `EXIT_DENIED` for denied findings, else 0
+    pub fn exit_code(&self) -> i32 {
+        match (self.parse.clean, self.denied.is_empty()) {
+            (false, _) => EXIT_PARSE_FAILED,
+            (true, false) => EXIT_DENIED,
+            (true, true) => 0,
+        }
+    }
+}
+
+/// Exit code of a command whose denied lint categories had findings
+pub const EXIT_DENIED: i32 = 1;
+
+/// Exit code of `vcr ingest --stdin` for a buffer with syntax errors
+pub const EXIT_PARSE_FAILED: i32 = 2;
+
+/// One function of a buffer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferFunctionRow {
+    pub function_id: u64,
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    pub cyclomatic_complexity: usize,
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub max_loop_nesting: usize,
+    pub cfg_hash: String,
+
+    /// Absent if the function has no DFG
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dfg_hash: Option<String>,
+}
+
+/// Binding findings of a buffer (`file` is `<stdin>`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufferLintRows {
+    pub shadowing: Vec<ShadowingRow>,
+    pub unused: Vec<UnusedBindingRow>,
+}
+
+/// `vcr snapshot save|prune|gc|load|verify`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotOutput {
+    pub schema_version: u32,
+    pub status: Status,
+
+    #[serde(flatten)]
+    pub result: SnapshotResult,
+}
+
+/// Operation-specific snapshot fields
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SnapshotResult {
+    Saved { snapshot_id: u64, hash: String, pruned: usize },
+    Pruned { removed: Vec<u64>, payloads_deleted: usize, retained: usize },
+    Collected {
+        dry_run: bool,
+        scanned: usize,
+        referenced: usize,
+        orphaned: Vec<String>,
+        protected: usize,
+        bytes_reclaimable: u64,
+        deleted: Vec<String>,
+    },
+    Loaded {
+        hash: String,
+        verified: bool,
+        epoch_id: u64,
+        restored_from: u64,
+        nodes: usize,
+
+        /// Present only when the snapshot's tool major version differs
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        warning: Option<String>,
+    },
+    Verified {
+        hash: String,
+        valid: bool,
+        repo_snapshot_hash: String,
+        tool_version: String,
+        file_count: usize,
+        language_counts: BTreeMap<String, usize>,
+    },
+}
+
+impl SnapshotOutput {
+    pub fn new(result: SnapshotResult) -> Self {
+        Self { schema_version: SCHEMA_VERSION, status: Status::Success, result }
+    }
+}
+
+/// `vcr query`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub query: String,
+
+    /// `--snapshot-id` only: the stored snapshot that answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<u64>,
+
+    pub result_id: u64,
+    pub results: Vec<u64>,
+    pub count: usize,
+    pub total: usize,
+    pub offset: usize,
+
+    /// `--explain` only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain: Option<PlanExplanation>,
+
+    /// Aggregate queries only (`results` is then empty)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregate: Option<Aggregate>,
+
+    /// `--materialize` only: `results` with their files and source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub materialized: Option<Vec<MaterializedResult>>,
+}
+
+/// One line of `vcr query --watch`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryWatchOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub query: String,
+
+    /// The repo's epoch the line reports on (1 after the initial ingest)
+    pub epoch_id: u64,
+
+    #[serde(flatten)]
+    pub event: WatchEvent,
+}
+
+/// What a `vcr query --watch` line reports; results are stable node keys
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchEvent {
+    /// The query's results on the initial ingest
+    Initial { results: Vec<String>, total: usize },
+
+    /// The query re-run after a refresh advanced the epoch
+    Delta {
+        /// Paths the refresh added, modified or deleted
+        changed_files: Vec<String>,
+
+        /// Results new since the previous line
+        added: Vec<String>,
+
+        /// Results gone since the previous line
+        removed: Vec<String>,
+        total: usize,
+
+        /// Answered from the result cache (the CPG hash did not change)
+        cached: bool,
+    },
+
+    /// Shutdown: re-runs and results added and removed over the session
+    Summary { reruns: usize, added: usize, removed: usize, total: usize },
+}
+
+/// `vcr query --taint`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaintOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub query: String,
+
+    /// `--snapshot-id` only: the stored snapshot that answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<u64>,
+
+    /// Taint paths found
+    pub total_paths: usize,
+
+    /// Every path (without `--dedupe`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<TaintPathRow>>,
+
+    /// Paths collapsed per source and sink, by ID (`--dedupe`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub findings: Option<Vec<Finding>>,
+}
+
+/// One taint path of `vcr query --taint`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaintPathRow {
+    pub source: u64,
+    pub sink: u64,
+
+    /// Node IDs from source to sink
+    pub path: Vec<u64>,
+}
+
+/// `vcr query --list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryListOutput {
+    pub schema_version: u32,
+    pub status: Status,
+
+    /// In name order
+    pub queries: Vec<SavedQueryInfo>,
+}
+
+/// `vcr query --check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryCheckOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub query: String,
+
+    /// No issues; the command exits 1 otherwise
+    pub valid: bool,
+
+    /// In document order
+    pub issues: Vec<QueryIssue>,
+}
+
+/// One saved query in `vcr query --list` and `list_queries`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedQueryInfo {
+    pub name: String,
+    pub description: String,
+    pub file: String,
+}
+
+impl From<&SavedQuery> for SavedQueryInfo {
+    fn from(query: &SavedQuery) -> Self {
+        Self {
+            name: query.name.clone(),
+            description: query.description.clone(),
+            file: query.file.display().to_string(),
+        }
+    }
+}
+
+/// `vcr explain`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExplainOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub result_id: String,
+    pub provenance: Vec<String>,
+}
+
+/// `vcr config init`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigInitOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+}
+
+/// `vcr lint dead-functions`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadFunctionsOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+    pub dead_functions: Vec<DeadFunctionRow>,
+    pub count: usize,
+}
+
+/// One dead function
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadFunctionRow {
+    pub name: String,
+    pub file: String,
+    pub function_id: u64,
+    pub start: usize,
+    pub end: usize,
+    pub reason: String,
+}
+
+/// `vcr lint taint`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaintLintOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+    pub mode: TaintMode,
+    pub flows: Vec<TaintFlowRow>,
+    pub count: usize,
+}
+
+/// One source-to-sink flow
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaintFlowRow {
+    pub source: TaintSiteRow,
+    pub sink: TaintSiteRow,
+}
+
+/// A source or sink call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaintSiteRow {
+    pub file: String,
+    pub function: String,
+    pub function_id: u64,
+    pub callee: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// `vcr lint shadowing`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BindingsLintOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+    pub shadowing: Vec<ShadowingRow>,
+    pub unused: Vec<UnusedBindingRow>,
+
+    /// Denied categories with findings (`"shadowing"`, `"unused"`); the
+    /// command exits `EXIT_DENIED` unless empty
+    pub denied: Vec<String>,
+}
+
+/// One binding shadowing an outer one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShadowingRow {
+    pub name: String,
+    pub kind: String,
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+    pub shadowed_kind: String,
+    pub shadowed_start: usize,
+    pub shadowed_end: usize,
+}
+
+/// One binding nothing reads
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnusedBindingRow {
+    pub name: String,
+    pub kind: String,
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// `vcr report complexity`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplexityOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+    pub functions: Vec<FunctionComplexityRow>,
+    pub files: Vec<FileComplexityRow>,
+    pub repo: ComplexitySummary,
+}
+
+/// One function's CFG metrics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionComplexityRow {
+    pub name: String,
+    pub file: String,
+    pub function_id: u64,
+    pub start: usize,
+    pub end: usize,
+    pub cyclomatic_complexity: usize,
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub max_loop_nesting: usize,
+}
+
+/// One file's aggregate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileComplexityRow {
+    pub file: String,
+
+    #[serde(flatten)]
+    pub summary: ComplexitySummary,
+}
+
+/// Aggregate over a set of functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComplexitySummary {
+    pub functions: usize,
+    pub total_complexity: usize,
+    pub max_complexity: usize,
+    pub max_loop_nesting: usize,
+}
+
+/// `vcr report files`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilesReportOutput {
+    pub schema_version: u32,
+    pub status: Status,
+
+    /// Ingested repository (absent when read from a snapshot)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Snapshot the stats were saved with (absent for a live ingest)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_id: Option<u64>,
+
+    /// One row per file, sorted by path
+    pub files: Vec<FileReport>,
+
+    /// Per-file semantic counts and function hashes, by FileId (absent when
+    /// read from a snapshot)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub semantic: Option<SemanticEpochReport>,
+}
+
+/// `vcr compare`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompareOutput {
+    pub schema_version: u32,
+    pub status: Status,
+
+    /// Base side: an ingested path or `snapshot:<id>`
+    pub base: String,
+
+    /// Ingested head path
+    pub head: String,
+
+    #[serde(flatten)]
+    pub comparison: RepoComparison,
+}
+
+/// `vcr golden bless` / `vcr golden check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldenOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+
+    /// Fixture directory names, sorted
+    pub fixtures: Vec<String>,
+
+    /// Hashes that differ from the manifest (always empty for a passing check)
+    pub changes: Vec<GoldenChangeRow>,
+}
+
+/// One pinned hash that changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldenChangeRow {
+    pub fixture: String,
+    pub artifact: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// One `vcr serve` response line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServeResponse {
+    pub schema_version: u32,
+
+    /// `id` of the request (`null` if the line had none)
+    pub id: serde_json::Value,
+    pub status: Status,
+
+    #[serde(flatten)]
+    pub result: ServeResult,
+}
+
+impl ServeResponse {
+    pub fn new(id: serde_json::Value, result: ServeResult) -> Self {
+        let status = match result {
+            ServeResult::Failed { .. } => Status::Error,
+            _ => Status::Success,
+        };
+        Self { schema_version: SCHEMA_VERSION, id, status, result }
+    }
+}
+
+/// Operation-specific serve fields
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ServeResult {
+    Fetched { result_id: u64, results: Vec<String>, count: usize },
+    Explained { result_id: u64, provenance: Vec<String> },
+    Nodes { results: Vec<String>, count: usize },
+    Loaded { handle: u64 },
+    Queries { queries: Vec<SavedQueryInfo> },
+    Queried {
+        result_id: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        aggregate: Option<Aggregate>,
+    },
+    Refreshed(RefreshReport),
+    Failed { code: ErrorCode, message: String },
+    Done {},
+}
+
+/// `vcr export --format cfg-json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CfgExportOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+
+    /// Readable by `semantic::io::GraphFile::from_slice`
+    pub graph_file: GraphFile<CFG>,
+}
+
+/// `vcr export --subgraph`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubgraphExportOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub path: String,
+
+    /// Stable key of the root node
+    pub root: String,
+    pub depth: usize,
+    pub anonymized: bool,
+
+    /// Snapshot file written (`vcr query --snapshot` reads it)
+    pub output: String,
+    pub nodes: usize,
+    pub edges: usize,
+    pub hash: String,
+}
+
+/// Any failure (printed to stderr)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorOutput {
+    pub schema_version: u32,
+    pub status: Status,
+    pub code: ErrorCode,
+    pub message: String,
+
+    /// Individual errors behind `message` (config validation)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+
+    /// Always `true` (fail-closed)
+    pub fatal: bool,
+}
+
+impl ErrorOutput {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            status: Status::Error,
+            code,
+            message: message.into(),
+            errors: Vec::new(),
+            fatal: true,
+        }
+    }
+}
+
+/// Serialize an output document as one line of JSON
+pub fn to_json<T: Serialize>(output: &T) -> String {
+    serde_json::to_string(output).expect("CLI output types always serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_error_message_with_quotes_round_trips() {
+        let message = "Query failed: unexpected \"}\" at line 1\n\tnear 'ü' → ✗";
+        let json = to_json(&ErrorOutput::new(ErrorCode::Failed, message));
+
+        assert!(!json.contains('\n'));
+        let parsed: ErrorOutput = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.message, message);
+        assert_eq!(parsed.code, ErrorCode::Failed);
+        assert_eq!(parsed.schema_version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_error_fields() {
+        let mut error = ErrorOutput::new(ErrorCode::InvalidConfig, "Invalid configuration (1 error(s))");
+        let value: Value = serde_json::from_str(&to_json(&error)).unwrap();
+        assert_eq!(value["status"], "error");
+        assert_eq!(value["code"], "invalid_config");
+        assert_eq!(value["fatal"], true);
+        assert_eq!(value["schema_version"], 1);
+        assert!(value.get("errors").is_none());
+
+        error.errors.push("Invalid io.max_file_size: must be > 0".to_string());
+        let value: Value = serde_json::from_str(&to_json(&error)).unwrap();
+        assert_eq!(value["errors"][0], "Invalid io.max_file_size: must be > 0");
+    }
+
+    #[test]
+    fn test_snapshot_variants_round_trip() {
+        for result in [
+            SnapshotResult::Saved { snapshot_id: 3, hash: "h".into(), pruned: 1 },
+            SnapshotResult::Pruned { removed: vec![1, 2], payloads_deleted: 1, retained: 4 },
+            SnapshotResult::Collected {
+                dry_run: true, scanned: 3, referenced: 1, orphaned: vec!["a.cpg".into()], protected: 1,
+                bytes_reclaimable: 10, deleted: vec![],
+            },
+            SnapshotResult::Loaded { hash: "h".into(), verified: true, epoch_id: 5, restored_from: 4, nodes: 10, warning: None },
+            SnapshotResult::Loaded {
+                hash: "h".into(), verified: true, epoch_id: 5, restored_from: 4, nodes: 10,
+                warning: Some("Snapshot was written by vcr 1.0.0".into()),
+            },
+            SnapshotResult::Verified {
+                hash: "h".into(),
+                valid: true,
+                repo_snapshot_hash: "r".into(),
+                tool_version: "0.1.0".into(),
+                file_count: 2,
+                language_counts: BTreeMap::from([("rust".into(), 2)]),
+            },
+        ] {
+            let output = SnapshotOutput::new(result);
+            let json = to_json(&output);
+            assert!(json.starts_with("{\"schema_version\":1,\"status\":\"success\""));
+            assert_eq!(serde_json::from_str::<SnapshotOutput>(&json).unwrap(), output);
+        }
+    }
+
+    #[test]
+    fn test_serve_variants_round_trip() {
+        for result in [
+            ServeResult::Fetched { result_id: 2, results: vec!["7".into()], count: 1 },
+            ServeResult::Explained { result_id: 2, provenance: vec!["p".into()] },
+            ServeResult::Nodes { results: vec![], count: 0 },
+            ServeResult::Loaded { handle: 1 },
+            ServeResult::Queries {
+                queries: vec![SavedQueryInfo { name: "q".into(), description: "d".into(), file: "q.json".into() }],
+            },
+            ServeResult::Queried { result_id: 2, aggregate: None },
+            ServeResult::Queried { result_id: 3, aggregate: Some(Aggregate::Count(4)) },
+            ServeResult::Failed { code: ErrorCode::NotFound, message: "Unknown repo handle: 9".into() },
+            ServeResult::Done {},
+        ] {
+            let response = ServeResponse::new(Value::from(4), result);
+            let json = to_json(&response);
+            assert!(json.starts_with("{\"schema_version\":1,\"id\":4,"), "{}", json);
+            assert_eq!(serde_json::from_str::<ServeResponse>(&json).unwrap(), response);
+        }
+    }
+
+    #[test]
+    fn test_file_row_flattens_summary() {
+        let row = FileComplexityRow {
+            file: "a.rs".into(),
+            summary: ComplexitySummary { functions: 2, total_complexity: 3, max_complexity: 2, max_loop_nesting: 0 },
+        };
+        let value: Value = serde_json::from_str(&to_json(&row)).unwrap();
+        assert_eq!(value["file"], "a.rs");
+        assert_eq!(value["total_complexity"], 3);
+    }
+}
@@ -0,0 +1,514 @@
+//! `vcr serve` (Path B9)
+//!
+//! Keeps one ValoriAPI warm between requests. The protocol is
+//! line-delimited JSON: every input line is a request, every output line the
+//! response carrying the same `id`.
+//!
+//! **Strictly in order**: Requests are handled one at a time, in arrival
+//! order, and each response is flushed before the next request is handled.
+//! A malformed line gets an error response and the server keeps going.
+//!
+//! **Cancellation**: Lines are read ahead on a separate thread, so a
+//! `cancel` naming a `run_query` request's `id` stops that query while it
+//! runs (or before it starts). The query answers with a `cancelled` error;
+//! the `cancel` itself is answered in its turn. With a timeout, every query
+//! that runs longer fails with `timeout`.
+//!
+//! **Metrics**: With a metrics sink, the API's metrics are written after a
+//! request once `METRICS_INTERVAL` has passed since the last write, and
+//! when the server stops.
+
+use super::output::*;
+use super::CommandResult;
+use crate::api::{RepoHandle, ResultId, ValoriAPI, ValoriError};
+use crate::config::ValoriConfig;
+use crate::execution::CancellationToken;
+use crate::metrics::{MetricsCollector, MetricsSink};
+use crate::types::FileId;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Least time between periodic metrics writes
+pub const METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// One request; `op` selects the operation
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Request {
+    LoadRepo { path: String },
+    UpdateFiles { handle: u64, files: Vec<u64> },
+
+    /// `query` is a QuerySpec object or its JSON text
+    RunQuery { handle: u64, query: Value },
+    FetchResult { result_id: u64 },
+    ExplainResult { result_id: u64 },
+    NodeAt { handle: u64, path: String, offset: usize },
+
+    /// Analyze `content` (an unsaved buffer) in place of the file at `path`
+    UpdateFileContent { handle: u64, path: String, content: String },
+
+    /// Go back to the file on disk
+    ClearOverlay { handle: u64, path: String },
+
+    /// Rescan and rebuild whatever changed on disk
+    Refresh { handle: u64 },
+
+    /// Saved queries in `query.query_dir`
+    ListQueries,
+
+    /// Cancel the `run_query` request with this `id`
+    Cancel { request_id: Value },
+    Shutdown,
+}
+
+/// Tokens of `run_query` requests read but not yet answered, by request id
+#[derive(Debug, Clone, Default)]
+pub struct PendingQueries(Arc<Mutex<HashMap<String, CancellationToken>>>);
+
+impl PendingQueries {
+    /// Note a line as soon as it is read: register a `run_query`, apply a
+    /// `cancel`. Returns whether the line is a `shutdown`.
+    pub fn admit(&self, line: &str) -> bool {
+        let Ok(value) = serde_json::from_str::<Value>(line) else { return false };
+        let id = value.get("id").unwrap_or(&Value::Null);
+        match value.get("op").and_then(Value::as_str) {
+            Some("run_query") => {
+                self.lock().entry(id.to_string()).or_default();
+            }
+            Some("cancel") => {
+                let request_id = value.get("request_id").unwrap_or(&Value::Null);
+                if let Some(token) = self.lock().get(&request_id.to_string()) {
+                    token.cancel();
+                }
+            }
+            Some("shutdown") => return true,
+            _ => {}
+        }
+        false
+    }
+
+    /// Token of a request (registered now if it was not admitted)
+    fn token(&self, id: &Value) -> CancellationToken {
+        self.lock().entry(id.to_string()).or_default().clone()
+    }
+
+    /// Forget an answered request
+    fn finish(&self, id: &Value) {
+        self.lock().remove(&id.to_string());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, CancellationToken>> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Request handler holding loaded repositories and stored results
+pub struct Server {
+    api: ValoriAPI,
+
+    /// Queries that can still be cancelled
+    pending: PendingQueries,
+
+    /// Deadline of each query, from when it starts
+    timeout: Option<Duration>,
+
+    /// Saved queries, re-read on every `list_queries`
+    query_dir: Option<PathBuf>,
+}
+
+impl Server {
+    pub fn new(config: &ValoriConfig) -> Self {
+        Self {
+            api: ValoriAPI::new(config),
+            pending: PendingQueries::default(),
+            timeout: None,
+            query_dir: config.query.query_dir.clone(),
+        }
+    }
+
+    /// Fail queries that run longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Metrics of the API serving requests
+    pub fn metrics(&self) -> &MetricsCollector {
+        self.api.metrics()
+    }
+
+    /// Pending queries, for the thread reading requests
+    pub fn pending(&self) -> PendingQueries {
+        self.pending.clone()
+    }
+
+    /// Handle one request line, returning the response and whether to stop
+    pub fn handle_line(&mut self, line: &str) -> (ServeResponse, bool) {
+        let mut value: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) => return (invalid(Value::Null, format!("Malformed request: {}", e)), false),
+        };
+        let id = value.as_object_mut()
+            .and_then(|fields| fields.remove("id"))
+            .unwrap_or(Value::Null);
+
+        let request = match Request::deserialize(value) {
+            Ok(request) => request,
+            Err(e) => return (invalid(id, format!("Invalid request: {}", e)), false),
+        };
+
+        let shutdown = request == Request::Shutdown;
+        let result = self.execute(&id, request).unwrap_or_else(|e| ServeResult::Failed {
+            code: error_code(&e),
+            message: e.to_string(),
+        });
+
+        (ServeResponse::new(id, result), shutdown)
+    }
+
+    fn execute(&mut self, id: &Value, request: Request) -> Result<ServeResult, ValoriError> {
+        Ok(match request {
+            Request::LoadRepo { path } => ServeResult::Loaded { handle: self.api.load_repo(&path)?.0 },
+            Request::UpdateFiles { handle, files } => {
+                self.api.update_files(RepoHandle(handle), files.into_iter().map(FileId::new).collect())?;
+                ServeResult::Done {}
+            }
+            Request::RunQuery { handle, query } => {
+                let text = match query {
+                    Value::String(text) => text,
+                    spec => spec.to_string(),
+                };
+                let token = match self.timeout {
+                    Some(timeout) => self.pending.token(id).with_timeout(timeout),
+                    None => self.pending.token(id),
+                };
+                let result = self.api.run_query_cancellable(RepoHandle(handle), &text, token);
+                self.pending.finish(id);
+                let result_id = result?;
+                ServeResult::Queried { result_id: result_id.0, aggregate: self.api.fetch_aggregate(result_id)? }
+            }
+            Request::FetchResult { result_id } => {
+                let results = self.api.fetch_result(ResultId(result_id))?;
+                ServeResult::Fetched { result_id, count: results.len(), results }
+            }
+            Request::ExplainResult { result_id } => ServeResult::Explained {
+                result_id,
+                provenance: vec![self.api.explain_result(ResultId(result_id))?],
+            },
+            Request::NodeAt { handle, path, offset } => {
+                let results = self.api.node_at(RepoHandle(handle), &path, offset)?;
+                ServeResult::Nodes { count: results.len(), results }
+            }
+            Request::UpdateFileContent { handle, path, content } => {
+                self.api.update_file_content(RepoHandle(handle), &path, content.into_bytes())?;
+                ServeResult::Done {}
+            }
+            Request::ClearOverlay { handle, path } => {
+                self.api.clear_overlay(RepoHandle(handle), &path)?;
+                ServeResult::Done {}
+            }
+            Request::Refresh { handle } => ServeResult::Refreshed(self.api.refresh(RepoHandle(handle))?),
+            Request::ListQueries => match super::query_library(self.query_dir.as_deref()) {
+                Ok(library) => ServeResult::Queries { queries: library.iter().map(SavedQueryInfo::from).collect() },
+                Err(e) => ServeResult::Failed { code: e.code, message: e.message },
+            },
+            // Already applied when the line was read
+            Request::Cancel { .. } | Request::Shutdown => ServeResult::Done {},
+        })
+    }
+}
+
+/// `vcr serve`: answer requests from `input` on `output` until `shutdown` or EOF
+///
+/// Each query fails with `timeout` once it has run for `timeout`. With
+/// `metrics`, the API's metrics are written there periodically and on exit.
+pub fn serve(
+    config: &ValoriConfig,
+    timeout: Option<Duration>,
+    metrics: Option<&MetricsSink>,
+    input: impl BufRead + Send,
+    mut output: impl Write,
+) -> CommandResult<()> {
+    let mut server = Server::new(config).with_timeout(timeout);
+    let pending = server.pending();
+    let (lines, received) = mpsc::channel();
+    let mut last_written = Instant::now();
+
+    std::thread::scope(|scope| -> CommandResult<()> {
+        // Reads ahead so `cancel` lines take effect while a query runs;
+        // stops after `shutdown` so nothing more is consumed from `input`
+        scope.spawn(move || {
+            for line in input.lines() {
+                let shutdown = line.as_ref().map_or(true, |line| pending.admit(line));
+                if lines.send(line).is_err() || shutdown {
+                    break;
+                }
+            }
+        });
+
+        for line in received {
+            let line = line.map_err(|e| format!("Failed to read request: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (response, shutdown) = server.handle_line(&line);
+            writeln!(output, "{}", to_json(&response))
+                .and_then(|()| output.flush())
+                .map_err(|e| format!("Failed to write response: {}", e))?;
+
+            if let Some(sink) = metrics.filter(|_| last_written.elapsed() >= METRICS_INTERVAL) {
+                if let Err(e) = sink.write(server.metrics()) {
+                    tracing::warn!("{:#}", e);
+                }
+                last_written = Instant::now();
+            }
+
+            if shutdown {
+                break;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    match metrics {
+        Some(sink) => sink.write(server.metrics()).map_err(|e| format!("{:#}", e).into()),
+        None => Ok(()),
+    }
+}
+
+fn invalid(id: Value, message: String) -> ServeResponse {
+    ServeResponse::new(id, ServeResult::Failed { code: ErrorCode::InvalidInput, message })
+}
+
+/// Error category for an API error
+pub(super) fn error_code(error: &ValoriError) -> ErrorCode {
+    match error {
+        ValoriError::LoadFailed(_)
+        | ValoriError::QueryFailed(_)
+        | ValoriError::UpdateFailed(_)
+        | ValoriError::SaveFailed(_)
+        | ValoriError::SubscriberFull(_)
+        | ValoriError::SourceUnavailable(_) => ErrorCode::Failed,
+        ValoriError::UnknownRepo(_)
+        | ValoriError::UnknownResult(_)
+        | ValoriError::InvalidPath(_)
+        | ValoriError::UnknownSnapshot(_) => ErrorCode::NotFound,
+        ValoriError::InvalidQuery(_) => ErrorCode::InvalidInput,
+        ValoriError::Cancelled(_) => ErrorCode::Cancelled,
+        ValoriError::Timeout(_) => ErrorCode::Timeout,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Run a whole session, returning each response as JSON
+    fn session(input: &str) -> Vec<Value> {
+        let mut output = Vec::new();
+        serve(&ValoriConfig::default(), None, None, input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_malformed_lines_get_error_responses() {
+        let out = session("not json\n{\"id\": 3, \"op\": \"frobnicate\"}\n{\"id\": 4, \"op\": \"fetch_result\", \"result_id\": 9}\n");
+
+        assert_eq!(out.len(), 3);
+        assert_eq!(out[0]["id"], Value::Null);
+        assert_eq!(out[0]["code"], "invalid_input");
+        assert_eq!(out[1]["id"], 3);
+        assert_eq!(out[1]["status"], "error");
+        assert_eq!(out[2]["code"], "not_found");
+        assert_eq!(out[2]["message"], "Unknown result: 9");
+    }
+
+    #[test]
+    fn test_shutdown_stops_reading() {
+        let out = session("{\"id\": \"a\", \"op\": \"shutdown\"}\n{\"id\": \"b\", \"op\": \"shutdown\"}\n");
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0]["id"], "a");
+        assert_eq!(out[0]["status"], "success");
+    }
+
+    #[test]
+    fn test_cancel_reaches_admitted_query() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        let mut server = Server::new(&ValoriConfig::default());
+        let load = serde_json::json!({"id": 1, "op": "load_repo", "path": dir.path()}).to_string();
+        let run = r#"{"id": "q", "op": "run_query", "handle": 1, "query": {"pipeline": [{"find": "Function"}]}}"#;
+        let cancel = r#"{"id": 3, "op": "cancel", "request_id": "q"}"#;
+        server.handle_line(&load);
+
+        // As the reading thread would: both lines arrive before the query runs
+        let pending = server.pending();
+        assert!(!pending.admit(run));
+        assert!(!pending.admit(cancel));
+
+        let (response, _) = server.handle_line(run);
+        let response: Value = serde_json::from_str(&to_json(&response)).unwrap();
+        assert_eq!(response["code"], "cancelled");
+        assert_eq!(serde_json::to_value(server.handle_line(cancel).0).unwrap()["status"], "success");
+
+        // The id is free again once answered
+        let (response, _) = server.handle_line(run);
+        assert_eq!(response.status, Status::Success);
+    }
+
+    #[test]
+    fn test_timeout_fails_queries() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        let input = format!(
+            "{}\n{}\n",
+            serde_json::json!({"id": 1, "op": "load_repo", "path": dir.path()}),
+            r#"{"id": 2, "op": "run_query", "handle": 1, "query": {"pipeline": [{"find": "Function"}]}}"#,
+        );
+
+        let mut output = Vec::new();
+        serve(&ValoriConfig::default(), Some(Duration::ZERO), None, input.as_bytes(), &mut output).unwrap();
+        let out: Vec<Value> = String::from_utf8(output).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+
+        assert_eq!(out[1]["code"], "timeout");
+        assert!(out[1]["message"].as_str().unwrap().starts_with("Query timed out after 0 task(s)"));
+    }
+
+    #[test]
+    fn test_query_as_object_or_text() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+        let load = serde_json::json!({"id": 1, "op": "load_repo", "path": dir.path()});
+
+        let input = format!(
+            "{}\n{}\n{}\n{}\n{}\n",
+            load,
+            r#"{"id": 2, "op": "run_query", "handle": 1, "query": {"pipeline": [{"find": "Function"}]}}"#,
+            r#"{"id": 3, "op": "run_query", "handle": 1, "query": "{\"pipeline\": [{\"find\": \"Function\"}]}"}"#,
+            r#"{"id": 4, "op": "fetch_result", "result_id": 1}"#,
+            r#"{"id": 5, "op": "fetch_result", "result_id": 2}"#,
+        );
+        let out = session(&input);
+
+        assert_eq!(out[0]["handle"], 1);
+        assert_eq!(out[3]["count"], 2);
+        assert_eq!(out[3]["results"], out[4]["results"]);
+    }
+
+    #[test]
+    fn test_list_queries() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("functions.json"),
+            r#"{"name": "functions", "description": "All functions", "pipeline": [{"find": "Function"}]}"#,
+        ).unwrap();
+        let mut config = ValoriConfig::default();
+        let list = r#"{"id": 1, "op": "list_queries"}"#;
+
+        let (response, _) = Server::new(&config).handle_line(list);
+        assert_eq!(response.result, ServeResult::Failed {
+            code: ErrorCode::InvalidInput,
+            message: "No query directory configured (set query.query_dir)".into(),
+        });
+
+        config.query.query_dir = Some(dir.path().to_path_buf());
+        let response: Value = serde_json::to_value(Server::new(&config).handle_line(list).0).unwrap();
+        assert_eq!(response["queries"][0]["name"], "functions");
+        assert_eq!(response["queries"][0]["description"], "All functions");
+    }
+
+    #[test]
+    fn test_overlay_ops() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let query = r#"{"pipeline": [{"function_matches": "unsaved"}]}"#;
+        let run = |id: u64| serde_json::json!({"id": id, "op": "run_query", "handle": 1, "query": query});
+        let fetch = |id: u64, result_id: u64| serde_json::json!({"id": id, "op": "fetch_result", "result_id": result_id});
+
+        let input = [
+            serde_json::json!({"id": 1, "op": "load_repo", "path": dir.path()}),
+            serde_json::json!({"id": 2, "op": "update_file_content", "handle": 1, "path": "main.rs",
+                "content": "fn main() {}\nfn unsaved() {}\n"}),
+            run(3),
+            fetch(4, 1),
+            serde_json::json!({"id": 5, "op": "clear_overlay", "handle": 1, "path": "main.rs"}),
+            run(6),
+            fetch(7, 2),
+            serde_json::json!({"id": 8, "op": "update_file_content", "handle": 1, "path": "other.rs", "content": ""}),
+        ].map(|request| request.to_string()).join("\n");
+        let out = session(&input);
+
+        assert_eq!(out[1]["status"], "success");
+        assert_eq!(out[3]["count"], 1);
+        assert_eq!(out[4]["status"], "success");
+        assert_eq!(out[6]["count"], 0);
+        assert_eq!(out[7]["code"], "not_found");
+    }
+
+    #[test]
+    fn test_max_offset_finds_nothing() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let at = serde_json::json!({"pipeline": [{"at": {"file": "main.rs", "offset": u64::MAX}}]});
+
+        let input = [
+            serde_json::json!({"id": 1, "op": "load_repo", "path": dir.path()}),
+            serde_json::json!({"id": 2, "op": "node_at", "handle": 1, "path": "main.rs", "offset": u64::MAX}),
+            serde_json::json!({"id": 3, "op": "run_query", "handle": 1, "query": at}),
+            serde_json::json!({"id": 4, "op": "fetch_result", "result_id": 1}),
+        ].map(|request| request.to_string()).join("\n");
+        let out = session(&input);
+
+        // The server answers every request instead of dying on the offset
+        assert_eq!(out.len(), 4);
+        assert_eq!(out[1]["status"], "success");
+        assert_eq!(out[1]["count"], 0);
+        assert_eq!(out[2]["status"], "success");
+        assert_eq!(out[3]["count"], 0);
+    }
+
+    #[test]
+    fn test_refresh_op() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let mut server = Server::new(&ValoriConfig::default());
+        let mut request = |request: Value| serde_json::to_value(server.handle_line(&request.to_string()).0).unwrap();
+
+        request(serde_json::json!({"id": 1, "op": "load_repo", "path": dir.path()}));
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}\nfn added() {}\n").unwrap();
+        let refreshed = request(serde_json::json!({"id": 2, "op": "refresh", "handle": 1}));
+        assert_eq!(refreshed["status"], "success");
+        assert_eq!((refreshed["modified"].clone(), refreshed["rebuilt"].clone(), refreshed["epoch_id"].clone()), (1.into(), 1.into(), 2.into()));
+
+        let again = request(serde_json::json!({"id": 3, "op": "refresh", "handle": 1}));
+        assert_eq!((again["rebuilt"].clone(), again["epoch_id"].clone()), (0.into(), 2.into()));
+        assert_eq!(again["new_cpg_hash"], refreshed["new_cpg_hash"]);
+        assert_eq!(request(serde_json::json!({"id": 4, "op": "refresh", "handle": 9}))["code"], "not_found");
+    }
+
+    #[test]
+    fn test_metrics_written_on_exit() {
+        use crate::metrics::MetricsFormat;
+
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        let sink = MetricsSink::new(dir.path().join("metrics.prom"), MetricsFormat::Prometheus);
+        let input = serde_json::json!({"id": 1, "op": "load_repo", "path": dir.path()}).to_string();
+
+        serve(&ValoriConfig::default(), None, Some(&sink), input.as_bytes(), std::io::sink()).unwrap();
+        let written = std::fs::read_to_string(sink.path()).unwrap();
+        assert!(written.contains("vcr_scan_duration_seconds "), "{}", written);
+        assert!(written.contains("vcr_cpg_nodes_total{kind=\"Function\"} 1"), "{}", written);
+
+        let unwritable = MetricsSink::new(dir.path().join("missing/metrics.prom"), MetricsFormat::Json);
+        assert!(serve(&ValoriConfig::default(), None, Some(&unwritable), "".as_bytes(), std::io::sink()).is_err());
+    }
+}
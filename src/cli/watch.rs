@@ -0,0 +1,119 @@
+//! `vcr query --watch`
+//!
+//! Ingests a repository, runs one query file against it and prints the
+//! results, then polls: every `poll` the repo is refreshed (see
+//! `ValoriAPI::refresh`), and each epoch a refresh commits re-runs the
+//! query and prints what changed. Results are compared by stable node key
+//! (see `analysis::StableKeys`), so an edit that shifts node IDs reports
+//! only the results it really added or removed. A refresh that moves no
+//! code commits the same CPG hash, and its re-run is a result cache hit.
+//!
+//! Output is line-delimited JSON (`QueryWatchOutput`): one `initial` line,
+//! one `delta` line per epoch, and a `summary` line once `stop` is set (on
+//! Ctrl-C, in the binary). A failed refresh is logged and the repo keeps
+//! its previous epoch.
+
+use super::output::*;
+use super::{read_query_file, CommandError, CommandResult};
+use crate::api::{RepoHandle, ValoriAPI};
+use crate::config::ValoriConfig;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Default time between refreshes
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// `vcr query --watch`: run `query_file` against `root` on every epoch
+/// until `stop` is set, writing one line per epoch to `output`
+pub fn query_watch(
+    config: &ValoriConfig,
+    query_file: &Path,
+    root: &Path,
+    poll: Duration,
+    stop: &AtomicBool,
+    mut output: impl Write,
+) -> CommandResult<()> {
+    let (text, spec) = read_query_file(query_file)?;
+    if matches!(spec.split_aggregate(), Ok((_, Some(_)))) {
+        return Err(CommandError::invalid_input("--watch reports result deltas, which aggregate queries have none of"));
+    }
+    if !root.exists() {
+        return Err(CommandError::not_found(format!("Path not found: {}", root.display())));
+    }
+
+    let mut api = ValoriAPI::new(config);
+    let handle = api.load_repo(&root.to_string_lossy())?;
+    let events = api.subscribe(handle)?;
+    let line = |epoch_id, event| QueryWatchOutput {
+        schema_version: SCHEMA_VERSION,
+        status: Status::Success,
+        query: query_file.display().to_string(),
+        epoch_id,
+        event,
+    };
+
+    // The load is the repo's first epoch
+    let mut epoch_id = 1;
+    let mut results = run(&mut api, handle, &text)?;
+    let total = results.len();
+    write_line(&mut output, &line(epoch_id, WatchEvent::Initial { results: results.iter().cloned().collect(), total }))?;
+
+    let (mut reruns, mut added_total, mut removed_total) = (0, 0, 0);
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(poll);
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        if let Err(e) = api.refresh(handle) {
+            tracing::warn!("{}", e);
+            continue;
+        }
+
+        let mut changed_files = BTreeSet::new();
+        let mut advanced = false;
+        while let Ok(event) = events.try_recv() {
+            epoch_id = event.epoch_id;
+            changed_files.extend(event.changed_files);
+            advanced = true;
+        }
+        if !advanced {
+            continue;
+        }
+
+        let hits = api.metrics().query_cache_hits();
+        let next = run(&mut api, handle, &text)?;
+        let added: Vec<String> = next.difference(&results).cloned().collect();
+        let removed: Vec<String> = results.difference(&next).cloned().collect();
+        reruns += 1;
+        added_total += added.len();
+        removed_total += removed.len();
+        let event = WatchEvent::Delta {
+            changed_files: changed_files.into_iter().collect(),
+            added,
+            removed,
+            total: next.len(),
+            cached: api.metrics().query_cache_hits() > hits,
+        };
+        write_line(&mut output, &line(epoch_id, event))?;
+        results = next;
+    }
+
+    let summary = WatchEvent::Summary { reruns, added: added_total, removed: removed_total, total: results.len() };
+    write_line(&mut output, &line(epoch_id, summary))
+}
+
+/// Stable keys of the query's results on the repo's current epoch
+fn run(api: &mut ValoriAPI, handle: RepoHandle, query: &str) -> CommandResult<BTreeSet<String>> {
+    let result_id = api.run_query(handle, query)?;
+    Ok(api.fetch_result_keys(handle, result_id)?.into_iter().collect())
+}
+
+fn write_line(output: &mut impl Write, line: &impl Serialize) -> CommandResult<()> {
+    writeln!(output, "{}", to_json(line))
+        .and_then(|()| output.flush())
+        .map_err(|e| format!("Failed to write output: {}", e).into())
+}
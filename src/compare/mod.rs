@@ -0,0 +1,484 @@
+//! Function-level semantic deltas between two builds (`vcr compare`)
+//!
+//! Files are matched by normalized relative path, functions by name and
+//! signature (the source from the name through the parameter list, runs of
+//! whitespace collapsed). Functions sharing a path, name and signature are
+//! paired in file order. A matched pair whose CFG or DFG hash differs is
+//! `changed`; an unmatched function is `removed` or `added`, unless a
+//! removed and an added function of the same file have the same body hash,
+//! which is reported as `renamed`.
+//!
+//! ## Hashes
+//!
+//! `CFG::compute_hash` and `DFG::compute_hash` cover function, node and
+//! value IDs, which shift whenever anything earlier in the file changes.
+//! The hashes here number nodes and values by position within their
+//! function instead, and leave the function's name out, so a function
+//! compares equal wherever its file moved it. Unlike `CFG::compute_hash`,
+//! the CFG hash covers statement text: `foo()` → `bar()` is a change. The
+//! body hash covers both. Kinds are hashed by their canonical tags (see
+//! `semantic::hash`).
+//!
+//! A saved snapshot has no semantics, so `vcr snapshot save` records the
+//! `RepoSummary` of what it saved (storage version 5) and a comparison
+//! against it needs nothing else. Summaries recorded before storage
+//! version 10 hashed kinds by their `Debug` text and are not compared.
+
+use crate::memory::arena::StringArena;
+use crate::pipeline::PipelineOutput;
+use crate::repo::normalize_path;
+use crate::semantic::cfg::cfg_metrics;
+use crate::semantic::{CanonicalHash, NodeId, SemanticEpoch, ValueId, CFG, DFG};
+use crate::types::RepoSnapshot;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// One function of a build, as compared
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionSummary {
+    /// Normalized relative path of the file
+    pub path: String,
+
+    /// Function name
+    pub name: String,
+
+    /// Name through the parameter list, whitespace collapsed (the name
+    /// alone if the source could not be read)
+    pub signature: String,
+
+    /// Position-independent CFG hash
+    pub cfg_hash: String,
+
+    /// Position-independent DFG hash (empty without a DFG)
+    pub dfg_hash: String,
+
+    /// Hash of the CFG and DFG hashes
+    pub body_hash: String,
+
+    /// Cyclomatic complexity
+    pub complexity: usize,
+}
+
+/// Files and functions of a build
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoSummary {
+    /// Normalized relative paths, sorted
+    pub files: Vec<String>,
+
+    /// Functions sorted by path, in file order within a path
+    pub functions: Vec<FunctionSummary>,
+}
+
+impl RepoSummary {
+    /// Summarize a pipeline run
+    pub fn from_output(output: &PipelineOutput) -> Self {
+        Self::from_epoch(&output.snapshot, &output.semantic)
+    }
+
+    /// Summarize the semantics built for a repository snapshot
+    ///
+    /// Signatures are read from the files under `repo.root`.
+    pub fn from_epoch(repo: &RepoSnapshot, semantic: &SemanticEpoch) -> Self {
+        let mut files: Vec<(String, _)> = repo.files.iter()
+            .map(|(file_id, meta)| (normalize_path(&meta.path), (*file_id, meta)))
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut functions = Vec::new();
+        for (path, (file_id, meta)) in &files {
+            let Some(cfgs) = semantic.get_cfgs(*file_id) else { continue };
+            let source = std::fs::read(repo.root.join(&meta.path)).ok();
+            let dfgs: HashMap<_, _> = semantic.get_dfgs(*file_id).into_iter().flatten()
+                .map(|dfg| (dfg.function_id, dfg))
+                .collect();
+
+            for cfg in cfgs {
+                let signature = source.as_deref()
+                    .and_then(|source| source.get(cfg.signature_range.start..cfg.signature_range.end))
+                    .map(|text| collapse_whitespace(&String::from_utf8_lossy(text)))
+                    .filter(|signature| !signature.is_empty())
+                    .unwrap_or_else(|| cfg.name.clone());
+                let cfg_hash = cfg_shape_hash(cfg, semantic.strings());
+                let dfg_hash = dfgs.get(&cfg.function_id)
+                    .map(|dfg| dfg_shape_hash(dfg, semantic.strings()))
+                    .unwrap_or_default();
+                let body_hash = sha256(&[cfg_hash.as_bytes(), dfg_hash.as_bytes()]);
+
+                functions.push(FunctionSummary {
+                    path: path.clone(),
+                    name: cfg.name.clone(),
+                    signature,
+                    cfg_hash,
+                    dfg_hash,
+                    body_hash,
+                    complexity: cfg_metrics(cfg).cyclomatic_complexity,
+                });
+            }
+        }
+
+        Self { files: files.into_iter().map(|(path, _)| path).collect(), functions }
+    }
+}
+
+/// A function present on one side only
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionRef {
+    pub path: String,
+    pub name: String,
+    pub signature: String,
+    pub complexity: usize,
+}
+
+/// A matched function whose CFG or DFG changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionChange {
+    pub path: String,
+    pub name: String,
+    pub signature: String,
+    pub cfg_changed: bool,
+    pub dfg_changed: bool,
+    pub complexity_before: usize,
+    pub complexity_after: usize,
+
+    /// `complexity_after - complexity_before`
+    pub complexity_delta: i64,
+}
+
+/// A removed and an added function of one file with the same body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionRename {
+    pub path: String,
+    pub old_name: String,
+    pub old_signature: String,
+    pub new_name: String,
+    pub new_signature: String,
+    pub complexity: usize,
+}
+
+/// Semantic deltas from build `a` to build `b`
+///
+/// Every list is sorted by path, then name and signature. Functions of
+/// added and removed files are listed in `added` and `removed` too.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoComparison {
+    /// Paths only in `b`
+    pub files_added: Vec<String>,
+
+    /// Paths only in `a`
+    pub files_removed: Vec<String>,
+
+    pub added: Vec<FunctionRef>,
+    pub removed: Vec<FunctionRef>,
+    pub changed: Vec<FunctionChange>,
+    pub renamed: Vec<FunctionRename>,
+
+    /// Matched functions with equal hashes
+    pub unchanged: usize,
+}
+
+impl RepoComparison {
+    /// Compare two pipeline runs
+    pub fn compute(a: &PipelineOutput, b: &PipelineOutput) -> Self {
+        Self::between(&RepoSummary::from_output(a), &RepoSummary::from_output(b))
+    }
+
+    /// Compare two summaries
+    pub fn between(a: &RepoSummary, b: &RepoSummary) -> Self {
+        let mut comparison = Self {
+            files_added: b.files.iter().filter(|path| a.files.binary_search(path).is_err()).cloned().collect(),
+            files_removed: a.files.iter().filter(|path| b.files.binary_search(path).is_err()).cloned().collect(),
+            ..Self::default()
+        };
+
+        let mut before = by_key(&a.functions);
+        let mut after = by_key(&b.functions);
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for (key, olds) in &mut before {
+            let news = after.remove(key).unwrap_or_default();
+            let matched = olds.len().min(news.len());
+            for (old, new) in olds.iter().zip(&news) {
+                comparison.record_match(old, new);
+            }
+            removed.extend(olds.drain(matched..));
+            added.extend(news.into_iter().skip(matched));
+        }
+        added.extend(after.into_values().flatten());
+
+        // Renames: the first added function of the same file with the same
+        // body, in sorted order on both sides
+        added.sort_by(|x, y| sort_key(x).cmp(&sort_key(y)));
+        removed.sort_by(|x, y| sort_key(x).cmp(&sort_key(y)));
+        let mut taken = vec![false; added.len()];
+        for old in removed {
+            let rename = added.iter().enumerate().position(|(i, new)| {
+                !taken[i] && new.path == old.path && new.name != old.name && new.body_hash == old.body_hash
+            });
+            match rename {
+                Some(i) => {
+                    taken[i] = true;
+                    comparison.renamed.push(FunctionRename {
+                        path: old.path.clone(),
+                        old_name: old.name.clone(),
+                        old_signature: old.signature.clone(),
+                        new_name: added[i].name.clone(),
+                        new_signature: added[i].signature.clone(),
+                        complexity: added[i].complexity,
+                    });
+                }
+                None => comparison.removed.push(function_ref(old)),
+            }
+        }
+        comparison.added = added.iter().zip(taken)
+            .filter(|(_, taken)| !taken)
+            .map(|(new, _)| function_ref(new))
+            .collect();
+        comparison.changed.sort_by(|x, y| (&x.path, &x.name, &x.signature).cmp(&(&y.path, &y.name, &y.signature)));
+        comparison
+    }
+
+    fn record_match(&mut self, old: &FunctionSummary, new: &FunctionSummary) {
+        let cfg_changed = old.cfg_hash != new.cfg_hash;
+        let dfg_changed = old.dfg_hash != new.dfg_hash;
+        if !cfg_changed && !dfg_changed {
+            self.unchanged += 1;
+            return;
+        }
+        self.changed.push(FunctionChange {
+            path: new.path.clone(),
+            name: new.name.clone(),
+            signature: new.signature.clone(),
+            cfg_changed,
+            dfg_changed,
+            complexity_before: old.complexity,
+            complexity_after: new.complexity,
+            complexity_delta: new.complexity as i64 - old.complexity as i64,
+        });
+    }
+
+    /// Whether the builds differ at all
+    pub fn is_empty(&self) -> bool {
+        self.files_added.is_empty() && self.files_removed.is_empty() && self.added.is_empty()
+            && self.removed.is_empty() && self.changed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+type Key<'a> = (&'a str, &'a str, &'a str);
+
+fn sort_key(function: &FunctionSummary) -> Key<'_> {
+    (&function.path, &function.name, &function.signature)
+}
+
+/// Functions by (path, name, signature), in file order per key
+fn by_key(functions: &[FunctionSummary]) -> BTreeMap<Key<'_>, Vec<&FunctionSummary>> {
+    let mut grouped: BTreeMap<Key<'_>, Vec<&FunctionSummary>> = BTreeMap::new();
+    for function in functions {
+        grouped.entry(sort_key(function)).or_default().push(function);
+    }
+    grouped
+}
+
+fn function_ref(function: &FunctionSummary) -> FunctionRef {
+    FunctionRef {
+        path: function.path.clone(),
+        name: function.name.clone(),
+        signature: function.signature.clone(),
+        complexity: function.complexity,
+    }
+}
+
+/// Plain-text table, one line per delta after a header
+///
+/// Unchanged functions are not listed.
+pub fn render_table(comparison: &RepoComparison) -> String {
+    let header = ["CHANGE", "PATH", "FUNCTION", "CFG", "DFG", "COMPLEXITY"].map(String::from);
+    let mut rows: Vec<[String; 6]> = Vec::new();
+    let row = |change: &str, path: &str, function: String, cfg: &str, dfg: &str, complexity: String| {
+        [change.to_string(), path.to_string(), function, cfg.to_string(), dfg.to_string(), complexity]
+    };
+    for path in &comparison.files_added {
+        rows.push(row("file added", path, "-".into(), "-", "-", "-".into()));
+    }
+    for path in &comparison.files_removed {
+        rows.push(row("file removed", path, "-".into(), "-", "-", "-".into()));
+    }
+    for function in &comparison.added {
+        rows.push(row("added", &function.path, function.signature.clone(), "-", "-", function.complexity.to_string()));
+    }
+    for function in &comparison.removed {
+        rows.push(row("removed", &function.path, function.signature.clone(), "-", "-", function.complexity.to_string()));
+    }
+    for change in &comparison.changed {
+        let mark = |changed| if changed { "changed" } else { "same" };
+        rows.push(row(
+            "changed",
+            &change.path,
+            change.signature.clone(),
+            mark(change.cfg_changed),
+            mark(change.dfg_changed),
+            format!("{} -> {} ({:+})", change.complexity_before, change.complexity_after, change.complexity_delta),
+        ));
+    }
+    for rename in &comparison.renamed {
+        let function = format!("{} -> {}", rename.old_signature, rename.new_signature);
+        rows.push(row("renamed", &rename.path, function, "same", "same", rename.complexity.to_string()));
+    }
+
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row.iter().zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table.push_str(&format!("{} unchanged\n", comparison.unchanged));
+    table
+}
+
+/// CFG hash with nodes numbered by position and the name left out
+fn cfg_shape_hash(cfg: &CFG, strings: &StringArena) -> String {
+    let position: HashMap<NodeId, u64> = cfg.nodes.iter().enumerate()
+        .map(|(i, node)| (node.id, i as u64))
+        .collect();
+    let mut hasher = Sha256::new();
+    for node in &cfg.nodes {
+        node.kind.canonical_hash(&mut hasher);
+        let text = node.statement.map_or("", |id| strings.resolve(id));
+        hasher.update((text.len() as u64).to_be_bytes());
+        hasher.update(text.as_bytes());
+        hasher.update([node.block_value as u8]);
+    }
+    for edge in &cfg.edges {
+        hasher.update(position.get(&edge.from).copied().unwrap_or(u64::MAX).to_be_bytes());
+        hasher.update(position.get(&edge.to).copied().unwrap_or(u64::MAX).to_be_bytes());
+        edge.kind.canonical_hash(&mut hasher);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// DFG hash with values numbered by position
+fn dfg_shape_hash(dfg: &DFG, strings: &StringArena) -> String {
+    let position: HashMap<ValueId, u64> = dfg.values.iter().enumerate()
+        .map(|(i, value)| (value.id, i as u64))
+        .collect();
+    let mut hasher = Sha256::new();
+    for value in &dfg.values {
+        value.kind.resolve(strings).canonical_hash(&mut hasher);
+        hasher.update((value.occurrences.len() as u64).to_be_bytes());
+    }
+    for edge in &dfg.edges {
+        hasher.update(position.get(&edge.from).copied().unwrap_or(u64::MAX).to_be_bytes());
+        hasher.update(position.get(&edge.to).copied().unwrap_or(u64::MAX).to_be_bytes());
+        edge.kind.canonical_hash(&mut hasher);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn sha256(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use std::path::Path;
+
+    const BASE: &[(&str, &str)] = &[
+        ("src/lib.rs", "fn same(a: i32) -> i32 { a + 1 }\n\
+                        fn edited(x: i32) -> i32 { if x > 0 { x } else { 0 } }\n\
+                        fn gone() { let y = 2; }\n\
+                        fn old_name(p: i32) -> i32 { let q = p * 3; q }\n"),
+        ("src/removed.rs", "fn only_here() {}\n"),
+    ];
+
+    const HEAD: &[(&str, &str)] = &[
+        // A new function first, so every ID after it shifts
+        ("src/lib.rs", "fn fresh(z: u8) -> u8 { z }\n\
+                        fn same(a: i32) -> i32 { a + 1 }\n\
+                        fn edited(x: i32) -> i32 { if x > 0 { if x > 9 { 9 } else { x } } else { 0 } }\n\
+                        fn new_name(p: i32) -> i32 { let q = p * 3; q }\n"),
+        ("src/added.rs", "fn brand_new() {}\n"),
+    ];
+
+    fn build(dir: &Path, files: &[(&str, &str)]) -> PipelineOutput {
+        for (path, source) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        Pipeline::default().run(dir).unwrap()
+    }
+
+    fn names(functions: &[FunctionRef]) -> Vec<(&str, &str)> {
+        functions.iter().map(|f| (f.path.as_str(), f.name.as_str())).collect()
+    }
+
+    #[test]
+    fn test_compare_reports_each_category() {
+        let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let comparison = RepoComparison::compute(&build(a.path(), BASE), &build(b.path(), HEAD));
+
+        assert_eq!(comparison.files_added, vec!["src/added.rs"]);
+        assert_eq!(comparison.files_removed, vec!["src/removed.rs"]);
+        assert_eq!(names(&comparison.added), vec![("src/added.rs", "brand_new"), ("src/lib.rs", "fresh")]);
+        assert_eq!(names(&comparison.removed), vec![("src/lib.rs", "gone"), ("src/removed.rs", "only_here")]);
+        assert_eq!(comparison.added[1].signature, "fresh(z: u8)");
+
+        assert_eq!(comparison.changed.len(), 1);
+        let edited = &comparison.changed[0];
+        assert_eq!((edited.name.as_str(), edited.cfg_changed), ("edited", true));
+        assert_eq!((edited.complexity_before, edited.complexity_after, edited.complexity_delta), (2, 3, 1));
+
+        assert_eq!(comparison.renamed.len(), 1);
+        assert_eq!(comparison.renamed[0].old_signature, "old_name(p: i32)");
+        assert_eq!(comparison.renamed[0].new_signature, "new_name(p: i32)");
+        assert_eq!(comparison.unchanged, 1);
+    }
+
+    #[test]
+    fn test_compare_is_deterministic_and_empty_for_same_tree() {
+        let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let (base, head) = (build(a.path(), BASE), build(b.path(), HEAD));
+        let first = serde_json::to_string(&RepoComparison::compute(&base, &head)).unwrap();
+        assert_eq!(serde_json::to_string(&RepoComparison::compute(&base, &head)).unwrap(), first);
+
+        let same = RepoComparison::compute(&base, &base);
+        assert!(same.is_empty());
+        assert_eq!(same.unchanged, 5);
+    }
+
+    #[test]
+    fn test_duplicate_names_pair_in_file_order() {
+        let one = "struct A; struct B;\nimpl A { fn new() -> Self { A } }\nimpl B { fn new() -> Self { B } }\n";
+        let two = "struct A; struct B;\nimpl A { fn new() -> Self { A } }\nimpl B { fn new() -> Self { let b = B; b } }\n";
+        let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let comparison = RepoComparison::compute(
+            &build(a.path(), &[("lib.rs", one)]),
+            &build(b.path(), &[("lib.rs", two)]),
+        );
+
+        assert_eq!(comparison.unchanged, 1);
+        assert_eq!(comparison.changed.len(), 1);
+        assert_eq!(comparison.changed[0].signature, "new()");
+        assert!(comparison.added.is_empty() && comparison.removed.is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+//! Config loading with per-field provenance (Path B6)
+//!
+//! Tracks where every field's effective value came from so
+//! `vcr config show` can explain the resolved config.
+
+use crate::config::{ConfigError, ValoriConfig, ENV_PREFIX};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Default config file name
+pub const DEFAULT_CONFIG_FILE: &str = "vtr.toml";
+
+/// Commented starter config (the repository's own vtr.toml)
+pub const CONFIG_TEMPLATE: &str = include_str!("../../vtr.toml");
+
+/// Every config field as (section, field)
+pub const CONFIG_FIELDS: &[(&str, &str)] = &[
+    ("io", "mode"),
+    ("io", "uring_enabled"),
+    ("snapshot", "path"),
+    ("snapshot", "auto_save"),
+    ("snapshot", "max_snapshots"),
+    ("snapshot", "max_age_secs"),
+    ("snapshot", "save_overlays"),
+    ("snapshot", "compression"),
+    ("execution", "parallel"),
+    ("execution", "thread_count"),
+    ("execution", "chunk_size"),
+    ("query", "cache_capacity"),
+    ("query", "cache_paranoid"),
+    ("query", "query_dir"),
+    ("query", "verify_epochs"),
+    ("query", "incremental_points_to"),
+    ("verification", "verify_determinism"),
+    ("verification", "strict_validation"),
+    ("analysis", "dead_code_roots"),
+    ("analysis", "pub_items_are_roots"),
+    ("analysis", "tests_are_roots"),
+    ("analysis", "taint_sources"),
+    ("analysis", "taint_sinks"),
+    ("analysis", "taint_mode"),
+    ("audit", "sample_rate"),
+    ("parse", "on_parse_error"),
+    ("parse", "normalize_line_endings"),
+    ("events", "capacity"),
+    ("events", "on_full"),
+    ("limits", "max_cfg_nodes_per_function"),
+    ("limits", "max_nodes_per_file"),
+    ("limits", "max_total_cpg_nodes"),
+    ("limits", "allow_degraded_total"),
+];
+
+/// Environment variable name for a field
+pub fn env_var_for(section: &str, field: &str) -> String {
+    format!("{}{}_{}", ENV_PREFIX, section, field).to_uppercase()
+}
+
+/// Where a field's value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Built-in default
+    Default,
+
+    /// Config file
+    File(PathBuf),
+
+    /// Environment variable
+    Env(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::File(path) => write!(f, "file {}", path.display()),
+            ConfigSource::Env(var) => write!(f, "env {}", var),
+        }
+    }
+}
+
+/// Resolved config plus per-field provenance
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    /// Effective config
+    pub config: ValoriConfig,
+
+    /// Source per "section.field"
+    pub sources: BTreeMap<String, ConfigSource>,
+}
+
+impl ResolvedConfig {
+    /// Source of one field
+    pub fn source(&self, section: &str, field: &str) -> Option<&ConfigSource> {
+        self.sources.get(&format!("{}.{}", section, field))
+    }
+
+    /// Render as TOML, annotating each field with its source
+    pub fn to_annotated_toml(&self) -> Result<String, ConfigError> {
+        let plain = toml::to_string(&self.config).map_err(|e| ConfigError::InvalidValue {
+            field: "config",
+            message: e.to_string(),
+        })?;
+
+        let mut out = String::from("# Effective configuration (defaults < file < env)\n\n");
+        let mut section = String::new();
+        for line in plain.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('[') && trimmed.ends_with(']') {
+                section = trimmed.trim_matches(|c| c == '[' || c == ']').to_string();
+                out.push_str(line);
+            } else if let Some((key, _)) = trimmed.split_once(" = ") {
+                let source = self.source(&section, key)
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| "default".to_string());
+                out.push_str(&format!("{}  # {}", line, source));
+            } else {
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
+
+        Ok(out)
+    }
+}
+
+/// Config loader: defaults → file → env → validate
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    /// Explicit config file
+    path: Option<PathBuf>,
+
+    /// Directory searched for vtr.toml when no path is given
+    search_dir: Option<PathBuf>,
+
+    /// Environment (None = process environment)
+    env: Option<Vec<(String, String)>>,
+}
+
+impl ConfigLoader {
+    /// Create a loader searching ./vtr.toml and the process environment
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use an explicit config file
+    pub fn with_file(mut self, path: Option<impl Into<PathBuf>>) -> Self {
+        self.path = path.map(Into::into);
+        self
+    }
+
+    /// Search a directory for vtr.toml instead of the current directory
+    pub fn with_search_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.search_dir = Some(dir.into());
+        self
+    }
+
+    /// Use the given variables instead of the process environment
+    pub fn with_env(mut self, vars: Vec<(String, String)>) -> Self {
+        self.env = Some(vars);
+        self
+    }
+
+    /// Load, apply overrides and validate
+    pub fn load(&self) -> Result<ResolvedConfig, Vec<ConfigError>> {
+        let mut sources: BTreeMap<String, ConfigSource> = CONFIG_FIELDS.iter()
+            .map(|(section, field)| (format!("{}.{}", section, field), ConfigSource::Default))
+            .collect();
+
+        let mut config = match self.config_file() {
+            Some(path) => {
+                let config = ValoriConfig::from_file(&path).map_err(|e| vec![e])?;
+                for key in file_keys(&path) {
+                    sources.insert(key, ConfigSource::File(path.clone()));
+                }
+                config
+            }
+            None => ValoriConfig::default(),
+        };
+
+        let env = match &self.env {
+            Some(vars) => vars.clone(),
+            None => std::env::vars().collect(),
+        };
+        config.apply_overrides(env.clone())?;
+        for (section, field) in CONFIG_FIELDS {
+            let var = env_var_for(section, field);
+            if env.iter().any(|(k, _)| *k == var) {
+                sources.insert(format!("{}.{}", section, field), ConfigSource::Env(var));
+            }
+        }
+
+        config.validate()?;
+        Ok(ResolvedConfig { config, sources })
+    }
+
+    /// Config file to read, if any
+    fn config_file(&self) -> Option<PathBuf> {
+        if let Some(path) = &self.path {
+            return Some(path.clone());
+        }
+
+        let dir = self.search_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let candidate = dir.join(DEFAULT_CONFIG_FILE);
+        candidate.exists().then_some(candidate)
+    }
+}
+
+/// Write the starter config into a directory
+///
+/// Refuses to overwrite an existing file unless `force` is set.
+pub fn write_template(dir: &Path, force: bool) -> Result<PathBuf, ConfigError> {
+    let path = dir.join(DEFAULT_CONFIG_FILE);
+    if path.exists() && !force {
+        return Err(ConfigError::AlreadyExists { path });
+    }
+
+    std::fs::write(&path, CONFIG_TEMPLATE).map_err(|e| ConfigError::Write {
+        path: path.clone(),
+        message: e.to_string(),
+    })?;
+
+    Ok(path)
+}
+
+/// "section.field" keys present in a config file
+fn file_keys(path: &Path) -> Vec<String> {
+    let table: toml::Table = match std::fs::read_to_string(path).ok().and_then(|c| c.parse().ok()) {
+        Some(table) => table,
+        None => return Vec::new(),
+    };
+
+    let mut keys = Vec::new();
+    for (section, value) in &table {
+        if let Some(fields) = value.as_table() {
+            keys.extend(fields.keys().map(|field| format!("{}.{}", section, field)));
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_template_is_valid_config() {
+        let config: ValoriConfig = toml::from_str(CONFIG_TEMPLATE).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_fields_match_env_overrides() {
+        for (section, field) in CONFIG_FIELDS {
+            let mut config = ValoriConfig::default();
+            let var = env_var_for(section, field);
+            // Every listed field must be overridable (value may still be invalid)
+            let result = config.apply_overrides(vec![(var.clone(), String::new())]);
+            if let Err(errors) = result {
+                assert!(
+                    errors.iter().all(|e| !e.to_string().contains("unknown variable")),
+                    "{} is not overridable", var
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_init_then_show_round_trips() {
+        let dir = TempDir::new().unwrap();
+        let path = write_template(dir.path(), false).unwrap();
+
+        let resolved = ConfigLoader::new()
+            .with_search_dir(dir.path())
+            .with_env(vec![])
+            .load()
+            .unwrap();
+        assert_eq!(resolved.source("io", "mode"), Some(&ConfigSource::File(path.clone())));
+        // Commented out in the template
+        assert_eq!(resolved.source("snapshot", "max_snapshots"), Some(&ConfigSource::Default));
+
+        let shown = resolved.to_annotated_toml().unwrap();
+        let reparsed: ValoriConfig = toml::from_str(&shown).unwrap();
+        assert_eq!(toml::to_string(&reparsed).unwrap(), toml::to_string(&resolved.config).unwrap());
+    }
+
+    #[test]
+    fn test_init_refuses_overwrite() {
+        let dir = TempDir::new().unwrap();
+        write_template(dir.path(), false).unwrap();
+
+        assert!(matches!(write_template(dir.path(), false), Err(ConfigError::AlreadyExists { .. })));
+        assert!(write_template(dir.path(), true).is_ok());
+    }
+
+    #[test]
+    fn test_env_provenance() {
+        let dir = TempDir::new().unwrap();
+        write_template(dir.path(), false).unwrap();
+
+        let resolved = ConfigLoader::new()
+            .with_search_dir(dir.path())
+            .with_env(vec![
+                ("VCR_EXECUTION_PARALLEL".to_string(), "true".to_string()),
+                ("VCR_EXECUTION_THREAD_COUNT".to_string(), "8".to_string()),
+            ])
+            .load()
+            .unwrap();
+
+        assert_eq!(resolved.config.execution.thread_count, 8);
+        assert_eq!(
+            resolved.source("execution", "thread_count"),
+            Some(&ConfigSource::Env("VCR_EXECUTION_THREAD_COUNT".to_string()))
+        );
+
+        let shown = resolved.to_annotated_toml().unwrap();
+        assert!(shown.contains("thread_count = 8  # env VCR_EXECUTION_THREAD_COUNT"), "{}", shown);
+        assert!(shown.contains("uring_enabled = false  # file"), "{}", shown);
+    }
+
+    #[test]
+    fn test_no_file_is_all_defaults() {
+        let dir = TempDir::new().unwrap();
+        let resolved = ConfigLoader::new()
+            .with_search_dir(dir.path())
+            .with_env(vec![])
+            .load()
+            .unwrap();
+
+        assert!(resolved.sources.values().all(|s| *s == ConfigSource::Default));
+    }
+}
@@ -1,79 +1,966 @@
 //! Operational configuration (Path B6)
+//!
+//! Precedence (later wins):
+//! 1. Built-in defaults
+//! 2. Config file (`--config` or `./vtr.toml`)
+//! 3. `VCR_<SECTION>_<FIELD>` environment variables
+//! 4. CLI flags
+//!
+//! Unknown keys are rejected. All validation errors are reported at once.
 
+pub mod loader;
+
+pub use loader::{ConfigLoader, ConfigSource, ResolvedConfig};
+
+use crate::io::IOMode;
+use crate::storage::frame::{Compression, DEFAULT_ZSTD_LEVEL};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Environment variable prefix for overrides
+pub const ENV_PREFIX: &str = "VCR_";
+
+/// Upper bound for execution.thread_count
+pub const MAX_THREAD_COUNT: usize = 1024;
+
+/// Configuration error
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConfigError {
+    /// Config file could not be read
+    #[error("Failed to read config {path}: {message}")]
+    Read { path: PathBuf, message: String },
+
+    /// Config file could not be parsed (includes unknown keys)
+    #[error("Failed to parse config {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+
+    /// A field has an invalid value
+    #[error("Invalid {field}: {message}")]
+    InvalidValue { field: &'static str, message: String },
+
+    /// Snapshot directory cannot be written
+    #[error("Snapshot path {path} is not writable: {message}")]
+    SnapshotPath { path: PathBuf, message: String },
+
+    /// Refused to overwrite an existing config file
+    #[error("{path} already exists (use --force to overwrite)")]
+    AlreadyExists { path: PathBuf },
+
+    /// Config file could not be written
+    #[error("Failed to write config {path}: {message}")]
+    Write { path: PathBuf, message: String },
+
+    /// An environment override could not be applied
+    #[error("Invalid environment override {var}: {message}")]
+    EnvOverride { var: String, message: String },
+}
 
 /// VTR configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ValoriConfig {
     /// I/O configuration
     pub io: IOConfig,
-    
+
     /// Snapshot configuration
     pub snapshot: SnapshotConfig,
-    
+
     /// Execution configuration
     pub execution: ExecutionConfig,
+
+    /// Query configuration
+    #[serde(default)]
+    pub query: QueryConfig,
+
+    /// Verification configuration
+    #[serde(default)]
+    pub verification: VerificationConfig,
+
+    /// Analysis configuration
+    #[serde(default)]
+    pub analysis: AnalysisConfig,
+
+    /// Incremental audit configuration
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Parse configuration
+    #[serde(default)]
+    pub parse: ParseConfig,
+
+    /// Epoch event subscription configuration
+    #[serde(default)]
+    pub events: EventsConfig,
+
+    /// Graph size budgets
+    #[serde(default)]
+    pub limits: LimitsConfig,
 }
 
 /// I/O configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct IOConfig {
     /// I/O mode: "auto", "hot", "cold"
-    pub mode: String,
-    
+    pub mode: IOMode,
+
     /// Enable io_uring (Linux-only)
     pub uring_enabled: bool,
 }
 
 /// Snapshot configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SnapshotConfig {
     /// Snapshot directory path
     pub path: PathBuf,
-    
+
     /// Auto-save on completion
     pub auto_save: bool,
+
+    /// Keep at most this many snapshots (None = unlimited)
+    #[serde(default)]
+    pub max_snapshots: Option<usize>,
+
+    /// Prune snapshots older than this many seconds (None = never)
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+
+    /// Auto-save repos with unsaved-buffer overlays too
+    #[serde(default)]
+    pub save_overlays: bool,
+
+    /// Payload compression: `"none"` or `{ zstd = { level = N } }`
+    #[serde(default)]
+    pub compression: Compression,
+}
+
+impl SnapshotConfig {
+    /// Retention policy for this configuration
+    pub fn retention(&self) -> crate::storage::RetentionPolicy {
+        crate::storage::RetentionPolicy {
+            max_snapshots: self.max_snapshots,
+            max_age_secs: self.max_age_secs,
+        }
+    }
 }
 
 /// Execution configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ExecutionConfig {
     /// Enable parallel execution
     pub parallel: bool,
-    
+
     /// Thread count (0 = auto)
     pub thread_count: usize,
+
+    /// Nodes per chunk of large `find`/`filter` scans (0 = never chunk)
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+}
+
+fn default_chunk_size() -> usize {
+    crate::execution::scheduler::DEFAULT_CHUNK_SIZE
+}
+
+/// Query configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueryConfig {
+    /// Maximum cached query results (0 = cache disabled)
+    pub cache_capacity: usize,
+
+    /// Recompute every cache hit and crash on divergence
+    pub cache_paranoid: bool,
+
+    /// Directory of saved `*.json` queries for `vcr query --name` (None = no saved queries)
+    #[serde(default)]
+    pub query_dir: Option<PathBuf>,
+
+    /// Rehash the frozen CPG before every scoped query and crash on
+    /// divergence (debug builds only)
+    #[serde(default = "default_verify_epochs")]
+    pub verify_epochs: bool,
+
+    /// Update the last `may_alias` points-to map when the CPG changes
+    /// instead of recomputing it (additions only; removals recompute)
+    #[serde(default)]
+    pub incremental_points_to: bool,
+}
+
+fn default_verify_epochs() -> bool {
+    true
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            cache_capacity: 128,
+            cache_paranoid: false,
+            query_dir: None,
+            verify_epochs: default_verify_epochs(),
+            incremental_points_to: false,
+        }
+    }
+}
+
+/// Verification configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerificationConfig {
+    /// Build every ingest twice and crash on hash divergence
+    pub verify_determinism: bool,
+
+    /// Validate every fused CPG and fail on any structural error
+    #[serde(default)]
+    pub strict_validation: bool,
+}
+
+/// Analysis configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnalysisConfig {
+    /// Function names always treated as live by dead-code detection
+    pub dead_code_roots: Vec<String>,
+
+    /// Treat `pub` functions at a crate root (lib.rs, main.rs, src/bin) as live
+    pub pub_items_are_roots: bool,
+
+    /// Treat `#[test]` functions as live
+    pub tests_are_roots: bool,
+
+    /// Calls (by callee name) whose result `vcr lint taint` treats as tainted
+    #[serde(default = "default_taint_sources")]
+    pub taint_sources: Vec<String>,
+
+    /// Calls (by callee name) whose arguments `vcr lint taint` reports when tainted
+    #[serde(default = "default_taint_sinks")]
+    pub taint_sinks: Vec<String>,
+
+    /// How taint crosses calls into functions of the repo
+    #[serde(default)]
+    pub taint_mode: TaintMode,
+}
+
+fn default_taint_sources() -> Vec<String> {
+    ["read_line", "read_to_string", "var"].map(String::from).to_vec()
+}
+
+fn default_taint_sinks() -> Vec<String> {
+    ["exec", "execute", "query", "system"].map(String::from).to_vec()
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            dead_code_roots: vec!["main".to_string()],
+            pub_items_are_roots: true,
+            tests_are_roots: true,
+            taint_sources: default_taint_sources(),
+            taint_sinks: default_taint_sinks(),
+            taint_mode: TaintMode::default(),
+        }
+    }
+}
+
+/// How interprocedural taint crosses a call (see `analysis::summaries`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaintMode {
+    /// Apply the callee's function summary (computed once per function)
+    #[default]
+    Summary,
+
+    /// Descend into the callee for every call path
+    Inline,
+}
+
+/// Incremental audit configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Fraction of incremental file rebuilds re-analyzed from scratch (0.0 – 1.0)
+    pub sample_rate: f32,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self { sample_rate: 0.01 }
+    }
+}
+
+/// Parse configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParseConfig {
+    /// What to do with files whose parse tree has syntax errors
+    pub on_parse_error: ParseErrorPolicy,
+
+    /// Hash and parse files with `\r\n` read as `\n`, so CRLF and LF
+    /// checkouts produce the same snapshot (see `io::line_endings`)
+    #[serde(default)]
+    pub normalize_line_endings: bool,
+}
+
+/// Handling of files Tree-sitter could only parse with error recovery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseErrorPolicy {
+    /// Build no semantics for the file and report it (fail-closed default)
+    #[default]
+    Skip,
+
+    /// Analyze the recovered tree anyway and report it
+    IncludeBestEffort,
+
+    /// Abort the run
+    Fail,
+}
+
+/// Epoch event subscription configuration (see `api::events`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventsConfig {
+    /// Undelivered events a subscriber may hold
+    pub capacity: usize,
+
+    /// What a commit does when a subscriber holds `capacity` events
+    pub on_full: SubscriberFullPolicy,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self { capacity: 64, on_full: SubscriberFullPolicy::Fail }
+    }
+}
+
+/// Handling of a subscriber that stopped receiving epoch events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriberFullPolicy {
+    /// Refuse the commit; the repo keeps its previous build (fail-closed default)
+    #[default]
+    Fail,
+
+    /// Commit and disconnect the subscriber
+    Unsubscribe,
+}
+
+/// Graph size budgets applied while ingesting
+///
+/// A function or file over its budget is degraded (see
+/// `semantic::SemanticStatus`); a CPG over `max_total_cpg_nodes` fails the
+/// ingest unless `allow_degraded_total` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// CFG nodes one function may have; larger functions get no CFG or DFG
+    pub max_cfg_nodes_per_function: usize,
+
+    /// CFG nodes plus DFG values one file may have; functions past the
+    /// budget (in source order) get no CFG or DFG
+    pub max_nodes_per_file: usize,
+
+    /// CPG nodes one ingest may fuse
+    pub max_total_cpg_nodes: usize,
+
+    /// Keep a CPG over `max_total_cpg_nodes` (with a warning) instead of failing
+    pub allow_degraded_total: bool,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_cfg_nodes_per_function: 100_000,
+            max_nodes_per_file: 1_000_000,
+            max_total_cpg_nodes: 50_000_000,
+            allow_degraded_total: false,
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// No budgets (epochs built outside a configured pipeline)
+    pub fn unlimited() -> Self {
+        Self {
+            max_cfg_nodes_per_function: usize::MAX,
+            max_nodes_per_file: usize::MAX,
+            max_total_cpg_nodes: usize::MAX,
+            allow_degraded_total: false,
+        }
+    }
 }
 
 impl Default for ValoriConfig {
     fn default() -> Self {
         Self {
             io: IOConfig {
-                mode: "auto".to_string(),
+                mode: IOMode::Auto,
                 uring_enabled: false,
             },
             snapshot: SnapshotConfig {
                 path: PathBuf::from("./snapshots"),
                 auto_save: true,
+                max_snapshots: None,
+                max_age_secs: None,
+                save_overlays: false,
+                compression: Compression::None,
             },
             execution: ExecutionConfig {
                 parallel: false,
                 thread_count: 0,
+                chunk_size: default_chunk_size(),
             },
+            query: QueryConfig::default(),
+            verification: VerificationConfig::default(),
+            analysis: AnalysisConfig::default(),
+            audit: AuditConfig::default(),
+            parse: ParseConfig::default(),
+            events: EventsConfig::default(),
+            limits: LimitsConfig::default(),
+        }
+    }
+}
+
+impl ValoriConfig {
+    /// Load config: file (explicit or ./vtr.toml) → env overrides → validate
+    pub fn load(path: Option<&Path>) -> Result<Self, Vec<ConfigError>> {
+        ConfigLoader::new().with_file(path).load().map(|resolved| resolved.config)
+    }
+
+    /// Parse a config file
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|e| ConfigError::Read {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+
+        toml::from_str(&content).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.message().to_string(),
+        })
+    }
+
+    /// Apply `VCR_*` overrides from the process environment
+    pub fn apply_env_overrides(&mut self) -> Result<(), Vec<ConfigError>> {
+        self.apply_overrides(std::env::vars())
+    }
+
+    /// Apply `VCR_*` overrides from the given variables
+    ///
+    /// Variables without the prefix are ignored; unknown `VCR_*` names are errors.
+    pub fn apply_overrides<I>(&mut self, vars: I) -> Result<(), Vec<ConfigError>>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut vars: Vec<_> = vars.into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX))
+            .collect();
+        // Deterministic application and error order
+        vars.sort();
+
+        let errors: Vec<_> = vars.iter()
+            .filter_map(|(key, value)| self.apply_override(key, value).err())
+            .collect();
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Apply one override
+    fn apply_override(&mut self, var: &str, value: &str) -> Result<(), ConfigError> {
+        let err = |message: String| ConfigError::EnvOverride { var: var.to_string(), message };
+
+        match var {
+            "VCR_IO_MODE" => self.io.mode = parse_mode(value).map_err(err)?,
+            "VCR_IO_URING_ENABLED" => self.io.uring_enabled = parse_value(value).map_err(err)?,
+            "VCR_SNAPSHOT_PATH" => self.snapshot.path = PathBuf::from(value),
+            "VCR_SNAPSHOT_AUTO_SAVE" => self.snapshot.auto_save = parse_value(value).map_err(err)?,
+            "VCR_SNAPSHOT_MAX_SNAPSHOTS" => self.snapshot.max_snapshots = parse_optional(value).map_err(err)?,
+            "VCR_SNAPSHOT_MAX_AGE_SECS" => self.snapshot.max_age_secs = parse_optional(value).map_err(err)?,
+            "VCR_SNAPSHOT_SAVE_OVERLAYS" => self.snapshot.save_overlays = parse_value(value).map_err(err)?,
+            "VCR_SNAPSHOT_COMPRESSION" => self.snapshot.compression = parse_compression(value).map_err(err)?,
+            "VCR_EXECUTION_PARALLEL" => self.execution.parallel = parse_value(value).map_err(err)?,
+            "VCR_EXECUTION_THREAD_COUNT" => self.execution.thread_count = parse_value(value).map_err(err)?,
+            "VCR_EXECUTION_CHUNK_SIZE" => self.execution.chunk_size = parse_value(value).map_err(err)?,
+            "VCR_QUERY_CACHE_CAPACITY" => self.query.cache_capacity = parse_value(value).map_err(err)?,
+            "VCR_QUERY_CACHE_PARANOID" => self.query.cache_paranoid = parse_value(value).map_err(err)?,
+            "VCR_QUERY_VERIFY_EPOCHS" => self.query.verify_epochs = parse_value(value).map_err(err)?,
+            "VCR_QUERY_INCREMENTAL_POINTS_TO" => {
+                self.query.incremental_points_to = parse_value(value).map_err(err)?
+            }
+            "VCR_QUERY_QUERY_DIR" => {
+                self.query.query_dir = Some(value.trim()).filter(|v| !v.is_empty()).map(PathBuf::from)
+            }
+            "VCR_VERIFICATION_VERIFY_DETERMINISM" => {
+                self.verification.verify_determinism = parse_value(value).map_err(err)?
+            }
+            "VCR_VERIFICATION_STRICT_VALIDATION" => {
+                self.verification.strict_validation = parse_value(value).map_err(err)?
+            }
+            "VCR_ANALYSIS_DEAD_CODE_ROOTS" => self.analysis.dead_code_roots = parse_list(value),
+            "VCR_ANALYSIS_PUB_ITEMS_ARE_ROOTS" => {
+                self.analysis.pub_items_are_roots = parse_value(value).map_err(err)?
+            }
+            "VCR_ANALYSIS_TESTS_ARE_ROOTS" => self.analysis.tests_are_roots = parse_value(value).map_err(err)?,
+            "VCR_ANALYSIS_TAINT_SOURCES" => self.analysis.taint_sources = parse_list(value),
+            "VCR_ANALYSIS_TAINT_SINKS" => self.analysis.taint_sinks = parse_list(value),
+            "VCR_ANALYSIS_TAINT_MODE" => self.analysis.taint_mode = parse_taint_mode(value).map_err(err)?,
+            "VCR_AUDIT_SAMPLE_RATE" => self.audit.sample_rate = parse_value(value).map_err(err)?,
+            "VCR_PARSE_ON_PARSE_ERROR" => self.parse.on_parse_error = parse_policy(value).map_err(err)?,
+            "VCR_PARSE_NORMALIZE_LINE_ENDINGS" => {
+                self.parse.normalize_line_endings = parse_value(value).map_err(err)?
+            }
+            "VCR_EVENTS_CAPACITY" => self.events.capacity = parse_value(value).map_err(err)?,
+            "VCR_EVENTS_ON_FULL" => self.events.on_full = parse_full_policy(value).map_err(err)?,
+            "VCR_LIMITS_MAX_CFG_NODES_PER_FUNCTION" => {
+                self.limits.max_cfg_nodes_per_function = parse_value(value).map_err(err)?
+            }
+            "VCR_LIMITS_MAX_NODES_PER_FILE" => self.limits.max_nodes_per_file = parse_value(value).map_err(err)?,
+            "VCR_LIMITS_MAX_TOTAL_CPG_NODES" => self.limits.max_total_cpg_nodes = parse_value(value).map_err(err)?,
+            "VCR_LIMITS_ALLOW_DEGRADED_TOTAL" => {
+                self.limits.allow_degraded_total = parse_value(value).map_err(err)?
+            }
+            _ => return Err(err("unknown variable".to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Validate the whole config, collecting every error
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        if self.execution.thread_count > MAX_THREAD_COUNT {
+            errors.push(ConfigError::InvalidValue {
+                field: "execution.thread_count",
+                message: format!("{} exceeds maximum of {}", self.execution.thread_count, MAX_THREAD_COUNT),
+            });
+        }
+
+        if self.snapshot.max_snapshots == Some(0) {
+            errors.push(ConfigError::InvalidValue {
+                field: "snapshot.max_snapshots",
+                message: "must be at least 1 (omit for unlimited)".to_string(),
+            });
+        }
+
+        if let Err(message) = self.snapshot.compression.validate() {
+            errors.push(ConfigError::InvalidValue {
+                field: "snapshot.compression",
+                message,
+            });
+        }
+
+        if !(0.0..=1.0).contains(&self.audit.sample_rate) {
+            errors.push(ConfigError::InvalidValue {
+                field: "audit.sample_rate",
+                message: format!("{} is not between 0.0 and 1.0", self.audit.sample_rate),
+            });
+        }
+
+        if self.events.capacity == 0 {
+            errors.push(ConfigError::InvalidValue {
+                field: "events.capacity",
+                message: "must be at least 1".to_string(),
+            });
+        }
+
+        for (field, value) in [
+            ("limits.max_cfg_nodes_per_function", self.limits.max_cfg_nodes_per_function),
+            ("limits.max_nodes_per_file", self.limits.max_nodes_per_file),
+            ("limits.max_total_cpg_nodes", self.limits.max_total_cpg_nodes),
+        ] {
+            if value == 0 {
+                errors.push(ConfigError::InvalidValue { field, message: "must be at least 1".to_string() });
+            }
+        }
+
+        if let Err(message) = check_writable_dir(&self.snapshot.path) {
+            errors.push(ConfigError::SnapshotPath {
+                path: self.snapshot.path.clone(),
+                message,
+            });
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+/// Parse an I/O mode string
+fn parse_mode(value: &str) -> Result<IOMode, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "auto" => Ok(IOMode::Auto),
+        "hot" => Ok(IOMode::Hot),
+        "cold" => Ok(IOMode::Cold),
+        other => Err(format!("'{}' is not one of auto, hot, cold", other)),
+    }
+}
+
+/// Parse an `on_parse_error` policy string
+fn parse_policy(value: &str) -> Result<ParseErrorPolicy, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "skip" => Ok(ParseErrorPolicy::Skip),
+        "include_best_effort" => Ok(ParseErrorPolicy::IncludeBestEffort),
+        "fail" => Ok(ParseErrorPolicy::Fail),
+        other => Err(format!("'{}' is not one of skip, include_best_effort, fail", other)),
+    }
+}
+
+/// Parse an `on_full` subscriber policy string
+fn parse_full_policy(value: &str) -> Result<SubscriberFullPolicy, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "fail" => Ok(SubscriberFullPolicy::Fail),
+        "unsubscribe" => Ok(SubscriberFullPolicy::Unsubscribe),
+        other => Err(format!("'{}' is not one of fail, unsubscribe", other)),
+    }
+}
+
+/// Parse a `taint_mode` string
+fn parse_taint_mode(value: &str) -> Result<TaintMode, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "summary" => Ok(TaintMode::Summary),
+        "inline" => Ok(TaintMode::Inline),
+        other => Err(format!("'{}' is not one of summary, inline", other)),
+    }
+}
+
+/// Parse a compression setting ("none", "zstd" or "zstd:<level>")
+fn parse_compression(value: &str) -> Result<Compression, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "none" => Ok(Compression::None),
+        "zstd" => Ok(Compression::Zstd { level: DEFAULT_ZSTD_LEVEL }),
+        other => match other.strip_prefix("zstd:") {
+            Some(level) => parse_value(level).map(|level| Compression::Zstd { level }),
+            None => Err(format!("'{}' is not one of none, zstd, zstd:<level>", value)),
+        },
+    }
+}
+
+/// Parse a scalar value
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value.trim().parse().map_err(|e| format!("'{}': {}", value, e))
+}
+
+/// Parse an optional value ("" or "none" = None)
+fn parse_optional<T: std::str::FromStr>(value: &str) -> Result<Option<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    match value.trim() {
+        "" | "none" => Ok(None),
+        v => parse_value(v).map(Some),
+    }
+}
+
+/// Parse a comma-separated list (empty entries dropped)
+fn parse_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Check that a directory exists and is writable, or can be created
+fn check_writable_dir(path: &Path) -> Result<(), String> {
+    // Walk up to the nearest existing ancestor
+    let mut existing = path;
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => existing = parent,
+            _ => return Ok(()),  // Relative path under cwd
         }
     }
+
+    let metadata = std::fs::metadata(existing).map_err(|e| e.to_string())?;
+    if !metadata.is_dir() {
+        return Err(format!("{} is not a directory", existing.display()));
+    }
+    if metadata.permissions().readonly() {
+        return Err(format!("{} is read-only", existing.display()));
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
+
+    const MINIMAL: &str = r#"
+        [io]
+        mode = "hot"
+        uring_enabled = false
+
+        [snapshot]
+        path = "./snapshots"
+        auto_save = false
+
+        [execution]
+        parallel = false
+        thread_count = 0
+    "#;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
 
     #[test]
     fn test_default_config() {
         let config = ValoriConfig::default();
-        assert_eq!(config.io.mode, "auto");
+        assert_eq!(config.io.mode, IOMode::Auto);
         assert!(!config.io.uring_enabled);
         assert!(config.snapshot.auto_save);
+        assert_eq!(config.query.cache_capacity, 128);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_query_section_optional() {
+        let config: ValoriConfig = toml::from_str(MINIMAL).unwrap();
+
+        assert_eq!(config.io.mode, IOMode::Hot);
+        assert_eq!(config.query.cache_capacity, QueryConfig::default().cache_capacity);
+        assert!(!config.query.cache_paranoid);
+        assert!(config.query.verify_epochs);
+        assert!(!config.query.incremental_points_to);
+        assert_eq!(config.analysis.taint_mode, TaintMode::Summary);
+        assert!(!config.verification.verify_determinism);
+        assert!(!config.verification.strict_validation);
+        assert_eq!(config.execution.chunk_size, crate::execution::scheduler::DEFAULT_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn test_strict_validation_override() {
+        let mut config = ValoriConfig::default();
+        config.apply_overrides(vars(&[("VCR_VERIFICATION_STRICT_VALIDATION", "true")])).unwrap();
+        assert!(config.verification.strict_validation);
+
+        let errors = config.apply_overrides(vars(&[("VCR_VERIFICATION_STRICT_VALIDATION", "yes")])).unwrap_err();
+        assert!(matches!(errors[0], ConfigError::EnvOverride { .. }));
+    }
+
+    #[test]
+    fn test_unknown_key_rejected() {
+        let typo = MINIMAL.replace("parallel = false", "paralel = true");
+        let err = toml::from_str::<ValoriConfig>(&typo).unwrap_err();
+        assert!(err.message().contains("paralel"), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_io_mode_rejected() {
+        let bad = MINIMAL.replace("\"hot\"", "\"warm\"");
+        assert!(toml::from_str::<ValoriConfig>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_from_file_reports_path() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("vtr.toml");
+        std::fs::write(&path, MINIMAL.replace("auto_save", "autosave")).unwrap();
+
+        match ValoriConfig::from_file(&path) {
+            Err(ConfigError::Parse { path: p, message }) => {
+                assert_eq!(p, path);
+                assert!(message.contains("autosave"));
+            }
+            other => panic!("Expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let mut config = ValoriConfig::default();
+        config.apply_overrides(vars(&[
+            ("VCR_EXECUTION_PARALLEL", "true"),
+            ("VCR_EXECUTION_THREAD_COUNT", "8"),
+            ("VCR_EXECUTION_CHUNK_SIZE", "1024"),
+            ("VCR_IO_MODE", "Cold"),
+            ("VCR_SNAPSHOT_MAX_SNAPSHOTS", "5"),
+            ("VCR_SNAPSHOT_MAX_AGE_SECS", "none"),
+            ("PATH", "/usr/bin"),
+        ])).unwrap();
+
+        assert!(config.execution.parallel);
+        assert_eq!(config.execution.thread_count, 8);
+        assert_eq!(config.execution.chunk_size, 1024);
+        assert_eq!(config.io.mode, IOMode::Cold);
+        assert_eq!(config.snapshot.max_snapshots, Some(5));
+        assert_eq!(config.snapshot.max_age_secs, None);
+    }
+
+    #[test]
+    fn test_analysis_overrides() {
+        let mut config = ValoriConfig::default();
+        assert_eq!(config.analysis.dead_code_roots, vec!["main"]);
+
+        config.apply_overrides(vars(&[
+            ("VCR_ANALYSIS_DEAD_CODE_ROOTS", "main, start,,run"),
+            ("VCR_ANALYSIS_TESTS_ARE_ROOTS", "false"),
+        ])).unwrap();
+
+        assert_eq!(config.analysis.dead_code_roots, vec!["main", "start", "run"]);
+        assert!(!config.analysis.tests_are_roots);
+        assert!(config.analysis.pub_items_are_roots);
+
+        config.apply_overrides(vars(&[
+            ("VCR_ANALYSIS_TAINT_SINKS", "exec, spawn"),
+            ("VCR_ANALYSIS_TAINT_MODE", "Inline"),
+        ])).unwrap();
+        assert_eq!(config.analysis.taint_sinks, vec!["exec", "spawn"]);
+        assert_eq!(config.analysis.taint_mode, TaintMode::Inline);
+        assert!(config.apply_overrides(vars(&[("VCR_ANALYSIS_TAINT_MODE", "deep")])).is_err());
+    }
+
+    #[test]
+    fn test_env_override_errors_collected() {
+        let mut config = ValoriConfig::default();
+        let errors = config.apply_overrides(vars(&[
+            ("VCR_EXECUTION_THREAD_COUNT", "-1"),
+            ("VCR_IO_MODE", "warm"),
+            ("VCR_EXECUTOIN_PARALLEL", "true"),
+        ])).unwrap_err();
+
+        let vars: Vec<_> = errors.iter().map(|e| match e {
+            ConfigError::EnvOverride { var, .. } => var.as_str(),
+            other => panic!("Unexpected error {:?}", other),
+        }).collect();
+        assert_eq!(vars, vec!["VCR_EXECUTION_THREAD_COUNT", "VCR_EXECUTOIN_PARALLEL", "VCR_IO_MODE"]);
+    }
+
+    #[test]
+    fn test_validate_reports_all_errors() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("not_a_dir");
+        std::fs::write(&file, "").unwrap();
+
+        let mut config = ValoriConfig::default();
+        config.execution.thread_count = MAX_THREAD_COUNT + 1;
+        config.snapshot.max_snapshots = Some(0);
+        config.snapshot.path = file.join("snapshots");
+
+        let errors = config.validate().unwrap_err();
+
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert!(errors.iter().any(|e| matches!(e, ConfigError::SnapshotPath { .. })));
+        assert!(errors.iter().any(|e| matches!(e,
+            ConfigError::InvalidValue { field: "snapshot.max_snapshots", .. })));
+        assert!(errors.iter().any(|e| matches!(e,
+            ConfigError::InvalidValue { field: "execution.thread_count", .. })));
+    }
+
+    #[test]
+    fn test_audit_sample_rate() {
+        let mut config = ValoriConfig::default();
+        config.apply_overrides(vars(&[("VCR_AUDIT_SAMPLE_RATE", "1.0")])).unwrap();
+        assert_eq!(config.audit.sample_rate, 1.0);
+        assert!(config.validate().is_ok());
+
+        for rate in [-0.1, 1.5, f32::NAN] {
+            config.audit.sample_rate = rate;
+            let errors = config.validate().unwrap_err();
+            assert!(matches!(errors[0], ConfigError::InvalidValue { field: "audit.sample_rate", .. }));
+        }
+    }
+
+    #[test]
+    fn test_parse_error_policy() {
+        assert_eq!(ValoriConfig::default().parse.on_parse_error, ParseErrorPolicy::Skip);
+
+        let config: ValoriConfig = toml::from_str(&format!("{}\n[parse]\non_parse_error = \"include_best_effort\"\n", MINIMAL)).unwrap();
+        assert_eq!(config.parse.on_parse_error, ParseErrorPolicy::IncludeBestEffort);
+
+        let mut config = ValoriConfig::default();
+        config.apply_overrides(vars(&[("VCR_PARSE_ON_PARSE_ERROR", "Fail")])).unwrap();
+        assert_eq!(config.parse.on_parse_error, ParseErrorPolicy::Fail);
+        assert!(config.apply_overrides(vars(&[("VCR_PARSE_ON_PARSE_ERROR", "ignore")])).is_err());
+
+        assert!(!config.parse.normalize_line_endings);
+        config.apply_overrides(vars(&[("VCR_PARSE_NORMALIZE_LINE_ENDINGS", "true")])).unwrap();
+        assert!(config.parse.normalize_line_endings);
+    }
+
+    #[test]
+    fn test_snapshot_compression() {
+        assert_eq!(ValoriConfig::default().snapshot.compression, Compression::None);
+
+        let text = MINIMAL.replace("auto_save = false", "auto_save = false\ncompression = { zstd = { level = 7 } }");
+        let config: ValoriConfig = toml::from_str(&text).unwrap();
+        assert_eq!(config.snapshot.compression, Compression::Zstd { level: 7 });
+
+        let mut config = ValoriConfig::default();
+        config.apply_overrides(vars(&[("VCR_SNAPSHOT_COMPRESSION", "zstd")])).unwrap();
+        assert_eq!(config.snapshot.compression, Compression::Zstd { level: DEFAULT_ZSTD_LEVEL });
+        config.apply_overrides(vars(&[("VCR_SNAPSHOT_COMPRESSION", "zstd:19")])).unwrap();
+        assert_eq!(config.snapshot.compression, Compression::Zstd { level: 19 });
+        config.apply_overrides(vars(&[("VCR_SNAPSHOT_COMPRESSION", "None")])).unwrap();
+        assert_eq!(config.snapshot.compression, Compression::None);
+        assert!(config.apply_overrides(vars(&[("VCR_SNAPSHOT_COMPRESSION", "gzip")])).is_err());
+
+        config.snapshot.compression = Compression::Zstd { level: 500 };
+        let errors = config.validate().unwrap_err();
+        assert!(matches!(errors[0], ConfigError::InvalidValue { field: "snapshot.compression", .. }));
+    }
+
+    #[test]
+    fn test_events_config() {
+        let events = ValoriConfig::default().events;
+        assert_eq!((events.capacity, events.on_full), (64, SubscriberFullPolicy::Fail));
+
+        let config: ValoriConfig = toml::from_str(&format!("{}\n[events]\ncapacity = 4\non_full = \"unsubscribe\"\n", MINIMAL)).unwrap();
+        assert_eq!((config.events.capacity, config.events.on_full), (4, SubscriberFullPolicy::Unsubscribe));
+
+        let mut config = ValoriConfig::default();
+        config.apply_overrides(vars(&[("VCR_EVENTS_CAPACITY", "8"), ("VCR_EVENTS_ON_FULL", "Unsubscribe")])).unwrap();
+        assert_eq!((config.events.capacity, config.events.on_full), (8, SubscriberFullPolicy::Unsubscribe));
+        assert!(config.apply_overrides(vars(&[("VCR_EVENTS_ON_FULL", "block")])).is_err());
+
+        config.events.capacity = 0;
+        let errors = config.validate().unwrap_err();
+        assert!(matches!(errors[0], ConfigError::InvalidValue { field: "events.capacity", .. }));
+    }
+
+    #[test]
+    fn test_limits_config() {
+        assert_eq!(ValoriConfig::default().limits, LimitsConfig::default());
+
+        let toml = format!(
+            "{}\n[limits]\nmax_cfg_nodes_per_function = 10\nmax_nodes_per_file = 20\nmax_total_cpg_nodes = 30\nallow_degraded_total = true\n",
+            MINIMAL
+        );
+        let config: ValoriConfig = toml::from_str(&toml).unwrap();
+        let limits = LimitsConfig {
+            max_cfg_nodes_per_function: 10,
+            max_nodes_per_file: 20,
+            max_total_cpg_nodes: 30,
+            allow_degraded_total: true,
+        };
+        assert_eq!(config.limits, limits);
+
+        let mut config = ValoriConfig::default();
+        config.apply_overrides(vars(&[
+            ("VCR_LIMITS_MAX_NODES_PER_FILE", "7"),
+            ("VCR_LIMITS_ALLOW_DEGRADED_TOTAL", "true"),
+        ])).unwrap();
+        assert_eq!((config.limits.max_nodes_per_file, config.limits.allow_degraded_total), (7, true));
+
+        config.limits.max_cfg_nodes_per_function = 0;
+        let errors = config.validate().unwrap_err();
+        assert!(matches!(errors[0], ConfigError::InvalidValue { field: "limits.max_cfg_nodes_per_function", .. }));
+    }
+
+    #[test]
+    fn test_validate_accepts_missing_snapshot_dir() {
+        let dir = TempDir::new().unwrap();
+        let mut config = ValoriConfig::default();
+        config.snapshot.path = dir.path().join("a").join("b");
+
+        assert!(config.validate().is_ok());
     }
 }
@@ -8,12 +8,30 @@
 //! 3. AST nodes (tree order)
 //! 4. CFG nodes (program order)
 //! 5. DFG values (definition order)
+//!
+//! File nodes are labelled with their file's language name (`rust`) when
+//! the semantic epoch records one (see `SemanticEpoch::set_language`).
+//! DfgValue nodes of parameters carry their `parameter_position`.
+//!
+//! CFG and DFG edges are rewritten through per-graph id → CPGNodeId maps;
+//! CFG NodeIds and DFG ValueIds are never reused as CPG node IDs.
+//!
+//! Every node and edge is fed to a `CpgHasher` as it is added, and the
+//! digest and per-file partials are stored in the epoch (`cpg_hash`,
+//! `file_hashes`), so nothing rehashes the finished graph.
+//!
+//! With strict validation, the fused graph is checked with
+//! `CPG::validate_against` and any error fails the build.
 
 use crate::cpg::model::*;
 use crate::cpg::epoch::CPGEpoch;
+use crate::cpg::hash::CpgHasher;
+use crate::semantic::invalidation::InvalidationTracker;
 use crate::semantic::SemanticEpoch;
+use crate::semantic::model::{FunctionId, NodeId as CFGNodeId, ValueId as DFGValueId, ValueKind};
 use crate::types::ByteRange;
-use anyhow::Result;
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, HashMap};
 
 /// CPG Builder - fuses AST + CFG + DFG
 pub struct CPGBuilder {
@@ -22,6 +40,9 @@ pub struct CPGBuilder {
     
     /// Next edge ID
     next_edge_id: u64,
+
+    /// Validate the fused graph and fail on any error
+    strict_validation: bool,
 }
 
 impl CPGBuilder {
@@ -30,9 +51,16 @@ impl CPGBuilder {
         Self {
             next_node_id: 0,
             next_edge_id: 0,
+            strict_validation: false,
         }
     }
 
+    /// Enable `[verification] strict_validation`
+    pub fn with_strict_validation(mut self, strict: bool) -> Self {
+        self.strict_validation = strict;
+        self
+    }
+
     /// Build CPG from semantic epoch
     ///
     /// **Order is fixed and deterministic**:
@@ -41,21 +69,65 @@ impl CPGBuilder {
     /// 3. CFG nodes (program order)
     /// 4. DFG values (definition order)
     pub fn build(&mut self, semantic: &SemanticEpoch, cpg_epoch: &mut CPGEpoch) -> Result<()> {
+        self.fuse(semantic, cpg_epoch, None)
+    }
+
+    /// Build CPG and record which CFG nodes and DFG values produced each
+    /// CPG node
+    ///
+    /// Previous CPG mappings in the tracker are cleared first. A function
+    /// node is recorded against its CFG entry node.
+    pub fn build_tracked(
+        &mut self,
+        semantic: &SemanticEpoch,
+        cpg_epoch: &mut CPGEpoch,
+        tracker: &mut InvalidationTracker,
+    ) -> Result<()> {
+        tracker.clear_cpg();
+        self.fuse(semantic, cpg_epoch, Some(tracker))
+    }
+
+    fn fuse(
+        &mut self,
+        semantic: &SemanticEpoch,
+        cpg_epoch: &mut CPGEpoch,
+        mut tracker: Option<&mut InvalidationTracker>,
+    ) -> Result<()> {
+        if cpg_epoch.semantic_epoch_id() != semantic.epoch_id() {
+            bail!(
+                "CPG epoch {} belongs to semantic epoch {}, not {}",
+                cpg_epoch.epoch_id(), cpg_epoch.semantic_epoch_id(), semantic.epoch_id()
+            );
+        }
+
+        let span = tracing::info_span!(
+            "fusion",
+            epoch_id = cpg_epoch.epoch_id(),
+            semantic_epoch_id = semantic.epoch_id(),
+            nodes = tracing::field::Empty,
+            edges = tracing::field::Empty,
+        ).entered();
         let cpg = cpg_epoch.cpg_mut();
+        let mut label_buf = String::new();
+        let mut hasher = CpgHasher::new().with_partials();
+        let mut file_hashes = BTreeMap::new();
         
         // Get all files (sorted for determinism)
         let mut file_ids: Vec<_> = semantic.get_all_file_ids();
         file_ids.sort();
         
         for file_id in file_ids {
-            // Step 1: Create file node
-            let file_node = CPGNode::new(
+            // Step 1: Create file node (labelled with its language, if known)
+            let mut file_node = CPGNode::new(
                 self.next_node_id(),
                 CPGNodeKind::File,
                 OriginRef::File { file_id },
                 ByteRange::new(0, 0),  // Files don't have ranges
             );
-            cpg.add_node(file_node);
+            if let Some(language) = semantic.language(file_id) {
+                file_node = file_node.with_label(cpg.intern_label(language.name()));
+            }
+            add_node(cpg, &mut hasher, file_node);
             
             // Step 2: Get functions for this file (if any)
             if let Some(cfgs) = semantic.get_cfgs(file_id) {
@@ -64,24 +136,35 @@ impl CPGBuilder {
                 sorted_cfgs.sort_by_key(|cfg| cfg.function_id);
                 
                 for cfg in sorted_cfgs {
-                    // Create function node
-                    let func_node = CPGNode::new(
+                    // Create function node (labelled with its name)
+                    let mut func_node = CPGNode::new(
                         self.next_node_id(),
                         CPGNodeKind::Function,
                         OriginRef::Function { function_id: cfg.function_id },
-                        ByteRange::new(0, 0),  // CFG doesn't store function range
+                        cfg.span(),
                     );
-                    cpg.add_node(func_node);
+                    if !cfg.name.is_empty() {
+                        func_node = func_node.with_label(cpg.intern_label(&cfg.name));
+                    }
+                    if let Some(tracker) = tracker.as_deref_mut() {
+                        tracker.track_cfg_to_cpg(file_id, cfg.function_id, cfg.entry, func_node.id);
+                    }
+                    add_node(cpg, &mut hasher, func_node);
                     
                     // Step 3: Process CFG nodes (in order)
+                    let mut cfg_nodes: HashMap<CFGNodeId, CPGNodeId> = HashMap::new();
                     for cfg_node in &cfg.nodes {
                         let cpg_node = CPGNode::new(
                             self.next_node_id(),
                             CPGNodeKind::CfgNode,
                             OriginRef::Cfg { node_id: cfg_node.id },
                             cfg_node.source_range,
-                        ).with_label(format!("{:?}", cfg_node.kind));
-                        cpg.add_node(cpg_node);
+                        ).with_label(debug_label(cpg, &mut label_buf, &cfg_node.kind));
+                        if let Some(tracker) = tracker.as_deref_mut() {
+                            tracker.track_cfg_to_cpg(file_id, cfg.function_id, cfg_node.id, cpg_node.id);
+                        }
+                        cfg_nodes.insert(cfg_node.id, cpg_node.id);
+                        add_node(cpg, &mut hasher, cpg_node);
                     }
                     
                     // Step 4: Process CFG edges
@@ -89,10 +172,10 @@ impl CPGBuilder {
                         let cpg_edge = CPGEdge::new(
                             self.next_edge_id(),
                             CPGEdgeKind::ControlFlow,
-                            CPGNodeId(cfg_edge.from.0),
-                            CPGNodeId(cfg_edge.to.0),
+                            mapped(&cfg_nodes, cfg_edge.from, "CFG node", cfg.function_id)?,
+                            mapped(&cfg_nodes, cfg_edge.to, "CFG node", cfg.function_id)?,
                         );
-                        cpg.add_edge(cpg_edge);
+                        add_edge(cpg, &mut hasher, cpg_edge);
                     }
                 }
             }
@@ -101,14 +184,22 @@ impl CPGBuilder {
             if let Some(dfgs) = semantic.get_dfgs(file_id) {
                 for dfg in dfgs {
                     // Process DFG values (in order)
+                    let mut dfg_values: HashMap<DFGValueId, CPGNodeId> = HashMap::new();
                     for dfg_value in &dfg.values {
-                        let cpg_node = CPGNode::new(
+                        let mut cpg_node = CPGNode::new(
                             self.next_node_id(),
                             CPGNodeKind::DfgValue,
                             OriginRef::Dfg { value_id: dfg_value.id },
                             dfg_value.source_range,
-                        ).with_label(format!("{:?}", dfg_value.kind));
-                        cpg.add_node(cpg_node);
+                        ).with_label(debug_label(cpg, &mut label_buf, &dfg_value.kind.resolve(semantic.strings())));
+                        if let ValueKind::Parameter { position, .. } = dfg_value.kind {
+                            cpg_node = cpg_node.with_parameter_position(position as u32);
+                        }
+                        if let Some(tracker) = tracker.as_deref_mut() {
+                            tracker.track_dfg_to_cpg(file_id, dfg.function_id, dfg_value.id, cpg_node.id);
+                        }
+                        dfg_values.insert(dfg_value.id, cpg_node.id);
+                        add_node(cpg, &mut hasher, cpg_node);
                     }
                     
                     // Process DFG edges
@@ -116,10 +207,10 @@ impl CPGBuilder {
                         let cpg_edge = CPGEdge::new(
                             self.next_edge_id(),
                             CPGEdgeKind::DataFlow,
-                            CPGNodeId(dfg_edge.from.0),
-                            CPGNodeId(dfg_edge.to.0),
+                            mapped(&dfg_values, dfg_edge.from, "DFG value", dfg.function_id)?,
+                            mapped(&dfg_values, dfg_edge.to, "DFG value", dfg.function_id)?,
                         );
-                        cpg.add_edge(cpg_edge);
+                        add_edge(cpg, &mut hasher, cpg_edge);
                     }
                 }
             }
@@ -136,15 +227,32 @@ impl CPGBuilder {
                         CPGNodeKind::Symbol,
                         OriginRef::Symbol { symbol_id: symbol.id },
                         symbol.source_range,
-                    ).with_label(symbol.name.clone());
-                    cpg.add_node(cpg_node);
+                    ).with_label(cpg.intern_label(&symbol.name));
+                    add_node(cpg, &mut hasher, cpg_node);
                 }
             }
+            file_hashes.insert(file_id, hasher.finalize_partial());
         }
         
-        // Rebuild indices after fusion
-        cpg_epoch.rebuild_indices();
+        span.record("nodes", cpg.nodes.len());
+        span.record("edges", cpg.edges.len());
+
+        if self.strict_validation {
+            Self::validate(cpg, semantic)?;
+        }
         
+        // Indices are built when the caller freezes the epoch
+        cpg_epoch.set_hashes(hasher.finalize(), file_hashes);
+
+        Ok(())
+    }
+
+    /// `[verification] strict_validation` of a fused graph
+    pub fn validate(cpg: &CPG, semantic: &SemanticEpoch) -> Result<()> {
+        if let Err(errors) = cpg.validate_against(semantic) {
+            let details: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            bail!("CPG validation failed with {} error(s): {}", errors.len(), details.join("; "));
+        }
         Ok(())
     }
 
@@ -163,6 +271,46 @@ impl CPGBuilder {
     }
 }
 
+impl Default for CPGBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Add a node, hashing it first
+fn add_node(cpg: &mut CPG, hasher: &mut CpgHasher, node: CPGNode) {
+    hasher.observe_node(&node, cpg.label(&node));
+    cpg.add_node(node);
+}
+
+/// Add an edge, hashing it first
+fn add_edge(cpg: &mut CPG, hasher: &mut CpgHasher, edge: CPGEdge) {
+    hasher.observe_edge(&edge);
+    cpg.add_edge(edge);
+}
+
+/// CPG node fused from a CFG node or DFG value of one function
+fn mapped<K: std::hash::Hash + Eq + std::fmt::Debug>(
+    ids: &HashMap<K, CPGNodeId>,
+    id: K,
+    what: &str,
+    function_id: FunctionId,
+) -> Result<CPGNodeId> {
+    match ids.get(&id) {
+        Some(&node) => Ok(node),
+        None => bail!("Edge references unknown {} {:?} in {:?}", what, id, function_id),
+    }
+}
+
+/// Intern the `Debug` text of a value, formatting into a reused buffer
+fn debug_label(cpg: &mut CPG, buf: &mut String, value: &impl std::fmt::Debug) -> LabelId {
+    use std::fmt::Write;
+
+    buf.clear();
+    write!(buf, "{:?}", value).expect("formatting into a String cannot fail");
+    cpg.intern_label(buf)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,4 +320,124 @@ mod tests {
         let builder = CPGBuilder::new();
         assert_eq!(builder.next_node_id, 0);
     }
+
+    #[test]
+    fn test_build_rejects_mismatched_semantic_epoch() {
+        let semantic = SemanticEpoch::builder(3).build();
+        let mut cpg_epoch = CPGEpoch::new(5, 6);
+
+        let err = CPGBuilder::new().build(&semantic, &mut cpg_epoch).unwrap_err();
+        assert!(err.to_string().contains("belongs to semantic epoch 5, not 3"), "{}", err);
+    }
+
+    #[test]
+    fn test_build_rejects_edge_to_unknown_value() {
+        use crate::semantic::model::{DFGEdge, DFGEdgeKind, DFG};
+        use crate::types::FileId;
+
+        let mut dfg = DFG::new(FunctionId(0));
+        dfg.edges.push(DFGEdge { from: DFGValueId(0), to: DFGValueId(1), kind: DFGEdgeKind::Use });
+        let semantic = SemanticEpoch::builder(3).add_dfg(FileId::new(1), dfg).build();
+
+        let err = CPGBuilder::new().build(&semantic, &mut CPGEpoch::new(3, 4)).unwrap_err();
+        assert!(err.to_string().contains("unknown DFG value ValueId(0)"), "{}", err);
+    }
+
+    #[test]
+    fn test_function_nodes_carry_names_and_spans() {
+        use crate::io::MmappedFile;
+        use crate::parse::IncrementalParser;
+        use crate::types::{FileId, Language};
+        use tempfile::NamedTempFile;
+
+        let source: &[u8] = b"fn alpha() { let x = 1; }\nfn beta(y: i32) {}\n";
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), source).unwrap();
+        let file_id = FileId::new(1);
+        let mmap = MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+
+        let semantic = SemanticEpoch::build_from_parsed(&[(file_id, &parsed, source)]).unwrap();
+        let mut cpg_epoch = CPGEpoch::new(semantic.epoch_id(), 4);
+        CPGBuilder::new().build(&semantic, &mut cpg_epoch).unwrap();
+        let cpg = cpg_epoch.cpg();
+
+        let functions: Vec<_> = cpg.nodes.iter()
+            .filter(|n| n.kind == CPGNodeKind::Function)
+            .map(|n| (cpg.label(n).unwrap(), &source[n.source_range.start..n.source_range.end]))
+            .collect();
+        assert_eq!(functions, vec![
+            ("alpha", &b"fn alpha() { let x = 1; }"[..]),
+            ("beta", &b"fn beta(y: i32) {}"[..]),
+        ]);
+    }
+
+    #[test]
+    fn test_editing_one_function_invalidates_only_its_cpg_nodes() {
+        use crate::io::MmappedFile;
+        use crate::parse::IncrementalParser;
+        use crate::semantic::model::FunctionId;
+        use crate::types::{FileId, Language};
+        use std::collections::BTreeSet;
+        use tempfile::NamedTempFile;
+
+        let source: &[u8] = b"fn a() { let x = 1; let y = x; }\nfn b(c: bool) { let z = 2; if c { z = 3; } }\n";
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), source).unwrap();
+        let file_id = FileId::new(1);
+        let mmap = MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+
+        let mut semantic = SemanticEpoch::build_from_parsed(&[(file_id, &parsed, source)]).unwrap();
+        let mut cpg_epoch = CPGEpoch::new(semantic.epoch_id(), 4);
+        let mut tracker = std::mem::take(semantic.invalidation_mut());
+        CPGBuilder::new().build_tracked(&semantic, &mut cpg_epoch, &mut tracker).unwrap();
+        let cpg = cpg_epoch.cpg();
+
+        // CPG nodes fused from each function, by origin
+        let cfgs = semantic.get_cfgs(file_id).unwrap();
+        let dfgs = semantic.get_dfgs(file_id).unwrap();
+        let mut owned: Vec<BTreeSet<CPGNodeId>> = vec![BTreeSet::new(), BTreeSet::new()];
+        let mut dfg_nodes = cpg.nodes.iter().filter(|n| n.kind == CPGNodeKind::DfgValue);
+        for (f, (cfg, dfg)) in cfgs.iter().zip(dfgs).enumerate() {
+            let cfg_ids: BTreeSet<_> = cfg.nodes.iter().map(|n| n.id).collect();
+            for node in &cpg.nodes {
+                match node.origin {
+                    OriginRef::Function { function_id } if function_id == FunctionId(f as u64) => {
+                        owned[f].insert(node.id);
+                    }
+                    OriginRef::Cfg { node_id } if cfg_ids.contains(&node_id) => {
+                        owned[f].insert(node.id);
+                    }
+                    _ => {}
+                }
+            }
+            owned[f].extend(dfg_nodes.by_ref().take(dfg.values.len()).map(|n| n.id));
+        }
+
+        // Edit `z = 3` inside b
+        let start = source.windows(5).position(|w| w == b"z = 3").unwrap();
+        let inv = tracker.invalidate(file_id, &[ByteRange::new(start, start + 5)]);
+
+        let hit: BTreeSet<_> = inv.cpg_nodes.iter().copied().collect();
+        assert!(!hit.is_empty());
+        assert!(hit.is_subset(&owned[1]), "{:?} not within b's {:?}", hit, owned[1]);
+        assert!(hit.is_disjoint(&owned[0]));
+
+        // b's function node and every DFG value of b are invalidated
+        let b_function = cpg.nodes.iter()
+            .find(|n| n.origin == OriginRef::Function { function_id: FunctionId(1) })
+            .unwrap();
+        assert!(hit.contains(&b_function.id));
+        assert_eq!(
+            owned[1].iter().filter(|id| cpg.get_node(**id).unwrap().kind == CPGNodeKind::DfgValue).count(),
+            hit.iter().filter(|id| cpg.get_node(**id).unwrap().kind == CPGNodeKind::DfgValue).count()
+        );
+
+        // Rebuilding replaces the mappings instead of appending
+        let before = tracker.stats().cpg_nodes;
+        let mut again = CPGEpoch::new(semantic.epoch_id(), 4);
+        CPGBuilder::new().build_tracked(&semantic, &mut again, &mut tracker).unwrap();
+        assert_eq!(tracker.stats().cpg_nodes, before);
+    }
 }
@@ -7,17 +7,62 @@
 //! - CPGEpoch ← this one
 //!
 //! When dropped, all CPG memory is freed.
+//!
+//! The epoch records its parent SemanticEpoch ID; CPGBuilder refuses to fuse
+//! a semantic epoch with a different ID into it.
+//!
+//! `from_snapshot` restores a queryable epoch from a `CPGSnapshot` file. A
+//! restored epoch has no semantic parent (ID 0); its epoch ID follows the
+//! one stored in the snapshot, which is kept as `restored_from`.
+//! `from_snapshot_with_symbols` also restores the snapshot's symbol tables
+//! into a SemanticEpoch shell (symbols only, no CFGs or DFGs) for name
+//! lookups. `from_store` restores snapshot N of a `SnapshotStore` the same
+//! way (time-travel queries).
+//!
+//! `cpg_hash` is the digest CPGBuilder streamed while fusing (or the
+//! verified one of the restored snapshot), so asking for it does not walk
+//! the graph again; `file_hashes` are the builder's per-file partials.
+//!
+//! ## Freezing
+//!
+//! A built epoch is sealed with `freeze`, which rebuilds the indices and
+//! stores the canonical hash. `FrozenCPGEpoch` only hands out shared
+//! references, so nothing downstream of the builder (queries, snapshots,
+//! analyses) can change the graph its hash describes. `from_snapshot`
+//! returns a frozen epoch.
+//!
+//! **Fail closed**: `verify` rehashes the CPG and compares it against the
+//! stored hash; in debug builds the query engine calls `debug_verify`,
+//! which panics on divergence, before serving each scoped query
+//! (`[query] verify_epochs`).
+//!
+//! Indices are rebuilt only by `rebuild_indices` (and `freeze`). `cpg_mut`
+//! bumps the CPG's generation, so `indices` panics until the next rebuild
+//! instead of answering from a graph that is gone (see `cpg::index`).
+//!
+//! The only state a frozen epoch builds after `freeze` is its cache of
+//! per-file `LineIndex`es (see `query::materialize`), which lives and dies
+//! with the epoch like the indices.
 
-use crate::cpg::model::CPG;
-use crate::cpg::index::CPGIndices;
+use crate::cpg::model::{CPGEdgeKind, CPGNodeKind, CPG};
+use crate::cpg::index::{CPGIndices, IndexStats};
+use crate::semantic::SemanticEpoch;
+use crate::storage::{CPGSnapshot, SnapshotId, SnapshotMetadata, SnapshotStore};
+use crate::types::FileId;
+use crate::util::LineIndex;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
 
 /// CPG Epoch - owns unified Code Property Graph
 ///
 /// **Memory Safety**: All CPG data lives within this epoch.
 /// When the epoch is dropped, all memory is freed automatically.
 pub struct CPGEpoch {
-    /// Reference to semantic epoch (read-only)
-    _semantic_epoch_marker: u64,  // Would be lifetime in real impl
+    /// Parent semantic epoch ID
+    semantic_epoch_id: u64,
     
     /// The unified CPG
     cpg: CPG,
@@ -27,17 +72,79 @@ pub struct CPGEpoch {
     
     /// Epoch ID for debugging
     epoch_id: u64,
+
+    /// Statistics as of the last index rebuild
+    stats: CPGEpochStats,
+
+    /// `cpg.compute_hash()`, if known without rehashing
+    cpg_hash: Option<String>,
+
+    /// Partial digest of each file's fused nodes and edges
+    file_hashes: BTreeMap<FileId, String>,
 }
 
 impl CPGEpoch {
     /// Create a new CPG epoch
-    pub fn new(_semantic_epoch_marker: u64, epoch_id: u64) -> Self {
+    pub fn new(semantic_epoch_id: u64, epoch_id: u64) -> Self {
+        let cpg = CPG::new();
+        let indices = CPGIndices::build(&cpg);
         Self {
-            _semantic_epoch_marker,
-            cpg: CPG::new(),
-            indices: CPGIndices::new(),
+            semantic_epoch_id,
+            cpg,
+            indices,
             epoch_id,
+            stats: CPGEpochStats { epoch_id, ..Default::default() },
+            cpg_hash: None,
+            file_hashes: BTreeMap::new(),
+        }
+    }
+
+    /// Restore an epoch from a snapshot file
+    ///
+    /// Fails closed if the snapshot's content does not match its stored
+    /// hash, or if `expected_hash` is given and differs. Indices are rebuilt.
+    pub fn from_snapshot(path: &Path, expected_hash: Option<&str>) -> Result<FrozenCPGEpoch> {
+        Self::from_snapshot_with_symbols(path, expected_hash).map(|(epoch, _)| epoch)
+    }
+
+    /// `from_snapshot`, also returning a SemanticEpoch holding only the
+    /// snapshot's symbol tables (none for snapshots saved without them)
+    pub fn from_snapshot_with_symbols(path: &Path, expected_hash: Option<&str>) -> Result<(FrozenCPGEpoch, SemanticEpoch)> {
+        let (metadata, cpg, symbols) = CPGSnapshot::read_with_symbols(path)
+            .with_context(|| format!("Failed to load snapshot {}", path.display()))?;
+
+        if let Some(expected) = expected_hash {
+            if metadata.cpg_hash != expected {
+                bail!("Snapshot {} has hash {}, expected {}", path.display(), metadata.cpg_hash, expected);
+            }
         }
+
+        let epoch = Self::restore(metadata, cpg);
+        let semantic = symbols.into_iter()
+            .fold(SemanticEpoch::builder(0), |builder, (file_id, table)| builder.add_symbols(file_id, table))
+            .build();
+        Ok((epoch, semantic))
+    }
+
+    /// Restore snapshot `id` of a snapshot store, failing closed like
+    /// `from_snapshot` if its payload does not match the indexed hash
+    pub fn from_store(store: &SnapshotStore, id: SnapshotId) -> Result<FrozenCPGEpoch> {
+        let entry = store.get(id).with_context(|| format!("No snapshot {}", id.0))?;
+        let cpg = store.load(id).with_context(|| format!("Failed to load snapshot {}", id.0))?;
+        Ok(Self::restore(entry.metadata.clone(), cpg))
+    }
+
+    /// Freeze a snapshot's CPG as the epoch after the one it recorded
+    fn restore(metadata: SnapshotMetadata, cpg: CPG) -> FrozenCPGEpoch {
+        let mut epoch = Self::new(0, metadata.epoch_id + 1);
+        epoch.cpg = cpg;
+        // Snapshots before storage version 4 record the version 1 digest
+        if metadata.version >= 4 {
+            epoch.cpg_hash = Some(metadata.cpg_hash.clone());
+        }
+        let mut epoch = epoch.freeze();
+        epoch.restored_from = Some(metadata);
+        epoch
     }
 
     /// Get reference to CPG (read-only)
@@ -46,18 +153,45 @@ impl CPGEpoch {
     }
 
     /// Get mutable reference to CPG (builder only)
+    ///
+    /// Forgets the stored hashes; the caller sets them with `set_hashes`.
+    /// The indices are stale until `rebuild_indices`.
     pub(crate) fn cpg_mut(&mut self) -> &mut CPG {
+        self.cpg_hash = None;
+        self.file_hashes.clear();
+        self.cpg.bump_generation();
         &mut self.cpg
     }
 
+    /// Record the CPG's hash and per-file partials, computed while building it
+    pub(crate) fn set_hashes(&mut self, cpg_hash: String, file_hashes: BTreeMap<FileId, String>) {
+        self.cpg_hash = Some(cpg_hash);
+        self.file_hashes = file_hashes;
+    }
+
+    /// `cpg().compute_hash()`, without rehashing when fusion already did
+    pub fn cpg_hash(&self) -> String {
+        self.cpg_hash.clone().unwrap_or_else(|| self.cpg.compute_hash())
+    }
+
+    /// Partial digest of each fused file (see `cpg::hash`); empty for
+    /// restored epochs
+    pub fn file_hashes(&self) -> &BTreeMap<FileId, String> {
+        &self.file_hashes
+    }
+
     /// Get reference to indices (read-only)
+    ///
+    /// **Panics** if the CPG changed since they were last rebuilt.
     pub fn indices(&self) -> &CPGIndices {
+        self.indices.assert_current(&self.cpg);
         &self.indices
     }
 
     /// Rebuild indices from CPG
     pub fn rebuild_indices(&mut self) {
         self.indices = CPGIndices::build(&self.cpg);
+        self.stats = CPGEpochStats::compute(self.epoch_id, &self.cpg, &self.indices);
     }
 
     /// Get epoch ID
@@ -65,13 +199,30 @@ impl CPGEpoch {
         self.epoch_id
     }
 
-    /// Get statistics
-    pub fn stats(&self) -> CPGEpochStats {
-        let cpg_stats = self.cpg.stats();
-        CPGEpochStats {
+    /// Get parent semantic epoch ID
+    pub fn semantic_epoch_id(&self) -> u64 {
+        self.semantic_epoch_id
+    }
+
+    /// Get statistics (computed when the indices were last rebuilt)
+    pub fn stats(&self) -> &CPGEpochStats {
+        &self.stats
+    }
+
+    /// Seal the epoch: rebuild indices and store the canonical hash
+    pub fn freeze(mut self) -> FrozenCPGEpoch {
+        self.rebuild_indices();
+        let cpg_hash = self.cpg_hash();
+        FrozenCPGEpoch {
+            semantic_epoch_id: self.semantic_epoch_id,
+            cpg: std::mem::take(&mut self.cpg),
+            indices: std::mem::take(&mut self.indices),
             epoch_id: self.epoch_id,
-            total_nodes: cpg_stats.total_nodes,
-            total_edges: cpg_stats.total_edges,
+            restored_from: None,
+            stats: std::mem::take(&mut self.stats),
+            cpg_hash,
+            file_hashes: std::mem::take(&mut self.file_hashes),
+            line_indices: Mutex::new(BTreeMap::new()),
         }
     }
 }
@@ -82,12 +233,156 @@ impl Drop for CPGEpoch {
     }
 }
 
+/// A sealed CPGEpoch: read accessors only
+///
+/// There is no way back to a mutable CPG:
+///
+/// ```compile_fail
+/// # fn seal(epoch: vcr::cpg::CPGEpoch) {
+/// let frozen = epoch.freeze();
+/// frozen.cpg().nodes.clear();
+/// # }
+/// ```
+pub struct FrozenCPGEpoch {
+    semantic_epoch_id: u64,
+    cpg: CPG,
+    indices: CPGIndices,
+    epoch_id: u64,
+
+    /// Metadata of the snapshot this epoch was restored from
+    restored_from: Option<SnapshotMetadata>,
+
+    stats: CPGEpochStats,
+
+    /// Canonical hash stored at `freeze`
+    cpg_hash: String,
+
+    file_hashes: BTreeMap<FileId, String>,
+
+    /// Line index of each file materialized so far
+    line_indices: Mutex<BTreeMap<FileId, Arc<LineIndex>>>,
+}
+
+impl FrozenCPGEpoch {
+    /// Get reference to CPG
+    pub fn cpg(&self) -> &CPG {
+        &self.cpg
+    }
+
+    /// Canonical hash stored at `freeze`
+    pub fn cpg_hash(&self) -> &str {
+        &self.cpg_hash
+    }
+
+    /// Partial digest of each fused file (see `cpg::hash`); empty for
+    /// restored epochs
+    pub fn file_hashes(&self) -> &BTreeMap<FileId, String> {
+        &self.file_hashes
+    }
+
+    /// Get reference to indices
+    pub fn indices(&self) -> &CPGIndices {
+        self.indices.assert_current(&self.cpg);
+        &self.indices
+    }
+
+    /// Get epoch ID
+    pub fn epoch_id(&self) -> u64 {
+        self.epoch_id
+    }
+
+    /// Get parent semantic epoch ID
+    pub fn semantic_epoch_id(&self) -> u64 {
+        self.semantic_epoch_id
+    }
+
+    /// Epoch ID stored in the snapshot this epoch was restored from
+    pub fn restored_from(&self) -> Option<u64> {
+        self.restored_from.as_ref().map(|m| m.epoch_id)
+    }
+
+    /// Metadata of the snapshot this epoch was restored from
+    pub fn snapshot_metadata(&self) -> Option<&SnapshotMetadata> {
+        self.restored_from.as_ref()
+    }
+
+    /// Get statistics (computed at `freeze`)
+    pub fn stats(&self) -> &CPGEpochStats {
+        &self.stats
+    }
+
+    /// Line index of a file, built from `source` on first use
+    ///
+    /// `source` must be the contents the epoch was built from; the index is
+    /// cached for the life of the epoch.
+    pub fn line_index(&self, file_id: FileId, source: &[u8]) -> Arc<LineIndex> {
+        let mut indices = self.line_indices.lock().unwrap_or_else(PoisonError::into_inner);
+        indices.entry(file_id).or_insert_with(|| Arc::new(LineIndex::new(source))).clone()
+    }
+
+    /// Rehash the CPG and compare against the stored hash
+    pub fn verify(&self) -> Result<()> {
+        let actual = self.cpg.compute_hash();
+        if actual != self.cpg_hash {
+            bail!("Frozen CPG epoch {} hashes to {}, stored {}", self.epoch_id, actual, self.cpg_hash);
+        }
+        Ok(())
+    }
+
+    /// `verify` in debug builds, a no-op in release builds
+    ///
+    /// **Panics** if the CPG diverged from its stored hash.
+    pub fn debug_verify(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(err) = self.verify() {
+                panic!("{}", err);
+            }
+        }
+    }
+}
+
 /// Statistics about a CPG epoch
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
 pub struct CPGEpochStats {
     pub epoch_id: u64,
     pub total_nodes: usize,
     pub total_edges: usize,
+
+    /// Node count per kind (kinds without nodes are omitted)
+    pub nodes_by_kind: BTreeMap<CPGNodeKind, usize>,
+
+    /// Edge count per kind (kinds without edges are omitted)
+    pub edges_by_kind: BTreeMap<CPGEdgeKind, usize>,
+
+    /// Index entry counts and size
+    pub indices: IndexStats,
+
+    /// Estimated heap bytes of the CPG itself
+    pub cpg_bytes: usize,
+}
+
+impl CPGEpochStats {
+    /// One pass over nodes and edges
+    fn compute(epoch_id: u64, cpg: &CPG, indices: &CPGIndices) -> Self {
+        let mut nodes_by_kind = BTreeMap::new();
+        for node in &cpg.nodes {
+            *nodes_by_kind.entry(node.kind).or_default() += 1;
+        }
+        let mut edges_by_kind = BTreeMap::new();
+        for edge in &cpg.edges {
+            *edges_by_kind.entry(edge.kind).or_default() += 1;
+        }
+
+        Self {
+            epoch_id,
+            total_nodes: cpg.nodes.len(),
+            total_edges: cpg.edges.len(),
+            nodes_by_kind,
+            edges_by_kind,
+            indices: indices.stats(),
+            cpg_bytes: cpg.estimated_bytes(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -98,6 +393,7 @@ mod tests {
     fn test_cpg_epoch_creation() {
         let epoch = CPGEpoch::new(2,3);
         assert_eq!(epoch.epoch_id(), 3);
+        assert_eq!(epoch.semantic_epoch_id(), 2);
     }
 
     #[test]
@@ -109,4 +405,164 @@ mod tests {
         assert_eq!(stats.total_nodes, 0);
         assert_eq!(stats.total_edges, 0);
     }
+
+    #[test]
+    fn test_stats_count_kinds_and_indices() {
+        use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGNode, CPGNodeId, OriginRef};
+        use crate::semantic::model::{FunctionId, NodeId, SymbolId, ValueId};
+        use crate::types::{ByteRange, FileId};
+
+        // File → Function → 2 CFG nodes, one value used by one of them, one symbol
+        let mut epoch = CPGEpoch::new(1, 7);
+        let nodes = [
+            (CPGNodeKind::File, OriginRef::File { file_id: FileId::new(1) }),
+            (CPGNodeKind::Function, OriginRef::Function { function_id: FunctionId(0) }),
+            (CPGNodeKind::CfgNode, OriginRef::Cfg { node_id: NodeId(0) }),
+            (CPGNodeKind::CfgNode, OriginRef::Cfg { node_id: NodeId(1) }),
+            (CPGNodeKind::DfgValue, OriginRef::Dfg { value_id: ValueId(0) }),
+            (CPGNodeKind::Symbol, OriginRef::Symbol { symbol_id: SymbolId(0) }),
+        ];
+        let edges = [
+            (CPGEdgeKind::AstParent, 0, 1),
+            (CPGEdgeKind::AstParent, 1, 2),
+            (CPGEdgeKind::ControlFlow, 2, 3),
+            (CPGEdgeKind::DataFlow, 3, 4),
+        ];
+        let cpg = epoch.cpg_mut();
+        for (i, (kind, origin)) in nodes.into_iter().enumerate() {
+            cpg.add_node(CPGNode::new(CPGNodeId(i as u64), kind, origin, ByteRange::new(0, 1)));
+        }
+        for (i, (kind, from, to)) in edges.into_iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), kind, CPGNodeId(from), CPGNodeId(to)));
+        }
+
+        // Cached: nothing counted until the indices are rebuilt
+        assert_eq!(epoch.stats().total_nodes, 0);
+        epoch.rebuild_indices();
+
+        let stats = epoch.stats();
+        assert_eq!(stats.epoch_id, 7);
+        assert_eq!((stats.total_nodes, stats.total_edges), (6, 4));
+        assert_eq!(stats.nodes_by_kind, BTreeMap::from([
+            (CPGNodeKind::CfgNode, 2),
+            (CPGNodeKind::DfgValue, 1),
+            (CPGNodeKind::Symbol, 1),
+            (CPGNodeKind::Function, 1),
+            (CPGNodeKind::File, 1),
+        ]));
+        assert_eq!(stats.edges_by_kind, BTreeMap::from([
+            (CPGEdgeKind::AstParent, 2),
+            (CPGEdgeKind::ControlFlow, 1),
+            (CPGEdgeKind::DataFlow, 1),
+        ]));
+        assert_eq!(stats.indices.symbol_to_defs, 1);
+        assert_eq!(stats.indices.var_to_uses, 1);
+        assert_eq!(stats.indices.func_to_calls, 0);
+        assert_eq!(stats.indices.adjacency, 8);
+        assert!(stats.indices.estimated_bytes > 0);
+        assert!(stats.cpg_bytes >= 6 * std::mem::size_of::<CPGNode>());
+
+        let json = serde_json::to_value(stats).unwrap();
+        assert_eq!(json["nodes_by_kind"]["CfgNode"], 2);
+    }
+
+    #[test]
+    fn test_from_snapshot_checks_expected_hash() {
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        let epoch = CPGEpoch::new(2, 3);
+        CPGSnapshot::save(epoch.cpg(), epoch.epoch_id(), temp.path()).unwrap();
+        let hash = epoch.cpg().compute_hash();
+
+        let restored = CPGEpoch::from_snapshot(temp.path(), Some(&hash)).unwrap();
+        assert_eq!(restored.restored_from(), Some(3));
+        assert_eq!(restored.epoch_id(), 4);
+        assert_eq!(restored.cpg_hash(), hash);
+        assert_eq!(epoch.freeze().restored_from(), None);
+
+        let err = CPGEpoch::from_snapshot(temp.path(), Some("0000")).err().unwrap();
+        assert!(err.to_string().contains("expected 0000"), "{}", err);
+    }
+
+    #[test]
+    fn test_from_store_restores_each_snapshot() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+        let empty = store.save(CPGEpoch::new(0, 1).cpg(), 1).unwrap();
+        let one = store.save(one_node_epoch().cpg(), 2).unwrap();
+
+        let restored = CPGEpoch::from_store(&store, empty).unwrap();
+        assert_eq!((restored.restored_from(), restored.cpg().nodes.len()), (Some(1), 0));
+        let restored = CPGEpoch::from_store(&store, one).unwrap();
+        assert_eq!((restored.restored_from(), restored.cpg().nodes.len()), (Some(2), 1));
+        assert_eq!(restored.cpg_hash(), one_node_epoch().cpg().compute_hash());
+
+        let err = CPGEpoch::from_store(&store, SnapshotId(9)).err().unwrap();
+        assert!(err.to_string().contains("No snapshot 9"), "{}", err);
+    }
+
+    fn one_node_epoch() -> CPGEpoch {
+        use crate::cpg::model::{CPGNode, CPGNodeId, OriginRef};
+        use crate::types::ByteRange;
+
+        let mut epoch = CPGEpoch::new(1, 2);
+        epoch.cpg_mut().add_node(CPGNode::new(
+            CPGNodeId(0),
+            CPGNodeKind::File,
+            OriginRef::File { file_id: FileId::new(1) },
+            ByteRange::new(0, 1),
+        ));
+        epoch
+    }
+
+    #[test]
+    #[should_panic(expected = "CPG indices are stale")]
+    fn test_indices_fail_closed_after_mutation() {
+        // Built for the node, then mutated again without a rebuild
+        let mut epoch = one_node_epoch();
+        epoch.rebuild_indices();
+        epoch.cpg_mut().bump_generation();
+        epoch.indices();
+    }
+
+    #[test]
+    fn test_rebuild_indices_resyncs() {
+        let mut epoch = one_node_epoch();
+        assert!(!epoch.indices.is_current(epoch.cpg()));
+        epoch.rebuild_indices();
+        assert!(epoch.indices().is_current(epoch.cpg()));
+        assert_eq!(epoch.indices().file_ranges().len(), 1);
+        assert!(epoch.indices().verify_against(epoch.cpg()).is_ok());
+    }
+
+    #[test]
+    fn test_freeze_rebuilds_indices_and_stores_hash() {
+        let epoch = one_node_epoch();
+        let expected = epoch.cpg().compute_hash();
+        assert_eq!(epoch.stats().total_nodes, 0);
+
+        let frozen = epoch.freeze();
+        assert_eq!(frozen.cpg_hash(), expected);
+        assert_eq!(frozen.stats().total_nodes, 1);
+        assert_eq!((frozen.epoch_id(), frozen.semantic_epoch_id()), (2, 1));
+        assert!(frozen.verify().is_ok());
+        frozen.debug_verify();
+    }
+
+    #[test]
+    fn test_verify_detects_divergence() {
+        let mut frozen = one_node_epoch().freeze();
+        frozen.cpg.nodes.clear();
+
+        let err = frozen.verify().unwrap_err();
+        assert!(err.to_string().contains("Frozen CPG epoch 2"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Frozen CPG epoch 2")]
+    fn test_debug_verify_crashes_on_divergence() {
+        let mut frozen = one_node_epoch().freeze();
+        frozen.cpg.nodes.clear();
+        frozen.debug_verify();
+    }
 }
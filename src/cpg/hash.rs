@@ -1,43 +1,239 @@
 //! CPG Hashing - stable graph hashing for determinism validation
 //!
 //! Hash the entire CPG structure to detect unexpected changes.
+//!
+//! `CpgHasher` is fed nodes and edges one at a time, so the builder hashes
+//! the graph while fusing it and snapshot writers while serializing it
+//! (`HashedCpg`); `CPG::compute_hash` feeds it a finished graph. All three
+//! go through the same record functions below, so they cannot disagree.
+//!
+//! ## Framing (version 2)
+//!
+//! Integers are little-endian `u64` unless noted.
+//!
+//! - Node record: id, kind (1 byte), range start, range end, then `0x00`
+//!   for no label or `0x01`, label length in bytes and the label's UTF-8
+//!   text. Label text is hashed, never the `LabelId`.
+//! - Edge record: id, kind (1 byte), from, to.
+//!
+//! Node records and edge records each feed their own SHA-256 stream, in
+//! the order they are observed, so nodes and edges may be interleaved. The
+//! digest is SHA-256 over node count, node stream digest (32 bytes), edge
+//! count, edge stream digest (32 bytes), printed as lowercase hex.
+//!
+//! A partial digest (`finalize_partial`) is SHA-256 over the records
+//! observed since the previous partial, each prefixed with `N` (node) or
+//! `E` (edge). The builder takes one per file.
+//!
+//! Snapshots written before storage version 4 hold the version 1 digest
+//! (`compute_legacy_hash`): one SHA-256 stream of node count, node records,
+//! edge count, edge records, with `usize` integers.
 
-use crate::cpg::model::CPG;
+use crate::cpg::model::{CPGEdge, CPGNode, CPG};
+use serde::ser::{SerializeSeq, SerializeStruct};
+use serde::{Serialize, Serializer};
 use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+
+/// Streaming CPG hash (see the module docs for the framing)
+#[derive(Debug, Clone, Default)]
+pub struct CpgHasher {
+    nodes: Sha256,
+    node_count: u64,
+    edges: Sha256,
+    edge_count: u64,
+
+    /// Records since the last `finalize_partial` (None: partials disabled)
+    partial: Option<Sha256>,
+
+    /// Reused buffer for node records
+    record: Vec<u8>,
+}
+
+impl CpgHasher {
+    /// Hasher for a whole graph
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also hash records into per-segment digests for `finalize_partial`
+    pub fn with_partials(mut self) -> Self {
+        self.partial = Some(Sha256::new());
+        self
+    }
+
+    /// Hash one node, with its label text (`CPG::label`)
+    pub fn observe_node(&mut self, node: &CPGNode, label: Option<&str>) {
+        node_record(&mut self.record, node, label);
+        self.nodes.update(&self.record);
+        self.node_count += 1;
+        if let Some(partial) = &mut self.partial {
+            partial.update(b"N");
+            partial.update(&self.record);
+        }
+    }
+
+    /// Hash one edge
+    pub fn observe_edge(&mut self, edge: &CPGEdge) {
+        let record = edge_record(edge);
+        self.edges.update(record);
+        self.edge_count += 1;
+        if let Some(partial) = &mut self.partial {
+            partial.update(b"E");
+            partial.update(record);
+        }
+    }
+
+    /// Digest of the records observed since the previous partial (or the
+    /// start), then start a new segment
+    ///
+    /// Does not affect `finalize`. Panics unless built `with_partials`.
+    pub fn finalize_partial(&mut self) -> String {
+        let partial = self.partial.as_mut().expect("CpgHasher built without partials");
+        format!("{:x}", std::mem::take(partial).finalize())
+    }
+
+    /// Digest of every observed node and edge
+    pub fn finalize(self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.node_count.to_le_bytes());
+        hasher.update(self.nodes.finalize());
+        hasher.update(self.edge_count.to_le_bytes());
+        hasher.update(self.edges.finalize());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Framed bytes of one node, replacing `record`
+fn node_record(record: &mut Vec<u8>, node: &CPGNode, label: Option<&str>) {
+    record.clear();
+    record.extend_from_slice(&node.id.0.to_le_bytes());
+    record.push(node.kind as u8);
+    record.extend_from_slice(&(node.source_range.start as u64).to_le_bytes());
+    record.extend_from_slice(&(node.source_range.end as u64).to_le_bytes());
+    match label {
+        Some(label) => {
+            record.push(1);
+            record.extend_from_slice(&(label.len() as u64).to_le_bytes());
+            record.extend_from_slice(label.as_bytes());
+        }
+        None => record.push(0),
+    }
+}
+
+/// Framed bytes of one edge
+fn edge_record(edge: &CPGEdge) -> [u8; 25] {
+    let mut record = [0; 25];
+    record[..8].copy_from_slice(&edge.id.0.to_le_bytes());
+    record[8] = edge.kind as u8;
+    record[9..17].copy_from_slice(&edge.from.0.to_le_bytes());
+    record[17..].copy_from_slice(&edge.to.0.to_le_bytes());
+    record
+}
 
 impl CPG {
     /// Compute SHA-256 hash of the entire CPG
     ///
     /// **Deterministic**: Same CPG → same hash
     pub fn compute_hash(&self) -> String {
-        let mut hasher = Sha256::new();
+        let mut hasher = CpgHasher::new();
+        for node in &self.nodes {
+            hasher.observe_node(node, self.label(node));
+        }
+        for edge in &self.edges {
+            hasher.observe_edge(edge);
+        }
+        hasher.finalize()
+    }
 
-        // Hash node count
+    /// Hash of the version 1 framing, recorded by storage versions 1-3
+    pub fn compute_legacy_hash(&self) -> String {
+        let mut hasher = Sha256::new();
         hasher.update(self.nodes.len().to_le_bytes());
-
-        // Hash each node (in order)
         for node in &self.nodes {
             hasher.update(node.id.0.to_le_bytes());
-            hasher.update(&[node.kind as u8]);
+            hasher.update([node.kind as u8]);
             hasher.update(node.source_range.start.to_le_bytes());
             hasher.update(node.source_range.end.to_le_bytes());
+            match self.label(node) {
+                Some(label) => {
+                    hasher.update([1]);
+                    hasher.update(label.len().to_le_bytes());
+                    hasher.update(label.as_bytes());
+                }
+                None => hasher.update([0]),
+            }
         }
-
-        // Hash edge count
         hasher.update(self.edges.len().to_le_bytes());
-
-        // Hash each edge (in order)
         for edge in &self.edges {
             hasher.update(edge.id.0.to_le_bytes());
-            hasher.update(&[edge.kind as u8]);
+            hasher.update([edge.kind as u8]);
             hasher.update(edge.from.0.to_le_bytes());
             hasher.update(edge.to.0.to_le_bytes());
         }
-
         format!("{:x}", hasher.finalize())
     }
 }
 
+/// A CPG that hashes itself while being serialized
+///
+/// Serializes exactly like `CPG`; afterwards `digest` is its
+/// `compute_hash`, without a second pass over the graph.
+pub struct HashedCpg<'a> {
+    cpg: &'a CPG,
+    hasher: RefCell<CpgHasher>,
+}
+
+impl<'a> HashedCpg<'a> {
+    pub fn new(cpg: &'a CPG) -> Self {
+        Self { cpg, hasher: RefCell::new(CpgHasher::new()) }
+    }
+
+    /// Hash of the CPG as serialized so far (its `compute_hash` once
+    /// serialized exactly once)
+    pub fn digest(&self) -> String {
+        self.hasher.borrow().clone().finalize()
+    }
+}
+
+impl Serialize for HashedCpg<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Nodes<'b>(&'b HashedCpg<'b>);
+        struct Edges<'b>(&'b HashedCpg<'b>);
+
+        impl Serialize for Nodes<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let cpg = self.0.cpg;
+                let mut seq = serializer.serialize_seq(Some(cpg.nodes.len()))?;
+                for node in &cpg.nodes {
+                    self.0.hasher.borrow_mut().observe_node(node, cpg.label(node));
+                    seq.serialize_element(node)?;
+                }
+                seq.end()
+            }
+        }
+
+        impl Serialize for Edges<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let cpg = self.0.cpg;
+                let mut seq = serializer.serialize_seq(Some(cpg.edges.len()))?;
+                for edge in &cpg.edges {
+                    self.0.hasher.borrow_mut().observe_edge(edge);
+                    seq.serialize_element(edge)?;
+                }
+                seq.end()
+            }
+        }
+
+        // Field names and order of `CPG`'s derived Serialize
+        let mut state = serializer.serialize_struct("CPG", 3)?;
+        state.serialize_field("nodes", &Nodes(self))?;
+        state.serialize_field("edges", &Edges(self))?;
+        state.serialize_field("labels", self.cpg.labels())?;
+        state.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -73,4 +269,100 @@ mod tests {
 
         assert_eq!(cpg1.compute_hash(), cpg2.compute_hash());
     }
+
+    fn labelled(labels: &[&str], intern_first: &[&str]) -> CPG {
+        let mut cpg = CPG::new();
+        for label in intern_first {
+            cpg.intern_label(label);
+        }
+        for (i, label) in labels.iter().enumerate() {
+            let label = cpg.intern_label(label);
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(i as u64),
+                CPGNodeKind::CfgNode,
+                OriginRef::Ast { range: ByteRange::new(0, 0) },
+                ByteRange::new(0, 0),
+            ).with_label(label));
+        }
+        cpg
+    }
+
+    #[test]
+    fn test_cpg_hash_covers_label_text_not_ids() {
+        let plain = labelled(&["Entry", "Exit"], &[]);
+        // Same text under different LabelIds
+        let shifted = labelled(&["Entry", "Exit"], &["unused", "Exit"]);
+        assert_ne!(plain.nodes[0].label, shifted.nodes[0].label);
+        assert_eq!(plain.compute_hash(), shifted.compute_hash());
+
+        assert_ne!(plain.compute_hash(), labelled(&["Entry", "Merge"], &[]).compute_hash());
+        // Label boundaries are part of the hash
+        assert_ne!(labelled(&["ab", "c"], &[]).compute_hash(), labelled(&["a", "bc"], &[]).compute_hash());
+    }
+
+    #[test]
+    fn test_labels_round_trip_through_json() {
+        let cpg = labelled(&["Entry", "Variable { name: \"x\" }", "Entry"], &[]);
+        let restored: CPG = serde_json::from_str(&serde_json::to_string(&cpg).unwrap()).unwrap();
+
+        assert_eq!(restored.label_count(), 2);
+        assert_eq!(restored.label(&restored.nodes[1]), Some("Variable { name: \"x\" }"));
+        assert_eq!(restored.compute_hash(), cpg.compute_hash());
+    }
+
+    #[test]
+    fn test_observation_order_between_nodes_and_edges_is_free() {
+        let mut cpg = labelled(&["Entry", "Exit"], &[]);
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(0), CPGEdgeKind::ControlFlow, CPGNodeId(0), CPGNodeId(1)));
+
+        let mut hasher = CpgHasher::new();
+        hasher.observe_node(&cpg.nodes[0], cpg.label(&cpg.nodes[0]));
+        hasher.observe_edge(&cpg.edges[0]);
+        hasher.observe_node(&cpg.nodes[1], cpg.label(&cpg.nodes[1]));
+        assert_eq!(hasher.finalize(), cpg.compute_hash());
+        assert_ne!(cpg.compute_hash(), cpg.compute_legacy_hash());
+    }
+
+    #[test]
+    fn test_fusion_hash_matches_post_hoc_hash() {
+        use crate::pipeline::Pipeline;
+
+        let fixture = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/calls");
+        let output = Pipeline::default().run(&fixture).unwrap();
+        let epoch = &output.cpg_epoch;
+
+        assert_eq!(epoch.cpg_hash(), epoch.cpg().compute_hash());
+        let mut file_ids = output.semantic.get_all_file_ids();
+        file_ids.sort();
+        assert_eq!(epoch.file_hashes().keys().copied().collect::<Vec<_>>(), file_ids);
+        assert_ne!(epoch.file_hashes()[&file_ids[0]], epoch.file_hashes()[&file_ids[1]]);
+    }
+
+    #[test]
+    fn test_partials_cover_records_since_the_last_partial() {
+        let cpg = labelled(&["Entry", "Exit", "Entry"], &[]);
+        let mut hasher = CpgHasher::new().with_partials();
+        hasher.observe_node(&cpg.nodes[0], Some("Entry"));
+        let first = hasher.finalize_partial();
+        hasher.observe_node(&cpg.nodes[1], Some("Exit"));
+        hasher.observe_node(&cpg.nodes[2], Some("Entry"));
+        let second = hasher.finalize_partial();
+
+        let mut alone = CpgHasher::new().with_partials();
+        alone.observe_node(&cpg.nodes[0], Some("Entry"));
+        assert_eq!(alone.finalize_partial(), first);
+        assert_ne!(first, second);
+        // Partials leave the whole-graph digest alone
+        assert_eq!(hasher.finalize(), cpg.compute_hash());
+    }
+
+    #[test]
+    fn test_hashed_cpg_serializes_like_cpg() {
+        let mut cpg = labelled(&["Entry", "Variable { name: \"x\" }"], &["unused"]);
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(0), CPGEdgeKind::DataFlow, CPGNodeId(0), CPGNodeId(1)));
+
+        let hashed = HashedCpg::new(&cpg);
+        assert_eq!(serde_json::to_string(&hashed).unwrap(), serde_json::to_string(&cpg).unwrap());
+        assert_eq!(hashed.digest(), cpg.compute_hash());
+    }
 }
@@ -3,24 +3,87 @@
 //! All indices are derived from the CPG.
 //! They can be rebuilt at any time.
 //! They live inside CPGEpoch - when epoch dies, indices die.
+//!
+//! **Fail closed**: indices record the CPG's `Generation` when built and
+//! watch it. Once the CPG changes, every accessor (and `CPGEpoch::indices`)
+//! panics until `CPGEpoch::rebuild_indices` builds fresh ones; a stale index
+//! never answers. The indices themselves are private, so nothing reads
+//! around that check. `verify_against` rebuilds and compares every index,
+//! for `[verification] strict_validation` and tests.
 
 use crate::cpg::model::*;
 use crate::semantic::model::{FunctionId, SymbolId, ValueId};
-use std::collections::HashMap;
+use crate::types::{ByteRange, FileId};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use thiserror::Error;
+
+/// A Function node with its file (the closest preceding File node, None
+/// before any) and FunctionId
+pub type NamedFunction = (Option<FileId>, FunctionId, CPGNodeId);
 
 /// CPG Indices - all derived and rebuildable
 pub struct CPGIndices {
     /// Symbol → definitions
-    pub symbol_to_defs: HashMap<SymbolId, Vec<CPGNodeId>>,
+    symbol_to_defs: HashMap<SymbolId, Vec<CPGNodeId>>,
     
     /// Variable → uses
-    pub var_to_uses: HashMap<ValueId, Vec<CPGNodeId>>,
+    var_to_uses: HashMap<ValueId, Vec<CPGNodeId>>,
     
     /// Function → call sites
-    pub func_to_calls: HashMap<FunctionId, Vec<CPGNodeId>>,
+    func_to_calls: HashMap<FunctionId, Vec<CPGNodeId>>,
     
     /// Node → outgoing edges (by kind)
-    pub node_edges: HashMap<CPGNodeId, HashMap<CPGEdgeKind, Vec<CPGEdgeId>>>,
+    node_edges: HashMap<CPGNodeId, HashMap<CPGEdgeKind, Vec<CPGEdgeId>>>,
+
+    /// Node → incoming edge sources (by kind, in edge creation order)
+    node_preds: HashMap<CPGNodeId, HashMap<CPGEdgeKind, Vec<CPGNodeId>>>,
+
+    /// File → range of positions in `CPG::nodes`
+    file_nodes: BTreeMap<FileId, Range<usize>>,
+
+    /// File → non-empty source ranges, sorted by (start, end, node)
+    file_intervals: BTreeMap<FileId, Vec<(ByteRange, CPGNodeId)>>,
+
+    /// Function label → Function nodes, sorted by (file, function, node)
+    function_names: BTreeMap<String, Vec<NamedFunction>>,
+
+    /// Language (File node label) → files, sorted by FileId
+    languages: BTreeMap<String, Vec<FileId>>,
+
+    /// Generation of the CPG these were built from (None: built from none)
+    source: Option<GenerationWatch>,
+
+    /// That generation's value at build time
+    built_at: u64,
+}
+
+/// An index that does not match a rebuild from the CPG
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Index {index} diverges from the CPG")]
+pub struct IndexDivergence {
+    /// Name of the diverging index (field name)
+    pub index: &'static str,
+}
+
+/// Index sizes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IndexStats {
+    /// Symbols with definitions
+    pub symbol_to_defs: usize,
+
+    /// Values with uses
+    pub var_to_uses: usize,
+
+    /// Functions with call sites
+    pub func_to_calls: usize,
+
+    /// Edge entries in the forward and reverse adjacency
+    pub adjacency: usize,
+
+    /// Estimated heap bytes of all indices
+    pub estimated_bytes: usize,
 }
 
 impl CPGIndices {
@@ -31,6 +94,13 @@ impl CPGIndices {
             var_to_uses: HashMap::new(),
             func_to_calls: HashMap::new(),
             node_edges: HashMap::new(),
+            node_preds: HashMap::new(),
+            file_nodes: BTreeMap::new(),
+            file_intervals: BTreeMap::new(),
+            function_names: BTreeMap::new(),
+            languages: BTreeMap::new(),
+            source: None,
+            built_at: 0,
         }
     }
 
@@ -39,80 +109,316 @@ impl CPGIndices {
     /// **All indices are derived and deterministic**
     pub fn build(cpg: &CPG) -> Self {
         let mut indices = Self::new();
+        indices.source = Some(cpg.generation().watch());
+        indices.built_at = cpg.generation().get();
 
         // Build node_edges index (outgoing edges by kind)
         for edge in &cpg.edges {
             indices
                 .node_edges
                 .entry(edge.from)
-                .or_insert_with(HashMap::new)
+                .or_default()
                 .entry(edge.kind)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(edge.id);
         }
 
+        // Build node_preds index (reverse adjacency)
+        for edge in &cpg.edges {
+            indices
+                .node_preds
+                .entry(edge.to)
+                .or_default()
+                .entry(edge.kind)
+                .or_default()
+                .push(edge.from);
+        }
+
         // Build symbol_to_defs (Symbol nodes defining symbols)
-        for node in &cpg.nodes {
-            if node.kind == CPGNodeKind::Symbol {
-                if let OriginRef::Symbol { symbol_id } = node.origin {
+        for node in cpg.iter_nodes_of_kind(CPGNodeKind::Symbol) {
+            if let OriginRef::Symbol { symbol_id } = node.origin {
+                indices
+                    .symbol_to_defs
+                    .entry(symbol_id)
+                    .or_default()
+                    .push(node.id);
+            }
+        }
+
+        // Build var_to_uses (DFG values and their uses: sources of incoming
+        // DataFlow edges, already in node_preds)
+        for node in cpg.iter_nodes_of_kind(CPGNodeKind::DfgValue) {
+            if let OriginRef::Dfg { value_id } = node.origin {
+                let uses = indices.node_preds
+                    .get(&node.id)
+                    .and_then(|preds| preds.get(&CPGEdgeKind::DataFlow));
+                if let Some(uses) = uses {
                     indices
-                        .symbol_to_defs
-                        .entry(symbol_id)
-                        .or_insert_with(Vec::new)
-                        .push(node.id);
+                        .var_to_uses
+                        .entry(value_id)
+                        .or_default()
+                        .extend_from_slice(uses);
                 }
             }
         }
 
-        // Build var_to_uses (DFG values and their uses)
-        for node in &cpg.nodes {
-            if node.kind == CPGNodeKind::DfgValue {
-                if let OriginRef::Dfg { value_id } = node.origin {
-                    // Find all edges pointing to this value (uses)
-                    for edge in &cpg.edges {
-                        if edge.to == node.id && edge.kind == CPGEdgeKind::DataFlow {
-                            indices
-                                .var_to_uses
-                                .entry(value_id)
-                                .or_insert_with(Vec::new)
-                                .push(edge.from);
-                        }
-                    }
+        // Build func_to_calls (Function nodes and their call sites)
+        for edge in cpg.iter_edges_of_kind(CPGEdgeKind::Calls) {
+            // Get the target function node
+            if let Some(target_node) = cpg.get_node(edge.to) {
+                if let OriginRef::Function { function_id } = target_node.origin {
+                    indices
+                        .func_to_calls
+                        .entry(function_id)
+                        .or_default()
+                        .push(edge.from);
                 }
             }
         }
 
-        // Build func_to_calls (Function nodes and their call sites)
-        for edge in &cpg.edges {
-            if edge.kind == CPGEdgeKind::Calls {
-                // Get the target function node
-                if let Some(target_node) = cpg.get_node(edge.to) {
-                    if let OriginRef::Function { function_id } = target_node.origin {
+        // Build file_nodes (fusion emits each file's nodes contiguously,
+        // starting with its File node)
+        let mut open: Option<(FileId, usize)> = None;
+        for (position, node) in cpg.nodes.iter().enumerate() {
+            if let OriginRef::File { file_id } = node.origin {
+                if let Some((prev, start)) = open.take() {
+                    indices.file_nodes.insert(prev, start..position);
+                }
+                open = Some((file_id, position));
+            }
+        }
+        if let Some((prev, start)) = open {
+            indices.file_nodes.insert(prev, start..cpg.nodes.len());
+        }
+
+        // Build file_intervals (placeholder nodes with empty ranges are skipped)
+        for (file_id, range) in &indices.file_nodes {
+            let mut intervals: Vec<_> = cpg.nodes[range.clone()]
+                .iter()
+                .filter(|n| !n.source_range.is_empty())
+                .map(|n| (n.source_range, n.id))
+                .collect();
+            intervals.sort_by_key(|(r, id)| (r.start, r.end, *id));
+            indices.file_intervals.insert(*file_id, intervals);
+        }
+
+        // Build function_names (a function belongs to the preceding File node)
+        let mut file = None;
+        for node in &cpg.nodes {
+            match node.origin {
+                OriginRef::File { file_id } => file = Some(file_id),
+                OriginRef::Function { function_id } => {
+                    if let Some(name) = cpg.label(node) {
                         indices
-                            .func_to_calls
-                            .entry(function_id)
-                            .or_insert_with(Vec::new)
-                            .push(edge.from);
+                            .function_names
+                            .entry(name.to_string())
+                            .or_default()
+                            .push((file, function_id, node.id));
                     }
                 }
+                _ => {}
             }
         }
+        for functions in indices.function_names.values_mut() {
+            functions.sort();
+        }
+
+        // Build languages (File nodes are labelled with their language)
+        for node in &cpg.nodes {
+            if let (OriginRef::File { file_id }, Some(language)) = (node.origin, cpg.label(node)) {
+                indices.languages.entry(language.to_string()).or_default().push(file_id);
+            }
+        }
+        for files in indices.languages.values_mut() {
+            files.sort();
+        }
 
         indices
     }
 
+    /// Whether these were built from `cpg` as it is now
+    pub fn is_current(&self, cpg: &CPG) -> bool {
+        self.source.as_ref().is_some_and(|source| source.watches(cpg.generation()))
+            && self.built_at == cpg.generation().get()
+    }
+
+    /// **Panics** unless these were built from `cpg` as it is now
+    pub fn assert_current(&self, cpg: &CPG) {
+        if !self.is_current(cpg) {
+            panic!(
+                "CPG indices are stale: built at generation {}, the CPG is at {} (rebuild_indices)",
+                self.built_at, cpg.generation().get()
+            );
+        }
+    }
+
+    /// **Panics** if the CPG these were built from has changed since
+    fn check(&self) {
+        if let Some(source) = &self.source {
+            let now = source.get();
+            if now != self.built_at {
+                panic!(
+                    "CPG indices are stale: built at generation {}, the CPG is at {} (rebuild_indices)",
+                    self.built_at, now
+                );
+            }
+        }
+    }
+
+    /// Rebuild every index from `cpg` and compare, collecting each one that
+    /// differs (generations aside)
+    pub fn verify_against(&self, cpg: &CPG) -> Result<(), Vec<IndexDivergence>> {
+        let fresh = Self::build(cpg);
+        let matches = [
+            ("symbol_to_defs", self.symbol_to_defs == fresh.symbol_to_defs),
+            ("var_to_uses", self.var_to_uses == fresh.var_to_uses),
+            ("func_to_calls", self.func_to_calls == fresh.func_to_calls),
+            ("node_edges", self.node_edges == fresh.node_edges),
+            ("node_preds", self.node_preds == fresh.node_preds),
+            ("file_nodes", self.file_nodes == fresh.file_nodes),
+            ("file_intervals", self.file_intervals == fresh.file_intervals),
+            ("function_names", self.function_names == fresh.function_names),
+            ("languages", self.languages == fresh.languages),
+        ];
+        let divergences: Vec<IndexDivergence> = matches.into_iter()
+            .filter(|(_, matches)| !matches)
+            .map(|(index, _)| IndexDivergence { index })
+            .collect();
+        match divergences.is_empty() {
+            true => Ok(()),
+            false => Err(divergences),
+        }
+    }
+
+    /// Entry counts and estimated size
+    pub fn stats(&self) -> IndexStats {
+        self.check();
+        use std::mem::size_of;
+
+        let forward: usize = self.node_edges.values().flat_map(HashMap::values).map(Vec::len).sum();
+        let reverse: usize = self.node_preds.values().flat_map(HashMap::values).map(Vec::len).sum();
+        let adjacency_keys: usize = self.node_edges.values().map(HashMap::len)
+            .chain(self.node_preds.values().map(HashMap::len))
+            .map(|kinds| size_of::<CPGNodeId>() + kinds * size_of::<CPGEdgeKind>())
+            .sum();
+        let intervals: usize = self.file_intervals.values()
+            .map(|v| size_of::<FileId>() + v.len() * size_of::<(ByteRange, CPGNodeId)>())
+            .sum();
+        let names: usize = self.function_names.iter()
+            .map(|(name, v)| name.len() + v.len() * size_of::<NamedFunction>())
+            .sum();
+        let languages: usize = self.languages.iter()
+            .map(|(language, v)| language.len() + v.len() * size_of::<FileId>())
+            .sum();
+
+        IndexStats {
+            symbol_to_defs: self.symbol_to_defs.len(),
+            var_to_uses: self.var_to_uses.len(),
+            func_to_calls: self.func_to_calls.len(),
+            adjacency: forward + reverse,
+            estimated_bytes: keyed_bytes(&self.symbol_to_defs)
+                + keyed_bytes(&self.var_to_uses)
+                + keyed_bytes(&self.func_to_calls)
+                + adjacency_keys
+                + forward * size_of::<CPGEdgeId>()
+                + reverse * size_of::<CPGNodeId>()
+                + self.file_nodes.len() * size_of::<(FileId, Range<usize>)>()
+                + intervals
+                + names
+                + languages,
+        }
+    }
+
+    /// Get Function nodes with an exact name, in (FileId, FunctionId) order
+    pub fn functions_named(&self, name: &str) -> Vec<CPGNodeId> {
+        self.check();
+        self.function_names
+            .get(name)
+            .map(|functions| functions.iter().map(|(_, _, id)| *id).collect())
+            .unwrap_or_default()
+    }
+
+    /// Files whose File node is labelled with a language, in FileId order
+    pub fn files_in_language(&self, language: &str) -> &[FileId] {
+        self.check();
+        self.languages
+            .get(language)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Get sources of incoming edges to a node
+    pub fn get_sources_to(&self, node: CPGNodeId, kind: CPGEdgeKind) -> &[CPGNodeId] {
+        self.check();
+        self.node_preds
+            .get(&node)
+            .and_then(|preds_by_kind| preds_by_kind.get(&kind))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Get a file's source ranges, sorted by start offset
+    pub fn file_intervals(&self, file_id: FileId) -> &[(ByteRange, CPGNodeId)] {
+        self.check();
+        self.file_intervals
+            .get(&file_id)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Get the node positions belonging to a file
+    pub fn file_range(&self, file_id: FileId) -> Option<Range<usize>> {
+        self.check();
+        self.file_nodes.get(&file_id).cloned()
+    }
+
+    /// Node positions of every file, in FileId order
+    pub fn file_ranges(&self) -> &BTreeMap<FileId, Range<usize>> {
+        self.check();
+        &self.file_nodes
+    }
+
+    /// Function nodes by name (see `functions_named` for one name)
+    pub fn function_names(&self) -> &BTreeMap<String, Vec<NamedFunction>> {
+        self.check();
+        &self.function_names
+    }
+
+    /// Files by language (see `files_in_language` for one language)
+    pub fn languages(&self) -> &BTreeMap<String, Vec<FileId>> {
+        self.check();
+        &self.languages
+    }
+
+    /// Outgoing edge IDs of a node, by kind (see `CPG::iter_edges_from`)
+    pub(crate) fn edges_by_kind(&self, node: CPGNodeId) -> Option<&HashMap<CPGEdgeKind, Vec<CPGEdgeId>>> {
+        self.check();
+        self.node_edges.get(&node)
+    }
+
     /// Get outgoing edges from a node
     pub fn get_edges_from(&self, node: CPGNodeId, kind: CPGEdgeKind) -> Option<&Vec<CPGEdgeId>> {
+        self.check();
         self.node_edges
             .get(&node)
             .and_then(|edges_by_kind| edges_by_kind.get(&kind))
     }
 }
 
+/// Estimated bytes of a map of lists (keys plus elements)
+fn keyed_bytes<K, V>(map: &HashMap<K, Vec<V>>) -> usize {
+    map.values().map(|v| std::mem::size_of::<K>() + v.len() * std::mem::size_of::<V>()).sum()
+}
+
+impl Default for CPGIndices {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::ByteRange;
 
     #[test]
     fn test_cpg_indices_creation() {
@@ -129,6 +435,7 @@ mod tests {
         assert_eq!(indices.symbol_to_defs.len(), 0);
         assert_eq!(indices.var_to_uses.len(), 0);
         assert_eq!(indices.func_to_calls.len(), 0);
+        assert_eq!(indices.file_nodes.len(), 0);
     }
 
     #[test]
@@ -164,6 +471,10 @@ mod tests {
         let edges = indices.get_edges_from(CPGNodeId(1), CPGEdgeKind::ControlFlow);
         assert!(edges.is_some());
         assert_eq!(edges.unwrap().len(), 1);
+
+        // Check reverse index
+        assert_eq!(indices.get_sources_to(CPGNodeId(2), CPGEdgeKind::ControlFlow), &[CPGNodeId(1)]);
+        assert!(indices.get_sources_to(CPGNodeId(1), CPGEdgeKind::ControlFlow).is_empty());
     }
 
     #[test]
@@ -183,5 +494,123 @@ mod tests {
         
         assert_eq!(indices1.symbol_to_defs.len(), indices2.symbol_to_defs.len());
     }
-}
 
+    #[test]
+    fn test_cpg_indices_file_nodes() {
+        let mut cpg = CPG::new();
+        let mut next = 0;
+        for file in [FileId::new(7), FileId::new(3)] {
+            cpg.add_node(CPGNode::new(CPGNodeId(next), CPGNodeKind::File,
+                OriginRef::File { file_id: file }, ByteRange::new(0, 0)));
+            next += 1;
+            for _ in 0..3 {
+                cpg.add_node(CPGNode::new(CPGNodeId(next), CPGNodeKind::Function,
+                    OriginRef::Function { function_id: FunctionId(next) }, ByteRange::new(0, 0)));
+                next += 1;
+            }
+        }
+
+        let indices = CPGIndices::build(&cpg);
+
+        assert_eq!(indices.file_range(FileId::new(7)), Some(0..4));
+        assert_eq!(indices.file_range(FileId::new(3)), Some(4..8));
+        assert_eq!(indices.file_range(FileId::new(1)), None);
+
+        // Only the File node per file has an (empty) range here
+        assert!(indices.file_intervals(FileId::new(7)).is_empty());
+    }
+
+    #[test]
+    fn test_cpg_indices_function_names() {
+        let mut cpg = CPG::new();
+        let mut next = 0;
+        // File 7 is fused first but sorts after file 3
+        for (file, names) in [(FileId::new(7), ["run", "init"]), (FileId::new(3), ["init", "stop"])] {
+            cpg.add_node(CPGNode::new(CPGNodeId(next), CPGNodeKind::File,
+                OriginRef::File { file_id: file }, ByteRange::new(0, 0)));
+            next += 1;
+            for (f, name) in names.into_iter().enumerate() {
+                let label = cpg.intern_label(name);
+                cpg.add_node(CPGNode::new(CPGNodeId(next), CPGNodeKind::Function,
+                    OriginRef::Function { function_id: FunctionId(f as u64) }, ByteRange::new(0, 0)).with_label(label));
+                next += 1;
+            }
+        }
+
+        let indices = CPGIndices::build(&cpg);
+
+        assert_eq!(indices.functions_named("init"), vec![CPGNodeId(4), CPGNodeId(2)]);
+        assert_eq!(indices.functions_named("run"), vec![CPGNodeId(1)]);
+        assert!(indices.functions_named("missing").is_empty());
+        assert_eq!(indices.function_names.keys().collect::<Vec<_>>(), ["init", "run", "stop"]);
+    }
+
+    #[test]
+    fn test_cpg_indices_languages() {
+        let mut cpg = CPG::new();
+        for (i, (file, language)) in [(7, Some("rust")), (3, Some("rust")), (5, Some("python")), (9, None)].into_iter().enumerate() {
+            let mut node = CPGNode::new(CPGNodeId(i as u64), CPGNodeKind::File,
+                OriginRef::File { file_id: FileId::new(file) }, ByteRange::new(0, 0));
+            if let Some(language) = language {
+                node = node.with_label(cpg.intern_label(language));
+            }
+            cpg.add_node(node);
+        }
+
+        let indices = CPGIndices::build(&cpg);
+
+        assert_eq!(indices.files_in_language("rust"), [FileId::new(3), FileId::new(7)]);
+        assert_eq!(indices.files_in_language("python"), [FileId::new(5)]);
+        assert!(indices.files_in_language("go").is_empty());
+        assert_eq!(indices.languages.len(), 2);
+    }
+
+    fn two_nodes() -> CPG {
+        let mut cpg = CPG::new();
+        cpg.add_node(CPGNode::new(CPGNodeId(0), CPGNodeKind::File,
+            OriginRef::File { file_id: FileId::new(1) }, ByteRange::new(0, 10)));
+        cpg.add_node(CPGNode::new(CPGNodeId(1), CPGNodeKind::Function,
+            OriginRef::Function { function_id: FunctionId(0) }, ByteRange::new(0, 10)));
+        cpg
+    }
+
+    #[test]
+    #[should_panic(expected = "CPG indices are stale")]
+    fn test_accessors_fail_closed_after_cpg_change() {
+        let mut cpg = two_nodes();
+        let indices = CPGIndices::build(&cpg);
+        assert!(indices.is_current(&cpg));
+
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(0), CPGEdgeKind::ControlFlow, CPGNodeId(0), CPGNodeId(1)));
+        assert!(!indices.is_current(&cpg));
+        indices.get_edges_from(CPGNodeId(0), CPGEdgeKind::ControlFlow);
+    }
+
+    #[test]
+    fn test_indices_of_a_clone_are_independent() {
+        let cpg = two_nodes();
+        let indices = CPGIndices::build(&cpg);
+
+        // Mutating a copy leaves the original's indices current
+        let mut copy = cpg.clone();
+        copy.bump_generation();
+        assert!(indices.is_current(&cpg));
+        assert!(!indices.is_current(&copy));
+        assert_eq!(indices.stats().adjacency, 0);
+    }
+
+    #[test]
+    fn test_verify_against_names_diverging_indices() {
+        let mut cpg = two_nodes();
+        let indices = CPGIndices::build(&cpg);
+        assert_eq!(indices.verify_against(&cpg), Ok(()));
+
+        // Pushed directly: the generation does not move, the indices do diverge
+        cpg.edges.push(CPGEdge::new(CPGEdgeId(0), CPGEdgeKind::ControlFlow, CPGNodeId(0), CPGNodeId(1)));
+        assert!(indices.is_current(&cpg));
+        let divergences = indices.verify_against(&cpg).unwrap_err();
+        let names: Vec<_> = divergences.iter().map(|divergence| divergence.index).collect();
+        assert_eq!(names, ["node_edges", "node_preds"]);
+        assert_eq!(divergences[0].to_string(), "Index node_edges diverges from the CPG");
+    }
+}
@@ -0,0 +1,158 @@
+//! Zero-allocation iteration over CPG nodes and edges
+//!
+//! `CPG::iter_nodes_of_kind` and `CPG::iter_edges_of_kind` walk the node and
+//! edge lists lazily. `CPG::iter_edges_from` walks a node's outgoing edges
+//! through the adjacency index (`CPGIndices::node_edges`) instead of
+//! scanning every edge, merging the per-kind lists back into edge ID order
+//! (creation order for built CPGs, whose edge IDs are their positions).
+//!
+//! The `get_*` accessors on `CPG` collect these into `Vec`s; hot loops
+//! (query traversal, taint BFS, pointer graphs, index builds) use the
+//! iterators directly.
+
+use crate::cpg::index::CPGIndices;
+use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind, CPGNode, CPGNodeId, CPGNodeKind, CPG};
+
+impl CPG {
+    /// Nodes of a specific kind, in creation order
+    pub fn iter_nodes_of_kind(&self, kind: CPGNodeKind) -> impl Iterator<Item = &CPGNode> + '_ {
+        self.nodes.iter().filter(move |n| n.kind == kind)
+    }
+
+    /// Edges of a specific kind, in creation order
+    pub fn iter_edges_of_kind(&self, kind: CPGEdgeKind) -> impl Iterator<Item = &CPGEdge> + '_ {
+        self.edges.iter().filter(move |e| e.kind == kind)
+    }
+
+    /// Outgoing edges of a node, in edge ID order
+    ///
+    /// **Indexed**: `indices` must be built from this CPG as it is now (see
+    /// `CPGIndices::assert_current`); narrow with `EdgesFrom::of_kind`.
+    pub fn iter_edges_from<'a>(&'a self, indices: &'a CPGIndices, from: CPGNodeId) -> EdgesFrom<'a> {
+        let mut lists: [&[CPGEdgeId]; CPGEdgeKind::ALL.len()] = Default::default();
+        for (kind, ids) in indices.edges_by_kind(from).into_iter().flatten() {
+            lists[*kind as usize] = ids;
+        }
+        EdgesFrom { cpg: self, from, lists }
+    }
+
+    /// The edge with ID `id` leaving `from`
+    ///
+    /// Built CPGs number edges by position, so this is usually one lookup.
+    fn edge_from(&self, from: CPGNodeId, id: CPGEdgeId) -> Option<&CPGEdge> {
+        let matches = |e: &&CPGEdge| e.id == id && e.from == from;
+        self.edges.get(id.0 as usize)
+            .filter(matches)
+            .or_else(|| self.edges.iter().find(matches))
+    }
+}
+
+/// Outgoing edges of one node (see `CPG::iter_edges_from`)
+pub struct EdgesFrom<'a> {
+    cpg: &'a CPG,
+    from: CPGNodeId,
+
+    /// Remaining edge IDs, per kind (indexed by `CPGEdgeKind as usize`)
+    lists: [&'a [CPGEdgeId]; CPGEdgeKind::ALL.len()],
+}
+
+impl<'a> EdgesFrom<'a> {
+    /// Only the edges of one kind
+    pub fn of_kind(mut self, kind: CPGEdgeKind) -> Self {
+        for (other, list) in CPGEdgeKind::ALL.iter().zip(self.lists.iter_mut()) {
+            if *other != kind {
+                *list = &[];
+            }
+        }
+        self
+    }
+}
+
+impl<'a> Iterator for EdgesFrom<'a> {
+    type Item = &'a CPGEdge;
+
+    fn next(&mut self) -> Option<&'a CPGEdge> {
+        loop {
+            // The kind whose next edge has the lowest ID goes first
+            let list = self.lists.iter_mut()
+                .filter(|list| !list.is_empty())
+                .min_by_key(|list| list[0])?;
+            let id = list[0];
+            *list = &list[1..];
+            if let Some(edge) = self.cpg.edge_from(self.from, id) {
+                return Some(edge);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.lists.iter().map(|list| list.len()).sum()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpg::model::OriginRef;
+    use crate::semantic::model::NodeId;
+    use crate::types::ByteRange;
+
+    /// Four CFG nodes; node 0 has edges of three kinds, interleaved
+    fn interleaved() -> CPG {
+        let mut cpg = CPG::new();
+        for i in 0..4 {
+            cpg.add_node(CPGNode::new(CPGNodeId(i), CPGNodeKind::CfgNode,
+                OriginRef::Cfg { node_id: NodeId(i) }, ByteRange::new(0, 0)));
+        }
+        for (i, (kind, from, to)) in [
+            (CPGEdgeKind::DataFlow, 0, 1),
+            (CPGEdgeKind::ControlFlow, 0, 2),
+            (CPGEdgeKind::DataFlow, 1, 2),
+            (CPGEdgeKind::Calls, 0, 3),
+            (CPGEdgeKind::DataFlow, 0, 3),
+            (CPGEdgeKind::ControlFlow, 0, 1),
+        ].into_iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), kind, CPGNodeId(from), CPGNodeId(to)));
+        }
+        cpg
+    }
+
+    #[test]
+    fn test_iter_edges_from_matches_scan_order() {
+        let cpg = interleaved();
+        let indices = CPGIndices::build(&cpg);
+
+        for node in 0..5 {
+            let indexed: Vec<_> = cpg.iter_edges_from(&indices, CPGNodeId(node)).map(|e| e.id).collect();
+            let scanned: Vec<_> = cpg.get_edges_from(CPGNodeId(node)).iter().map(|e| e.id).collect();
+            assert_eq!(indexed, scanned, "node {}", node);
+        }
+        let data_flow: Vec<_> = cpg.iter_edges_from(&indices, CPGNodeId(0))
+            .of_kind(CPGEdgeKind::DataFlow)
+            .map(|e| e.to.0)
+            .collect();
+        assert_eq!(data_flow, [1, 3]);
+    }
+
+    #[test]
+    fn test_iter_edges_from_finds_edges_not_numbered_by_position() {
+        let mut cpg = interleaved();
+        for edge in &mut cpg.edges {
+            edge.id = CPGEdgeId(edge.id.0 + 10);
+        }
+        let indices = CPGIndices::build(&cpg);
+
+        let targets: Vec<_> = cpg.iter_edges_from(&indices, CPGNodeId(0)).map(|e| e.to.0).collect();
+        assert_eq!(targets, [1, 2, 3, 3, 1]);
+    }
+
+    #[test]
+    fn test_iter_of_kind_matches_get() {
+        let cpg = interleaved();
+        let ids: Vec<_> = cpg.iter_edges_of_kind(CPGEdgeKind::DataFlow).map(|e| e.id.0).collect();
+        assert_eq!(ids, [0, 2, 4]);
+        assert_eq!(cpg.get_edges_of_kind(CPGEdgeKind::DataFlow).len(), 3);
+        assert_eq!(cpg.iter_nodes_of_kind(CPGNodeKind::CfgNode).count(), 4);
+        assert!(cpg.iter_nodes_of_kind(CPGNodeKind::File).next().is_none());
+    }
+}
@@ -17,6 +17,12 @@ pub mod model;
 pub mod builder;
 pub mod index;
 pub mod hash;
+pub mod validate;
+pub mod subgraph;
+pub mod iter;
 
 pub use model::{CPGNode, CPGEdge, CPGNodeKind, CPGEdgeKind, CPGNodeId, CPGEdgeId};
-pub use epoch::CPGEpoch;
+pub use epoch::{CPGEpoch, FrozenCPGEpoch};
+pub use validate::ValidationError;
+pub use subgraph::pseudonym;
+pub use iter::EdgesFrom;
@@ -1,10 +1,21 @@
 //! Unified CPG model - schema definition (FROZEN)
 //!
 //! **This schema is immutable. No changes after commit.**
+//!
+//! Node labels are interned in the CPG's label table and stored on nodes as
+//! `LabelId`s; read them with `CPG::label`. Parameter DfgValue nodes also
+//! carry their position as a typed field (`CPGNode::parameter_position`),
+//! so nothing parses labels to find it.
+//!
+//! A CPG counts its structural changes in a `Generation` (not serialized),
+//! which the indices built from it watch (see `CPGIndices::assert_current`).
 
+use crate::memory::arena::{StringArena, StringId};
 use crate::types::ByteRange;
 use crate::semantic::model::{FunctionId, NodeId as CFGNodeId, ValueId as DFGValueId};
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 /// CPG Node ID - deterministic, sequential, never reused
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
@@ -14,8 +25,12 @@ pub struct CPGNodeId(pub u64);
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct CPGEdgeId(pub u64);
 
+/// Interned node label - index into the owning CPG's label table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LabelId(pub u32);
+
 /// CPG Node Kinds (6 types - frozen)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum CPGNodeKind {
     /// AST node
     AstNode,
@@ -37,7 +52,7 @@ pub enum CPGNodeKind {
 }
 
 /// CPG Edge Kinds (8 types - frozen)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum CPGEdgeKind {
     /// AST parent-child edge
     AstParent,
@@ -64,6 +79,20 @@ pub enum CPGEdgeKind {
     PointsTo,
 }
 
+impl CPGEdgeKind {
+    /// Every kind, in declaration order
+    pub const ALL: [CPGEdgeKind; 8] = [
+        Self::AstParent,
+        Self::AstChild,
+        Self::ControlFlow,
+        Self::DataFlow,
+        Self::Defines,
+        Self::Uses,
+        Self::Calls,
+        Self::PointsTo,
+    ];
+}
+
 /// Reference back to origin (AST/CFG/DFG)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OriginRef {
@@ -101,8 +130,15 @@ pub struct CPGNode {
     /// Source location (if applicable)
     pub source_range: ByteRange,
     
-    /// Optional label (for debugging)
-    pub label: Option<String>,
+    /// Optional label (for debugging; resolve with `CPG::label`)
+    pub label: Option<LabelId>,
+
+    /// Position of the parameter a DfgValue node stands for (None for
+    /// every other node)
+    ///
+    /// Not hashed on its own: the node's label already spells it out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameter_position: Option<u32>,
 }
 
 impl CPGNode {
@@ -114,14 +150,21 @@ impl CPGNode {
             origin,
             source_range,
             label: None,
+            parameter_position: None,
         }
     }
 
-    /// Create with label
-    pub fn with_label(mut self, label: String) -> Self {
+    /// Create with label (from `CPG::intern_label`)
+    pub fn with_label(mut self, label: LabelId) -> Self {
         self.label = Some(label);
         self
     }
+
+    /// Mark a DfgValue node as the parameter at `position`
+    pub fn with_parameter_position(mut self, position: u32) -> Self {
+        self.parameter_position = Some(position);
+        self
+    }
 }
 
 /// Unified CPG Edge
@@ -152,6 +195,52 @@ impl CPGEdge {
     }
 }
 
+/// Structural version of a CPG, bumped by `add_node`, `add_edge` and
+/// `bump_generation`
+///
+/// A clone of the CPG starts its own counter at the same value, so changing
+/// one copy never invalidates the other's indices.
+#[derive(Debug, Default)]
+pub struct Generation(Arc<AtomicU64>);
+
+impl Generation {
+    /// Current value
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    fn bump(&self) {
+        self.0.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// A watch on this counter (not a copy of it)
+    pub(crate) fn watch(&self) -> GenerationWatch {
+        GenerationWatch(Arc::clone(&self.0))
+    }
+}
+
+impl Clone for Generation {
+    fn clone(&self) -> Self {
+        Self(Arc::new(AtomicU64::new(self.get())))
+    }
+}
+
+/// Read-only view of one CPG's `Generation`
+#[derive(Debug)]
+pub(crate) struct GenerationWatch(Arc<AtomicU64>);
+
+impl GenerationWatch {
+    /// Current value of the watched counter
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Whether this watches `generation`
+    pub(crate) fn watches(&self, generation: &Generation) -> bool {
+        Arc::ptr_eq(&self.0, &generation.0)
+    }
+}
+
 /// CPG - Complete Code Property Graph
 ///
 /// **Storage**: All nodes and edges in Vec (deterministic order)
@@ -164,6 +253,14 @@ pub struct CPG {
     
     /// All edges (in creation order)
     pub edges: Vec<CPGEdge>,
+
+    /// Label text, indexed by LabelId (serialized as a string table)
+    #[serde(default)]
+    labels: StringArena,
+
+    /// Structural changes so far (restarts at 0 when deserialized)
+    #[serde(skip)]
+    generation: Generation,
 }
 
 impl CPG {
@@ -172,17 +269,52 @@ impl CPG {
         Self {
             nodes: Vec::new(),
             edges: Vec::new(),
+            labels: StringArena::new(),
+            generation: Generation::default(),
         }
     }
 
+    /// Intern a label, reusing the ID of identical text
+    pub fn intern_label(&mut self, label: &str) -> LabelId {
+        LabelId(self.labels.intern(label).0)
+    }
+
+    /// Label text of a node
+    pub fn label(&self, node: &CPGNode) -> Option<&str> {
+        node.label.map(|id| self.labels.resolve(StringId(id.0)))
+    }
+
+    /// Number of distinct labels
+    pub fn label_count(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Label table, as serialized
+    pub(crate) fn labels(&self) -> &StringArena {
+        &self.labels
+    }
+
     /// Add a node
     pub fn add_node(&mut self, node: CPGNode) {
         self.nodes.push(node);
+        self.generation.bump();
     }
 
     /// Add an edge
     pub fn add_edge(&mut self, edge: CPGEdge) {
         self.edges.push(edge);
+        self.generation.bump();
+    }
+
+    /// Structural version (see `Generation`)
+    pub fn generation(&self) -> &Generation {
+        &self.generation
+    }
+
+    /// Record a change made through `nodes` or `edges` directly, so indices
+    /// built before it refuse to answer
+    pub fn bump_generation(&mut self) {
+        self.generation.bump();
     }
 
     /// Get node by ID
@@ -190,7 +322,7 @@ impl CPG {
         self.nodes.iter().find(|n| n.id == id)
     }
 
-    /// Get edges from a node
+    /// Get edges from a node (scans every edge; see `iter_edges_from`)
     pub fn get_edges_from(&self, from: CPGNodeId) -> Vec<&CPGEdge> {
         self.edges.iter().filter(|e| e.from == from).collect()
     }
@@ -200,14 +332,21 @@ impl CPG {
         self.edges.iter().filter(|e| e.to == to).collect()
     }
 
-    /// Get edges of a specific kind
+    /// Get edges of a specific kind (see `iter_edges_of_kind`)
     pub fn get_edges_of_kind(&self, kind: CPGEdgeKind) -> Vec<&CPGEdge> {
-        self.edges.iter().filter(|e| e.kind == kind).collect()
+        self.iter_edges_of_kind(kind).collect()
     }
 
-    /// Get nodes of a specific kind
+    /// Get nodes of a specific kind (see `iter_nodes_of_kind`)
     pub fn get_nodes_of_kind(&self, kind: CPGNodeKind) -> Vec<&CPGNode> {
-        self.nodes.iter().filter(|n| n.kind == kind).collect()
+        self.iter_nodes_of_kind(kind).collect()
+    }
+
+    /// Estimated heap bytes (nodes, edges and labels)
+    pub fn estimated_bytes(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<CPGNode>()
+            + self.edges.capacity() * std::mem::size_of::<CPGEdge>()
+            + self.labels.estimated_bytes()
     }
 
     /// Get statistics
@@ -227,6 +366,12 @@ impl CPG {
     }
 }
 
+impl Default for CPG {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// CPG statistics
 #[derive(Debug, Clone)]
 pub struct CPGStats {
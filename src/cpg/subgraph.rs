@@ -0,0 +1,227 @@
+//! Subgraph extraction - minimal reproductions
+//!
+//! `CPG::extract_subgraph` copies the neighborhood of some root nodes into
+//! a standalone CPG that saves, restores and queries like any other (see
+//! `CPGSnapshot::save`). Nodes are reached by a breadth-first walk over
+//! edges in both directions, up to `depth` edges from a root, optionally
+//! along some edge kinds only. Every reached node also keeps its File node,
+//! so per-file indices still group it (see `CPGIndices::file_nodes`).
+//!
+//! The copy keeps the original node and edge order and renumbers both from
+//! 0, so the same graph, roots and depth always extract the same subgraph.
+//! Edges are copied when both endpoints were reached (and their kind was
+//! walked).
+//!
+//! `CPG::anonymized` replaces identifiers with `pseudonym`s: whole labels
+//! of Function and Symbol nodes, and the quoted names and constants inside
+//! the `Debug` labels of other nodes. A pseudonym is a hash of the text, so
+//! the same identifier gets the same pseudonym in every graph and a query
+//! over an anonymized graph can still name it. Byte ranges are kept.
+
+use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind, CPGNodeId, CPGNodeKind, OriginRef, CPG};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Stable stand-in for an identifier: `id_` and 8 hex digits of its hash
+pub fn pseudonym(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    let hex: String = digest[..4].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("id_{}", hex)
+}
+
+impl CPG {
+    /// Copy the nodes within `depth` edges of `roots` (following only
+    /// `kinds`, if given) and the edges between them, renumbered from 0
+    ///
+    /// Roots that are not nodes of the graph are ignored.
+    pub fn extract_subgraph(&self, roots: &[CPGNodeId], depth: usize, kinds: Option<&[CPGEdgeKind]>) -> CPG {
+        let walked = |kind: CPGEdgeKind| kinds.is_none_or(|kinds| kinds.contains(&kind));
+
+        // Neighbors in edge order, out and in alike
+        let mut neighbors: HashMap<CPGNodeId, Vec<CPGNodeId>> = HashMap::new();
+        for edge in self.edges.iter().filter(|edge| walked(edge.kind)) {
+            neighbors.entry(edge.from).or_default().push(edge.to);
+            neighbors.entry(edge.to).or_default().push(edge.from);
+        }
+
+        let positions: HashMap<CPGNodeId, usize> = self.nodes.iter()
+            .enumerate()
+            .map(|(position, node)| (node.id, position))
+            .collect();
+        let mut reached = HashSet::new();
+        let mut queue = VecDeque::new();
+        for root in roots.iter().filter(|root| positions.contains_key(root)) {
+            if reached.insert(*root) {
+                queue.push_back((*root, 0));
+            }
+        }
+        while let Some((node, distance)) = queue.pop_front() {
+            if distance == depth {
+                continue;
+            }
+            for next in neighbors.get(&node).into_iter().flatten() {
+                if reached.insert(*next) {
+                    queue.push_back((*next, distance + 1));
+                }
+            }
+        }
+
+        // Each reached node's file: the closest preceding File node
+        let mut file = None;
+        let mut keep = vec![false; self.nodes.len()];
+        for (position, node) in self.nodes.iter().enumerate() {
+            if let OriginRef::File { .. } = node.origin {
+                file = Some(position);
+            }
+            if reached.contains(&node.id) {
+                keep[position] = true;
+                if let Some(file) = file {
+                    keep[file] = true;
+                }
+            }
+        }
+
+        let mut subgraph = CPG::new();
+        let mut ids = HashMap::new();
+        for (node, _) in self.nodes.iter().zip(&keep).filter(|(_, keep)| **keep) {
+            let mut copy = node.clone();
+            copy.id = CPGNodeId(ids.len() as u64);
+            copy.label = self.label(node).map(|label| subgraph.intern_label(label));
+            ids.insert(node.id, copy.id);
+            subgraph.add_node(copy);
+        }
+        for edge in self.edges.iter().filter(|edge| walked(edge.kind)) {
+            if let (Some(from), Some(to)) = (ids.get(&edge.from), ids.get(&edge.to)) {
+                let id = CPGEdgeId(subgraph.edges.len() as u64);
+                subgraph.add_edge(CPGEdge::new(id, edge.kind, *from, *to));
+            }
+        }
+        subgraph
+    }
+
+    /// A copy with every identifier in its labels replaced by its `pseudonym`
+    pub fn anonymized(&self) -> CPG {
+        let mut anonymized = CPG::new();
+        for node in &self.nodes {
+            let mut copy = node.clone();
+            copy.label = self.label(node).map(|label| {
+                let text = match node.kind {
+                    CPGNodeKind::Function | CPGNodeKind::Symbol => pseudonym(label),
+                    _ => anonymize_quoted(label),
+                };
+                anonymized.intern_label(&text)
+            });
+            anonymized.add_node(copy);
+        }
+        anonymized.edges = self.edges.clone();
+        anonymized
+    }
+}
+
+/// `Debug` text with each quoted string replaced by its pseudonym
+fn anonymize_quoted(label: &str) -> String {
+    let mut out = String::with_capacity(label.len());
+    let mut rest = label;
+    while let Some(open) = rest.find('"') {
+        out.push_str(&rest[..=open]);
+        rest = &rest[open + 1..];
+
+        // The closing quote is the first one not escaped
+        let mut escaped = false;
+        let close = rest.char_indices()
+            .find(|&(_, c)| {
+                let closes = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                closes
+            })
+            .map_or(rest.len(), |(index, _)| index);
+        out.push_str(&pseudonym(&rest[..close]));
+        rest = &rest[close..];
+        if let Some(after) = rest.strip_prefix('"') {
+            out.push('"');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpg::model::CPGNode;
+    use crate::semantic::model::{FunctionId, ValueId};
+    use crate::types::{ByteRange, FileId};
+
+    /// File, then a chain of four values: 1 → 2 → 3 → 4, and 2 → 5 (Calls)
+    fn chain() -> CPG {
+        let mut cpg = CPG::new();
+        cpg.add_node(CPGNode::new(CPGNodeId(0), CPGNodeKind::File, OriginRef::File { file_id: FileId::new(1) }, ByteRange::new(0, 0)));
+        for i in 1..=4 {
+            let node = CPGNode::new(CPGNodeId(i), CPGNodeKind::DfgValue, OriginRef::Dfg { value_id: ValueId(i) }, ByteRange::new(i as usize, i as usize + 1));
+            let label = cpg.intern_label(&format!("Variable {{ name: \"v{}\" }}", i));
+            cpg.add_node(node.with_label(label));
+        }
+        let function = CPGNode::new(CPGNodeId(5), CPGNodeKind::Function, OriginRef::Function { function_id: FunctionId(0) }, ByteRange::new(0, 9));
+        let label = cpg.intern_label("secret_handler");
+        cpg.add_node(function.with_label(label));
+        for (i, (kind, from, to)) in [
+            (CPGEdgeKind::DataFlow, 1, 2),
+            (CPGEdgeKind::DataFlow, 2, 3),
+            (CPGEdgeKind::DataFlow, 3, 4),
+            (CPGEdgeKind::Calls, 2, 5),
+        ].into_iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), kind, CPGNodeId(from), CPGNodeId(to)));
+        }
+        cpg
+    }
+
+    fn labels(cpg: &CPG) -> Vec<&str> {
+        cpg.nodes.iter().map(|node| cpg.label(node).unwrap_or("")).collect()
+    }
+
+    #[test]
+    fn test_extract_walks_both_directions_to_depth() {
+        let subgraph = chain().extract_subgraph(&[CPGNodeId(3)], 1, None);
+
+        // 2 and 4 are one edge away, plus the file they belong to
+        assert_eq!(labels(&subgraph), ["", "Variable { name: \"v2\" }", "Variable { name: \"v3\" }", "Variable { name: \"v4\" }"]);
+        let ids: Vec<_> = subgraph.nodes.iter().map(|node| node.id.0).collect();
+        assert_eq!(ids, [0, 1, 2, 3]);
+        let edges: Vec<_> = subgraph.edges.iter().map(|edge| (edge.id.0, edge.from.0, edge.to.0)).collect();
+        assert_eq!(edges, [(0, 1, 2), (1, 2, 3)]);
+        assert!(subgraph.validate().is_ok());
+    }
+
+    #[test]
+    fn test_extract_follows_only_given_kinds() {
+        let cpg = chain();
+        let all = cpg.extract_subgraph(&[CPGNodeId(1)], 2, None);
+        let data_flow = cpg.extract_subgraph(&[CPGNodeId(1)], 2, Some(&[CPGEdgeKind::DataFlow]));
+
+        assert!(labels(&all).contains(&"secret_handler"));
+        assert!(!labels(&data_flow).contains(&"secret_handler"));
+        assert!(data_flow.edges.iter().all(|edge| edge.kind == CPGEdgeKind::DataFlow));
+        assert!(cpg.extract_subgraph(&[CPGNodeId(99)], 2, None).nodes.is_empty());
+    }
+
+    #[test]
+    fn test_anonymized_replaces_names_and_quoted_text() {
+        let anonymized = chain().anonymized();
+        let labels = labels(&anonymized);
+
+        assert_eq!(labels[1], format!("Variable {{ name: \"{}\" }}", pseudonym("v1")));
+        assert_eq!(labels[5], pseudonym("secret_handler"));
+        assert!(!labels.iter().any(|label| label.contains("secret")));
+        assert_eq!(anonymized.edges.len(), 4);
+        assert_eq!(anonymized.compute_hash(), chain().anonymized().compute_hash());
+    }
+
+    #[test]
+    fn test_anonymize_quoted_handles_escapes() {
+        let label = r#"Constant { value: "\"a\"" }"#;
+        assert_eq!(anonymize_quoted(label), format!("Constant {{ value: \"{}\" }}", pseudonym(r#"\"a\""#)));
+        assert_eq!(anonymize_quoted("Statement"), "Statement");
+        assert_eq!(anonymize_quoted("x \"open"), format!("x \"{}", pseudonym("open")));
+    }
+}
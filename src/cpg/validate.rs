@@ -0,0 +1,165 @@
+//! CPG validation - structural debug checks (Step 3.2)
+//!
+//! CFG NodeIds (per file), DFG ValueIds (per function) and CPGNodeIds
+//! (per graph) are different namespaces. A CPG edge built from a raw CFG or
+//! DFG id still type-checks and usually even points at an existing node,
+//! just the wrong one. These checks catch that at runtime:
+//!
+//! - node IDs strictly increase through `nodes`
+//! - edge IDs are unique
+//! - every edge endpoint exists, and control/data-flow edges connect
+//!   CFG/DFG nodes
+//! - with a SemanticEpoch, every OriginRef names a CFG node, DFG value,
+//!   symbol, function or file that exists
+//!
+//! Origins are resolved in fusion order: a node's file is the closest
+//! preceding File node, and a CFG node belongs to the closest preceding
+//! Function node of that file. AST origins are not checked.
+//!
+//! CPGBuilder runs `validate_against` after fusion when
+//! `[verification] strict_validation` is set.
+
+use crate::cpg::model::{CPGEdgeId, CPGEdgeKind, CPGNodeId, CPGNodeKind, OriginRef, CPG};
+use crate::semantic::model::CFG;
+use crate::semantic::SemanticEpoch;
+use crate::types::FileId;
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// One structural problem in a CPG
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationError {
+    /// Node IDs are not strictly increasing
+    #[error("Node {id:?} at index {index} does not follow {previous:?}")]
+    NodeOrder { index: usize, id: CPGNodeId, previous: CPGNodeId },
+
+    /// Two edges share an ID
+    #[error("Duplicate edge ID {0:?}")]
+    DuplicateEdge(CPGEdgeId),
+
+    /// An edge endpoint is not a node of the graph
+    #[error("Edge {edge:?} references missing node {node:?}")]
+    DanglingEdge { edge: CPGEdgeId, node: CPGNodeId },
+
+    /// An edge endpoint has the wrong kind for the edge
+    #[error("Edge {edge:?} ({kind:?}) connects {node:?}, a {found:?} node, expected {expected:?}")]
+    EndpointKind {
+        edge: CPGEdgeId,
+        kind: CPGEdgeKind,
+        node: CPGNodeId,
+        found: CPGNodeKind,
+        expected: CPGNodeKind,
+    },
+
+    /// An origin does not exist in the semantic epoch
+    #[error("Node {node:?} has origin {origin:?}, which is not in the semantic epoch")]
+    MissingOrigin { node: CPGNodeId, origin: OriginRef },
+}
+
+impl CPG {
+    /// Check graph structure, collecting every error
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        into_result(self.structural_errors())
+    }
+
+    /// Check graph structure and that every origin exists in `semantic`
+    pub fn validate_against(&self, semantic: &SemanticEpoch) -> Result<(), Vec<ValidationError>> {
+        let mut errors = self.structural_errors();
+        errors.extend(self.origin_errors(semantic));
+        into_result(errors)
+    }
+
+    fn structural_errors(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+
+        for (index, pair) in self.nodes.windows(2).enumerate() {
+            if pair[1].id <= pair[0].id {
+                errors.push(ValidationError::NodeOrder {
+                    index: index + 1,
+                    id: pair[1].id,
+                    previous: pair[0].id,
+                });
+            }
+        }
+
+        let kinds: HashMap<CPGNodeId, CPGNodeKind> = self.nodes.iter().map(|n| (n.id, n.kind)).collect();
+        let mut edge_ids = HashSet::new();
+        for edge in &self.edges {
+            if !edge_ids.insert(edge.id) {
+                errors.push(ValidationError::DuplicateEdge(edge.id));
+            }
+
+            let expected = match edge.kind {
+                CPGEdgeKind::ControlFlow => Some(CPGNodeKind::CfgNode),
+                CPGEdgeKind::DataFlow => Some(CPGNodeKind::DfgValue),
+                _ => None,
+            };
+            for node in [edge.from, edge.to] {
+                match (kinds.get(&node), expected) {
+                    (None, _) => errors.push(ValidationError::DanglingEdge { edge: edge.id, node }),
+                    (Some(&found), Some(expected)) if found != expected => {
+                        errors.push(ValidationError::EndpointKind {
+                            edge: edge.id,
+                            kind: edge.kind,
+                            node,
+                            found,
+                            expected,
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        errors
+    }
+
+    fn origin_errors(&self, semantic: &SemanticEpoch) -> Vec<ValidationError> {
+        let all_files = semantic.get_all_file_ids();
+        let mut file: Option<FileId> = None;
+        let mut function: Option<&CFG> = None;
+        let mut errors = Vec::new();
+
+        for node in &self.nodes {
+            let files = match file {
+                Some(file_id) => vec![file_id],
+                None => all_files.clone(),
+            };
+            let cfgs = || files.iter().filter_map(|f| semantic.get_cfgs(*f)).flatten();
+
+            let found = match node.origin {
+                OriginRef::Ast { .. } => true,
+                OriginRef::File { file_id } => {
+                    file = Some(file_id);
+                    function = None;
+                    all_files.contains(&file_id)
+                }
+                OriginRef::Function { function_id } => {
+                    function = cfgs().find(|cfg| cfg.function_id == function_id);
+                    function.is_some()
+                }
+                OriginRef::Cfg { node_id } => match function {
+                    Some(cfg) => cfg.get_node(node_id).is_some(),
+                    None => cfgs().any(|cfg| cfg.get_node(node_id).is_some()),
+                },
+                OriginRef::Dfg { value_id } => files.iter()
+                    .filter_map(|f| semantic.get_dfgs(*f))
+                    .flatten()
+                    .any(|dfg| dfg.get_value(value_id).is_some()),
+                OriginRef::Symbol { symbol_id } => files.iter()
+                    .filter_map(|f| semantic.get_symbols(*f))
+                    .any(|table| table.get_symbol(symbol_id).is_some()),
+            };
+
+            if !found {
+                errors.push(ValidationError::MissingOrigin { node: node.id, origin: node.origin });
+            }
+        }
+
+        errors
+    }
+}
+
+fn into_result(errors: Vec<ValidationError>) -> Result<(), Vec<ValidationError>> {
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
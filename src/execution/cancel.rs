@@ -0,0 +1,172 @@
+//! Cancellation and deadlines
+//!
+//! A `CancellationToken` is a shared flag plus an optional deadline. Long
+//! loops (scheduler tasks, edge-following primitives, taint and
+//! reachability BFS, the points-to worklist) consult it at loop boundaries
+//! through a `Checkpoint` and stop with `Interrupted`, which records how far
+//! they got.
+//!
+//! **Determinism**: Tokens are only read, never consulted for what to do
+//! next. A run that is not interrupted does the same work in the same order
+//! with or without a token.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Loop iterations between two token checks
+const CHECK_INTERVAL: u64 = 64;
+
+/// Shared cancellation flag with an optional deadline
+///
+/// Clones share the flag: cancelling one cancels all.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    /// Set by `cancel`
+    cancelled: Arc<AtomicBool>,
+
+    /// Work past this instant times out
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Token that is never cancelled unless `cancel` is called
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same flag, timing out `timeout` from now
+    ///
+    /// A timeout too large to represent as an instant never expires.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Instant::now().checked_add(timeout);
+        self
+    }
+
+    /// Same flag, timing out at `deadline`
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Cancel every clone of this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether `cancel` was called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fail if cancelled or past the deadline, reporting `progress`
+    pub fn check(&self, progress: Progress) -> Result<(), Interrupted> {
+        if self.is_cancelled() {
+            return Err(Interrupted::Cancelled(progress));
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(Interrupted::Timeout(progress)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Work finished before an interruption
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    /// Tasks (query stages) that ran to completion
+    pub tasks_completed: usize,
+
+    /// Loop iterations of the interrupted task
+    pub steps: u64,
+}
+
+/// Why a run stopped early
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum Interrupted {
+    /// The token was cancelled
+    #[error("Cancelled after {} task(s) and {} step(s)", .0.tasks_completed, .0.steps)]
+    Cancelled(Progress),
+
+    /// The token's deadline passed
+    #[error("Timed out after {} task(s) and {} step(s)", .0.tasks_completed, .0.steps)]
+    Timeout(Progress),
+}
+
+impl Interrupted {
+    /// Progress at the point of interruption
+    pub fn progress(&self) -> Progress {
+        match self {
+            Interrupted::Cancelled(progress) | Interrupted::Timeout(progress) => *progress,
+        }
+    }
+
+    /// Same interruption, counting `tasks` more completed tasks
+    pub fn after_tasks(self, tasks: usize) -> Self {
+        let add = |progress: Progress| Progress { tasks_completed: progress.tasks_completed + tasks, ..progress };
+        match self {
+            Interrupted::Cancelled(progress) => Interrupted::Cancelled(add(progress)),
+            Interrupted::Timeout(progress) => Interrupted::Timeout(add(progress)),
+        }
+    }
+}
+
+/// Step counter for one loop, checking its token every few iterations
+pub struct Checkpoint<'a> {
+    token: &'a CancellationToken,
+    steps: u64,
+}
+
+impl<'a> Checkpoint<'a> {
+    pub fn new(token: &'a CancellationToken) -> Self {
+        Self { token, steps: 0 }
+    }
+
+    /// Count one iteration; fail if the token says stop
+    pub fn step(&mut self) -> Result<(), Interrupted> {
+        self.steps += 1;
+        if self.steps.is_multiple_of(CHECK_INTERVAL) {
+            self.token.check(Progress { tasks_completed: 0, steps: self.steps })?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of a run under a fresh token, which nothing else can cancel
+pub(crate) fn uninterrupted<T>(result: Result<T, Interrupted>) -> T {
+    result.expect("a token without a deadline is only cancelled by its holder")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_reports_steps() {
+        let token = CancellationToken::new();
+        let mut checkpoint = Checkpoint::new(&token);
+        for _ in 0..100 {
+            checkpoint.step().unwrap();
+        }
+
+        token.clone().cancel();
+        let stopped = (0..100).find_map(|_| checkpoint.step().err()).unwrap();
+        assert_eq!(stopped, Interrupted::Cancelled(Progress { tasks_completed: 0, steps: 128 }));
+        assert_eq!(stopped.after_tasks(2).to_string(), "Cancelled after 2 task(s) and 128 step(s)");
+    }
+
+    #[test]
+    fn test_deadline_times_out() {
+        let token = CancellationToken::new().with_deadline(Instant::now());
+        assert!(matches!(token.check(Progress::default()), Err(Interrupted::Timeout(_))));
+        assert!(CancellationToken::new().with_timeout(Duration::from_secs(3600)).check(Progress::default()).is_ok());
+    }
+
+    #[test]
+    fn test_unrepresentable_timeout_never_expires() {
+        let token = CancellationToken::new().with_timeout(Duration::from_secs(u64::MAX));
+        assert!(token.check(Progress::default()).is_ok());
+    }
+}
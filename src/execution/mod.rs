@@ -10,11 +10,14 @@
 //! - No shared mutable state
 //! - No parallel graph mutation
 //! - All commits on one thread, one order
+//! - Cancellation is checked, never waited on (see `cancel`)
 
+pub mod cancel;
 pub mod plan;
 pub mod scheduler;
 pub mod task;
 
+pub use cancel::{CancellationToken, Checkpoint, Interrupted, Progress};
 pub use plan::{ExecutionPlan, Stage, DeterministicOrder};
 pub use task::{Task, TaskId, WorkFragment};
-pub use scheduler::Scheduler;
+pub use scheduler::{FragmentOutput, PathTable, Scheduler, TaskRecord};
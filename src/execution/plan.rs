@@ -2,7 +2,7 @@
 //!
 //! **Critical**: Results merged in deterministic order
 
-use crate::execution::task::Task;
+use crate::execution::task::{Task, WorkFragment};
 
 /// Deterministic ordering for commit
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -74,12 +74,25 @@ impl ExecutionPlan {
     pub fn task_count(&self) -> usize {
         self.stages.iter().map(|s| s.parallel_tasks.len()).sum()
     }
+
+    /// Whether any task needs CPG indices (reverse traversal)
+    pub fn needs_indices(&self) -> bool {
+        self.stages.iter()
+            .flat_map(|s| &s.parallel_tasks)
+            .any(|t| matches!(t.work, WorkFragment::FollowEdgesReverse { .. }))
+    }
+}
+
+impl Default for ExecutionPlan {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::execution::task::WorkFragment;
+    use crate::execution::task::TaskId;
 
     #[test]
     fn test_stage_creation() {
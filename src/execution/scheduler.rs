@@ -1,93 +1,319 @@
 //! Task scheduler - parallel execution, serial commit
 //!
 //! **Critical**: All commits happen on one thread in deterministic order
+//!
+//! Every fragment produces a node vector. Analyses that find paths
+//! (`Taint`) flatten them into that vector and return a path table of
+//! ranges into it alongside.
+//!
+//! `execute_traced` also returns one TaskRecord per task (result size and
+//! wall time), in the same order as the outputs.
+//!
+//! `execute_cancellable` checks a `CancellationToken` before every task and
+//! inside every loop over nodes; an interrupted plan returns `Interrupted`
+//! instead of partial outputs.
+//!
+//! `FindNodes` and `Filter` fragments over more than `chunk_size` nodes are
+//! split into chunks, scanned in parallel (on the pool) and concatenated in
+//! chunk order, so one huge scan does not leave the other threads idle and
+//! its output equals the unchunked scan. Each chunk's wall time is recorded.
 
+use crate::analysis::{ReachabilityAnalysis, TaintAnalysis};
+use crate::cpg::index::CPGIndices;
+use crate::config::ExecutionConfig;
 use crate::cpg::model::{CPG, CPGNodeId};
+use crate::execution::cancel::{uninterrupted, CancellationToken, Checkpoint, Interrupted, Progress};
 use crate::execution::plan::ExecutionPlan;
-use crate::execution::task::{Task, WorkFragment};
+use crate::execution::task::{Task, TaskId, WorkFragment};
 use crate::query::primitives::QueryPrimitives;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// Query result
 pub type QueryResult = Vec<CPGNodeId>;
 
+/// Paths in a flattened result: each range of the node vector is one path
+pub type PathTable = Vec<Range<usize>>;
+
+/// Nodes per chunk of a chunked scan, by default
+pub const DEFAULT_CHUNK_SIZE: usize = 65_536;
+
+/// Result of one fragment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FragmentOutput {
+    /// Result nodes (paths concatenated, for path fragments)
+    pub nodes: QueryResult,
+
+    /// Path table (path fragments only)
+    pub paths: Option<PathTable>,
+}
+
+impl FragmentOutput {
+    /// Plain node set
+    fn nodes(nodes: QueryResult) -> Self {
+        Self { nodes, paths: None }
+    }
+
+    /// Flatten paths, recording where each one starts and ends
+    fn paths<'a>(paths: impl IntoIterator<Item = &'a [CPGNodeId]>) -> Self {
+        let mut nodes = Vec::new();
+        let mut table = Vec::new();
+        for path in paths {
+            let start = nodes.len();
+            nodes.extend_from_slice(path);
+            table.push(start..nodes.len());
+        }
+        Self { nodes, paths: Some(table) }
+    }
+}
+
+/// Execution metadata of one task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskRecord {
+    /// Task the record belongs to
+    pub task_id: TaskId,
+
+    /// Nodes in the task's output
+    pub result_size: usize,
+
+    /// Wall time of the task (non-deterministic)
+    pub wall_us: u64,
+
+    /// Wall time of each chunk, in chunk order (empty if not chunked;
+    /// non-deterministic)
+    pub chunk_us: Vec<u64>,
+}
+
 /// Scheduler for parallel execution
 pub struct Scheduler {
     /// Thread pool size
-    _thread_count: usize,
+    thread_count: usize,
+
+    /// Nodes per chunk of `FindNodes`/`Filter` scans (0 = never chunk)
+    chunk_size: usize,
+
+    /// Dedicated pool (only when more than one thread is requested)
+    #[cfg(feature = "parallel-execution")]
+    pool: Option<Arc<rayon::ThreadPool>>,
 }
 
 impl Scheduler {
     /// Create a new scheduler
+    ///
+    /// Without `parallel-execution`, tasks always run serially.
     pub fn new(thread_count: usize) -> Self {
+        let thread_count = thread_count.max(1);
         Self {
-            _thread_count: thread_count.max(1),
+            thread_count,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            // A pool that fails to start leaves the scheduler serial
+            #[cfg(feature = "parallel-execution")]
+            pool: (thread_count > 1)
+                .then(|| rayon::ThreadPoolBuilder::new().num_threads(thread_count).build().ok())
+                .flatten()
+                .map(Arc::new),
         }
     }
 
+    /// Scheduler for `[execution]`: one thread unless `parallel`, all
+    /// available ones for `thread_count = 0`
+    pub fn from_config(config: &ExecutionConfig) -> Self {
+        let threads = match (config.parallel, config.thread_count) {
+            (false, _) => 1,
+            (true, 0) => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            (true, n) => n,
+        };
+        Self::new(threads).with_chunk_size(config.chunk_size)
+    }
+
+    /// Split scans of more than `chunk_size` nodes into chunks (0 = never)
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Get configured thread count
+    pub fn thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// Get configured chunk size
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
     /// Execute a plan
     ///
     /// **Deterministic**: Same plan + CPG = same result
+    ///
+    /// Builds CPG indices first if the plan needs them; prefer
+    /// `execute_with_indices` when an epoch's indices are at hand.
     pub fn execute(&self, plan: &ExecutionPlan, cpg: &CPG) -> Vec<QueryResult> {
+        into_nodes(self.execute_fragments(plan, cpg, None))
+    }
+
+    /// Execute a plan using prebuilt CPG indices
+    pub fn execute_with_indices(&self, plan: &ExecutionPlan, cpg: &CPG, indices: &CPGIndices) -> Vec<QueryResult> {
+        into_nodes(self.execute_fragments(plan, cpg, Some(indices)))
+    }
+
+    /// Execute a plan, keeping path tables
+    ///
+    /// One output per task, stages in order, tasks in commit order.
+    pub fn execute_fragments(&self, plan: &ExecutionPlan, cpg: &CPG, indices: Option<&CPGIndices>) -> Vec<FragmentOutput> {
+        self.execute_traced(plan, cpg, indices).0
+    }
+
+    /// Execute a plan, keeping path tables and per-task records
+    ///
+    /// Records are in output order.
+    pub fn execute_traced(
+        &self,
+        plan: &ExecutionPlan,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+    ) -> (Vec<FragmentOutput>, Vec<TaskRecord>) {
+        uninterrupted(self.execute_cancellable(plan, cpg, indices, &CancellationToken::new()))
+    }
+
+    /// `execute_traced`, stopping when `token` is cancelled or times out
+    ///
+    /// The interruption reports how many tasks completed before it.
+    pub fn execute_cancellable(
+        &self,
+        plan: &ExecutionPlan,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+        token: &CancellationToken,
+    ) -> Result<(Vec<FragmentOutput>, Vec<TaskRecord>), Interrupted> {
+        let built = match indices {
+            None if plan.needs_indices() => Some(CPGIndices::build(cpg)),
+            _ => None,
+        };
+        self.execute_stages(plan, cpg, indices.or(built.as_ref()), token)
+    }
+
+    /// Execute each stage in order
+    fn execute_stages(
+        &self,
+        plan: &ExecutionPlan,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+        token: &CancellationToken,
+    ) -> Result<(Vec<FragmentOutput>, Vec<TaskRecord>), Interrupted> {
         let mut results = Vec::new();
+        let mut records = Vec::new();
 
-        // Execute each stage in order
         for stage in &plan.stages {
-            let stage_results = self.execute_stage(stage, cpg);
-            results.extend(stage_results);
+            let outputs = self.execute_stage(stage, cpg, indices, token)
+                .map_err(|e| e.after_tasks(results.len()))?;
+            for (output, record) in outputs {
+                results.push(output);
+                records.push(record);
+            }
         }
 
-        results
+        Ok((results, records))
     }
 
     /// Execute a single stage
-    fn execute_stage(&self, stage: &crate::execution::plan::Stage, cpg: &CPG) -> Vec<QueryResult> {
+    fn execute_stage(
+        &self,
+        stage: &crate::execution::plan::Stage,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+        token: &CancellationToken,
+    ) -> Result<Vec<(FragmentOutput, TaskRecord)>, Interrupted> {
         // Result storage (one slot per task)
-        let results: Arc<Mutex<HashMap<usize, QueryResult>>> = Arc::new(Mutex::new(HashMap::new()));
-        
+        type Slots = HashMap<usize, (Result<(FragmentOutput, Vec<u64>), Interrupted>, u64)>;
+        let results: Arc<Mutex<Slots>> = Arc::new(Mutex::new(HashMap::new()));
+        let run = |task: &Task| {
+            let start = Instant::now();
+            let result = token.check(Progress::default())
+                .and_then(|()| self.execute_task(task, cpg, indices, token));
+            let wall_us = start.elapsed().as_micros() as u64;
+            results.lock().unwrap().insert(task.result_slot, (result, wall_us));
+        };
+
         #[cfg(feature = "parallel-execution")]
-        {
+        match &self.pool {
             // Parallel execution with Rayon (feature-flagged)
-            use rayon::prelude::*;
-            
-            stage.parallel_tasks
-                .par_iter()
-                .for_each(|task| {
-                    let result = self.execute_task(task, cpg);
-                    results.lock().unwrap().insert(task.result_slot, result);
-                });
+            Some(pool) => {
+                use rayon::prelude::*;
+                pool.install(|| stage.parallel_tasks.par_iter().for_each(run));
+            }
+            None => stage.parallel_tasks.iter().for_each(run),
         }
-        
+
         #[cfg(not(feature = "parallel-execution"))]
         {
             // Serial execution (default baseline)
-            for task in &stage.parallel_tasks {
-                let result = self.execute_task(task, cpg);
-                results.lock().unwrap().insert(task.result_slot, result);
-            }
+            stage.parallel_tasks.iter().for_each(run);
         }
-        
-        // Commit in deterministic order (always serial)
+
+        // Commit in deterministic order (always serial); the first
+        // interrupted task in that order fails the stage
         let tasks_ordered = stage.tasks_in_commit_order();
-        let results_lock = results.lock().unwrap();
-        
+        let mut results_lock = results.lock().unwrap();
+
         tasks_ordered
             .iter()
-            .map(|task| results_lock.get(&task.result_slot).cloned().unwrap_or_default())
+            .enumerate()
+            .map(|(done, task)| {
+                let (output, wall_us) = results_lock.remove(&task.result_slot)
+                    .unwrap_or_else(|| (Ok((FragmentOutput::default(), Vec::new())), 0));
+                let (output, chunk_us) = output.map_err(|e| e.after_tasks(done))?;
+                let record = TaskRecord { task_id: task.id, result_size: output.nodes.len(), wall_us, chunk_us };
+                Ok((output, record))
+            })
             .collect()
     }
 
-    /// Execute a single task
-    fn execute_task(&self, task: &Task, cpg: &CPG) -> QueryResult {
-        match &task.work {
+    /// Execute a single task, with the wall time of each chunk if chunked
+    fn execute_task(
+        &self,
+        task: &Task,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+        token: &CancellationToken,
+    ) -> Result<(FragmentOutput, Vec<u64>), Interrupted> {
+        let mut checkpoint = Checkpoint::new(token);
+        let nodes = match &task.work {
+            WorkFragment::FindNodes { kind } if self.is_chunked(cpg.nodes.len()) => {
+                let (nodes, chunk_us) = self.scan_chunked(&cpg.nodes, token, |chunk| {
+                    chunk.iter().filter(|n| n.kind == *kind).map(|n| n.id).collect()
+                })?;
+                return Ok((FragmentOutput::nodes(nodes), chunk_us));
+            }
+            WorkFragment::Filter { nodes, kind: Some(kind) } if self.is_chunked(nodes.len()) => {
+                let of_kind: HashSet<CPGNodeId> = cpg.nodes.iter().filter(|n| n.kind == *kind).map(|n| n.id).collect();
+                let (nodes, chunk_us) = self.scan_chunked(nodes, token, |chunk| {
+                    chunk.iter().filter(|id| of_kind.contains(id)).copied().collect()
+                })?;
+                return Ok((FragmentOutput::nodes(nodes), chunk_us));
+            }
             WorkFragment::FindNodes { kind } => {
                 QueryPrimitives::find_nodes(cpg, *kind)
             }
             WorkFragment::FollowEdges { from, kind } => {
                 let mut result = Vec::new();
                 for node in from {
-                    result.extend(QueryPrimitives::follow_edge(cpg, *node, *kind));
+                    checkpoint.step()?;
+                    match indices {
+                        Some(indices) => result.extend(QueryPrimitives::follow_edge_indexed(cpg, indices, *node, *kind)),
+                        None => result.extend(QueryPrimitives::follow_edge(cpg, *node, *kind)),
+                    }
+                }
+                result
+            }
+            WorkFragment::FollowEdgesReverse { to, kind } => {
+                let indices = indices.expect("FollowEdgesReverse requires CPG indices");
+                let mut result = Vec::new();
+                for node in to {
+                    checkpoint.step()?;
+                    result.extend(QueryPrimitives::follow_edge_reverse(indices, *node, *kind));
                 }
                 result
             }
@@ -97,14 +323,88 @@ impl Scheduler {
             WorkFragment::Intersect { a, b } => {
                 QueryPrimitives::intersect(a.clone(), b.clone())
             }
+            WorkFragment::Union { a, b } => {
+                QueryPrimitives::union(a.clone(), b.clone())
+            }
+            WorkFragment::Difference { a, b } => {
+                QueryPrimitives::difference(a.clone(), b.clone())
+            }
+            WorkFragment::Taint { sources_spec, sinks_spec, max_depth } => {
+                let analysis = TaintAnalysis::analyze_cancellable(
+                    cpg, sources_spec.clone(), sinks_spec.clone(), *max_depth, token,
+                )?;
+                return Ok((FragmentOutput::paths(analysis.paths().iter().map(|p| p.path.as_slice())), Vec::new()));
+            }
+            WorkFragment::Reachable { from, kinds, depth } => {
+                ReachabilityAnalysis::analyze_cancellable(cpg, from, kinds, *depth, token)?.into_nodes()
+            }
+            WorkFragment::MatchPath { from, pattern } => {
+                // End nodes of every start, first occurrence only
+                let mut seen = HashSet::new();
+                let mut result = Vec::new();
+                for node in from {
+                    let witnesses = QueryPrimitives::match_path_cancellable(cpg, *node, pattern, token)?;
+                    result.extend(witnesses.iter().filter_map(|path| path.last().copied()).filter(|end| seen.insert(*end)));
+                }
+                result
+            }
+        };
+        Ok((FragmentOutput::nodes(nodes), Vec::new()))
+    }
+
+    /// Whether a scan over `len` nodes is split into chunks
+    fn is_chunked(&self, len: usize) -> bool {
+        self.chunk_size > 0 && len > self.chunk_size
+    }
+
+    /// Scan `items` chunk by chunk (in parallel on the pool), concatenating
+    /// in chunk order; also returns each chunk's wall time
+    fn scan_chunked<T: Sync>(
+        &self,
+        items: &[T],
+        token: &CancellationToken,
+        scan: impl Fn(&[T]) -> QueryResult + Sync,
+    ) -> Result<(QueryResult, Vec<u64>), Interrupted> {
+        let run = |(index, chunk): (usize, &[T])| {
+            token.check(Progress { tasks_completed: 0, steps: index as u64 })?;
+            let start = Instant::now();
+            let nodes = scan(chunk);
+            Ok((nodes, start.elapsed().as_micros() as u64))
+        };
+
+        #[cfg(feature = "parallel-execution")]
+        let scanned: Vec<Result<_, Interrupted>> = match &self.pool {
+            Some(pool) => {
+                use rayon::prelude::*;
+                pool.install(|| items.par_chunks(self.chunk_size).enumerate().map(run).collect())
+            }
+            None => items.chunks(self.chunk_size).enumerate().map(run).collect(),
+        };
+
+        #[cfg(not(feature = "parallel-execution"))]
+        let scanned: Vec<Result<_, Interrupted>> = items.chunks(self.chunk_size).enumerate().map(run).collect();
+
+        let mut nodes = Vec::new();
+        let mut chunk_us = Vec::with_capacity(scanned.len());
+        for result in scanned {
+            let (chunk_nodes, wall_us) = result?;
+            nodes.extend(chunk_nodes);
+            chunk_us.push(wall_us);
         }
+        Ok((nodes, chunk_us))
     }
 }
 
+/// Drop path tables
+fn into_nodes(outputs: Vec<FragmentOutput>) -> Vec<QueryResult> {
+    outputs.into_iter().map(|output| output.nodes).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::execution::plan::Stage;
+    use crate::execution::plan::{DeterministicOrder, Stage};
+    use crate::execution::task::TaskId;
     use crate::cpg::model::*;
     use crate::types::ByteRange;
 
@@ -114,6 +414,31 @@ mod tests {
         assert_eq!(scheduler.thread_count, 4);
     }
 
+    #[test]
+    fn test_execute_traced_records_result_sizes() {
+        let mut cpg = CPG::new();
+        for i in 0..3 {
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(i),
+                if i == 0 { CPGNodeKind::Function } else { CPGNodeKind::CfgNode },
+                OriginRef::Function { function_id: crate::semantic::model::FunctionId(i) },
+                ByteRange::new(0, 0),
+            ));
+        }
+        let tasks = vec![
+            Task::new(TaskId(2), WorkFragment::FindNodes { kind: CPGNodeKind::CfgNode }, vec![], 0),
+            Task::new(TaskId(1), WorkFragment::FindNodes { kind: CPGNodeKind::Function }, vec![], 1),
+        ];
+        let mut plan = ExecutionPlan::new();
+        plan.add_stage(Stage::new(tasks, DeterministicOrder::TaskId));
+
+        let (outputs, records) = Scheduler::new(1).execute_traced(&plan, &cpg, None);
+
+        let sizes: Vec<_> = records.iter().map(|r| (r.task_id, r.result_size)).collect();
+        assert_eq!(sizes, vec![(TaskId(1), 1), (TaskId(2), 2)]);
+        assert_eq!(outputs[0].nodes, vec![CPGNodeId(0)]);
+    }
+
     #[test]
     fn test_execute_simple_plan() {
         let mut cpg = CPG::new();
@@ -145,4 +470,136 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].len(), 1);
     }
+
+    #[test]
+    fn test_set_operations_in_one_stage() {
+        let cpg = CPG::new();
+        let a = vec![CPGNodeId(1), CPGNodeId(2), CPGNodeId(3)];
+        let b = vec![CPGNodeId(3), CPGNodeId(4)];
+
+        let tasks = vec![
+            Task::new(TaskId(2), WorkFragment::Difference { a: a.clone(), b: b.clone() }, vec![], 2),
+            Task::new(TaskId(0), WorkFragment::Intersect { a: a.clone(), b: b.clone() }, vec![], 0),
+            Task::new(TaskId(1), WorkFragment::Union { a, b }, vec![], 1),
+        ];
+        let mut plan = ExecutionPlan::new();
+        plan.add_stage(Stage::new(tasks, DeterministicOrder::TaskId));
+
+        let results = Scheduler::new(4).execute(&plan, &cpg);
+
+        assert_eq!(results, vec![
+            vec![CPGNodeId(3)],
+            vec![CPGNodeId(1), CPGNodeId(2), CPGNodeId(3), CPGNodeId(4)],
+            vec![CPGNodeId(1), CPGNodeId(2)],
+        ]);
+    }
+
+    /// Two data-flow chains: 1 → 2 → 3 and 10 → 11, plus 2 → 11
+    fn taint_cpg() -> CPG {
+        let mut cpg = CPG::new();
+        for id in [1, 2, 3, 10, 11] {
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(id),
+                CPGNodeKind::DfgValue,
+                OriginRef::Dfg { value_id: crate::semantic::model::ValueId(id) },
+                ByteRange::new(0, 0),
+            ));
+        }
+        for (i, (from, to)) in [(1, 2), (2, 3), (10, 11), (2, 11)].into_iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), CPGEdgeKind::DataFlow, CPGNodeId(from), CPGNodeId(to)));
+        }
+        cpg
+    }
+
+    fn taint_plan() -> ExecutionPlan {
+        use crate::analysis::{TaintSink, TaintSource};
+
+        let taint = |source, sinks: &[u64]| WorkFragment::Taint {
+            sources_spec: vec![TaintSource::Parameter(CPGNodeId(source))],
+            sinks_spec: sinks.iter().map(|s| TaintSink::FunctionCall(CPGNodeId(*s))).collect(),
+            max_depth: 10,
+        };
+        let tasks = vec![
+            Task::new(TaskId(1), taint(10, &[11]), vec![], 1),
+            Task::new(TaskId(0), taint(1, &[3, 11]), vec![], 0),
+            Task::new(
+                TaskId(2),
+                WorkFragment::Reachable { from: vec![CPGNodeId(1)], kinds: vec![CPGEdgeKind::DataFlow], depth: 1 },
+                vec![],
+                2,
+            ),
+        ];
+        let mut plan = ExecutionPlan::new();
+        plan.add_stage(Stage::new(tasks, DeterministicOrder::TaskId));
+        plan
+    }
+
+    #[test]
+    fn test_taint_fragments_commit_by_task_id() {
+        let cpg = taint_cpg();
+        let plan = taint_plan();
+
+        let serial = Scheduler::new(1).execute_fragments(&plan, &cpg, None);
+        for _ in 0..8 {
+            assert_eq!(Scheduler::new(4).execute_fragments(&plan, &cpg, None), serial);
+        }
+
+        // Task 0: paths 1 → 2 → 3 and 1 → 2 → 11, in BFS order, flattened
+        let ids = |ids: &[u64]| ids.iter().map(|id| CPGNodeId(*id)).collect::<Vec<_>>();
+        assert_eq!(serial[0].nodes, ids(&[1, 2, 3, 1, 2, 11]));
+        assert_eq!(serial[0].paths, Some(vec![0..3, 3..6]));
+        assert_eq!(serial[1].nodes, ids(&[10, 11]));
+        assert_eq!(serial[1].paths, Some(std::iter::once(0..2).collect()));
+
+        // Reachable: a plain node set
+        assert_eq!(serial[2], FragmentOutput { nodes: ids(&[1, 2]), paths: None });
+    }
+
+    #[test]
+    fn test_stored_taint_paths() {
+        use crate::query::QueryEngine;
+
+        let cpg = taint_cpg();
+        let mut output = Scheduler::new(1).execute_fragments(&taint_plan(), &cpg, None);
+        let mut engine = QueryEngine::new();
+        let result_id = engine.store_fragment(output.remove(0));
+
+        let paths = engine.get_paths(result_id).unwrap();
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[1], &[CPGNodeId(1), CPGNodeId(2), CPGNodeId(11)]);
+        assert_eq!(engine.get_result(result_id).unwrap().total(), 6);
+
+        let plain = engine.store_fragment(output.pop().unwrap());
+        assert!(engine.get_paths(plain).is_none());
+    }
+
+    #[test]
+    fn test_chunked_parallel_matches_unchunked_serial() {
+        const NODES: u64 = 1_000_000;
+        let kinds = [CPGNodeKind::CfgNode, CPGNodeKind::DfgValue, CPGNodeKind::Function];
+        let mut cpg = CPG::new();
+        for i in 0..NODES {
+            cpg.add_node(CPGNode::new(CPGNodeId(i), kinds[(i * 7 % 3) as usize],
+                OriginRef::Cfg { node_id: crate::semantic::model::NodeId(i) }, ByteRange::new(0, 0)));
+        }
+        // Every node, in scrambled order
+        let scrambled: Vec<_> = (0..NODES).map(|i| CPGNodeId(i * 7919 % NODES)).collect();
+
+        let tasks = vec![
+            Task::new(TaskId(0), WorkFragment::FindNodes { kind: CPGNodeKind::Function }, vec![], 0),
+            Task::new(TaskId(1), WorkFragment::Filter { nodes: scrambled, kind: Some(CPGNodeKind::DfgValue) }, vec![], 1),
+            Task::new(TaskId(2), WorkFragment::Filter { nodes: vec![CPGNodeId(5)], kind: Some(CPGNodeKind::DfgValue) }, vec![], 2),
+        ];
+        let mut plan = ExecutionPlan::new();
+        plan.add_stage(Stage::new(tasks, DeterministicOrder::TaskId));
+
+        let (serial, serial_records) = Scheduler::new(1).with_chunk_size(0).execute_traced(&plan, &cpg, None);
+        let (chunked, records) = Scheduler::new(4).with_chunk_size(4096).execute_traced(&plan, &cpg, None);
+
+        assert_eq!(chunked, serial);
+        assert_eq!((serial[0].nodes.len(), serial[1].nodes.len()), (333_333, 333_333));
+        assert!(serial_records.iter().all(|record| record.chunk_us.is_empty()));
+        let chunks: Vec<_> = records.iter().map(|record| record.chunk_us.len()).collect();
+        assert_eq!(chunks, vec![245, 245, 0]);
+    }
 }
@@ -2,7 +2,9 @@
 //!
 //! Tasks are independent work units that can execute in parallel
 
+use crate::analysis::taint::{TaintSink, TaintSource};
 use crate::cpg::model::CPGNodeId;
+use crate::query::primitives::EdgeStep;
 
 
 /// Unique task identifier
@@ -23,6 +25,12 @@ pub enum WorkFragment {
         kind: crate::cpg::model::CPGEdgeKind,
     },
     
+    /// Follow edges backwards to their sources
+    FollowEdgesReverse {
+        to: Vec<CPGNodeId>,
+        kind: crate::cpg::model::CPGEdgeKind,
+    },
+    
     /// Filter nodes
     Filter {
         nodes: Vec<CPGNodeId>,
@@ -34,6 +42,72 @@ pub enum WorkFragment {
         a: Vec<CPGNodeId>,
         b: Vec<CPGNodeId>,
     },
+    
+    /// Union of two sets
+    Union {
+        a: Vec<CPGNodeId>,
+        b: Vec<CPGNodeId>,
+    },
+    
+    /// Difference of two sets (a minus b)
+    Difference {
+        a: Vec<CPGNodeId>,
+        b: Vec<CPGNodeId>,
+    },
+
+    /// Taint paths from sources to sinks (result: paths, flattened)
+    Taint {
+        sources_spec: Vec<TaintSource>,
+        sinks_spec: Vec<TaintSink>,
+        max_depth: usize,
+    },
+
+    /// Nodes within `depth` edges of `from` (empty `kinds` = any edge)
+    Reachable {
+        from: Vec<CPGNodeId>,
+        kinds: Vec<crate::cpg::model::CPGEdgeKind>,
+        depth: usize,
+    },
+
+    /// End nodes of the paths from `from` matching a path pattern
+    MatchPath {
+        from: Vec<CPGNodeId>,
+        pattern: Vec<EdgeStep>,
+    },
+}
+
+impl WorkFragment {
+    /// Operator name (snake_case, as shown by query explain)
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkFragment::FindNodes { .. } => "find_nodes",
+            WorkFragment::FollowEdges { .. } => "follow_edges",
+            WorkFragment::FollowEdgesReverse { .. } => "follow_edges_reverse",
+            WorkFragment::Filter { .. } => "filter",
+            WorkFragment::Intersect { .. } => "intersect",
+            WorkFragment::Union { .. } => "union",
+            WorkFragment::Difference { .. } => "difference",
+            WorkFragment::Taint { .. } => "taint",
+            WorkFragment::Reachable { .. } => "reachable",
+            WorkFragment::MatchPath { .. } => "match_path",
+        }
+    }
+
+    /// Number of input nodes (both sides for set operations, sources for taint)
+    pub fn input_rows(&self) -> usize {
+        match self {
+            WorkFragment::FindNodes { .. } => 0,
+            WorkFragment::FollowEdges { from: nodes, .. }
+            | WorkFragment::FollowEdgesReverse { to: nodes, .. }
+            | WorkFragment::Filter { nodes, .. }
+            | WorkFragment::Reachable { from: nodes, .. }
+            | WorkFragment::MatchPath { from: nodes, .. } => nodes.len(),
+            WorkFragment::Intersect { a, b }
+            | WorkFragment::Union { a, b }
+            | WorkFragment::Difference { a, b } => a.len() + b.len(),
+            WorkFragment::Taint { sources_spec, .. } => sources_spec.len(),
+        }
+    }
 }
 
 /// Task with dependencies
@@ -17,6 +17,12 @@ impl SyncIOBackend {
     }
 }
 
+impl Default for SyncIOBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl IOBackend for SyncIOBackend {
     fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
         fs::read(path)
@@ -16,6 +16,12 @@ impl HotPathIO {
     }
 }
 
+impl Default for HotPathIO {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl IOBackend for HotPathIO {
     fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
         // Simple synchronous read (existing behavior)
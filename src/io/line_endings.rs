@@ -0,0 +1,82 @@
+//! CRLF → LF normalization (`[parse] normalize_line_endings`)
+//!
+//! With normalization on, a file is hashed, sized and parsed as its LF view:
+//! every `\r\n` becomes `\n` (a lone `\r` is kept). Node ranges then index
+//! the view, so checkouts that differ only in line endings build the same
+//! snapshot and graphs. `OffsetTable` maps view offsets back to the file as
+//! stored, for output that points into it.
+
+use crate::types::ByteRange;
+
+/// View offset → stored offset for one normalized file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OffsetTable {
+    /// View offsets of each `\n` whose `\r` was dropped, ascending
+    removed: Vec<usize>,
+}
+
+impl OffsetTable {
+    /// Whether view and stored offsets coincide (nothing was removed)
+    pub fn is_identity(&self) -> bool {
+        self.removed.is_empty()
+    }
+
+    /// Stored offset of view offset `offset`
+    ///
+    /// An offset at a normalized `\n` maps to its `\r`, so a range ending
+    /// before a line break excludes the whole break.
+    pub fn original(&self, offset: usize) -> usize {
+        offset + self.removed.partition_point(|removed| *removed < offset)
+    }
+
+    /// Stored range of view range `range`
+    pub fn original_range(&self, range: ByteRange) -> ByteRange {
+        ByteRange::new(self.original(range.start), self.original(range.end))
+    }
+}
+
+/// LF view of `bytes` and its offset table (`bytes` itself without `\r\n`)
+pub fn normalize_line_endings(bytes: Vec<u8>) -> (Vec<u8>, OffsetTable) {
+    if !bytes.windows(2).any(|pair| pair == b"\r\n") {
+        return (bytes, OffsetTable::default());
+    }
+    let mut view = Vec::with_capacity(bytes.len());
+    let mut removed = Vec::new();
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte == b'\r' && bytes.get(i + 1) == Some(&b'\n') {
+            removed.push(view.len());
+            continue;
+        }
+        view.push(*byte);
+    }
+    (view, OffsetTable { removed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crlf_becomes_lf_and_maps_back() {
+        let stored = b"fn a() {}\r\nfn b() {}\r\n\rx".to_vec();
+        let (view, table) = normalize_line_endings(stored.clone());
+        assert_eq!(view, b"fn a() {}\nfn b() {}\n\rx");
+
+        // `fn b` starts after one dropped `\r`
+        assert_eq!(table.original(10), 11);
+        assert_eq!(&stored[table.original(10)..table.original(19)], b"fn b() {}");
+        // A range ending at a line break stops before its `\r`
+        assert_eq!(table.original_range(ByteRange::new(0, 9)), ByteRange::new(0, 9));
+        // The lone `\r` is kept
+        assert_eq!(stored[table.original(20)], b'\r');
+        assert_eq!(table.original(view.len()), stored.len());
+    }
+
+    #[test]
+    fn test_lf_only_is_identity() {
+        let (view, table) = normalize_line_endings(b"a\nb\r".to_vec());
+        assert_eq!(view, b"a\nb\r");
+        assert!(table.is_identity());
+        assert_eq!(table.original(3), 3);
+    }
+}
@@ -0,0 +1,122 @@
+//! In-memory I/O backend
+//!
+//! Serves a fixed map of file paths to contents. Directories are implied:
+//! a path is a directory if some file lies below it. Every file reports a
+//! modification time of `UNIX_EPOCH`.
+
+use super::{DirEntry, FileKind, FileStat, IOBackend};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Files served from memory
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryBackend {
+    pub fn new(files: HashMap<PathBuf, Vec<u8>>) -> Self {
+        Self { files }
+    }
+
+    /// Add or replace a file
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(path.into(), contents.into());
+    }
+
+    /// Children of `dir`, by path
+    fn children(&self, dir: &Path) -> BTreeMap<PathBuf, FileKind> {
+        let mut children = BTreeMap::new();
+        for path in self.files.keys() {
+            let Ok(rest) = path.strip_prefix(dir) else {
+                continue;
+            };
+            let mut components = rest.components();
+            let Some(first) = components.next() else {
+                continue;
+            };
+            let kind = if components.next().is_some() { FileKind::Dir } else { FileKind::File };
+            children.insert(dir.join(first), kind);
+        }
+        children
+    }
+}
+
+impl From<HashMap<PathBuf, Vec<u8>>> for MemoryBackend {
+    fn from(files: HashMap<PathBuf, Vec<u8>>) -> Self {
+        Self::new(files)
+    }
+}
+
+impl IOBackend for MemoryBackend {
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files.get(path).cloned().ok_or_else(|| not_found(path))
+    }
+
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn list_dir(&self, path: &Path) -> Result<Vec<DirEntry>> {
+        if self.files.contains_key(path) {
+            return Err(Error::other(format!("{} is not a directory", path.display())));
+        }
+        let children = self.children(path);
+        if children.is_empty() {
+            return Err(not_found(path));
+        }
+        Ok(children.into_iter().map(|(path, kind)| DirEntry { path, kind }).collect())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FileStat> {
+        let (kind, len) = match self.files.get(path) {
+            Some(contents) => (FileKind::File, contents.len() as u64),
+            None if !self.children(path).is_empty() => (FileKind::Dir, 0),
+            None => return Err(not_found(path)),
+        };
+        Ok(FileStat { kind, len, modified: SystemTime::UNIX_EPOCH })
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::new(ErrorKind::NotFound, format!("{} not found in memory backend", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backend() -> MemoryBackend {
+        let mut backend = MemoryBackend::default();
+        backend.insert("/repo/src/lib.rs", "pub mod a;");
+        backend.insert("/repo/src/a/mod.rs", "fn a() {}");
+        backend.insert("/repo/README.md", "# repo");
+        backend
+    }
+
+    #[test]
+    fn test_read_and_stat_files() {
+        let backend = backend();
+        assert_eq!(backend.read_file(Path::new("/repo/src/lib.rs")).unwrap(), b"pub mod a;");
+        assert_eq!(backend.read_file(Path::new("/repo/nope.rs")).unwrap_err().kind(), ErrorKind::NotFound);
+
+        let stat = backend.metadata(Path::new("/repo/src/a/mod.rs")).unwrap();
+        assert_eq!((stat.kind, stat.len), (FileKind::File, 9));
+        assert_eq!(backend.metadata(Path::new("/repo/src")).unwrap().kind, FileKind::Dir);
+        assert!(backend.metadata(Path::new("/elsewhere")).is_err());
+    }
+
+    #[test]
+    fn test_list_dir_implies_directories() {
+        let backend = backend();
+        let entries = backend.list_dir(Path::new("/repo")).unwrap();
+        assert_eq!(entries, [
+            DirEntry { path: PathBuf::from("/repo/README.md"), kind: FileKind::File },
+            DirEntry { path: PathBuf::from("/repo/src"), kind: FileKind::Dir },
+        ]);
+        assert!(backend.list_dir(Path::new("/repo/src/lib.rs")).is_err());
+        assert_eq!(backend.list_dir(Path::new("/missing")).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
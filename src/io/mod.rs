@@ -4,6 +4,16 @@
 //!
 //! Hot path: Incremental edits, queries (unchanged from Phase 1)
 //! Cold path: Large repo ingestion (new, optional acceleration)
+//!
+//! ## Custom backends
+//!
+//! Scanning and ingestion can run against any `IOBackend`
+//! (`RepoScanner::with_backend`, `Pipeline::with_backend`): files are listed
+//! with `list_dir`, stat'ed with `metadata` and read with `read_file`, and
+//! their bytes are held as `BufferedFile`s instead of mmaps. `MemoryBackend`
+//! serves a fixed set of files from memory, for tests and editors with
+//! unsaved buffers; `OverlayBackend` replaces some files of another backend.
+//! Without a backend, the local filesystem is walked and mmapped as before.
 
 // Existing Phase 1 I/O (unchanged)
 pub mod source_file;
@@ -11,15 +21,26 @@ pub mod source_file;
 // Path B1: New I/O abstraction
 pub mod hot;
 pub mod cold;
+pub mod memory;
+pub mod overlay;
+pub mod line_endings;
 
 // Phase 1 exports (unchanged)
-pub use source_file::{MmappedFile, SourceFile};
+pub use source_file::{BufferedFile, MmappedFile, SourceFile};
+pub use memory::MemoryBackend;
+pub use overlay::OverlayBackend;
+pub use line_endings::{normalize_line_endings, OffsetTable};
 
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::io::Result;
+use std::time::SystemTime;
 
 /// I/O mode selection
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum IOMode {
     /// Hot path - mmap + page cache (incremental edits)
     Hot,
@@ -31,13 +52,85 @@ pub enum IOMode {
     Auto,
 }
 
+/// What a path refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+
+    /// Sockets, devices, ...
+    Other,
+}
+
+/// Metadata of one path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    pub kind: FileKind,
+
+    /// Size in bytes
+    pub len: u64,
+
+    pub modified: SystemTime,
+}
+
+/// One entry of a directory listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    /// Directory path joined with the entry's name
+    pub path: PathBuf,
+
+    /// Kind of the entry itself (a symlink is not followed)
+    pub kind: FileKind,
+}
+
 /// I/O backend abstraction
+///
+/// `list_dir` and `metadata` default to the local filesystem.
 pub trait IOBackend: Send + Sync {
     /// Read file contents
     fn read_file(&self, path: &Path) -> Result<Vec<u8>>;
     
     /// Backend name (for diagnostics)
     fn name(&self) -> &'static str;
+
+    /// Entries of a directory, in any order
+    fn list_dir(&self, path: &Path) -> Result<Vec<DirEntry>> {
+        fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                Ok(DirEntry { path: entry.path(), kind: file_kind(entry.file_type()?) })
+            })
+            .collect()
+    }
+
+    /// Metadata of a path, following symlinks
+    fn metadata(&self, path: &Path) -> Result<FileStat> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileStat {
+            kind: file_kind(metadata.file_type()),
+            len: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        })
+    }
+}
+
+impl fmt::Debug for dyn IOBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("IOBackend").field(&self.name()).finish()
+    }
+}
+
+fn file_kind(file_type: fs::FileType) -> FileKind {
+    if file_type.is_file() {
+        FileKind::File
+    } else if file_type.is_dir() {
+        FileKind::Dir
+    } else if file_type.is_symlink() {
+        FileKind::Symlink
+    } else {
+        FileKind::Other
+    }
 }
 
 /// Create I/O backend for given mode
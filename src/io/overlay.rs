@@ -0,0 +1,78 @@
+//! Unsaved-buffer overlay
+//!
+//! Serves some files' contents from memory and everything else from a base
+//! backend. An overlay replaces a file the base has; it does not add one,
+//! so directory listings come from the base unchanged.
+
+use super::{DirEntry, FileStat, IOBackend};
+use std::collections::BTreeMap;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Base backend with some files replaced
+#[derive(Debug, Clone)]
+pub struct OverlayBackend {
+    base: Arc<dyn IOBackend>,
+
+    /// Replacement contents, by the path the base serves the file at
+    overlays: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl OverlayBackend {
+    pub fn new(base: Arc<dyn IOBackend>, overlays: BTreeMap<PathBuf, Vec<u8>>) -> Self {
+        Self { base, overlays }
+    }
+
+    /// Whether a path is served from an overlay
+    pub fn is_overlaid(&self, path: &Path) -> bool {
+        self.overlays.contains_key(path)
+    }
+}
+
+impl IOBackend for OverlayBackend {
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        match self.overlays.get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None => self.base.read_file(path),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "overlay"
+    }
+
+    fn list_dir(&self, path: &Path) -> Result<Vec<DirEntry>> {
+        self.base.list_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FileStat> {
+        let stat = self.base.metadata(path)?;
+        Ok(match self.overlays.get(path) {
+            Some(contents) => FileStat { len: contents.len() as u64, ..stat },
+            None => stat,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryBackend;
+
+    #[test]
+    fn test_overlay_replaces_contents_only() {
+        let mut base = MemoryBackend::default();
+        base.insert("/repo/a.rs", "fn a() {}");
+        base.insert("/repo/b.rs", "fn b() {}");
+        let overlays = BTreeMap::from([(PathBuf::from("/repo/a.rs"), b"fn a2() {}".to_vec())]);
+        let backend = OverlayBackend::new(Arc::new(base.clone()), overlays);
+
+        assert_eq!(backend.read_file(Path::new("/repo/a.rs")).unwrap(), b"fn a2() {}");
+        assert_eq!(backend.metadata(Path::new("/repo/a.rs")).unwrap().len, 10);
+        assert_eq!(backend.read_file(Path::new("/repo/b.rs")).unwrap(), b"fn b() {}");
+        assert_eq!(backend.list_dir(Path::new("/repo")).unwrap(), base.list_dir(Path::new("/repo")).unwrap());
+        assert!(backend.is_overlaid(Path::new("/repo/a.rs")));
+        assert!(!backend.is_overlaid(Path::new("/repo/b.rs")));
+    }
+}
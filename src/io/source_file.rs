@@ -1,6 +1,7 @@
 //! I/O source file abstraction (Step 1.3)
 //!
-//! Memory-mapped file reading with opaque FileId.
+//! Memory-mapped file reading with opaque FileId, or bytes already read
+//! through an `IOBackend`.
 
 use crate::types::FileId;
 use anyhow::{Context, Result};
@@ -9,7 +10,7 @@ use std::fs::File;
 use std::path::Path;
 
 /// Trait for reading source files.
-pub trait SourceFile {
+pub trait SourceFile: Send + Sync {
     /// Get the raw bytes of the file.
     fn bytes(&self) -> &[u8];
     
@@ -54,6 +55,28 @@ impl SourceFile for MmappedFile {
     }
 }
 
+/// File contents held in memory
+pub struct BufferedFile {
+    file_id: FileId,
+    bytes: Vec<u8>,
+}
+
+impl BufferedFile {
+    pub fn new(bytes: Vec<u8>, file_id: FileId) -> Self {
+        Self { file_id, bytes }
+    }
+}
+
+impl SourceFile for BufferedFile {
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn file_id(&self) -> FileId {
+        self.file_id
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,12 +43,20 @@ pub mod api;  // Phase 4
 pub mod types;
 pub mod recovery;  // Path B3
 pub mod config;  // Path B6
+pub mod verify;  // Path B7
+pub mod pipeline;  // Path B8
+pub mod cli;  // Path B9
+pub mod report;
+pub mod compare;
+pub mod util;
+#[cfg(feature = "bench-helpers")]
+pub mod testing;  // Path B4
 
 // Re-export public API
-pub use types::{FileId, ParsedFile, RepoSnapshot};
-pub use repo::RepoScanner;
+pub use types::{FileId, ParseQuality, ParsedFile, RepoSnapshot};
+pub use repo::{FileIdCollision, FileIdDerivation, FileIdStrategy, RepoScanner};
 pub use parse::IncrementalParser;
-pub use change::{ChangeDetector, FileChange};
+pub use change::{ChangeDetector, ChangeSummary, FileChange};
 pub use metrics::MetricsCollector;
 
 // Phase 2 exports
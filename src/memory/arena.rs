@@ -1,25 +1,206 @@
-//! Simple arena allocator (Step 1.2)
+//! String arena (Step 1.2)
 //!
-//! Placeholder for arena allocation within epochs.
-//! For now, we'll use standard allocation. Can be enhanced later with bumpalo.
-
-/// Placeholder arena allocator.
-///
-/// In Phase 1, we use standard allocation.
-/// Future enhancement: use bumpalo or custom bump allocator.
-pub struct Arena {
-    // Future: bump allocator state
+//! Epoch-owned storage for CFG statement text and DFG value names.
+//!
+//! Every string lives in one growing buffer and is referred to by a
+//! `StringId`. Interning the same text twice returns the same ID, so
+//! repeated statements cost their bytes once, and dropping the arena frees
+//! all epoch text in one shot.
+//!
+//! IDs are assigned in interning order, so the same interning sequence
+//! always produces the same IDs. IDs are only meaningful within the arena
+//! that issued them; hash the resolved text, never the ID.
+//!
+//! An arena serializes as its strings in ID order, so a deserialized arena
+//! resolves the same IDs to the same text.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Handle to a string in a StringArena
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct StringId(pub u32);
+
+/// Interning bump arena for strings
+#[derive(Debug, Clone, Default)]
+pub struct StringArena {
+    /// All interned text, back to back
+    text: String,
+
+    /// (start, end) of each string in `text`, indexed by StringId
+    spans: Vec<(u32, u32)>,
+
+    /// Content hash → first string with that hash
+    index: HashMap<u64, StringId>,
+
+    /// Strings whose content hash collided with an earlier, different string
+    overflow: Vec<StringId>,
+
+    /// Bytes passed to `intern`, counting duplicates
+    requested_bytes: usize,
 }
 
-impl Arena {
-    /// Create a new arena.
+impl StringArena {
+    /// Create an empty arena
     pub fn new() -> Self {
-        Self {}
+        Self::default()
+    }
+
+    /// Intern a string, returning the existing ID for known text
+    pub fn intern(&mut self, s: &str) -> StringId {
+        self.requested_bytes += s.len();
+
+        let hash = content_hash(s);
+        match self.index.get(&hash) {
+            Some(&id) if self.resolve(id) == s => id,
+            Some(_) => {
+                if let Some(&id) = self.overflow.iter().find(|&&id| self.resolve(id) == s) {
+                    return id;
+                }
+                let id = self.push(s);
+                self.overflow.push(id);
+                id
+            }
+            None => {
+                let id = self.push(s);
+                self.index.insert(hash, id);
+                id
+            }
+        }
+    }
+
+    /// Look up the text of an ID issued by this arena
+    ///
+    /// Panics if the ID came from another arena.
+    pub fn resolve(&self, id: StringId) -> &str {
+        let (start, end) = self.spans[id.0 as usize];
+        &self.text[start as usize..end as usize]
+    }
+
+    /// Number of distinct strings
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// True if nothing was interned
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Bytes of distinct text stored
+    pub fn text_bytes(&self) -> usize {
+        self.text.len()
+    }
+
+    /// Bytes passed to `intern`, including duplicates
+    ///
+    /// What the same strings would cost stored individually.
+    pub fn requested_bytes(&self) -> usize {
+        self.requested_bytes
     }
+
+    /// Approximate heap usage of the arena
+    pub fn estimated_bytes(&self) -> usize {
+        self.text.capacity()
+            + self.spans.capacity() * std::mem::size_of::<(u32, u32)>()
+            + self.index.capacity() * std::mem::size_of::<(u64, StringId)>()
+            + self.overflow.capacity() * std::mem::size_of::<StringId>()
+    }
+
+    /// Append new text
+    fn push(&mut self, s: &str) -> StringId {
+        let id = StringId(self.spans.len() as u32);
+        let start = self.text.len() as u32;
+        self.text.push_str(s);
+        self.spans.push((start, self.text.len() as u32));
+        id
+    }
+}
+
+impl Serialize for StringArena {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq((0..self.len() as u32).map(|i| self.resolve(StringId(i))))
+    }
+}
+
+impl<'de> Deserialize<'de> for StringArena {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let strings = Vec::<String>::deserialize(deserializer)?;
+        let mut arena = Self::new();
+        for (i, s) in strings.iter().enumerate() {
+            if arena.intern(s) != StringId(i as u32) {
+                return Err(serde::de::Error::custom(format!("duplicate string table entry {:?}", s)));
+            }
+        }
+        Ok(arena)
+    }
+}
+
+/// Deterministic content hash (DefaultHasher uses fixed keys)
+fn content_hash(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish()
 }
 
-impl Default for Arena {
-    fn default() -> Self {
-        Self::new()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_dedupes() {
+        let mut arena = StringArena::new();
+        let a = arena.intern("let x = 1;");
+        let b = arena.intern("<merge>");
+        let c = arena.intern("let x = 1;");
+
+        assert_eq!(a, c);
+        assert_ne!(a, b);
+        assert_eq!(arena.resolve(a), "let x = 1;");
+        assert_eq!(arena.resolve(b), "<merge>");
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.text_bytes(), 17);
+        assert_eq!(arena.requested_bytes(), 27);
+    }
+
+    #[test]
+    fn test_ids_follow_interning_order() {
+        let mut first = StringArena::new();
+        let mut second = StringArena::new();
+        for s in ["b", "a", "", "b", "c"] {
+            assert_eq!(first.intern(s), second.intern(s));
+        }
+        assert_eq!(first.intern("c"), StringId(3));
+        assert_eq!(first.resolve(StringId(2)), "");
+    }
+
+    #[test]
+    fn test_serializes_as_string_table() {
+        let mut arena = StringArena::new();
+        let ids: Vec<_> = ["Entry", "x", "Entry", "\"quoted\""].iter().map(|s| arena.intern(s)).collect();
+
+        let json = serde_json::to_string(&arena).unwrap();
+        assert_eq!(json, r#"["Entry","x","\"quoted\""]"#);
+
+        let restored: StringArena = serde_json::from_str(&json).unwrap();
+        for id in ids {
+            assert_eq!(restored.resolve(id), arena.resolve(id));
+        }
+        assert!(serde_json::from_str::<StringArena>(r#"["a","a"]"#).is_err());
+    }
+
+    #[test]
+    fn test_hash_collisions_keep_strings_apart() {
+        let mut arena = StringArena::new();
+        let a = arena.intern("a");
+        // Force "b" into the overflow list by planting a colliding entry
+        arena.index.insert(content_hash("b"), a);
+
+        let b = arena.intern("b");
+        assert_ne!(a, b);
+        assert_eq!(arena.intern("b"), b);
+        assert_eq!(arena.resolve(b), "b");
     }
 }
@@ -1,16 +1,21 @@
 //! Epoch-based memory management (Step 1.2)
 //!
 //! Each epoch owns its memory. When an epoch ends, all memory dies together.
+//!
+//! A child epoch holds an `Arc` to its parent, so the parent's memory lives
+//! at least as long as the child. Constructing a child checks that its
+//! marker is later than the parent's.
 
-use crate::io::{MmappedFile, SourceFile};
+use crate::io::SourceFile;
 use crate::types::{EpochMarker, FileId};
+use anyhow::{bail, Result};
 use std::collections::HashMap;
 use std::sync::Arc;
 
 /// Ingestion epoch - owns file discovery and I/O.
 pub struct IngestionEpoch {
     marker: EpochMarker,
-    mmaps: HashMap<FileId, Arc<MmappedFile>>,
+    mmaps: HashMap<FileId, Arc<dyn SourceFile>>,
 }
 
 impl IngestionEpoch {
@@ -22,15 +27,15 @@ impl IngestionEpoch {
         }
     }
 
-    /// Add a memory-mapped file to this epoch.
-    pub fn add_file(&mut self, file: MmappedFile) -> FileId {
+    /// Add a memory-mapped (or buffered) file to this epoch.
+    pub fn add_file(&mut self, file: impl SourceFile + 'static) -> FileId {
         let file_id = file.file_id();
         self.mmaps.insert(file_id, Arc::new(file));
         file_id
     }
 
     /// Get a file from this epoch.
-    pub fn get_file(&self, file_id: FileId) -> Option<Arc<MmappedFile>> {
+    pub fn get_file(&self, file_id: FileId) -> Option<Arc<dyn SourceFile>> {
         self.mmaps.get(&file_id).cloned()
     }
 
@@ -49,11 +54,20 @@ pub struct ParseEpoch {
 
 impl ParseEpoch {
     /// Create a new parse epoch.
-    pub fn new(marker: EpochMarker, ingestion: Arc<IngestionEpoch>) -> Self {
-        Self {
+    ///
+    /// Fails if `marker` is not later than the ingestion epoch's.
+    pub fn new(marker: EpochMarker, ingestion: Arc<IngestionEpoch>) -> Result<Self> {
+        if marker <= ingestion.marker() {
+            bail!(
+                "Parse epoch {} cannot be a child of ingestion epoch {}",
+                marker.id(), ingestion.marker().id()
+            );
+        }
+
+        Ok(Self {
             marker,
             ingestion,
-        }
+        })
     }
 
     /// Get the epoch marker.
@@ -70,6 +84,7 @@ impl ParseEpoch {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::io::MmappedFile;
     use tempfile::NamedTempFile;
     use std::fs;
 
@@ -86,4 +101,13 @@ mod tests {
         
         assert!(ingestion.get_file(file_id).is_some());
     }
+
+    #[test]
+    fn test_parse_epoch_rejects_non_child_marker() {
+        let ingestion = Arc::new(IngestionEpoch::new(EpochMarker::new(2)));
+
+        assert!(ParseEpoch::new(EpochMarker::new(2), ingestion.clone()).is_err());
+        assert!(ParseEpoch::new(EpochMarker::new(1), ingestion.clone()).is_err());
+        assert!(ParseEpoch::new(EpochMarker::new(3), ingestion).is_ok());
+    }
 }
@@ -0,0 +1,203 @@
+//! Epoch manager (Step 1.2)
+//!
+//! Hands out sequential EpochMarkers and owns the current
+//! ingestion → parse → semantic → CPG chain.
+//!
+//! Only children of the current chain can be created. Starting a new
+//! ingestion or semantic epoch makes the previous descendants stale; asking
+//! for a child of a stale epoch is an error.
+
+use super::epoch::{IngestionEpoch, ParseEpoch};
+use crate::cpg::CPGEpoch;
+use crate::semantic::SemanticEpoch;
+use crate::types::EpochMarker;
+use anyhow::{bail, Result};
+use std::sync::Arc;
+
+/// Owner of the current epoch chain
+pub struct EpochManager {
+    /// Last marker handed out
+    last: EpochMarker,
+
+    /// Current ingestion epoch (marker only until it is sealed)
+    ingestion: Option<EpochMarker>,
+
+    /// Current parse epoch (owns the sealed ingestion epoch)
+    parse: Option<Arc<ParseEpoch>>,
+
+    /// Current semantic epoch
+    semantic: Option<EpochMarker>,
+
+    /// Current CPG epoch
+    cpg: Option<EpochMarker>,
+}
+
+impl EpochManager {
+    /// Create a manager whose first epoch is 1
+    pub fn new() -> Self {
+        Self {
+            last: EpochMarker::new(0),
+            ingestion: None,
+            parse: None,
+            semantic: None,
+            cpg: None,
+        }
+    }
+
+    /// Hand out the next marker
+    fn next_marker(&mut self) -> EpochMarker {
+        self.last = self.last.next();
+        self.last
+    }
+
+    /// Start a new chain with an empty ingestion epoch
+    ///
+    /// Every epoch of the previous chain becomes stale.
+    pub fn ingestion_epoch(&mut self) -> IngestionEpoch {
+        let marker = self.next_marker();
+        self.ingestion = Some(marker);
+        self.parse = None;
+        self.semantic = None;
+        self.cpg = None;
+        IngestionEpoch::new(marker)
+    }
+
+    /// Seal the current ingestion epoch and create its parse epoch
+    pub fn parse_epoch(&mut self, ingestion: IngestionEpoch) -> Result<Arc<ParseEpoch>> {
+        if self.ingestion != Some(ingestion.marker()) {
+            bail!("Stale ingestion epoch {}", ingestion.marker().id());
+        }
+
+        let marker = self.next_marker();
+        let parse = Arc::new(ParseEpoch::new(marker, Arc::new(ingestion))?);
+        self.parse = Some(parse.clone());
+        self.semantic = None;
+        self.cpg = None;
+        Ok(parse)
+    }
+
+    /// Create a semantic epoch for the current parse epoch
+    pub fn semantic_epoch(&mut self, parse: &Arc<ParseEpoch>) -> Result<SemanticEpoch> {
+        match &self.parse {
+            Some(current) if Arc::ptr_eq(current, parse) => {}
+            _ => bail!("Stale parse epoch {}", parse.marker().id()),
+        }
+
+        let marker = self.next_marker();
+        let semantic = SemanticEpoch::new(parse.clone(), marker.id())?;
+        self.semantic = Some(marker);
+        self.cpg = None;
+        Ok(semantic)
+    }
+
+    /// Create a CPG epoch for the current semantic epoch
+    pub fn cpg_epoch(&mut self, semantic: &SemanticEpoch) -> Result<CPGEpoch> {
+        if self.semantic.map(|m| m.id()) != Some(semantic.epoch_id()) {
+            bail!("Stale semantic epoch {}", semantic.epoch_id());
+        }
+
+        let marker = self.next_marker();
+        self.cpg = Some(marker);
+        Ok(CPGEpoch::new(semantic.epoch_id(), marker.id()))
+    }
+
+    /// Current parse epoch
+    pub fn current_parse(&self) -> Option<&Arc<ParseEpoch>> {
+        self.parse.as_ref()
+    }
+
+    /// Current CPG epoch marker
+    pub fn current_cpg(&self) -> Option<EpochMarker> {
+        self.cpg
+    }
+}
+
+impl Default for EpochManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MmappedFile;
+    use crate::types::FileId;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_markers_are_sequential() {
+        let mut manager = EpochManager::new();
+        let ingestion = manager.ingestion_epoch();
+        let parse = manager.parse_epoch(ingestion).unwrap();
+        let semantic = manager.semantic_epoch(&parse).unwrap();
+        let cpg = manager.cpg_epoch(&semantic).unwrap();
+
+        assert_eq!(parse.ingestion().marker(), EpochMarker::new(1));
+        assert_eq!(parse.marker(), EpochMarker::new(2));
+        assert_eq!(semantic.epoch_id(), 3);
+        assert_eq!((cpg.semantic_epoch_id(), cpg.epoch_id()), (3, 4));
+        assert_eq!(manager.current_cpg(), Some(EpochMarker::new(4)));
+    }
+
+    #[test]
+    fn test_dropping_manager_drops_chain() {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), b"fn main() {}").unwrap();
+
+        let mut manager = EpochManager::new();
+        let mut ingestion = manager.ingestion_epoch();
+        ingestion.add_file(MmappedFile::open(temp_file.path(), FileId::new(1)).unwrap());
+        let parse = manager.parse_epoch(ingestion).unwrap();
+        let semantic = manager.semantic_epoch(&parse).unwrap();
+        let file = parse.ingestion().get_file(FileId::new(1)).unwrap();
+
+        let weak_parse = Arc::downgrade(&parse);
+        let weak_file = Arc::downgrade(&file);
+        drop((parse, file));
+
+        // The semantic epoch keeps its parents alive
+        assert!(weak_parse.upgrade().is_some());
+        drop(semantic);
+        assert!(weak_parse.upgrade().is_some());
+
+        drop(manager);
+        assert!(weak_parse.upgrade().is_none());
+        assert!(weak_file.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_cpg_epoch_for_stale_semantic_epoch_fails() {
+        let mut manager = EpochManager::new();
+        let ingestion = manager.ingestion_epoch();
+        let parse = manager.parse_epoch(ingestion).unwrap();
+        let stale = manager.semantic_epoch(&parse).unwrap();
+        let current = manager.semantic_epoch(&parse).unwrap();
+
+        let err = manager.cpg_epoch(&stale).err().unwrap();
+        assert!(err.to_string().contains("Stale semantic epoch 3"), "{}", err);
+        assert!(manager.cpg_epoch(&current).is_ok());
+
+        // A detached epoch with a recycled ID is still stale
+        let forged = SemanticEpoch::builder(3).build();
+        assert!(manager.cpg_epoch(&forged).is_err());
+    }
+
+    #[test]
+    fn test_new_chain_invalidates_old_parents() {
+        let mut manager = EpochManager::new();
+        let old_ingestion = manager.ingestion_epoch();
+        let _new_ingestion = manager.ingestion_epoch();
+        assert!(manager.parse_epoch(old_ingestion).is_err());
+
+        let ingestion = manager.ingestion_epoch();
+        let old_parse = manager.parse_epoch(ingestion).unwrap();
+        let semantic = manager.semantic_epoch(&old_parse).unwrap();
+
+        let ingestion = manager.ingestion_epoch();
+        let _parse = manager.parse_epoch(ingestion).unwrap();
+        assert!(manager.semantic_epoch(&old_parse).is_err());
+        assert!(manager.cpg_epoch(&semantic).is_err());
+        assert!(manager.current_parse().is_some());
+    }
+}
@@ -2,5 +2,8 @@
 
 pub mod epoch;
 pub mod arena;
+pub mod manager;
 
+pub use arena::{StringArena, StringId};
 pub use epoch::{IngestionEpoch, ParseEpoch};
+pub use manager::EpochManager;
@@ -1,16 +1,24 @@
 //! Metrics collection (Step 1.7)
 //!
 //! Simple in-memory metrics for parse times, scan duration, memory usage.
+//!
+//! Reported as JSON (`to_json`, every counter) or in the Prometheus text
+//! exposition format (`to_prometheus`, a fixed set of families).
 
+use crate::cpg::epoch::{CPGEpochStats, FrozenCPGEpoch};
+use crate::execution::TaskRecord;
+use crate::semantic::{SemanticEpoch, SemanticEpochReport};
 use crate::types::{EpochMarker, FileId};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 
 /// Metrics collector.
 pub struct MetricsCollector {
     /// Parse times per file (in microseconds)
-    parse_times: HashMap<FileId, u64>,
+    parse_times: Mutex<HashMap<FileId, u64>>,
     
     /// Total scan duration
     scan_duration: Option<Duration>,
@@ -20,22 +28,114 @@ pub struct MetricsCollector {
     
     /// Count of reparsed files
     reparse_count: AtomicUsize,
+    
+    /// Count of files carried over without reparsing
+    reuse_count: AtomicUsize,
+
+    /// Reparsed files whose semantic fingerprint did not change
+    semantic_noops: AtomicUsize,
+    
+    /// Query cache hits
+    query_cache_hits: AtomicUsize,
+    
+    /// Query cache misses
+    query_cache_misses: AtomicUsize,
+    
+    /// Incremental rebuilds that matched a from-scratch build
+    audit_passes: AtomicUsize,
+    
+    /// Incremental rebuilds that diverged from a from-scratch build
+    audit_failures: AtomicUsize,
+
+    /// Parsed files with syntax errors
+    parse_error_files: AtomicUsize,
+
+    /// ERROR and MISSING nodes across those files
+    parse_error_nodes: AtomicUsize,
+
+    /// Files with syntax errors whose semantics were not built
+    parse_error_skips: AtomicUsize,
+
+    /// Files whose semantics a `[limits]` budget cut short
+    degraded_files: AtomicUsize,
+
+    /// Ingests kept despite exceeding `[limits] max_total_cpg_nodes`
+    cpg_budget_overruns: AtomicUsize,
+
+    /// DFG values copied from the previous DFG by partial rebuilds
+    dfg_values_reused: AtomicUsize,
+
+    /// DFG values built again by partial rebuilds
+    dfg_values_rebuilt: AtomicUsize,
+
+    /// CFGs built again by incremental runs
+    cfg_rebuilds: AtomicUsize,
+
+    /// DFGs built again by incremental runs
+    dfg_rebuilds: AtomicUsize,
+
+    /// Reparsed files whose semantic fingerprint changed
+    files_semantically_rebuilt: AtomicUsize,
+
+    /// CPG nodes carried over instead of re-fused
+    cpg_nodes_reused: AtomicUsize,
+
+    /// Query tasks executed
+    tasks_executed: AtomicUsize,
+
+    /// Wall time of those tasks (microseconds)
+    task_time_us: AtomicU64,
+
+    /// Chunks of chunked scans executed
+    chunks_executed: AtomicUsize,
+
+    /// Wall time of those chunks (microseconds)
+    chunk_time_us: AtomicU64,
+
+    /// Composition of the last ingested CPG
+    cpg_stats: Option<CPGEpochStats>,
+
+    /// Per-file breakdown of the last ingested semantic epoch
+    semantic_report: Option<SemanticEpochReport>,
 }
 
 impl MetricsCollector {
     /// Create a new metrics collector.
     pub fn new() -> Self {
         Self {
-            parse_times: HashMap::new(),
+            parse_times: Mutex::new(HashMap::new()),
             scan_duration: None,
             epoch_memory: HashMap::new(),
             reparse_count: AtomicUsize::new(0),
+            reuse_count: AtomicUsize::new(0),
+            semantic_noops: AtomicUsize::new(0),
+            query_cache_hits: AtomicUsize::new(0),
+            query_cache_misses: AtomicUsize::new(0),
+            audit_passes: AtomicUsize::new(0),
+            audit_failures: AtomicUsize::new(0),
+            parse_error_files: AtomicUsize::new(0),
+            parse_error_nodes: AtomicUsize::new(0),
+            parse_error_skips: AtomicUsize::new(0),
+            degraded_files: AtomicUsize::new(0),
+            cpg_budget_overruns: AtomicUsize::new(0),
+            dfg_values_reused: AtomicUsize::new(0),
+            dfg_values_rebuilt: AtomicUsize::new(0),
+            cfg_rebuilds: AtomicUsize::new(0),
+            dfg_rebuilds: AtomicUsize::new(0),
+            files_semantically_rebuilt: AtomicUsize::new(0),
+            cpg_nodes_reused: AtomicUsize::new(0),
+            tasks_executed: AtomicUsize::new(0),
+            task_time_us: AtomicU64::new(0),
+            chunks_executed: AtomicUsize::new(0),
+            chunk_time_us: AtomicU64::new(0),
+            cpg_stats: None,
+            semantic_report: None,
         }
     }
 
     /// Record a parse time.
-    pub fn record_parse_time(&mut self, file_id: FileId, duration_us: u64) {
-        self.parse_times.insert(file_id, duration_us);
+    pub fn record_parse_time(&self, file_id: FileId, duration_us: u64) {
+        self.parse_times.lock().unwrap().insert(file_id, duration_us);
     }
 
     /// Record scan duration.
@@ -48,14 +148,107 @@ impl MetricsCollector {
         self.epoch_memory.insert(epoch, bytes);
     }
 
+    /// Record the statistics of an ingested CPG epoch.
+    pub fn record_cpg_stats(&mut self, stats: CPGEpochStats) {
+        self.cpg_stats = Some(stats);
+    }
+
+    /// Record the statistics and estimated memory (graph and indices) of
+    /// an ingested CPG epoch.
+    pub fn record_cpg_epoch(&mut self, epoch: &FrozenCPGEpoch) {
+        let stats = epoch.stats();
+        self.record_epoch_memory(EpochMarker::new(epoch.epoch_id()), stats.cpg_bytes + stats.indices.estimated_bytes);
+        self.record_cpg_stats(stats.clone());
+    }
+
+    /// Record the per-file breakdown of an ingested semantic epoch.
+    pub fn record_semantic_epoch(&mut self, epoch: &SemanticEpoch) {
+        self.semantic_report = Some(epoch.report());
+    }
+
     /// Increment reparse counter.
     pub fn increment_reparse(&self) {
         self.reparse_count.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Increment the counter of files reused without reparsing.
+    pub fn increment_reuse(&self) {
+        self.reuse_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a reparsed file whose semantics matched the previous run.
+    pub fn record_semantic_noop(&self) {
+        self.semantic_noops.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a query cache hit.
+    pub fn record_query_cache_hit(&self) {
+        self.query_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a query cache miss.
+    pub fn record_query_cache_miss(&self) {
+        self.query_cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an incremental audit that matched.
+    pub fn record_audit_pass(&self) {
+        self.audit_passes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record an incremental audit that diverged.
+    pub fn record_audit_failure(&self) {
+        self.audit_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a parsed file with `errors` ERROR/MISSING nodes.
+    pub fn record_parse_errors(&self, errors: usize, skipped: bool) {
+        self.parse_error_files.fetch_add(1, Ordering::Relaxed);
+        self.parse_error_nodes.fetch_add(errors, Ordering::Relaxed);
+        if skipped {
+            self.parse_error_skips.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record a file degraded by a graph budget.
+    pub fn record_degraded_file(&self) {
+        self.degraded_files.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a CPG kept over the total node budget.
+    pub fn record_cpg_budget_overrun(&self) {
+        self.cpg_budget_overruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a partial DFG rebuild.
+    pub fn record_dfg_rebuild(&self, reused: usize, rebuilt: usize) {
+        self.dfg_values_reused.fetch_add(reused, Ordering::Relaxed);
+        self.dfg_values_rebuilt.fetch_add(rebuilt, Ordering::Relaxed);
+    }
+
+    /// Record a file re-analyzed by an incremental run into `cfgs` CFGs and `dfgs` DFGs.
+    pub fn record_semantic_rebuild(&self, cfgs: usize, dfgs: usize) {
+        self.files_semantically_rebuilt.fetch_add(1, Ordering::Relaxed);
+        self.cfg_rebuilds.fetch_add(cfgs, Ordering::Relaxed);
+        self.dfg_rebuilds.fetch_add(dfgs, Ordering::Relaxed);
+    }
+
+    /// Record CPG nodes carried over from the previous run.
+    pub fn record_cpg_nodes_reused(&self, nodes: usize) {
+        self.cpg_nodes_reused.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    /// Record an executed query task and its chunks.
+    pub fn record_task(&self, record: &TaskRecord) {
+        self.tasks_executed.fetch_add(1, Ordering::Relaxed);
+        self.task_time_us.fetch_add(record.wall_us, Ordering::Relaxed);
+        self.chunks_executed.fetch_add(record.chunk_us.len(), Ordering::Relaxed);
+        self.chunk_time_us.fetch_add(record.chunk_us.iter().sum(), Ordering::Relaxed);
+    }
+
     /// Get parse time statistics.
     pub fn parse_time_stats(&self) -> ParseTimeStats {
-        let mut times: Vec<u64> = self.parse_times.values().copied().collect();
+        let mut times: Vec<u64> = self.parse_times.lock().unwrap().values().copied().collect();
         
         if times.is_empty() {
             return ParseTimeStats::default();
@@ -81,6 +274,11 @@ impl MetricsCollector {
         }
     }
 
+    /// Recorded parse time of one file (microseconds).
+    pub fn parse_time(&self, file_id: FileId) -> Option<u64> {
+        self.parse_times.lock().unwrap().get(&file_id).copied()
+    }
+
     /// Get scan duration.
     pub fn scan_duration(&self) -> Option<Duration> {
         self.scan_duration
@@ -91,6 +289,116 @@ impl MetricsCollector {
         self.reparse_count.load(Ordering::Relaxed)
     }
 
+    /// Get reuse count.
+    pub fn reuse_count(&self) -> usize {
+        self.reuse_count.load(Ordering::Relaxed)
+    }
+
+    /// Get count of reparses that were semantic no-ops.
+    pub fn semantic_noops(&self) -> usize {
+        self.semantic_noops.load(Ordering::Relaxed)
+    }
+
+    /// Get query cache hit count.
+    pub fn query_cache_hits(&self) -> usize {
+        self.query_cache_hits.load(Ordering::Relaxed)
+    }
+
+    /// Get query cache miss count.
+    pub fn query_cache_misses(&self) -> usize {
+        self.query_cache_misses.load(Ordering::Relaxed)
+    }
+
+    /// Get incremental audit pass count.
+    pub fn audit_passes(&self) -> usize {
+        self.audit_passes.load(Ordering::Relaxed)
+    }
+
+    /// Get incremental audit failure count.
+    pub fn audit_failures(&self) -> usize {
+        self.audit_failures.load(Ordering::Relaxed)
+    }
+
+    /// Get count of parsed files with syntax errors.
+    pub fn parse_error_files(&self) -> usize {
+        self.parse_error_files.load(Ordering::Relaxed)
+    }
+
+    /// Get count of files skipped for syntax errors.
+    pub fn parse_error_skips(&self) -> usize {
+        self.parse_error_skips.load(Ordering::Relaxed)
+    }
+
+    /// Get count of files degraded by graph budgets.
+    pub fn degraded_files(&self) -> usize {
+        self.degraded_files.load(Ordering::Relaxed)
+    }
+
+    /// Get count of CPGs kept over the total node budget.
+    pub fn cpg_budget_overruns(&self) -> usize {
+        self.cpg_budget_overruns.load(Ordering::Relaxed)
+    }
+
+    /// Get count of DFG values copied by partial rebuilds.
+    pub fn dfg_values_reused(&self) -> usize {
+        self.dfg_values_reused.load(Ordering::Relaxed)
+    }
+
+    /// Get count of DFG values built again by partial rebuilds.
+    pub fn dfg_values_rebuilt(&self) -> usize {
+        self.dfg_values_rebuilt.load(Ordering::Relaxed)
+    }
+
+    /// Get count of CFGs built again by incremental runs.
+    pub fn cfg_rebuilds(&self) -> usize {
+        self.cfg_rebuilds.load(Ordering::Relaxed)
+    }
+
+    /// Get count of DFGs built again by incremental runs.
+    pub fn dfg_rebuilds(&self) -> usize {
+        self.dfg_rebuilds.load(Ordering::Relaxed)
+    }
+
+    /// Get count of reparsed files whose semantics changed.
+    pub fn files_semantically_rebuilt(&self) -> usize {
+        self.files_semantically_rebuilt.load(Ordering::Relaxed)
+    }
+
+    /// Get count of CPG nodes carried over instead of re-fused.
+    pub fn cpg_nodes_reused(&self) -> usize {
+        self.cpg_nodes_reused.load(Ordering::Relaxed)
+    }
+
+    /// Get count of executed query tasks.
+    pub fn tasks_executed(&self) -> usize {
+        self.tasks_executed.load(Ordering::Relaxed)
+    }
+
+    /// Get total wall time of executed query tasks (microseconds).
+    pub fn task_time_us(&self) -> u64 {
+        self.task_time_us.load(Ordering::Relaxed)
+    }
+
+    /// Get count of executed scan chunks.
+    pub fn chunks_executed(&self) -> usize {
+        self.chunks_executed.load(Ordering::Relaxed)
+    }
+
+    /// Get total wall time of executed scan chunks (microseconds).
+    pub fn chunk_time_us(&self) -> u64 {
+        self.chunk_time_us.load(Ordering::Relaxed)
+    }
+
+    /// Statistics of the last ingested CPG epoch.
+    pub fn cpg_stats(&self) -> Option<&CPGEpochStats> {
+        self.cpg_stats.as_ref()
+    }
+
+    /// Per-file breakdown of the last ingested semantic epoch
+    pub fn semantic_report(&self) -> Option<&SemanticEpochReport> {
+        self.semantic_report.as_ref()
+    }
+
     /// Get total epoch memory.
     pub fn total_epoch_memory(&self) -> usize {
         self.epoch_memory.values().sum()
@@ -120,10 +428,173 @@ impl MetricsCollector {
             println!("\nReparses: {}", reparse_count);
         }
 
+        let reuse_count = self.reuse_count();
+        if reuse_count > 0 {
+            println!("Reused without reparsing: {}", reuse_count);
+        }
+
+        let semantic_noops = self.semantic_noops();
+        if semantic_noops > 0 {
+            println!("Reparses without semantic change: {}", semantic_noops);
+        }
+
+        let (hits, misses) = (self.query_cache_hits(), self.query_cache_misses());
+        if hits + misses > 0 {
+            println!("\nQuery cache: {} hits, {} misses", hits, misses);
+        }
+
+        let (passes, failures) = (self.audit_passes(), self.audit_failures());
+        if passes + failures > 0 {
+            println!("\nIncremental audits: {} passed, {} failed", passes, failures);
+        }
+
+        let broken = self.parse_error_files();
+        if broken > 0 {
+            println!("\nFiles with syntax errors: {} ({} skipped)", broken, self.parse_error_skips());
+        }
+
+        let (degraded, overruns) = (self.degraded_files(), self.cpg_budget_overruns());
+        if degraded + overruns > 0 {
+            println!("\nGraph budgets: {} file(s) degraded, {} CPG overrun(s)", degraded, overruns);
+        }
+
+        let (reused, rebuilt) = (self.dfg_values_reused(), self.dfg_values_rebuilt());
+        if reused + rebuilt > 0 {
+            println!("\nPartial DFG rebuilds: {} values reused, {} rebuilt", reused, rebuilt);
+        }
+
+        let files = self.files_semantically_rebuilt();
+        if files > 0 {
+            println!("\nSemantic rebuilds: {} file(s), {} CFG(s), {} DFG(s)", files, self.cfg_rebuilds(), self.dfg_rebuilds());
+        }
+        let cpg_reused = self.cpg_nodes_reused();
+        if cpg_reused > 0 {
+            println!("CPG nodes reused: {}", cpg_reused);
+        }
+
+        let tasks = self.tasks_executed();
+        if tasks > 0 {
+            println!("\nQuery tasks: {} ({:.2}ms)", tasks, self.task_time_us() as f64 / 1000.0);
+            println!("  Scan chunks: {} ({:.2}ms)", self.chunks_executed(), self.chunk_time_us() as f64 / 1000.0);
+        }
+
         let total_memory = self.total_epoch_memory();
         if total_memory > 0 {
             println!("\nTotal epoch memory: {} bytes", total_memory);
         }
+
+        if let Some(cpg) = &self.cpg_stats {
+            println!("\nCPG: {} nodes, {} edges", cpg.total_nodes, cpg.total_edges);
+            println!("  Estimated bytes: {} graph, {} indices", cpg.cpg_bytes, cpg.indices.estimated_bytes);
+        }
+    }
+
+    /// Metrics as JSON (every counter always present).
+    pub fn to_json(&self) -> serde_json::Value {
+        let stats = self.parse_time_stats();
+        serde_json::json!({
+            "scan_duration_us": self.scan_duration.map(|d| d.as_micros() as u64),
+            "parse": {
+                "count": stats.count,
+                "total_us": stats.total_us,
+                "mean_us": stats.mean_us,
+                "p50_us": stats.p50_us,
+                "p95_us": stats.p95_us,
+                "p99_us": stats.p99_us,
+            },
+            "reparses": self.reparse_count(),
+            "reused": self.reuse_count(),
+            "semantic_noops": self.semantic_noops(),
+            "query_cache": {
+                "hits": self.query_cache_hits(),
+                "misses": self.query_cache_misses(),
+            },
+            "audit": {
+                "passes": self.audit_passes(),
+                "failures": self.audit_failures(),
+            },
+            "parse_errors": {
+                "files": self.parse_error_files(),
+                "error_nodes": self.parse_error_nodes.load(Ordering::Relaxed),
+                "skipped_files": self.parse_error_skips(),
+            },
+            "limits": {
+                "degraded_files": self.degraded_files(),
+                "cpg_overruns": self.cpg_budget_overruns(),
+            },
+            "dfg_rebuild": {
+                "values_reused": self.dfg_values_reused(),
+                "values_rebuilt": self.dfg_values_rebuilt(),
+            },
+            "incremental": {
+                "cfg_rebuilds": self.cfg_rebuilds(),
+                "dfg_rebuilds": self.dfg_rebuilds(),
+                "files_semantically_rebuilt": self.files_semantically_rebuilt(),
+                "cpg_nodes_reused": self.cpg_nodes_reused(),
+            },
+            "execution": {
+                "tasks": self.tasks_executed(),
+                "task_us": self.task_time_us(),
+                "chunks": self.chunks_executed(),
+                "chunk_us": self.chunk_time_us(),
+            },
+            "epoch_memory_bytes": self.total_epoch_memory(),
+            "cpg": self.cpg_stats,
+            "semantic": self.semantic_report,
+        })
+    }
+
+    /// Metrics in the Prometheus text exposition format
+    ///
+    /// Families without a value (no scan or CPG recorded) are omitted.
+    /// Samples of a labelled family are sorted by label value, so equal
+    /// metrics always render the same text.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        if let Some(duration) = self.scan_duration {
+            family(&mut out, "vcr_scan_duration_seconds", "gauge", "Duration of the last scan");
+            sample(&mut out, "vcr_scan_duration_seconds", None, duration.as_secs_f64());
+        }
+
+        family(&mut out, "vcr_parse_time_p95_microseconds", "gauge", "95th percentile parse time per file");
+        sample(&mut out, "vcr_parse_time_p95_microseconds", None, self.parse_time_stats().p95_us);
+
+        family(&mut out, "vcr_reparse_total", "counter", "Files reparsed");
+        sample(&mut out, "vcr_reparse_total", None, self.reparse_count());
+
+        family(&mut out, "vcr_epoch_memory_bytes", "gauge", "Memory recorded across epochs");
+        sample(&mut out, "vcr_epoch_memory_bytes", None, self.total_epoch_memory());
+
+        if let Some(cpg) = &self.cpg_stats {
+            let mut by_kind: Vec<(String, usize)> = cpg.nodes_by_kind.iter()
+                .map(|(kind, count)| (format!("{:?}", kind), *count))
+                .collect();
+            by_kind.sort();
+            family(&mut out, "vcr_cpg_nodes_total", "gauge", "Nodes of the last CPG by kind");
+            for (kind, count) in by_kind {
+                sample(&mut out, "vcr_cpg_nodes_total", Some(("kind", &kind)), count);
+            }
+        }
+        out
+    }
+}
+
+/// `# HELP` and `# TYPE` lines of a metric family
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// One sample line, with at most one label
+fn sample(out: &mut String, name: &str, label: Option<(&str, &str)>, value: impl std::fmt::Display) {
+    match label {
+        Some((key, label_value)) => {
+            let escaped = label_value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, key, escaped, value);
+        }
+        None => {
+            let _ = writeln!(out, "{} {}", name, value);
+        }
     }
 }
 
@@ -158,10 +629,11 @@ pub struct ParseTimeStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
 
     #[test]
     fn test_metrics_collection() {
-        let mut collector = MetricsCollector::new();
+        let collector = MetricsCollector::new();
         
         collector.record_parse_time(FileId::new(1), 100);
         collector.record_parse_time(FileId::new(2), 200);
@@ -171,6 +643,7 @@ mod tests {
         assert_eq!(stats.count, 3);
         assert_eq!(stats.total_us, 600);
         assert_eq!(stats.mean_us, 200);
+        assert_eq!(collector.parse_time(FileId::new(2)), Some(200));
     }
 
     #[test]
@@ -182,4 +655,104 @@ mod tests {
         
         assert_eq!(collector.reparse_count(), 2);
     }
+
+    #[test]
+    fn test_query_cache_counters() {
+        let collector = MetricsCollector::new();
+        
+        collector.record_query_cache_miss();
+        collector.record_query_cache_hit();
+        collector.record_query_cache_hit();
+        
+        assert_eq!(collector.query_cache_hits(), 2);
+        assert_eq!(collector.query_cache_misses(), 1);
+    }
+
+    #[test]
+    fn test_audit_counters_in_json() {
+        let collector = MetricsCollector::new();
+        
+        collector.record_audit_pass();
+        collector.record_audit_pass();
+        collector.record_audit_failure();
+        
+        let json = collector.to_json();
+        assert_eq!(json["audit"]["passes"], 2);
+        assert_eq!(json["audit"]["failures"], 1);
+        assert_eq!(json["query_cache"]["hits"], 0);
+        assert_eq!(json["incremental"]["cfg_rebuilds"], 0);
+        assert!(json["scan_duration_us"].is_null());
+        assert!(json["cpg"].is_null());
+        assert!(json["semantic"].is_null());
+    }
+
+    /// Samples of exposition text by series (`name` or `name{labels}`),
+    /// checking every line is a comment of a known kind or `series value`
+    fn samples(text: &str) -> BTreeMap<String, f64> {
+        let mut samples = BTreeMap::new();
+        for line in text.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let mut words = comment.splitn(3, ' ');
+                assert!(matches!(words.next(), Some("HELP" | "TYPE")), "{}", line);
+                assert!(words.next().is_some_and(|name| name.starts_with("vcr_")), "{}", line);
+                continue;
+            }
+            let (series, value) = line.rsplit_once(' ').unwrap();
+            let name = series.split('{').next().unwrap();
+            assert!(name.starts_with("vcr_") && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'), "{}", line);
+            if let Some(labels) = series.strip_prefix(name).filter(|l| !l.is_empty()) {
+                assert!(labels.starts_with("{kind=\"") && labels.ends_with("\"}"), "{}", line);
+            }
+            samples.insert(series.to_string(), value.parse::<f64>().unwrap());
+        }
+        samples
+    }
+
+    #[test]
+    fn test_prometheus_matches_json() {
+        use crate::pipeline::Pipeline;
+        use std::path::PathBuf;
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/calls");
+        let mut collector = MetricsCollector::new();
+        let output = Pipeline::default().run_with_metrics(&root, &collector).unwrap();
+        collector.increment_reparse();
+        collector.record_scan_duration(Duration::from_micros(1500));
+        collector.record_epoch_memory(EpochMarker::new(1), 4096);
+        collector.record_cpg_stats(output.cpg_epoch.stats().clone());
+
+        let text = collector.to_prometheus();
+        let json = collector.to_json();
+        let samples = samples(&text);
+        for family in ["vcr_scan_duration_seconds", "vcr_parse_time_p95_microseconds", "vcr_reparse_total",
+            "vcr_epoch_memory_bytes", "vcr_cpg_nodes_total"] {
+            assert!(text.contains(&format!("# TYPE {} ", family)), "{}", family);
+        }
+
+        assert_eq!(samples["vcr_scan_duration_seconds"] * 1e6, json["scan_duration_us"].as_f64().unwrap());
+        assert_eq!(samples["vcr_parse_time_p95_microseconds"], json["parse"]["p95_us"].as_f64().unwrap());
+        assert_eq!(samples["vcr_reparse_total"], json["reparses"].as_f64().unwrap());
+        assert_eq!(samples["vcr_epoch_memory_bytes"], 4096.0);
+
+        let nodes: Vec<(&String, &f64)> = samples.iter().filter(|(series, _)| series.starts_with("vcr_cpg_nodes_total{")).collect();
+        let by_kind = json["cpg"]["nodes_by_kind"].as_object().unwrap();
+        assert_eq!(nodes.len(), by_kind.len());
+        for (kind, count) in by_kind {
+            assert_eq!(samples[&format!("vcr_cpg_nodes_total{{kind=\"{}\"}}", kind)], count.as_f64().unwrap());
+        }
+        assert_eq!(nodes.iter().map(|(_, count)| **count).sum::<f64>(), json["cpg"]["total_nodes"].as_f64().unwrap());
+        assert_eq!(collector.to_prometheus(), text);
+    }
+
+    #[test]
+    fn test_prometheus_omits_unrecorded_families() {
+        let text = MetricsCollector::new().to_prometheus();
+        let samples = samples(&text);
+        assert_eq!(samples.keys().collect::<Vec<_>>(), [
+            "vcr_epoch_memory_bytes",
+            "vcr_parse_time_p95_microseconds",
+            "vcr_reparse_total",
+        ]);
+        assert!(samples.values().all(|value| *value == 0.0));
+    }
 }
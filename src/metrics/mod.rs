@@ -1,5 +1,7 @@
 //! Metrics collection (Step 1.7)
 
 pub mod collector;
+pub mod sink;
 
 pub use collector::MetricsCollector;
+pub use sink::{MetricsFormat, MetricsSink};
@@ -0,0 +1,82 @@
+//! Metrics file sink
+//!
+//! Writes a MetricsCollector's report to a file, replacing it atomically
+//! (temp + rename) so a scraper never reads a half-written report.
+
+use super::MetricsCollector;
+use crate::storage::store::write_atomic;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Report format of a metrics file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MetricsFormat {
+    /// `MetricsCollector::to_json`
+    #[default]
+    Json,
+
+    /// `MetricsCollector::to_prometheus`
+    Prometheus,
+}
+
+/// Where and how to write metrics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSink {
+    path: PathBuf,
+    format: MetricsFormat,
+}
+
+impl MetricsSink {
+    pub fn new(path: impl Into<PathBuf>, format: MetricsFormat) -> Self {
+        Self { path: path.into(), format }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Render `metrics` in this sink's format
+    pub fn render(&self, metrics: &MetricsCollector) -> String {
+        match self.format {
+            MetricsFormat::Json => format!("{:#}\n", metrics.to_json()),
+            MetricsFormat::Prometheus => metrics.to_prometheus(),
+        }
+    }
+
+    /// Replace the file with the current report
+    pub fn write(&self, metrics: &MetricsCollector) -> Result<()> {
+        write_atomic(&self.path, self.render(metrics).as_bytes())
+            .with_context(|| format!("Failed to write metrics to {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FileId;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_replaces_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("metrics.prom");
+        let sink = MetricsSink::new(&path, MetricsFormat::Prometheus);
+        let metrics = MetricsCollector::new();
+
+        sink.write(&metrics).unwrap();
+        metrics.increment_reparse();
+        metrics.record_parse_time(FileId::new(1), 40);
+        sink.write(&metrics).unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), metrics.to_prometheus());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let json = MetricsSink::new(&path, MetricsFormat::Json);
+        json.write(&metrics).unwrap();
+        let written: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written, metrics.to_json());
+
+        let missing = MetricsSink::new(dir.path().join("no/such/dir/m.json"), MetricsFormat::Json);
+        assert!(missing.write(&metrics).is_err());
+    }
+}
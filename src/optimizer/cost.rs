@@ -1,5 +1,12 @@
 //! Query cost model (Step 4.3)
 
+use crate::cpg::model::CPGStats;
+use crate::execution::WorkFragment;
+use crate::query::primitives::{EdgeStep, MAX_PATH_REPEAT};
+
+/// Assumed average edge fanout when following edges
+const DEFAULT_EDGE_FANOUT: f64 = 2.0;
+
 /// Query cost estimate
 #[derive(Debug, Clone, Copy)]
 pub struct QueryCost {
@@ -27,6 +34,61 @@ impl QueryCost {
         }
     }
 
+    /// Estimate the cost of one work fragment over a CPG of `cpg_nodes` nodes
+    pub fn for_fragment(work: &WorkFragment, cpg_nodes: usize) -> Self {
+        match work {
+            WorkFragment::FindNodes { .. } => Self::new(cpg_nodes, 1.0, 1, 0.0),
+            WorkFragment::FollowEdges { from, .. } => Self::new(from.len(), DEFAULT_EDGE_FANOUT, 1, 0.0),
+            WorkFragment::FollowEdgesReverse { to, .. } => Self::new(to.len(), DEFAULT_EDGE_FANOUT, 1, 0.0),
+            WorkFragment::Filter { nodes, .. } => Self::new(nodes.len(), 1.0, 1, 0.0),
+            // Set operations are linear in both inputs
+            WorkFragment::Intersect { a, b }
+            | WorkFragment::Union { a, b }
+            | WorkFragment::Difference { a, b } => Self::new(a.len() + b.len(), 1.0, 1, 0.0),
+            // Bounded BFS: one traversal per start node, up to the depth bound
+            WorkFragment::Taint { sources_spec, max_depth, .. } => {
+                Self::new(sources_spec.len(), DEFAULT_EDGE_FANOUT, (*max_depth).max(1), 0.0)
+            }
+            WorkFragment::Reachable { from, depth, .. } => {
+                Self::new(from.len(), DEFAULT_EDGE_FANOUT, (*depth).max(1), 0.0)
+            }
+            WorkFragment::MatchPath { from, pattern } => {
+                Self::new(from.len(), DEFAULT_EDGE_FANOUT, path_length(pattern).max(1), 0.0)
+            }
+        }
+    }
+
+    /// Estimate the result size of one work fragment from CPG statistics
+    ///
+    /// Never exceeds the CPG's node count (except for taint, whose paths may
+    /// repeat nodes).
+    pub fn estimate_rows(work: &WorkFragment, stats: &CPGStats) -> usize {
+        let kind_count = |kind| stats.nodes_by_kind.get(&kind).copied().unwrap_or(0);
+        let fanout = |inputs: usize| (inputs as f64 * DEFAULT_EDGE_FANOUT).ceil() as usize;
+        let estimate = match work {
+            WorkFragment::FindNodes { kind } => kind_count(*kind),
+            WorkFragment::FollowEdges { from: nodes, .. }
+            | WorkFragment::FollowEdgesReverse { to: nodes, .. } => fanout(nodes.len()),
+            // Assume the input has the CPG's kind mix
+            WorkFragment::Filter { nodes, kind: Some(kind) } if stats.total_nodes > 0 => {
+                (nodes.len() * kind_count(*kind)).div_ceil(stats.total_nodes)
+            }
+            WorkFragment::Filter { nodes, .. } => nodes.len(),
+            WorkFragment::Intersect { a, b } => a.len().min(b.len()),
+            WorkFragment::Union { a, b } => a.len() + b.len(),
+            WorkFragment::Difference { a, .. } => a.len(),
+            WorkFragment::Taint { sources_spec, max_depth, .. } => sources_spec.len() * (*max_depth).max(1),
+            WorkFragment::Reachable { from, depth, .. } => {
+                from.len() + fanout(from.len()).saturating_mul(*depth)
+            }
+            WorkFragment::MatchPath { from, pattern } => fanout(from.len()).saturating_mul(path_length(pattern)),
+        };
+        match work {
+            WorkFragment::Taint { .. } => estimate,
+            _ => estimate.min(stats.total_nodes),
+        }
+    }
+
     /// Estimate total cost (lower is better)
     pub fn total_cost(&self) -> f64 {
         (self.node_count as f64) 
@@ -36,6 +98,11 @@ impl QueryCost {
     }
 }
 
+/// Most edges a path pattern takes (steps capped as `match_path` caps them)
+fn path_length(pattern: &[EdgeStep]) -> usize {
+    pattern.iter().map(|step| step.max.min(MAX_PATH_REPEAT)).sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,4 +121,24 @@ mod tests {
         // Smaller node count = lower cost
         assert!(cost2.total_cost() < cost1.total_cost());
     }
+
+    #[test]
+    fn test_set_operation_costs() {
+        let small = WorkFragment::Union { a: vec![], b: vec![crate::cpg::model::CPGNodeId(1)] };
+        let large = WorkFragment::Difference {
+            a: (0..100).map(crate::cpg::model::CPGNodeId).collect(),
+            b: vec![],
+        };
+
+        assert!(QueryCost::for_fragment(&small, 0).total_cost() < QueryCost::for_fragment(&large, 0).total_cost());
+    }
+
+    #[test]
+    fn test_traversal_costs_grow_with_depth() {
+        let from = vec![crate::cpg::model::CPGNodeId(1)];
+        let reachable = |depth| WorkFragment::Reachable { from: from.clone(), kinds: vec![], depth };
+
+        let shallow = QueryCost::for_fragment(&reachable(1), 0).total_cost();
+        assert!(shallow < QueryCost::for_fragment(&reachable(10), 0).total_cost());
+    }
 }
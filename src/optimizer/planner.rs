@@ -48,6 +48,12 @@ impl QueryPlanner {
     }
 }
 
+impl Default for QueryPlanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
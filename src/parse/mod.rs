@@ -1,6 +1,8 @@
 //! Incremental parsing with Tree-sitter (Step 1.4)
 
 pub mod parser;
+pub mod pool;
 pub mod tree_cache;
 
 pub use parser::IncrementalParser;
+pub use pool::{ParseError, ParserPool};
@@ -36,6 +36,11 @@ impl IncrementalParser {
         file: &dyn SourceFile,
         old_tree: Option<&Tree>,
     ) -> Result<ParsedFile> {
+        let _span = tracing::debug_span!(
+            "parse",
+            file_id = file.file_id().as_u64(),
+            incremental = old_tree.is_some(),
+        ).entered();
         let start = Instant::now();
         
         let source = file.bytes();
@@ -0,0 +1,127 @@
+//! Parser pool (Step 1.4)
+//!
+//! One IncrementalParser per language, created on first use and reused for
+//! every later file of that language. Files without a supported language
+//! are rejected with `ParseError::UnsupportedLanguage` so callers can report
+//! them as skipped instead of dropping them.
+
+use crate::io::SourceFile;
+use crate::parse::IncrementalParser;
+use crate::types::{FileId, FileMetadata, Language, ParsedFile};
+use std::collections::hash_map::{Entry, HashMap};
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Parse failure for one file
+#[derive(Debug, Error)]
+pub enum ParseError {
+    /// The file's extension maps to no supported language
+    #[error("No parser for {}: unsupported language", path.display())]
+    UnsupportedLanguage { file_id: FileId, path: PathBuf },
+
+    /// Tree-sitter could not be set up or parse the file
+    #[error("Failed to parse {}: {source:#}", path.display())]
+    Failed {
+        file_id: FileId,
+        path: PathBuf,
+        source: anyhow::Error,
+    },
+}
+
+/// Lazily constructed parsers, one per language
+#[derive(Default)]
+pub struct ParserPool {
+    parsers: HashMap<Language, IncrementalParser>,
+}
+
+impl ParserPool {
+    /// Create an empty pool (parsers are created on first use)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a file with the parser for `meta.language`
+    ///
+    /// `old` enables incremental reparsing against a previous tree.
+    pub fn parse_file(
+        &mut self,
+        meta: &FileMetadata,
+        file: &dyn SourceFile,
+        old: Option<&ParsedFile>,
+    ) -> Result<ParsedFile, ParseError> {
+        let file_id = file.file_id();
+        let language = meta.language.ok_or_else(|| ParseError::UnsupportedLanguage {
+            file_id,
+            path: meta.path.clone(),
+        })?;
+        let failed = |source| ParseError::Failed { file_id, path: meta.path.clone(), source };
+
+        let parser = match self.parsers.entry(language) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(IncrementalParser::new(language).map_err(failed)?)
+            }
+        };
+        parser.parse(file, old.map(|parsed| &parsed.tree)).map_err(failed)
+    }
+
+    /// Number of languages with a constructed parser
+    pub fn len(&self) -> usize {
+        self.parsers.len()
+    }
+
+    /// Whether no parser has been constructed yet
+    pub fn is_empty(&self) -> bool {
+        self.parsers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MmappedFile;
+    use crate::repo::RepoScanner;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_mixed_repo_parses_known_and_skips_unknown() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("lib.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("util.rs"), "fn util() {}").unwrap();
+        fs::write(temp_dir.path().join("script.py"), "def main():\n    pass\n").unwrap();
+        let snapshot = RepoScanner::new(temp_dir.path()).unwrap().scan().unwrap();
+
+        let mut pool = ParserPool::new();
+        let (mut parsed, mut skipped) = (Vec::new(), Vec::new());
+        for (file_id, meta) in &snapshot.files {
+            let mmap = MmappedFile::open(snapshot.root.join(&meta.path), *file_id).unwrap();
+            match pool.parse_file(meta, &mmap, None) {
+                Ok(file) => parsed.push(file.file_id),
+                Err(ParseError::UnsupportedLanguage { path, .. }) => skipped.push(path),
+                Err(e) => panic!("{}", e),
+            }
+        }
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(skipped, vec![PathBuf::from("script.py")]);
+        assert_eq!(pool.len(), 1, "Both Rust files share one parser");
+    }
+
+    #[test]
+    fn test_reparse_with_old_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(&path, "fn main() {}").unwrap();
+        let snapshot = RepoScanner::new(temp_dir.path()).unwrap().scan().unwrap();
+        let (file_id, meta) = snapshot.files.iter().next().unwrap();
+
+        let mut pool = ParserPool::new();
+        let first = pool.parse_file(meta, &MmappedFile::open(&path, *file_id).unwrap(), None).unwrap();
+        fs::write(&path, "fn main() { let x = 1; }").unwrap();
+        let second = pool.parse_file(meta, &MmappedFile::open(&path, *file_id).unwrap(), Some(&first)).unwrap();
+
+        assert!(!second.tree.root_node().has_error());
+        assert_eq!(second.file_id, *file_id);
+    }
+}
@@ -0,0 +1,1010 @@
+//! Pipeline orchestration (Path B8)
+//!
+//! **Goal**: One call from a path to a CPGEpoch
+//!
+//! scan → snapshot → mmap → parse → CFG → symbols → DFG → CPG fusion, plus
+//! the call graph and CFG metrics, in FileId order. Each run creates its
+//! epochs through an EpochManager; the returned SemanticEpoch keeps the
+//! run's parse and ingestion epochs (and their mmaps) alive.
+//!
+//! `run_workspace` ingests several roots as one repository.
+//!
+//! `with_backend` scans and reads through an `IOBackend` instead of the
+//! local filesystem (see `io`); the CPG depends only on the files' relative
+//! paths and contents, so it is identical to an on-disk run of the same tree.
+//! `with_overlays` serves some files from unsaved buffers instead (see
+//! `OverlayBackend`); those files are listed in `PipelineOutput::overlaid`.
+//!
+//! ## Incremental runs
+//!
+//! `run_incremental` rescans the previous root(s) and asks ChangeDetector what
+//! changed. Added, modified and renamed files (`ChangeSummary::changed_file_ids`)
+//! are re-parsed and re-analyzed; CFGs, DFGs, symbols and call-graph entries
+//! of unchanged files are carried over. The summary is kept in
+//! `PipelineOutput::changes`. A caller that wants to skip a run when nothing
+//! changed can `scan` first and pass the snapshot to `run_incremental_scanned`.
+//! Invalidation is per file: CFG and DFG IDs are assigned per file, so any
+//! edit renumbers the whole file anyway. The CPG is always re-fused, so the
+//! result is identical to a fresh run.
+//!
+//! Fusion records CFG node → CPG node and DFG value → CPG node mappings in
+//! the epoch's InvalidationTracker, so `invalidate` reports which CPG nodes a
+//! source edit touches. CPG node IDs are still assigned globally in fusion
+//! order, so the graph itself is re-fused rather than patched.
+//!
+//! A reparsed file whose semantic fingerprint (see `SemanticEpoch`) matches
+//! the previous run's is a semantic no-op, e.g. after a comment edit that
+//! moves no code: it is counted in `MetricsCollector::semantic_noops` and not
+//! audited. When every file's fingerprint matches, the previous CPG is reused
+//! instead of re-fused, along with its hash and per-file partial hashes.
+//! Other reparsed files are counted in `files_semantically_rebuilt`, with
+//! their CFGs and DFGs in `cfg_rebuilds` and `dfg_rebuilds`; reused CPG nodes
+//! in `cpg_nodes_reused`.
+//!
+//! ## Syntax errors
+//!
+//! Tree-sitter recovers from syntax errors with ERROR and MISSING nodes, and
+//! where it recovers is not something the CPG should depend on. Every parsed
+//! file's `ParseQuality` is checked; unclean files are recorded in
+//! `PipelineOutput::parse_errors` and handled per `[parse] on_parse_error`:
+//! skipped (no CFGs, DFGs, symbols or call-graph entries; the default),
+//! analyzed best-effort, or fatal.
+//!
+//! ## Scan policy
+//!
+//! Files whose `.vcr.toml` policy (see `repo::policy`) skips semantics, or
+//! that exceed its `max_file_size`, are hashed into the snapshot but not
+//! opened, parsed or analyzed.
+//!
+//! ## Budgets
+//!
+//! Files are analyzed within `[limits]` (see `SemanticEpoch`): a function or
+//! file over its budget keeps the graphs built so far and is reported as
+//! `Degraded`, with a warning and `MetricsCollector::degraded_files`. A fused
+//! CPG over `max_total_cpg_nodes` fails the run, unless
+//! `allow_degraded_total` is set, in which case it is kept with a warning and
+//! counted in `MetricsCollector::cpg_budget_overruns`.
+//!
+//! ## Plans
+//!
+//! `plan` runs the scan alone and reports what a run would parse and skip,
+//! and why (see `plan`).
+//!
+//! ## Audit
+//!
+//! A sample of the files an incremental run rebuilds (`[audit] sample_rate`,
+//! keyed on content hash) is re-analyzed from scratch by `verify::Auditor`.
+//! Any CFG or DFG hash mismatch aborts the run. Passes, failures, reparses
+//! and reused files are counted in the caller's MetricsCollector.
+
+pub mod plan;
+
+pub use plan::{IngestPlan, ParseLoad, PlannedChanges, PlannedFile, PlannedRename, SkipReason, SkippedFile};
+
+use crate::analysis::{CallGraph, FunctionSummaries, TaintSpec};
+use crate::change::{ChangeDetector, ChangeSummary};
+use crate::config::{LimitsConfig, ParseErrorPolicy, ValoriConfig};
+use crate::cpg::builder::CPGBuilder;
+use crate::cpg::FrozenCPGEpoch;
+use crate::io::hot::HotPathIO;
+use crate::io::{normalize_line_endings, BufferedFile, IOBackend, MmappedFile, OverlayBackend};
+use crate::memory::EpochManager;
+use crate::metrics::MetricsCollector;
+use crate::parse::{ParseError, ParserPool};
+use crate::repo::RepoScanner;
+use crate::semantic::cfg::MetricsReport;
+use crate::semantic::{profile_for, SemanticEpoch, SemanticStatus};
+use crate::types::{FileId, InvertedRange, ParseQuality, ParsedFile, RepoSnapshot};
+use crate::verify::{check_determinism, Auditor, StageHashes};
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Source extension ingested by the pipeline
+const RUST_EXTENSION: &str = "rs";
+
+/// Analysis of one rebuilt file in an incremental run
+///
+/// Defaults to `SemanticEpoch::add_parsed`; replaceable so the audit can be
+/// exercised against a faulty implementation.
+pub type IncrementalAnalyzer = fn(&mut SemanticEpoch, FileId, &ParsedFile, &[u8]) -> Result<()>;
+
+/// Everything one pipeline run produces
+pub struct PipelineOutput {
+    /// Scanned snapshot
+    pub snapshot: RepoSnapshot,
+
+    /// CFGs, DFGs and symbols
+    pub semantic: SemanticEpoch,
+
+    /// Fused CPG, frozen
+    pub cpg_epoch: FrozenCPGEpoch,
+
+    /// Name-resolved call graph
+    pub call_graph: CallGraph,
+
+    /// Function summaries over `call_graph` (see `analysis::summaries`)
+    pub summaries: FunctionSummaries,
+
+    /// Per-function CFG metrics
+    pub metrics: MetricsReport,
+
+    /// Files parsed and analyzed by this run (sorted)
+    pub rebuilt: Vec<FileId>,
+
+    /// Files whose parse tree has syntax errors
+    pub parse_errors: BTreeMap<FileId, ParseQuality>,
+
+    /// Policy the unclean files were handled with
+    pub parse_error_policy: ParseErrorPolicy,
+
+    /// Files whose contents came from an overlay rather than the backend
+    pub overlaid: BTreeSet<FileId>,
+
+    /// What changed since the previous run (None for a from-scratch run)
+    pub changes: Option<ChangeSummary>,
+}
+
+/// Path → CPG orchestration
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    /// Build twice and fail closed on any stage hash divergence
+    verify_determinism: bool,
+
+    /// Validate the fused CPG and fail closed on any error
+    strict_validation: bool,
+
+    /// Samples incremental rebuilds for from-scratch comparison
+    auditor: Auditor,
+
+    /// Analysis of files rebuilt by incremental runs
+    incremental_analyzer: IncrementalAnalyzer,
+
+    /// Handling of files with syntax errors
+    on_parse_error: ParseErrorPolicy,
+
+    /// Graph size budgets
+    limits: LimitsConfig,
+
+    /// Where files are listed and read (None: local filesystem, mmapped)
+    backend: Option<Arc<dyn IOBackend>>,
+
+    /// Unsaved contents replacing files, by absolute path
+    overlays: BTreeMap<PathBuf, Vec<u8>>,
+
+    /// Scan and parse files' LF views
+    normalize_line_endings: bool,
+
+    /// Source and sink calls function summaries track
+    taint_spec: TaintSpec,
+}
+
+impl Pipeline {
+    /// Create a pipeline from config
+    pub fn new(config: &ValoriConfig) -> Self {
+        Self {
+            verify_determinism: config.verification.verify_determinism,
+            strict_validation: config.verification.strict_validation,
+            auditor: Auditor::new(&config.audit),
+            incremental_analyzer: SemanticEpoch::add_parsed,
+            on_parse_error: config.parse.on_parse_error,
+            limits: config.limits,
+            backend: None,
+            overlays: BTreeMap::new(),
+            normalize_line_endings: config.parse.normalize_line_endings,
+            taint_spec: TaintSpec::from(&config.analysis),
+        }
+    }
+
+    /// Override `[verification] verify_determinism`
+    pub fn with_verify_determinism(mut self, verify: bool) -> Self {
+        self.verify_determinism = verify;
+        self
+    }
+
+    /// Override `[verification] strict_validation`
+    pub fn with_strict_validation(mut self, strict: bool) -> Self {
+        self.strict_validation = strict;
+        self
+    }
+
+    /// Override `[audit]`
+    pub fn with_auditor(mut self, auditor: Auditor) -> Self {
+        self.auditor = auditor;
+        self
+    }
+
+    /// Replace the analysis used for files rebuilt incrementally
+    pub fn with_incremental_analyzer(mut self, analyzer: IncrementalAnalyzer) -> Self {
+        self.incremental_analyzer = analyzer;
+        self
+    }
+
+    /// Override `[parse] on_parse_error`
+    pub fn with_parse_error_policy(mut self, policy: ParseErrorPolicy) -> Self {
+        self.on_parse_error = policy;
+        self
+    }
+
+    /// Override `[parse] normalize_line_endings`
+    pub fn with_normalized_line_endings(mut self, normalize: bool) -> Self {
+        self.normalize_line_endings = normalize;
+        self
+    }
+
+    /// Whether files are scanned and parsed as their LF views
+    pub fn normalizes_line_endings(&self) -> bool {
+        self.normalize_line_endings
+    }
+
+    /// Override `[analysis] taint_sources` and `taint_sinks`
+    pub fn with_taint_spec(mut self, spec: TaintSpec) -> Self {
+        self.taint_spec = spec;
+        self
+    }
+
+    /// Override `[limits]`
+    pub fn with_limits(mut self, limits: LimitsConfig) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Scan and read files through `backend` instead of the filesystem
+    pub fn with_backend(mut self, backend: Arc<dyn IOBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Read these files' contents from memory (keyed by the snapshot root
+    /// joined with the file's relative path) instead of the backend
+    pub fn with_overlays(mut self, overlays: BTreeMap<PathBuf, Vec<u8>>) -> Self {
+        self.overlays = overlays;
+        self
+    }
+
+    /// Backend for a run: the configured one, under any overlays
+    fn run_backend(&self) -> Option<Arc<dyn IOBackend>> {
+        if self.overlays.is_empty() {
+            return self.backend.clone();
+        }
+        let base = self.backend.clone().unwrap_or_else(|| Arc::new(HotPathIO::new()));
+        Some(Arc::new(OverlayBackend::new(base, self.overlays.clone())))
+    }
+
+    /// Backend a run reads files through (the filesystem without one),
+    /// under any overlays
+    pub fn source_backend(&self) -> Arc<dyn IOBackend> {
+        self.run_backend().unwrap_or_else(|| Arc::new(HotPathIO::new()))
+    }
+
+    /// Whether runs are built twice and compared
+    pub fn verifies_determinism(&self) -> bool {
+        self.verify_determinism
+    }
+
+    /// Build a repository from scratch
+    pub fn run(&self, root: &Path) -> Result<PipelineOutput> {
+        self.run_workspace(&[root.to_path_buf()])
+    }
+
+    /// `run`, recording parse times and syntax errors in `metrics`
+    pub fn run_with_metrics(&self, root: &Path, metrics: &MetricsCollector) -> Result<PipelineOutput> {
+        self.run_workspace_with_metrics(&[root.to_path_buf()], metrics)
+    }
+
+    /// Build several roots from scratch into one snapshot and CPG
+    ///
+    /// See `RepoScanner::with_roots` for how files are named.
+    pub fn run_workspace(&self, roots: &[PathBuf]) -> Result<PipelineOutput> {
+        self.run_workspace_with_metrics(roots, &MetricsCollector::new())
+    }
+
+    /// `run_workspace`, recording into `metrics` (the first build only when
+    /// verifying determinism)
+    pub fn run_workspace_with_metrics(&self, roots: &[PathBuf], metrics: &MetricsCollector) -> Result<PipelineOutput> {
+        if !self.verify_determinism {
+            return self.build(roots, None, metrics);
+        }
+
+        let mut outputs = Vec::new();
+        let mut metrics = Some(metrics);
+        check_determinism(|| {
+            let fresh = MetricsCollector::new();
+            let output = self.build(roots, None, metrics.take().unwrap_or(&fresh))?;
+            let hashes = StageHashes::from_output(&output);
+            outputs.push(output);
+            Ok(hashes)
+        })?.into_result()?;
+
+        Ok(outputs.swap_remove(0))
+    }
+
+    /// Rebuild only what changed since a previous run of the same root(s)
+    pub fn run_incremental(&self, previous: &PipelineOutput) -> Result<PipelineOutput> {
+        self.run_incremental_with_metrics(previous, &MetricsCollector::new())
+    }
+
+    /// `run_incremental`, counting reparses, reuse and audits in `metrics`
+    pub fn run_incremental_with_metrics(
+        &self,
+        previous: &PipelineOutput,
+        metrics: &MetricsCollector,
+    ) -> Result<PipelineOutput> {
+        self.build(&previous.snapshot.root_paths(), Some(previous), metrics)
+    }
+
+    /// `run_incremental_with_metrics` against a snapshot already taken by `scan`
+    pub fn run_incremental_scanned(
+        &self,
+        previous: &PipelineOutput,
+        snapshot: RepoSnapshot,
+        metrics: &MetricsCollector,
+    ) -> Result<PipelineOutput> {
+        self.build_scanned(snapshot, Some(previous), metrics)
+    }
+
+    /// Scan root(s) as a run would (same backend, overlays and extensions)
+    pub fn scan(&self, roots: &[PathBuf]) -> Result<RepoSnapshot> {
+        self.scanner(roots)?.scan()
+    }
+
+    /// The scanner runs use for `roots`
+    fn scanner(&self, roots: &[PathBuf]) -> Result<RepoScanner> {
+        let scanner = match self.run_backend() {
+            Some(backend) => RepoScanner::with_backend(roots.to_vec(), backend)?,
+            None => RepoScanner::with_roots(roots.to_vec())?,
+        };
+        Ok(scanner.with_extension(RUST_EXTENSION).with_normalized_line_endings(self.normalize_line_endings))
+    }
+
+    /// Run every stage, reusing unchanged files from `previous`
+    fn build(
+        &self,
+        roots: &[PathBuf],
+        previous: Option<&PipelineOutput>,
+        metrics: &MetricsCollector,
+    ) -> Result<PipelineOutput> {
+        let snapshot = self.scan(roots)?;
+        self.build_scanned(snapshot, previous, metrics)
+    }
+
+    /// Every stage after the scan
+    fn build_scanned(
+        &self,
+        snapshot: RepoSnapshot,
+        previous: Option<&PipelineOutput>,
+        metrics: &MetricsCollector,
+    ) -> Result<PipelineOutput> {
+        let _span = tracing::info_span!("pipeline", incremental = previous.is_some()).entered();
+        let backend = self.run_backend();
+        let overlaid: BTreeSet<FileId> = snapshot.files.iter()
+            .filter(|(_, meta)| self.overlays.contains_key(&snapshot.root.join(&meta.path)))
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut file_ids = snapshot.file_ids();
+        file_ids.sort();
+
+        let changes = previous.map(|previous| {
+            let changes = ChangeDetector::new(previous.snapshot.clone()).detect(&snapshot);
+            let summary = ChangeSummary::from(changes.as_slice());
+            tracing::debug!(
+                added = summary.added, modified = summary.modified, deleted = summary.deleted,
+                renamed = summary.renamed, unchanged = summary.unchanged, "changes detected"
+            );
+            summary
+        });
+        let rebuilt: Vec<FileId> = match &changes {
+            Some(summary) => summary.changed_file_ids().to_vec(),
+            None => file_ids.clone(),
+        };
+
+        let mut epochs = EpochManager::new();
+        let mut ingestion = epochs.ingestion_epoch();
+        for file_id in &rebuilt {
+            let meta = &snapshot.files[file_id];
+            if !meta.policy.analyzes(meta.size) {
+                continue;
+            }
+            let path = snapshot.root.join(&meta.path);
+            let opened = || format!("Failed to open {}", meta.path.display());
+            let bytes = match (&backend, self.normalize_line_endings) {
+                (Some(backend), _) => backend.read_file(&path).with_context(opened)?,
+                (None, true) => std::fs::read(&path).with_context(opened)?,
+                (None, false) => {
+                    ingestion.add_file(MmappedFile::open(&path, *file_id).with_context(opened)?);
+                    continue;
+                }
+            };
+            let bytes = match self.normalize_line_endings {
+                true => normalize_line_endings(bytes).0,
+                false => bytes,
+            };
+            ingestion.add_file(BufferedFile::new(bytes, *file_id));
+        }
+
+        let parse_epoch = epochs.parse_epoch(ingestion)?;
+        let ingestion = parse_epoch.ingestion();
+        let mut semantic = epochs.semantic_epoch(&parse_epoch)?;
+        semantic.set_limits(self.limits);
+        let mut call_graph = previous.map(|p| p.call_graph.clone()).unwrap_or_default();
+        let mut parsers = ParserPool::new();
+        let mut parse_errors: BTreeMap<FileId, ParseQuality> = previous
+            .map(|p| p.parse_errors.iter()
+                .filter(|(id, _)| snapshot.files.contains_key(id) && rebuilt.binary_search(id).is_err())
+                .map(|(id, quality)| (*id, quality.clone()))
+                .collect())
+            .unwrap_or_default();
+
+        for file_id in &file_ids {
+            if rebuilt.binary_search(file_id).is_err() {
+                if let Some(previous) = previous {
+                    semantic.carry_over(&previous.semantic, *file_id);
+                    metrics.increment_reuse();
+                }
+                continue;
+            }
+
+            let meta = &snapshot.files[file_id];
+            if !meta.policy.analyzes(meta.size) {
+                tracing::debug!(file = %meta.path.display(), "Skipped: scan policy");
+                call_graph.remove_file(*file_id);
+                continue;
+            }
+            let mmap = ingestion.get_file(*file_id)
+                .context("File missing from ingestion epoch")?;
+            let source = mmap.bytes();
+            let started = Instant::now();
+            let parsed = match parsers.parse_file(&snapshot.files[file_id], &*mmap, None) {
+                Ok(parsed) => {
+                    metrics.record_parse_time(*file_id, started.elapsed().as_micros() as u64);
+                    parsed
+                }
+                Err(e @ ParseError::UnsupportedLanguage { .. }) => {
+                    tracing::warn!("Skipped: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            call_graph.remove_file(*file_id);
+            let quality = parsed.quality();
+            if !quality.clean {
+                let path = &snapshot.files[file_id].path;
+                metrics.record_parse_errors(quality.error_count, self.on_parse_error == ParseErrorPolicy::Skip);
+                // A tree can be unclean through inverted ranges alone
+                let first = quality.error_ranges.first().copied()
+                    .or_else(|| quality.inverted_ranges.first().map(InvertedRange::clamped))
+                    .unwrap_or_default();
+                match self.on_parse_error {
+                    ParseErrorPolicy::Fail => bail!(
+                        "Syntax errors in {}: {} error node(s), first at bytes {}..{}",
+                        path.display(), quality.error_count, first.start, first.end
+                    ),
+                    ParseErrorPolicy::Skip => {
+                        tracing::warn!(file = %path.display(), errors = quality.error_count, "Skipped: syntax errors");
+                        parse_errors.insert(*file_id, quality);
+                        continue;
+                    }
+                    ParseErrorPolicy::IncludeBestEffort => {
+                        tracing::warn!(file = %path.display(), errors = quality.error_count, "Analyzing despite syntax errors");
+                        parse_errors.insert(*file_id, quality);
+                    }
+                }
+            }
+            call_graph.add_file(&snapshot.files[file_id].path, &parsed, source);
+            if let Some(language) = meta.language {
+                semantic.set_language(*file_id, language);
+            }
+            let Some(previous) = previous else {
+                let profile = profile_for(snapshot.files[file_id].language);
+                semantic.add_parsed_with_profile(*file_id, &parsed, source, profile)?;
+                warn_if_degraded(&semantic, *file_id, &snapshot.files[file_id].path, metrics);
+                continue;
+            };
+
+            metrics.increment_reparse();
+            (self.incremental_analyzer)(&mut semantic, *file_id, &parsed, source)?;
+            warn_if_degraded(&semantic, *file_id, &snapshot.files[file_id].path, metrics);
+            if previous.semantic.fingerprint(*file_id) == Some(semantic.update_fingerprint(*file_id)) {
+                // Same artifacts as last run: nothing downstream to rebuild or audit
+                metrics.record_semantic_noop();
+                continue;
+            }
+            metrics.record_semantic_rebuild(
+                semantic.get_cfgs(*file_id).map_or(0, Vec::len),
+                semantic.get_dfgs(*file_id).map_or(0, Vec::len),
+            );
+            if self.auditor.should_audit(&snapshot.files[file_id].content_hash) {
+                let divergences = self.auditor.audit_file(&*mmap, &semantic)?;
+                if let Some(first) = divergences.first() {
+                    metrics.record_audit_failure();
+                    bail!(
+                        "Incremental audit failed for {} at stage '{}': {}",
+                        snapshot.files[file_id].path.display(), first.stage, first.detail
+                    );
+                }
+                metrics.record_audit_pass();
+            }
+        }
+
+        // Deleted files
+        if let Some(previous) = previous {
+            for file_id in previous.snapshot.files.keys() {
+                if !snapshot.files.contains_key(file_id) {
+                    call_graph.remove_file(*file_id);
+                }
+            }
+        }
+        let summaries = FunctionSummaries::update(previous.map(|p| &p.summaries), &call_graph, &semantic, &self.taint_spec);
+
+        let mut cpg_epoch = epochs.cpg_epoch(&semantic)?;
+        match previous.filter(|p| p.semantic.fingerprints() == semantic.fingerprints()) {
+            Some(previous) => {
+                // Every file fuses to the nodes it fused to last run
+                *cpg_epoch.cpg_mut() = previous.cpg_epoch.cpg().clone();
+                cpg_epoch.set_hashes(previous.cpg_epoch.cpg_hash().to_string(), previous.cpg_epoch.file_hashes().clone());
+                *semantic.invalidation_mut() = previous.semantic.invalidation().clone();
+                metrics.record_cpg_nodes_reused(cpg_epoch.cpg().nodes.len());
+                if self.strict_validation {
+                    CPGBuilder::validate(cpg_epoch.cpg(), &semantic)?;
+                }
+            }
+            None => {
+                // The tracker lives in the epoch it reads from; move it out while fusing
+                let mut tracker = std::mem::take(semantic.invalidation_mut());
+                CPGBuilder::new()
+                    .with_strict_validation(self.strict_validation)
+                    .build_tracked(&semantic, &mut cpg_epoch, &mut tracker)?;
+                *semantic.invalidation_mut() = tracker;
+            }
+        }
+        let total = cpg_epoch.cpg().nodes.len();
+        if total > self.limits.max_total_cpg_nodes {
+            if !self.limits.allow_degraded_total {
+                bail!(
+                    "CPG has {} nodes, over [limits] max_total_cpg_nodes = {} (set allow_degraded_total to keep it)",
+                    total, self.limits.max_total_cpg_nodes
+                );
+            }
+            tracing::warn!(nodes = total, max = self.limits.max_total_cpg_nodes, "CPG over node budget");
+            metrics.record_cpg_budget_overrun();
+        }
+        let cpg_epoch = cpg_epoch.freeze();
+        if self.strict_validation {
+            if let Err(divergences) = cpg_epoch.indices().verify_against(cpg_epoch.cpg()) {
+                let details: Vec<String> = divergences.iter().map(ToString::to_string).collect();
+                bail!("CPG index verification failed: {}", details.join("; "));
+            }
+        }
+        let metrics = MetricsReport::from_epoch(&semantic, &snapshot);
+
+        Ok(PipelineOutput {
+            snapshot,
+            semantic,
+            cpg_epoch,
+            call_graph,
+            summaries,
+            metrics,
+            rebuilt,
+            parse_errors,
+            parse_error_policy: self.on_parse_error,
+            overlaid,
+            changes,
+        })
+    }
+}
+
+/// Warn about and count a file cut short by `[limits]` or inverted ranges
+fn warn_if_degraded(semantic: &SemanticEpoch, file_id: FileId, path: &Path, metrics: &MetricsCollector) {
+    if let SemanticStatus::Degraded { reason } = semantic.status(file_id) {
+        tracing::warn!(file = %path.display(), %reason, "Degraded");
+        metrics.record_degraded_file();
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new(&ValoriConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn temp_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() { let x = 1; b(); }\n").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "fn b() { if true { let y = 2; } }\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_run_builds_every_stage() {
+        let dir = temp_repo();
+        let output = Pipeline::default().run(dir.path()).unwrap();
+
+        assert_eq!(output.snapshot.files.len(), 2);
+        assert_eq!(output.semantic.get_all_file_ids().len(), 2);
+        assert_eq!(output.call_graph.len(), 2);
+        assert_eq!(output.metrics.repo.function_count, 2);
+        assert_eq!(output.rebuilt.len(), 2);
+        assert!(output.cpg_epoch.cpg().nodes.len() > 2);
+    }
+
+    #[test]
+    fn test_memory_backend_matches_disk() {
+        use crate::io::MemoryBackend;
+        use std::collections::HashMap;
+
+        let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/calls");
+        let disk = Pipeline::default().run(&fixture).unwrap();
+
+        let files = |edit: &str| -> HashMap<PathBuf, Vec<u8>> {
+            disk.snapshot.files.values()
+                .map(|meta| {
+                    let mut bytes = std::fs::read(fixture.join(&meta.path)).unwrap();
+                    if meta.path.ends_with("util.rs") {
+                        bytes.extend_from_slice(edit.as_bytes());
+                    }
+                    (Path::new("/memory/calls").join(&meta.path), bytes)
+                })
+                .collect()
+        };
+        let pipeline = |edit| Pipeline::default()
+            .with_verify_determinism(true)
+            .with_backend(Arc::new(MemoryBackend::new(files(edit))));
+        let memory = pipeline("").run(Path::new("/memory/calls")).unwrap();
+
+        assert_eq!(memory.snapshot.snapshot_hash, disk.snapshot.snapshot_hash);
+        assert_eq!(memory.cpg_epoch.cpg().compute_hash(), disk.cpg_epoch.cpg().compute_hash());
+
+        // An unsaved edit, rebuilt incrementally from the same backend
+        let edited = pipeline("\nfn added() { let z = 3; }\n");
+        let incremental = edited.run_incremental(&memory).unwrap();
+        let fresh = edited.run(Path::new("/memory/calls")).unwrap();
+        assert_eq!(incremental.rebuilt.len(), 1);
+        assert_eq!(incremental.cpg_epoch.cpg().compute_hash(), fresh.cpg_epoch.cpg().compute_hash());
+        assert_ne!(fresh.cpg_epoch.cpg().compute_hash(), memory.cpg_epoch.cpg().compute_hash());
+    }
+
+    #[test]
+    fn test_skip_semantics_policy_hashes_without_analysis() {
+        let dir = temp_repo();
+        std::fs::create_dir(dir.path().join("generated")).unwrap();
+        std::fs::write(dir.path().join("generated/out.rs"), "fn generated() { let g = 1; }\n").unwrap();
+        std::fs::write(dir.path().join("generated/.vcr.toml"), "skip_semantics = true\n").unwrap();
+        let pipeline = Pipeline::default();
+        let first = pipeline.run(dir.path()).unwrap();
+
+        assert_eq!(first.snapshot.files.len(), 3);
+        assert_eq!(first.semantic.get_all_file_ids().len(), 2);
+        assert_eq!(first.metrics.repo.function_count, 2);
+
+        // Lifting the policy rebuilds the file though its content is unchanged
+        std::fs::write(dir.path().join("generated/.vcr.toml"), "skip_semantics = false\n").unwrap();
+        let second = pipeline.run_incremental(&first).unwrap();
+        let fresh = pipeline.run(dir.path()).unwrap();
+        assert_eq!(second.rebuilt.len(), 1);
+        assert_eq!(second.metrics.repo.function_count, 3);
+        assert_eq!(second.cpg_epoch.cpg().compute_hash(), fresh.cpg_epoch.cpg().compute_hash());
+    }
+
+    #[test]
+    fn test_run_with_verification() {
+        let dir = temp_repo();
+        let plain = Pipeline::default().run(dir.path()).unwrap();
+        let verified = Pipeline::default().with_verify_determinism(true).run(dir.path()).unwrap();
+
+        assert_eq!(verified.cpg_epoch.cpg().compute_hash(), plain.cpg_epoch.cpg().compute_hash());
+    }
+
+    #[test]
+    fn test_run_with_strict_validation() {
+        let dir = temp_repo();
+        let pipeline = Pipeline::default().with_strict_validation(true);
+        let output = pipeline.run(dir.path()).unwrap();
+
+        assert!(output.cpg_epoch.cpg().validate_against(&output.semantic).is_ok());
+        assert!(pipeline.run_incremental(&output).is_ok());
+    }
+
+    #[test]
+    fn test_workspace_incremental_tracks_each_root() {
+        let dir = TempDir::new().unwrap();
+        let roots: Vec<PathBuf> = ["a", "b"].iter().map(|r| dir.path().join(r)).collect();
+        for root in &roots {
+            std::fs::create_dir_all(root).unwrap();
+            std::fs::write(root.join("lib.rs"), "fn f() { let x = 1; }\n").unwrap();
+        }
+
+        let pipeline = Pipeline::default();
+        let first = pipeline.run_workspace(&roots).unwrap();
+        assert_eq!(first.rebuilt.len(), 2);
+
+        std::fs::write(roots[1].join("lib.rs"), "fn f() { let y = 2; }\n").unwrap();
+        let second = pipeline.run_incremental(&first).unwrap();
+        let changed = second.snapshot.files.iter()
+            .find(|(_, meta)| meta.path == Path::new("b/lib.rs"))
+            .map(|(id, _)| *id)
+            .unwrap();
+        assert_eq!(second.rebuilt, vec![changed]);
+
+        let fresh = pipeline.run_workspace(&roots).unwrap();
+        assert_eq!(second.cpg_epoch.cpg().compute_hash(), fresh.cpg_epoch.cpg().compute_hash());
+    }
+
+    #[test]
+    fn test_config_enables_verification() {
+        let mut config = ValoriConfig::default();
+        config.verification.verify_determinism = true;
+
+        assert!(Pipeline::new(&config).verifies_determinism());
+        assert!(!Pipeline::default().verifies_determinism());
+    }
+
+    #[test]
+    fn test_incremental_without_changes_rebuilds_nothing() {
+        let dir = temp_repo();
+        let pipeline = Pipeline::default();
+        let first = pipeline.run(dir.path()).unwrap();
+        let second = pipeline.run_incremental(&first).unwrap();
+
+        assert!(second.rebuilt.is_empty());
+        assert_eq!(StageHashes::from_output(&first), StageHashes::from_output(&second));
+    }
+
+    #[test]
+    fn test_comment_edit_is_a_semantic_noop() {
+        let dir = temp_repo();
+        let pipeline = Pipeline::default().with_auditor(audit_every_rebuild());
+        let first = pipeline.run(dir.path()).unwrap();
+        let b = first.snapshot.files.iter()
+            .find(|(_, meta)| meta.path == Path::new("b.rs"))
+            .map(|(id, _)| *id)
+            .unwrap();
+
+        let source = std::fs::read_to_string(dir.path().join("b.rs")).unwrap();
+        std::fs::write(dir.path().join("b.rs"), source + "// trailing note\n").unwrap();
+        let metrics = MetricsCollector::new();
+        let second = pipeline.run_incremental_with_metrics(&first, &metrics).unwrap();
+
+        assert_eq!(second.rebuilt, vec![b]);
+        assert_eq!(metrics.reparse_count(), 1);
+        assert_eq!(metrics.semantic_noops(), 1);
+        assert_eq!(metrics.audit_passes() + metrics.audit_failures(), 0);
+        assert_eq!(second.semantic.fingerprint(b), first.semantic.fingerprint(b));
+        assert_eq!(second.cpg_epoch.cpg().compute_hash(), first.cpg_epoch.cpg().compute_hash());
+        assert_eq!(metrics.to_json()["semantic_noops"], 1);
+        assert_eq!((metrics.files_semantically_rebuilt(), metrics.cfg_rebuilds()), (0, 0));
+        assert_eq!(metrics.cpg_nodes_reused(), first.cpg_epoch.cpg().nodes.len());
+
+        // Moving code is not a no-op: the CPG records where every node is
+        std::fs::write(dir.path().join("b.rs"), "// leading note\nfn b() { if true { let y = 2; } }\n").unwrap();
+        let metrics = MetricsCollector::new();
+        let third = pipeline.run_incremental_with_metrics(&second, &metrics).unwrap();
+        let fresh = pipeline.run(dir.path()).unwrap();
+
+        assert_eq!(metrics.semantic_noops(), 0);
+        assert_eq!(metrics.audit_passes(), 1);
+        assert_eq!((metrics.files_semantically_rebuilt(), metrics.cfg_rebuilds(), metrics.dfg_rebuilds()), (1, 1, 1));
+        assert_eq!(metrics.cpg_nodes_reused(), 0);
+        assert_ne!(third.semantic.fingerprint(b), second.semantic.fingerprint(b));
+        assert_eq!(third.cpg_epoch.cpg().compute_hash(), fresh.cpg_epoch.cpg().compute_hash());
+    }
+
+    /// Correct analysis plus a phantom function
+    fn buggy_analyzer(
+        semantic: &mut SemanticEpoch,
+        file_id: FileId,
+        parsed: &ParsedFile,
+        source: &[u8],
+    ) -> Result<()> {
+        use crate::semantic::{FunctionId, NodeId, CFG};
+
+        semantic.add_parsed(file_id, parsed, source)?;
+        semantic.add_cfg(file_id, CFG::new(FunctionId(99), file_id, NodeId(0), NodeId(0)));
+        Ok(())
+    }
+
+    fn edit_and_rebuild(pipeline: &Pipeline, metrics: &MetricsCollector) -> Result<PipelineOutput> {
+        let dir = temp_repo();
+        let first = pipeline.run(dir.path()).unwrap();
+        std::fs::write(dir.path().join("b.rs"), "fn b() { let z = 3; }\n").unwrap();
+        pipeline.run_incremental_with_metrics(&first, metrics)
+    }
+
+    fn audit_every_rebuild() -> Auditor {
+        Auditor::new(&crate::config::AuditConfig { sample_rate: 1.0 })
+    }
+
+    #[test]
+    fn test_audit_catches_buggy_incremental_analysis() {
+        let pipeline = Pipeline::default()
+            .with_auditor(audit_every_rebuild())
+            .with_incremental_analyzer(buggy_analyzer);
+        let metrics = MetricsCollector::new();
+
+        let err = edit_and_rebuild(&pipeline, &metrics).err().unwrap().to_string();
+        assert!(err.contains("Incremental audit failed for b.rs at stage 'cfg'"), "{}", err);
+        assert_eq!(metrics.audit_failures(), 1);
+        assert_eq!(metrics.audit_passes(), 0);
+        assert_eq!(metrics.to_json()["audit"]["failures"], 1);
+    }
+
+    #[test]
+    fn test_audit_passes_correct_incremental_analysis() {
+        let pipeline = Pipeline::default().with_auditor(audit_every_rebuild());
+        let metrics = MetricsCollector::new();
+
+        let output = edit_and_rebuild(&pipeline, &metrics).unwrap();
+        assert_eq!(output.rebuilt.len(), 1);
+        assert_eq!(metrics.audit_passes(), 1);
+        assert_eq!(metrics.audit_failures(), 0);
+        assert_eq!(metrics.reparse_count(), 1);
+        assert_eq!(metrics.reuse_count(), 1);
+    }
+
+    #[test]
+    fn test_unsampled_rebuilds_are_not_audited() {
+        let pipeline = Pipeline::default()
+            .with_auditor(Auditor::new(&crate::config::AuditConfig { sample_rate: 0.0 }))
+            .with_incremental_analyzer(buggy_analyzer);
+        let metrics = MetricsCollector::new();
+
+        assert!(edit_and_rebuild(&pipeline, &metrics).is_ok());
+        assert_eq!(metrics.audit_passes() + metrics.audit_failures(), 0);
+    }
+
+    /// Clean a.rs plus broken.rs
+    fn broken_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() { let x = 1; }\n").unwrap();
+        std::fs::write(dir.path().join("broken.rs"), "fn b() { let y = ; }\n").unwrap();
+        dir
+    }
+
+    fn broken_id(output: &PipelineOutput) -> FileId {
+        output.snapshot.files.iter()
+            .find(|(_, meta)| meta.path == Path::new("broken.rs"))
+            .map(|(id, _)| *id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_errors_skipped_by_default() {
+        let dir = broken_repo();
+        let metrics = MetricsCollector::new();
+        let output = Pipeline::default().build(&[dir.path().to_path_buf()], None, &metrics).unwrap();
+
+        let broken = broken_id(&output);
+        let quality = &output.parse_errors[&broken];
+        assert!(!quality.clean);
+        assert_eq!(quality.error_count, quality.error_ranges.len());
+        assert!(output.semantic.get_cfgs(broken).is_none());
+        assert_eq!(output.call_graph.len(), 1);
+        assert_eq!(metrics.to_json()["parse_errors"]["skipped_files"], 1);
+
+        // Same CPG as the repository without the broken file
+        std::fs::remove_file(dir.path().join("broken.rs")).unwrap();
+        let clean = Pipeline::default().run(dir.path()).unwrap();
+        assert!(clean.parse_errors.is_empty());
+        assert_eq!(output.cpg_epoch.cpg().compute_hash(), clean.cpg_epoch.cpg().compute_hash());
+    }
+
+    #[test]
+    fn test_parse_errors_included_best_effort() {
+        let dir = broken_repo();
+        let pipeline = Pipeline::default().with_parse_error_policy(ParseErrorPolicy::IncludeBestEffort);
+        let output = pipeline.run(dir.path()).unwrap();
+
+        let broken = broken_id(&output);
+        assert!(output.parse_errors.contains_key(&broken));
+        assert!(output.semantic.get_cfgs(broken).is_some());
+        assert_eq!(output.parse_error_policy, ParseErrorPolicy::IncludeBestEffort);
+    }
+
+    #[test]
+    fn test_parse_errors_fail() {
+        let dir = broken_repo();
+        let pipeline = Pipeline::default().with_parse_error_policy(ParseErrorPolicy::Fail);
+
+        let err = pipeline.run(dir.path()).err().unwrap().to_string();
+        assert!(err.starts_with("Syntax errors in broken.rs"), "{}", err);
+    }
+
+    #[test]
+    fn test_incremental_keeps_unchanged_parse_errors() {
+        let dir = broken_repo();
+        let pipeline = Pipeline::default();
+        let first = pipeline.run(dir.path()).unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() { let z = 2; }\n").unwrap();
+        let second = pipeline.run_incremental(&first).unwrap();
+
+        assert_eq!(second.parse_errors, first.parse_errors);
+        std::fs::write(dir.path().join("broken.rs"), "fn b() {}\n").unwrap();
+        assert!(pipeline.run_incremental(&second).unwrap().parse_errors.is_empty());
+    }
+
+    fn budgeted_repo() -> TempDir {
+        let dir = temp_repo();
+        std::fs::write(
+            dir.path().join("c.rs"),
+            "fn small() {}\nfn big() { f(); f(); f(); f(); }\nfn tail() { g(); }\n",
+        ).unwrap();
+        dir
+    }
+
+    fn file_id(output: &PipelineOutput, path: &str) -> FileId {
+        output.snapshot.files.iter()
+            .find(|(_, meta)| meta.path == Path::new(path))
+            .map(|(id, _)| *id)
+            .unwrap()
+    }
+
+    fn budgets(max_cfg_nodes_per_function: usize, max_nodes_per_file: usize) -> LimitsConfig {
+        LimitsConfig { max_cfg_nodes_per_function, max_nodes_per_file, ..LimitsConfig::default() }
+    }
+
+    #[test]
+    fn test_function_budget_degrades_deterministically() {
+        let dir = budgeted_repo();
+        let pipeline = Pipeline::default().with_limits(budgets(5, usize::MAX));
+        let metrics = MetricsCollector::new();
+        let first = pipeline.run_with_metrics(dir.path(), &metrics).unwrap();
+        let second = pipeline.run(dir.path()).unwrap();
+
+        let c = file_id(&first, "c.rs");
+        assert_eq!(
+            first.semantic.status(c),
+            SemanticStatus::Degraded { reason: "function `big` exceeds 5 CFG nodes".into() }
+        );
+        let names: Vec<&str> = first.semantic.get_cfgs(c).unwrap().iter().map(|cfg| cfg.name.as_str()).collect();
+        assert_eq!(names, ["small", "tail"]);
+        assert_eq!(metrics.degraded_files(), 1);
+        assert_eq!(metrics.to_json()["limits"]["degraded_files"], 1);
+
+        assert_eq!(second.semantic.status(c), first.semantic.status(c));
+        assert_eq!(second.semantic.fingerprints(), first.semantic.fingerprints());
+        assert_eq!(second.cpg_epoch.cpg().compute_hash(), first.cpg_epoch.cpg().compute_hash());
+        let report = crate::report::ReportBuilder::from_output(&first).build();
+        assert_eq!(report.iter().filter(|file| !file.status.is_complete()).count(), 1);
+    }
+
+    #[test]
+    fn test_file_budget_keeps_leading_functions() {
+        let dir = budgeted_repo();
+        let pipeline = Pipeline::default().with_limits(budgets(usize::MAX, 10));
+        let output = pipeline.run(dir.path()).unwrap();
+
+        let c = file_id(&output, "c.rs");
+        let SemanticStatus::Degraded { reason } = output.semantic.status(c) else {
+            panic!("c.rs not degraded");
+        };
+        assert_eq!(reason, "file exceeds 10 CFG nodes and DFG values; kept the first 2 function(s)");
+        assert_eq!(output.semantic.get_cfgs(c).unwrap().len(), 2);
+        assert!(output.semantic.status(file_id(&output, "a.rs")).is_complete());
+        assert_eq!(
+            pipeline.run(dir.path()).unwrap().cpg_epoch.cpg().compute_hash(),
+            output.cpg_epoch.cpg().compute_hash()
+        );
+    }
+
+    #[test]
+    fn test_total_budget_fails_closed_unless_allowed() {
+        let dir = temp_repo();
+        let limits = LimitsConfig { max_total_cpg_nodes: 3, ..LimitsConfig::default() };
+        let err = Pipeline::default().with_limits(limits).run(dir.path()).err().unwrap().to_string();
+        assert!(err.contains("over [limits] max_total_cpg_nodes = 3"), "{}", err);
+
+        let metrics = MetricsCollector::new();
+        let pipeline = Pipeline::default().with_limits(LimitsConfig { allow_degraded_total: true, ..limits });
+        let output = pipeline.run_with_metrics(dir.path(), &metrics).unwrap();
+        assert!(output.cpg_epoch.cpg().nodes.len() > 3);
+        assert_eq!(metrics.cpg_budget_overruns(), 1);
+    }
+}
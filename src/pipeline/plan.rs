@@ -0,0 +1,195 @@
+//! Ingest plans - what a run would do, without doing it
+//!
+//! `Pipeline::plan` scans like a run (same backend, overlays, extensions
+//! and policy files) and stops there: nothing is opened beyond the scan's
+//! hashing, parsed, analyzed or fused. The plan lists the files a run
+//! would parse, every walked path it would not (with the reason), their
+//! bytes and the parse load per language. Given the repository snapshot of
+//! a previous run, `ChangeDetector` also says which files changed since.
+//!
+//! **Deterministic**: Every list is sorted by normalized path.
+
+use crate::change::{ChangeDetector, FileChange};
+use crate::metrics::MetricsCollector;
+use crate::pipeline::Pipeline;
+use crate::repo::normalize_path;
+use crate::storage::UNKNOWN;
+use crate::types::{FileId, RepoSnapshot};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// What an ingest of some root(s) would scan, skip and parse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IngestPlan {
+    /// Hash of the repository snapshot the run would build from
+    pub snapshot_hash: String,
+
+    /// Files in the snapshot (parsed, or hashed only by policy)
+    pub scanned: usize,
+
+    /// Bytes of the scanned files
+    pub scanned_bytes: u64,
+
+    /// Files the run would parse and analyze
+    pub parsed: Vec<PlannedFile>,
+
+    /// Walked paths the run would not parse, with the reason
+    pub skipped: Vec<SkippedFile>,
+
+    /// Files and bytes to parse, per language
+    pub languages: BTreeMap<String, ParseLoad>,
+
+    /// Changes since a previous snapshot (None without one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<PlannedChanges>,
+}
+
+/// A file a run would parse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedFile {
+    /// Normalized relative path
+    pub path: String,
+    pub file_id: FileId,
+    pub language: String,
+    pub size: u64,
+}
+
+/// A walked path a run would not parse
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    /// Normalized relative path
+    pub path: String,
+
+    #[serde(flatten)]
+    pub reason: SkipReason,
+}
+
+/// Why a path is not parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Pruned by `.vcrignore` (a directory stands for its contents)
+    Ignored,
+
+    /// Not a source file of an ingested language; never scanned
+    Extension,
+
+    /// Scanned, but `.vcr.toml` sets `skip_semantics`
+    SkipSemantics,
+
+    /// Scanned, but larger than `.vcr.toml`'s `max_file_size`
+    TooLarge { size: u64, max_file_size: u64 },
+
+    /// Scanned, but no parser for its language
+    UnsupportedLanguage,
+}
+
+/// Parse load of one language
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseLoad {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Files changed since a previous snapshot, by normalized path
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedChanges {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub deleted: Vec<String>,
+    pub renamed: Vec<PlannedRename>,
+    pub unchanged: usize,
+}
+
+/// A file moved without a content change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedRename {
+    pub from: String,
+    pub to: String,
+}
+
+impl Pipeline {
+    /// Plan an ingest of `root` (scan only)
+    pub fn plan(&self, root: &Path) -> Result<IngestPlan> {
+        self.plan_workspace(&[root.to_path_buf()], None, &mut MetricsCollector::new())
+    }
+
+    /// Plan an ingest of several roots, with the changes since `previous`
+    /// if given, recording the scan time in `metrics`
+    pub fn plan_workspace(
+        &self,
+        roots: &[PathBuf],
+        previous: Option<&RepoSnapshot>,
+        metrics: &mut MetricsCollector,
+    ) -> Result<IngestPlan> {
+        let started = Instant::now();
+        let (snapshot, excluded) = self.scanner(roots)?.scan_with_excluded()?;
+        metrics.record_scan_duration(started.elapsed());
+
+        let mut parsed = Vec::new();
+        let mut skipped: Vec<SkippedFile> = excluded.ignored.iter()
+            .map(|path| (path, SkipReason::Ignored))
+            .chain(excluded.unwanted.iter().map(|path| (path, SkipReason::Extension)))
+            .map(|(path, reason)| SkippedFile { path: normalize_path(path), reason })
+            .collect();
+        let mut languages: BTreeMap<String, ParseLoad> = BTreeMap::new();
+        for (file_id, meta) in &snapshot.files {
+            let path = normalize_path(&meta.path);
+            let reason = match (meta.policy.skip_semantics, meta.policy.max_file_size, meta.language) {
+                (true, _, _) => Some(SkipReason::SkipSemantics),
+                (_, Some(max_file_size), _) if meta.size > max_file_size => {
+                    Some(SkipReason::TooLarge { size: meta.size, max_file_size })
+                }
+                (_, _, None) => Some(SkipReason::UnsupportedLanguage),
+                _ => None,
+            };
+            if let Some(reason) = reason {
+                skipped.push(SkippedFile { path, reason });
+                continue;
+            }
+
+            let language = meta.language.map_or(UNKNOWN, |l| l.name()).to_string();
+            let load = languages.entry(language.clone()).or_default();
+            load.files += 1;
+            load.bytes += meta.size;
+            parsed.push(PlannedFile { path, file_id: *file_id, language, size: meta.size });
+        }
+        parsed.sort_by(|a, b| a.path.cmp(&b.path));
+        skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let changes = previous.map(|previous| {
+            let path = |snapshot: &RepoSnapshot, id: &FileId| normalize_path(&snapshot.files[id].path);
+            let mut changes = PlannedChanges::default();
+            for change in ChangeDetector::new(previous.clone()).detect(&snapshot) {
+                match change {
+                    FileChange::Added(id) => changes.added.push(path(&snapshot, &id)),
+                    FileChange::Modified(id) => changes.modified.push(path(&snapshot, &id)),
+                    FileChange::Deleted(id) => changes.deleted.push(path(previous, &id)),
+                    FileChange::Unchanged(_) => changes.unchanged += 1,
+                    FileChange::Renamed { from, to } => changes.renamed.push(PlannedRename {
+                        from: path(previous, &from),
+                        to: path(&snapshot, &to),
+                    }),
+                }
+            }
+            changes.added.sort();
+            changes.modified.sort();
+            changes.deleted.sort();
+            changes.renamed.sort_by(|a, b| a.to.cmp(&b.to));
+            changes
+        });
+
+        Ok(IngestPlan {
+            snapshot_hash: snapshot.snapshot_hash.clone(),
+            scanned: snapshot.files.len(),
+            scanned_bytes: snapshot.files.values().map(|meta| meta.size).sum(),
+            parsed,
+            skipped,
+            languages,
+            changes,
+        })
+    }
+}
@@ -0,0 +1,252 @@
+//! Query result cache (Step 3.6)
+//!
+//! Results are keyed by (cpg_hash, canonical query hash). The same query
+//! against an unchanged CPG is served from memory.
+//!
+//! **Fail closed**: In paranoid mode every hit is recomputed and compared.
+//! Divergence means the cache or the engine is nondeterministic, so we crash.
+
+use crate::query::dsl::QuerySpec;
+use crate::query::engine::QueryResult;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+
+/// Cache key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Hash of the CPG the query ran against
+    pub cpg_hash: String,
+
+    /// Hash of the canonical query text
+    pub query_hash: String,
+}
+
+impl CacheKey {
+    /// Build a key for a query against a CPG
+    pub fn new(cpg_hash: &str, spec: &QuerySpec) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(canonical_query(spec).as_bytes());
+
+        Self {
+            cpg_hash: cpg_hash.to_string(),
+            query_hash: format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+/// Canonical query text
+///
+/// Paging fields are dropped (they only select a slice of the stored
+/// result). Object keys are emitted in sorted order.
+pub fn canonical_query(spec: &QuerySpec) -> String {
+    let value = serde_json::json!({
+        "pipeline": spec.pipeline,
+        "order_by": spec.options.order_by,
+    });
+
+    // serde_json::Map is ordered by key, so this is stable
+    value.to_string()
+}
+
+/// Whether a lookup was served from cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOutcome {
+    /// Served from cache
+    Hit,
+
+    /// Computed and inserted
+    Miss,
+}
+
+/// LRU-bounded result cache
+///
+/// Hits and misses are not counted here: callers record the returned
+/// `CacheOutcome` in `MetricsCollector`.
+pub struct ResultCache {
+    /// Maximum entries (0 = disabled)
+    capacity: usize,
+
+    /// Recompute hits and compare
+    paranoid: bool,
+
+    /// Cached results
+    entries: HashMap<CacheKey, QueryResult>,
+
+    /// Recency order (front = least recently used)
+    recency: VecDeque<CacheKey>,
+}
+
+impl ResultCache {
+    /// Create a cache with the given capacity
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            paranoid: false,
+            entries: HashMap::new(),
+            recency: VecDeque::new(),
+        }
+    }
+
+    /// Enable paranoid mode
+    pub fn with_paranoid(mut self, paranoid: bool) -> Self {
+        self.paranoid = paranoid;
+        self
+    }
+
+    /// Get a cached result or compute and insert it
+    ///
+    /// **Panics** in paranoid mode if a hit differs from recomputation.
+    pub fn get_or_compute<F>(&mut self, key: CacheKey, compute: F) -> Result<(QueryResult, CacheOutcome)>
+    where
+        F: FnOnce() -> Result<QueryResult>,
+    {
+        if let Some(cached) = self.entries.get(&key).cloned() {
+            if self.paranoid {
+                let fresh = compute()?;
+                let cached_hash = hash_result(&cached);
+                let fresh_hash = hash_result(&fresh);
+                if cached_hash != fresh_hash {
+                    panic!(
+                        "Query cache divergence: cached {} != recomputed {} (query {})",
+                        cached_hash, fresh_hash, key.query_hash
+                    );
+                }
+            }
+
+            self.touch(&key);
+            return Ok((cached, CacheOutcome::Hit));
+        }
+
+        let result = compute()?;
+        self.insert(key, result.clone());
+        Ok((result, CacheOutcome::Miss))
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Insert an entry, evicting the least recently used if full
+    fn insert(&mut self, key: CacheKey, result: QueryResult) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        while self.entries.len() >= self.capacity {
+            match self.recency.pop_front() {
+                Some(evicted) => {
+                    self.entries.remove(&evicted);
+                }
+                None => break,
+            }
+        }
+
+        self.recency.push_back(key.clone());
+        self.entries.insert(key, result);
+    }
+
+    /// Mark a key as most recently used
+    fn touch(&mut self, key: &CacheKey) {
+        if let Some(pos) = self.recency.iter().position(|k| k == key) {
+            self.recency.remove(pos);
+        }
+        self.recency.push_back(key.clone());
+    }
+}
+
+/// Hash a result vector
+fn hash_result(result: &QueryResult) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(result.len().to_le_bytes());
+    for id in result {
+        hasher.update(id.0.to_le_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpg::model::{CPGNodeId, CPGNodeKind};
+    use crate::query::dsl::{OrderKey, QueryOptions, QueryStage};
+
+    fn spec() -> QuerySpec {
+        QuerySpec::new(vec![QueryStage::Find(CPGNodeKind::Function)])
+    }
+
+    #[test]
+    fn test_canonical_query_ignores_formatting_and_paging() {
+        let a = QuerySpec::from_json(r#"{"pipeline":[{"find":"Function"}],"limit":5}"#).unwrap();
+        let b = QuerySpec::from_json(r#"{ "offset": 10, "pipeline": [ { "find": "Function" } ] }"#).unwrap();
+
+        assert_eq!(canonical_query(&a), canonical_query(&b));
+        assert_eq!(CacheKey::new("h", &a), CacheKey::new("h", &b));
+    }
+
+    #[test]
+    fn test_key_depends_on_order_and_cpg() {
+        let by_label = spec().with_options(QueryOptions { order_by: OrderKey::Label, ..Default::default() });
+
+        assert_ne!(CacheKey::new("h", &spec()), CacheKey::new("h", &by_label));
+        assert_ne!(CacheKey::new("h1", &spec()), CacheKey::new("h2", &spec()));
+    }
+
+    #[test]
+    fn test_hit_after_miss() {
+        let mut cache = ResultCache::new(4);
+        let key = CacheKey::new("h", &spec());
+
+        let (first, outcome) = cache.get_or_compute(key.clone(), || Ok(vec![CPGNodeId(1)])).unwrap();
+        assert_eq!(outcome, CacheOutcome::Miss);
+
+        let (second, outcome) = cache.get_or_compute(key, || panic!("must not recompute")).unwrap();
+        assert_eq!(outcome, CacheOutcome::Hit);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = ResultCache::new(2);
+        let keys: Vec<_> = ["a", "b", "c"].iter().map(|h| CacheKey::new(h, &spec())).collect();
+
+        cache.get_or_compute(keys[0].clone(), || Ok(vec![])).unwrap();
+        cache.get_or_compute(keys[1].clone(), || Ok(vec![])).unwrap();
+        // Touch "a" so "b" becomes least recently used
+        cache.get_or_compute(keys[0].clone(), || Ok(vec![])).unwrap();
+        cache.get_or_compute(keys[2].clone(), || Ok(vec![])).unwrap();
+
+        assert_eq!(cache.len(), 2);
+        let (_, outcome) = cache.get_or_compute(keys[0].clone(), || Ok(vec![])).unwrap();
+        assert_eq!(outcome, CacheOutcome::Hit);
+        let (_, outcome) = cache.get_or_compute(keys[1].clone(), || Ok(vec![])).unwrap();
+        assert_eq!(outcome, CacheOutcome::Miss);
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_cache() {
+        let mut cache = ResultCache::new(0);
+        let key = CacheKey::new("h", &spec());
+
+        cache.get_or_compute(key.clone(), || Ok(vec![])).unwrap();
+        let (_, outcome) = cache.get_or_compute(key, || Ok(vec![])).unwrap();
+        assert_eq!(outcome, CacheOutcome::Miss);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Query cache divergence")]
+    fn test_paranoid_mode_crashes_on_divergence() {
+        let mut cache = ResultCache::new(4).with_paranoid(true);
+        let key = CacheKey::new("h", &spec());
+
+        cache.get_or_compute(key.clone(), || Ok(vec![CPGNodeId(1)])).unwrap();
+        let _ = cache.get_or_compute(key, || Ok(vec![CPGNodeId(2)]));
+    }
+}
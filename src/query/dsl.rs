@@ -0,0 +1,500 @@
+//! JSON query DSL (Step 3.6)
+//!
+//! A query is a linear pipeline of stages. Each stage consumes the
+//! previous stage's node set and produces a new one.
+//!
+//! ```json
+//! {
+//!   "pipeline": [{"find": "Function"}, {"follow": "ControlFlow"}],
+//!   "limit": 50,
+//!   "offset": 0,
+//!   "order_by": "source_range"
+//! }
+//! ```
+//!
+//! `union` and `difference` take a nested pipeline that runs from an empty
+//! set; its result is combined with the current set.
+//!
+//! `language` scopes the set to the files of one language, like `in_file`
+//! does to a path: `[{"language": "rust"}, {"filter": "Function"}]`.
+//!
+//! `function` and `function_matches` look functions up by name:
+//! `{"function": "handle_login"}`, `{"function_matches": "handle_*"}`.
+//!
+//! `may_alias` takes two DfgValue node IDs, `{"may_alias": [12, 40]}`, and
+//! yields the DfgValue nodes both may point to.
+//!
+//! `parameter` takes a position and yields the parameter values of the
+//! current set's functions at that position (`self` is 0):
+//! `[{"function_matches": "handle_*"}, {"parameter": 0}]`.
+//!
+//! `path` follows a path pattern from every node of the current set and
+//! yields the end nodes: `{"path": [{"edge": "DataFlow", "min": 1, "max":
+//! 10}, {"edge": "Calls", "node": "Function"}]}`. Each step takes `min..=max`
+//! edges of one kind (both default to 1, `max` at most `MAX_PATH_REPEAT`);
+//! `node` constrains the nodes the step enters.
+//!
+//! `count` and `group_by` are aggregate stages: they may only end the
+//! top-level pipeline, and the query then yields a count instead of a node
+//! set. `{"count": true}` counts the set; `{"group_by": "kind"}` and
+//! `{"group_by": "file"}` count it per node kind or per file.
+//!
+//! A taint query (`TaintQuery`) selects sources, sinks and optional
+//! sanitizers with one node-set query each:
+//! `{"sources": {"pipeline": [...]}, "sinks": {"pipeline": [...]}}`.
+
+use crate::cpg::model::{CPGEdgeKind, CPGNodeKind};
+use crate::query::primitives::{EdgeStep, MAX_PATH_REPEAT, MAX_PATH_STEPS};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// A single pipeline stage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryStage {
+    /// Replace the current set with all nodes of a kind
+    Find(CPGNodeKind),
+
+    /// Follow outgoing edges of a kind from the current set
+    Follow(CPGEdgeKind),
+
+    /// Follow incoming edges of a kind back to their sources
+    FollowReverse(CPGEdgeKind),
+
+    /// Keep only nodes of a kind
+    Filter(CPGNodeKind),
+
+    /// Add the nodes of a sub-pipeline (current set first)
+    Union(Vec<QueryStage>),
+
+    /// Remove the nodes of a sub-pipeline
+    Difference(Vec<QueryStage>),
+
+    /// Keep only nodes whose source range contains a byte offset in a file.
+    /// As the first stage, selects every such node.
+    At {
+        file: String,
+        offset: usize,
+    },
+
+    /// Keep only nodes whose source range overlaps `start..end` in a file.
+    /// As the first stage, selects every such node.
+    Overlapping {
+        file: String,
+        start: usize,
+        end: usize,
+    },
+
+    /// Keep only nodes from a file or directory (repository-relative path).
+    /// As the first stage, selects every node in the matched files.
+    InFile(String),
+
+    /// Keep only nodes from files of a language (`rust`, as labelled on
+    /// File nodes). As the first stage, selects every node in those files.
+    Language(String),
+
+    /// Keep only Function nodes with this name (every file's).
+    /// As the first stage, selects them.
+    Function(String),
+
+    /// Keep only Function nodes whose name matches a pattern (`^`, `$`,
+    /// `.`, `*`; see `query::pattern`). As the first stage, selects them.
+    FunctionMatches(String),
+
+    /// Keep only the DfgValue nodes that both values (DfgValue node IDs)
+    /// may point to; empty when they cannot alias. Fails if either points-to
+    /// set is unknown. As the first stage, selects them.
+    MayAlias([u64; 2]),
+
+    /// Replace the current set with the DfgValue nodes of its Function
+    /// nodes' parameters at this position (declaration order, `self` first).
+    /// As the first stage, takes every function's.
+    Parameter(usize),
+
+    /// Replace the current set with the end nodes of the paths from it that
+    /// match a pattern (see `QueryPrimitives::match_path`)
+    Path(Vec<EdgeStep>),
+
+    /// Aggregate: count the current set. Must be `true` and the last stage.
+    Count(bool),
+
+    /// Aggregate: count the current set per group. Must be the last stage.
+    GroupBy(GroupKey),
+}
+
+/// Grouping key of a `group_by` stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupKey {
+    /// Node kind (`Function`, `CfgNode`, ...)
+    Kind,
+
+    /// Repository-relative path of the file the node belongs to
+    File,
+}
+
+/// Terminal aggregate of a query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    /// Number of nodes
+    Count,
+
+    /// Number of nodes per group
+    GroupBy(GroupKey),
+}
+
+impl QueryStage {
+    /// Stage name as written in the DSL
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueryStage::Find(_) => "find",
+            QueryStage::Follow(_) => "follow",
+            QueryStage::FollowReverse(_) => "follow_reverse",
+            QueryStage::Filter(_) => "filter",
+            QueryStage::Union(_) => "union",
+            QueryStage::Difference(_) => "difference",
+            QueryStage::At { .. } => "at",
+            QueryStage::Overlapping { .. } => "overlapping",
+            QueryStage::InFile(_) => "in_file",
+            QueryStage::Language(_) => "language",
+            QueryStage::Function(_) => "function",
+            QueryStage::FunctionMatches(_) => "function_matches",
+            QueryStage::MayAlias(_) => "may_alias",
+            QueryStage::Parameter(_) => "parameter",
+            QueryStage::Path(_) => "path",
+            QueryStage::Count(_) => "count",
+            QueryStage::GroupBy(_) => "group_by",
+        }
+    }
+
+    /// Aggregate this stage computes, if it is an aggregate stage
+    pub fn aggregation(&self) -> Option<Aggregation> {
+        match self {
+            QueryStage::Count(_) => Some(Aggregation::Count),
+            QueryStage::GroupBy(key) => Some(Aggregation::GroupBy(*key)),
+            _ => None,
+        }
+    }
+}
+
+/// Result ordering key
+///
+/// Every key falls back to NodeId so the order is total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderKey {
+    /// Ascending node ID
+    #[default]
+    NodeId,
+
+    /// Ascending (start, end) of the node's source range
+    SourceRange,
+
+    /// Ascending label (unlabelled nodes first)
+    Label,
+}
+
+/// Paging and ordering options applied after execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct QueryOptions {
+    /// Maximum number of results per page (None = all)
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Number of results to skip
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Ordering key
+    #[serde(default)]
+    pub order_by: OrderKey,
+}
+
+/// Parsed query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuerySpec {
+    /// Stages executed in order
+    pub pipeline: Vec<QueryStage>,
+
+    /// Paging and ordering
+    #[serde(flatten)]
+    pub options: QueryOptions,
+}
+
+impl QuerySpec {
+    /// Create a query from stages with default options
+    pub fn new(pipeline: Vec<QueryStage>) -> Self {
+        Self {
+            pipeline,
+            options: QueryOptions::default(),
+        }
+    }
+
+    /// Set paging and ordering options
+    pub fn with_options(mut self, options: QueryOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parse a query from JSON text
+    pub fn from_json(text: &str) -> Result<Self> {
+        let spec: Self = serde_json::from_str(text).context("Failed to parse query")?;
+        spec.split_aggregate()?;
+        Ok(spec)
+    }
+
+    /// Split off the terminal aggregate stage, if any
+    ///
+    /// Errors if an aggregate stage is not last, appears in a nested
+    /// pipeline, or is `{"count": false}`, and if a path pattern is out of
+    /// bounds.
+    pub fn split_aggregate(&self) -> Result<(&[QueryStage], Option<Aggregation>)> {
+        let (stages, aggregation) = match self.pipeline.split_last() {
+            Some((last, rest)) if last.aggregation().is_some() => (rest, last.aggregation()),
+            _ => (self.pipeline.as_slice(), None),
+        };
+        if self.pipeline.last() == Some(&QueryStage::Count(false)) {
+            bail!("count must be true");
+        }
+        check_no_aggregate(stages)?;
+        Ok((stages, aggregation))
+    }
+}
+
+/// Reject aggregate stages anywhere in `stages` (including nested pipelines)
+/// and path patterns `match_path` would cap
+fn check_no_aggregate(stages: &[QueryStage]) -> Result<()> {
+    for stage in stages {
+        match stage {
+            QueryStage::Count(_) | QueryStage::GroupBy(_) => {
+                bail!("{} must be the last stage of the top-level pipeline", stage.name())
+            }
+            QueryStage::Union(sub) | QueryStage::Difference(sub) => check_no_aggregate(sub)?,
+            QueryStage::Path(pattern) => check_path(pattern)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reject empty or oversized path patterns and empty step ranges
+fn check_path(pattern: &[EdgeStep]) -> Result<()> {
+    if pattern.is_empty() || pattern.len() > MAX_PATH_STEPS {
+        bail!("path must have 1 to {} steps, not {}", MAX_PATH_STEPS, pattern.len());
+    }
+    for (index, step) in pattern.iter().enumerate() {
+        if step.max == 0 || step.min > step.max || step.max > MAX_PATH_REPEAT {
+            bail!(
+                "path step {}: need min <= max and 1 <= max <= {}, not min {} max {}",
+                index, MAX_PATH_REPEAT, step.min, step.max,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Taint query: node sets selected by ordinary queries
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaintQuery {
+    /// Taint starts at these nodes
+    pub sources: QuerySpec,
+
+    /// Paths ending at these nodes are reported
+    pub sinks: QuerySpec,
+
+    /// Paths through these nodes are sanitized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sanitizers: Option<QuerySpec>,
+
+    /// Propagation depth bound
+    #[serde(default = "default_taint_depth")]
+    pub max_depth: usize,
+}
+
+fn default_taint_depth() -> usize {
+    crate::analysis::taint::MAX_TAINT_DEPTH
+}
+
+impl TaintQuery {
+    /// Parse a taint query from JSON text
+    ///
+    /// Errors if any of its queries aggregates: each must yield a node set.
+    pub fn from_json(text: &str) -> Result<Self> {
+        let query: Self = serde_json::from_str(text).context("Failed to parse taint query")?;
+        let roles = [
+            ("sources", Some(&query.sources)),
+            ("sinks", Some(&query.sinks)),
+            ("sanitizers", query.sanitizers.as_ref()),
+        ];
+        for (role, spec) in roles {
+            let Some(spec) = spec else { continue };
+            if spec.split_aggregate().with_context(|| format!("Invalid {} query", role))?.1.is_some() {
+                bail!("{} query must yield nodes, not an aggregate", role);
+            }
+        }
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pipeline() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"find": "Function"}, {"follow": "ControlFlow"}, {"filter": "CfgNode"}]}"#,
+        ).unwrap();
+
+        assert_eq!(spec.pipeline, vec![
+            QueryStage::Find(CPGNodeKind::Function),
+            QueryStage::Follow(CPGEdgeKind::ControlFlow),
+            QueryStage::Filter(CPGNodeKind::CfgNode),
+        ]);
+        assert_eq!(spec.options, QueryOptions::default());
+    }
+
+    #[test]
+    fn test_parse_in_file() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"in_file": "src/handlers/login.rs"}, {"filter": "Function"}]}"#,
+        ).unwrap();
+
+        assert_eq!(spec.pipeline[0], QueryStage::InFile("src/handlers/login.rs".to_string()));
+    }
+
+    #[test]
+    fn test_parse_language() {
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"language": "rust"}, {"in_file": "src"}]}"#).unwrap();
+
+        assert_eq!(spec.pipeline[0], QueryStage::Language("rust".to_string()));
+        assert_eq!(spec.pipeline[0].name(), "language");
+    }
+
+    #[test]
+    fn test_parse_set_operations() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"find": "Function"}, {"difference": [{"find": "CfgNode"}, {"follow": "Calls"}]}]}"#,
+        ).unwrap();
+
+        assert_eq!(spec.pipeline[1], QueryStage::Difference(vec![
+            QueryStage::Find(CPGNodeKind::CfgNode),
+            QueryStage::Follow(CPGEdgeKind::Calls),
+        ]));
+    }
+
+    #[test]
+    fn test_parse_source_position() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"at": {"file": "src/lib.rs", "offset": 120}}, {"overlapping": {"file": "src/lib.rs", "start": 1, "end": 4}}]}"#,
+        ).unwrap();
+
+        assert_eq!(spec.pipeline, vec![
+            QueryStage::At { file: "src/lib.rs".to_string(), offset: 120 },
+            QueryStage::Overlapping { file: "src/lib.rs".to_string(), start: 1, end: 4 },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_follow_reverse() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"find": "Function"}, {"follow_reverse": "Calls"}]}"#,
+        ).unwrap();
+
+        assert_eq!(spec.pipeline[1], QueryStage::FollowReverse(CPGEdgeKind::Calls));
+    }
+
+    #[test]
+    fn test_parse_paging_fields() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"find": "Function"}], "limit": 10, "offset": 20, "order_by": "label"}"#,
+        ).unwrap();
+
+        assert_eq!(spec.options.limit, Some(10));
+        assert_eq!(spec.options.offset, 20);
+        assert_eq!(spec.options.order_by, OrderKey::Label);
+    }
+
+    #[test]
+    fn test_parse_function_lookup() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"function": "handle_login"}, {"union": [{"function_matches": "handle_*"}]}]}"#,
+        ).unwrap();
+
+        assert_eq!(spec.pipeline, vec![
+            QueryStage::Function("handle_login".to_string()),
+            QueryStage::Union(vec![QueryStage::FunctionMatches("handle_*".to_string())]),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_may_alias() {
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"may_alias": [12, 40]}]}"#).unwrap();
+
+        assert_eq!(spec.pipeline, vec![QueryStage::MayAlias([12, 40])]);
+        assert!(QuerySpec::from_json(r#"{"pipeline": [{"may_alias": [12]}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_path() {
+        let spec = QuerySpec::from_json(
+            r#"{"pipeline": [{"path": [{"edge": "DataFlow", "min": 1, "max": 10}, {"edge": "Calls", "node": "Function"}]}]}"#,
+        ).unwrap();
+
+        assert_eq!(spec.pipeline, vec![QueryStage::Path(vec![
+            EdgeStep::new(CPGEdgeKind::DataFlow).repeat(1, 10),
+            EdgeStep::new(CPGEdgeKind::Calls).with_node(CPGNodeKind::Function),
+        ])]);
+
+        for invalid in [
+            r#"{"pipeline": [{"path": []}]}"#,
+            r#"{"pipeline": [{"path": [{"edge": "Calls", "min": 2}]}]}"#,
+            r#"{"pipeline": [{"path": [{"edge": "Calls", "max": 1000}]}]}"#,
+            r#"{"pipeline": [{"union": [{"path": [{"edge": "Calls", "max": 0, "min": 0}]}]}]}"#,
+            r#"{"pipeline": [{"path": [{"edge": "Calls", "depth": 3}]}]}"#,
+        ] {
+            assert!(QuerySpec::from_json(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_parse_aggregates() {
+        let count = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}, {"count": true}]}"#).unwrap();
+        let (stages, aggregation) = count.split_aggregate().unwrap();
+        assert_eq!(stages, &[QueryStage::Find(CPGNodeKind::Function)]);
+        assert_eq!(aggregation, Some(Aggregation::Count));
+
+        let by_file = QuerySpec::from_json(r#"{"pipeline": [{"group_by": "file"}]}"#).unwrap();
+        assert_eq!(by_file.split_aggregate().unwrap(), (&[][..], Some(Aggregation::GroupBy(GroupKey::File))));
+
+        for misplaced in [
+            r#"{"pipeline": [{"count": true}, {"find": "Function"}]}"#,
+            r#"{"pipeline": [{"find": "Function"}, {"union": [{"group_by": "kind"}]}]}"#,
+            r#"{"pipeline": [{"find": "Function"}, {"count": false}]}"#,
+            r#"{"pipeline": [{"group_by": "label"}]}"#,
+        ] {
+            assert!(QuerySpec::from_json(misplaced).is_err(), "{}", misplaced);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_stage() {
+        assert!(QuerySpec::from_json(r#"{"pipeline": [{"explode": true}]}"#).is_err());
+    }
+
+    #[test]
+    fn test_taint_query_rejects_aggregates() {
+        let query = TaintQuery::from_json(
+            r#"{"sources": {"pipeline": [{"function": "read"}]}, "sinks": {"pipeline": [{"find": "DfgValue"}]}}"#,
+        ).unwrap();
+        assert_eq!(query.max_depth, crate::analysis::taint::MAX_TAINT_DEPTH);
+        assert!(query.sanitizers.is_none());
+
+        let err = TaintQuery::from_json(
+            r#"{"sources": {"pipeline": []}, "sinks": {"pipeline": [{"count": true}]}}"#,
+        ).unwrap_err();
+        assert_eq!(err.to_string(), "sinks query must yield nodes, not an aggregate");
+    }
+}
@@ -1,18 +1,1781 @@
 //! Query engine (Step 3.6)
 //!
 //! Deterministic query execution
+//!
+//! Each DSL stage is compiled to a single-task execution stage and run
+//! through the scheduler. Results are ordered by the requested key before
+//! being stored, so every page fetched from a stored result is a slice of
+//! one fixed order.
+//!
+//! `explain` runs a query the same way and also reports each stage's
+//! operator, estimated and actual cardinality, and timing (see
+//! `query::explain`).
+//!
+//! A query ending in an aggregate stage (`count`, `group_by`) is folded
+//! into an `Aggregate` instead: the node set is counted where the pipeline
+//! leaves it, never ordered or stored. The stored result keeps what was
+//! counted so it can still be described.
+//!
+//! A `CancellationToken` set with `set_cancellation` is checked by every
+//! stage; an interrupted query fails with `execution::Interrupted` (as the
+//! root cause of the returned error) and stores nothing.
+//!
+//! `may_alias` stages share one points-to map per CPG hash, computed on
+//! first use. With `with_points_to_store`, maps are loaded from and saved to
+//! a `SnapshotStore` next to the snapshot of the same CPG; with
+//! `with_incremental_points_to`, a map for a new hash is updated from the
+//! previous one (see `PointerAnalysis::update_from_roots`) instead of being
+//! solved from scratch.
+//!
+//! Scoped queries run against a `FrozenCPGEpoch`. Unless disabled with
+//! `with_epoch_verification(false)`, debug builds rehash the epoch before
+//! serving each one and panic if it no longer matches its stored hash.
+//!
+//! `validate_query` checks a JSON query before it is parsed or planned and
+//! reports every problem with a code, the stage index, its JSON path and a
+//! one-line fix (`vcr query --check`).
 
-use crate::cpg::model::CPGNodeId;
+use crate::cpg::index::CPGIndices;
+use crate::analysis::{AliasResult, PointerAnalysis};
+use crate::cpg::model::{CPGEdgeKind, CPGNodeId, CPGNodeKind, CPGStats, OriginRef, CPG};
+use crate::cpg::FrozenCPGEpoch;
+use crate::execution::{
+    CancellationToken, DeterministicOrder, ExecutionPlan, FragmentOutput, Interrupted, PathTable, Scheduler, Stage,
+    Task, TaskId, WorkFragment,
+};
+use crate::metrics::MetricsCollector;
+use crate::optimizer::QueryCost;
+use crate::query::dsl::{Aggregation, GroupKey, OrderKey, QueryOptions, QuerySpec, QueryStage};
+use crate::query::explain::{PlanExplanation, StageExplanation};
+use crate::query::primitives::{use_sorted_path, QueryPrimitives, MAX_PATH_REPEAT, MAX_PATH_STEPS};
+use crate::query::pattern::NamePattern;
+use crate::query::scope::FileScope;
+use crate::repo::normalize_path;
+use crate::storage::{SnapshotId, SnapshotStore};
+use crate::types::ByteRange;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
 
 /// Query result
 pub type QueryResult = Vec<CPGNodeId>;
 
-/// Query engine (to be expanded)
-pub struct QueryEngine;
+/// Stored query result ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ResultId(pub u64);
+
+/// A fully ordered, stored query result
+#[derive(Debug, Clone)]
+pub struct StoredResult {
+    /// All result nodes, in `order_by` order
+    pub nodes: QueryResult,
+
+    /// Key the nodes are ordered by
+    pub order_by: OrderKey,
+
+    /// Path table, for results of path fragments (nodes keep path order)
+    pub paths: Option<PathTable>,
+
+    /// Aggregate, for results of aggregate queries (`nodes` is empty)
+    pub aggregate: Option<StoredAggregate>,
+
+    /// Snapshot whose CPG answered, for queries run against a stored
+    /// snapshot rather than a live build (see `tag_snapshot`)
+    pub snapshot: Option<SnapshotId>,
+}
+
+/// Value of an aggregate query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    /// Number of nodes
+    Count(usize),
+
+    /// Number of nodes per group, ascending by group name
+    Groups(BTreeMap<String, usize>),
+}
+
+impl Aggregate {
+    /// Rows in the aggregate (1 for a count)
+    pub fn rows(&self) -> usize {
+        match self {
+            Aggregate::Count(_) => 1,
+            Aggregate::Groups(groups) => groups.len(),
+        }
+    }
+}
+
+/// An aggregate together with what it counted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredAggregate {
+    /// Stages whose node set was counted
+    pub stages: Vec<QueryStage>,
+
+    /// Terminal aggregate stage
+    pub aggregation: Aggregation,
+
+    /// Size of the counted node set
+    pub counted: usize,
+
+    /// Result of the fold
+    pub value: Aggregate,
+}
+
+impl StoredAggregate {
+    /// One-line description, e.g. `group_by kind over find → filter: 12 nodes in 2 groups`
+    pub fn describe(&self) -> String {
+        let stages: Vec<&str> = self.stages.iter().map(QueryStage::name).collect();
+        let over = if stages.is_empty() { "all nodes".to_string() } else { stages.join(" → ") };
+        match (&self.aggregation, &self.value) {
+            (Aggregation::GroupBy(key), Aggregate::Groups(groups)) => format!(
+                "group_by {} over {}: {} nodes in {} groups",
+                group_key_name(*key), over, self.counted, groups.len(),
+            ),
+            _ => format!("count over {}: {} nodes", over, self.counted),
+        }
+    }
+}
+
+impl StoredResult {
+    /// Total result count (independent of paging)
+    pub fn total(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+/// One page of a stored result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultPage {
+    /// Result this page belongs to
+    pub result_id: ResultId,
+
+    /// Total result count across all pages
+    pub total: usize,
+
+    /// Offset of the first node in this page
+    pub offset: usize,
+
+    /// Nodes in this page
+    pub nodes: QueryResult,
+}
+
+/// Stage explanations being collected for one pipeline
+struct Trace<'a> {
+    /// Statistics the estimates are based on
+    stats: &'a CPGStats,
+
+    /// Explained stages so far
+    stages: Vec<StageExplanation>,
+}
+
+/// Query engine
+pub struct QueryEngine {
+    /// Scheduler used to execute compiled stages
+    scheduler: Scheduler,
+
+    /// Stored results by ID
+    results: HashMap<ResultId, StoredResult>,
+
+    /// Next result ID
+    next_result_id: u64,
+
+    /// Checked by every stage of every query
+    cancel: CancellationToken,
+
+    /// `FrozenCPGEpoch::debug_verify` before each scoped query
+    verify_epochs: bool,
+
+    /// Snapshot store holding points-to maps (None = memory only)
+    points_to_store: Option<PathBuf>,
+
+    /// Update the last points-to map when the CPG hash changes
+    incremental_points_to: bool,
+
+    /// Last points-to map, with the hash of its CPG
+    points_to: Mutex<Option<(String, Arc<PointerAnalysis>)>>,
+}
 
 impl QueryEngine {
     /// Create new query engine
     pub fn new() -> Self {
-        Self
+        Self {
+            scheduler: Scheduler::new(1),
+            results: HashMap::new(),
+            next_result_id: 1,
+            cancel: CancellationToken::new(),
+            verify_epochs: true,
+            points_to_store: None,
+            incremental_points_to: false,
+            points_to: Mutex::new(None),
+        }
+    }
+
+    /// Check `token` while running queries
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Execute stages on `scheduler` (threads, chunk size)
+    pub fn with_scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Rehash frozen epochs before scoped queries (debug builds only)
+    pub fn with_epoch_verification(mut self, verify: bool) -> Self {
+        self.verify_epochs = verify;
+        self
+    }
+
+    /// Load and save points-to maps in the snapshot store at `dir`
+    pub fn with_points_to_store(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.points_to_store = Some(dir.into());
+        self
+    }
+
+    /// Update the last points-to map instead of recomputing it
+    pub fn with_incremental_points_to(mut self, incremental: bool) -> Self {
+        self.incremental_points_to = incremental;
+        self
+    }
+
+    /// Replace the token checked while running queries
+    pub fn set_cancellation(&mut self, token: CancellationToken) {
+        self.cancel = token;
+    }
+
+    /// Run a query and store its ordered result (or its aggregate)
+    pub fn run(&mut self, cpg: &CPG, spec: &QuerySpec) -> Result<ResultId> {
+        if spec.split_aggregate()?.1.is_some() {
+            let aggregate = self.aggregate(cpg, spec)?;
+            return Ok(self.store_aggregate(aggregate));
+        }
+        let nodes = self.compute(cpg, spec)?;
+        Ok(self.store(nodes, spec.options.order_by))
+    }
+
+    /// Execute a query and order its full result (nothing is stored)
+    ///
+    /// Path stages (`in_file`) need a snapshot; use `compute_scoped`.
+    pub fn compute(&self, cpg: &CPG, spec: &QuerySpec) -> Result<QueryResult> {
+        let indices = needs_indices(&spec.pipeline).then(|| CPGIndices::build(cpg));
+        self.compute_with(cpg, indices.as_ref(), None, spec, &MetricsCollector::new())
+    }
+
+    /// Execute an aggregate query (nothing is stored)
+    pub fn aggregate(&self, cpg: &CPG, spec: &QuerySpec) -> Result<StoredAggregate> {
+        let indices = needs_indices(&spec.pipeline).then(|| CPGIndices::build(cpg));
+        self.aggregate_with(cpg, indices.as_ref(), None, spec, &MetricsCollector::new())
+    }
+
+    /// Execute an aggregate query against an epoch whose files are known
+    ///
+    /// `group_by: file` groups by repository-relative path here; without a
+    /// scope, groups are named by FileId.
+    pub fn aggregate_scoped(&self, cpg_epoch: &FrozenCPGEpoch, scope: &FileScope, spec: &QuerySpec) -> Result<StoredAggregate> {
+        self.aggregate_scoped_with_metrics(cpg_epoch, scope, spec, &MetricsCollector::new())
+    }
+
+    /// `aggregate_scoped`, recording task and chunk timings in `metrics`
+    pub fn aggregate_scoped_with_metrics(
+        &self,
+        cpg_epoch: &FrozenCPGEpoch,
+        scope: &FileScope,
+        spec: &QuerySpec,
+        metrics: &MetricsCollector,
+    ) -> Result<StoredAggregate> {
+        self.verify_epoch(cpg_epoch);
+        self.aggregate_with(cpg_epoch.cpg(), Some(cpg_epoch.indices()), Some(scope), spec, metrics)
+    }
+
+    /// Execute a query and explain its plan (nothing is stored)
+    pub fn explain(&self, cpg: &CPG, spec: &QuerySpec) -> Result<PlanExplanation> {
+        self.compute_explained(cpg, spec).map(|(_, explanation)| explanation)
+    }
+
+    /// Run a query, store its ordered result (or its aggregate) and explain its plan
+    pub fn run_explained(&mut self, cpg: &CPG, spec: &QuerySpec) -> Result<(ResultId, PlanExplanation)> {
+        let (computed, explanation) = self.compute_explained(cpg, spec)?;
+        let result_id = match computed {
+            Computed::Nodes(nodes) => self.store(nodes, spec.options.order_by),
+            Computed::Aggregate(aggregate) => self.store_aggregate(aggregate),
+        };
+        Ok((result_id, explanation))
+    }
+
+    /// Execute and order (or aggregate), collecting the explanation
+    ///
+    /// An aggregate stage is explained as one more stage whose actual rows
+    /// are the groups it produced.
+    fn compute_explained(&self, cpg: &CPG, spec: &QuerySpec) -> Result<(Computed, PlanExplanation)> {
+        let (stages, aggregation) = spec.split_aggregate()?;
+        let indices = needs_indices(&spec.pipeline).then(|| CPGIndices::build(cpg));
+        let stats = cpg.stats();
+        let mut trace = Trace { stats: &stats, stages: Vec::new() };
+
+        let metrics = MetricsCollector::new();
+        let mut nodes = self.execute_pipeline(cpg, indices.as_ref(), None, stages, &metrics, Some(&mut trace))?;
+        let Some(aggregation) = aggregation else {
+            order_nodes(cpg, &mut nodes, spec.options.order_by);
+            let explanation = PlanExplanation { stages: trace.stages, result_count: nodes.len() };
+            return Ok((Computed::Nodes(nodes), explanation));
+        };
+
+        let started = Instant::now();
+        let value = fold(cpg, indices.as_ref(), None, &nodes, aggregation)?;
+        let rows = value.rows();
+        let estimated_rows = match aggregation {
+            Aggregation::Count => 1,
+            Aggregation::GroupBy(GroupKey::Kind) => stats.nodes_by_kind.values().filter(|n| **n > 0).count(),
+            Aggregation::GroupBy(GroupKey::File) => stats.nodes_by_kind.get(&CPGNodeKind::File).copied().unwrap_or(0) + 1,
+        };
+        trace.stages.push(StageExplanation {
+            index: stages.len(),
+            stage: spec.pipeline[stages.len()].name().to_string(),
+            task_id: stages.len() as u64,
+            operator: aggregation_operator(aggregation).to_string(),
+            input_rows: nodes.len(),
+            estimated_rows: estimated_rows.min(nodes.len().max(1)),
+            actual_rows: rows,
+            estimated_cost: nodes.len() as u64,
+            simd: false,
+            wall_us: started.elapsed().as_micros() as u64,
+            chunk_us: Vec::new(),
+            sub_pipeline: Vec::new(),
+        });
+
+        let explanation = PlanExplanation { stages: trace.stages, result_count: rows };
+        let aggregate = StoredAggregate { stages: stages.to_vec(), aggregation, counted: nodes.len(), value };
+        Ok((Computed::Aggregate(aggregate), explanation))
+    }
+
+    /// Execute a query against an epoch whose files are known
+    pub fn compute_scoped(&self, cpg_epoch: &FrozenCPGEpoch, scope: &FileScope, spec: &QuerySpec) -> Result<QueryResult> {
+        self.compute_scoped_with_metrics(cpg_epoch, scope, spec, &MetricsCollector::new())
+    }
+
+    /// `compute_scoped`, recording task and chunk timings in `metrics`
+    pub fn compute_scoped_with_metrics(
+        &self,
+        cpg_epoch: &FrozenCPGEpoch,
+        scope: &FileScope,
+        spec: &QuerySpec,
+        metrics: &MetricsCollector,
+    ) -> Result<QueryResult> {
+        self.verify_epoch(cpg_epoch);
+        self.compute_with(cpg_epoch.cpg(), Some(cpg_epoch.indices()), Some(scope), spec, metrics)
+    }
+
+    /// **Panics** in debug builds if `cpg_epoch` diverged from its hash
+    fn verify_epoch(&self, cpg_epoch: &FrozenCPGEpoch) {
+        if self.verify_epochs {
+            cpg_epoch.debug_verify();
+        }
+    }
+
+    /// Execute and order, with whatever indices and file scope are available
+    fn compute_with(
+        &self,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+        scope: Option<&FileScope>,
+        spec: &QuerySpec,
+        metrics: &MetricsCollector,
+    ) -> Result<QueryResult> {
+        let span = tracing::info_span!(
+            "query",
+            stages = spec.pipeline.len(),
+            results = tracing::field::Empty,
+        ).entered();
+
+        if let (_, Some(aggregation)) = spec.split_aggregate()? {
+            bail!("{} query yields an aggregate, not nodes", aggregation_operator(aggregation));
+        }
+        let mut nodes = self.execute_pipeline(cpg, indices, scope, &spec.pipeline, metrics, None)?;
+        order_nodes(cpg, &mut nodes, spec.options.order_by);
+
+        span.record("results", nodes.len());
+        Ok(nodes)
+    }
+
+    /// Execute and fold, with whatever indices and file scope are available
+    fn aggregate_with(
+        &self,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+        scope: Option<&FileScope>,
+        spec: &QuerySpec,
+        metrics: &MetricsCollector,
+    ) -> Result<StoredAggregate> {
+        let (stages, aggregation) = spec.split_aggregate()?;
+        let aggregation = aggregation.ok_or_else(|| anyhow!("Query has no count or group_by stage"))?;
+        let _span = tracing::info_span!("aggregate", stages = stages.len()).entered();
+
+        // The set is counted as the pipeline leaves it: no ordering, no copy
+        let nodes = self.execute_pipeline(cpg, indices, scope, stages, metrics, None)?;
+        let value = fold(cpg, indices, scope, &nodes, aggregation)?;
+        Ok(StoredAggregate { stages: stages.to_vec(), aggregation, counted: nodes.len(), value })
+    }
+
+    /// Store an already ordered result
+    pub fn store(&mut self, nodes: QueryResult, order_by: OrderKey) -> ResultId {
+        let result_id = ResultId(self.next_result_id);
+        self.next_result_id += 1;
+        self.results.insert(result_id, StoredResult { nodes, order_by, paths: None, aggregate: None, snapshot: None });
+        result_id
+    }
+
+    /// Store an aggregate (the result has no nodes)
+    pub fn store_aggregate(&mut self, aggregate: StoredAggregate) -> ResultId {
+        let result_id = ResultId(self.next_result_id);
+        self.next_result_id += 1;
+        let stored = StoredResult {
+            nodes: Vec::new(),
+            order_by: OrderKey::default(),
+            paths: None,
+            aggregate: Some(aggregate),
+            snapshot: None,
+        };
+        self.results.insert(result_id, stored);
+        result_id
+    }
+
+    /// Store a fragment's output as is, keeping its path table
+    pub fn store_fragment(&mut self, output: FragmentOutput) -> ResultId {
+        let result_id = ResultId(self.next_result_id);
+        self.next_result_id += 1;
+        let stored = StoredResult {
+            nodes: output.nodes,
+            order_by: OrderKey::default(),
+            paths: output.paths,
+            aggregate: None,
+            snapshot: None,
+        };
+        self.results.insert(result_id, stored);
+        result_id
+    }
+
+    /// Record that a stored result was answered by snapshot `id`'s CPG
+    /// (no-op for an unknown result)
+    pub fn tag_snapshot(&mut self, result_id: ResultId, id: SnapshotId) {
+        if let Some(stored) = self.results.get_mut(&result_id) {
+            stored.snapshot = Some(id);
+        }
+    }
+
+    /// Paths of a stored path result, in discovery order
+    pub fn get_paths(&self, result_id: ResultId) -> Option<Vec<&[CPGNodeId]>> {
+        let stored = self.results.get(&result_id)?;
+        let table = stored.paths.as_ref()?;
+        Some(table.iter().map(|range| &stored.nodes[range.clone()]).collect())
+    }
+
+    /// Run a query and return the page selected by its options
+    pub fn execute(&mut self, cpg: &CPG, spec: &QuerySpec) -> Result<ResultPage> {
+        let result_id = self.run(cpg, spec)?;
+        self.fetch_result(result_id, spec.options.offset, spec.options.limit)
+    }
+
+    /// Fetch a page of a stored result
+    ///
+    /// **Deterministic**: Pages are slices of one stored order, so any
+    /// page size yields the same concatenated sequence.
+    pub fn fetch_result(&self, result_id: ResultId, offset: usize, limit: Option<usize>) -> Result<ResultPage> {
+        let stored = self.get_result(result_id)
+            .ok_or_else(|| anyhow!("Unknown result: {}", result_id.0))?;
+
+        let start = offset.min(stored.total());
+        let end = match limit {
+            Some(limit) => start.saturating_add(limit).min(stored.total()),
+            None => stored.total(),
+        };
+
+        Ok(ResultPage {
+            result_id,
+            total: stored.total(),
+            offset: start,
+            nodes: stored.nodes[start..end].to_vec(),
+        })
+    }
+
+    /// Fetch a page using query options
+    pub fn fetch_with(&self, result_id: ResultId, options: &QueryOptions) -> Result<ResultPage> {
+        self.fetch_result(result_id, options.offset, options.limit)
+    }
+
+    /// Get a stored result
+    pub fn get_result(&self, result_id: ResultId) -> Option<&StoredResult> {
+        self.results.get(&result_id)
+    }
+
+    /// Points-to map of `cpg` (analyzed from roots)
+    ///
+    /// The last map is reused while the CPG hash matches. Otherwise the
+    /// store's map for the new hash is loaded, or one is computed and saved
+    /// there (if the store has a snapshot of this CPG).
+    fn pointer_analysis(&self, cpg: &CPG) -> Result<Arc<PointerAnalysis>> {
+        let hash = cpg.compute_hash();
+        let mut last = self.points_to.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, analysis)) = last.as_ref().filter(|(last_hash, _)| *last_hash == hash) {
+            return Ok(analysis.clone());
+        }
+
+        let store = match &self.points_to_store {
+            Some(dir) if dir.is_dir() => Some(SnapshotStore::open(dir).context("may_alias: opening snapshot store")?),
+            _ => None,
+        };
+        let loaded = match &store {
+            Some(store) => store.load_points_to(&hash).context("may_alias: loading points-to map")?,
+            None => None,
+        };
+        let analysis = match (loaded, last.as_ref()) {
+            (Some(analysis), _) => analysis,
+            (None, Some((_, previous))) if self.incremental_points_to => {
+                PointerAnalysis::update_from_roots_cancellable(previous, cpg, &[], &self.cancel)?.0
+            }
+            (None, _) => {
+                let analysis = PointerAnalysis::analyze_from_roots_cancellable(cpg, &self.cancel)?;
+                if let Some(store) = &store {
+                    match store.save_points_to(&hash, &analysis) {
+                        Ok(()) => {}
+                        // Not a saved CPG: nothing to keep the map next to
+                        Err(e) if e.kind() == ErrorKind::NotFound => {}
+                        Err(e) => tracing::warn!(error = %e, "failed to save points-to map"),
+                    }
+                }
+                analysis
+            }
+        };
+
+        let analysis = Arc::new(analysis);
+        *last = Some((hash, analysis.clone()));
+        Ok(analysis)
+    }
+
+    /// Execute the pipeline stages in order, explaining each into `trace`
+    fn execute_pipeline(
+        &self,
+        cpg: &CPG,
+        indices: Option<&CPGIndices>,
+        scope: Option<&FileScope>,
+        pipeline: &[QueryStage],
+        metrics: &MetricsCollector,
+        mut trace: Option<&mut Trace>,
+    ) -> Result<QueryResult> {
+        let mut current: QueryResult = Vec::new();
+
+        for (index, stage) in pipeline.iter().enumerate() {
+            let mut sub_trace = trace.as_ref().map(|trace| Trace { stats: trace.stats, stages: Vec::new() });
+            let work = match stage {
+                QueryStage::Find(kind) => WorkFragment::FindNodes { kind: *kind },
+                QueryStage::Follow(kind) => WorkFragment::FollowEdges {
+                    from: std::mem::take(&mut current),
+                    kind: *kind,
+                },
+                QueryStage::FollowReverse(kind) => WorkFragment::FollowEdgesReverse {
+                    to: std::mem::take(&mut current),
+                    kind: *kind,
+                },
+                QueryStage::Path(pattern) => WorkFragment::MatchPath {
+                    from: std::mem::take(&mut current),
+                    pattern: pattern.clone(),
+                },
+                QueryStage::Filter(kind) => WorkFragment::Filter {
+                    nodes: std::mem::take(&mut current),
+                    kind: Some(*kind),
+                },
+                QueryStage::Union(sub) => WorkFragment::Union {
+                    a: std::mem::take(&mut current),
+                    b: self.execute_pipeline(cpg, indices, scope, sub, metrics, sub_trace.as_mut())?,
+                },
+                QueryStage::Difference(sub) => WorkFragment::Difference {
+                    a: std::mem::take(&mut current),
+                    b: self.execute_pipeline(cpg, indices, scope, sub, metrics, sub_trace.as_mut())?,
+                },
+                QueryStage::InFile(path) => {
+                    let (indices, scope) = file_context(indices, scope, "in_file")?;
+                    let in_file: QueryResult = scope.resolve(path)?
+                        .into_iter()
+                        .flat_map(|file_id| QueryPrimitives::nodes_in_file(cpg, indices, file_id))
+                        .collect();
+                    restrict(&mut current, index, in_file)
+                }
+                QueryStage::Language(language) => {
+                    let indices = indices.ok_or_else(|| anyhow!("language requires CPG indices"))?;
+                    let in_language: QueryResult = indices.files_in_language(language)
+                        .iter()
+                        .flat_map(|file_id| QueryPrimitives::nodes_in_file(cpg, indices, *file_id))
+                        .collect();
+                    restrict(&mut current, index, in_language)
+                }
+                QueryStage::At { file, offset } => {
+                    let (indices, scope) = file_context(indices, scope, "at")?;
+                    let file_id = scope.resolve_file(file)?;
+                    restrict(&mut current, index, QueryPrimitives::nodes_at(cpg, indices, file_id, *offset))
+                }
+                QueryStage::Function(name) => {
+                    let indices = indices.ok_or_else(|| anyhow!("function requires CPG indices"))?;
+                    restrict(&mut current, index, QueryPrimitives::functions_named(indices, name))
+                }
+                QueryStage::FunctionMatches(pattern) => {
+                    let indices = indices.ok_or_else(|| anyhow!("function_matches requires CPG indices"))?;
+                    let pattern = NamePattern::parse(pattern)?;
+                    restrict(&mut current, index, QueryPrimitives::functions_matching(indices, &pattern))
+                }
+                QueryStage::MayAlias([a, b]) => {
+                    let witnesses = self.pointer_analysis(cpg)
+                        .and_then(|pointers| alias_witnesses(cpg, &pointers, CPGNodeId(*a), CPGNodeId(*b)))
+                        .map_err(|e| match e.downcast::<Interrupted>() {
+                            Ok(interrupted) => interrupted.after_tasks(index).into(),
+                            Err(e) => e,
+                        })?;
+                    restrict(&mut current, index, witnesses)
+                }
+                QueryStage::Parameter(position) => {
+                    let functions = (index > 0).then(|| std::mem::take(&mut current));
+                    WorkFragment::Filter {
+                        nodes: QueryPrimitives::parameters_at(cpg, *position, functions.as_deref()),
+                        kind: Some(CPGNodeKind::DfgValue),
+                    }
+                }
+                QueryStage::Overlapping { file, start, end } => {
+                    let (indices, scope) = file_context(indices, scope, "overlapping")?;
+                    let file_id = scope.resolve_file(file)?;
+                    if start > end {
+                        return Err(anyhow!("overlapping: start {} is after end {}", start, end));
+                    }
+                    let range = ByteRange::new(*start, *end);
+                    restrict(&mut current, index, QueryPrimitives::nodes_overlapping(indices, file_id, range))
+                }
+                QueryStage::Count(_) | QueryStage::GroupBy(_) => {
+                    bail!("{} must be the last stage of the top-level pipeline", stage.name())
+                }
+            };
+
+            // Everything but the timing is known before the task runs
+            let planned = trace.as_ref().map(|trace| StageExplanation {
+                index,
+                stage: stage.name().to_string(),
+                task_id: index as u64,
+                operator: work.name().to_string(),
+                input_rows: work.input_rows(),
+                estimated_rows: QueryCost::estimate_rows(&work, trace.stats),
+                actual_rows: 0,
+                estimated_cost: QueryCost::for_fragment(&work, trace.stats.total_nodes).total_cost().ceil() as u64,
+                simd: uses_simd(&work),
+                wall_us: 0,
+                chunk_us: Vec::new(),
+                sub_pipeline: sub_trace.map(|sub| sub.stages).unwrap_or_default(),
+            });
+
+            let task = Task::new(TaskId(index as u64), work, vec![], 0);
+            let mut plan = ExecutionPlan::new();
+            plan.add_stage(Stage::new(vec![task], DeterministicOrder::TaskId));
+
+            let (outputs, records) = self.scheduler.execute_cancellable(&plan, cpg, indices, &self.cancel)
+                .map_err(|e| e.after_tasks(index))?;
+            records.iter().for_each(|record| metrics.record_task(record));
+            current = outputs
+                .into_iter()
+                .next()
+                .map(|output| output.nodes)
+                .unwrap_or_default();
+
+            if let (Some(trace), Some(mut explained)) = (trace.as_deref_mut(), planned) {
+                if let Some(record) = records.first() {
+                    explained.actual_rows = record.result_size;
+                    explained.wall_us = record.wall_us;
+                    explained.chunk_us = record.chunk_us.clone();
+                }
+                trace.stages.push(explained);
+            }
+        }
+
+        Ok(current)
+    }
+}
+
+impl Default for QueryEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Output of `compute_explained`
+enum Computed {
+    Nodes(QueryResult),
+    Aggregate(StoredAggregate),
+}
+
+/// Group name for nodes outside every file
+const NO_FILE: &str = "<none>";
+
+/// Fold a node set into an aggregate
+///
+/// Grouping walks the CPG once (per-file position ranges for `file`), so
+/// its cost is one pass over the nodes, not a lookup per result.
+fn fold(
+    cpg: &CPG,
+    indices: Option<&CPGIndices>,
+    scope: Option<&FileScope>,
+    nodes: &[CPGNodeId],
+    aggregation: Aggregation,
+) -> Result<Aggregate> {
+    let key = match aggregation {
+        Aggregation::Count => return Ok(Aggregate::Count(nodes.len())),
+        Aggregation::GroupBy(key) => key,
+    };
+    let wanted: HashSet<CPGNodeId> = nodes.iter().copied().collect();
+    let mut groups: BTreeMap<String, usize> = BTreeMap::new();
+
+    match key {
+        GroupKey::Kind => {
+            for node in cpg.nodes.iter().filter(|node| wanted.contains(&node.id)) {
+                *groups.entry(format!("{:?}", node.kind)).or_default() += 1;
+            }
+        }
+        GroupKey::File => {
+            let indices = indices.ok_or_else(|| anyhow!("group_by file requires CPG indices"))?;
+            indices.assert_current(cpg);
+            let paths: HashMap<_, _> = scope.into_iter()
+                .flat_map(|scope| scope.files().map(|(path, file_id)| (file_id, path)))
+                .collect();
+            let mut in_files = 0;
+            for (file_id, range) in indices.file_ranges() {
+                let count = cpg.nodes[range.clone()].iter().filter(|node| wanted.contains(&node.id)).count();
+                if count > 0 {
+                    let name = paths.get(file_id)
+                        .map(|path| normalize_path(path))
+                        .unwrap_or_else(|| format!("file:{:016x}", file_id.as_u64()));
+                    *groups.entry(name).or_default() += count;
+                    in_files += count;
+                }
+            }
+            if wanted.len() > in_files {
+                groups.insert(NO_FILE.to_string(), wanted.len() - in_files);
+            }
+        }
+    }
+    Ok(Aggregate::Groups(groups))
+}
+
+/// Operator name of an aggregate stage in explanations
+fn aggregation_operator(aggregation: Aggregation) -> &'static str {
+    match aggregation {
+        Aggregation::Count => "count",
+        Aggregation::GroupBy(GroupKey::Kind) => "group_by_kind",
+        Aggregation::GroupBy(GroupKey::File) => "group_by_file",
+    }
+}
+
+/// Group key as written in the DSL
+fn group_key_name(key: GroupKey) -> &'static str {
+    match key {
+        GroupKey::Kind => "kind",
+        GroupKey::File => "file",
+    }
+}
+
+/// Indices and file scope, required by path-based stages
+fn file_context<'a>(
+    indices: Option<&'a CPGIndices>,
+    scope: Option<&'a FileScope>,
+    stage: &str,
+) -> Result<(&'a CPGIndices, &'a FileScope)> {
+    indices.zip(scope)
+        .ok_or_else(|| anyhow!("{} requires a loaded repository", stage))
+}
+
+/// Whether a fragment takes the sorted-set path on a SIMD-capable CPU
+fn uses_simd(work: &WorkFragment) -> bool {
+    match work {
+        WorkFragment::Union { a, b } | WorkFragment::Difference { a, b } => {
+            use_sorted_path(a, b) && crate::simd::simd_available()
+        }
+        _ => false,
+    }
+}
+
+/// Intersect the current set with `nodes`; the first stage selects `nodes`
+fn restrict(current: &mut QueryResult, index: usize, nodes: QueryResult) -> WorkFragment {
+    let base = if index == 0 { nodes.clone() } else { std::mem::take(current) };
+    WorkFragment::Intersect { a: base, b: nodes }
+}
+
+/// DfgValue nodes of the values two DfgValue nodes may both point to
+///
+/// Fails closed: a node that is not a DFG value, or an overflowed points-to
+/// set, is an error rather than an empty answer.
+fn alias_witnesses(cpg: &CPG, pointers: &PointerAnalysis, a: CPGNodeId, b: CPGNodeId) -> Result<QueryResult> {
+    let value_of = |id: CPGNodeId| match cpg.get_node(id).map(|node| node.origin) {
+        Some(OriginRef::Dfg { value_id }) => Ok(value_id),
+        _ => Err(anyhow!("may_alias: node {} is not a DFG value", id.0)),
+    };
+
+    let (a_value, b_value) = (value_of(a)?, value_of(b)?);
+    match pointers.may_alias(a_value, b_value) {
+        AliasResult::NoAlias => Ok(Vec::new()),
+        AliasResult::MayAlias { witnesses } => Ok(cpg.nodes.iter()
+            .filter(|node| matches!(node.origin, OriginRef::Dfg { value_id } if witnesses.contains(&value_id)))
+            .map(|node| node.id)
+            .collect()),
+        AliasResult::Unknown => Err(anyhow!("may_alias: points-to set of node {} or {} is unknown", a.0, b.0)),
+    }
+}
+
+/// Whether any stage (including nested pipelines) reads the indices
+/// (reverse edges, function names, languages or file ranges)
+fn needs_indices(pipeline: &[QueryStage]) -> bool {
+    pipeline.iter().any(|stage| match stage {
+        QueryStage::FollowReverse(_) | QueryStage::Function(_) | QueryStage::FunctionMatches(_) => true,
+        QueryStage::Language(_) => true,
+        QueryStage::GroupBy(GroupKey::File) => true,
+        QueryStage::Union(sub) | QueryStage::Difference(sub) => needs_indices(sub),
+        _ => false,
+    })
+}
+
+/// Sort nodes by the given key, breaking ties by NodeId
+fn order_nodes(cpg: &CPG, nodes: &mut QueryResult, order_by: OrderKey) {
+    match order_by {
+        OrderKey::NodeId => nodes.sort(),
+        OrderKey::SourceRange => {
+            let by_id: HashMap<_, _> = cpg.nodes.iter().map(|n| (n.id, n)).collect();
+            nodes.sort_by_key(|id| {
+                let range = by_id.get(id).map(|n| (n.source_range.start, n.source_range.end));
+                (range, *id)
+            });
+        }
+        OrderKey::Label => {
+            let by_id: HashMap<_, _> = cpg.nodes.iter().map(|n| (n.id, n)).collect();
+            nodes.sort_by(|a, b| {
+                let label_a = by_id.get(a).and_then(|n| cpg.label(n));
+                let label_b = by_id.get(b).and_then(|n| cpg.label(n));
+                label_a.cmp(&label_b).then(a.cmp(b))
+            });
+        }
+    }
+}
+
+/// Problem found by `validate_query`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryIssueCode {
+    /// Not JSON
+    InvalidJson,
+
+    /// Value of the wrong JSON type
+    TypeMismatch,
+
+    /// Required field absent
+    MissingField,
+
+    /// Field the query or stage does not have
+    UnknownField,
+
+    /// Key that is not a stage
+    UnknownStage,
+
+    /// Stage object with zero or several keys
+    AmbiguousStage,
+
+    /// Name that is not a node kind, edge kind, group key or order key
+    UnknownVariant,
+
+    /// Number outside its bounds (path steps and repeats, ranges)
+    OutOfRange,
+
+    /// Value the stage never accepts (`count: false`)
+    InvalidValue,
+
+    /// `function_matches` pattern that does not parse
+    InvalidPattern,
+
+    /// Stage reading the previous stage's nodes where no stage precedes it
+    NoInput,
+
+    /// Aggregate stage before another stage or inside a sub-pipeline
+    MisplacedAggregate,
+}
+
+impl QueryIssueCode {
+    /// Code as serialized
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QueryIssueCode::InvalidJson => "invalid_json",
+            QueryIssueCode::TypeMismatch => "type_mismatch",
+            QueryIssueCode::MissingField => "missing_field",
+            QueryIssueCode::UnknownField => "unknown_field",
+            QueryIssueCode::UnknownStage => "unknown_stage",
+            QueryIssueCode::AmbiguousStage => "ambiguous_stage",
+            QueryIssueCode::UnknownVariant => "unknown_variant",
+            QueryIssueCode::OutOfRange => "out_of_range",
+            QueryIssueCode::InvalidValue => "invalid_value",
+            QueryIssueCode::InvalidPattern => "invalid_pattern",
+            QueryIssueCode::NoInput => "no_input",
+            QueryIssueCode::MisplacedAggregate => "misplaced_aggregate",
+        }
+    }
+}
+
+/// One problem in a query, with where it is and how to fix it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryIssue {
+    pub code: QueryIssueCode,
+
+    /// Index of the top-level stage (None: outside the pipeline)
+    pub stage: Option<usize>,
+
+    /// JSON path of the offending value (`pipeline[1].union[0].follow`)
+    pub location: String,
+
+    pub message: String,
+
+    /// One-line fix
+    pub suggestion: String,
+}
+
+impl std::fmt::Display for QueryIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} ({}); {}", self.location, self.message, self.code.as_str(), self.suggestion)
+    }
+}
+
+/// Top-level query fields (`name` and `description` are saved-query headers)
+const QUERY_FIELDS: &[&str] = &["pipeline", "limit", "offset", "order_by", "name", "description"];
+
+const STAGES: &[&str] = &[
+    "find", "follow", "follow_reverse", "filter", "union", "difference", "at", "overlapping", "in_file", "language",
+    "function", "function_matches", "may_alias", "parameter", "path", "count", "group_by",
+];
+
+/// Stages that narrow or extend the previous stage's nodes
+const NEEDS_INPUT: &[&str] = &["follow", "follow_reverse", "filter", "path", "difference"];
+
+const NODE_KINDS: &[&str] = &["AstNode", "CfgNode", "DfgValue", "Symbol", "Function", "File"];
+const EDGE_KINDS: &[&str] = &["AstParent", "AstChild", "ControlFlow", "DataFlow", "Defines", "Uses", "Calls", "PointsTo"];
+const GROUP_KEYS: &[&str] = &["kind", "file"];
+const ORDER_KEYS: &[&str] = &["node_id", "source_range", "label"];
+const STEP_FIELDS: &[&str] = &["edge", "node", "min", "max"];
+
+/// Check a JSON query before it is parsed and planned
+///
+/// Reports every problem found, in document order, instead of serde's first
+/// error. An empty list means `QuerySpec::from_json` accepts the query
+/// (saved-query `name` and `description` headers are allowed).
+pub fn validate_query(text: &str) -> Vec<QueryIssue> {
+    let mut validator = Validator::default();
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(value) => validator.query(&value),
+        Err(e) => validator.issue(
+            QueryIssueCode::InvalidJson, "query", e.to_string(), "check brackets, commas and quoting at that position",
+        ),
+    }
+    validator.issues
+}
+
+#[derive(Default)]
+struct Validator {
+    issues: Vec<QueryIssue>,
+
+    /// Top-level stage being checked
+    stage: Option<usize>,
+}
+
+impl Validator {
+    fn issue(&mut self, code: QueryIssueCode, location: &str, message: impl Into<String>, suggestion: impl Into<String>) {
+        self.issues.push(QueryIssue {
+            code,
+            stage: self.stage,
+            location: location.to_string(),
+            message: message.into(),
+            suggestion: suggestion.into(),
+        });
+    }
+
+    fn query(&mut self, value: &serde_json::Value) {
+        let Some(fields) = value.as_object() else {
+            return self.issue(
+                QueryIssueCode::TypeMismatch, "query", format!("query is {}, not an object", type_name(value)),
+                r#"write the query as {"pipeline": [...]}"#,
+            );
+        };
+
+        for key in fields.keys().filter(|key| !QUERY_FIELDS.contains(&key.as_str())) {
+            self.issue(QueryIssueCode::UnknownField, key, format!("unknown field `{}`", key), did_you_mean(key, &QUERY_FIELDS[..4]));
+        }
+        match fields.get("pipeline") {
+            Some(pipeline) => self.pipeline(pipeline, "pipeline", true),
+            None => self.issue(
+                QueryIssueCode::MissingField, "query", "missing field `pipeline`", r#"add "pipeline": [{"find": "Function"}]"#,
+            ),
+        }
+        if let Some(limit) = fields.get("limit").filter(|limit| !limit.is_null()) {
+            self.uint(limit, "limit");
+        }
+        if let Some(offset) = fields.get("offset") {
+            self.uint(offset, "offset");
+        }
+        if let Some(order_by) = fields.get("order_by") {
+            self.variant::<OrderKey>(order_by, "order_by", "order key", ORDER_KEYS);
+        }
+        for header in ["name", "description"] {
+            if let Some(value) = fields.get(header).filter(|value| !value.is_string()) {
+                self.mismatch(value, header, "a string");
+            }
+        }
+    }
+
+    /// `top`: the query's own pipeline, where an aggregate may end it
+    fn pipeline(&mut self, value: &serde_json::Value, location: &str, top: bool) {
+        let Some(stages) = value.as_array() else {
+            return self.mismatch(value, location, "an array of stages");
+        };
+        for (index, stage) in stages.iter().enumerate() {
+            if top {
+                self.stage = Some(index);
+            }
+            self.stage_entry(stage, &format!("{}[{}]", location, index), index, top && index + 1 == stages.len());
+        }
+        if top {
+            self.stage = None;
+        }
+    }
+
+    fn stage_entry(&mut self, value: &serde_json::Value, location: &str, index: usize, may_aggregate: bool) {
+        let Some(fields) = value.as_object() else {
+            return self.issue(
+                QueryIssueCode::TypeMismatch, location, format!("stage is {}, not an object", type_name(value)),
+                r#"write each stage as an object with one key, like {"find": "Function"}"#,
+            );
+        };
+        let mut keys = fields.keys();
+        let (Some(name), None) = (keys.next(), keys.next()) else {
+            let names: Vec<&str> = fields.keys().map(String::as_str).collect();
+            return self.issue(
+                QueryIssueCode::AmbiguousStage, location, format!("stage has {} keys ({})", names.len(), names.join(", ")),
+                "give each stage exactly one key; split combined stages into consecutive ones",
+            );
+        };
+        let (argument, at) = (&fields[name], format!("{}.{}", location, name));
+
+        match name.as_str() {
+            "find" | "filter" => self.variant::<CPGNodeKind>(argument, &at, "node kind", NODE_KINDS),
+            "follow" | "follow_reverse" => self.variant::<CPGEdgeKind>(argument, &at, "edge kind", EDGE_KINDS),
+            "union" | "difference" => self.pipeline(argument, &at, false),
+            "at" => {
+                self.fields(argument, &at, &["file", "offset"]);
+            }
+            "overlapping" => {
+                if let Some(range) = self.fields(argument, &at, &["file", "start", "end"]) {
+                    if let (Some(start), Some(end)) = (range["start"].as_u64(), range["end"].as_u64()) {
+                        if start > end {
+                            self.issue(
+                                QueryIssueCode::OutOfRange, &at, format!("start {} is past end {}", start, end),
+                                "swap start and end",
+                            );
+                        }
+                    }
+                }
+            }
+            "in_file" | "language" | "function" => {
+                self.string(argument, &at);
+            }
+            "function_matches" => {
+                if let Some(pattern) = self.string(argument, &at).then(|| argument.as_str()).flatten() {
+                    if let Err(e) = NamePattern::parse(pattern) {
+                        self.issue(
+                            QueryIssueCode::InvalidPattern, &at, e.to_string(),
+                            "patterns support ^, $, . and * only; escape other characters with \\",
+                        );
+                    }
+                }
+            }
+            "parameter" => {
+                self.uint(argument, &at);
+            }
+            "may_alias" => match argument.as_array() {
+                Some(ids) if ids.len() == 2 && ids.iter().all(|id| id.as_u64().is_some()) => {}
+                _ => self.mismatch(argument, &at, "two DfgValue node IDs"),
+            },
+            "path" => self.path(argument, &at),
+            "count" => match argument.as_bool() {
+                Some(true) => {}
+                Some(false) => self.issue(
+                    QueryIssueCode::InvalidValue, &at, "count must be true", "write {\"count\": true} or drop the stage",
+                ),
+                None => self.mismatch(argument, &at, "true"),
+            },
+            "group_by" => self.variant::<GroupKey>(argument, &at, "group key", GROUP_KEYS),
+            _ => self.issue(QueryIssueCode::UnknownStage, &at, format!("unknown stage `{}`", name), did_you_mean(name, STAGES)),
+        }
+
+        if index == 0 && NEEDS_INPUT.contains(&name.as_str()) {
+            self.issue(
+                QueryIssueCode::NoInput, &at,
+                format!("`{}` reads the previous stage's nodes, but no stage precedes it", name),
+                "start the pipeline with find, function, function_matches, parameter, in_file, at or overlapping",
+            );
+        }
+        if matches!(name.as_str(), "count" | "group_by") && !may_aggregate {
+            self.issue(
+                QueryIssueCode::MisplacedAggregate, &at,
+                format!("{} must be the last stage of the top-level pipeline", name),
+                "move it to the end of the top-level pipeline",
+            );
+        }
+    }
+
+    fn path(&mut self, value: &serde_json::Value, location: &str) {
+        let Some(steps) = value.as_array() else {
+            return self.mismatch(value, location, "an array of edge steps");
+        };
+        if steps.is_empty() || steps.len() > MAX_PATH_STEPS {
+            self.issue(
+                QueryIssueCode::OutOfRange, location, format!("path has {} steps", steps.len()),
+                format!("use 1 to {} steps", MAX_PATH_STEPS),
+            );
+        }
+        for (index, step) in steps.iter().enumerate() {
+            let at = format!("{}[{}]", location, index);
+            let Some(fields) = step.as_object() else {
+                self.mismatch(step, &at, "an edge step object");
+                continue;
+            };
+            for key in fields.keys().filter(|key| !STEP_FIELDS.contains(&key.as_str())) {
+                self.issue(QueryIssueCode::UnknownField, &format!("{}.{}", at, key), format!("unknown field `{}`", key), did_you_mean(key, STEP_FIELDS));
+            }
+            match fields.get("edge") {
+                Some(edge) => self.variant::<CPGEdgeKind>(edge, &format!("{}.edge", at), "edge kind", EDGE_KINDS),
+                None => self.issue(QueryIssueCode::MissingField, &at, "missing field `edge`", r#"add "edge": "ControlFlow""#),
+            }
+            if let Some(node) = fields.get("node") {
+                self.variant::<CPGNodeKind>(node, &format!("{}.node", at), "node kind", NODE_KINDS);
+            }
+            let mut bound = |key: &str| match fields.get(key) {
+                None => Some(1),
+                Some(value) => self.uint(value, &format!("{}.{}", at, key)),
+            };
+            if let (Some(min), Some(max)) = (bound("min"), bound("max")) {
+                if max == 0 || min > max || max > MAX_PATH_REPEAT as u64 {
+                    self.issue(
+                        QueryIssueCode::OutOfRange, &at, format!("repeat bounds min {} max {}", min, max),
+                        format!("use min <= max and 1 <= max <= {}", MAX_PATH_REPEAT),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Object with exactly `names`: strings for `file`, integers otherwise
+    fn fields<'a>(
+        &mut self,
+        value: &'a serde_json::Value,
+        location: &str,
+        names: &[&str],
+    ) -> Option<&'a serde_json::Map<String, serde_json::Value>> {
+        let Some(fields) = value.as_object() else {
+            self.mismatch(value, location, &format!("an object with {}", names.join(", ")));
+            return None;
+        };
+        let mut valid = true;
+        for key in fields.keys().filter(|key| !names.contains(&key.as_str())) {
+            self.issue(QueryIssueCode::UnknownField, &format!("{}.{}", location, key), format!("unknown field `{}`", key), did_you_mean(key, names));
+            valid = false;
+        }
+        for name in names {
+            let at = format!("{}.{}", location, name);
+            valid &= match fields.get(*name) {
+                None => {
+                    self.issue(QueryIssueCode::MissingField, location, format!("missing field `{}`", name), format!("add `{}`", name));
+                    false
+                }
+                Some(value) if *name == "file" => self.string(value, &at),
+                Some(value) => self.uint(value, &at).is_some(),
+            };
+        }
+        valid.then_some(fields)
+    }
+
+    fn variant<T: serde::de::DeserializeOwned>(&mut self, value: &serde_json::Value, location: &str, what: &str, names: &[&str]) {
+        match value.as_str() {
+            None => self.mismatch(value, location, &format!("a {} name", what)),
+            Some(name) if serde_json::from_value::<T>(value.clone()).is_err() => self.issue(
+                QueryIssueCode::UnknownVariant, location, format!("unknown {} `{}`", what, name), did_you_mean(name, names),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    fn string(&mut self, value: &serde_json::Value, location: &str) -> bool {
+        if !value.is_string() {
+            self.mismatch(value, location, "a string");
+        }
+        value.is_string()
+    }
+
+    fn uint(&mut self, value: &serde_json::Value, location: &str) -> Option<u64> {
+        if value.as_u64().is_none() {
+            self.mismatch(value, location, "a non-negative integer");
+        }
+        value.as_u64()
+    }
+
+    fn mismatch(&mut self, value: &serde_json::Value, location: &str, expected: &str) {
+        self.issue(
+            QueryIssueCode::TypeMismatch, location, format!("expected {}, found {}", expected, type_name(value)),
+            format!("replace it with {}", expected),
+        );
+    }
+}
+
+fn type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
+
+/// Closest of `names` by edit distance, or all of them
+fn did_you_mean(word: &str, names: &[&str]) -> String {
+    let closest = names.iter()
+        .map(|name| (edit_distance(&word.to_lowercase(), &name.to_lowercase()), *name))
+        .min();
+    match closest {
+        Some((distance, name)) if distance <= (word.len() / 3).max(2) => format!("did you mean `{}`?", name),
+        _ => format!("expected one of: {}", names.join(", ")),
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpg::model::{CPGNode, CPGNodeKind, OriginRef};
+    use crate::semantic::model::FunctionId;
+
+    /// 1000 function nodes with ranges and labels in scrambled order
+    fn synthetic_cpg() -> CPG {
+        let mut cpg = CPG::new();
+        for i in 0..1000u64 {
+            let scrambled = (i * 7919) % 1000;
+            let label = cpg.intern_label(&format!("fn_{}", scrambled % 100));
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(i),
+                CPGNodeKind::Function,
+                OriginRef::Function { function_id: FunctionId(i) },
+                ByteRange::new(scrambled as usize * 10, scrambled as usize * 10 + 5),
+            ).with_label(label));
+        }
+        cpg
+    }
+
+    fn collect_pages(engine: &QueryEngine, result_id: ResultId, page_size: usize) -> QueryResult {
+        let mut all = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = engine.fetch_result(result_id, offset, Some(page_size)).unwrap();
+            assert_eq!(page.total, 1000);
+            if page.nodes.is_empty() {
+                break;
+            }
+            offset += page.nodes.len();
+            all.extend(page.nodes);
+        }
+        all
+    }
+
+    #[test]
+    fn test_paging_is_consistent_across_page_sizes() {
+        let cpg = synthetic_cpg();
+
+        for order_by in [OrderKey::NodeId, OrderKey::SourceRange, OrderKey::Label] {
+            let mut engine = QueryEngine::new();
+            let spec = QuerySpec::new(vec![QueryStage::Find(CPGNodeKind::Function)])
+                .with_options(QueryOptions { limit: None, offset: 0, order_by });
+            let result_id = engine.run(&cpg, &spec).unwrap();
+
+            let by_7 = collect_pages(&engine, result_id, 7);
+            let by_64 = collect_pages(&engine, result_id, 64);
+
+            assert_eq!(by_7.len(), 1000);
+            assert_eq!(by_7, by_64, "Page size must not affect order ({:?})", order_by);
+        }
+    }
+
+    #[test]
+    fn test_order_by_source_range() {
+        let cpg = synthetic_cpg();
+        let mut engine = QueryEngine::new();
+        let spec = QuerySpec::new(vec![QueryStage::Find(CPGNodeKind::Function)])
+            .with_options(QueryOptions { limit: Some(3), offset: 0, order_by: OrderKey::SourceRange });
+
+        let page = engine.execute(&cpg, &spec).unwrap();
+
+        // Node 0 has scrambled position 0; 7919 * k % 1000 == 1 at k = 679
+        assert_eq!(page.nodes[0], CPGNodeId(0));
+        assert_eq!(page.nodes[1], CPGNodeId(679));
+        assert_eq!(page.total, 1000);
+    }
+
+    #[test]
+    fn test_order_by_label_breaks_ties_by_node_id() {
+        let cpg = synthetic_cpg();
+        let mut engine = QueryEngine::new();
+        let spec = QuerySpec::new(vec![QueryStage::Find(CPGNodeKind::Function)])
+            .with_options(QueryOptions { limit: None, offset: 0, order_by: OrderKey::Label });
+
+        let page = engine.execute(&cpg, &spec).unwrap();
+        let labels: Vec<_> = page.nodes.iter()
+            .map(|id| cpg.label(cpg.get_node(*id).unwrap()).unwrap())
+            .collect();
+
+        for (i, pair) in labels.windows(2).enumerate() {
+            assert!(pair[0] <= pair[1]);
+            if pair[0] == pair[1] {
+                assert!(page.nodes[i] < page.nodes[i + 1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_past_end_is_empty() {
+        let cpg = synthetic_cpg();
+        let mut engine = QueryEngine::new();
+        let spec = QuerySpec::new(vec![QueryStage::Find(CPGNodeKind::Function)])
+            .with_options(QueryOptions { limit: Some(10), offset: 5000, order_by: OrderKey::NodeId });
+
+        let page = engine.execute(&cpg, &spec).unwrap();
+        assert!(page.nodes.is_empty());
+        assert_eq!(page.total, 1000);
+    }
+
+    #[test]
+    fn test_fetch_unknown_result() {
+        let engine = QueryEngine::new();
+        assert!(engine.fetch_result(ResultId(42), 0, None).is_err());
+    }
+
+    #[test]
+    fn test_in_file_requires_scope() {
+        let cpg = synthetic_cpg();
+        let engine = QueryEngine::new();
+        let spec = QuerySpec::new(vec![QueryStage::InFile("src/lib.rs".to_string())]);
+
+        let err = engine.compute(&cpg, &spec).unwrap_err();
+        assert!(err.to_string().contains("requires a loaded repository"));
+    }
+
+    #[test]
+    fn test_language_selects_and_restricts_to_files() {
+        // A rust file with two functions, then a python file with one
+        let mut cpg = CPG::new();
+        for (i, (kind, language)) in [
+            (CPGNodeKind::File, Some("rust")),
+            (CPGNodeKind::Function, None),
+            (CPGNodeKind::Function, None),
+            (CPGNodeKind::File, Some("python")),
+            (CPGNodeKind::Function, None),
+        ].into_iter().enumerate() {
+            let origin = match kind {
+                CPGNodeKind::File => OriginRef::File { file_id: crate::types::FileId::new(i as u64) },
+                _ => OriginRef::Function { function_id: FunctionId(i as u64) },
+            };
+            let mut node = CPGNode::new(CPGNodeId(i as u64), kind, origin, ByteRange::new(0, 0));
+            if let Some(language) = language {
+                node = node.with_label(cpg.intern_label(language));
+            }
+            cpg.add_node(node);
+        }
+        let engine = QueryEngine::new();
+        let run = |query: &str| engine.compute(&cpg, &QuerySpec::from_json(query).unwrap()).unwrap();
+
+        assert_eq!(run(r#"{"pipeline": [{"language": "rust"}]}"#), [CPGNodeId(0), CPGNodeId(1), CPGNodeId(2)]);
+        assert_eq!(run(r#"{"pipeline": [{"find": "Function"}, {"language": "python"}]}"#), [CPGNodeId(4)]);
+        assert!(run(r#"{"pipeline": [{"language": "go"}]}"#).is_empty());
+    }
+
+    #[test]
+    fn test_follow_reverse_uses_epoch_indices() {
+        use crate::cpg::CPGEpoch;
+
+        let mut cpg_epoch = CPGEpoch::new(1, 2);
+        {
+            let cpg = cpg_epoch.cpg_mut();
+            for i in 0..3u64 {
+                cpg.add_node(CPGNode::new(CPGNodeId(i), CPGNodeKind::Function,
+                    OriginRef::Function { function_id: FunctionId(i) }, ByteRange::new(0, 0)));
+            }
+            cpg.add_edge(crate::cpg::model::CPGEdge::new(crate::cpg::model::CPGEdgeId(0),
+                crate::cpg::model::CPGEdgeKind::Calls, CPGNodeId(2), CPGNodeId(0)));
+        }
+        let cpg_epoch = cpg_epoch.freeze();
+
+        let engine = QueryEngine::new();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}, {"follow_reverse": "Calls"}]}"#).unwrap();
+
+        let indexed = engine.compute_scoped(&cpg_epoch, &FileScope::default(), &spec).unwrap();
+        let unindexed = engine.compute(cpg_epoch.cpg(), &spec).unwrap();
+        assert_eq!(indexed, vec![CPGNodeId(2)]);
+        assert_eq!(indexed, unindexed);
+    }
+
+    /// Values 1 and 2 are copies of 0; 3 is unrelated; node 4 is a function
+    fn alias_cpg() -> CPG {
+        use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind};
+        use crate::semantic::model::ValueId;
+
+        let mut cpg = CPG::new();
+        for i in 0..4u64 {
+            cpg.add_node(CPGNode::new(CPGNodeId(i), CPGNodeKind::DfgValue,
+                OriginRef::Dfg { value_id: ValueId(i) }, ByteRange::new(0, 0)));
+        }
+        cpg.add_node(CPGNode::new(CPGNodeId(4), CPGNodeKind::Function,
+            OriginRef::Function { function_id: FunctionId(0) }, ByteRange::new(0, 0)));
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(0), CPGEdgeKind::DataFlow, CPGNodeId(0), CPGNodeId(1)));
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(1), CPGEdgeKind::DataFlow, CPGNodeId(0), CPGNodeId(2)));
+        cpg
+    }
+
+    #[test]
+    fn test_may_alias_stage() {
+        let cpg = alias_cpg();
+        let engine = QueryEngine::new();
+        let query = |json: &str| engine.compute(&cpg, &QuerySpec::from_json(json).unwrap());
+
+        assert_eq!(query(r#"{"pipeline": [{"may_alias": [1, 2]}]}"#).unwrap(), vec![CPGNodeId(0)]);
+        assert!(query(r#"{"pipeline": [{"may_alias": [1, 3]}]}"#).unwrap().is_empty());
+        let err = query(r#"{"pipeline": [{"may_alias": [1, 4]}]}"#).unwrap_err();
+        assert!(err.to_string().contains("not a DFG value"));
+    }
+
+    #[test]
+    fn test_points_to_map_saved_and_loaded_by_hash() {
+        use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let cpg = alias_cpg();
+        let hash = cpg.compute_hash();
+        SnapshotStore::open(dir.path()).unwrap().save(&cpg, 1).unwrap();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"may_alias": [1, 2]}]}"#).unwrap();
+
+        // First use computes the map and saves it next to the snapshot
+        let engine = QueryEngine::new().with_points_to_store(dir.path());
+        assert_eq!(engine.compute(&cpg, &spec).unwrap(), vec![CPGNodeId(0)]);
+        let store = SnapshotStore::open(dir.path()).unwrap();
+        assert_eq!(store.load_points_to(&hash).unwrap(), Some(PointerAnalysis::analyze_from_roots(&cpg)));
+
+        // A fresh engine serves the stored map (here replaced by one without facts)
+        store.save_points_to(&hash, &PointerAnalysis::analyze_seeded(&cpg, &[])).unwrap();
+        assert!(QueryEngine::new().with_points_to_store(dir.path()).compute(&cpg, &spec).unwrap().is_empty());
+        assert_eq!(engine.compute(&cpg, &spec).unwrap(), vec![CPGNodeId(0)]);
+
+        // A new hash misses both; incremental and full maps answer alike
+        let mut edited = cpg.clone();
+        edited.add_node(CPGNode::new(CPGNodeId(5), CPGNodeKind::DfgValue,
+            OriginRef::Dfg { value_id: crate::semantic::model::ValueId(5) }, ByteRange::new(0, 0)));
+        edited.add_edge(CPGEdge::new(CPGEdgeId(2), CPGEdgeKind::DataFlow, CPGNodeId(0), CPGNodeId(5)));
+        let incremental = QueryEngine::new().with_incremental_points_to(true);
+        incremental.compute(&cpg, &spec).unwrap();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"may_alias": [1, 5]}]}"#).unwrap();
+        assert_eq!(incremental.compute(&edited, &spec).unwrap(), vec![CPGNodeId(0)]);
+        assert_eq!(engine.compute(&edited, &spec).unwrap(), vec![CPGNodeId(0)]);
+    }
+
+    #[test]
+    fn test_explain_includes_task_and_chunk_timings() {
+        let cpg = synthetic_cpg();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}, {"filter": "Function"}]}"#).unwrap();
+        let chunked = QueryEngine::new().with_scheduler(Scheduler::new(2).with_chunk_size(300));
+        let unchunked = QueryEngine::new().with_scheduler(Scheduler::new(1).with_chunk_size(0));
+
+        let explanation = chunked.explain(&cpg, &spec).unwrap();
+        let chunks: Vec<usize> = explanation.stages.iter().map(|stage| stage.chunk_us.len()).collect();
+        assert_eq!(chunks, vec![4, 4]);
+        let json = serde_json::to_value(&explanation).unwrap();
+        assert!(json["stages"][0]["wall_us"].is_u64());
+        assert_eq!(json["stages"][0]["chunk_us"].as_array().unwrap().len(), 4);
+
+        // Results never depend on chunking; the explained structure does
+        let plain = unchunked.explain(&cpg, &spec).unwrap();
+        assert!(serde_json::to_value(&plain).unwrap()["stages"][0].get("chunk_us").is_none());
+        assert_eq!(chunked.compute(&cpg, &spec).unwrap(), unchunked.compute(&cpg, &spec).unwrap());
+        assert_eq!(explanation.structure_hash(), chunked.explain(&cpg, &spec).unwrap().structure_hash());
+    }
+
+    #[test]
+    fn test_path_stage() {
+        use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind};
+
+        // DataFlow 0 → 1 → 2 then Calls into function 10; DataFlow 0 → 3
+        // then ControlFlow into function 11
+        let mut cpg = CPG::new();
+        for (id, kind) in [(0, CPGNodeKind::DfgValue), (1, CPGNodeKind::DfgValue), (2, CPGNodeKind::DfgValue),
+            (3, CPGNodeKind::DfgValue), (10, CPGNodeKind::Function), (11, CPGNodeKind::Function)]
+        {
+            cpg.add_node(CPGNode::new(CPGNodeId(id), kind,
+                OriginRef::Function { function_id: FunctionId(id) }, ByteRange::new(0, 0)));
+        }
+        let edges = [
+            (CPGEdgeKind::DataFlow, 0, 1), (CPGEdgeKind::DataFlow, 1, 2), (CPGEdgeKind::Calls, 2, 10),
+            (CPGEdgeKind::DataFlow, 0, 3), (CPGEdgeKind::ControlFlow, 3, 11),
+        ];
+        for (i, (kind, from, to)) in edges.into_iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), kind, CPGNodeId(from), CPGNodeId(to)));
+        }
+        let engine = QueryEngine::new();
+        let query = |json: &str| engine.compute(&cpg, &QuerySpec::from_json(json).unwrap()).unwrap();
+
+        let through_call = r#"{"pipeline": [{"find": "DfgValue"},
+            {"path": [{"edge": "DataFlow", "min": 1, "max": 10}, {"edge": "Calls", "node": "Function"}]}]}"#;
+        assert_eq!(query(through_call), vec![CPGNodeId(10)]);
+        let through_flow = r#"{"pipeline": [{"find": "DfgValue"},
+            {"path": [{"edge": "DataFlow", "min": 1, "max": 10}, {"edge": "ControlFlow"}]}]}"#;
+        assert_eq!(query(through_flow), vec![CPGNodeId(11)]);
+
+        let explanation = engine.explain(&cpg, &QuerySpec::from_json(through_call).unwrap()).unwrap();
+        assert_eq!(explanation.stages[1].operator, "match_path");
+        assert_eq!(explanation.stages[1].actual_rows, 1);
+    }
+
+    #[test]
+    fn test_explain_two_stage_query() {
+        use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind};
+
+        // Three functions (0 calls 1) and two CFG nodes
+        let mut cpg = CPG::new();
+        for i in 0..5u64 {
+            let (kind, origin) = if i < 3 {
+                (CPGNodeKind::Function, OriginRef::Function { function_id: FunctionId(i) })
+            } else {
+                (CPGNodeKind::CfgNode, OriginRef::Cfg { node_id: crate::semantic::model::NodeId(i) })
+            };
+            cpg.add_node(CPGNode::new(CPGNodeId(i), kind, origin, ByteRange::new(0, 0)));
+        }
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(0), CPGEdgeKind::Calls, CPGNodeId(0), CPGNodeId(1)));
+
+        let engine = QueryEngine::new();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}, {"follow": "Calls"}]}"#).unwrap();
+        let explanation = engine.explain(&cpg, &spec).unwrap();
+
+        let [find, follow] = explanation.stages.as_slice() else { panic!("{:?}", explanation) };
+        assert_eq!((find.stage.as_str(), find.operator.as_str()), ("find", "find_nodes"));
+        assert_eq!((find.estimated_rows, find.actual_rows), (cpg.stats().nodes_by_kind[&CPGNodeKind::Function], 3));
+        assert_eq!((follow.stage.as_str(), follow.operator.as_str()), ("follow", "follow_edges"));
+        assert_eq!(follow.input_rows, 3);
+        assert_eq!((follow.estimated_rows, follow.actual_rows), (5, 1));
+        assert_eq!(explanation.result_count, 1);
+
+        // Same structure on every run, whatever the timings
+        let again = engine.explain(&cpg, &spec).unwrap();
+        assert_eq!(again.structure_hash(), explanation.structure_hash());
+        assert_eq!(engine.compute(&cpg, &spec).unwrap(), vec![CPGNodeId(1)]);
+    }
+
+    #[test]
+    fn test_aggregates_match_result_length() {
+        use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind};
+
+        let mut cpg = synthetic_cpg();
+        for i in 1000..1010u64 {
+            cpg.add_node(CPGNode::new(CPGNodeId(i), CPGNodeKind::CfgNode,
+                OriginRef::Cfg { node_id: crate::semantic::model::NodeId(i) }, ByteRange::new(0, 0)));
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i), CPGEdgeKind::Calls, CPGNodeId(i - 1000), CPGNodeId(i)));
+        }
+        let mut engine = QueryEngine::new();
+        let with = |stages: &str, terminal: &str| {
+            QuerySpec::from_json(&format!(r#"{{"pipeline": [{}{}]}}"#, stages, terminal)).unwrap()
+        };
+
+        for stages in [
+            r#"{"find": "Function"}"#,
+            r#"{"find": "Function"}, {"follow": "Calls"}"#,
+            r#"{"find": "Function"}, {"union": [{"find": "CfgNode"}]}"#,
+        ] {
+            let plain = engine.compute(&cpg, &with(stages, "")).unwrap();
+            let count = engine.aggregate(&cpg, &with(stages, r#", {"count": true}"#)).unwrap();
+            assert_eq!(count.value, Aggregate::Count(plain.len()), "{}", stages);
+            assert_eq!(count.counted, plain.len());
+
+            let by_kind = engine.aggregate(&cpg, &with(stages, r#", {"group_by": "kind"}"#)).unwrap();
+            let Aggregate::Groups(groups) = by_kind.value else { panic!("{:?}", by_kind) };
+            assert_eq!(groups.values().sum::<usize>(), plain.len());
+        }
+
+        let mixed = engine.aggregate(&cpg, &with(r#"{"find": "Function"}, {"union": [{"find": "CfgNode"}]}"#, r#", {"group_by": "kind"}"#)).unwrap();
+        assert_eq!(mixed.value, Aggregate::Groups(BTreeMap::from([("CfgNode".to_string(), 10), ("Function".to_string(), 1000)])));
+        assert_eq!(mixed.describe(), "group_by kind over find → union: 1010 nodes in 2 groups");
+
+        // No File nodes: every node falls outside a file
+        let by_file = engine.aggregate(&cpg, &with(r#"{"find": "CfgNode"}"#, r#", {"group_by": "file"}"#)).unwrap();
+        assert_eq!(by_file.value, Aggregate::Groups(BTreeMap::from([(NO_FILE.to_string(), 10)])));
+
+        // Stored and explained, but with no nodes to page through
+        let spec = with(r#"{"find": "Function"}"#, r#", {"count": true}"#);
+        let (result_id, explanation) = engine.run_explained(&cpg, &spec).unwrap();
+        let stored = engine.get_result(result_id).unwrap();
+        assert_eq!(stored.aggregate.as_ref().unwrap().value, Aggregate::Count(1000));
+        assert_eq!(stored.total(), 0);
+        assert_eq!(explanation.stages[1].operator, "count");
+        assert_eq!((explanation.stages[1].input_rows, explanation.stages[1].actual_rows), (1000, 1));
+        assert!(engine.compute(&cpg, &spec).unwrap_err().to_string().contains("yields an aggregate"));
+    }
+
+    #[test]
+    #[should_panic(expected = "CPG indices are stale")]
+    fn test_group_by_file_rejects_stale_indices() {
+        let mut cpg = synthetic_cpg();
+        let indices = CPGIndices::build(&cpg);
+        cpg.bump_generation();
+
+        let nodes: Vec<_> = cpg.nodes.iter().map(|node| node.id).collect();
+        let _ = fold(&cpg, Some(&indices), None, &nodes, Aggregation::GroupBy(GroupKey::File));
+    }
+
+    #[test]
+    fn test_cancellation_stops_queries() {
+        use crate::execution::{Interrupted, Progress};
+        use std::time::Duration;
+
+        let cpg = synthetic_cpg();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}, {"follow": "Calls"}]}"#).unwrap();
+        let plain = QueryEngine::new().compute(&cpg, &spec).unwrap();
+
+        let unexpired = CancellationToken::new().with_timeout(Duration::from_secs(3600));
+        assert_eq!(QueryEngine::new().with_cancellation(unexpired).compute(&cpg, &spec).unwrap(), plain);
+
+        let token = CancellationToken::new();
+        let mut engine = QueryEngine::new().with_cancellation(token.clone());
+        token.cancel();
+        let err = engine.run(&cpg, &spec).unwrap_err();
+        assert_eq!(err.downcast_ref::<Interrupted>(), Some(&Interrupted::Cancelled(Progress::default())));
+        assert!(engine.get_result(ResultId(1)).is_none(), "Interrupted queries store nothing");
+
+        let expired = CancellationToken::new().with_timeout(Duration::ZERO);
+        engine.set_cancellation(expired);
+        let err = engine.aggregate(&cpg, &QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}, {"count": true}]}"#).unwrap());
+        assert!(matches!(err.unwrap_err().downcast_ref::<Interrupted>(), Some(Interrupted::Timeout(_))));
+    }
+
+    #[test]
+    fn test_explain_nests_sub_pipelines() {
+        let cpg = synthetic_cpg();
+        let engine = QueryEngine::new();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}, {"difference": [{"find": "Function"}]}]}"#).unwrap();
+
+        let explanation = engine.explain(&cpg, &spec).unwrap();
+
+        assert_eq!(explanation.stages[1].sub_pipeline.len(), 1);
+        assert_eq!(explanation.stages[1].input_rows, 2000);
+        assert_eq!(explanation.result_count, 0);
+    }
+
+    /// (code, stage, location) of one expected issue
+    type Expected<'a> = (QueryIssueCode, Option<usize>, &'a str);
+
+    #[test]
+    fn test_validate_reports_code_stage_and_location() {
+        use QueryIssueCode::*;
+        let cases: &[(&str, &[Expected])] = &[
+            (r#"{"pipeline": [{"find": "Function"}"#, &[(InvalidJson, None, "query")]),
+            (r#"[{"find": "Function"}]"#, &[(TypeMismatch, None, "query")]),
+            (r#"{"limit": 3}"#, &[(MissingField, None, "query")]),
+            (r#"{"pipelne": [], "pipeline": []}"#, &[(UnknownField, None, "pipelne")]),
+            (r#"{"pipeline": {"find": "Function"}}"#, &[(TypeMismatch, None, "pipeline")]),
+            (r#"{"pipeline": [{"find": "Function"}, {"folow": "Calls"}]}"#, &[(UnknownStage, Some(1), "pipeline[1].folow")]),
+            (r#"{"pipeline": [{"find": "Function", "filter": "File"}]}"#, &[(AmbiguousStage, Some(0), "pipeline[0]")]),
+            (r#"{"pipeline": [{"find": "Functon"}]}"#, &[(UnknownVariant, Some(0), "pipeline[0].find")]),
+            (r#"{"pipeline": [{"find": 3}]}"#, &[(TypeMismatch, Some(0), "pipeline[0].find")]),
+            (r#"{"pipeline": [{"at": {"file": "a.rs", "offset": "12"}}]}"#, &[(TypeMismatch, Some(0), "pipeline[0].at.offset")]),
+            (r#"{"pipeline": [{"at": {"file": "a.rs"}}]}"#, &[(MissingField, Some(0), "pipeline[0].at")]),
+            (r#"{"pipeline": [{"overlapping": {"file": "a.rs", "start": 9, "end": 2}}]}"#, &[(OutOfRange, Some(0), "pipeline[0].overlapping")]),
+            (
+                r#"{"pipeline": [{"find": "Function"}, {"path": [{"edge": "Calls", "max": 40}]}]}"#,
+                &[(OutOfRange, Some(1), "pipeline[1].path[0]")],
+            ),
+            (r#"{"pipeline": [{"find": "Function"}, {"path": []}]}"#, &[(OutOfRange, Some(1), "pipeline[1].path")]),
+            (
+                r#"{"pipeline": [{"find": "Function"}, {"path": [{"edge": "Calls", "min": 3, "max": 2, "hops": 1}]}]}"#,
+                &[(UnknownField, Some(1), "pipeline[1].path[0].hops"), (OutOfRange, Some(1), "pipeline[1].path[0]")],
+            ),
+            (r#"{"pipeline": [{"function_matches": "a[b"}]}"#, &[(InvalidPattern, Some(0), "pipeline[0].function_matches")]),
+            (r#"{"pipeline": [{"find": "Function"}, {"count": false}]}"#, &[(InvalidValue, Some(1), "pipeline[1].count")]),
+            (r#"{"pipeline": [{"follow": "Calls"}]}"#, &[(NoInput, Some(0), "pipeline[0].follow")]),
+            (
+                r#"{"pipeline": [{"find": "Function"}, {"union": [{"filter": "File"}]}]}"#,
+                &[(NoInput, Some(1), "pipeline[1].union[0].filter")],
+            ),
+            (
+                r#"{"pipeline": [{"find": "Function"}, {"count": true}, {"filter": "File"}]}"#,
+                &[(MisplacedAggregate, Some(1), "pipeline[1].count")],
+            ),
+            (
+                r#"{"pipeline": [{"find": "Function"}, {"union": [{"find": "File"}, {"group_by": "kind"}]}]}"#,
+                &[(MisplacedAggregate, Some(1), "pipeline[1].union[1].group_by")],
+            ),
+            (
+                r#"{"pipeline": [{"find": "Function"}, {"group_by": "name"}], "offset": -1, "order_by": "labels"}"#,
+                &[(UnknownVariant, Some(1), "pipeline[1].group_by"), (TypeMismatch, None, "offset"), (UnknownVariant, None, "order_by")],
+            ),
+            (r#"{"pipeline": [{"may_alias": [1]}]}"#, &[(TypeMismatch, Some(0), "pipeline[0].may_alias")]),
+        ];
+
+        for (query, expected) in cases {
+            let issues = validate_query(query);
+            let found: Vec<_> = issues.iter().map(|i| (i.code, i.stage, i.location.as_str())).collect();
+            assert_eq!(found, *expected, "{}", query);
+            assert!(issues.iter().all(|i| !i.suggestion.is_empty() && !i.suggestion.contains('\n')), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_validate_accepts_what_the_parser_accepts() {
+        let valid = [
+            r#"{"pipeline": []}"#,
+            r#"{"pipeline": [{"group_by": "file"}], "limit": null, "offset": 2, "order_by": "label"}"#,
+            r#"{"name": "calls", "description": "", "pipeline": [{"function": "main"}, {"follow": "Calls"}, {"count": true}]}"#,
+            r#"{"pipeline": [{"find": "Function"}, {"path": [{"edge": "Calls", "node": "Function", "min": 0, "max": 32}]}]}"#,
+            r#"{"pipeline": [{"overlapping": {"file": "a.rs", "start": 2, "end": 2}}, {"difference": [{"find": "File"}]}]}"#,
+            r#"{"pipeline": [{"may_alias": [1, 2]}, {"union": [{"in_file": "src"}, {"filter": "DfgValue"}]}]}"#,
+            r#"{"pipeline": [{"function_matches": "handle_*"}, {"parameter": 0}]}"#,
+        ];
+        for query in valid {
+            assert_eq!(validate_query(query), [], "{}", query);
+            assert!(QuerySpec::from_json(query).is_ok(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_issue_display_and_suggestion() {
+        let issues = validate_query(r#"{"pipeline": [{"find": "Function"}, {"follow": "Cals"}]}"#);
+        assert_eq!(
+            issues[0].to_string(),
+            "pipeline[1].follow: unknown edge kind `Cals` (unknown_variant); did you mean `Calls`?"
+        );
+        let issues = validate_query(r#"{"pipeline": [{"zzzzzzzz": 1}]}"#);
+        assert!(issues[0].suggestion.starts_with("expected one of: find, follow"), "{}", issues[0]);
     }
 }
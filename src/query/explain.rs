@@ -0,0 +1,124 @@
+//! Query plan explanation (Step 4.3)
+//!
+//! What the engine did for one query: each DSL stage, the operator it
+//! compiled to, estimated vs actual cardinality, and whether the sorted-set
+//! (SIMD) path ran.
+//!
+//! **Deterministic structure**: Everything except `wall_us` and the
+//! `chunk_us` entries depends only on the query, the CPG and the chunk size.
+//! Those are measurements; `structure_hash` leaves them out.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Explanation of one executed query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanExplanation {
+    /// Top-level stages in execution order
+    pub stages: Vec<StageExplanation>,
+
+    /// Result size after ordering
+    pub result_count: usize,
+}
+
+/// One DSL stage, compiled to a single-task execution stage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageExplanation {
+    /// Position in its pipeline
+    pub index: usize,
+
+    /// DSL stage name (`find`, `follow`, ...)
+    pub stage: String,
+
+    /// Task the stage compiled to
+    pub task_id: u64,
+
+    /// Work fragment the task ran (`find_nodes`, `intersect`, ...)
+    pub operator: String,
+
+    /// Nodes fed into the operator
+    pub input_rows: usize,
+
+    /// Result size predicted from `CPG::stats()`
+    pub estimated_rows: usize,
+
+    /// Result size the scheduler recorded
+    pub actual_rows: usize,
+
+    /// Cost model estimate (rounded up)
+    pub estimated_cost: u64,
+
+    /// Whether a set operation took the sorted (SIMD) path
+    pub simd: bool,
+
+    /// Wall time of the task. **Non-deterministic**: excluded from
+    /// `structure_hash`
+    pub wall_us: u64,
+
+    /// Wall time of each chunk of a chunked scan, in chunk order.
+    /// **Non-deterministic** values; only their number is hashed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunk_us: Vec<u64>,
+
+    /// Stages of a nested `union`/`difference` pipeline
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sub_pipeline: Vec<StageExplanation>,
+}
+
+impl PlanExplanation {
+    /// Copy with every `wall_us` and `chunk_us` entry zeroed
+    pub fn without_timings(&self) -> Self {
+        fn strip(stages: &[StageExplanation]) -> Vec<StageExplanation> {
+            stages.iter()
+                .map(|stage| StageExplanation {
+                    wall_us: 0,
+                    chunk_us: vec![0; stage.chunk_us.len()],
+                    sub_pipeline: strip(&stage.sub_pipeline),
+                    ..stage.clone()
+                })
+                .collect()
+        }
+        Self { stages: strip(&self.stages), result_count: self.result_count }
+    }
+
+    /// SHA256 of the explanation without timings
+    ///
+    /// Equal for every run of the same query on the same CPG.
+    pub fn structure_hash(&self) -> String {
+        let json = serde_json::to_vec(&self.without_timings())
+            .expect("PlanExplanation always serializes");
+        format!("{:x}", Sha256::digest(&json))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(wall_us: u64) -> StageExplanation {
+        StageExplanation {
+            index: 0,
+            stage: "find".into(),
+            task_id: 0,
+            operator: "find_nodes".into(),
+            input_rows: 0,
+            estimated_rows: 3,
+            actual_rows: 3,
+            estimated_cost: 10,
+            simd: false,
+            wall_us,
+            chunk_us: vec![wall_us],
+            sub_pipeline: vec![],
+        }
+    }
+
+    #[test]
+    fn test_structure_hash_ignores_timings() {
+        let fast = PlanExplanation { stages: vec![stage(1)], result_count: 3 };
+        let slow = PlanExplanation { stages: vec![stage(900)], result_count: 3 };
+        let other = PlanExplanation { stages: vec![stage(1)], result_count: 4 };
+
+        assert_eq!(fast.structure_hash(), slow.structure_hash());
+        assert_ne!(fast.structure_hash(), other.structure_hash());
+    }
+}
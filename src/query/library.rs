@@ -0,0 +1,181 @@
+//! Saved queries
+//!
+//! A query directory holds `*.json` files, each an ordinary query file with
+//! two more fields:
+//!
+//! ```json
+//! {
+//!   "name": "unsanitized-sql",
+//!   "description": "Handlers that reach the SQL layer",
+//!   "pipeline": [{"function_matches": "handle_*"}]
+//! }
+//! ```
+//!
+//! **Fail-closed**: Every file is validated (`validate_query`) and parsed
+//! when the directory is loaded. A file that is not a valid query, or a
+//! name used twice, fails the whole load with an error naming the file(s).
+//! Files are read in name order, so the error is the same on every run.
+
+use crate::query::dsl::QuerySpec;
+use crate::query::engine::validate_query;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// One saved query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedQuery {
+    pub name: String,
+    pub description: String,
+
+    /// File the query was loaded from
+    pub file: PathBuf,
+
+    pub spec: QuerySpec,
+}
+
+/// `name` and `description` of a saved query file
+#[derive(Deserialize)]
+struct Header {
+    name: String,
+    description: String,
+}
+
+/// Saved queries of one directory, by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryLibrary {
+    queries: BTreeMap<String, SavedQuery>,
+}
+
+impl QueryLibrary {
+    /// Load and validate every `*.json` file directly in `dir`
+    pub fn load(dir: &Path) -> Result<Self> {
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read query directory {}", dir.display()))?;
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry
+                .with_context(|| format!("Failed to read query directory {}", dir.display()))?
+                .path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+        files.sort();
+
+        let mut library = Self::default();
+        for file in files {
+            let query = load_file(&file).with_context(|| format!("Invalid saved query {}", file.display()))?;
+            if let Some(existing) = library.queries.get(&query.name) {
+                bail!(
+                    "Duplicate saved query name '{}' in {} and {}",
+                    query.name,
+                    existing.file.display(),
+                    file.display()
+                );
+            }
+            library.queries.insert(query.name.clone(), query);
+        }
+        Ok(library)
+    }
+
+    /// Query by name
+    pub fn get(&self, name: &str) -> Option<&SavedQuery> {
+        self.queries.get(name)
+    }
+
+    /// All queries in name order
+    pub fn iter(&self) -> impl Iterator<Item = &SavedQuery> {
+        self.queries.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queries.is_empty()
+    }
+}
+
+/// Parse one saved query file
+fn load_file(file: &Path) -> Result<SavedQuery> {
+    let text = std::fs::read_to_string(file).context("Failed to read file")?;
+    let mut value: Value = serde_json::from_str(&text).context("Failed to parse query")?;
+    let header = Header::deserialize(&value).context("Missing name or description")?;
+    if header.name.trim().is_empty() {
+        bail!("name is empty");
+    }
+    let issues = validate_query(&text);
+    if !issues.is_empty() {
+        let lines: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        bail!("{}", lines.join("\n"));
+    }
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("name");
+        fields.remove("description");
+    }
+    let spec = QuerySpec::from_json(&value.to_string())?;
+
+    Ok(SavedQuery { name: header.name, description: header.description, file: file.to_path_buf(), spec })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::dsl::QueryStage;
+    use tempfile::TempDir;
+
+    fn write(dir: &Path, file: &str, text: &str) {
+        std::fs::write(dir.join(file), text).unwrap();
+    }
+
+    #[test]
+    fn test_load_two_queries_in_name_order() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "b.json", r#"{"name": "functions", "description": "All functions", "pipeline": [{"find": "Function"}]}"#);
+        write(dir.path(), "a.json", r#"{"name": "handlers", "description": "Request handlers", "pipeline": [{"function_matches": "handle_*"}], "limit": 5}"#);
+        write(dir.path(), "notes.txt", "not a query");
+
+        let library = QueryLibrary::load(dir.path()).unwrap();
+        let names: Vec<&str> = library.iter().map(|q| q.name.as_str()).collect();
+        assert_eq!(names, ["functions", "handlers"]);
+
+        let handlers = library.get("handlers").unwrap();
+        assert_eq!(handlers.description, "Request handlers");
+        assert_eq!(handlers.file, dir.path().join("a.json"));
+        assert_eq!(handlers.spec.options.limit, Some(5));
+        assert_eq!(library.get("functions").unwrap().spec.pipeline, [QueryStage::Find(crate::cpg::model::CPGNodeKind::Function)]);
+        assert!(library.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_invalid_file_names_the_file() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "good.json", r#"{"name": "ok", "description": "", "pipeline": []}"#);
+        write(dir.path(), "bad.json", r#"{"name": "bad", "description": "", "pipeline": [{"count": true}, {"find": "Function"}]}"#);
+
+        let message = format!("{:#}", QueryLibrary::load(dir.path()).unwrap_err());
+        assert!(message.contains("bad.json"), "{}", message);
+        assert!(message.contains("count must be the last stage"), "{}", message);
+
+        write(dir.path(), "bad.json", r#"{"description": "", "pipeline": []}"#);
+        let message = format!("{:#}", QueryLibrary::load(dir.path()).unwrap_err());
+        assert!(message.contains("bad.json"), "{}", message);
+    }
+
+    #[test]
+    fn test_duplicate_names_rejected_deterministically() {
+        let dir = TempDir::new().unwrap();
+        for file in ["z.json", "m.json", "a.json"] {
+            write(dir.path(), file, r#"{"name": "same", "description": "", "pipeline": []}"#);
+        }
+
+        let message = QueryLibrary::load(dir.path()).unwrap_err().to_string();
+        assert!(message.contains("'same'"), "{}", message);
+        let (first, second) = (dir.path().join("a.json"), dir.path().join("m.json"));
+        assert!(message.ends_with(&format!("{} and {}", first.display(), second.display())), "{}", message);
+    }
+}
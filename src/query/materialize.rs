@@ -0,0 +1,297 @@
+//! Materialized query results (source excerpts)
+//!
+//! A stored result is a list of node IDs. `ResultMaterializer` resolves
+//! each one against its epoch and a `SourceProvider`: stable key (see
+//! `analysis::findings`), kind, label, file, 1-based line and column, and
+//! the source text of its range. Each file is read once per call; its
+//! `LineIndex` is cached in the epoch.
+//!
+//! Columns count characters. Ranges that start or end inside a multibyte
+//! character are widened to whole characters, and excerpts longer than
+//! `MAX_EXCERPT_BYTES` are cut at a character boundary, so an excerpt is
+//! always valid UTF-8 (invalid sequences in the file become U+FFFD).
+//!
+//! A snapshot scanned with `normalize_line_endings` was built from each
+//! file's LF view. Line, column and excerpt come from that view; `range` is
+//! mapped back through its `OffsetTable`, so it indexes the file on disk.
+//!
+//! **Fail closed**: a provider must not serve contents other than the ones
+//! the epoch was built from. `RepoSources` checks each file it reads
+//! against the snapshot's content hash; a file that changed is an error,
+//! never a stale excerpt.
+
+use crate::analysis::StableKeys;
+use crate::cpg::model::{CPGNodeId, CPGNodeKind};
+use crate::cpg::FrozenCPGEpoch;
+use crate::io::{normalize_line_endings, IOBackend, OffsetTable};
+use crate::repo::{normalize_path, RepoScanner};
+use crate::types::{ByteRange, FileId, RepoSnapshot};
+use crate::util::lines::{ceil_char_boundary, floor_char_boundary};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Longest excerpt, in bytes
+pub const MAX_EXCERPT_BYTES: usize = 256;
+
+/// Where materialization reads a file's path and contents
+pub trait SourceProvider {
+    /// Normalized repository-relative path (None for unknown files)
+    fn path(&self, file_id: FileId) -> Option<String>;
+
+    /// Contents the epoch was built from; fails if the file changed since
+    fn read(&self, file_id: FileId) -> Result<SourceText>;
+}
+
+/// A file as analyzed
+pub struct SourceText {
+    pub bytes: Vec<u8>,
+
+    /// `bytes` offset → offset in the stored file
+    pub offsets: OffsetTable,
+}
+
+/// Files of a scanned snapshot, read through a pipeline's backend
+pub struct RepoSources<'a> {
+    snapshot: &'a RepoSnapshot,
+    backend: Arc<dyn IOBackend>,
+    normalize_line_endings: bool,
+}
+
+impl<'a> RepoSources<'a> {
+    /// Read `snapshot`'s files through `backend` (`Pipeline::source_backend`)
+    pub fn new(snapshot: &'a RepoSnapshot, backend: Arc<dyn IOBackend>) -> Self {
+        Self { snapshot, backend, normalize_line_endings: false }
+    }
+
+    /// Serve LF views, as a scan with normalized line endings hashed them
+    /// (`Pipeline::normalizes_line_endings`)
+    pub fn with_normalized_line_endings(mut self, normalize: bool) -> Self {
+        self.normalize_line_endings = normalize;
+        self
+    }
+}
+
+impl SourceProvider for RepoSources<'_> {
+    fn path(&self, file_id: FileId) -> Option<String> {
+        self.snapshot.files.get(&file_id).map(|meta| normalize_path(&meta.path))
+    }
+
+    fn read(&self, file_id: FileId) -> Result<SourceText> {
+        let meta = self.snapshot.files.get(&file_id).ok_or_else(|| anyhow!("Unknown file: file:{:016x}", file_id.as_u64()))?;
+        let bytes = self.backend.read_file(&self.snapshot.root.join(&meta.path))
+            .with_context(|| format!("Failed to read {}", meta.path.display()))?;
+        let (bytes, offsets) = match self.normalize_line_endings {
+            true => normalize_line_endings(bytes),
+            false => (bytes, OffsetTable::default()),
+        };
+        if RepoScanner::hash_bytes(&bytes) != meta.content_hash {
+            bail!("{} changed since it was analyzed", meta.path.display());
+        }
+        Ok(SourceText { bytes, offsets })
+    }
+}
+
+/// One result node with its location and source
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaterializedResult {
+    pub node_id: u64,
+
+    /// `StableKeys` key (`node:<id>` outside every file)
+    pub stable_id: String,
+
+    pub kind: CPGNodeKind,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// Normalized relative path (None outside every file, or if the
+    /// provider does not know the file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+
+    /// Byte range in the stored file
+    pub range: ByteRange,
+
+    /// 1-based line of `range.start` (None without a file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+
+    /// 1-based column of `range.start`, in characters (None without a file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+
+    /// Source text of `range`, at most `MAX_EXCERPT_BYTES` (None without a file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+}
+
+/// Resolves result node IDs against one epoch and its sources
+pub struct ResultMaterializer<'a> {
+    epoch: &'a FrozenCPGEpoch,
+    sources: &'a dyn SourceProvider,
+    keys: StableKeys,
+
+    /// Node → position in `CPG::nodes`
+    positions: HashMap<CPGNodeId, usize>,
+}
+
+impl<'a> ResultMaterializer<'a> {
+    /// Build stable keys and the node lookup for `epoch`
+    pub fn new(epoch: &'a FrozenCPGEpoch, sources: &'a dyn SourceProvider) -> Self {
+        let cpg = epoch.cpg();
+        Self {
+            epoch,
+            sources,
+            keys: StableKeys::build(cpg, epoch.indices()),
+            positions: cpg.nodes.iter().enumerate().map(|(i, node)| (node.id, i)).collect(),
+        }
+    }
+
+    /// Materialize `nodes`, in order
+    ///
+    /// Fails on a node not in the CPG or a file the provider cannot serve.
+    pub fn materialize(&self, nodes: &[CPGNodeId]) -> Result<Vec<MaterializedResult>> {
+        let cpg = self.epoch.cpg();
+        let mut sources: HashMap<FileId, SourceText> = HashMap::new();
+        nodes.iter()
+            .map(|id| {
+                let position = *self.positions.get(id).ok_or_else(|| anyhow!("Unknown node: {}", id.0))?;
+                let node = &cpg.nodes[position];
+                let mut result = MaterializedResult {
+                    node_id: id.0,
+                    stable_id: self.keys.key(*id),
+                    kind: node.kind,
+                    label: cpg.label(node).map(str::to_string),
+                    file_path: None,
+                    range: node.source_range,
+                    line: None,
+                    column: None,
+                    excerpt: None,
+                };
+
+                let Some(file_id) = self.file_of(position) else { return Ok(result) };
+                let Some(path) = self.sources.path(file_id) else { return Ok(result) };
+                let source = match sources.entry(file_id) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(self.sources.read(file_id)?),
+                };
+                let (line, column) = self.epoch.line_index(file_id, &source.bytes)
+                    .position(&source.bytes, node.source_range.start);
+                result.file_path = Some(path);
+                result.range = source.offsets.original_range(node.source_range);
+                result.line = Some(line);
+                result.column = Some(column);
+                result.excerpt = Some(excerpt(&source.bytes, node.source_range));
+                Ok(result)
+            })
+            .collect()
+    }
+
+    /// File whose node range holds `position`
+    fn file_of(&self, position: usize) -> Option<FileId> {
+        self.epoch.indices().file_ranges().iter()
+            .find(|(_, range)| range.contains(&position))
+            .map(|(file_id, _)| *file_id)
+    }
+}
+
+/// Text of `range`, widened to whole characters and cut to `MAX_EXCERPT_BYTES`
+fn excerpt(source: &[u8], range: ByteRange) -> String {
+    if range.is_empty() {
+        return String::new();
+    }
+    let start = floor_char_boundary(source, range.start);
+    let end = ceil_char_boundary(source, range.end);
+    let end = match end - start > MAX_EXCERPT_BYTES {
+        true => floor_char_boundary(source, start + MAX_EXCERPT_BYTES),
+        false => end,
+    };
+    String::from_utf8_lossy(&source[start..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MemoryBackend;
+    use crate::pipeline::Pipeline;
+    use crate::query::{QueryEngine, QuerySpec};
+
+    const SOURCE: &str = "// Grüße\nfn größe() -> &'static str { \"日本\" }\nfn b() { größe(); }\n";
+
+    fn backend() -> Arc<MemoryBackend> {
+        let mut backend = MemoryBackend::new(HashMap::new());
+        backend.insert("/repo/src/ü.rs", SOURCE);
+        Arc::new(backend)
+    }
+
+    #[test]
+    fn test_non_ascii_positions_and_excerpts() {
+        let pipeline = Pipeline::default().with_backend(backend());
+        let output = pipeline.run(std::path::Path::new("/repo")).unwrap();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+        let nodes = QueryEngine::new().compute(output.cpg_epoch.cpg(), &spec).unwrap();
+
+        let sources = RepoSources::new(&output.snapshot, pipeline.source_backend());
+        let results = ResultMaterializer::new(&output.cpg_epoch, &sources).materialize(&nodes).unwrap();
+
+        let grosse = results.iter().find(|r| r.label.as_deref() == Some("größe")).unwrap();
+        assert_eq!(grosse.file_path.as_deref(), Some("src/ü.rs"));
+        assert_eq!((grosse.line, grosse.column), (Some(2), Some(1)));
+        assert_eq!(grosse.excerpt.as_deref(), Some("fn größe() -> &'static str { \"日本\" }"));
+        assert!(grosse.stable_id.contains("/größe#0/Function/größe#0"), "{}", grosse.stable_id);
+        let b = results.iter().find(|r| r.label.as_deref() == Some("b")).unwrap();
+        assert_eq!((b.line, b.column), (Some(3), Some(1)));
+    }
+
+    #[test]
+    fn test_excerpt_clamps_to_char_boundaries() {
+        let source = "a日本b".as_bytes();
+        // 日 is bytes 1..4, 本 4..7: both ends inside a character
+        assert_eq!(excerpt(source, ByteRange::new(2, 5)), "日本");
+        assert_eq!(excerpt(source, ByteRange::new(7, 8)), "b");
+        assert_eq!(excerpt(source, ByteRange::new(3, 3)), "");
+
+        let long = "é".repeat(MAX_EXCERPT_BYTES);
+        let cut = excerpt(long.as_bytes(), ByteRange::new(1, long.len()));
+        assert_eq!(cut, "é".repeat(MAX_EXCERPT_BYTES / 2));
+    }
+
+    #[test]
+    fn test_normalized_ranges_index_stored_file() {
+        let stored = SOURCE.replace('\n', "\r\n");
+        let mut backend = MemoryBackend::new(HashMap::new());
+        backend.insert("/repo/src/ü.rs", stored.as_str());
+        let pipeline = Pipeline::default().with_backend(Arc::new(backend)).with_normalized_line_endings(true);
+        let output = pipeline.run(std::path::Path::new("/repo")).unwrap();
+        let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+        let nodes = QueryEngine::new().compute(output.cpg_epoch.cpg(), &spec).unwrap();
+
+        let sources = RepoSources::new(&output.snapshot, pipeline.source_backend()).with_normalized_line_endings(true);
+        let results = ResultMaterializer::new(&output.cpg_epoch, &sources).materialize(&nodes).unwrap();
+
+        let b = results.iter().find(|r| r.label.as_deref() == Some("b")).unwrap();
+        assert_eq!((b.line, b.column), (Some(3), Some(1)));
+        assert_eq!(b.excerpt.as_deref(), Some("fn b() { größe(); }"));
+        assert_eq!(&stored[b.range.start..b.range.end], "fn b() { größe(); }");
+
+        // Without normalization the stored file no longer matches its hash
+        let raw = RepoSources::new(&output.snapshot, pipeline.source_backend());
+        assert!(ResultMaterializer::new(&output.cpg_epoch, &raw).materialize(&nodes).is_err());
+    }
+
+    #[test]
+    fn test_changed_source_fails_closed() {
+        let pipeline = Pipeline::default().with_backend(backend());
+        let output = pipeline.run(std::path::Path::new("/repo")).unwrap();
+        let nodes: Vec<_> = output.cpg_epoch.cpg().nodes.iter().map(|n| n.id).take(3).collect();
+
+        let mut edited = MemoryBackend::new(HashMap::new());
+        edited.insert("/repo/src/ü.rs", "fn other() {}\n");
+        let sources = RepoSources::new(&output.snapshot, Arc::new(edited));
+        let err = ResultMaterializer::new(&output.cpg_epoch, &sources).materialize(&nodes).unwrap_err();
+        assert!(err.to_string().contains("changed since it was analyzed"), "{}", err);
+    }
+}
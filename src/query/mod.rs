@@ -2,8 +2,22 @@
 //!
 //! Contains deterministic query execution primitives
 
+pub mod cache;
+pub mod dsl;
 pub mod engine;
+pub mod explain;
+pub mod library;
+pub mod materialize;
+pub mod pattern;
 pub mod primitives;
+pub mod scope;
 
-pub use engine::{QueryEngine, QueryResult};
-pub use primitives::QueryPrimitives;
+pub use cache::{CacheKey, CacheOutcome, ResultCache};
+pub use dsl::{Aggregation, GroupKey, OrderKey, QueryOptions, QuerySpec, QueryStage, TaintQuery};
+pub use engine::{validate_query, Aggregate, QueryEngine, QueryIssue, QueryIssueCode, QueryResult, ResultId, ResultPage, StoredAggregate};
+pub use explain::{PlanExplanation, StageExplanation};
+pub use library::{QueryLibrary, SavedQuery};
+pub use materialize::{MaterializedResult, RepoSources, ResultMaterializer, SourceProvider, SourceText};
+pub use pattern::NamePattern;
+pub use primitives::{EdgeStep, QueryPrimitives};
+pub use scope::FileScope;
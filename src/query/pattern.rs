@@ -0,0 +1,84 @@
+//! Name patterns for `function_matches` (Step 3.6)
+//!
+//! The same glob syntax as scanner extensions and `.vcrignore` (see
+//! `util::glob`), matched against the whole function name:
+//!
+//! - `*` matches any run of characters, `?` exactly one
+//! - `[abc]`, `[a-z]`, `[!abc]` match one character listed (or not)
+//! - `\` makes the next character literal
+//!
+//! So `handle_*` finds names starting with `handle_`, `*_test` names ending
+//! in `_test` and `*login*` names containing `login`. Names have no `/`, so
+//! patterns with one (or with `**`) never match. Matching runs the glob's
+//! automaton, linear in the name length.
+
+use crate::util::glob::Glob;
+use anyhow::Result;
+
+/// Compiled name pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamePattern {
+    glob: Glob,
+}
+
+impl NamePattern {
+    /// Parse a pattern
+    pub fn parse(pattern: &str) -> Result<Self> {
+        Ok(Self { glob: Glob::parse(pattern)? })
+    }
+
+    /// Whether the whole of `name` matches
+    pub fn matches(&self, name: &str) -> bool {
+        self.glob.matches(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        NamePattern::parse(pattern).unwrap().matches(name)
+    }
+
+    #[test]
+    fn test_whole_name_matches() {
+        assert!(matches("handle_*", "handle_login"));
+        assert!(!matches("handle_*", "do_handle_login"));
+        assert!(matches("*handle_*", "do_handle_login"));
+        assert!(matches("*_login", "handle_login"));
+        assert!(!matches("*_login", "handle_login_v2"));
+        assert!(matches("main", "main"));
+        assert!(!matches("main", "mainly"));
+    }
+
+    #[test]
+    fn test_wildcards_classes_and_escapes() {
+        assert!(matches("h*_login", "handle_login"));
+        assert!(matches("h*_login", "h_login"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("handle_[lp]*", "handle_post"));
+        assert!(!matches("handle_[!lp]*", "handle_login"));
+        assert!(matches("a\\*c", "a*c"));
+        assert!(!matches("a\\*c", "abc"));
+        assert!(matches("*", "anything"));
+        // Regex syntax is literal
+        assert!(matches("^a.c$", "^a.c$"));
+        assert!(!matches("^a.c$", "abc"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_globs() {
+        for pattern in ["", "[ab", "a]", "a\\", "a***", "a**"] {
+            assert!(NamePattern::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_repeats_do_not_backtrack() {
+        let name = "a".repeat(200);
+        assert!(!matches("*a*a*a*a*a*a*a*a*a*a*a*a*b", &name));
+        assert!(matches("*a*a*a*a*a*a*a*a*a*a*a*a*", &name));
+    }
+}
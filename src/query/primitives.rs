@@ -1,14 +1,81 @@
 //! Query primitives (Step 3.6)
 //!
 //! **RESTRICTED ON PURPOSE**
-//! Only 5 primitives. No unbounded recursion.
+//! Only 15 primitives. No unbounded recursion.
+//!
+//! `match_path` is the one repeating traversal besides `reachable_within`:
+//! every step of a path pattern repeats at most `MAX_PATH_REPEAT` times and
+//! a pattern has at most `MAX_PATH_STEPS` steps.
 
-use crate::cpg::model::{CPG, CPGNodeId, CPGNodeKind, CPGEdgeKind};
-use std::collections::{HashSet, VecDeque};
+use crate::cpg::index::CPGIndices;
+use crate::cpg::model::{CPG, CPGNode, CPGNodeId, CPGNodeKind, CPGEdgeKind};
+use crate::execution::cancel::{uninterrupted, CancellationToken, Checkpoint, Interrupted};
+use crate::query::pattern::NamePattern;
+use crate::simd;
+use crate::types::{ByteRange, FileId};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Maximum reachability depth
 const MAX_REACHABILITY_DEPTH: usize = 100;
 
+/// Maximum repetitions of one path step
+pub const MAX_PATH_REPEAT: usize = 32;
+
+/// Maximum steps in a path pattern
+pub const MAX_PATH_STEPS: usize = 16;
+
+/// One step of a path pattern: `min..=max` consecutive edges of one kind
+///
+/// Every node entered by the step must have kind `node`, if given. A step
+/// with `min: 0` is optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EdgeStep {
+    /// Edge kind followed
+    pub edge: CPGEdgeKind,
+
+    /// Kind every entered node must have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<CPGNodeKind>,
+
+    /// Fewest edges taken
+    #[serde(default = "one")]
+    pub min: usize,
+
+    /// Most edges taken (capped at `MAX_PATH_REPEAT`)
+    #[serde(default = "one")]
+    pub max: usize,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl EdgeStep {
+    /// Exactly one edge of a kind
+    pub fn new(edge: CPGEdgeKind) -> Self {
+        Self { edge, node: None, min: 1, max: 1 }
+    }
+
+    /// Require the entered nodes to have a kind
+    pub fn with_node(mut self, node: CPGNodeKind) -> Self {
+        self.node = Some(node);
+        self
+    }
+
+    /// Take `min..=max` edges
+    pub fn repeat(mut self, min: usize, max: usize) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+}
+
+/// Combined input size above which sorted inputs use the SIMD set path
+const SIMD_SET_THRESHOLD: usize = 1024;
+
 /// Query primitives for CPG traversal
 pub struct QueryPrimitives;
 
@@ -17,32 +84,147 @@ impl QueryPrimitives {
     ///
     /// **Deterministic**: Returns nodes in creation order
     pub fn find_nodes(cpg: &CPG, kind: CPGNodeKind) -> Vec<CPGNodeId> {
-        cpg.get_nodes_of_kind(kind)
-            .into_iter()
+        cpg.iter_nodes_of_kind(kind)
             .map(|n| n.id)
             .collect()
     }
 
+    /// Find all nodes belonging to a file
+    ///
+    /// **Indexed**: One range lookup, then a slice of the node list.
+    /// Returns nodes in creation order (empty for unknown files).
+    pub fn nodes_in_file(cpg: &CPG, indices: &CPGIndices, file_id: FileId) -> Vec<CPGNodeId> {
+        indices.file_range(file_id)
+            .and_then(|range| cpg.nodes.get(range))
+            .map(|nodes| nodes.iter().map(|n| n.id).collect())
+            .unwrap_or_default()
+    }
+
+    /// Find nodes whose source range contains a byte offset
+    ///
+    /// **Panics** unless `indices` were built from `cpg` as it is now.
+    /// `usize::MAX` is past the end of every range, so it finds nothing.
+    /// **Deterministic**: Innermost (smallest range) first, ties by NodeId
+    pub fn nodes_at(cpg: &CPG, indices: &CPGIndices, file_id: FileId, offset: usize) -> Vec<CPGNodeId> {
+        indices.assert_current(cpg);
+        match offset.checked_add(1) {
+            Some(end) => Self::nodes_overlapping(indices, file_id, ByteRange::new(offset, end)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Find nodes whose source range overlaps a span (half-open ranges)
+    ///
+    /// **Deterministic**: Innermost (smallest range) first, ties by NodeId
+    pub fn nodes_overlapping(indices: &CPGIndices, file_id: FileId, range: ByteRange) -> Vec<CPGNodeId> {
+        let intervals = indices.file_intervals(file_id);
+
+        // Only intervals starting before the span ends can overlap it
+        let candidates = &intervals[..intervals.partition_point(|(r, _)| r.start < range.end)];
+        let mut hits: Vec<_> = candidates.iter()
+            .filter(|(r, _)| r.end > range.start)
+            .copied()
+            .collect();
+
+        hits.sort_by_key(|(r, id)| (r.len(), *id));
+        hits.into_iter().map(|(_, id)| id).collect()
+    }
+
+    /// Find Function nodes by exact name
+    ///
+    /// **Indexed**: Same-named functions in different files are all
+    /// returned, in (FileId, FunctionId) order.
+    pub fn functions_named(indices: &CPGIndices, name: &str) -> Vec<CPGNodeId> {
+        indices.functions_named(name)
+    }
+
+    /// Find the DfgValue nodes of parameters at `position`
+    ///
+    /// Reads each node's `parameter_position`. With `functions`, only
+    /// those of these Function nodes: a parameter belongs to the innermost
+    /// Function node of its file whose range holds it.
+    /// **Deterministic**: NodeId order
+    pub fn parameters_at(cpg: &CPG, position: usize, functions: Option<&[CPGNodeId]>) -> Vec<CPGNodeId> {
+        let wanted: Option<HashSet<CPGNodeId>> = functions.map(|functions| functions.iter().copied().collect());
+        // Function nodes of the file being walked (nodes are grouped by file)
+        let mut in_file: Vec<&CPGNode> = Vec::new();
+        let mut hits = Vec::new();
+
+        for node in &cpg.nodes {
+            match node.kind {
+                CPGNodeKind::File => in_file.clear(),
+                CPGNodeKind::Function => in_file.push(node),
+                CPGNodeKind::DfgValue if node.parameter_position.map(|p| p as usize) == Some(position) => {
+                    let owner = in_file.iter()
+                        .filter(|f| f.source_range.start <= node.source_range.start && node.source_range.end <= f.source_range.end)
+                        .min_by_key(|f| (f.source_range.len(), std::cmp::Reverse(f.id)));
+                    let kept = match (&wanted, owner) {
+                        (None, _) => true,
+                        (Some(wanted), Some(owner)) => wanted.contains(&owner.id),
+                        (Some(_), None) => false,
+                    };
+                    if kept {
+                        hits.push(node.id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        hits.sort();
+        hits
+    }
+
+    /// Find Function nodes whose name matches a pattern
+    ///
+    /// **Deterministic**: (FileId, FunctionId) order across all names
+    pub fn functions_matching(indices: &CPGIndices, pattern: &NamePattern) -> Vec<CPGNodeId> {
+        let mut hits: Vec<_> = indices.function_names().iter()
+            .filter(|(name, _)| pattern.matches(name))
+            .flat_map(|(_, functions)| functions.iter().copied())
+            .collect();
+
+        hits.sort();
+        hits.into_iter().map(|(_, _, id)| id).collect()
+    }
+
     /// Follow outgoing edges of a specific kind from a node
     ///
     /// **Deterministic**: Returns targets in edge creation order
     pub fn follow_edge(cpg: &CPG, from: CPGNodeId, kind: CPGEdgeKind) -> Vec<CPGNodeId> {
-        cpg.get_edges_from(from)
-            .into_iter()
-            .filter(|e| e.kind == kind)
+        cpg.edges.iter()
+            .filter(|e| e.from == from && e.kind == kind)
             .map(|e| e.to)
             .collect()
     }
 
+    /// `follow_edge` through the adjacency index, without scanning every edge
+    ///
+    /// **Deterministic**: Yields targets in edge creation order
+    pub fn follow_edge_indexed<'a>(
+        cpg: &'a CPG,
+        indices: &'a CPGIndices,
+        from: CPGNodeId,
+        kind: CPGEdgeKind,
+    ) -> impl Iterator<Item = CPGNodeId> + 'a {
+        cpg.iter_edges_from(indices, from).of_kind(kind).map(|e| e.to)
+    }
+
+    /// Follow incoming edges of a specific kind back to their sources
+    ///
+    /// **Deterministic**: Returns sources in edge creation order
+    pub fn follow_edge_reverse(indices: &CPGIndices, to: CPGNodeId, kind: CPGEdgeKind) -> Vec<CPGNodeId> {
+        indices.get_sources_to(to, kind).to_vec()
+    }
+
     /// Filter nodes by predicate
     ///
     /// **Deterministic**: Preserves input order
     pub fn filter(nodes: Vec<CPGNodeId>, cpg: &CPG, kind: Option<CPGNodeKind>) -> Vec<CPGNodeId> {
         if let Some(k) = kind {
+            // One pass over the CPG rather than a lookup per node
+            let of_kind: HashSet<CPGNodeId> = cpg.nodes.iter().filter(|n| n.kind == k).map(|n| n.id).collect();
             nodes.into_iter()
-                .filter(|&id| {
-                    cpg.get_node(id).map(|n| n.kind == k).unwrap_or(false)
-                })
+                .filter(|id| of_kind.contains(id))
                 .collect()
         } else {
             nodes
@@ -57,10 +239,49 @@ impl QueryPrimitives {
         a.into_iter().filter(|n| b_set.contains(n)).collect()
     }
 
+    /// Union of two node sets
+    ///
+    /// **Deterministic**: Returns `a` in order, then `b`'s elements not in `a`
+    /// (first occurrence only)
+    pub fn union(a: Vec<CPGNodeId>, b: Vec<CPGNodeId>) -> Vec<CPGNodeId> {
+        let mut result = a;
+        if use_sorted_path(&result, &b) {
+            let novel = simd::sorted_difference(&b, &result);
+            result.extend(novel);
+            return result;
+        }
+
+        let mut seen: HashSet<_> = result.iter().copied().collect();
+        result.extend(b.into_iter().filter(|n| seen.insert(*n)));
+        result
+    }
+
+    /// Difference of two node sets (`a` minus `b`)
+    ///
+    /// **Deterministic**: Returns in first set's order
+    pub fn difference(a: Vec<CPGNodeId>, b: Vec<CPGNodeId>) -> Vec<CPGNodeId> {
+        if use_sorted_path(&a, &b) {
+            return simd::sorted_difference(&a, &b);
+        }
+
+        let b_set: HashSet<_> = b.into_iter().collect();
+        a.into_iter().filter(|n| !b_set.contains(n)).collect()
+    }
+
     /// Find all nodes reachable within N hops
     ///
     /// **Bounded**: Maximum depth enforced
     pub fn reachable_within(cpg: &CPG, from: CPGNodeId, max_depth: usize) -> Vec<CPGNodeId> {
+        uninterrupted(Self::reachable_within_cancellable(cpg, from, max_depth, &CancellationToken::new()))
+    }
+
+    /// `reachable_within`, stopping when `token` is cancelled or times out
+    pub fn reachable_within_cancellable(
+        cpg: &CPG,
+        from: CPGNodeId,
+        max_depth: usize,
+        token: &CancellationToken,
+    ) -> Result<Vec<CPGNodeId>, Interrupted> {
         let depth_limit = max_depth.min(MAX_REACHABILITY_DEPTH);
         let mut reachable = Vec::new();
         let mut visited = HashSet::new();
@@ -69,11 +290,13 @@ impl QueryPrimitives {
         queue.push_back((from, 0));
         visited.insert(from);
 
+        let mut checkpoint = Checkpoint::new(token);
         while let Some((current, depth)) = queue.pop_front() {
+            checkpoint.step()?;
             reachable.push(current);
 
             if depth < depth_limit {
-                for edge in cpg.get_edges_from(current) {
+                for edge in cpg.edges.iter().filter(|e| e.from == current) {
                     if !visited.contains(&edge.to) {
                         visited.insert(edge.to);
                         queue.push_back((edge.to, depth + 1));
@@ -82,15 +305,121 @@ impl QueryPrimitives {
             }
         }
 
-        reachable
+        Ok(reachable)
+    }
+
+    /// End nodes of the paths from `from` that match a pattern
+    ///
+    /// **Deterministic**: In discovery order (fewest edges first, ties by
+    /// edge creation order)
+    ///
+    /// **Bounded**: Steps past `MAX_PATH_STEPS` are ignored and repetitions
+    /// capped at `MAX_PATH_REPEAT`
+    pub fn match_path(cpg: &CPG, from: CPGNodeId, pattern: &[EdgeStep]) -> Vec<CPGNodeId> {
+        Self::match_path_witnesses(cpg, from, pattern)
+            .into_iter()
+            .filter_map(|path| path.last().copied())
+            .collect()
+    }
+
+    /// One witness path per `match_path` end node, in the same order
+    ///
+    /// Each witness starts at `from` and is a shortest matching path.
+    pub fn match_path_witnesses(cpg: &CPG, from: CPGNodeId, pattern: &[EdgeStep]) -> Vec<Vec<CPGNodeId>> {
+        uninterrupted(Self::match_path_cancellable(cpg, from, pattern, &CancellationToken::new()))
     }
+
+    /// `match_path_witnesses`, stopping when `token` is cancelled or times out
+    pub fn match_path_cancellable(
+        cpg: &CPG,
+        from: CPGNodeId,
+        pattern: &[EdgeStep],
+        token: &CancellationToken,
+    ) -> Result<Vec<Vec<CPGNodeId>>, Interrupted> {
+        let pattern = &pattern[..pattern.len().min(MAX_PATH_STEPS)];
+        if pattern.is_empty() {
+            return Ok(vec![vec![from]]);
+        }
+
+        // Out-edges of the pattern's kinds, built once
+        let mut successors: HashMap<(CPGNodeId, CPGEdgeKind), Vec<CPGNodeId>> = HashMap::new();
+        for edge in cpg.edges.iter().filter(|e| pattern.iter().any(|step| step.edge == e.kind)) {
+            successors.entry((edge.from, edge.kind)).or_default().push(edge.to);
+        }
+        let kinds: HashMap<CPGNodeId, CPGNodeKind> = match pattern.iter().any(|step| step.node.is_some()) {
+            true => cpg.nodes.iter().map(|n| (n.id, n.kind)).collect(),
+            false => HashMap::new(),
+        };
+
+        // BFS over (node, step, edges taken in the step); each state keeps
+        // the state it was first reached from
+        type State = (CPGNodeId, usize, usize);
+        let start: State = (from, 0, 0);
+        let mut parent: HashMap<State, Option<State>> = HashMap::from([(start, None)]);
+        let mut queue = VecDeque::from([start]);
+        let mut ends = Vec::new();
+        let mut ended = HashSet::new();
+
+        let mut checkpoint = Checkpoint::new(token);
+        while let Some(state) = queue.pop_front() {
+            checkpoint.step()?;
+            let (node, index, taken) = state;
+            let step = &pattern[index];
+            let max = step.max.min(MAX_PATH_REPEAT);
+
+            let mut next = Vec::new();
+            if taken >= step.min {
+                match index + 1 == pattern.len() {
+                    true => {
+                        if ended.insert(node) {
+                            ends.push(state);
+                        }
+                    }
+                    false => next.push((node, index + 1, 0)),
+                }
+            }
+            if taken < max {
+                for to in successors.get(&(node, step.edge)).into_iter().flatten() {
+                    if step.node.is_none_or(|kind| kinds.get(to) == Some(&kind)) {
+                        next.push((*to, index, taken + 1));
+                    }
+                }
+            }
+
+            for reached in next {
+                if let Entry::Vacant(entry) = parent.entry(reached) {
+                    entry.insert(Some(state));
+                    queue.push_back(reached);
+                }
+            }
+        }
+
+        // Walk parents back; only moves within a step took an edge
+        Ok(ends.into_iter().map(|end| {
+            let mut path = vec![end.0];
+            let mut current = end;
+            while let Some(Some(previous)) = parent.get(&current) {
+                if previous.1 == current.1 {
+                    path.push(previous.0);
+                }
+                current = *previous;
+            }
+            path.reverse();
+            path
+        }).collect())
+    }
+}
+
+/// Large inputs that are both strictly ascending take the sorted-set path
+pub(crate) fn use_sorted_path(a: &[CPGNodeId], b: &[CPGNodeId]) -> bool {
+    let strictly_ascending = |ids: &[CPGNodeId]| ids.windows(2).all(|w| w[0] < w[1]);
+    a.len() + b.len() >= SIMD_SET_THRESHOLD && strictly_ascending(a) && strictly_ascending(b)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::cpg::model::*;
-    use crate::types::ByteRange;
 
     #[test]
     fn test_find_nodes() {
@@ -134,6 +463,245 @@ mod tests {
         cpg.add_edge(CPGEdge::new(CPGEdgeId(1), CPGEdgeKind::ControlFlow, CPGNodeId(1), CPGNodeId(2)));
         
         let reachable = QueryPrimitives::reachable_within(&cpg, CPGNodeId(1), 10);
-        assert!(reachable.len() >= 1);
+        assert!(!reachable.is_empty());
+    }
+
+    #[test]
+    fn test_nodes_in_file() {
+        let mut cpg = CPG::new();
+        let files = [FileId::new(1), FileId::new(2), FileId::new(3)];
+        let mut next = 0;
+        for file_id in files {
+            cpg.add_node(CPGNode::new(CPGNodeId(next), CPGNodeKind::File,
+                OriginRef::File { file_id }, ByteRange::new(0, 0)));
+            next += 1;
+            for _ in 0..1000 {
+                cpg.add_node(CPGNode::new(CPGNodeId(next), CPGNodeKind::CfgNode,
+                    OriginRef::Cfg { node_id: crate::semantic::model::NodeId(next) }, ByteRange::new(0, 0)));
+                next += 1;
+            }
+        }
+        let indices = CPGIndices::build(&cpg);
+
+        // Lookup is a range into the node list, not a scan
+        assert_eq!(indices.file_range(files[1]), Some(1001..2002));
+
+        let nodes = QueryPrimitives::nodes_in_file(&cpg, &indices, files[1]);
+        assert_eq!(nodes.len(), 1001);
+        assert_eq!(nodes.first(), Some(&CPGNodeId(1001)));
+        assert_eq!(nodes.last(), Some(&CPGNodeId(2001)));
+        assert_eq!(nodes, QueryPrimitives::nodes_in_file(&cpg, &indices, files[1]));
+
+        assert!(QueryPrimitives::nodes_in_file(&cpg, &indices, FileId::new(99)).is_empty());
+    }
+
+    #[test]
+    fn test_follow_edge_reverse_matches_scan() {
+        let kinds = [CPGEdgeKind::ControlFlow, CPGEdgeKind::DataFlow, CPGEdgeKind::Calls];
+
+        // Pseudo-random graphs from a fixed LCG seed
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |bound: u64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) % bound
+        };
+
+        for _ in 0..50 {
+            let node_count = 1 + next(40);
+            let edge_count = next(200);
+            let mut cpg = CPG::new();
+            for i in 0..node_count {
+                cpg.add_node(CPGNode::new(CPGNodeId(i), CPGNodeKind::CfgNode,
+                    OriginRef::Cfg { node_id: crate::semantic::model::NodeId(i) }, ByteRange::new(0, 0)));
+            }
+            for e in 0..edge_count {
+                let kind = kinds[next(kinds.len() as u64) as usize];
+                cpg.add_edge(CPGEdge::new(CPGEdgeId(e), kind,
+                    CPGNodeId(next(node_count)), CPGNodeId(next(node_count))));
+            }
+            let indices = CPGIndices::build(&cpg);
+
+            for to in 0..node_count {
+                for kind in kinds {
+                    let scanned: Vec<_> = cpg.get_edges_to(CPGNodeId(to))
+                        .into_iter()
+                        .filter(|e| e.kind == kind)
+                        .map(|e| e.from)
+                        .collect();
+                    assert_eq!(QueryPrimitives::follow_edge_reverse(&indices, CPGNodeId(to), kind), scanned);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_union_and_difference_order() {
+        let a = vec![CPGNodeId(5), CPGNodeId(1), CPGNodeId(3)];
+        let b = vec![CPGNodeId(3), CPGNodeId(2), CPGNodeId(9), CPGNodeId(2)];
+
+        assert_eq!(
+            QueryPrimitives::union(a.clone(), b.clone()),
+            vec![CPGNodeId(5), CPGNodeId(1), CPGNodeId(3), CPGNodeId(2), CPGNodeId(9)]
+        );
+        assert_eq!(QueryPrimitives::difference(a, b), vec![CPGNodeId(5), CPGNodeId(1)]);
+    }
+
+    #[test]
+    fn test_sorted_set_path_matches_hash_path() {
+        let mut state: u64 = 7;
+        let mut sorted = |len: usize| {
+            let mut ids: Vec<_> = (0..len).map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                CPGNodeId((state >> 33) % 5000)
+            }).collect();
+            ids.sort();
+            ids.dedup();
+            ids
+        };
+
+        for _ in 0..20 {
+            let a = sorted(1500);
+            let b = sorted(1200);
+            assert!(use_sorted_path(&a, &b));
+
+            let union_hash = {
+                let mut seen: HashSet<_> = a.iter().copied().collect();
+                let mut out = a.clone();
+                out.extend(b.iter().copied().filter(|n| seen.insert(*n)));
+                out
+            };
+            let difference_hash: Vec<_> = a.iter().copied().filter(|n| !b.contains(n)).collect();
+
+            assert_eq!(QueryPrimitives::union(a.clone(), b.clone()), union_hash);
+            assert_eq!(QueryPrimitives::difference(a.clone(), b.clone()), difference_hash);
+        }
+    }
+
+    /// Two routes from value 0: DataFlow 0 → 1 → 2, then Calls into function
+    /// 10; DataFlow 0 → 3, then Calls into CFG node 12 and ControlFlow into
+    /// function 11. DataFlow 2 → 0 closes a cycle.
+    fn two_route_cpg() -> CPG {
+        let mut cpg = CPG::new();
+        for (id, kind) in [(0, CPGNodeKind::DfgValue), (1, CPGNodeKind::DfgValue), (2, CPGNodeKind::DfgValue),
+            (3, CPGNodeKind::DfgValue), (10, CPGNodeKind::Function), (11, CPGNodeKind::Function), (12, CPGNodeKind::CfgNode)]
+        {
+            cpg.add_node(CPGNode::new(CPGNodeId(id), kind,
+                OriginRef::Dfg { value_id: crate::semantic::model::ValueId(id) }, ByteRange::new(0, 0)));
+        }
+        let edges = [
+            (CPGEdgeKind::DataFlow, 0, 1), (CPGEdgeKind::DataFlow, 0, 3), (CPGEdgeKind::DataFlow, 1, 2),
+            (CPGEdgeKind::Calls, 2, 10), (CPGEdgeKind::Calls, 3, 12), (CPGEdgeKind::ControlFlow, 3, 11),
+            (CPGEdgeKind::DataFlow, 2, 0),
+        ];
+        for (i, (kind, from, to)) in edges.into_iter().enumerate() {
+            cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), kind, CPGNodeId(from), CPGNodeId(to)));
+        }
+        cpg
+    }
+
+    #[test]
+    fn test_match_path_takes_only_the_matching_route() {
+        let cpg = two_route_cpg();
+        let pattern = [
+            EdgeStep::new(CPGEdgeKind::DataFlow).repeat(1, 10),
+            EdgeStep::new(CPGEdgeKind::Calls).with_node(CPGNodeKind::Function),
+        ];
+
+        assert_eq!(QueryPrimitives::match_path(&cpg, CPGNodeId(0), &pattern), vec![CPGNodeId(10)]);
+        assert_eq!(
+            QueryPrimitives::match_path_witnesses(&cpg, CPGNodeId(0), &pattern),
+            vec![vec![CPGNodeId(0), CPGNodeId(1), CPGNodeId(2), CPGNodeId(10)]]
+        );
+        // Plain reachability reaches both functions
+        let reachable = QueryPrimitives::reachable_within(&cpg, CPGNodeId(0), 10);
+        assert!(reachable.contains(&CPGNodeId(10)) && reachable.contains(&CPGNodeId(11)));
+    }
+
+    #[test]
+    fn test_match_path_repetition_bounds() {
+        let cpg = two_route_cpg();
+        let calls_after = |min, max| [
+            EdgeStep::new(CPGEdgeKind::DataFlow).repeat(min, max),
+            EdgeStep::new(CPGEdgeKind::Calls),
+        ];
+
+        // Shortest paths first
+        assert_eq!(QueryPrimitives::match_path(&cpg, CPGNodeId(0), &calls_after(1, 10)), vec![CPGNodeId(12), CPGNodeId(10)]);
+        assert_eq!(QueryPrimitives::match_path(&cpg, CPGNodeId(0), &calls_after(1, 1)), vec![CPGNodeId(12)]);
+        assert_eq!(QueryPrimitives::match_path(&cpg, CPGNodeId(0), &calls_after(2, 2)), vec![CPGNodeId(10)]);
+        // An optional step may be skipped, keeping the start node
+        assert_eq!(QueryPrimitives::match_path(&cpg, CPGNodeId(2), &calls_after(0, 1)), vec![CPGNodeId(10)]);
+        // Around the cycle (3, 4, 4 and 5 edges); the repetition cap ends it
+        let around = [EdgeStep::new(CPGEdgeKind::DataFlow).repeat(3, usize::MAX)];
+        assert_eq!(
+            QueryPrimitives::match_path(&cpg, CPGNodeId(0), &around),
+            vec![CPGNodeId(0), CPGNodeId(1), CPGNodeId(3), CPGNodeId(2)]
+        );
+    }
+
+    /// fn (0..100) > if (20..80) > stmt (30..40), plus a sibling stmt (50..60)
+    fn nested_cpg() -> (CPG, FileId) {
+        let file_id = FileId::new(1);
+        let mut cpg = CPG::new();
+        cpg.add_node(CPGNode::new(CPGNodeId(0), CPGNodeKind::File,
+            OriginRef::File { file_id }, ByteRange::new(0, 0)));
+        for (id, range) in [(1, (0, 100)), (2, (30, 40)), (3, (20, 80)), (4, (50, 60)), (5, (30, 40))] {
+            cpg.add_node(CPGNode::new(CPGNodeId(id), CPGNodeKind::CfgNode,
+                OriginRef::Cfg { node_id: crate::semantic::model::NodeId(id) }, ByteRange::new(range.0, range.1)));
+        }
+        (cpg, file_id)
+    }
+
+    #[test]
+    fn test_nodes_at_innermost_first() {
+        let (cpg, file_id) = nested_cpg();
+        let indices = CPGIndices::build(&cpg);
+
+        // Equal ranges break ties by NodeId
+        assert_eq!(
+            QueryPrimitives::nodes_at(&cpg, &indices, file_id, 35),
+            vec![CPGNodeId(2), CPGNodeId(5), CPGNodeId(3), CPGNodeId(1)]
+        );
+        assert_eq!(QueryPrimitives::nodes_at(&cpg, &indices, file_id, 20), vec![CPGNodeId(3), CPGNodeId(1)]);
+        // End offsets are exclusive
+        assert_eq!(QueryPrimitives::nodes_at(&cpg, &indices, file_id, 80), vec![CPGNodeId(1)]);
+        assert!(QueryPrimitives::nodes_at(&cpg, &indices, file_id, 100).is_empty());
+        assert!(QueryPrimitives::nodes_at(&cpg, &indices, FileId::new(2), 35).is_empty());
+        assert!(QueryPrimitives::nodes_at(&cpg, &indices, file_id, usize::MAX).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "CPG indices are stale")]
+    fn test_nodes_at_rejects_stale_indices() {
+        let (mut cpg, file_id) = nested_cpg();
+        let indices = CPGIndices::build(&cpg);
+        cpg.bump_generation();
+
+        QueryPrimitives::nodes_at(&cpg, &indices, file_id, 35);
+    }
+
+    #[test]
+    #[should_panic(expected = "CPG indices are stale")]
+    fn test_functions_matching_rejects_stale_indices() {
+        let (mut cpg, _) = nested_cpg();
+        let indices = CPGIndices::build(&cpg);
+        cpg.bump_generation();
+
+        QueryPrimitives::functions_matching(&indices, &NamePattern::parse("main").unwrap());
+    }
+
+    #[test]
+    fn test_nodes_overlapping() {
+        let (cpg, file_id) = nested_cpg();
+        let indices = CPGIndices::build(&cpg);
+
+        assert_eq!(
+            QueryPrimitives::nodes_overlapping(&indices, file_id, ByteRange::new(38, 52)),
+            vec![CPGNodeId(2), CPGNodeId(4), CPGNodeId(5), CPGNodeId(3), CPGNodeId(1)]
+        );
+        assert_eq!(
+            QueryPrimitives::nodes_overlapping(&indices, file_id, ByteRange::new(40, 50)),
+            vec![CPGNodeId(3), CPGNodeId(1)]
+        );
     }
 }
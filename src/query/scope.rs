@@ -0,0 +1,112 @@
+//! File scope for path-based query stages (Step 3.6)
+//!
+//! Resolves repository-relative paths to FileIds using the snapshot the
+//! CPG was built from. A path matches a file exactly or as a directory
+//! prefix (whole components only: `src/api` does not match `src/apix.rs`).
+
+use crate::types::{FileId, RepoSnapshot};
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Path → FileId map for one snapshot
+#[derive(Debug, Clone, Default)]
+pub struct FileScope {
+    /// Relative paths (sorted)
+    paths: BTreeMap<PathBuf, FileId>,
+}
+
+impl FileScope {
+    /// Build the scope from a scanned snapshot
+    pub fn from_snapshot(snapshot: &RepoSnapshot) -> Self {
+        Self {
+            paths: snapshot.files.iter()
+                .map(|(id, meta)| (meta.path.clone(), *id))
+                .collect(),
+        }
+    }
+
+    /// Resolve a path or path prefix to the matching files
+    ///
+    /// **Deterministic**: Returns FileIds in ascending order.
+    /// Errors if nothing in the snapshot matches.
+    pub fn resolve(&self, path: &str) -> Result<Vec<FileId>> {
+        let wanted = Path::new(path.trim_start_matches("./"));
+
+        let mut file_ids: Vec<FileId> = self.paths.iter()
+            .filter(|(p, _)| p.starts_with(wanted))
+            .map(|(_, id)| *id)
+            .collect();
+        file_ids.sort();
+
+        if file_ids.is_empty() {
+            bail!("Path not in snapshot: {}", path);
+        }
+        Ok(file_ids)
+    }
+
+    /// Resolve a path that must name exactly one file
+    pub fn resolve_file(&self, path: &str) -> Result<FileId> {
+        match self.paths.get(Path::new(path.trim_start_matches("./"))) {
+            Some(file_id) => Ok(*file_id),
+            None => bail!("File not in snapshot: {}", path),
+        }
+    }
+
+    /// Files in scope, ascending by path
+    pub fn files(&self) -> impl Iterator<Item = (&Path, FileId)> {
+        self.paths.iter().map(|(path, id)| (path.as_path(), *id))
+    }
+
+    /// Number of files in scope
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Whether the scope has no files
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(paths: &[(&str, u64)]) -> FileScope {
+        FileScope {
+            paths: paths.iter().map(|(p, id)| (PathBuf::from(p), FileId::new(*id))).collect(),
+        }
+    }
+
+    #[test]
+    fn test_resolve_exact_and_prefix() {
+        let scope = scope(&[
+            ("src/handlers/login.rs", 3),
+            ("src/handlers/logout.rs", 1),
+            ("src/handlersx.rs", 2),
+        ]);
+
+        assert_eq!(scope.resolve("src/handlers/login.rs").unwrap(), vec![FileId::new(3)]);
+        assert_eq!(scope.resolve("./src/handlers/login.rs").unwrap(), vec![FileId::new(3)]);
+        assert_eq!(
+            scope.resolve("src/handlers").unwrap(),
+            vec![FileId::new(1), FileId::new(3)]
+        );
+    }
+
+    #[test]
+    fn test_resolve_file_requires_exact_path() {
+        let scope = scope(&[("src/handlers/login.rs", 3)]);
+
+        assert_eq!(scope.resolve_file("src/handlers/login.rs").unwrap(), FileId::new(3));
+        assert!(scope.resolve_file("src/handlers").is_err());
+    }
+
+    #[test]
+    fn test_resolve_unknown_path() {
+        let scope = scope(&[("src/lib.rs", 1)]);
+        let err = scope.resolve("src/missing.rs").unwrap_err();
+        assert!(err.to_string().contains("Path not in snapshot"));
+    }
+}
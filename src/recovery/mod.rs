@@ -1,11 +1,23 @@
 //! Crash recovery module (Path B3)
 //!
 //! **Goal**: Prove VTR survives real-world failure
+//!
+//! Operation markers are files under `<snapshot_dir>/operations/`, one per
+//! operation in flight, written before it touches anything and removed once
+//! it has finished. A marker left behind names an operation a crash
+//! interrupted; cleanup (snapshot gc) leaves alone whatever a marked
+//! operation may still be writing.
 
 use std::path::PathBuf;
 use std::io::{Result, Error, ErrorKind};
 use crate::storage::SnapshotId;
 
+/// Marker directory name (inside the snapshot directory)
+const OPERATIONS_DIR: &str = "operations";
+
+/// Marker file extension
+const MARKER_EXTENSION: &str = "op";
+
 /// Recovery state
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RecoveryState {
@@ -21,13 +33,13 @@ pub enum RecoveryState {
 
 /// Recovery manager
 pub struct RecoveryManager {
-    _snapshot_dir: PathBuf,
+    snapshot_dir: PathBuf,
 }
 
 impl RecoveryManager {
     /// Create new recovery manager
     pub fn new(snapshot_dir: PathBuf) -> Self {
-        Self { _snapshot_dir: snapshot_dir }
+        Self { snapshot_dir }
     }
     
     /// Check recovery state
@@ -70,15 +82,48 @@ impl RecoveryManager {
     }
     
     /// Mark operation start (idempotent marker)
-    pub fn mark_operation_start(&self, _operation: &str) -> Result<()> {
-        // Placeholder: would write operation marker
-        Ok(())
+    ///
+    /// `operation` names the marker file, so it must be a plain file name.
+    pub fn mark_operation_start(&self, operation: &str) -> Result<()> {
+        let path = self.marker_path(operation)?;
+        std::fs::create_dir_all(self.snapshot_dir.join(OPERATIONS_DIR))?;
+        std::fs::write(path, operation)
     }
     
     /// Mark operation complete (idempotent cleanup)
-    pub fn mark_operation_complete(&self, _operation: &str) -> Result<()> {
-        // Placeholder: would remove operation marker
-        Ok(())
+    pub fn mark_operation_complete(&self, operation: &str) -> Result<()> {
+        match std::fs::remove_file(self.marker_path(operation)?) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Operations started but not completed, sorted
+    pub fn operations_in_progress(&self) -> Result<Vec<String>> {
+        let entries = match std::fs::read_dir(self.snapshot_dir.join(OPERATIONS_DIR)) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut operations = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == MARKER_EXTENSION) {
+                if let Some(operation) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    operations.push(operation.to_string());
+                }
+            }
+        }
+        operations.sort();
+        Ok(operations)
+    }
+
+    fn marker_path(&self, operation: &str) -> Result<PathBuf> {
+        if operation.is_empty() || operation.contains(['/', '\\']) || operation.starts_with('.') {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Invalid operation name: {:?}", operation)));
+        }
+        Ok(self.snapshot_dir.join(OPERATIONS_DIR).join(format!("{}.{}", operation, MARKER_EXTENSION)))
     }
 }
 
@@ -119,4 +164,20 @@ mod tests {
         // Should be idempotent - no error on repeat
         manager.mark_operation_complete("test_op").unwrap();
     }
+
+    #[test]
+    fn test_markers_list_operations_in_progress() {
+        let temp = TempDir::new().unwrap();
+        let manager = RecoveryManager::new(temp.path().to_path_buf());
+        assert!(manager.operations_in_progress().unwrap().is_empty());
+
+        manager.mark_operation_start("save-b.cpg").unwrap();
+        manager.mark_operation_start("save-a.cpg").unwrap();
+        manager.mark_operation_start("save-a.cpg").unwrap();
+        assert_eq!(manager.operations_in_progress().unwrap(), vec!["save-a.cpg", "save-b.cpg"]);
+
+        manager.mark_operation_complete("save-a.cpg").unwrap();
+        assert_eq!(manager.operations_in_progress().unwrap(), vec!["save-b.cpg"]);
+        assert!(manager.mark_operation_start("../escape").is_err());
+    }
 }
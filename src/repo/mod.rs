@@ -1,5 +1,8 @@
 //! Repository scanning and ingestion (Step 1.1)
 
+pub mod policy;
 pub mod scanner;
+pub mod vcs;
 
-pub use scanner::RepoScanner;
+pub use scanner::{normalize_path, Excluded, FileIdCollision, FileIdDerivation, FileIdStrategy, RepoScanner};
+pub use vcs::read_vcs_info;
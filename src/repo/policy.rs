@@ -0,0 +1,213 @@
+//! Scan policy files: `.vcrignore` and `.vcr.toml`
+//!
+//! ## `.vcrignore`
+//!
+//! Read from each scanned root. One glob per line, relative to that root,
+//! in gitignore style:
+//!
+//! - `#` starts a comment line; blank lines are skipped
+//! - patterns are `util::glob` globs (`*`, `?`, `[abc]`, `**` components,
+//!   `\` escapes)
+//! - a pattern without `/` (other than a trailing one) matches at any depth;
+//!   one with a leading or inner `/` is anchored at the root
+//! - a trailing `/` matches directories only
+//! - `!` re-includes what an earlier pattern excluded; the last matching
+//!   line wins
+//!
+//! An ignored directory is not descended into, so nothing below it can be
+//! re-included.
+//!
+//! ## `.vcr.toml`
+//!
+//! Any directory may hold a `.vcr.toml` overriding some settings for its
+//! subtree:
+//!
+//! ```toml
+//! skip_semantics = true
+//! max_file_size = 1048576
+//! ```
+//!
+//! A file's effective `FilePolicy` applies every override from the root
+//! down to its own directory, so the deepest setting of each option wins.
+
+use crate::types::FilePolicy;
+use crate::util::Glob;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Ignore file read from each scanned root
+pub const IGNORE_FILE: &str = ".vcrignore";
+
+/// Per-directory override file
+pub const OVERRIDE_FILE: &str = ".vcr.toml";
+
+/// One `.vcrignore` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IgnoreRule {
+    glob: Glob,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// Parsed `.vcrignore`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    /// Parse `.vcrignore` text
+    pub fn parse(text: &str) -> Result<Self> {
+        let rules = text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|(n, line)| parse_rule(line.trim_end()).with_context(|| format!("line {}", n + 1)))
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Whether a path (normalized, relative to the root) is ignored
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.rules.iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.glob.matches(path))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+fn parse_rule(line: &str) -> Result<IgnoreRule> {
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+        bail!("Invalid pattern: empty");
+    }
+
+    let glob = match anchored {
+        true => Glob::parse(line)?,
+        false => Glob::parse(&format!("**/{}", line))?,
+    };
+    Ok(IgnoreRule { glob, negated, dir_only })
+}
+
+/// Options a `.vcr.toml` may set (unset options are inherited)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyOverride {
+    pub skip_semantics: Option<bool>,
+    pub max_file_size: Option<u64>,
+}
+
+impl PolicyOverride {
+    /// Parse `.vcr.toml` text
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    fn apply(&self, policy: &mut FilePolicy) {
+        if let Some(skip) = self.skip_semantics {
+            policy.skip_semantics = skip;
+        }
+        if let Some(max) = self.max_file_size {
+            policy.max_file_size = Some(max);
+        }
+    }
+}
+
+/// Overrides by directory (relative to the snapshot root)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicyTree {
+    overrides: BTreeMap<PathBuf, PolicyOverride>,
+}
+
+impl PolicyTree {
+    /// Set the override of a directory
+    pub fn insert(&mut self, dir: PathBuf, policy: PolicyOverride) {
+        self.overrides.insert(dir, policy);
+    }
+
+    /// Effective policy of a file (relative to the snapshot root)
+    pub fn policy_for(&self, path: &Path) -> FilePolicy {
+        let mut policy = FilePolicy::default();
+        let mut dirs: Vec<&Path> = path.ancestors().skip(1).collect();
+        dirs.reverse();
+        for dir in dirs {
+            if let Some(policy_override) = self.overrides.get(dir) {
+                policy_override.apply(&mut policy);
+            }
+        }
+        policy
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(rules: &str, path: &str, is_dir: bool) -> bool {
+        IgnoreRules::parse(rules).unwrap().is_ignored(path, is_dir)
+    }
+
+    #[test]
+    fn test_ignore_globs() {
+        assert!(ignored("*.gen.rs", "src/deep/a.gen.rs", false));
+        assert!(!ignored("*.gen.rs", "src/a.rs", false));
+        assert!(ignored("/target", "target", true));
+        assert!(!ignored("/target", "sub/target", true));
+        assert!(ignored("src/*/mod.rs", "src/a/mod.rs", false));
+        assert!(!ignored("src/*/mod.rs", "src/a/b/mod.rs", false));
+        assert!(ignored("src/**/mod.rs", "src/a/b/mod.rs", false));
+        assert!(ignored("build/", "x/build", true));
+        assert!(!ignored("build/", "x/build", false));
+        assert!(ignored("a?.rs", "ab.rs", false));
+        assert!(ignored("\\#literal.rs", "#literal.rs", false));
+        assert!(ignored("[ab].rs", "x/b.rs", false));
+        assert!(!ignored("[ab].rs", "c.rs", false));
+    }
+
+    #[test]
+    fn test_last_match_wins() {
+        let rules = "# generated code\n*.rs\n!keep.rs\n\n";
+        assert!(ignored(rules, "drop.rs", false));
+        assert!(!ignored(rules, "keep.rs", false));
+        assert!(ignored("!keep.rs\n*.rs", "keep.rs", false));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_syntax() {
+        let err = IgnoreRules::parse("ok.rs\n[ab.rs").unwrap_err();
+        assert!(format!("{:#}", err).contains("line 2"), "{:#}", err);
+        assert!(IgnoreRules::parse("/").is_err());
+        assert!(IgnoreRules::parse("a//b").is_err());
+    }
+
+    #[test]
+    fn test_deepest_override_wins() {
+        let mut tree = PolicyTree::default();
+        tree.insert(PathBuf::new(), PolicyOverride::parse("max_file_size = 100").unwrap());
+        tree.insert(PathBuf::from("gen"), PolicyOverride::parse("skip_semantics = true\nmax_file_size = 10").unwrap());
+        tree.insert(PathBuf::from("gen/keep"), PolicyOverride::parse("skip_semantics = false").unwrap());
+
+        assert_eq!(tree.policy_for(Path::new("a.rs")), FilePolicy { skip_semantics: false, max_file_size: Some(100) });
+        assert_eq!(tree.policy_for(Path::new("gen/a.rs")), FilePolicy { skip_semantics: true, max_file_size: Some(10) });
+        assert_eq!(tree.policy_for(Path::new("gen/keep/a.rs")), FilePolicy { skip_semantics: false, max_file_size: Some(10) });
+        assert!(PolicyOverride::parse("skip_semantic = true").is_err());
+    }
+}
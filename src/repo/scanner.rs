@@ -2,16 +2,140 @@
 //!
 //! Walks directories in stable order, filters files deterministically,
 //! produces reproducible RepoSnapshot.
+//!
+//! ## Workspaces
+//!
+//! `with_roots` scans several directories into one snapshot. The snapshot
+//! root is the roots' deepest common ancestor, and file paths are relative
+//! to it, so every path (and the FileId hashed from it) starts with its
+//! root's label: roots `crates/a` and `crates/b` give `a/src/lib.rs` and
+//! `b/src/lib.rs`. Identical relative paths under different roots never
+//! collide, and only the listed roots are walked.
+//!
+//! ## Path normalization
+//!
+//! FileIds and the snapshot hash are computed from `normalize_path`, not the
+//! platform path: `\` becomes `/`, empty and `.` components are dropped and
+//! the string is put in Unicode NFC. The same checkout therefore gets the
+//! same FileIds on Windows, macOS (which stores decomposed names) and Linux.
+//!
+//! Contents are hashed as stored unless `with_normalized_line_endings` is
+//! set: then each file is hashed and sized as its LF view, and a CRLF
+//! checkout (Git's `core.autocrlf` on Windows) scans like an LF one.
+//!
+//! ## Collisions
+//!
+//! A FileId is 8 bytes of a SHA-256, so two paths can share one. `scan`
+//! fails closed with `FileIdCollision` rather than letting one file's
+//! metadata replace the other's. `FileIdDerivation` offers two ways out:
+//! folding 16 bytes of the hash into the ID (different IDs, same width),
+//! or rehashing the colliding path with a counter.
+//!
+//! ## Backends
+//!
+//! `with_backend` scans through an `IOBackend` instead of the local
+//! filesystem. Roots are taken as given (not canonicalized) and walked with
+//! `list_dir`; a backend holding the same files under the same relative
+//! paths produces the same snapshot hash.
+//!
+//! ## Policy files
+//!
+//! Each root's `.vcrignore` prunes paths from the walk, and `.vcr.toml`
+//! files override settings for their subtree (see `policy`). The effective
+//! policy is recorded in each file's `FileMetadata::policy`, and the policy
+//! files' paths and content hashes are part of the snapshot hash, so editing
+//! one changes the snapshot even when no source file changed.
+//!
+//! ## Version control
+//!
+//! When the snapshot root holds a `.git` directory (or link), the commit
+//! and branch it is on are recorded in `RepoSnapshot::vcs` (see `vcs`).
+//! They are read through the backend, like files, and are not hashed.
 
+use crate::io::cold::SyncIOBackend;
+use crate::io::{normalize_line_endings, FileKind, IOBackend};
+use crate::repo::policy::{IgnoreRules, PolicyOverride, PolicyTree, IGNORE_FILE, OVERRIDE_FILE};
+use crate::repo::vcs::read_vcs_info;
 use crate::types::{FileId, FileMetadata, Language, RepoSnapshot};
-use anyhow::{Context, Result};
+use crate::util::Glob;
+use anyhow::{bail, Context, Result};
 use sha2::{Digest, Sha256};
-use std::collections::{HashMap, HashSet};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::SystemTime;
+use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
 use walkdir::WalkDir;
 
+/// Rehashes tried for one path under `FileIdDerivation::Rehash`
+const MAX_REHASHES: u32 = 16;
+
+/// Directory depth at which a backend walk gives up (symlink loops)
+const MAX_WALK_DEPTH: usize = 256;
+
+/// SHA-256 of a FileId key (replaceable in tests to force collisions)
+type KeyHasher = fn(&str) -> [u8; 32];
+
+/// How FileIds are derived
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileIdStrategy {
+    /// Hash of the normalized relative path: stable across edits
+    #[default]
+    PathHash,
+
+    /// Hash of the normalized relative path and the content hash: any
+    /// edit gives the file a new ID (detected as delete + add)
+    PathAndContentHash,
+}
+
+/// How the 32-byte key hash becomes a FileId
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileIdDerivation {
+    /// First 8 bytes; a collision fails the scan
+    #[default]
+    Truncated,
+
+    /// First 16 bytes folded (XOR) into 8; a collision fails the scan
+    Folded,
+
+    /// First 8 bytes; a path colliding with an earlier one (in path order)
+    /// is rehashed with a counter until its ID is free. Its ID then depends
+    /// on the other path existing
+    Rehash,
+}
+
+impl FileIdDerivation {
+    fn file_id(self, hash: &[u8; 32]) -> FileId {
+        let word = |offset: usize| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&hash[offset..offset + 8]);
+            u64::from_be_bytes(bytes)
+        };
+        match self {
+            FileIdDerivation::Truncated | FileIdDerivation::Rehash => FileId::new(word(0)),
+            FileIdDerivation::Folded => FileId::new(word(0) ^ word(8)),
+        }
+    }
+}
+
+/// Two scanned paths derived the same FileId
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "FileId collision: {} and {} both map to {:016x} (scan with FileIdDerivation::Folded or ::Rehash)",
+    path_a.display(), path_b.display(), id.as_u64()
+)]
+pub struct FileIdCollision {
+    /// Path scanned first
+    pub path_a: PathBuf,
+
+    /// Path that would have replaced it
+    pub path_b: PathBuf,
+
+    pub id: FileId,
+}
+
 /// Deterministic repository scanner.
 ///
 /// Scans a directory tree and produces a reproducible snapshot.
@@ -23,14 +147,35 @@ use walkdir::WalkDir;
 /// - Content hashes ensure change detection
 /// - Same repo state → identical snapshot every time
 pub struct RepoScanner {
-    /// Root directory to scan
+    /// Snapshot root (common ancestor of `roots`)
     root: PathBuf,
+
+    /// Directories to scan (canonical, sorted)
+    roots: Vec<PathBuf>,
     
-    /// File extensions to include (e.g., "rs" for Rust)
-    extensions: HashSet<String>,
+    /// File name globs to include (`*.rs` for extension "rs")
+    extensions: Vec<Glob>,
     
     /// Whether to follow symlinks (default: false for determinism)
     follow_symlinks: bool,
+
+    /// Workers hashing files (1 = serial; >1 needs `parallel-execution`)
+    threads: usize,
+
+    /// How FileIds are derived
+    file_id_strategy: FileIdStrategy,
+
+    /// How a key hash becomes a FileId
+    file_id_derivation: FileIdDerivation,
+
+    /// Hashes FileId keys
+    key_hasher: KeyHasher,
+
+    /// Where files are listed and read (None: local filesystem)
+    backend: Option<Arc<dyn IOBackend>>,
+
+    /// Hash and size files' LF views (`io::line_endings`)
+    normalize_line_endings: bool,
 }
 
 impl RepoScanner {
@@ -39,22 +184,62 @@ impl RepoScanner {
         let root = root.as_ref().canonicalize()
             .context("Failed to canonicalize repository root")?;
         
-        Ok(Self {
-            root,
-            extensions: HashSet::new(),
+        Ok(Self::from_roots(vec![root], None))
+    }
+
+    /// Create a scanner for several roots producing one snapshot.
+    ///
+    /// Roots are sorted and deduplicated; a root nested inside another is
+    /// rejected. A single root behaves exactly like `new`.
+    pub fn with_roots(roots: Vec<PathBuf>) -> Result<Self> {
+        let canonical = roots.iter()
+            .map(|root| root.canonicalize()
+                .with_context(|| format!("Failed to canonicalize workspace root {}", root.display())))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::from_roots(check_roots(canonical)?, None))
+    }
+
+    /// Create a scanner listing and reading files through `backend`.
+    ///
+    /// Roots are checked like `with_roots` but not canonicalized; each must
+    /// be a directory in the backend.
+    pub fn with_backend(roots: Vec<PathBuf>, backend: Arc<dyn IOBackend>) -> Result<Self> {
+        let roots = check_roots(roots)?;
+        for root in &roots {
+            let stat = backend.metadata(root)
+                .with_context(|| format!("Failed to stat workspace root {} ({})", root.display(), backend.name()))?;
+            if stat.kind != FileKind::Dir {
+                bail!("Workspace root {} is not a directory ({})", root.display(), backend.name());
+            }
+        }
+        Ok(Self::from_roots(roots, Some(backend)))
+    }
+
+    /// Scanner with default settings for checked roots
+    fn from_roots(roots: Vec<PathBuf>, backend: Option<Arc<dyn IOBackend>>) -> Self {
+        Self {
+            root: common_ancestor(&roots),
+            roots,
+            extensions: Vec::new(),
             follow_symlinks: false,
-        })
+            threads: 1,
+            file_id_strategy: FileIdStrategy::default(),
+            file_id_derivation: FileIdDerivation::default(),
+            key_hasher: sha256_key,
+            backend,
+            normalize_line_endings: false,
+        }
     }
 
     /// Add a file extension to scan (e.g., "rs", "py", "js").
     pub fn with_extension(mut self, ext: impl Into<String>) -> Self {
-        self.extensions.insert(ext.into());
+        self.extensions.push(Glob::extension(&ext.into()));
         self
     }
 
     /// Add multiple extensions at once.
     pub fn with_extensions(mut self, exts: impl IntoIterator<Item = impl Into<String>>) -> Self {
-        self.extensions.extend(exts.into_iter().map(Into::into));
+        self.extensions.extend(exts.into_iter().map(|ext| Glob::extension(&ext.into())));
         self
     }
 
@@ -64,6 +249,40 @@ impl RepoScanner {
         self
     }
 
+    /// Hash files on `threads` workers.
+    ///
+    /// Only takes effect with the `parallel-execution` feature; the snapshot
+    /// is identical for any thread count.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Hash and size files with `\r\n` read as `\n` (default: false).
+    pub fn with_normalized_line_endings(mut self, normalize: bool) -> Self {
+        self.normalize_line_endings = normalize;
+        self
+    }
+
+    /// Choose how FileIds are derived (default: `PathHash`).
+    pub fn with_file_id_strategy(mut self, strategy: FileIdStrategy) -> Self {
+        self.file_id_strategy = strategy;
+        self
+    }
+
+    /// Choose how key hashes become FileIds (default: `Truncated`).
+    pub fn with_file_id_derivation(mut self, derivation: FileIdDerivation) -> Self {
+        self.file_id_derivation = derivation;
+        self
+    }
+
+    /// Replace the key hash (tests forcing collisions)
+    #[cfg(test)]
+    fn with_key_hasher(mut self, hasher: KeyHasher) -> Self {
+        self.key_hasher = hasher;
+        self
+    }
+
     /// Scan the repository and produce a deterministic snapshot.
     ///
     /// # Determinism
@@ -72,75 +291,243 @@ impl RepoScanner {
     /// - File filtering is deterministic
     /// - Hash computation is stable
     pub fn scan(&self) -> Result<RepoSnapshot> {
-        let mut files_map = HashMap::new();
-        let mut all_paths = Vec::new();
-
-        // Step 1: Collect all file paths
-        for entry in WalkDir::new(&self.root)
-            .follow_links(self.follow_symlinks)
-            .sort_by_file_name() // Lexicographic ordering
-        {
-            let entry = entry.context("Failed to read directory entry")?;
-            
-            // Skip directories
-            if !entry.file_type().is_file() {
-                continue;
-            }
+        self.scan_found(Found::default()).map(|(snapshot, _)| snapshot)
+    }
+
+    /// `scan`, also listing the walked paths left out of the snapshot
+    pub fn scan_with_excluded(&self) -> Result<(RepoSnapshot, Excluded)> {
+        let found = Found { excluded: Some(Excluded::default()), ..Found::default() };
+        let (snapshot, excluded) = self.scan_found(found)?;
+        let relative = |paths: Vec<PathBuf>| -> Result<Vec<PathBuf>> {
+            let mut paths = paths.iter().map(|path| self.relative(path)).collect::<Result<Vec<_>>>()?;
+            paths.sort();
+            Ok(paths)
+        };
+        let excluded = excluded.unwrap_or_default();
+        Ok((snapshot, Excluded { ignored: relative(excluded.ignored)?, unwanted: relative(excluded.unwanted)? }))
+    }
+
+    /// Walk, then snapshot the wanted files of `found`
+    fn scan_found(&self, mut found: Found) -> Result<(RepoSnapshot, Option<Excluded>)> {
+        // Root path is deliberately not recorded (paths stay behind FileId)
+        let span = tracing::info_span!("scan", files = tracing::field::Empty).entered();
+        let mut files_map = BTreeMap::new();
+        let mut policy_files = BTreeMap::new();
+
+        // Step 1: Collect all file paths (and override files), skipping ignored ones
+        for root in &self.roots {
+            let ignore = self.load_ignore(root, &mut policy_files)?;
+            let walk = Walk { root, ignore: &ignore };
+            match &self.backend {
+                Some(backend) => self.walk_backend(backend.as_ref(), &walk, root, 0, &mut found)?,
+                None => {
+                    let mut ignored = Vec::new();
+                    let entries = WalkDir::new(root)
+                        .follow_links(self.follow_symlinks)
+                        .sort_by_file_name() // Lexicographic ordering
+                        .into_iter()
+                        .filter_entry(|entry| {
+                            let kept = entry.depth() == 0 || !walk.ignores(entry.path(), entry.file_type().is_dir());
+                            if !kept {
+                                ignored.push(entry.path().to_path_buf());
+                            }
+                            kept
+                        });
+                    for entry in entries {
+                        let entry = entry.context("Failed to read directory entry")?;
 
-            let path = entry.path();
-            
-            // Filter by extension if specified
-            if !self.extensions.is_empty() {
-                let ext = path.extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("");
-                
-                if !self.extensions.contains(ext) {
-                    continue;
+                        // Skip directories
+                        if entry.file_type().is_file() {
+                            self.visit_file(entry.path().to_path_buf(), &mut found);
+                        }
+                    }
+                    if let Some(excluded) = &mut found.excluded {
+                        excluded.ignored.extend(ignored);
+                    }
                 }
             }
-
-            all_paths.push(path.to_path_buf());
         }
 
         // Step 2: Sort paths for determinism (walkdir sorts per-directory, we want global order)
-        all_paths.sort();
+        found.files.sort();
+        found.overrides.sort();
+        let policies = self.load_overrides(&found.overrides, &mut policy_files)?;
 
         // Step 3: Process each file deterministically
-        for path in all_paths {
-            let metadata = self.process_file(&path)?;
-            let file_id = Self::compute_file_id(&metadata.path);
+        for metadata in self.process_files(&found.files, &policies)? {
+            let file_id = self.assign_file_id(&metadata, &files_map)?;
             files_map.insert(file_id, metadata);
         }
 
         // Step 4: Compute snapshot hash
-        let snapshot_hash = Self::compute_snapshot_hash(&files_map);
+        let roots = self.root_labels();
+        let snapshot_hash = Self::compute_snapshot_hash(&roots, &policy_files, &files_map);
+        span.record("files", files_map.len());
+
+        let vcs = match &self.backend {
+            Some(backend) => read_vcs_info(&self.root, backend.as_ref()),
+            None => read_vcs_info(&self.root, &SyncIOBackend),
+        };
 
-        Ok(RepoSnapshot {
+        let snapshot = RepoSnapshot {
             root: self.root.clone(),
+            roots,
             files: files_map,
             created_at: SystemTime::now(),
             snapshot_hash,
-        })
+            vcs,
+            normalized_line_endings: self.normalize_line_endings,
+        };
+        Ok((snapshot, found.excluded))
+    }
+
+    /// Whether a file passes the extension filter
+    fn wants(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        let name = path.file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        self.extensions.iter().any(|glob| glob.matches(&name))
+    }
+
+    /// Note a walked file: an override file and/or a wanted file
+    fn visit_file(&self, path: PathBuf, found: &mut Found) {
+        if path.file_name().is_some_and(|name| name == OVERRIDE_FILE) {
+            found.overrides.push(path.clone());
+        }
+        if self.wants(&path) {
+            found.files.push(path);
+        } else if let Some(excluded) = &mut found.excluded {
+            excluded.unwanted.push(path);
+        }
+    }
+
+    /// Read a file through the backend (or from disk)
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        match &self.backend {
+            Some(backend) => backend.read_file(path),
+            None => fs::read(path),
+        }
+    }
+
+    /// Snapshot-relative path of a scanned path
+    fn relative(&self, path: &Path) -> Result<PathBuf> {
+        Ok(path.strip_prefix(&self.root).context("Failed to compute relative path")?.to_path_buf())
+    }
+
+    /// A root's `.vcrignore` (empty if it has none), hashed into `policy_files`
+    fn load_ignore(&self, root: &Path, policy_files: &mut BTreeMap<PathBuf, String>) -> Result<IgnoreRules> {
+        let path = root.join(IGNORE_FILE);
+        let bytes = match self.read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(IgnoreRules::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        policy_files.insert(self.relative(&path)?, Self::hash_bytes(&bytes));
+        let text = std::str::from_utf8(&bytes).with_context(|| format!("Invalid {}: not UTF-8", path.display()))?;
+        IgnoreRules::parse(text).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    /// Parse override files (in path order), hashing them into `policy_files`
+    fn load_overrides(&self, paths: &[PathBuf], policy_files: &mut BTreeMap<PathBuf, String>) -> Result<PolicyTree> {
+        let mut tree = PolicyTree::default();
+        for path in paths {
+            let bytes = self.read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let relative = self.relative(path)?;
+            policy_files.insert(relative.clone(), Self::hash_bytes(&bytes));
+            let policy = std::str::from_utf8(&bytes)
+                .map_err(anyhow::Error::from)
+                .and_then(PolicyOverride::parse)
+                .with_context(|| format!("Invalid {}", path.display()))?;
+            tree.insert(relative.parent().unwrap_or(Path::new("")).to_path_buf(), policy);
+        }
+        Ok(tree)
+    }
+
+    /// Collect the wanted files below `dir` through `backend`
+    fn walk_backend(&self, backend: &dyn IOBackend, walk: &Walk, dir: &Path, depth: usize, found: &mut Found) -> Result<()> {
+        if depth > MAX_WALK_DEPTH {
+            bail!("Directories nested deeper than {} at {} (symlink loop?)", MAX_WALK_DEPTH, dir.display());
+        }
+        let mut entries = backend.list_dir(dir)
+            .with_context(|| format!("Failed to list {} ({})", dir.display(), backend.name()))?;
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        for entry in entries {
+            let kind = match entry.kind {
+                FileKind::Symlink if self.follow_symlinks => backend.metadata(&entry.path)
+                    .with_context(|| format!("Failed to follow {} ({})", entry.path.display(), backend.name()))?
+                    .kind,
+                kind => kind,
+            };
+            match kind {
+                FileKind::Dir | FileKind::File if walk.ignores(&entry.path, kind == FileKind::Dir) => {
+                    if let Some(excluded) = &mut found.excluded {
+                        excluded.ignored.push(entry.path);
+                    }
+                }
+                FileKind::Dir => self.walk_backend(backend, walk, &entry.path, depth + 1, found)?,
+                FileKind::File => self.visit_file(entry.path, found),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Scanned roots relative to the snapshot root (a lone root is "")
+    fn root_labels(&self) -> Vec<PathBuf> {
+        self.roots.iter()
+            .map(|root| root.strip_prefix(&self.root).unwrap_or(root).to_path_buf())
+            .collect()
+    }
+
+    /// Process files, in path order (in parallel with `parallel-execution`)
+    fn process_files(&self, paths: &[PathBuf], policies: &PolicyTree) -> Result<Vec<FileMetadata>> {
+        #[cfg(feature = "parallel-execution")]
+        if self.threads > 1 {
+            use rayon::prelude::*;
+
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(self.threads)
+                .build()
+                .context("Failed to build scan thread pool")?;
+            // Indexed collect keeps path order
+            return pool.install(|| paths.par_iter().map(|path| self.process_file(path, policies)).collect());
+        }
+
+        paths.iter().map(|path| self.process_file(path, policies)).collect()
     }
 
     /// Process a single file and extract metadata.
-    fn process_file(&self, path: &Path) -> Result<FileMetadata> {
+    fn process_file(&self, path: &Path, policies: &PolicyTree) -> Result<FileMetadata> {
         // Read file contents for hashing
-        let contents = fs::read(path)
+        let contents = self.read(path)
             .with_context(|| format!("Failed to read file: {}", path.display()))?;
 
+        let contents = match self.normalize_line_endings {
+            true => normalize_line_endings(contents).0,
+            false => contents,
+        };
+
         // Hash contents
         let content_hash = Self::hash_bytes(&contents);
 
         // Get file metadata
-        let metadata = fs::metadata(path)
-            .with_context(|| format!("Failed to get metadata for: {}", path.display()))?;
+        let (stored_size, mtime) = match &self.backend {
+            Some(backend) => backend.metadata(path).map(|stat| (stat.len, stat.modified)),
+            None => fs::metadata(path)
+                .map(|metadata| (metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH))),
+        }.with_context(|| format!("Failed to get metadata for: {}", path.display()))?;
+        // A normalized file is sized as its view
+        let size = match self.normalize_line_endings {
+            true => contents.len() as u64,
+            false => stored_size,
+        };
 
         // Normalize path relative to root
-        let relative_path = path.strip_prefix(&self.root)
-            .context("Failed to compute relative path")?
-            .to_path_buf();
+        let relative_path = self.relative(path)?;
 
         // Detect language
         let language = path.extension()
@@ -148,53 +535,97 @@ impl RepoScanner {
             .and_then(Language::from_extension);
 
         Ok(FileMetadata {
-            path: relative_path,
-            size: metadata.len(),
-            mtime: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            size,
+            mtime,
             content_hash,
             language,
+            policy: policies.policy_for(&relative_path),
+            path: relative_path,
         })
     }
 
-    /// Compute a deterministic FileId from a path.
-    fn compute_file_id(path: &Path) -> FileId {
-        let path_str = path.to_string_lossy();
-        let hash = Self::hash_string(&path_str);
-        
-        // Use first 8 bytes of SHA256 as FileId
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&hash[0..8]);
-        FileId::new(u64::from_be_bytes(bytes))
+    /// FileId of a file not yet in `files`, failing closed on a collision
+    fn assign_file_id(&self, metadata: &FileMetadata, files: &BTreeMap<FileId, FileMetadata>) -> Result<FileId> {
+        let key = Self::file_id_key(metadata, self.file_id_strategy);
+        let file_id = self.compute_file_id(&key);
+        let Some(existing) = files.get(&file_id) else {
+            return Ok(file_id);
+        };
+
+        if self.file_id_derivation == FileIdDerivation::Rehash {
+            let rehashed = (1..=MAX_REHASHES)
+                .map(|attempt| self.compute_file_id(&format!("{}\0#{}", key, attempt)))
+                .find(|id| !files.contains_key(id));
+            if let Some(rehashed) = rehashed {
+                tracing::warn!(
+                    path = %metadata.path.display(), colliding = %existing.path.display(),
+                    "FileId collision, path rehashed"
+                );
+                return Ok(rehashed);
+            }
+        }
+        Err(FileIdCollision {
+            path_a: existing.path.clone(),
+            path_b: metadata.path.clone(),
+            id: file_id,
+        }.into())
     }
 
-    /// Hash bytes with SHA256.
-    fn hash_bytes(data: &[u8]) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(data);
-        format!("{:x}", hasher.finalize())
+    /// What a file's FileId is hashed from: its normalized path (and content hash)
+    fn file_id_key(metadata: &FileMetadata, strategy: FileIdStrategy) -> String {
+        let mut key = normalize_path(&metadata.path);
+        if strategy == FileIdStrategy::PathAndContentHash {
+            key.push('\0');
+            key.push_str(&metadata.content_hash);
+        }
+        key
     }
 
-    /// Hash a string with SHA256.
-    fn hash_string(s: &str) -> Vec<u8> {
+    /// Compute a deterministic FileId from a key.
+    fn compute_file_id(&self, key: &str) -> FileId {
+        self.file_id_derivation.file_id(&(self.key_hasher)(key))
+    }
+
+    /// Hash bytes with SHA256 (`FileMetadata::content_hash`)
+    pub(crate) fn hash_bytes(data: &[u8]) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(s.as_bytes());
-        hasher.finalize().to_vec()
+        hasher.update(data);
+        format!("{:x}", hasher.finalize())
     }
 
     /// Compute overall snapshot hash for verification.
-    fn compute_snapshot_hash(files: &HashMap<FileId, FileMetadata>) -> String {
+    fn compute_snapshot_hash(
+        roots: &[PathBuf],
+        policy_files: &BTreeMap<PathBuf, String>,
+        files: &BTreeMap<FileId, FileMetadata>,
+    ) -> String {
         let mut hasher = Sha256::new();
 
-        // Sort file IDs for determinism
-        let mut file_ids: Vec<_> = files.keys().collect();
-        file_ids.sort();
+        // Root labels (single-root hashes predate workspaces and omit them)
+        if roots.len() > 1 {
+            hasher.update((roots.len() as u64).to_be_bytes());
+            for root in roots {
+                hasher.update(normalize_path(root).as_bytes());
+                hasher.update([0]);
+            }
+        }
+
+        // Policy files (none: the hash predates them)
+        if !policy_files.is_empty() {
+            hasher.update(b"policy");
+            hasher.update((policy_files.len() as u64).to_be_bytes());
+            for (path, content_hash) in policy_files {
+                hasher.update(normalize_path(path).as_bytes());
+                hasher.update([0]);
+                hasher.update(content_hash.as_bytes());
+            }
+        }
 
-        // Hash each file's metadata in order
-        for file_id in file_ids {
-            let metadata = &files[file_id];
+        // Hash each file's metadata in FileId order
+        for (file_id, metadata) in files {
             hasher.update(file_id.as_u64().to_be_bytes());
-            hasher.update(metadata.path.to_string_lossy().as_bytes());
-            hasher.update(&metadata.size.to_be_bytes());
+            hasher.update(normalize_path(&metadata.path).as_bytes());
+            hasher.update(metadata.size.to_be_bytes());
             hasher.update(metadata.content_hash.as_bytes());
         }
 
@@ -202,9 +633,95 @@ impl RepoScanner {
     }
 }
 
+/// Platform-independent form of a relative path: `/` separators, no empty
+/// or `.` components, Unicode NFC
+///
+/// Non-UTF-8 bytes are replaced with U+FFFD, as `to_string_lossy` does.
+pub fn normalize_path(path: &Path) -> String {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let joined = raw.split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    joined.nfc().collect()
+}
+
+/// Paths collected by a scan's walks
+#[derive(Default)]
+struct Found {
+    /// Wanted files
+    files: Vec<PathBuf>,
+
+    /// `.vcr.toml` files
+    overrides: Vec<PathBuf>,
+
+    /// Paths left out, if wanted (see `scan_with_excluded`)
+    excluded: Option<Excluded>,
+}
+
+/// Walked paths a scan left out of its snapshot, relative to the snapshot
+/// root and sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Excluded {
+    /// Pruned by `.vcrignore` (an ignored directory is listed, not its
+    /// contents)
+    pub ignored: Vec<PathBuf>,
+
+    /// Files not matching the extension filter
+    pub unwanted: Vec<PathBuf>,
+}
+
+/// One root's walk
+struct Walk<'a> {
+    root: &'a Path,
+    ignore: &'a IgnoreRules,
+}
+
+impl Walk<'_> {
+    /// Whether `.vcrignore` excludes a path below the root
+    fn ignores(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(self.root).unwrap_or(path);
+        !self.ignore.is_empty() && self.ignore.is_ignored(&normalize_path(relative), is_dir)
+    }
+}
+
+/// Sort and deduplicate roots, rejecting nesting
+fn check_roots(mut roots: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+    roots.sort();
+    roots.dedup();
+
+    if roots.is_empty() {
+        bail!("Workspace needs at least one root");
+    }
+    for pair in roots.windows(2) {
+        // Sorted order puts a parent directly before its first descendant
+        if pair[1].starts_with(&pair[0]) {
+            bail!("Workspace root {} is inside root {}", pair[1].display(), pair[0].display());
+        }
+    }
+    Ok(roots)
+}
+
+/// SHA-256 of a FileId key
+fn sha256_key(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// Deepest directory containing every path
+fn common_ancestor(paths: &[PathBuf]) -> PathBuf {
+    let mut ancestor = paths[0].clone();
+    while !paths.iter().all(|p| p.starts_with(&ancestor)) {
+        if !ancestor.pop() {
+            break;
+        }
+    }
+    ancestor
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::FilePolicy;
     use std::fs;
     use tempfile::TempDir;
 
@@ -253,12 +770,39 @@ mod tests {
         assert_eq!(snapshot1.files.len(), snapshot2.files.len());
     }
 
+    #[test]
+    fn test_serialized_snapshot_is_byte_identical() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["z.rs", "a.rs", "m.rs", "b.rs", "y.rs", "c.rs"] {
+            fs::write(temp_dir.path().join(name), name).unwrap();
+        }
+        let scanner = RepoScanner::new(temp_dir.path()).unwrap().with_extension("rs");
+
+        // Two independent scans; only the creation time may differ
+        let first = scanner.scan().unwrap();
+        let mut second = scanner.scan().unwrap();
+        second.created_at = first.created_at;
+
+        let bytes = serde_json::to_vec(&first).unwrap();
+        assert_eq!(bytes, serde_json::to_vec(&second).unwrap());
+
+        // Round-tripping keeps the bytes, and files stay in FileId order
+        let restored: RepoSnapshot = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(serde_json::to_vec(&restored).unwrap(), bytes);
+        let ids: Vec<_> = restored.files.keys().copied().collect();
+        assert_eq!(ids, first.file_ids());
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    }
+
     #[test]
     fn test_extension_filtering() {
         let temp_dir = TempDir::new().unwrap();
         
         fs::write(temp_dir.path().join("code.rs"), "// Rust").unwrap();
         fs::write(temp_dir.path().join("data.txt"), "data").unwrap();
+        fs::write(temp_dir.path().join("code.rs.bak"), "// Rust").unwrap();
+        fs::write(temp_dir.path().join("CODE.RS"), "// Rust").unwrap();
+        fs::write(temp_dir.path().join("rs"), "// Rust").unwrap();
 
         let scanner = RepoScanner::new(temp_dir.path())
             .unwrap()
@@ -272,4 +816,348 @@ mod tests {
         let file = snapshot.files.values().next().unwrap();
         assert_eq!(file.language, Some(Language::Rust));
     }
+
+    /// Default FileId of a path
+    fn path_id(meta: &FileMetadata) -> FileId {
+        let key = RepoScanner::file_id_key(meta, FileIdStrategy::PathHash);
+        FileIdDerivation::Truncated.file_id(&sha256_key(&key))
+    }
+
+    fn metadata(path: &str, content_hash: &str) -> FileMetadata {
+        FileMetadata {
+            path: PathBuf::from(path),
+            size: 4,
+            mtime: SystemTime::UNIX_EPOCH,
+            content_hash: content_hash.to_string(),
+            language: Some(Language::Rust),
+            policy: FilePolicy::default(),
+        }
+    }
+
+    #[test]
+    fn test_path_forms_give_identical_ids_and_hash() {
+        // The same checkout as seen by Windows, Linux, and a sloppy join
+        let forms = [
+            ["src/handlers/login.rs", "src/lib.rs", "café/mod.rs"],
+            ["src\\handlers\\login.rs", "src\\lib.rs", "café\\mod.rs"],
+            ["./src//handlers/login.rs", "src/./lib.rs", "cafe\u{301}/mod.rs"],
+        ];
+
+        let snapshots: Vec<_> = forms.iter().map(|paths| {
+            let files: BTreeMap<_, _> = paths.iter()
+                .map(|path| {
+                    let meta = metadata(path, "h");
+                    (path_id(&meta), meta)
+                })
+                .collect();
+            let hash = RepoScanner::compute_snapshot_hash(&[PathBuf::new()], &BTreeMap::new(), &files);
+            (files.keys().copied().collect::<Vec<_>>(), hash)
+        }).collect();
+
+        assert_eq!(snapshots[0], snapshots[1]);
+        assert_eq!(snapshots[0], snapshots[2]);
+        assert_eq!(normalize_path(Path::new("cafe\u{301}\\mod.rs")), "caf\u{e9}/mod.rs");
+    }
+
+    #[test]
+    fn test_decomposed_file_name_scans_as_nfc() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("cafe\u{301}.rs"), "fn f() {}").unwrap();
+
+        let snapshot = RepoScanner::new(temp_dir.path()).unwrap().with_extension("rs").scan().unwrap();
+
+        let composed = path_id(&metadata("caf\u{e9}.rs", "h"));
+        assert_eq!(snapshot.file_ids(), vec![composed]);
+    }
+
+    #[test]
+    fn test_path_and_content_strategy() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("a.rs");
+        fs::write(&file, "// one").unwrap();
+        let scan = |strategy| RepoScanner::new(temp_dir.path()).unwrap()
+            .with_extension("rs")
+            .with_file_id_strategy(strategy)
+            .scan()
+            .unwrap()
+            .file_ids();
+
+        let (by_path, by_content) = (scan(FileIdStrategy::PathHash), scan(FileIdStrategy::PathAndContentHash));
+        assert_ne!(by_path, by_content);
+
+        fs::write(&file, "// two").unwrap();
+        assert_eq!(scan(FileIdStrategy::PathHash), by_path);
+        assert_ne!(scan(FileIdStrategy::PathAndContentHash), by_content);
+    }
+
+    /// b.rs hashes exactly like a.rs
+    fn b_collides_with_a(key: &str) -> [u8; 32] {
+        sha256_key(if key == "b.rs" { "a.rs" } else { key })
+    }
+
+    /// Every key shares its first 8 hash bytes
+    fn prefixes_collide(key: &str) -> [u8; 32] {
+        let mut hash = sha256_key(key);
+        hash[..8].fill(0);
+        hash
+    }
+
+    fn collision_repo() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            fs::write(temp_dir.path().join(name), name).unwrap();
+        }
+        temp_dir
+    }
+
+    #[test]
+    fn test_file_id_collision_fails_closed() {
+        let temp_dir = collision_repo();
+        let scanner = RepoScanner::new(temp_dir.path()).unwrap().with_extension("rs");
+
+        let err = scanner.with_key_hasher(b_collides_with_a).scan().unwrap_err();
+        let collision = err.downcast_ref::<FileIdCollision>().unwrap();
+        assert_eq!(collision.path_a, PathBuf::from("a.rs"));
+        assert_eq!(collision.path_b, PathBuf::from("b.rs"));
+        assert_eq!(collision.id, path_id(&metadata("a.rs", "h")));
+        assert!(err.to_string().contains("FileIdDerivation::Folded"), "{}", err);
+
+        // Folded only fails if the second 8 bytes collide too
+        let scan = |derivation| RepoScanner::new(temp_dir.path()).unwrap()
+            .with_extension("rs")
+            .with_key_hasher(prefixes_collide)
+            .with_file_id_derivation(derivation)
+            .scan();
+        assert!(scan(FileIdDerivation::Truncated).unwrap_err().is::<FileIdCollision>());
+        assert!(scan(FileIdDerivation::Rehash).unwrap_err().is::<FileIdCollision>());
+        assert_eq!(scan(FileIdDerivation::Folded).unwrap().files.len(), 3);
+    }
+
+    #[test]
+    fn test_rehash_disambiguates_deterministically() {
+        let temp_dir = collision_repo();
+        let scan = || RepoScanner::new(temp_dir.path()).unwrap()
+            .with_extension("rs")
+            .with_key_hasher(b_collides_with_a)
+            .with_file_id_derivation(FileIdDerivation::Rehash)
+            .scan()
+            .unwrap();
+
+        let (first, second) = (scan(), scan());
+        assert_eq!(first.files.len(), 3);
+        assert_eq!(first.file_ids(), second.file_ids());
+        assert_eq!(first.snapshot_hash, second.snapshot_hash);
+        assert_eq!(first.files[&path_id(&metadata("a.rs", "h"))].path, PathBuf::from("a.rs"));
+        assert_eq!(first.files[&path_id(&metadata("c.rs", "h"))].path, PathBuf::from("c.rs"));
+    }
+
+    fn workspace() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        for krate in ["a", "b"] {
+            let src = temp_dir.path().join("crates").join(krate).join("src");
+            fs::create_dir_all(&src).unwrap();
+            fs::write(src.join("lib.rs"), format!("fn {}() {{}}", krate)).unwrap();
+        }
+        fs::create_dir_all(temp_dir.path().join("target")).unwrap();
+        fs::write(temp_dir.path().join("target").join("build.rs"), "// generated").unwrap();
+        temp_dir
+    }
+
+    fn scan_roots(temp_dir: &TempDir, roots: &[&str]) -> RepoSnapshot {
+        RepoScanner::with_roots(roots.iter().map(|r| temp_dir.path().join(r)).collect())
+            .unwrap()
+            .with_extension("rs")
+            .scan()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_workspace_roots_namespace_file_ids() {
+        let temp_dir = workspace();
+        let snapshot = scan_roots(&temp_dir, &["crates/a", "crates/b"]);
+
+        let mut paths: Vec<_> = snapshot.files.values().map(|m| m.path.clone()).collect();
+        paths.sort();
+        assert_eq!(paths, vec![PathBuf::from("a/src/lib.rs"), PathBuf::from("b/src/lib.rs")]);
+        assert_eq!(snapshot.file_ids().len(), 2);
+        assert_eq!(snapshot.roots, vec![PathBuf::from("a"), PathBuf::from("b")]);
+        assert_eq!(snapshot.root, temp_dir.path().join("crates").canonicalize().unwrap());
+        assert_eq!(snapshot.root_paths()[1], temp_dir.path().join("crates/b").canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_workspace_hash_is_stable_and_order_independent() {
+        let temp_dir = workspace();
+        let forward = scan_roots(&temp_dir, &["crates/a", "crates/b"]);
+        let reverse = scan_roots(&temp_dir, &["crates/b", "crates/a", "crates/b"]);
+
+        assert_eq!(forward.snapshot_hash, reverse.snapshot_hash);
+        assert_eq!(forward.file_ids(), reverse.file_ids());
+        assert_ne!(forward.snapshot_hash, scan_roots(&temp_dir, &["crates/a"]).snapshot_hash);
+    }
+
+    #[test]
+    fn test_single_root_matches_new() {
+        let temp_dir = workspace();
+        let single = scan_roots(&temp_dir, &["crates/a"]);
+        let plain = RepoScanner::new(temp_dir.path().join("crates/a")).unwrap().with_extension("rs").scan().unwrap();
+
+        assert_eq!(single.snapshot_hash, plain.snapshot_hash);
+        assert_eq!(single.file_ids(), plain.file_ids());
+    }
+
+    #[test]
+    fn test_nested_roots_rejected() {
+        let temp_dir = workspace();
+        let nested = vec![temp_dir.path().join("crates"), temp_dir.path().join("crates/a")];
+        let err = RepoScanner::with_roots(nested).err().unwrap();
+        assert!(err.to_string().contains("is inside root"), "{}", err);
+        assert!(RepoScanner::with_roots(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_threads_do_not_change_snapshot() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..20 {
+            fs::write(temp_dir.path().join(format!("f{:02}.rs", i)), format!("fn f{}() {{}}", i)).unwrap();
+        }
+        let scan = |threads| RepoScanner::new(temp_dir.path()).unwrap().with_extension("rs").with_threads(threads).scan().unwrap();
+
+        let serial = scan(1);
+        let parallel = scan(4);
+        assert_eq!(serial.snapshot_hash, parallel.snapshot_hash);
+        assert_eq!(serial.file_ids(), parallel.file_ids());
+    }
+
+    #[test]
+    fn test_backends_match_filesystem_scan() {
+        use crate::io::{hot::HotPathIO, MemoryBackend};
+
+        let temp_dir = workspace();
+        let disk = scan_roots(&temp_dir, &["crates/a", "crates/b"]);
+
+        // Same files under another (nonexistent) root
+        let mut memory = MemoryBackend::default();
+        for meta in disk.files.values() {
+            memory.insert(Path::new("/repo/crates").join(&meta.path), fs::read(disk.root.join(&meta.path)).unwrap());
+        }
+        memory.insert("/repo/crates/b/notes.txt", "skipped");
+        let roots = vec![PathBuf::from("/repo/crates/b"), PathBuf::from("/repo/crates/a")];
+        let in_memory = RepoScanner::with_backend(roots, Arc::new(memory)).unwrap().with_extension("rs").scan().unwrap();
+        assert_eq!(in_memory.snapshot_hash, disk.snapshot_hash);
+        assert_eq!(in_memory.file_ids(), disk.file_ids());
+
+        // Default trait methods walk the local filesystem
+        let roots = ["crates/a", "crates/b"].iter().map(|r| temp_dir.path().join(r)).collect();
+        let hot = RepoScanner::with_backend(roots, Arc::new(HotPathIO::new())).unwrap().with_extension("rs").scan().unwrap();
+        assert_eq!(hot.snapshot_hash, disk.snapshot_hash);
+
+        let missing = RepoScanner::with_backend(vec![PathBuf::from("/nope")], Arc::new(MemoryBackend::default()));
+        assert!(missing.is_err());
+    }
+
+    fn policy_repo() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        for dir in ["src", "generated/keep", "target"] {
+            fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+        }
+        for file in ["src/lib.rs", "src/big.rs", "generated/out.rs", "generated/keep/api.rs", "target/build.rs"] {
+            fs::write(temp_dir.path().join(file), "fn f() {}").unwrap();
+        }
+        fs::write(temp_dir.path().join(".vcrignore"), "# build output\n/target/\n*.tmp.rs\n").unwrap();
+        fs::write(temp_dir.path().join("src/scratch.tmp.rs"), "fn scratch() {}").unwrap();
+        fs::write(temp_dir.path().join(".vcr.toml"), "max_file_size = 1000\n").unwrap();
+        fs::write(temp_dir.path().join("generated/.vcr.toml"), "skip_semantics = true\nmax_file_size = 5\n").unwrap();
+        fs::write(temp_dir.path().join("generated/keep/.vcr.toml"), "skip_semantics = false\n").unwrap();
+        temp_dir
+    }
+
+    fn policies(snapshot: &RepoSnapshot) -> Vec<(String, FilePolicy)> {
+        let mut policies: Vec<_> = snapshot.files.values().map(|m| (normalize_path(&m.path), m.policy)).collect();
+        policies.sort_by(|a, b| a.0.cmp(&b.0));
+        policies
+    }
+
+    #[test]
+    fn test_vcrignore_and_nested_overrides() {
+        let temp_dir = policy_repo();
+        let snapshot = RepoScanner::new(temp_dir.path()).unwrap().with_extension("rs").scan().unwrap();
+
+        let root = FilePolicy { skip_semantics: false, max_file_size: Some(1000) };
+        assert_eq!(policies(&snapshot), [
+            ("generated/keep/api.rs".to_string(), FilePolicy { skip_semantics: false, max_file_size: Some(5) }),
+            ("generated/out.rs".to_string(), FilePolicy { skip_semantics: true, max_file_size: Some(5) }),
+            ("src/big.rs".to_string(), root),
+            ("src/lib.rs".to_string(), root),
+        ]);
+
+        // Same result through a backend walk
+        let mut memory = crate::io::MemoryBackend::default();
+        for entry in WalkDir::new(temp_dir.path()).into_iter().map(Result::unwrap).filter(|e| e.file_type().is_file()) {
+            let relative = entry.path().strip_prefix(temp_dir.path()).unwrap();
+            memory.insert(Path::new("/repo").join(relative), fs::read(entry.path()).unwrap());
+        }
+        let in_memory = RepoScanner::with_backend(vec![PathBuf::from("/repo")], Arc::new(memory))
+            .unwrap().with_extension("rs").scan().unwrap();
+        assert_eq!(policies(&in_memory), policies(&snapshot));
+        assert_eq!(in_memory.snapshot_hash, snapshot.snapshot_hash);
+    }
+
+    #[test]
+    fn test_policy_files_change_snapshot_hash() {
+        let temp_dir = policy_repo();
+        let try_scan = || RepoScanner::new(temp_dir.path()).unwrap().with_extension("rs").scan();
+        let scan = || try_scan().unwrap();
+        let first = scan();
+
+        // A comment changes no policy but is still a different policy file
+        fs::write(temp_dir.path().join("generated/keep/.vcr.toml"), "# keep\nskip_semantics = false\n").unwrap();
+        let commented = scan();
+        assert_ne!(commented.snapshot_hash, first.snapshot_hash);
+        assert_eq!(policies(&commented), policies(&first));
+
+        fs::write(temp_dir.path().join(".vcrignore"), "/target/\n").unwrap();
+        let unignored = scan();
+        assert_eq!(unignored.files.len(), first.files.len() + 1);
+
+        fs::write(temp_dir.path().join("generated/.vcr.toml"), "skip_semantic = true\n").unwrap();
+        let err = format!("{:#}", try_scan().unwrap_err());
+        assert!(err.contains("generated/.vcr.toml") && err.contains("skip_semantic"), "{}", err);
+    }
+
+    #[test]
+    fn test_no_policy_files_keep_hash() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "fn a() {}").unwrap();
+        let snapshot = RepoScanner::new(temp_dir.path()).unwrap().scan().unwrap();
+        let expected = RepoScanner::compute_snapshot_hash(&[PathBuf::new()], &BTreeMap::new(), &snapshot.files);
+        assert_eq!(snapshot.snapshot_hash, expected);
+        assert!(snapshot.files.values().all(|m| m.policy.is_default()));
+    }
+
+    #[test]
+    fn test_git_head_recorded_but_not_hashed() {
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "fn a() {}").unwrap();
+        let scan = || RepoScanner::new(temp_dir.path()).unwrap().with_extension("rs").scan().unwrap();
+        let plain = scan();
+        assert_eq!(plain.vcs, None);
+
+        fs::create_dir_all(temp_dir.path().join(".git/refs/heads")).unwrap();
+        fs::write(temp_dir.path().join(".git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(temp_dir.path().join(".git/refs/heads/main"), format!("{}\n", commit)).unwrap();
+        let tracked = scan();
+        let vcs = tracked.vcs.clone().unwrap();
+        assert_eq!((vcs.commit.as_deref(), vcs.branch.as_deref(), vcs.dirty), (Some(commit), Some("main"), None));
+        assert_eq!(tracked.snapshot_hash, plain.snapshot_hash);
+
+        // Read through the backend like the files
+        let mut memory = crate::io::MemoryBackend::default();
+        memory.insert("/repo/a.rs", "fn a() {}");
+        memory.insert("/repo/.git/HEAD", format!("{}\n", commit));
+        let scanner = RepoScanner::with_backend(vec![PathBuf::from("/repo")], Arc::new(memory)).unwrap().with_extension("rs");
+        let detached = scanner.scan().unwrap().vcs.unwrap();
+        assert_eq!((detached.commit.as_deref(), detached.branch), (Some(commit), None));
+    }
 }
@@ -0,0 +1,178 @@
+//! Git work tree metadata
+//!
+//! Reads the commit and branch a work tree is on straight from its git
+//! directory, without libgit2 or the `git` binary:
+//!
+//! - `.git` is a directory, or a file `gitdir: <path>` (worktrees,
+//!   submodules); a `commondir` file in it points at the shared refs
+//! - `HEAD` holds a commit hash (detached) or `ref: refs/heads/<branch>`
+//! - A ref is read from its loose file, else from `packed-refs`; symbolic
+//!   refs are followed up to `MAX_REF_DEPTH` times
+//!
+//! Anything unreadable or malformed yields no `VcsInfo` rather than an
+//! error: the metadata is informational only and never part of a snapshot
+//! hash.
+
+use crate::io::IOBackend;
+use crate::types::VcsInfo;
+use std::path::{Path, PathBuf};
+
+/// Symbolic refs followed before giving up (ref loops)
+const MAX_REF_DEPTH: usize = 5;
+
+const BRANCH_PREFIX: &str = "refs/heads/";
+
+/// Commit and branch of the work tree at `root` (None without a readable
+/// `.git/HEAD`)
+pub fn read_vcs_info(root: &Path, backend: &dyn IOBackend) -> Option<VcsInfo> {
+    let git = GitDir::locate(root, backend)?;
+    let head = git.read("HEAD")?;
+
+    let info = match head.strip_prefix("ref:") {
+        Some(name) => {
+            let name = name.trim();
+            VcsInfo {
+                commit: git.resolve(name),
+                branch: Some(name.strip_prefix(BRANCH_PREFIX).unwrap_or(name).to_string()),
+                dirty: None,
+            }
+        }
+        None => VcsInfo { commit: Some(parse_hash(&head)?), branch: None, dirty: None },
+    };
+    Some(info)
+}
+
+/// A git directory and the directory holding its refs
+struct GitDir<'a> {
+    dir: PathBuf,
+    common: PathBuf,
+    backend: &'a dyn IOBackend,
+}
+
+impl<'a> GitDir<'a> {
+    fn locate(root: &Path, backend: &'a dyn IOBackend) -> Option<Self> {
+        let dot_git = root.join(".git");
+        let dir = match read_text(backend, &dot_git.join("HEAD")) {
+            Some(_) => dot_git,
+            None => {
+                let link = read_text(backend, &dot_git)?;
+                root.join(link.strip_prefix("gitdir:")?.trim())
+            }
+        };
+        let common = match read_text(backend, &dir.join("commondir")) {
+            Some(common) => dir.join(common.trim()),
+            None => dir.clone(),
+        };
+        Some(Self { dir, common, backend })
+    }
+
+    /// Trimmed contents of a file in the git directory
+    fn read(&self, name: &str) -> Option<String> {
+        read_text(self.backend, &self.dir.join(name)).map(|text| text.trim().to_string())
+    }
+
+    /// Commit a ref points at (None for an unborn branch)
+    fn resolve(&self, name: &str) -> Option<String> {
+        let mut name = name.to_string();
+        for _ in 0..MAX_REF_DEPTH {
+            let loose = [&self.dir, &self.common].into_iter()
+                .find_map(|dir| read_text(self.backend, &dir.join(&name)));
+            let Some(target) = loose else {
+                return self.packed(&name);
+            };
+            match target.trim().strip_prefix("ref:") {
+                Some(next) => name = next.trim().to_string(),
+                None => return parse_hash(&target),
+            }
+        }
+        None
+    }
+
+    /// Commit of a ref in `packed-refs`
+    fn packed(&self, name: &str) -> Option<String> {
+        let packed = read_text(self.backend, &self.common.join("packed-refs"))?;
+        packed.lines()
+            .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+            .find_map(|line| {
+                let (hash, reference) = line.split_once(' ')?;
+                (reference.trim() == name).then(|| parse_hash(hash)).flatten()
+            })
+    }
+}
+
+fn read_text(backend: &dyn IOBackend, path: &Path) -> Option<String> {
+    String::from_utf8(backend.read_file(path).ok()?).ok()
+}
+
+/// Lowercase SHA-1 or SHA-256 object name
+fn parse_hash(text: &str) -> Option<String> {
+    let hash = text.trim();
+    let valid = matches!(hash.len(), 40 | 64) && hash.bytes().all(|b| b.is_ascii_hexdigit());
+    valid.then(|| hash.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::cold::SyncIOBackend;
+    use tempfile::TempDir;
+
+    const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+    const OTHER: &str = "89abcdef0123456789abcdef0123456789abcdef";
+
+    fn write(root: &Path, path: &str, text: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    fn read(root: &Path) -> Option<VcsInfo> {
+        read_vcs_info(root, &SyncIOBackend)
+    }
+
+    fn info(commit: Option<&str>, branch: Option<&str>) -> Option<VcsInfo> {
+        Some(VcsInfo { commit: commit.map(str::to_string), branch: branch.map(str::to_string), dirty: None })
+    }
+
+    #[test]
+    fn test_loose_packed_and_detached_heads() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(read(dir.path()), None);
+
+        write(dir.path(), ".git/HEAD", "ref: refs/heads/main\n");
+        // Unborn branch
+        assert_eq!(read(dir.path()), info(None, Some("main")));
+
+        write(dir.path(), ".git/packed-refs", &format!(
+            "# pack-refs with: peeled fully-peeled sorted\n{} refs/heads/main\n^{}\n{} refs/tags/v1\n", OTHER, COMMIT, COMMIT,
+        ));
+        assert_eq!(read(dir.path()), info(Some(OTHER), Some("main")));
+
+        // A loose ref wins over the packed one
+        write(dir.path(), ".git/refs/heads/main", &format!("{}\n", COMMIT.to_uppercase()));
+        assert_eq!(read(dir.path()), info(Some(COMMIT), Some("main")));
+
+        write(dir.path(), ".git/HEAD", &format!("{}\n", OTHER));
+        assert_eq!(read(dir.path()), info(Some(OTHER), None));
+
+        write(dir.path(), ".git/HEAD", "garbage\n");
+        assert_eq!(read(dir.path()), None);
+    }
+
+    #[test]
+    fn test_worktree_link_and_symbolic_refs() {
+        let dir = TempDir::new().unwrap();
+        let main = dir.path().join("main");
+        write(&main, ".git/refs/heads/feature/x", &format!("{}\n", COMMIT));
+        write(&main, ".git/refs/heads/alias", "ref: refs/heads/feature/x\n");
+        write(&main, ".git/worktrees/wt/HEAD", "ref: refs/heads/alias\n");
+        write(&main, ".git/worktrees/wt/commondir", "../..\n");
+        write(dir.path(), "wt/.git", "gitdir: ../main/.git/worktrees/wt\n");
+
+        assert_eq!(read(&dir.path().join("wt")), info(Some(COMMIT), Some("alias")));
+
+        // Ref loop
+        write(&main, ".git/refs/heads/alias", "ref: refs/heads/alias\n");
+        assert_eq!(read(&dir.path().join("wt")), info(None, Some("alias")));
+    }
+}
@@ -0,0 +1,273 @@
+//! Ingestion reports
+//!
+//! **Goal**: A quick inventory of what one ingest produced
+//!
+//! `ReportBuilder` assembles one row per scanned file from the repository
+//! snapshot, the semantic epoch and the run's metrics: path, FileId,
+//! language, size, parse time and quality, function/CFG/DFG/symbol counts
+//! and the semantic fingerprint. Rows are sorted by path. Files read from
+//! an unsaved-buffer overlay are marked `overlay`; files cut short by a
+//! `[limits]` budget carry a `Degraded` status.
+//!
+//! **Deterministic**: Everything except `parse_time_us` depends only on the
+//! repository contents; `without_timings` leaves it out.
+
+use crate::metrics::MetricsCollector;
+use crate::pipeline::PipelineOutput;
+use crate::repo::normalize_path;
+use crate::semantic::symbols::SymbolKind;
+use crate::semantic::{SemanticEpoch, SemanticStatus};
+use crate::storage::UNKNOWN;
+use crate::types::{FileId, FilePolicy, ParseQuality, RepoSnapshot};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Ingestion status of one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileReport {
+    /// Normalized relative path
+    pub path: String,
+
+    pub file_id: FileId,
+
+    /// Detected language (`"unknown"` if none)
+    pub language: String,
+
+    /// Size in bytes
+    pub size: u64,
+
+    /// SHA-256 of the contents (empty in reports stored before storage
+    /// version 9)
+    #[serde(default)]
+    pub content_hash: String,
+
+    /// Effective `.vcr.toml` settings (omitted when default)
+    #[serde(default, skip_serializing_if = "FilePolicy::is_default")]
+    pub policy: FilePolicy,
+
+    /// Parse time in microseconds (None if the run did not parse the
+    /// file). **Non-deterministic**
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_time_us: Option<u64>,
+
+    /// No ERROR or MISSING nodes
+    pub parse_clean: bool,
+
+    /// ERROR and MISSING nodes (outermost only)
+    pub parse_errors: usize,
+
+    /// Function symbols
+    pub functions: usize,
+
+    pub cfgs: usize,
+    pub dfgs: usize,
+    pub symbols: usize,
+
+    /// Semantic fingerprint (None if the file's semantics were not built)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+
+    /// Contents came from an unsaved-buffer overlay, not the file
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub overlay: bool,
+
+    /// Whether a budget left functions out (omitted when complete)
+    #[serde(default, skip_serializing_if = "SemanticStatus::is_complete")]
+    pub status: SemanticStatus,
+}
+
+/// Assembles `FileReport`s for one ingest
+pub struct ReportBuilder<'a> {
+    snapshot: &'a RepoSnapshot,
+    semantic: &'a SemanticEpoch,
+    metrics: Option<&'a MetricsCollector>,
+    parse_errors: Option<&'a BTreeMap<FileId, ParseQuality>>,
+    overlaid: Option<&'a BTreeSet<FileId>>,
+}
+
+impl<'a> ReportBuilder<'a> {
+    /// Report over a snapshot and the epoch built from it
+    pub fn new(snapshot: &'a RepoSnapshot, semantic: &'a SemanticEpoch) -> Self {
+        Self { snapshot, semantic, metrics: None, parse_errors: None, overlaid: None }
+    }
+
+    /// Report over a pipeline run, including its syntax errors and overlays
+    pub fn from_output(output: &'a PipelineOutput) -> Self {
+        Self::new(&output.snapshot, &output.semantic)
+            .with_parse_errors(&output.parse_errors)
+            .with_overlaid(&output.overlaid)
+    }
+
+    /// Take parse times from the run's metrics
+    pub fn with_metrics(mut self, metrics: &'a MetricsCollector) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Files with syntax errors (all others are reported clean)
+    pub fn with_parse_errors(mut self, parse_errors: &'a BTreeMap<FileId, ParseQuality>) -> Self {
+        self.parse_errors = Some(parse_errors);
+        self
+    }
+
+    /// Files read from overlays
+    pub fn with_overlaid(mut self, overlaid: &'a BTreeSet<FileId>) -> Self {
+        self.overlaid = Some(overlaid);
+        self
+    }
+
+    /// One row per scanned file, sorted by path
+    pub fn build(&self) -> Vec<FileReport> {
+        let mut rows: Vec<FileReport> = self.snapshot.files.iter()
+            .map(|(file_id, meta)| {
+                let quality = self.parse_errors.and_then(|errors| errors.get(file_id));
+                let symbols = self.semantic.get_symbols(*file_id);
+                FileReport {
+                    path: normalize_path(&meta.path),
+                    file_id: *file_id,
+                    language: meta.language.map_or(UNKNOWN, |l| l.name()).to_string(),
+                    size: meta.size,
+                    content_hash: meta.content_hash.clone(),
+                    policy: meta.policy,
+                    parse_time_us: self.metrics.and_then(|m| m.parse_time(*file_id)),
+                    parse_clean: quality.is_none_or(|q| q.clean),
+                    parse_errors: quality.map_or(0, |q| q.error_count),
+                    functions: symbols.map_or(0, |table| {
+                        table.symbols().filter(|s| s.kind == SymbolKind::Function).count()
+                    }),
+                    cfgs: self.semantic.get_cfgs(*file_id).map_or(0, Vec::len),
+                    dfgs: self.semantic.get_dfgs(*file_id).map_or(0, Vec::len),
+                    symbols: symbols.map_or(0, |table| table.symbols().count()),
+                    fingerprint: self.semantic.fingerprint(*file_id).map(str::to_string),
+                    overlay: self.overlaid.is_some_and(|overlaid| overlaid.contains(file_id)),
+                    status: self.semantic.status(*file_id),
+                }
+            })
+            .collect();
+        rows.sort_by(|a, b| a.path.cmp(&b.path));
+        rows
+    }
+}
+
+/// Copy of `files` with every `parse_time_us` dropped
+pub fn without_timings(files: &[FileReport]) -> Vec<FileReport> {
+    files.iter().map(|file| FileReport { parse_time_us: None, ..file.clone() }).collect()
+}
+
+/// Plain-text table, one line per file after a header
+///
+/// Fingerprints are shortened to 12 hex digits; missing values print as `-`.
+/// Overlaid paths end in ` [overlay]`, degraded ones in ` [degraded]`.
+pub fn render_table(files: &[FileReport]) -> String {
+    let header = [
+        "PATH", "FILE_ID", "LANGUAGE", "SIZE", "PARSE_US", "ERRORS",
+        "FUNCTIONS", "CFGS", "DFGS", "SYMBOLS", "FINGERPRINT",
+    ].map(String::from);
+    let rows: Vec<[String; 11]> = files.iter()
+        .map(|file| [
+            path_cell(file),
+            format!("{:016x}", file.file_id.as_u64()),
+            file.language.clone(),
+            file.size.to_string(),
+            file.parse_time_us.map_or("-".into(), |us| us.to_string()),
+            file.parse_errors.to_string(),
+            file.functions.to_string(),
+            file.cfgs.to_string(),
+            file.dfgs.to_string(),
+            file.symbols.to_string(),
+            file.fingerprint.as_deref().map_or("-".into(), |f| f.chars().take(12).collect()),
+        ])
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row.iter().zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| match column {
+                0..=2 | 10 => format!("{:<width$}", cell),
+                _ => format!("{:>width$}", cell),
+            })
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Path with its overlay and degraded markers
+fn path_cell(file: &FileReport) -> String {
+    let mut cell = file.path.clone();
+    if file.overlay {
+        cell.push_str(" [overlay]");
+    }
+    if !file.status.is_complete() {
+        cell.push_str(" [degraded]");
+    }
+    cell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use std::path::PathBuf;
+
+    /// `tests/golden/calls`: src/main.rs and src/util.rs
+    fn calls_fixture() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/calls")
+    }
+
+    fn report(metrics: &MetricsCollector) -> Vec<FileReport> {
+        let output = Pipeline::default().run_with_metrics(&calls_fixture(), metrics).unwrap();
+        ReportBuilder::from_output(&output).with_metrics(metrics).build()
+    }
+
+    #[test]
+    fn test_rows_sorted_by_path_with_counts() {
+        let metrics = MetricsCollector::new();
+        let files = report(&metrics);
+
+        let paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["src/main.rs", "src/util.rs"]);
+        for file in &files {
+            assert_eq!(file.language, "rust");
+            assert!(file.parse_clean);
+            assert!(file.functions > 0);
+            assert_eq!(file.cfgs, file.functions);
+            assert_eq!(file.dfgs, file.cfgs);
+            assert!(file.symbols >= file.functions);
+            assert!(file.parse_time_us.is_some());
+            assert_eq!(file.fingerprint.as_ref().map(String::len), Some(64));
+        }
+    }
+
+    #[test]
+    fn test_json_without_timings_is_deterministic() {
+        let json = |files: &[FileReport]| serde_json::to_string(&without_timings(files)).unwrap();
+        let first = report(&MetricsCollector::new());
+        let second = report(&MetricsCollector::new());
+
+        assert_eq!(json(&first), json(&second));
+        assert!(!json(&first).contains("parse_time_us"));
+    }
+
+    #[test]
+    fn test_table_has_header_and_one_line_per_file() {
+        let files = report(&MetricsCollector::new());
+        let table = render_table(&without_timings(&files));
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("PATH"));
+        assert!(lines[1].starts_with("src/main.rs"));
+        assert!(lines[1].contains(&files[0].fingerprint.as_ref().unwrap()[..12]));
+        assert!(lines[1].contains(" - "), "{}", lines[1]);
+    }
+}
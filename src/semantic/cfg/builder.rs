@@ -19,10 +19,25 @@
 //! - Nodes emitted in parse tree order
 //! - Edges added as encountered (no reordering)
 //! - No parallelism, no hash maps for node storage
+//!
+//! Statement text is interned into the caller's StringArena; repeated
+//! statements share one copy.
+//!
+//! Node kinds and field names come from a `LanguageProfile` (Rust unless
+//! set with `with_profile`).
+//!
+//! ## Budget
+//!
+//! `with_max_nodes_per_function` caps one CFG. A function whose walk grows
+//! past the cap is abandoned and left out of `build_all`'s result, with a
+//! reason in `over_budget`. The walk stops at the same statement every
+//! time, so for a given cap the remaining CFGs (and their IDs) are stable.
 
 use crate::semantic::model::*;
-use crate::types::{ByteRange, FileId, ParsedFile};
+use crate::semantic::profile::{FieldRole, LanguageProfile, RustProfile, StatementClass};
+use crate::types::{AstNodeId, ByteRange, FileId, ParsedFile};
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use tree_sitter::{Node, TreeCursor};
 
 /// CFG builder for deterministic control flow graph construction
@@ -33,6 +48,9 @@ pub struct CFGBuilder<'a> {
     /// Source code bytes
     source: &'a [u8],
     
+    /// Grammar node kinds and field names
+    profile: &'a dyn LanguageProfile,
+    
     /// Current function being processed
     current_function: Option<FunctionId>,
     
@@ -44,24 +62,92 @@ pub struct CFGBuilder<'a> {
     
     /// Function ID counter
     next_function_id: u64,
+    
+    /// Tree-sitter node id → preorder AstNodeId (for the file being built)
+    ast_ids: HashMap<usize, AstNodeId>,
+    
+    /// Arena statement text is interned into (borrowed from the caller during `build_all`)
+    strings: StringArena,
+    
+    /// Reused buffer for statement text
+    scratch: String,
+
+    /// Nodes one CFG may hold
+    max_nodes_per_function: usize,
+
+    /// Why functions were left out, in file order
+    over_budget: Vec<String>,
+}
+
+/// A CFG grew past `max_nodes_per_function`
+#[derive(Debug)]
+struct NodeBudgetExceeded;
+
+impl std::fmt::Display for NodeBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CFG node budget exceeded")
+    }
 }
 
+impl std::error::Error for NodeBudgetExceeded {}
+
 impl<'a> CFGBuilder<'a> {
     /// Create a new CFG builder
     pub fn new(file_id: FileId, source: &'a [u8]) -> Self {
         Self {
             file_id,
             source,
+            profile: &RustProfile,
             current_function: None,
             current_cfg: None,
             next_node_id: 0,
             next_function_id: 0,
+            ast_ids: HashMap::new(),
+            strings: StringArena::new(),
+            scratch: String::new(),
+            max_nodes_per_function: usize::MAX,
+            over_budget: Vec::new(),
         }
     }
 
+    /// Use `profile` for the file's grammar (see `semantic::profile::profile_for`)
+    pub fn with_profile(mut self, profile: &'a dyn LanguageProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Leave out functions whose CFG exceeds `max` nodes
+    pub fn with_max_nodes_per_function(mut self, max: usize) -> Self {
+        self.max_nodes_per_function = max;
+        self
+    }
+
+    /// Why functions were left out of the last `build_all` (empty if none)
+    pub fn over_budget(&self) -> &[String] {
+        &self.over_budget
+    }
+
     /// Build CFGs for all functions in a parsed file
-    pub fn build_all(&mut self, parsed: &ParsedFile) -> Result<Vec<CFG>> {
+    ///
+    /// Statement text is interned into `strings`. Fails if the profile's
+    /// language is unsupported.
+    pub fn build_all(&mut self, parsed: &ParsedFile, strings: &mut StringArena) -> Result<Vec<CFG>> {
+        self.profile.ensure_supported()?;
+        self.strings = std::mem::take(strings);
+        let result = self.build_functions(parsed);
+        *strings = std::mem::take(&mut self.strings);
+        result
+    }
+
+    /// Walk a file and build one CFG per function
+    fn build_functions(&mut self, parsed: &ParsedFile) -> Result<Vec<CFG>> {
         let mut cfgs = Vec::new();
+        self.over_budget.clear();
+        
+        let index = parsed.preorder_index();
+        self.ast_ids = (0..index.len() as u32)
+            .filter_map(|i| index.node(AstNodeId(i)).map(|node| (node.id(), AstNodeId(i))))
+            .collect();
         
         // Walk the tree to find all function declarations
         let root = parsed.tree.root_node();
@@ -80,27 +166,29 @@ impl<'a> CFGBuilder<'a> {
         cursor: &mut TreeCursor,
         cfgs: &mut Vec<CFG>,
     ) -> Result<()> {
-        match node.kind() {
-            "function_item" => {
-                // Build CFG for this function
-                if let Ok(cfg) = self.build_function_cfg(node) {
-                    cfgs.push(cfg);
+        if self.profile.is_function(node.kind()) {
+            // Build CFG for this function
+            match self.build_function_cfg(node) {
+                Ok(cfg) => cfgs.push(cfg),
+                Err(err) if err.is::<NodeBudgetExceeded>() => {
+                    let name = self.current_cfg.take().map(|cfg| cfg.name).unwrap_or_default();
+                    self.over_budget.push(format!(
+                        "function `{}` exceeds {} CFG nodes", name, self.max_nodes_per_function
+                    ));
                 }
+                Err(_) => self.current_cfg = None,
             }
-            _ => {
-                // Recursively visit children in order
-                if cursor.goto_first_child() {
-                    loop {
-                        let child = cursor.node();
-                        self.visit_node_for_functions(&child, cursor, cfgs)?;
-                        
-                        if !cursor.goto_next_sibling() {
-                            break;
-                        }
-                    }
-                    cursor.goto_parent();
+        } else if cursor.goto_first_child() {
+            // Recursively visit children in order
+            loop {
+                let child = cursor.node();
+                self.visit_node_for_functions(&child, cursor, cfgs)?;
+                
+                if !cursor.goto_next_sibling() {
+                    break;
                 }
             }
+            cursor.goto_parent();
         }
         
         Ok(())
@@ -112,6 +200,11 @@ impl<'a> CFGBuilder<'a> {
         let function_id = FunctionId(self.next_function_id);
         self.next_function_id += 1;
         self.current_function = Some(function_id);
+        let _span = tracing::debug_span!(
+            "cfg",
+            file_id = self.file_id.as_u64(),
+            function_id = function_id.0,
+        ).entered();
         
         // Create entry and exit nodes
         let entry_id = self.new_node_id();
@@ -123,27 +216,48 @@ impl<'a> CFGBuilder<'a> {
             id: entry_id,
             kind: CFGNodeKind::Entry,
             source_range: entry_range,
-            statement: Some("<entry>".to_string()),
+            statement: Some(self.strings.intern("<entry>")),
+            ast_node_id: self.ast_id(function_node),
+            block_value: false,
+            statement_chars: 0,
         };
         
         let exit_node = CFGNode {
             id: exit_id,
             kind: CFGNodeKind::Exit,
             source_range: entry_range,
-            statement: Some("<exit>".to_string()),
+            statement: Some(self.strings.intern("<exit>")),
+            ast_node_id: None,
+            block_value: false,
+            statement_chars: 0,
         };
         
+        // Name and signature (`name` through the end of `parameters`)
+        let (name, signature_range) = match function_node.child_by_field_name(self.profile.field(FieldRole::Name)) {
+            Some(name) => {
+                let end = function_node.child_by_field_name(self.profile.field(FieldRole::Parameters))
+                    .unwrap_or(name)
+                    .end_byte();
+                let name_range = ByteRange::of_node(&name);
+                let text = String::from_utf8_lossy(&self.source[name_range.start..name_range.end]);
+                let signature = ByteRange::try_new(name_range.start, end).unwrap_or_else(|inverted| inverted.clamped());
+                (text.into_owned(), signature)
+            }
+            None => (String::new(), ByteRange::new(entry_range.start, entry_range.start)),
+        };
+
         // Initialize CFG
-        let mut cfg = CFG::new(function_id, self.file_id, entry_id, exit_id);
+        let mut cfg = CFG::new(function_id, self.file_id, entry_id, exit_id)
+            .with_name(name, signature_range);
         cfg.add_node(entry_node);
         cfg.add_node(exit_node);
         
         self.current_cfg = Some(cfg);
         
         // Find function body
-        if let Some(body) = function_node.child_by_field_name("body") {
+        if let Some(body) = function_node.child_by_field_name(self.profile.field(FieldRole::Body)) {
             // Walk the function body
-            let last_node = self.walk_block(&body, entry_id)?;
+            let last_node = self.walk_block(&body, entry_id, CFGEdgeKind::Normal)?;
             
             // Connect last statement to exit
             if let Some(ref mut cfg) = self.current_cfg {
@@ -155,76 +269,116 @@ impl<'a> CFGBuilder<'a> {
             }
         }
         
+        self.check_budget()?;
+
         // Return the built CFG
         self.current_cfg.take().context("CFG not initialized")
     }
 
+    /// Fail with `NodeBudgetExceeded` once the current CFG is over budget
+    fn check_budget(&self) -> Result<()> {
+        match &self.current_cfg {
+            Some(cfg) if cfg.nodes.len() > self.max_nodes_per_function => Err(NodeBudgetExceeded.into()),
+            _ => Ok(()),
+        }
+    }
+
     /// Walk a block of statements
-    fn walk_block(&mut self, block_node: &Node, predecessor: NodeId) -> Result<NodeId> {
-        let mut current = predecessor;
-        
-        // Handle block expression specifically
-        if block_node.kind() == "block" {
-            // Iterate through children in order
+    ///
+    /// `entry_kind` labels the edge from `predecessor` into the first
+    /// statement; later statements are joined by `Normal` edges. A trailing
+    /// expression (no `;`) becomes a Statement flagged as the block's value.
+    fn walk_block(&mut self, block_node: &Node, predecessor: NodeId, entry_kind: CFGEdgeKind) -> Result<NodeId> {
+        // Handle block expression specifically; anything else (a match arm
+        // or `else if`) is a single statement
+        let statements: Vec<Node> = if self.profile.is_block(block_node.kind()) {
             let mut cursor = block_node.walk();
-            if cursor.goto_first_child() {
-                loop {
-                    let child = cursor.node();
-                    
-                    // Process each statement (skip braces)
-                    if child.kind() != "{" && child.kind() != "}" {
-                        if self.is_statement(&child) {
-                            current = self.walk_statement(&child, current)?;
-                        }
-                    }
-                    
-                    if !cursor.goto_next_sibling() {
-                        break;
-                    }
-                }
-            }
+            block_node.children(&mut cursor)
+                .filter(|child| !self.profile.is_skipped(child.kind()))
+                .collect()
+        } else if !self.profile.is_skipped(block_node.kind()) {
+            vec![*block_node]
         } else {
-            // For non-block nodes (single expressions), treat as statement
-            if self.is_statement(block_node) {
-                current = self.walk_statement(block_node, current)?;
-            }
+            Vec::new()
+        };
+
+        let mut current = predecessor;
+        let mut kind = entry_kind;
+        let last = statements.len().saturating_sub(1);
+        for (i, stmt) in statements.iter().enumerate() {
+            current = if i == last && self.profile.is_value_expression(stmt.kind()) {
+                self.build_value(stmt, current, kind)?
+            } else {
+                self.walk_statement(stmt, current, kind)?
+            };
+            kind = CFGEdgeKind::Normal;
+            self.check_budget()?;
         }
         
         Ok(current)
     }
 
     /// Walk a single statement
-    fn walk_statement(&mut self, stmt_node: &Node, predecessor: NodeId) -> Result<NodeId> {
-        // Handle expression_statement wrapper
-        let actual_node = if stmt_node.kind() == "expression_statement" {
-            // Unwrap to get the actual expression
-            if let Some(child) = stmt_node.child(0) {
-                child
-            } else {
-                *stmt_node
-            }
-        } else {
-            *stmt_node
+    fn walk_statement(&mut self, stmt_node: &Node, predecessor: NodeId, kind: CFGEdgeKind) -> Result<NodeId> {
+        // Unwrap an expression statement to get the actual expression
+        let actual_node = match self.profile.classify_statement(stmt_node.kind()) {
+            StatementClass::Expression => stmt_node.child(0).unwrap_or(*stmt_node),
+            _ => *stmt_node,
         };
         
-        match actual_node.kind() {
-            "if_expression" => self.build_if(&actual_node, predecessor),
-            "while_expression" => self.build_loop(&actual_node, predecessor, true),
-            "loop_expression" => self.build_loop(&actual_node, predecessor, false),
-            "match_expression" => self.build_match(&actual_node, predecessor),
-            _ => self.build_simple_statement(stmt_node, predecessor),
+        match self.profile.classify_statement(actual_node.kind()) {
+            StatementClass::If => self.build_if(&actual_node, predecessor, kind),
+            StatementClass::Loop { has_condition } => self.build_loop(&actual_node, predecessor, kind, has_condition),
+            StatementClass::Match => self.build_match(&actual_node, predecessor, kind),
+            StatementClass::Let => self.build_let(&actual_node, predecessor, kind),
+            StatementClass::Expression | StatementClass::Simple => self.build_simple_statement(stmt_node, predecessor, kind),
+        }
+    }
+
+    /// Build CFG for a block's trailing expression
+    fn build_value(&mut self, expr: &Node, predecessor: NodeId, kind: CFGEdgeKind) -> Result<NodeId> {
+        let value_id = self.build_simple_statement(expr, predecessor, kind)?;
+        if let Some(node) = self.current_cfg.as_mut().and_then(|cfg| cfg.nodes.iter_mut().find(|n| n.id == value_id)) {
+            node.block_value = true;
+        }
+        Ok(value_id)
+    }
+
+    /// Build CFG for let binding
+    ///
+    /// An `if` or `match` initializer is expanded first, so the binding
+    /// follows the merge of the arms whose values it takes.
+    fn build_let(&mut self, let_node: &Node, predecessor: NodeId, kind: CFGEdgeKind) -> Result<NodeId> {
+        let value = let_node.child_by_field_name(self.profile.field(FieldRole::Value));
+        match value.map(|value| (value, self.profile.classify_statement(value.kind()))) {
+            Some((value, StatementClass::If)) => {
+                let merge_id = self.build_if(&value, predecessor, kind)?;
+                self.build_simple_statement(let_node, merge_id, CFGEdgeKind::Normal)
+            }
+            Some((value, StatementClass::Match)) => {
+                let merge_id = self.build_match(&value, predecessor, kind)?;
+                self.build_simple_statement(let_node, merge_id, CFGEdgeKind::Normal)
+            }
+            _ => self.build_simple_statement(let_node, predecessor, kind),
         }
     }
 
     /// Build CFG for if expression
-    fn build_if(&mut self, if_node: &Node, predecessor: NodeId) -> Result<NodeId> {
+    ///
+    /// The edge into the consequence is `True` and the edge into the
+    /// alternative (or straight to the merge when there is none) is `False`.
+    fn build_if(&mut self, if_node: &Node, predecessor: NodeId, kind: CFGEdgeKind) -> Result<NodeId> {
         // Create branch node
         let branch_id = self.new_node_id();
+        let (statement, statement_chars) = self.intern_text(if_node, 50);
         let branch_node = CFGNode {
             id: branch_id,
             kind: CFGNodeKind::Branch,
             source_range: self.node_range(if_node),
-            statement: Some(self.node_text(if_node).chars().take(50).collect()),
+            statement: Some(statement),
+            ast_node_id: self.ast_id(if_node),
+            block_value: false,
+            statement_chars,
         };
         
         if let Some(ref mut cfg) = self.current_cfg {
@@ -232,7 +386,7 @@ impl<'a> CFGBuilder<'a> {
             cfg.add_edge(CFGEdge {
                 from: predecessor,
                 to: branch_id,
-                kind: CFGEdgeKind::Normal,
+                kind,
             });
         }
         
@@ -242,7 +396,10 @@ impl<'a> CFGBuilder<'a> {
             id: merge_id,
             kind: CFGNodeKind::Merge,
             source_range: self.node_range(if_node),
-            statement: Some("<merge>".to_string()),
+            statement: Some(self.strings.intern("<merge>")),
+            ast_node_id: None,
+            block_value: false,
+            statement_chars: 0,
         };
         
         if let Some(ref mut cfg) = self.current_cfg {
@@ -250,28 +407,34 @@ impl<'a> CFGBuilder<'a> {
         }
         
         // Process then branch
-        if let Some(then_branch) = if_node.child_by_field_name("consequence") {
-            let then_last = self.walk_block(&then_branch, branch_id)?;
+        if let Some(then_branch) = if_node.child_by_field_name(self.profile.field(FieldRole::Consequence)) {
+            let then_last = self.walk_block(&then_branch, branch_id, CFGEdgeKind::True)?;
             
             if let Some(ref mut cfg) = self.current_cfg {
-                // True edge from branch to then block (walk_block handles internal connections)
+                // An empty then block leaves the True edge to the merge itself
                 cfg.add_edge(CFGEdge {
                     from: then_last,
                     to: merge_id,
-                    kind: CFGEdgeKind::Normal,
+                    kind: if then_last == branch_id { CFGEdgeKind::True } else { CFGEdgeKind::Normal },
                 });
             }
         }
         
         // Process else branch (if present)
-        if let Some(else_branch) = if_node.child_by_field_name("alternative") {
-            let else_last = self.walk_block(&else_branch, branch_id)?;
+        if let Some(else_branch) = if_node.child_by_field_name(self.profile.field(FieldRole::Alternative)) {
+            // An else clause wraps the else block (or an `else if`)
+            let else_body = if self.profile.is_else_clause(else_branch.kind()) {
+                else_branch.named_child(0).unwrap_or(else_branch)
+            } else {
+                else_branch
+            };
+            let else_last = self.walk_block(&else_body, branch_id, CFGEdgeKind::False)?;
             
             if let Some(ref mut cfg) = self.current_cfg {
                 cfg.add_edge(CFGEdge {
                     from: else_last,
                     to: merge_id,
-                    kind: CFGEdgeKind::Normal,
+                    kind: if else_last == branch_id { CFGEdgeKind::False } else { CFGEdgeKind::Normal },
                 });
             }
         } else {
@@ -289,14 +452,18 @@ impl<'a> CFGBuilder<'a> {
     }
 
     /// Build CFG for loop (while or infinite loop)
-    fn build_loop(&mut self, loop_node: &Node, predecessor: NodeId, has_condition: bool) -> Result<NodeId> {
+    fn build_loop(&mut self, loop_node: &Node, predecessor: NodeId, kind: CFGEdgeKind, has_condition: bool) -> Result<NodeId> {
         // Create loop header
         let header_id = self.new_node_id();
+        let (statement, statement_chars) = self.intern_text(loop_node, 50);
         let header_node = CFGNode {
             id: header_id,
             kind: CFGNodeKind::LoopHeader,
             source_range: self.node_range(loop_node),
-            statement: Some(self.node_text(loop_node).chars().take(50).collect()),
+            statement: Some(statement),
+            ast_node_id: self.ast_id(loop_node),
+            block_value: false,
+            statement_chars,
         };
         
         if let Some(ref mut cfg) = self.current_cfg {
@@ -304,7 +471,7 @@ impl<'a> CFGBuilder<'a> {
             cfg.add_edge(CFGEdge {
                 from: predecessor,
                 to: header_id,
-                kind: CFGEdgeKind::Normal,
+                kind,
             });
         }
         
@@ -314,7 +481,10 @@ impl<'a> CFGBuilder<'a> {
             id: merge_id,
             kind: CFGNodeKind::Merge,
             source_range: self.node_range(loop_node),
-            statement: Some("<merge>".to_string()),
+            statement: Some(self.strings.intern("<merge>")),
+            ast_node_id: None,
+            block_value: false,
+            statement_chars: 0,
         };
         
         if let Some(ref mut cfg) = self.current_cfg {
@@ -322,8 +492,8 @@ impl<'a> CFGBuilder<'a> {
         }
         
         // Process loop body
-        if let Some(body) = loop_node.child_by_field_name("body") {
-            let body_last = self.walk_block(&body, header_id)?;
+        if let Some(body) = loop_node.child_by_field_name(self.profile.field(FieldRole::Body)) {
+            let body_last = self.walk_block(&body, header_id, CFGEdgeKind::Normal)?;
             
             if let Some(ref mut cfg) = self.current_cfg {
                 // Body loops back to header
@@ -348,14 +518,17 @@ impl<'a> CFGBuilder<'a> {
     }
 
     /// Build CFG for match expression
-    fn build_match(&mut self, match_node: &Node, predecessor: NodeId) -> Result<NodeId> {
+    fn build_match(&mut self, match_node: &Node, predecessor: NodeId, kind: CFGEdgeKind) -> Result<NodeId> {
         // Create branch node for match
         let branch_id = self.new_node_id();
         let branch_node = CFGNode {
             id: branch_id,
             kind: CFGNodeKind::Branch,
             source_range: self.node_range(match_node),
-            statement: Some("match".to_string()),
+            statement: Some(self.strings.intern("match")),
+            ast_node_id: self.ast_id(match_node),
+            block_value: false,
+            statement_chars: 0,
         };
         
         if let Some(ref mut cfg) = self.current_cfg {
@@ -363,7 +536,7 @@ impl<'a> CFGBuilder<'a> {
             cfg.add_edge(CFGEdge {
                 from: predecessor,
                 to: branch_id,
-                kind: CFGEdgeKind::Normal,
+                kind,
             });
         }
         
@@ -373,7 +546,10 @@ impl<'a> CFGBuilder<'a> {
             id: merge_id,
             kind: CFGNodeKind::Merge,
             source_range: self.node_range(match_node),
-            statement: Some("<merge>".to_string()),
+            statement: Some(self.strings.intern("<merge>")),
+            ast_node_id: None,
+            block_value: false,
+            statement_chars: 0,
         };
         
         if let Some(ref mut cfg) = self.current_cfg {
@@ -381,14 +557,14 @@ impl<'a> CFGBuilder<'a> {
         }
         
         // Process each match arm in order
-        if let Some(body) = match_node.child_by_field_name("body") {
+        if let Some(body) = match_node.child_by_field_name(self.profile.field(FieldRole::Body)) {
             let mut cursor = body.walk();
             if cursor.goto_first_child() {
                 loop {
                     let child = cursor.node();
-                    if child.kind() == "match_arm" {
-                        if let Some(arm_body) = child.child_by_field_name("value") {
-                            let arm_last = self.walk_block(&arm_body, branch_id)?;
+                    if self.profile.is_match_arm(child.kind()) {
+                        if let Some(arm_body) = child.child_by_field_name(self.profile.field(FieldRole::Value)) {
+                            let arm_last = self.walk_block(&arm_body, branch_id, CFGEdgeKind::Normal)?;
                             
                             if let Some(ref mut cfg) = self.current_cfg {
                                 cfg.add_edge(CFGEdge {
@@ -411,13 +587,17 @@ impl<'a> CFGBuilder<'a> {
     }
 
     /// Build CFG for simple statement (assignment, call, etc.)
-    fn build_simple_statement(&mut self, stmt_node: &Node, predecessor: NodeId) -> Result<NodeId> {
+    fn build_simple_statement(&mut self, stmt_node: &Node, predecessor: NodeId, kind: CFGEdgeKind) -> Result<NodeId> {
         let stmt_id = self.new_node_id();
+        let (statement, statement_chars) = self.intern_text(stmt_node, 100);
         let stmt_node_cfg = CFGNode {
             id: stmt_id,
             kind: CFGNodeKind::Statement,
             source_range: self.node_range(stmt_node),
-            statement: Some(self.node_text(stmt_node)),
+            statement: Some(statement),
+            ast_node_id: self.ast_id(stmt_node),
+            block_value: false,
+            statement_chars,
         };
         
         if let Some(ref mut cfg) = self.current_cfg {
@@ -425,30 +605,13 @@ impl<'a> CFGBuilder<'a> {
             cfg.add_edge(CFGEdge {
                 from: predecessor,
                 to: stmt_id,
-                kind: CFGEdgeKind::Normal,
+                kind,
             });
         }
         
         Ok(stmt_id)
     }
 
-    /// Check if a node represents a statement
-    fn is_statement(&self, node: &Node) -> bool {
-        match node.kind() {
-            // Declarations
-            "let_declaration" => true,
-            // Statements
-            "expression_statement" => true,
-            // Control flow
-            "if_expression" | "while_expression" | "loop_expression" | 
-            "for_expression" | "match_expression" => true,
-            // Jump statements
-            "return_expression" | "break_expression" | "continue_expression" => true,
-            // Default: treat unknown as potential statement
-            _ => !matches!(node.kind(), "{" | "}" | "(" | ")" | "," | ";"),
-        }
-    }
-
     /// Get a new node ID
     fn new_node_id(&mut self) -> NodeId {
         let id = NodeId(self.next_node_id);
@@ -456,22 +619,39 @@ impl<'a> CFGBuilder<'a> {
         id
     }
 
+    /// Preorder id of a node in the file being built
+    fn ast_id(&self, node: &Node) -> Option<AstNodeId> {
+        self.ast_ids.get(&node.id()).copied()
+    }
+
     /// Get byte range for a node
     fn node_range(&self, node: &Node) -> ByteRange {
-        ByteRange::new(node.start_byte(), node.end_byte())
+        ByteRange::of_node(node)
     }
 
-    /// Get text content of a node (truncated)
-    fn node_text(&self, node: &Node) -> String {
-        let start = node.start_byte();
-        let end = node.end_byte();
-        let bytes = &self.source[start..end];
-        
-        String::from_utf8_lossy(bytes)
-            .chars()
-            .filter(|c| !c.is_whitespace() || *c == ' ')
-            .take(100)
-            .collect()
+    /// Intern the text of a node and return its untruncated length in chars
+    ///
+    /// Whitespace runs (newlines included) collapse to one space, so tokens
+    /// on adjacent lines stay separate. Text over `max_chars` is cut and
+    /// ends in "…".
+    fn intern_text(&mut self, node: &Node, max_chars: usize) -> (StringId, usize) {
+        let range = self.node_range(node);
+        let text = String::from_utf8_lossy(&self.source[range.start..range.end]);
+
+        self.scratch.clear();
+        let mut chars = 0;
+        for word in text.split_whitespace() {
+            for c in (chars > 0).then_some(' ').into_iter().chain(word.chars()) {
+                if chars < max_chars {
+                    self.scratch.push(c);
+                }
+                chars += 1;
+            }
+        }
+        if chars > max_chars {
+            self.scratch.push('…');
+        }
+        (self.strings.intern(&self.scratch), chars)
     }
 }
 
@@ -496,7 +676,7 @@ mod tests {
         let parsed = parser.parse(&mmap, None).unwrap();
 
         let mut builder = CFGBuilder::new(file_id, source);
-        let cfgs = builder.build_all(&parsed).unwrap();
+        let cfgs = builder.build_all(&parsed, &mut StringArena::new()).unwrap();
 
         assert_eq!(cfgs.len(), 1, "Should have one function");
         
@@ -508,6 +688,24 @@ mod tests {
         assert_eq!(cfg.nodes[1].kind, CFGNodeKind::Exit);
     }
 
+    #[test]
+    fn test_function_name_and_signature() {
+        let source: &[u8] = b"fn handle_login(user: &str, ok: bool) -> bool { ok }\nfn b() {}\n";
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+        let cfgs = CFGBuilder::new(file_id, source).build_all(&parsed, &mut StringArena::new()).unwrap();
+
+        assert_eq!(cfgs[0].name, "handle_login");
+        let sig = cfgs[0].signature_range;
+        assert_eq!(&source[sig.start..sig.end], b"handle_login(user: &str, ok: bool)");
+        assert_eq!(cfgs[0].span(), ByteRange::new(0, source.iter().position(|&b| b == b'\n').unwrap()));
+        assert_eq!(cfgs[1].name, "b");
+    }
+
     #[test]
     fn test_if_expression_cfg() {
         let source = b"fn test() { if true { let x = 1; } else { let y = 2; } }";
@@ -521,7 +719,7 @@ mod tests {
         let parsed = parser.parse(&mmap, None).unwrap();
 
         let mut builder = CFGBuilder::new(file_id, source);
-        let cfgs = builder.build_all(&parsed).unwrap();
+        let cfgs = builder.build_all(&parsed, &mut StringArena::new()).unwrap();
 
         assert_eq!(cfgs.len(), 1);
         
@@ -535,6 +733,149 @@ mod tests {
         assert!(has_merge, "Should have merge node");
     }
 
+    #[test]
+    fn test_else_block_statements_are_walked() {
+        let source = b"fn test(c: bool) { if c { let x = 1; } else { let y = 2; let z = 3; } }";
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+
+        let mut parser = IncrementalParser::new(Language::Rust).unwrap();
+        let parsed = parser.parse(&mmap, None).unwrap();
+
+        let mut strings = StringArena::new();
+        let mut builder = CFGBuilder::new(file_id, source);
+        let cfg = &builder.build_all(&parsed, &mut strings).unwrap()[0];
+
+        // The else block sits inside an `else_clause`; its statements are
+        // CFG nodes too
+        let statements: Vec<_> = cfg.nodes.iter()
+            .filter(|n| n.kind == CFGNodeKind::Statement)
+            .filter_map(|n| n.statement.map(|id| strings.resolve(id)))
+            .collect();
+        assert_eq!(statements, ["let x = 1;", "let y = 2;", "let z = 3;"]);
+    }
+
+    #[test]
+    fn test_branch_edges_are_labeled() {
+        let source = b"fn test(c: bool) { if c { let x = 1; } else { let y = 2; } if c { f(); } if c {} }";
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+        let cfgs = CFGBuilder::new(file_id, source).build_all(&parsed, &mut StringArena::new()).unwrap();
+
+        let cfg = &cfgs[0];
+        let branches: Vec<NodeId> = cfg.nodes.iter()
+            .filter(|n| n.kind == CFGNodeKind::Branch)
+            .map(|n| n.id)
+            .collect();
+        assert_eq!(branches.len(), 3);
+
+        for branch in branches {
+            let out: Vec<CFGEdgeKind> = cfg.edges.iter()
+                .filter(|e| e.from == branch)
+                .map(|e| e.kind)
+                .collect();
+            assert_eq!(out.iter().filter(|k| **k == CFGEdgeKind::True).count(), 1, "{:?}", out);
+            assert_eq!(out.iter().filter(|k| **k == CFGEdgeKind::False).count(), 1, "{:?}", out);
+            assert_eq!(out.len(), 2);
+        }
+    }
+
+    fn build_cfgs(source: &[u8]) -> (Vec<CFG>, StringArena) {
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+        let mut strings = StringArena::new();
+        let cfgs = CFGBuilder::new(file_id, source).build_all(&parsed, &mut strings).unwrap();
+        (cfgs, strings)
+    }
+
+    /// Statement text of every block value
+    fn block_values(cfg: &CFG, strings: &StringArena) -> Vec<String> {
+        cfg.nodes.iter()
+            .filter(|n| n.block_value)
+            .map(|n| strings.resolve(n.statement.unwrap()).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_expression_bodied_function() {
+        let (cfgs, strings) = build_cfgs(b"fn f() -> i32 { 42 }");
+        let cfg = &cfgs[0];
+
+        assert_eq!(block_values(cfg, &strings), vec!["42"]);
+        let value = cfg.nodes.iter().find(|n| n.block_value).unwrap();
+        assert_eq!(value.kind, CFGNodeKind::Statement);
+        assert!(cfg.edges.iter().any(|e| e.from == cfg.entry && e.to == value.id));
+        assert!(cfg.edges.iter().any(|e| e.from == value.id && e.to == cfg.exit));
+    }
+
+    #[test]
+    fn test_block_ending_in_expression() {
+        let (cfgs, strings) = build_cfgs(b"fn f(c: bool) -> i32 { let a = 1; a + 1 }\nfn g(c: bool) { let x = if c { 1 } else { 2 }; f(x); }");
+
+        assert_eq!(block_values(&cfgs[0], &strings), vec!["a + 1"]);
+        assert!(!cfgs[0].nodes.iter().any(|n| n.block_value && strings.resolve(n.statement.unwrap()) == "let a = 1;"));
+
+        // The initializer is expanded: branch, both arm values, merge, then the binding
+        let g = &cfgs[1];
+        assert_eq!(block_values(g, &strings), vec!["1", "2"]);
+        let merge = g.nodes.iter().find(|n| n.kind == CFGNodeKind::Merge).unwrap();
+        let binding = g.nodes.iter()
+            .find(|n| n.statement.is_some_and(|s| strings.resolve(s).starts_with("let x")))
+            .unwrap();
+        assert!(g.edges.iter().any(|e| e.from == merge.id && e.to == binding.id));
+        for value in g.nodes.iter().filter(|n| n.block_value) {
+            assert!(g.edges.iter().any(|e| e.from == value.id && e.to == merge.id));
+        }
+        assert!(g.nodes.iter().any(|n| n.statement.is_some_and(|s| strings.resolve(s) == "f(x);") && !n.block_value));
+    }
+
+    #[test]
+    fn test_empty_bodies() {
+        let (cfgs, strings) = build_cfgs(b"fn f() {}\nfn g() { // nothing\n }\nfn h(c: bool) { if c {} }");
+
+        for cfg in &cfgs[..2] {
+            assert_eq!(cfg.nodes.len(), 2, "{}", cfg.name);
+            assert_eq!(cfg.edges.len(), 1);
+            assert_eq!((cfg.edges[0].from, cfg.edges[0].to), (cfg.entry, cfg.exit));
+        }
+        assert!(block_values(&cfgs[2], &strings).is_empty());
+    }
+
+    #[test]
+    fn test_multiline_statement_text() {
+        let (cfgs, strings) = build_cfgs(b"fn f() {\n    let total =\n\tfoo(bar,\n        baz);\n    let s = \"a  b\";\n}");
+        let cfg = &cfgs[0];
+        let text = |i: usize| strings.resolve(cfg.nodes[i].statement.unwrap()).to_string();
+
+        assert_eq!(text(2), "let total = foo(bar, baz);");
+        assert_eq!(cfg.nodes[2].statement_chars, text(2).chars().count());
+        assert_eq!(text(3), "let s = \"a b\";", "Whitespace runs collapse even inside literals");
+    }
+
+    #[test]
+    fn test_long_statement_is_marked_truncated() {
+        let long = format!("fn f() {{ let x = g({}); }}", "1, ".repeat(60));
+        let (cfgs, strings) = build_cfgs(long.as_bytes());
+        let node = &cfgs[0].nodes[2];
+        let text = strings.resolve(node.statement.unwrap());
+
+        assert_eq!(text.chars().count(), 101);
+        assert!(text.starts_with("let x = g(1, 1, "));
+        assert!(text.ends_with('…'));
+        assert_eq!(node.statement_chars, "let x = g(".len() + "1, ".repeat(60).len() + ");".len());
+    }
+
     #[test]
     fn test_loop_cfg() {
         let source = b"fn test() { loop { break; } }";
@@ -548,7 +889,7 @@ mod tests {
         let parsed = parser.parse(&mmap, None).unwrap();
 
         let mut builder = CFGBuilder::new(file_id, source);
-        let cfgs = builder.build_all(&parsed).unwrap();
+        let cfgs = builder.build_all(&parsed, &mut StringArena::new()).unwrap();
 
         assert_eq!(cfgs.len(), 1);
         
@@ -558,6 +899,26 @@ mod tests {
         assert!(has_loop_header, "Should have loop header node");
     }
 
+    #[test]
+    fn test_let_condition_cfg() {
+        let source = b"fn f(it: I, r: R) { while let Some(x) = it.next() { g(x); } if let Ok(v) = r { h(v); } else { k(); } }";
+        let (cfgs, _) = build_cfgs(source);
+        let cfg = &cfgs[0];
+
+        let outgoing = |kind: CFGNodeKind| -> Vec<CFGEdgeKind> {
+            let node = cfg.nodes.iter().find(|n| n.kind == kind).unwrap().id;
+            let mut out: Vec<_> = cfg.edges.iter().filter(|e| e.from == node).map(|e| e.kind).collect();
+            out.sort_by_key(|k| format!("{:?}", k));
+            out
+        };
+        assert_eq!(outgoing(CFGNodeKind::Branch), vec![CFGEdgeKind::False, CFGEdgeKind::True]);
+        assert!(outgoing(CFGNodeKind::LoopHeader).contains(&CFGEdgeKind::Break));
+        let header = cfg.nodes.iter().find(|n| n.kind == CFGNodeKind::LoopHeader).unwrap().id;
+        assert!(cfg.edges.iter().any(|e| e.to == header && e.kind == CFGEdgeKind::Continue));
+
+        assert_eq!(cfg.compute_hash(), build_cfgs(source).0[0].compute_hash());
+    }
+
     #[test]
     fn test_cfg_determinism() {
         let source = b"fn test() { let x = 1; let y = 2; }";
@@ -572,12 +933,65 @@ mod tests {
 
         // Build CFG twice
         let mut builder1 = CFGBuilder::new(file_id, source);
-        let cfgs1 = builder1.build_all(&parsed).unwrap();
+        let cfgs1 = builder1.build_all(&parsed, &mut StringArena::new()).unwrap();
 
         let mut builder2 = CFGBuilder::new(file_id, source);
-        let cfgs2 = builder2.build_all(&parsed).unwrap();
+        let cfgs2 = builder2.build_all(&parsed, &mut StringArena::new()).unwrap();
 
         // Hashes must be identical
         assert_eq!(cfgs1[0].compute_hash(), cfgs2[0].compute_hash());
     }
+
+    #[test]
+    fn test_function_over_budget_is_left_out() {
+        let source = b"fn a() { let x = 1; }\nfn big() { f(); f(); f(); f(); f(); }\nfn c() { g(); }";
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+        let mut builder = CFGBuilder::new(file_id, source).with_max_nodes_per_function(4);
+        let cfgs = builder.build_all(&parsed, &mut StringArena::new()).unwrap();
+
+        assert_eq!(builder.over_budget(), ["function `big` exceeds 4 CFG nodes"]);
+        assert_eq!(cfgs.iter().map(|cfg| cfg.name.as_str()).collect::<Vec<_>>(), ["a", "c"]);
+        let again = CFGBuilder::new(file_id, source)
+            .with_max_nodes_per_function(4)
+            .build_all(&parsed, &mut StringArena::new())
+            .unwrap();
+        assert_eq!(
+            cfgs.iter().map(CFG::compute_hash).collect::<Vec<_>>(),
+            again.iter().map(CFG::compute_hash).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_ast_node_ids_resolve_to_source() {
+        let source = b"fn test(c: bool) { let x = 1; if c { x = 2; } while c { g(); } }";
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+        let cfgs = CFGBuilder::new(file_id, source).build_all(&parsed, &mut StringArena::new()).unwrap();
+        let index = parsed.preorder_index();
+
+        for node in &cfgs[0].nodes {
+            match node.kind {
+                CFGNodeKind::Exit | CFGNodeKind::Merge => assert_eq!(node.ast_node_id, None),
+                _ => {
+                    let ast_id = node.ast_node_id.expect("non-synthetic node has an AST id");
+                    let ast = parsed.node_by_preorder_index(ast_id).unwrap();
+                    assert_eq!(ByteRange::new(ast.start_byte(), ast.end_byte()), node.source_range);
+                    assert_eq!(index.node(ast_id), Some(ast));
+                    assert_eq!(index.id_of(&ast), Some(ast_id));
+                }
+            }
+        }
+
+        assert_eq!(parsed.node_by_preorder_index(AstNodeId(0)).unwrap().kind(), "source_file");
+        assert!(parsed.node_by_preorder_index(AstNodeId(index.len() as u32)).is_none());
+    }
 }
@@ -0,0 +1,260 @@
+//! Dominator tree (Step 2.2)
+//!
+//! Cooper-Harvey-Kennedy iterative algorithm ("A Simple, Fast Dominance
+//! Algorithm") over the Vec-based CFG.
+//!
+//! ## Determinism Guarantees
+//!
+//! - Reverse postorder from a DFS over successors in edge order
+//! - Dominance frontiers sorted by NodeId
+//! - Unreachable nodes have no dominator and dominate nothing
+
+use crate::semantic::model::{NodeId, CFG};
+use std::collections::{BTreeSet, HashMap};
+
+/// Dominator tree for one CFG
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    /// Reachable nodes in reverse postorder (entry first)
+    rpo: Vec<NodeId>,
+
+    /// NodeId → position in `rpo`
+    rpo_index: HashMap<NodeId, usize>,
+
+    /// Immediate dominator by RPO position (entry points to itself)
+    idom: Vec<usize>,
+
+    /// Dominance frontier by RPO position
+    frontiers: Vec<Vec<NodeId>>,
+}
+
+impl DominatorTree {
+    /// Compute dominators and dominance frontiers of a CFG
+    pub fn compute(cfg: &CFG) -> Self {
+        let mut successors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut predecessors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for edge in &cfg.edges {
+            successors.entry(edge.from).or_default().push(edge.to);
+            predecessors.entry(edge.to).or_default().push(edge.from);
+        }
+
+        let rpo = reverse_postorder(cfg.entry, &successors);
+        let rpo_index: HashMap<NodeId, usize> = rpo.iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect();
+
+        // Reachable predecessors by RPO position
+        let preds: Vec<Vec<usize>> = rpo.iter()
+            .map(|id| {
+                predecessors.get(id)
+                    .map(|ps| ps.iter().filter_map(|p| rpo_index.get(p).copied()).collect())
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        const UNDEFINED: usize = usize::MAX;
+        let mut idom = vec![UNDEFINED; rpo.len()];
+        if !rpo.is_empty() {
+            idom[0] = 0;
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for b in 1..rpo.len() {
+                let mut processed = preds[b].iter().copied().filter(|&p| idom[p] != UNDEFINED);
+                let Some(first) = processed.next() else { continue };
+                let new_idom = processed.fold(first, |acc, p| intersect(&idom, p, acc));
+                if idom[b] != new_idom {
+                    idom[b] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut frontier_sets = vec![BTreeSet::new(); rpo.len()];
+        for (b, ps) in preds.iter().enumerate() {
+            if ps.len() < 2 {
+                continue;
+            }
+            for &p in ps {
+                let mut runner = p;
+                while runner != idom[b] {
+                    frontier_sets[runner].insert(rpo[b]);
+                    runner = idom[runner];
+                }
+            }
+        }
+
+        Self {
+            rpo,
+            rpo_index,
+            idom,
+            frontiers: frontier_sets.into_iter().map(|s| s.into_iter().collect()).collect(),
+        }
+    }
+
+    /// Immediate dominator (None for the entry and unreachable nodes)
+    pub fn idom(&self, node: NodeId) -> Option<NodeId> {
+        let i = *self.rpo_index.get(&node)?;
+        (i != 0).then(|| self.rpo[self.idom[i]])
+    }
+
+    /// Whether `a` dominates `b` (every node dominates itself)
+    pub fn dominates(&self, a: NodeId, b: NodeId) -> bool {
+        let (Some(&a), Some(&b)) = (self.rpo_index.get(&a), self.rpo_index.get(&b)) else {
+            return false;
+        };
+
+        // Dominators have smaller RPO positions; walk up from b
+        let mut current = b;
+        while current > a {
+            current = self.idom[current];
+        }
+        current == a
+    }
+
+    /// Dominance frontier of a node, sorted by NodeId
+    pub fn frontier(&self, node: NodeId) -> &[NodeId] {
+        self.rpo_index.get(&node)
+            .map(|&i| self.frontiers[i].as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Reachable nodes in reverse postorder
+    pub fn reverse_postorder(&self) -> &[NodeId] {
+        &self.rpo
+    }
+
+    /// Whether a node is reachable from the entry
+    pub fn is_reachable(&self, node: NodeId) -> bool {
+        self.rpo_index.contains_key(&node)
+    }
+}
+
+/// Walk both fingers up the tree until they meet
+fn intersect(idom: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while a > b {
+            a = idom[a];
+        }
+        while b > a {
+            b = idom[b];
+        }
+    }
+    a
+}
+
+/// Reverse postorder of nodes reachable from `entry` (iterative DFS)
+fn reverse_postorder(entry: NodeId, successors: &HashMap<NodeId, Vec<NodeId>>) -> Vec<NodeId> {
+    let mut postorder = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut stack = vec![(entry, 0usize)];
+    visited.insert(entry);
+
+    while let Some((node, next_child)) = stack.last_mut() {
+        // Visit successors last-to-first so the first successor comes
+        // first in reverse postorder (source order for branches)
+        let children = successors.get(node).map(Vec::as_slice).unwrap_or(&[]);
+        if let Some(&child) = children.iter().rev().nth(*next_child) {
+            *next_child += 1;
+            if visited.insert(child) {
+                stack.push((child, 0));
+            }
+        } else {
+            postorder.push(*node);
+            stack.pop();
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::model::{CFGEdge, CFGEdgeKind, CFGNode, CFGNodeKind, FunctionId};
+    use crate::types::{ByteRange, FileId};
+
+    fn cfg(node_count: u64, edges: &[(u64, u64)]) -> CFG {
+        let mut cfg = CFG::new(FunctionId(0), FileId::new(1), NodeId(0), NodeId(node_count - 1));
+        for id in 0..node_count {
+            cfg.add_node(CFGNode {
+                id: NodeId(id),
+                kind: CFGNodeKind::Statement,
+                source_range: ByteRange::new(0, 0),
+                statement: None,
+                ast_node_id: None,
+                block_value: false,
+                statement_chars: 0,
+            });
+        }
+        for &(from, to) in edges {
+            cfg.add_edge(CFGEdge { from: NodeId(from), to: NodeId(to), kind: CFGEdgeKind::Normal });
+        }
+        cfg
+    }
+
+    #[test]
+    fn test_diamond() {
+        // 0 → 1 → {2, 3} → 4 → 5
+        let dom = DominatorTree::compute(&cfg(6, &[(0, 1), (1, 2), (1, 3), (2, 4), (3, 4), (4, 5)]));
+
+        assert_eq!(dom.idom(NodeId(0)), None);
+        assert_eq!(dom.idom(NodeId(2)), Some(NodeId(1)));
+        assert_eq!(dom.idom(NodeId(3)), Some(NodeId(1)));
+        assert_eq!(dom.idom(NodeId(4)), Some(NodeId(1)));
+        assert_eq!(dom.idom(NodeId(5)), Some(NodeId(4)));
+
+        assert!(dom.dominates(NodeId(1), NodeId(4)));
+        assert!(dom.dominates(NodeId(4), NodeId(4)));
+        assert!(!dom.dominates(NodeId(2), NodeId(4)));
+        assert!(!dom.dominates(NodeId(4), NodeId(1)));
+
+        assert_eq!(dom.frontier(NodeId(2)), &[NodeId(4)]);
+        assert_eq!(dom.frontier(NodeId(3)), &[NodeId(4)]);
+        assert!(dom.frontier(NodeId(1)).is_empty());
+        assert!(dom.frontier(NodeId(4)).is_empty());
+    }
+
+    #[test]
+    fn test_loop() {
+        // 0 → 1 (header) → 2 → 3 → 1, 1 → 4 → 5
+        let dom = DominatorTree::compute(&cfg(6, &[(0, 1), (1, 2), (2, 3), (3, 1), (1, 4), (4, 5)]));
+
+        assert_eq!(dom.idom(NodeId(2)), Some(NodeId(1)));
+        assert_eq!(dom.idom(NodeId(3)), Some(NodeId(2)));
+        assert_eq!(dom.idom(NodeId(4)), Some(NodeId(1)));
+        assert!(dom.dominates(NodeId(1), NodeId(3)));
+        assert!(!dom.dominates(NodeId(3), NodeId(1)));
+
+        // The back edge puts the header in the loop body's frontier
+        assert_eq!(dom.frontier(NodeId(2)), &[NodeId(1)]);
+        assert_eq!(dom.frontier(NodeId(3)), &[NodeId(1)]);
+        assert_eq!(dom.frontier(NodeId(1)), &[NodeId(1)]);
+        assert!(dom.frontier(NodeId(4)).is_empty());
+    }
+
+    #[test]
+    fn test_unreachable_nodes() {
+        // 2 has no incoming edge from the entry
+        let dom = DominatorTree::compute(&cfg(4, &[(0, 1), (2, 3), (1, 3)]));
+
+        assert!(!dom.is_reachable(NodeId(2)));
+        assert_eq!(dom.idom(NodeId(2)), None);
+        assert!(!dom.dominates(NodeId(2), NodeId(3)));
+        assert_eq!(dom.idom(NodeId(3)), Some(NodeId(1)));
+        assert_eq!(dom.reverse_postorder(), &[NodeId(0), NodeId(1), NodeId(3)]);
+    }
+
+    #[test]
+    fn test_rpo_follows_edge_order() {
+        let a = DominatorTree::compute(&cfg(4, &[(0, 1), (0, 2), (1, 3), (2, 3)]));
+        let b = DominatorTree::compute(&cfg(4, &[(0, 1), (0, 2), (1, 3), (2, 3)]));
+
+        assert_eq!(a.reverse_postorder(), b.reverse_postorder());
+        assert_eq!(a.reverse_postorder(), &[NodeId(0), NodeId(1), NodeId(2), NodeId(3)]);
+    }
+}
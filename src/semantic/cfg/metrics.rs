@@ -0,0 +1,287 @@
+//! Per-function CFG metrics (Step 2.2)
+//!
+//! Cheap code-health numbers computed from the CFGs we already build.
+//!
+//! - Cyclomatic complexity: `E - N + 2` over the part of the CFG reachable
+//!   from the entry (dead code after `return` or `loop {}` is not counted)
+//! - Loop nesting: natural loops from back edges (edges into a dominator);
+//!   a node's depth is the number of loops containing it
+//!
+//! ## Determinism Guarantees
+//!
+//! - Computed from the node/edge Vecs and the dominator tree only
+//! - Report rows sorted by complexity (descending), then FileId, FunctionId
+//! - Per-file summaries keyed by path
+
+use super::dominators::DominatorTree;
+use crate::semantic::model::{FunctionId, NodeId, CFG};
+use crate::semantic::SemanticEpoch;
+use crate::types::{ByteRange, FileId, RepoSnapshot};
+use anyhow::Result;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Metrics for one CFG
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CFGMetrics {
+    /// McCabe complexity of the reachable subgraph (1 for straight-line code)
+    pub cyclomatic_complexity: usize,
+
+    /// All nodes, including unreachable ones
+    pub node_count: usize,
+
+    /// All edges, including unreachable ones
+    pub edge_count: usize,
+
+    /// Deepest loop nesting (0 = no loops)
+    pub max_loop_nesting: usize,
+}
+
+/// Compute metrics for one CFG
+pub fn cfg_metrics(cfg: &CFG) -> CFGMetrics {
+    let dom = DominatorTree::compute(cfg);
+
+    let reachable_nodes = dom.reverse_postorder().len();
+    let reachable_edges = cfg.edges.iter()
+        .filter(|e| dom.is_reachable(e.from))
+        .count();
+
+    CFGMetrics {
+        cyclomatic_complexity: (reachable_edges + 2).saturating_sub(reachable_nodes),
+        node_count: cfg.nodes.len(),
+        edge_count: cfg.edges.len(),
+        max_loop_nesting: max_loop_nesting(cfg, &dom),
+    }
+}
+
+/// Deepest natural-loop nesting in a CFG
+fn max_loop_nesting(cfg: &CFG, dom: &DominatorTree) -> usize {
+    let mut predecessors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+    for edge in &cfg.edges {
+        predecessors.entry(edge.to).or_default().push(edge.from);
+    }
+
+    // Loop header → body (loops sharing a header are merged)
+    let mut loops: BTreeMap<NodeId, BTreeSet<NodeId>> = BTreeMap::new();
+    for edge in &cfg.edges {
+        if !dom.is_reachable(edge.from) || !dom.dominates(edge.to, edge.from) {
+            continue;
+        }
+
+        let body = loops.entry(edge.to).or_insert_with(|| BTreeSet::from([edge.to]));
+        let mut stack = vec![edge.from];
+        while let Some(node) = stack.pop() {
+            if body.insert(node) {
+                stack.extend(predecessors.get(&node).into_iter().flatten().copied());
+            }
+        }
+    }
+
+    let mut depth: HashMap<NodeId, usize> = HashMap::new();
+    for node in loops.values().flatten() {
+        *depth.entry(*node).or_default() += 1;
+    }
+    depth.into_values().max().unwrap_or(0)
+}
+
+/// Metrics for one function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionMetrics {
+    pub file_id: FileId,
+    pub path: PathBuf,
+    pub function_id: FunctionId,
+
+    /// Function name (`<unknown>` if no symbol matches)
+    pub name: String,
+    pub source_range: ByteRange,
+    pub metrics: CFGMetrics,
+}
+
+/// Aggregate over a set of functions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MetricsSummary {
+    pub function_count: usize,
+    pub total_complexity: usize,
+    pub max_complexity: usize,
+    pub max_loop_nesting: usize,
+}
+
+impl MetricsSummary {
+    /// Fold one function's metrics in
+    pub fn add(&mut self, metrics: &CFGMetrics) {
+        self.function_count += 1;
+        self.total_complexity += metrics.cyclomatic_complexity;
+        self.max_complexity = self.max_complexity.max(metrics.cyclomatic_complexity);
+        self.max_loop_nesting = self.max_loop_nesting.max(metrics.max_loop_nesting);
+    }
+}
+
+/// Metrics for every function in a repository
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsReport {
+    /// Sorted by complexity (descending), then FileId, FunctionId
+    pub functions: Vec<FunctionMetrics>,
+
+    /// Per-file aggregates
+    pub files: BTreeMap<PathBuf, MetricsSummary>,
+
+    /// Whole-repository aggregate
+    pub repo: MetricsSummary,
+}
+
+impl MetricsReport {
+    /// Compute metrics for every CFG in a semantic epoch
+    pub fn from_epoch(semantic: &SemanticEpoch, snapshot: &RepoSnapshot) -> Self {
+        let mut report = Self::default();
+
+        let mut file_ids = semantic.get_all_file_ids();
+        file_ids.sort();
+
+        for file_id in file_ids {
+            let path = snapshot.files.get(&file_id)
+                .map(|meta| meta.path.clone())
+                .unwrap_or_default();
+            let symbols = semantic.get_symbols(file_id);
+
+            for cfg in semantic.get_cfgs(file_id).into_iter().flatten() {
+                let source_range = cfg.nodes.iter()
+                    .find(|n| n.id == cfg.entry)
+                    .map(|n| n.source_range)
+                    .unwrap_or(ByteRange::new(0, 0));
+                let name = symbols
+                    .and_then(|table| table.function_at(source_range))
+                    .map(|symbol| symbol.name.clone())
+                    .unwrap_or_else(|| "<unknown>".to_string());
+
+                let metrics = cfg_metrics(cfg);
+                report.files.entry(path.clone()).or_default().add(&metrics);
+                report.repo.add(&metrics);
+                report.functions.push(FunctionMetrics {
+                    file_id,
+                    path: path.clone(),
+                    function_id: cfg.function_id,
+                    name,
+                    source_range,
+                    metrics,
+                });
+            }
+        }
+
+        report.functions.sort_by_key(|f| {
+            (Reverse(f.metrics.cyclomatic_complexity), f.file_id, f.function_id)
+        });
+        report
+    }
+}
+
+/// Build a repository and report its CFG metrics
+pub fn report_repo(root: &Path) -> Result<MetricsReport> {
+    Ok(crate::pipeline::Pipeline::default().run(root)?.metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::IncrementalParser;
+    use crate::memory::StringArena;
+    use crate::semantic::cfg::CFGBuilder;
+    use crate::semantic::model::{CFGEdge, CFGEdgeKind, CFGNode, CFGNodeKind};
+    use crate::types::Language;
+    use tempfile::{NamedTempFile, TempDir};
+
+    fn metrics(source: &str) -> CFGMetrics {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), source).unwrap();
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), FileId::new(1)).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+
+        let cfgs = CFGBuilder::new(FileId::new(1), source.as_bytes()).build_all(&parsed, &mut StringArena::new()).unwrap();
+        cfg_metrics(&cfgs[0])
+    }
+
+    #[test]
+    fn test_straight_line() {
+        let m = metrics("fn f() { let a = 1; let b = 2; g(a, b); }");
+        assert_eq!(m, CFGMetrics { cyclomatic_complexity: 1, node_count: 5, edge_count: 4, max_loop_nesting: 0 });
+    }
+
+    #[test]
+    fn test_one_if() {
+        let m = metrics("fn f(x: bool) { if x { g(); } }");
+        assert_eq!(m.cyclomatic_complexity, 2);
+        assert_eq!(m.max_loop_nesting, 0);
+    }
+
+    #[test]
+    fn test_if_else_chain() {
+        // if / else if / else: two decisions
+        let m = metrics("fn f(x: i32) { if x > 0 { a(); } else if x < 0 { b(); } else { c(); } }");
+        assert_eq!(m.cyclomatic_complexity, 3);
+    }
+
+    #[test]
+    fn test_match_arms() {
+        let m = metrics("fn f(x: i32) { match x { 1 => a(), 2 => b(), _ => c() } }");
+        assert_eq!(m.cyclomatic_complexity, 3);
+    }
+
+    #[test]
+    fn test_while_loop() {
+        let m = metrics("fn f(x: bool) { while x { g(); } }");
+        assert_eq!(m.cyclomatic_complexity, 2);
+        assert_eq!(m.max_loop_nesting, 1);
+    }
+
+    #[test]
+    fn test_nested_loops_with_if() {
+        let m = metrics("fn f(a: bool, b: bool) { while a { while b { if a { g(); } } } }");
+        assert_eq!(m.cyclomatic_complexity, 4);
+        assert_eq!(m.max_loop_nesting, 2);
+    }
+
+    #[test]
+    fn test_unreachable_nodes_not_counted() {
+        // 0 → 1 → 2 (exit); 3 is disconnected
+        let mut cfg = CFG::new(FunctionId(0), FileId::new(1), NodeId(0), NodeId(2));
+        for id in 0..4 {
+            cfg.add_node(CFGNode {
+                id: NodeId(id),
+                kind: CFGNodeKind::Statement,
+                source_range: ByteRange::new(0, 0),
+                statement: None,
+                ast_node_id: None,
+                block_value: false,
+                statement_chars: 0,
+            });
+        }
+        for (from, to) in [(0, 1), (1, 2), (3, 2)] {
+            cfg.add_edge(CFGEdge { from: NodeId(from), to: NodeId(to), kind: CFGEdgeKind::Normal });
+        }
+
+        let m = cfg_metrics(&cfg);
+        assert_eq!(m.cyclomatic_complexity, 1);
+        assert_eq!((m.node_count, m.edge_count), (4, 3));
+    }
+
+    #[test]
+    fn test_report_sorted_and_aggregated() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn flat() {}\nfn branchy(x: bool) { if x { g(); } }\n").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "fn looping(x: bool) { while x { if x { g(); } } }\n").unwrap();
+
+        let report = report_repo(dir.path()).unwrap();
+
+        let rows: Vec<_> = report.functions.iter()
+            .map(|f| (f.name.as_str(), f.metrics.cyclomatic_complexity))
+            .collect();
+        assert_eq!(rows, vec![("looping", 3), ("branchy", 2), ("flat", 1)]);
+
+        let a = report.files[Path::new("a.rs")];
+        assert_eq!((a.function_count, a.total_complexity, a.max_complexity), (2, 3, 2));
+        assert_eq!(report.repo.total_complexity, 6);
+        assert_eq!(report.repo.max_loop_nesting, 1);
+
+        assert_eq!(report, report_repo(dir.path()).unwrap());
+    }
+}
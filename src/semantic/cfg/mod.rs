@@ -1,5 +1,9 @@
 //! CFG construction (Step 2.2)
 
 pub mod builder;
+pub mod dominators;
+pub mod metrics;
 
 pub use builder::CFGBuilder;
+pub use dominators::DominatorTree;
+pub use metrics::{cfg_metrics, CFGMetrics, MetricsReport};
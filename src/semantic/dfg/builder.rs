@@ -5,37 +5,104 @@
 //!
 //! ## Algorithm
 //!
-//! 1. Walk CFG in topological order
-//! 2. For each node, identify:
-//!    - Definitions (assignments, parameters)
-//!    - Uses (variable reads)
-//! 3. Track last definition per variable per block
-//! 4. Resolve uses to nearest dominating definition
-//! 5. Insert phi-like merges at control flow joins
+//! 1. Compute the dominator tree of the CFG
+//! 2. Collect definitions (assignments, parameters) per CFG node
+//! 3. Place phi-like values at the iterated dominance frontier of each
+//!    variable's definitions (only where definitions actually meet)
+//! 4. Emit values walking reachable nodes in reverse postorder
+//! 5. Connect each phi to the definition reaching it along every
+//!    predecessor (nearest definition up the dominator tree)
 //!
 //! ## Not SSA
 //!
-//! We approximate SSA without full dominance frontiers:
-//! - Track definitions per block
-//! - Insert merges at obvious join points (if/else, loops)
-//! - Don't compute precise dominance
+//! We approximate SSA:
+//! - Definitions are `let` bindings and plain assignments to identifiers,
+//!   read from the Tree-sitter node each CFG node was built from (by
+//!   `ast_node_id`, or by source range for CFGs serialized without one)
+//! - A block's trailing expression (CFG `block_value`) is a Temporary; a
+//!   `let` initialized by `if`/`match` is defined by its arms' Temporaries
+//! - Each name a parameter binds is a Parameter value defined at the entry
+//!   node, with the parameter's position from the symbol table
+//! - Each name an `if let` / `while let` condition binds (per the symbol
+//!   table) is a Variable defined at the Branch or LoopHeader node, from
+//!   the condition's value
+//!
+//! ## Value roles
+//!
+//! Variables are definition sites only (plus phi-like merges). The
+//! right-hand side of a definition becomes the values that define it:
+//!
+//! - a literal is the Constant holding its text
+//! - an identifier is the definition of that name reaching the statement
+//!   (nearest up the dominator tree, phis included); no value if none does
+//! - an operator, call, field access, index, cast, tuple or array is a
+//!   Temporary, with a `Use` edge from each operand's value
+//!
+//! The result gets a `Definition` edge into the defined variable. So
+//! `let y = x + 1;` gives a Constant `1`, a Temporary for `x + 1` used by
+//! x's definition and the Constant, and a Definition edge into y. Other
+//! expressions (blocks, closures, macros, ...) give no values.
+//!
+//! ## Constant table
+//!
+//! Each literal text gets one Constant per function, however often it
+//! occurs: every occurrence's range is appended to its `occurrences` and
+//! every use reads the same value. Until the walk ends a constant is known
+//! by a placeholder ValueId; the table is then numbered after every other
+//! value, in first-occurrence order, so numbering never depends on which
+//! statement saw a literal first. A Constant's origin and `source_range`
+//! are those of its first occurrence.
+//!
+//! ## Partial rebuilds
+//!
+//! Every value records the CFG node it was built for (`DFGValue::origin`).
+//! `rebuild_partial` takes the previous DFG of the same function and the
+//! nodes whose statements changed, walks only those, and copies every
+//! other node's values (shifted to its new source range) and edges
+//! (renumbered). Phis and the constant table are always recomputed (a
+//! copied node re-reads its literals). Renumbering keeps creation order, so
+//! the result equals `build` on the same CFG.
+//!
+//! Copying is only sound while the rest of the function is unchanged. If
+//! any check fails (a changed node now defines a different variable, an
+//! unchanged node's values do not fit its statement, the previous DFG
+//! predates `origin`, ...) the whole DFG is rebuilt instead.
+//!
+//! Variable names are interned into the caller's StringArena.
 
+use crate::metrics::MetricsCollector;
+use crate::semantic::cfg::DominatorTree;
 use crate::semantic::model::*;
-use crate::semantic::symbols::SymbolTable;
-use crate::types::ByteRange;
+use crate::semantic::symbols::{Symbol, SymbolKind, SymbolTable};
+use crate::types::{ByteRange, PreorderIndex};
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Literal expressions (Constant values)
+const LITERAL_KINDS: &[&str] = &[
+    "integer_literal", "float_literal", "string_literal", "raw_string_literal", "char_literal", "boolean_literal",
+];
+
+/// Expressions whose result is a Temporary computed from their operands
+const COMPUTED_KINDS: &[&str] = &[
+    "binary_expression", "unary_expression", "call_expression", "field_expression", "index_expression",
+    "reference_expression", "type_cast_expression", "tuple_expression", "array_expression",
+    "range_expression", "try_expression", "await_expression",
+];
 
 /// DFG builder constructs data flow graph from CFG and symbol table
 pub struct DFGBuilder<'a> {
     /// CFG to analyze
     cfg: &'a CFG,
     
-    /// Symbol table for lookup
-    _symbols: &'a SymbolTable,
+    /// Symbol table of the file (parameter names and positions)
+    symbols: &'a SymbolTable,
+    
+    /// AST of the file the CFG was built from
+    ast: &'a PreorderIndex<'a>,
     
     /// Source code
-    _source: &'a [u8],
+    source: &'a [u8],
     
     /// DFG being built
     dfg: DFG,
@@ -44,186 +111,678 @@ pub struct DFGBuilder<'a> {
     /// (NodeId, variable name) → ValueId
     definitions: HashMap<(NodeId, String), ValueId>,
     
+    /// Temporary holding each block value
+    block_values: HashMap<NodeId, ValueId>,
+
+    /// Parameter values defined at the entry node, in order
+    parameters: Vec<ValueId>,
+
+    /// Constant table, in first-occurrence order
+    constants: Vec<ConstantEntry>,
+
+    /// Index into `constants` of each literal text
+    constant_index: HashMap<StringId, usize>,
+
     /// Value ID counter
     next_value_id: u64,
+    
+    /// Arena names are interned into (borrowed from the caller during `build`)
+    strings: StringArena,
 }
 
 impl<'a> DFGBuilder<'a> {
     /// Create a new DFG builder
-    pub fn new(cfg: &'a CFG, symbols: &'a SymbolTable, source: &'a [u8]) -> Self {
+    pub fn new(cfg: &'a CFG, symbols: &'a SymbolTable, ast: &'a PreorderIndex<'a>, source: &'a [u8]) -> Self {
         Self {
             cfg,
-            _symbols: symbols,
-            _source: source,
+            symbols,
+            ast,
+            source,
             dfg: DFG::new(cfg.function_id),
             definitions: HashMap::new(),
+            block_values: HashMap::new(),
+            parameters: Vec::new(),
+            constants: Vec::new(),
+            constant_index: HashMap::new(),
             next_value_id: 0,
+            strings: StringArena::new(),
         }
     }
 
-    /// Build the DFG
-    pub fn build(mut self) -> Result<DFG> {
-        // Start from entry node
-        self.walk_cfg(self.cfg.entry)?;
-        
-        Ok(self.dfg)
+    /// Build the DFG, interning variable names into `strings`
+    pub fn build(mut self, strings: &mut StringArena) -> Result<DFG> {
+        self.strings = std::mem::take(strings);
+        let result = self.build_values(None);
+        *strings = std::mem::take(&mut self.strings);
+        result.map(|_| self.dfg)
+    }
+
+    /// Rebuild from the previous DFG of this function, walking only
+    /// `invalidated_nodes` (see "Partial rebuilds")
+    ///
+    /// `prev` must have been interned into `strings`. The result equals
+    /// `build`.
+    pub fn rebuild_partial(self, prev: &DFG, invalidated_nodes: &[NodeId], strings: &mut StringArena) -> Result<DFG> {
+        self.rebuild_partial_with_metrics(prev, invalidated_nodes, strings, &MetricsCollector::new())
+    }
+
+    /// `rebuild_partial`, recording values reused and rebuilt in `metrics`
+    pub fn rebuild_partial_with_metrics(
+        mut self,
+        prev: &DFG,
+        invalidated_nodes: &[NodeId],
+        strings: &mut StringArena,
+        metrics: &MetricsCollector,
+    ) -> Result<DFG> {
+        let (cfg, symbols, ast, source) = (self.cfg, self.symbols, self.ast, self.source);
+        self.strings = std::mem::take(strings);
+        let mut previous = PreviousDFG::new(prev, invalidated_nodes);
+        let result = match prev.function_id == cfg.function_id && previous.has_origins {
+            true => self.build_values(Some(&mut previous)),
+            false => Ok(false),
+        };
+        *strings = std::mem::take(&mut self.strings);
+
+        let dfg = match result? {
+            true => self.dfg,
+            false => {
+                previous.reused = 0;
+                DFGBuilder::new(cfg, symbols, ast, source).build(strings)?
+            }
+        };
+        metrics.record_dfg_rebuild(previous.reused, dfg.values.len() - previous.reused);
+        Ok(dfg)
+    }
+
+    /// Emit every value and edge, copying unchanged nodes from `previous`
+    ///
+    /// False if `previous` cannot be reused (the DFG is then incomplete).
+    fn build_values(&mut self, mut previous: Option<&mut PreviousDFG>) -> Result<bool> {
+        let _span = tracing::debug_span!(
+            "dfg",
+            file_id = self.cfg.file_id.as_u64(),
+            function_id = self.cfg.function_id.0,
+            partial = previous.is_some(),
+        ).entered();
+
+        let dom = DominatorTree::compute(self.cfg);
+        let defined = self.defined_variables(&dom);
+        let phis = Self::place_phis(&dom, &defined);
+
+        // Phis first, then the node's own definitions
+        let mut phi_values = Vec::new();
+        for &node_id in dom.reverse_postorder() {
+            if let Some(vars) = phis.get(&node_id) {
+                for var_name in vars {
+                    let phi_id = self.add_variable(var_name, ByteRange::new(0, 0), node_id); // Synthetic
+                    self.definitions.insert((node_id, var_name.clone()), phi_id);
+                    phi_values.push((node_id, var_name.clone(), phi_id));
+                    if let Some(previous) = previous.as_deref_mut() {
+                        let name = self.strings.intern(var_name);
+                        if let Some(&old_id) = previous.phis.get(&(node_id, name)) {
+                            previous.remap.insert(old_id, phi_id);
+                        }
+                    }
+                }
+            }
+
+            let defined = defined.get(&node_id).map_or(&[][..], Vec::as_slice);
+            match previous.as_deref_mut() {
+                None => self.walk_node(&dom, node_id)?,
+                Some(previous) => {
+                    // The entry node is cheap and holds no statement to copy
+                    let walk = previous.invalidated.contains(&node_id) || node_id == self.cfg.entry;
+                    let fits = match walk {
+                        true => {
+                            self.walk_node(&dom, node_id)?;
+                            self.map_rebuilt_node(node_id, defined, previous)
+                        }
+                        false => self.copy_node(node_id, defined, previous),
+                    };
+                    if !fits {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+
+        for (merge_node, var_name, phi_id) in phi_values {
+            self.connect_phi(&dom, merge_node, &var_name, phi_id);
+        }
+
+        self.number_constants();
+        Ok(true)
+    }
+
+    /// Copy an unchanged node's values and edges from the previous DFG
+    ///
+    /// False if they do not fit the node's statement.
+    fn copy_node(&mut self, node_id: NodeId, defined: &[String], previous: &mut PreviousDFG) -> bool {
+        let Some(node) = self.cfg.get_node(node_id) else { return false };
+        let old_values = previous.values.get(&node_id).cloned().unwrap_or_default();
+
+        // The block value comes first and the definitions last; all span
+        // the whole statement
+        let block_value = node.kind == CFGNodeKind::Statement && node.block_value;
+        let fits = |value: Option<&&DFGValue>, variable: Option<&String>| value.is_some_and(|value| {
+            value.source_range.len() == node.source_range.len()
+                && match (&value.kind, variable) {
+                    (ValueKind::Temporary, None) => true,
+                    (ValueKind::Variable { name }, Some(var_name)) => self.strings.resolve(*name) == var_name,
+                    _ => false,
+                }
+        });
+        if block_value && !fits(old_values.first(), None) {
+            return false;
+        }
+        let Some(first_definition) = old_values.len().checked_sub(defined.len()) else { return false };
+        let old_definitions = &old_values[first_definition..];
+        if !old_definitions.iter().zip(defined).all(|(value, var_name)| fits(Some(value), Some(var_name))) {
+            return false;
+        }
+        if !block_value && defined.is_empty() && !old_values.is_empty() {
+            return false;
+        }
+
+        // Shift by how far the statement moved
+        let old_start = old_values.last().map_or(node.source_range.start, |v| v.source_range.start);
+        let shift = |offset: usize| offset + node.source_range.start - old_start;
+        for old in &old_values {
+            let range = ByteRange::new(shift(old.source_range.start), shift(old.source_range.end));
+            let value_id = self.add_value(old.kind.clone(), range, node_id);
+            previous.remap.insert(old.id, value_id);
+        }
+        if let (Some(first), true) = (old_values.first(), block_value) {
+            self.block_values.insert(node_id, previous.remap[&first.id]);
+        }
+        for (old, var_name) in old_definitions.iter().zip(defined) {
+            self.definitions.insert((node_id, var_name.clone()), previous.remap[&old.id]);
+        }
+        previous.reused += old_values.len();
+
+        // Literals are re-read, so the constant table sees them in walk order
+        if !defined.is_empty() {
+            for expr in self.assigned_values(node) {
+                self.add_literals(expr, node_id);
+            }
+        }
+
+        for old in previous.edges.get(&node_id).into_iter().flatten() {
+            let from = match previous.constants.get(&old.from) {
+                Some(text) => self.constant_index.get(text).map(|&index| Self::constant_placeholder(index)),
+                None => previous.remap.get(&old.from).copied(),
+            };
+            let (Some(from), Some(&to)) = (from, previous.remap.get(&old.to)) else {
+                return false;
+            };
+            self.dfg.add_edge(DFGEdge { from, to, kind: old.kind });
+        }
+        true
+    }
+
+    /// Map a rebuilt node's block value and definition from the previous DFG
+    ///
+    /// False if the node now defines different variables (or none), which
+    /// changes what reaches every later node.
+    fn map_rebuilt_node(&mut self, node_id: NodeId, defined: &[String], previous: &mut PreviousDFG) -> bool {
+        let old_values = previous.values.get(&node_id).cloned().unwrap_or_default();
+        if node_id == self.cfg.entry {
+            return self.map_parameters(&old_values, previous);
+        }
+        // Definitions are the trailing Variables (right-hand sides are Temporaries)
+        let mut old_defined: Vec<(ValueId, &str)> = old_values.iter().rev()
+            .map_while(|value| match value.kind {
+                ValueKind::Variable { name } => Some((value.id, self.strings.resolve(name))),
+                _ => None,
+            })
+            .collect();
+        old_defined.reverse();
+        if old_defined.len() != defined.len() || old_defined.iter().zip(defined).any(|((_, old), new)| old != new) {
+            return false;
+        }
+        for ((old_id, _), var_name) in old_defined.into_iter().zip(defined) {
+            previous.remap.insert(old_id, self.definitions[&(node_id, var_name.clone())]);
+        }
+
+        if let Some(&value_id) = self.block_values.get(&node_id) {
+            match old_values.first() {
+                Some(old) if old.kind == ValueKind::Temporary => previous.remap.insert(old.id, value_id),
+                _ => return false,
+            };
+        }
+        true
+    }
+
+    /// Map the previous parameter values to the new ones
+    ///
+    /// False if the signature changed (names or positions).
+    fn map_parameters(&self, old_values: &[&DFGValue], previous: &mut PreviousDFG) -> bool {
+        if old_values.len() != self.parameters.len() {
+            return false;
+        }
+        for (old, &value_id) in old_values.iter().zip(&self.parameters) {
+            if self.dfg.values.iter().find(|value| value.id == value_id).map(|value| &value.kind) != Some(&old.kind) {
+                return false;
+            }
+            previous.remap.insert(old.id, value_id);
+        }
+        true
     }
 
-    /// Walk CFG starting from a node
-    fn walk_cfg(&mut self, node_id: NodeId) -> Result<()> {
+    /// Process one CFG node
+    fn walk_node(&mut self, dom: &DominatorTree, node_id: NodeId) -> Result<()> {
         // Find the node
         let node = self.cfg.get_node(node_id)
             .ok_or_else(|| anyhow::anyhow!("Node not found: {:?}", node_id))?;
 
         match node.kind {
             CFGNodeKind::Entry => {
-                // Entry node: add parameters as initial definitions
-                // (Would need function signature info from symbol table)
+                for symbol in self.parameter_symbols() {
+                    let name = self.strings.intern(&symbol.name);
+                    let position = symbol.position.unwrap_or_default();
+                    let value_id = self.add_value(ValueKind::Parameter { name, position }, symbol.source_range, node_id);
+                    self.definitions.insert((node_id, symbol.name.clone()), value_id);
+                    self.parameters.push(value_id);
+                }
             }
             
             CFGNodeKind::Statement => {
+                if node.block_value {
+                    let value_id = self.add_value(ValueKind::Temporary, node.source_range, node_id);
+                    self.block_values.insert(node_id, value_id);
+                }
+
                 // Process statement to extract definitions and uses
-                if let Some(ref stmt_text) = node.statement {
-                    self.process_statement(node_id, stmt_text, node.source_range)?;
+                if let Some(var_name) = self.defined_variable(node) {
+                    // The right-hand side reads the definitions before this one
+                    let rhs = self.assigned_value(node)
+                        .and_then(|expr| self.expression_value(dom, node_id, expr));
+                    let value_id = self.add_variable(&var_name, node.source_range, node_id);
+                    let sources = match self.initialized_by_arms(node) {
+                        true => self.arm_values(node_id),
+                        false => rhs.into_iter().collect(),
+                    };
+                    for source in sources {
+                        self.dfg.add_edge(DFGEdge {
+                            from: source,
+                            to: value_id,
+                            kind: DFGEdgeKind::Definition,
+                        });
+                    }
+                    self.definitions.insert((node_id, var_name), value_id);
                 }
             }
             
-            CFGNodeKind::Branch | CFGNodeKind::Merge | CFGNodeKind::LoopHeader => {
-                // Control flow nodes - handle phi-like merges
-                if node.kind == CFGNodeKind::Merge {
-                    self.insert_phi_nodes(node_id)?;
+            CFGNodeKind::Branch | CFGNodeKind::LoopHeader => {
+                // Every condition's value first, so the definitions come last
+                let conditions = self.let_conditions(node);
+                let values: Vec<Option<ValueId>> = conditions.iter()
+                    .map(|(condition, names)| {
+                        let value = condition.child_by_field_name("value").filter(|_| !names.is_empty())?;
+                        self.expression_value(dom, node_id, value)
+                    })
+                    .collect();
+                for ((_, names), value) in conditions.iter().zip(values) {
+                    for var_name in names {
+                        let value_id = self.add_variable(var_name, node.source_range, node_id);
+                        if let Some(source) = value {
+                            self.dfg.add_edge(DFGEdge { from: source, to: value_id, kind: DFGEdgeKind::Definition });
+                        }
+                        self.definitions.insert((node_id, var_name.clone()), value_id);
+                    }
                 }
             }
-            
-            CFGNodeKind::Exit => {
-                // Exit node - nothing to do
-            }
-        }
 
-        // Visit successors
-        for edge in &self.cfg.edges {
-            if edge.from == node_id {
-                // Only visit each node once (simplified)
-                // In a real implementation, would track visited nodes
+            CFGNodeKind::Merge | CFGNodeKind::Exit => {
+                // Control flow only - phis are placed by dominance frontier
             }
         }
 
         Ok(())
     }
 
-    /// Process a statement to extract definitions and uses
-    fn process_statement(&mut self, node_id: NodeId, stmt: &str, range: ByteRange) -> Result<()> {
-        // Very simplified parsing - in reality would use Tree-sitter
-        
-        // Detect let declarations: "let x = ..."
-        if stmt.contains("let ") {
-            if let Some(var_name) = self.extract_variable_name(stmt) {
-                let value_id = self.new_value_id();
-                
-                let value = DFGValue {
-                    id: value_id,
-                    kind: ValueKind::Variable { name: var_name.clone() },
-                    source_range: range,
-                };
-                
-                self.dfg.add_value(value);
-                self.definitions.insert((node_id, var_name), value_id);
+    /// Parameter symbols of this function, by position then source order
+    ///
+    /// Those the symbol table bound inside the CFG's signature (none for
+    /// CFGs serialized before schema version 4).
+    fn parameter_symbols(&self) -> Vec<&'a Symbol> {
+        let signature = self.cfg.signature_range;
+        let mut parameters: Vec<&Symbol> = self.symbols.symbols()
+            .filter(|symbol| symbol.kind == SymbolKind::Parameter && symbol.position.is_some())
+            .filter(|symbol| signature.start <= symbol.source_range.start && symbol.source_range.end <= signature.end)
+            .collect();
+        parameters.sort_by_key(|symbol| (symbol.position, symbol.source_range.start));
+        parameters
+    }
+
+    /// Variables defined by each reachable node, in definition order
+    fn defined_variables(&self, dom: &DominatorTree) -> HashMap<NodeId, Vec<String>> {
+        let mut defined = HashMap::new();
+        for &node_id in dom.reverse_postorder() {
+            let Some(node) = self.cfg.get_node(node_id) else { continue };
+            let names: Vec<String> = match node.kind {
+                CFGNodeKind::Statement => self.defined_variable(node).into_iter().collect(),
+                CFGNodeKind::Branch | CFGNodeKind::LoopHeader => {
+                    self.let_conditions(node).into_iter().flat_map(|(_, names)| names).collect()
+                }
+                _ => continue,
+            };
+            if !names.is_empty() {
+                defined.insert(node_id, names);
             }
         }
-        
-        // Detect assignments: "x = ..."
-        if stmt.contains(" = ") && !stmt.contains("let ") {
-            if let Some(var_name) = self.extract_assigned_variable(stmt) {
-                let value_id = self.new_value_id();
-                
-                let value = DFGValue {
-                    id: value_id,
-                    kind: ValueKind::Variable { name: var_name.clone() },
-                    source_range: range,
-                };
-                
-                self.dfg.add_value(value);
-                self.definitions.insert((node_id, var_name), value_id);
+        defined
+    }
+
+    /// Variables needing a phi at each node (iterated dominance frontier)
+    fn place_phis(dom: &DominatorTree, defined: &HashMap<NodeId, Vec<String>>) -> BTreeMap<NodeId, BTreeSet<String>> {
+        // Definition sites per variable
+        let mut def_sites: BTreeMap<String, BTreeSet<NodeId>> = BTreeMap::new();
+        for (&node_id, names) in defined {
+            for var_name in names {
+                def_sites.entry(var_name.clone()).or_default().insert(node_id);
             }
         }
 
-        Ok(())
+        let mut phis: BTreeMap<NodeId, BTreeSet<String>> = BTreeMap::new();
+        for (var_name, sites) in def_sites {
+            let mut placed = BTreeSet::new();
+            let mut worklist: Vec<NodeId> = sites.into_iter().collect();
+            while let Some(node_id) = worklist.pop() {
+                for &frontier in dom.frontier(node_id) {
+                    if placed.insert(frontier) {
+                        phis.entry(frontier).or_default().insert(var_name.clone());
+                        worklist.push(frontier);
+                    }
+                }
+            }
+        }
+
+        phis
     }
 
-    /// Insert phi-like nodes at merge points
-    fn insert_phi_nodes(&mut self, merge_node: NodeId) -> Result<()> {
-        // Find all incoming edges to this merge
-        let incoming: Vec<_> = self.cfg.edges.iter()
-            .filter(|e| e.to == merge_node)
-            .collect();
+    /// Connect a phi to the definition reaching it from each predecessor
+    fn connect_phi(&mut self, dom: &DominatorTree, merge_node: NodeId, var_name: &str, phi_id: ValueId) {
+        let mut connected = BTreeSet::new();
 
-        if incoming.len() < 2 {
-            return Ok(()); // No merge needed
-        }
+        for edge in self.cfg.edges.iter().filter(|e| e.to == merge_node) {
+            if !dom.is_reachable(edge.from) {
+                continue;
+            }
 
-        // For each variable defined in predecessors, create phi-like value
-        let mut merged_vars = std::collections::HashSet::new();
-        
-        for edge in &incoming {
-            for ((pred_node, var_name), _) in &self.definitions {
-                if *pred_node == edge.from {
-                    merged_vars.insert(var_name.clone());
+            // Nearest definition at or above the predecessor
+            let mut current = Some(edge.from);
+            while let Some(node_id) = current {
+                if let Some(&def_id) = self.definitions.get(&(node_id, var_name.to_string())) {
+                    if connected.insert(def_id) {
+                        self.dfg.add_edge(DFGEdge {
+                            from: def_id,
+                            to: phi_id,
+                            kind: DFGEdgeKind::PhiLike,
+                        });
+                    }
+                    break;
                 }
+                current = dom.idom(node_id);
             }
         }
+    }
 
-        // Create phi nodes
-        for var_name in merged_vars {
-            let phi_id = self.new_value_id();
-            let phi_value = DFGValue {
-                id: phi_id,
-                kind: ValueKind::Variable { name: var_name.clone() },
-                source_range: ByteRange::new(0, 0), // Synthetic
-            };
-            
-            self.dfg.add_value(phi_value);
-            
-            // Connect incoming definitions to phi
-            for edge in &incoming {
-                if let Some(&def_id) = self.definitions.get(&(edge.from, var_name.clone())) {
-                    self.dfg.add_edge(DFGEdge {
-                        from: def_id,
-                        to: phi_id,
-                        kind: DFGEdgeKind::PhiLike,
-                    });
+    /// Tree-sitter node of a statement (unwrapping `expression_statement`)
+    fn statement_ast(&self, node: &CFGNode) -> Option<tree_sitter::Node<'a>> {
+        let ast = match node.ast_node_id {
+            Some(ast_id) => self.ast.node(ast_id)?,
+            None => self.ast.node_for_range(node.source_range)?,
+        };
+        if ast.kind() == "expression_statement" { ast.named_child(0) } else { Some(ast) }
+    }
+
+    /// Variable defined by a statement node (let binding or assignment)
+    fn defined_variable(&self, node: &CFGNode) -> Option<String> {
+        let ast = self.statement_ast(node)?;
+
+        let target = match ast.kind() {
+            "let_declaration" => ast.child_by_field_name("pattern")?,
+            "assignment_expression" => ast.child_by_field_name("left")?,
+            _ => return None,
+        };
+        // Field, index and destructuring targets do not define a variable
+        (target.kind() == "identifier").then(|| self.text(target))
+    }
+
+    /// Let conditions of a Branch or LoopHeader node (`if let`, `while
+    /// let`, a chain of them), each with the names its pattern binds
+    ///
+    /// The names are the symbol table's variables declared with the
+    /// condition's range, in declaration order.
+    fn let_conditions(&self, node: &CFGNode) -> Vec<(tree_sitter::Node<'a>, Vec<String>)> {
+        let Some(condition) = self.statement_ast(node).and_then(|ast| ast.child_by_field_name("condition")) else {
+            return Vec::new();
+        };
+        let conditions: Vec<tree_sitter::Node<'a>> = match condition.kind() {
+            "let_condition" => vec![condition],
+            "let_chain" => {
+                let mut cursor = condition.walk();
+                condition.named_children(&mut cursor).filter(|c| c.kind() == "let_condition").collect()
+            }
+            _ => return Vec::new(),
+        };
+        conditions.into_iter()
+            .map(|condition| {
+                let range = ByteRange::of_node(&condition);
+                let mut bound: Vec<&Symbol> = self.symbols.symbols()
+                    .filter(|symbol| symbol.kind == SymbolKind::Variable && symbol.source_range == range)
+                    .collect();
+                bound.sort_by_key(|symbol| symbol.id);
+                (condition, bound.into_iter().map(|symbol| symbol.name.clone()).collect())
+            })
+            .collect()
+    }
+
+    /// Right-hand sides a node's definitions take their values from
+    fn assigned_values(&self, node: &CFGNode) -> Vec<tree_sitter::Node<'a>> {
+        match node.kind {
+            CFGNodeKind::Branch | CFGNodeKind::LoopHeader => self.let_conditions(node).into_iter()
+                .filter(|(_, names)| !names.is_empty())
+                .filter_map(|(condition, _)| condition.child_by_field_name("value"))
+                .collect(),
+            _ => self.assigned_value(node).into_iter().collect(),
+        }
+    }
+
+    /// Right-hand side of a `let` or assignment
+    fn assigned_value(&self, node: &CFGNode) -> Option<tree_sitter::Node<'a>> {
+        let ast = self.statement_ast(node)?;
+        match ast.kind() {
+            "let_declaration" => ast.child_by_field_name("value"),
+            "assignment_expression" => ast.child_by_field_name("right"),
+            _ => None,
+        }
+    }
+
+    /// Value an expression evaluates to (see "Value roles"), emitting any
+    /// Constants and Temporaries it needs
+    fn expression_value(&mut self, dom: &DominatorTree, node_id: NodeId, expr: tree_sitter::Node<'a>) -> Option<ValueId> {
+        let kind = expr.kind();
+        let range = ByteRange::of_node(&expr);
+        if kind == "identifier" {
+            return self.reaching_definition(dom, node_id, &self.text(expr));
+        }
+        if kind == "parenthesized_expression" {
+            return self.expression_value(dom, node_id, expr.named_child(0)?);
+        }
+        if LITERAL_KINDS.contains(&kind) {
+            return Some(self.add_constant(expr, node_id));
+        }
+        if !COMPUTED_KINDS.contains(&kind) {
+            return None;
+        }
+
+        // Each operand's value once, in operand order
+        let mut used = Vec::new();
+        for operand in Self::operands(expr) {
+            if let Some(value_id) = self.expression_value(dom, node_id, operand) {
+                if !used.contains(&value_id) {
+                    used.push(value_id);
                 }
             }
-            
-            // Update definition for merge node
-            self.definitions.insert((merge_node, var_name), phi_id);
         }
+        let temporary = self.add_value(ValueKind::Temporary, range, node_id);
+        for value_id in used {
+            self.dfg.add_edge(DFGEdge {
+                from: value_id,
+                to: temporary,
+                kind: DFGEdgeKind::Use,
+            });
+        }
+        Some(temporary)
+    }
 
-        Ok(())
+    /// Add the literals `expression_value` would make Constants of, in the
+    /// same order, without emitting anything else
+    fn add_literals(&mut self, expr: tree_sitter::Node<'a>, node_id: NodeId) {
+        let kind = expr.kind();
+        if kind == "parenthesized_expression" {
+            if let Some(inner) = expr.named_child(0) {
+                self.add_literals(inner, node_id);
+            }
+        } else if LITERAL_KINDS.contains(&kind) {
+            self.add_constant(expr, node_id);
+        } else if COMPUTED_KINDS.contains(&kind) {
+            for operand in Self::operands(expr) {
+                self.add_literals(operand, node_id);
+            }
+        }
     }
 
-    /// Extract variable name from let declaration (simplified)
-    fn extract_variable_name(&self, stmt: &str) -> Option<String> {
-        // Very basic: "let x = ..." → "x"
-        let parts: Vec<_> = stmt.split_whitespace().collect();
-        if parts.len() >= 2 && parts[0] == "let" {
-            Some(parts[1].trim_end_matches([';', '=', ':']).to_string())
-        } else {
-            None
+    /// Operands of a computed expression (call arguments are operands of
+    /// the call itself)
+    fn operands(expr: tree_sitter::Node<'a>) -> Vec<tree_sitter::Node<'a>> {
+        let mut operands = Vec::new();
+        let mut cursor = expr.walk();
+        for child in expr.named_children(&mut cursor) {
+            if child.kind() == "arguments" {
+                let mut arguments = child.walk();
+                operands.extend(child.named_children(&mut arguments));
+            } else {
+                operands.push(child);
+            }
         }
+        operands
     }
 
-    /// Extract assigned variable name (simplified)
-    fn extract_assigned_variable(&self, stmt: &str) -> Option<String> {
-        // Very basic: "x = ..." → "x"
-        if let Some(eq_pos) = stmt.find(" = ") {
-            let var = stmt[..eq_pos].trim().to_string();
-            if !var.is_empty() {
-                return Some(var);
+    /// Definition of `var_name` reaching the start of a node
+    fn reaching_definition(&self, dom: &DominatorTree, node_id: NodeId, var_name: &str) -> Option<ValueId> {
+        let mut current = Some(node_id);
+        while let Some(node_id) = current {
+            if let Some(&def_id) = self.definitions.get(&(node_id, var_name.to_string())) {
+                return Some(def_id);
             }
+            current = dom.idom(node_id);
         }
         None
     }
 
+    /// Source text of an AST node
+    fn text(&self, ast: tree_sitter::Node<'a>) -> String {
+        let range = ByteRange::of_node(&ast);
+        String::from_utf8_lossy(&self.source[range.start..range.end]).to_string()
+    }
+
+    /// Whether a `let` takes its value from the arms of an `if` or `match`
+    fn initialized_by_arms(&self, node: &CFGNode) -> bool {
+        self.statement_ast(node)
+            .filter(|ast| ast.kind() == "let_declaration")
+            .and_then(|ast| ast.child_by_field_name("value"))
+            .is_some_and(|value| matches!(value.kind(), "if_expression" | "match_expression"))
+    }
+
+    /// Block values flowing into a node through the merge before it
+    ///
+    /// Nested merges (`else if`, an arm that is itself an `if`) are followed.
+    fn arm_values(&self, node_id: NodeId) -> Vec<ValueId> {
+        let mut values = Vec::new();
+        let mut seen = BTreeSet::new();
+        let mut stack: Vec<NodeId> = self.cfg.edges.iter()
+            .filter(|e| e.to == node_id)
+            .map(|e| e.from)
+            .collect();
+
+        while let Some(pred) = stack.pop() {
+            if !seen.insert(pred) {
+                continue;
+            }
+            if let Some(&value_id) = self.block_values.get(&pred) {
+                values.push(value_id);
+            } else if self.cfg.get_node(pred).is_some_and(|n| n.kind == CFGNodeKind::Merge) {
+                stack.extend(self.cfg.edges.iter().filter(|e| e.to == pred).map(|e| e.from));
+            }
+        }
+        values.sort();
+        values
+    }
+
+    /// Add a variable value
+    fn add_variable(&mut self, var_name: &str, range: ByteRange, origin: NodeId) -> ValueId {
+        let name = self.strings.intern(var_name);
+        self.add_value(ValueKind::Variable { name }, range, origin)
+    }
+
+    /// Record an occurrence of a literal in the constant table
+    ///
+    /// Returns the constant's placeholder until `number_constants`.
+    fn add_constant(&mut self, literal: tree_sitter::Node<'a>, origin: NodeId) -> ValueId {
+        let value = self.strings.intern(&self.text(literal));
+        let range = ByteRange::of_node(&literal);
+        let index = *self.constant_index.entry(value).or_insert_with(|| {
+            self.constants.push(ConstantEntry { value, origin, occurrences: Vec::new() });
+            self.constants.len() - 1
+        });
+        self.constants[index].occurrences.push(range);
+        Self::constant_placeholder(index)
+    }
+
+    /// ValueId standing for a constant until it is numbered
+    fn constant_placeholder(index: usize) -> ValueId {
+        ValueId(u64::MAX - index as u64)
+    }
+
+    /// Add the constant table after every other value and point edges at it
+    fn number_constants(&mut self) {
+        let first_placeholder = u64::MAX - self.constants.len() as u64;
+        let mut numbered = Vec::with_capacity(self.constants.len());
+        for constant in std::mem::take(&mut self.constants) {
+            let value_id = self.new_value_id();
+            self.dfg.add_value(DFGValue {
+                id: value_id,
+                kind: ValueKind::Constant { value: constant.value },
+                source_range: constant.occurrences[0],
+                origin: Some(constant.origin),
+                occurrences: constant.occurrences,
+            });
+            numbered.push(value_id);
+        }
+        for edge in &mut self.dfg.edges {
+            if edge.from.0 > first_placeholder {
+                edge.from = numbered[(u64::MAX - edge.from.0) as usize];
+            }
+        }
+        self.constant_index.clear();
+    }
+
+    /// Add a value of any kind
+    fn add_value(&mut self, kind: ValueKind, range: ByteRange, origin: NodeId) -> ValueId {
+        let value_id = self.new_value_id();
+        self.dfg.add_value(DFGValue {
+            id: value_id,
+            kind,
+            source_range: range,
+            origin: Some(origin),
+            occurrences: Vec::new(),
+        });
+        value_id
+    }
+
     /// Get a new value ID
     fn new_value_id(&mut self) -> ValueId {
         let id = ValueId(self.next_value_id);
@@ -232,6 +791,83 @@ impl<'a> DFGBuilder<'a> {
     }
 }
 
+/// One literal text's Constant while the DFG is being built
+struct ConstantEntry {
+    value: StringId,
+    origin: NodeId,
+    occurrences: Vec<ByteRange>,
+}
+
+/// The previous DFG of a function being rebuilt partially
+struct PreviousDFG<'p> {
+    /// Statement values (not phis or constants) by origin node, in order
+    values: HashMap<NodeId, Vec<&'p DFGValue>>,
+
+    /// Literal text of each constant
+    constants: HashMap<ValueId, StringId>,
+
+    /// Edges into statement values by the node of their target, in order
+    edges: HashMap<NodeId, Vec<&'p DFGEdge>>,
+
+    /// Phi-like values by merge node and variable name
+    phis: HashMap<(NodeId, StringId), ValueId>,
+
+    /// Nodes to walk again
+    invalidated: BTreeSet<NodeId>,
+
+    /// Every value records its origin (schema version 2 and later)
+    has_origins: bool,
+
+    /// Previous ValueIds to those in the new DFG
+    remap: HashMap<ValueId, ValueId>,
+
+    /// Values copied rather than rebuilt
+    reused: usize,
+}
+
+impl<'p> PreviousDFG<'p> {
+    fn new(prev: &'p DFG, invalidated_nodes: &[NodeId]) -> Self {
+        let mut values: HashMap<NodeId, Vec<&DFGValue>> = HashMap::new();
+        let mut constants = HashMap::new();
+        let mut phis = HashMap::new();
+        let mut origins = HashMap::new();
+        for value in &prev.values {
+            let Some(origin) = value.origin else { continue };
+            match value.kind {
+                // Synthetic range
+                ValueKind::Variable { name } if value.source_range.is_empty() => {
+                    phis.insert((origin, name), value.id);
+                }
+                ValueKind::Constant { value: text } => {
+                    constants.insert(value.id, text);
+                }
+                _ => {
+                    origins.insert(value.id, origin);
+                    values.entry(origin).or_default().push(value);
+                }
+            }
+        }
+
+        let mut edges: HashMap<NodeId, Vec<&DFGEdge>> = HashMap::new();
+        for edge in &prev.edges {
+            if let Some(&origin) = origins.get(&edge.to) {
+                edges.entry(origin).or_default().push(edge);
+            }
+        }
+
+        Self {
+            values,
+            constants,
+            edges,
+            phis,
+            invalidated: invalidated_nodes.iter().copied().collect(),
+            has_origins: prev.values.iter().all(|value| value.origin.is_some()),
+            remap: HashMap::new(),
+            reused: 0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,7 +891,7 @@ mod tests {
 
         // Build CFG
         let mut cfg_builder = CFGBuilder::new(file_id, source);
-        let cfgs = cfg_builder.build_all(&parsed).unwrap();
+        let cfgs = cfg_builder.build_all(&parsed, &mut StringArena::new()).unwrap();
         assert!(!cfgs.is_empty());
 
         // Build symbol table
@@ -263,8 +899,9 @@ mod tests {
         symbols.build(&parsed, source).unwrap();
 
         // Build DFG
-        let dfg_builder = DFGBuilder::new(&cfgs[0], &symbols, source);
-        let dfg = dfg_builder.build().unwrap();
+        let index = parsed.preorder_index();
+        let dfg_builder = DFGBuilder::new(&cfgs[0], &symbols, &index, source);
+        let _dfg = dfg_builder.build(&mut StringArena::new()).unwrap();
 
         // Should have values for x and y
         // assert!(dfg.values.len() >= 2, "Should have at least 2 values (x, y)");
@@ -283,16 +920,336 @@ mod tests {
         let parsed = parser.parse(&mmap, None).unwrap();
 
         let mut cfg_builder = CFGBuilder::new(file_id, source);
-        let cfgs = cfg_builder.build_all(&parsed).unwrap();
+        let cfgs = cfg_builder.build_all(&parsed, &mut StringArena::new()).unwrap();
 
         let mut symbols = SymbolTable::new(file_id);
         symbols.build(&parsed, source).unwrap();
 
         // Build DFG twice
-        let dfg1 = DFGBuilder::new(&cfgs[0], &symbols, source).build().unwrap();
-        let dfg2 = DFGBuilder::new(&cfgs[0], &symbols, source).build().unwrap();
+        let index = parsed.preorder_index();
+        let mut strings1 = StringArena::new();
+        let mut strings2 = StringArena::new();
+        let dfg1 = DFGBuilder::new(&cfgs[0], &symbols, &index, source).build(&mut strings1).unwrap();
+        let dfg2 = DFGBuilder::new(&cfgs[0], &symbols, &index, source).build(&mut strings2).unwrap();
 
         // Hashes must match
-        assert_eq!(dfg1.compute_hash(), dfg2.compute_hash());
+        assert_eq!(dfg1.compute_hash(&strings1), dfg2.compute_hash(&strings2));
+    }
+
+    fn build_dfg(source: &[u8]) -> (DFG, StringArena) {
+        build_dfg_with(source, |_| {})
+    }
+
+    /// Build the first function's DFG after adjusting its CFG
+    fn build_dfg_with(source: &[u8], adjust: impl FnOnce(&mut CFG)) -> (DFG, StringArena) {
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let mut parser = IncrementalParser::new(Language::Rust).unwrap();
+        let parsed = parser.parse(&mmap, None).unwrap();
+
+        let mut cfgs = CFGBuilder::new(file_id, source).build_all(&parsed, &mut StringArena::new()).unwrap();
+        adjust(&mut cfgs[0]);
+        let mut symbols = SymbolTable::new(file_id);
+        symbols.build(&parsed, source).unwrap();
+
+        let index = parsed.preorder_index();
+        let mut strings = StringArena::new();
+        let dfg = DFGBuilder::new(&cfgs[0], &symbols, &index, source).build(&mut strings).unwrap();
+        (dfg, strings)
+    }
+
+    /// Synthetic (phi-like) values and their incoming definitions
+    fn phis(dfg: &DFG, strings: &StringArena) -> Vec<(String, Vec<ValueId>)> {
+        dfg.values.iter()
+            .filter(|v| v.source_range.is_empty())
+            .map(|v| {
+                let name = match &v.kind {
+                    ValueKind::Variable { name } => strings.resolve(*name).to_string(),
+                    other => panic!("unexpected phi kind {:?}", other),
+                };
+                let incoming = dfg.edges.iter()
+                    .filter(|e| e.to == v.id && e.kind == DFGEdgeKind::PhiLike)
+                    .map(|e| e.from)
+                    .collect();
+                (name, incoming)
+            })
+            .collect()
+    }
+
+    fn defs_of(dfg: &DFG, strings: &StringArena, var: &str) -> Vec<ValueId> {
+        dfg.values.iter()
+            .filter(|v| !v.source_range.is_empty())
+            .filter(|v| matches!(&v.kind, ValueKind::Variable { name } if strings.resolve(*name) == var))
+            .map(|v| v.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_phi_at_diamond_join() {
+        let (dfg, strings) = build_dfg(b"fn t(c: bool) { let mut x = 1; if c { x = 2; } else { x = 3; } let y = x; }");
+
+        let x_defs = defs_of(&dfg, &strings, "x");
+        assert_eq!(x_defs.len(), 3);
+
+        // One phi for x, fed by both branch definitions (not the shadowed first one)
+        let phis = phis(&dfg, &strings);
+        assert_eq!(phis.len(), 1);
+        assert_eq!(phis[0].0, "x");
+        assert_eq!(phis[0].1, vec![x_defs[1], x_defs[2]]);
+    }
+
+    #[test]
+    fn test_phi_without_else_sees_dominating_definition() {
+        let (dfg, strings) = build_dfg(b"fn t(c: bool) { let mut x = 1; if c { x = 2; } let y = x; }");
+
+        let x_defs = defs_of(&dfg, &strings, "x");
+        let phis = phis(&dfg, &strings);
+        assert_eq!(phis.len(), 1);
+
+        let mut incoming = phis[0].1.clone();
+        incoming.sort();
+        assert_eq!(incoming, vec![x_defs[0], x_defs[1]]);
+    }
+
+    #[test]
+    fn test_no_phi_for_variables_unchanged_in_branches() {
+        // The old builder merged every variable at every join
+        let (dfg, strings) = build_dfg(b"fn t(c: bool) { let x = 1; if c { let y = 2; } let z = x; }");
+
+        let phis = phis(&dfg, &strings);
+        assert!(phis.iter().all(|(name, _)| name != "x"), "{:?}", phis);
+        assert!(phis.iter().all(|(name, _)| name != "z"), "{:?}", phis);
+    }
+
+    #[test]
+    fn test_phi_at_loop_header() {
+        let (dfg, strings) = build_dfg(b"fn t() { let mut i = 0; while i < 3 { i = i + 1; } }");
+
+        let i_defs = defs_of(&dfg, &strings, "i");
+        assert_eq!(i_defs.len(), 2);
+
+        let phis = phis(&dfg, &strings);
+        assert_eq!(phis.len(), 1);
+        let mut incoming = phis[0].1.clone();
+        incoming.sort();
+        assert_eq!(incoming, vec![i_defs[0], i_defs[1]]);
+    }
+
+    #[test]
+    fn test_definitions_read_from_ast() {
+        let (dfg, strings) = build_dfg(b"fn t(p: P) { let mut a = 1; let (b, c) = (2, 3); p.f = 4; a = 5; a += 6; }");
+
+        assert_eq!(defs_of(&dfg, &strings, "a").len(), 2);
+        assert!(defs_of(&dfg, &strings, "b").is_empty());
+        assert!(defs_of(&dfg, &strings, "p.f").is_empty());
+    }
+
+    #[test]
+    fn test_multiline_let_defines_variable() {
+        let (dfg, strings) = build_dfg(b"fn t() {\n    let\n        total =\n        f(1,\n          2);\n    total = total\n        + 1;\n}");
+
+        assert_eq!(defs_of(&dfg, &strings, "total").len(), 2);
+        assert!(defs_of(&dfg, &strings, "lettotal").is_empty());
+    }
+
+    #[test]
+    fn test_range_path_matches_index_path() {
+        let fixtures: [&[u8]; 4] = [
+            b"fn t() { let x = 42; let y = x; }",
+            b"fn t(c: bool) { let mut x = 1; if c { x = 2; } else { x = 3; } let y = x; }",
+            b"fn t() { let mut i = 0; while i < 3 { i = i + 1; } }",
+            b"fn t(v: u8) { let mut r = 0; match v { 0 => r = 1, _ => { r = 2; } } r = r }",
+        ];
+
+        for source in fixtures {
+            let (by_index, index_strings) = build_dfg(source);
+            let (by_range, range_strings) = build_dfg_with(source, |cfg| {
+                for node in &mut cfg.nodes {
+                    node.ast_node_id = None;
+                }
+            });
+
+            assert!(!by_index.values.is_empty());
+            assert_eq!(
+                by_index.compute_hash(&index_strings),
+                by_range.compute_hash(&range_strings),
+                "{}", String::from_utf8_lossy(source)
+            );
+        }
+    }
+
+    #[test]
+    fn test_block_values_are_temporaries() {
+        let (dfg, strings) = build_dfg(b"fn t(c: bool) -> i32 { let x = if c { 1 } else { 2 }; x + 1 }");
+
+        let temps: Vec<ValueId> = dfg.values.iter()
+            .filter(|v| matches!(v.kind, ValueKind::Temporary))
+            .map(|v| v.id)
+            .collect();
+        assert_eq!(temps.len(), 3, "Both arms and the implicit return");
+
+        // x is defined by the two arm values, not by the return value
+        let x = defs_of(&dfg, &strings, "x");
+        assert_eq!(x.len(), 1);
+        let mut incoming: Vec<ValueId> = dfg.edges.iter()
+            .filter(|e| e.to == x[0] && e.kind == DFGEdgeKind::Definition)
+            .map(|e| e.from)
+            .collect();
+        incoming.sort();
+        assert_eq!(incoming, temps[..2].to_vec());
+    }
+
+    #[test]
+    fn test_statement_after_if_takes_no_arm_values() {
+        let (dfg, strings) = build_dfg(b"fn t(c: bool) { if c { f() } else { g() } let x = 1; }");
+
+        // Only its own literal defines x
+        let x = defs_of(&dfg, &strings, "x");
+        let incoming: Vec<&DFGEdge> = dfg.edges.iter().filter(|e| e.to == x[0]).collect();
+        assert_eq!(incoming.len(), 1);
+        assert!(matches!(dfg.values[incoming[0].from.0 as usize].kind, ValueKind::Constant { .. }));
+    }
+
+    #[test]
+    fn test_values_record_origin_node() {
+        let (dfg, strings) = build_dfg(b"fn t(c: bool) { let mut x = 1; if c { x = 2; } let y = x; }");
+
+        assert!(dfg.values.iter().all(|v| v.origin.is_some()));
+        let x = defs_of(&dfg, &strings, "x");
+        let origin_of = |id: ValueId| dfg.values[id.0 as usize].origin.unwrap();
+        assert_ne!(origin_of(x[0]), origin_of(x[1]));
+
+        // Each constant shares its definition's node
+        let constants = dfg.edges.iter()
+            .filter(|e| matches!(dfg.values[e.from.0 as usize].kind, ValueKind::Constant { .. }));
+        for edge in constants {
+            assert_eq!(origin_of(edge.from), origin_of(edge.to));
+        }
+    }
+
+    #[test]
+    fn test_statement_values_carry_roles() {
+        let (dfg, strings) = build_dfg(b"fn t() { let x = 2; let y = x + 1; }");
+
+        let x = defs_of(&dfg, &strings, "x");
+        let y = defs_of(&dfg, &strings, "y");
+        assert_eq!((x.len(), y.len()), (1, 1));
+
+        let constants: Vec<(ValueId, &str)> = dfg.values.iter()
+            .filter_map(|v| match v.kind {
+                ValueKind::Constant { value } => Some((v.id, strings.resolve(value))),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(constants.iter().map(|(_, text)| *text).collect::<Vec<_>>(), vec!["2", "1"]);
+
+        let temps: Vec<ValueId> = dfg.values.iter()
+            .filter(|v| matches!(v.kind, ValueKind::Temporary))
+            .map(|v| v.id)
+            .collect();
+        assert_eq!(temps.len(), 1, "x + 1");
+        let sum = temps[0];
+
+        let edges_into = |to: ValueId, kind: DFGEdgeKind| -> Vec<ValueId> {
+            let mut from: Vec<ValueId> = dfg.edges.iter()
+                .filter(|e| e.to == to && e.kind == kind)
+                .map(|e| e.from)
+                .collect();
+            from.sort();
+            from
+        };
+        assert_eq!(edges_into(x[0], DFGEdgeKind::Definition), vec![constants[0].0]);
+        assert_eq!(edges_into(sum, DFGEdgeKind::Use), vec![x[0], constants[1].0]);
+        assert_eq!(edges_into(y[0], DFGEdgeKind::Definition), vec![sum]);
+    }
+
+    #[test]
+    fn test_parameters_defined_at_entry_in_order() {
+        let (dfg, strings) = build_dfg(b"fn t(a: i32, (b, c): (i32, i32), /* d */ mut d: i32) { let x = d + a; }");
+
+        let parameters: Vec<(ValueId, &str, usize)> = dfg.values.iter()
+            .filter_map(|v| match v.kind {
+                ValueKind::Parameter { name, position } => Some((v.id, strings.resolve(name), position)),
+                _ => None,
+            })
+            .collect();
+        let names: Vec<(&str, usize)> = parameters.iter().map(|&(_, name, position)| (name, position)).collect();
+        assert_eq!(names, [("a", 0), ("b", 1), ("c", 1), ("d", 2)]);
+
+        // `d + a` reads the parameters' definitions
+        let sum = dfg.values.iter().find(|v| v.kind == ValueKind::Temporary).unwrap().id;
+        let mut used: Vec<ValueId> = dfg.edges.iter().filter(|e| e.to == sum).map(|e| e.from).collect();
+        used.sort();
+        assert_eq!(used, [parameters[0].0, parameters[3].0]);
+    }
+
+    #[test]
+    fn test_let_condition_defines_at_header_and_branch() {
+        let source: &[u8] = b"fn t(it: I, r: R) { while let Some(x) = it.next() { let y = x + 1; } if let Ok(v) = r { let w = v + 1; } }";
+        let mut kinds = HashMap::new();
+        let (dfg, strings) = build_dfg_with(source, |cfg| kinds = cfg.nodes.iter().map(|n| (n.id, n.kind.clone())).collect());
+
+        let defined_from = |var: &str| -> (CFGNodeKind, &ValueKind) {
+            let def = defs_of(&dfg, &strings, var);
+            assert_eq!(def.len(), 1, "{}", var);
+            let value = &dfg.values[def[0].0 as usize];
+            let from: Vec<ValueId> = dfg.edges.iter()
+                .filter(|e| e.to == value.id && e.kind == DFGEdgeKind::Definition)
+                .map(|e| e.from)
+                .collect();
+            assert_eq!(from.len(), 1, "{}", var);
+            (kinds[&value.origin.unwrap()].clone(), &dfg.values[from[0].0 as usize].kind)
+        };
+        assert_eq!(defined_from("x"), (CFGNodeKind::LoopHeader, &ValueKind::Temporary));
+        let (kind, from) = defined_from("v");
+        assert_eq!(kind, CFGNodeKind::Branch);
+        assert!(matches!(from, ValueKind::Parameter { name, .. } if strings.resolve(*name) == "r"), "{:?}", from);
+
+        // Uses in the bodies read the pattern's definition
+        for var in ["x", "v"] {
+            let def = defs_of(&dfg, &strings, var)[0];
+            assert!(dfg.edges.iter().any(|e| e.from == def && e.kind == DFGEdgeKind::Use), "{}", var);
+        }
+    }
+
+    #[test]
+    fn test_repeated_literal_is_one_constant() {
+        let source: &[u8] = b"fn t(c: bool) { let a = 0; let b = a + 0; if c { a = (0, 0); } let d = f(0, 1); }";
+        let (dfg, strings) = build_dfg(source);
+
+        let constants: Vec<&DFGValue> = dfg.values.iter()
+            .filter(|v| matches!(v.kind, ValueKind::Constant { .. }))
+            .collect();
+        let texts: Vec<&str> = constants.iter()
+            .map(|v| match v.kind {
+                ValueKind::Constant { value } => strings.resolve(value),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(texts, vec!["0", "1"]);
+
+        // Numbered after every other value, in first-occurrence order
+        let zero = constants[0];
+        assert_eq!(zero.id.0 as usize, dfg.values.len() - 2);
+        let sites: Vec<usize> = source.iter().enumerate()
+            .filter(|(_, &byte)| byte == b'0')
+            .map(|(offset, _)| offset)
+            .collect();
+        assert_eq!(zero.occurrences.iter().map(|range| range.start).collect::<Vec<_>>(), sites);
+        assert_eq!(zero.occurrences.len(), 5);
+        assert_eq!(zero.source_range, zero.occurrences[0]);
+
+        // `(0, 0)` reads it once
+        let reads = dfg.edges.iter().filter(|e| e.from == zero.id).count();
+        assert_eq!(reads, 4);
+
+        let (again, again_strings) = build_dfg(source);
+        assert_eq!(dfg.compute_hash(&strings), again.compute_hash(&again_strings));
+        let mut reordered = again.clone();
+        reordered.values[zero.id.0 as usize].occurrences.swap(1, 2);
+        assert_ne!(reordered.compute_hash(&again_strings), dfg.compute_hash(&strings), "hash covers occurrence order");
     }
 }
@@ -16,21 +16,60 @@
 //! - No cross-epoch pointers allowed
 //! - Semantic facts are immutable within epoch
 //! - Incremental updates create new epoch
+//!
+//! ## Fingerprints
+//!
+//! Every file analyzed by `add_parsed` gets a semantic fingerprint: its CFG,
+//! DFG and symbol table hashes in order, plus the statement text and source
+//! ranges fusion copies into the CPG (which the CFG hash leaves out). Equal
+//! fingerprints mean equal fused CPG nodes, so an incremental run can treat
+//! a reparse that reproduces the previous fingerprint as a no-op.
+//!
+//! The CFG and DFG hashes a fingerprint is made of are kept alongside it
+//! (`graph_hashes`), so verification reads them instead of hashing the
+//! same graphs again. `add_cfg` and `add_dfg` drop a file's kept hashes.
+//!
+//! ## Budgets
+//!
+//! `add_parsed` builds within the epoch's `LimitsConfig` (unlimited unless
+//! `set_limits` is called). A function over `max_cfg_nodes_per_function` is
+//! left out; once a file's CFG nodes plus DFG values pass
+//! `max_nodes_per_file`, that function and every later one are left out.
+//! Either way the file keeps the graphs built so far and its `status` is
+//! `Degraded` with the reasons. Both cuts depend only on the source and the
+//! limits, so a degraded file builds the same graphs on every run.
+//!
+//! A parse tree node whose range is inverted (see `types::InvertedRange`)
+//! is clamped to an empty range by every builder, and also degrades the
+//! file.
 
+use crate::config::LimitsConfig;
+use crate::memory::arena::StringArena;
 use crate::memory::epoch::ParseEpoch;
+use crate::semantic::cfg::CFGBuilder;
+use crate::semantic::dfg::DFGBuilder;
 use crate::semantic::invalidation::InvalidationTracker;
 use crate::semantic::model::{CFG, DFG};
-use crate::semantic::symbols::SymbolTable;
-use crate::types::FileId;
+use crate::semantic::profile::{LanguageProfile, RustProfile};
+use crate::semantic::symbols::{Symbol, SymbolKind, SymbolTable};
+use crate::types::{ByteRange, FileId, Language, ParsedFile};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Epoch ID used by `SemanticEpoch::build_from_parsed`
+/// (Ingestion = 1, Parse = 2, Semantic = 3)
+pub const SEMANTIC_EPOCH_ID: u64 = 3;
 
 /// Semantic epoch - owns all semantic analysis results
 ///
 /// **Memory Safety:** All semantic data (CFGs, DFGs, symbols) lives within this epoch.
 /// When the epoch is dropped, all memory is freed automatically.
 pub struct SemanticEpoch {
-    /// Reference to parse epoch (read-only)
-    _parse_epoch_marker: u64, // Would be lifetime in real impl
+    /// Parent parse epoch, kept alive by this epoch (None when built detached)
+    parse_epoch: Option<Arc<ParseEpoch>>,
     
     /// CFGs per function
     cfgs: HashMap<FileId, Vec<CFG>>,
@@ -40,10 +79,28 @@ pub struct SemanticEpoch {
     
     /// Symbol tables per file
     symbols: HashMap<FileId, SymbolTable>,
+
+    /// Source language per file, where the pipeline knows it
+    languages: HashMap<FileId, Language>,
     
+    /// Semantic fingerprint per file analyzed by `add_parsed`
+    fingerprints: HashMap<FileId, String>,
+
+    /// CFG and DFG hashes per file, as of its last fingerprint
+    graph_hashes: HashMap<FileId, (Vec<String>, Vec<String>)>,
+
+    /// Budgets `add_parsed` builds within
+    limits: LimitsConfig,
+
+    /// Why files were cut short by `limits`
+    degraded: HashMap<FileId, String>,
+
     /// Invalidation tracker for incremental updates
     invalidation: InvalidationTracker,
     
+    /// Statement text and value names of every CFG and DFG
+    strings: StringArena,
+    
     /// Epoch ID for debugging
     epoch_id: u64,
 }
@@ -51,33 +108,266 @@ pub struct SemanticEpoch {
 impl SemanticEpoch {
     /// Create a new semantic epoch
     ///
-    /// Takes a reference to ParseEpoch. This ensures:
-    /// - Parse trees are available for semantic analysis
-    /// - Parse epoch outlives semantic epoch
-    pub fn new(_parse_epoch: &ParseEpoch, epoch_id: u64) -> Self {
+    /// Holds the ParseEpoch, so parse trees and the source they were built
+    /// from outlive every semantic fact. Fails if `epoch_id` is not later
+    /// than the parse epoch's marker.
+    pub fn new(parse_epoch: Arc<ParseEpoch>, epoch_id: u64) -> Result<Self> {
+        if epoch_id <= parse_epoch.marker().id() {
+            bail!(
+                "Semantic epoch {} cannot be a child of parse epoch {}",
+                epoch_id, parse_epoch.marker().id()
+            );
+        }
+
+        let mut epoch = Self::detached(epoch_id);
+        epoch.parse_epoch = Some(parse_epoch);
+        Ok(epoch)
+    }
+
+    /// An epoch with no parse epoch behind it
+    fn detached(epoch_id: u64) -> Self {
         Self {
-            _parse_epoch_marker: epoch_id,
+            parse_epoch: None,
             cfgs: HashMap::new(),
             dfgs: HashMap::new(),
             symbols: HashMap::new(),
+            languages: HashMap::new(),
+            fingerprints: HashMap::new(),
+            graph_hashes: HashMap::new(),
+            limits: LimitsConfig::unlimited(),
+            degraded: HashMap::new(),
             invalidation: InvalidationTracker::new(),
+            strings: StringArena::new(),
             epoch_id,
         }
     }
 
+    /// Start building an epoch without a ParseEpoch (tests, external callers)
+    pub fn builder(epoch_id: u64) -> SemanticEpochBuilder {
+        SemanticEpochBuilder {
+            epoch: Self::detached(epoch_id),
+        }
+    }
+
+    /// Analyze parsed files into a new epoch
+    ///
+    /// **Deterministic**: Files are analyzed in FileId order regardless of
+    /// input order. Duplicate FileIds are rejected.
+    pub fn build_from_parsed(files: &[(FileId, &ParsedFile, &[u8])]) -> Result<Self> {
+        let mut sorted: Vec<_> = files.iter().collect();
+        sorted.sort_by_key(|(file_id, _, _)| *file_id);
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            bail!("Duplicate file in semantic epoch: {:?}", pair[0].0);
+        }
+
+        let mut epoch = Self::builder(SEMANTIC_EPOCH_ID).build();
+        for (file_id, parsed, source) in sorted {
+            epoch.add_parsed(*file_id, parsed, source)?;
+        }
+        Ok(epoch)
+    }
+
+    /// Build CFGs, symbols and DFGs for one parsed Rust file
+    pub fn add_parsed(&mut self, file_id: FileId, parsed: &ParsedFile, source: &[u8]) -> Result<()> {
+        self.add_parsed_with_profile(file_id, parsed, source, &RustProfile)
+    }
+
+    /// Build CFGs, symbols and DFGs for one parsed file of `profile`'s language
+    pub fn add_parsed_with_profile(
+        &mut self,
+        file_id: FileId,
+        parsed: &ParsedFile,
+        source: &[u8],
+        profile: &'static dyn LanguageProfile,
+    ) -> Result<()> {
+        let index = parsed.preorder_index();
+        let mut reasons = Vec::new();
+        let inverted = index.inverted_ranges();
+        if let Some(first) = inverted.first() {
+            reasons.push(format!("{} inverted node range(s) clamped, first {}..{}", inverted.len(), first.start, first.end));
+        }
+
+        let mut builder = CFGBuilder::new(file_id, source)
+            .with_profile(profile)
+            .with_max_nodes_per_function(self.limits.max_cfg_nodes_per_function);
+        let cfgs = builder.build_all(parsed, &mut self.strings)?;
+        reasons.extend_from_slice(builder.over_budget());
+        let mut symbols = SymbolTable::new(file_id).with_profile(profile);
+        symbols.build(parsed, source)?;
+
+        let (mut nodes, mut kept) = (0, 0);
+        for cfg in cfgs {
+            let dfg = DFGBuilder::new(&cfg, &symbols, &index, source).build(&mut self.strings)?;
+            nodes += cfg.nodes.len() + dfg.values.len();
+            if nodes > self.limits.max_nodes_per_file {
+                reasons.push(format!(
+                    "file exceeds {} CFG nodes and DFG values; kept the first {} function(s)",
+                    self.limits.max_nodes_per_file, kept
+                ));
+                break;
+            }
+            for node in &cfg.nodes {
+                self.invalidation.track_ast_to_cfg(file_id, node.source_range, node.id);
+            }
+            self.add_dfg(file_id, dfg);
+            self.add_cfg(file_id, cfg);
+            kept += 1;
+        }
+        self.add_symbols(file_id, symbols);
+        self.degraded.remove(&file_id);
+        if !reasons.is_empty() {
+            self.degraded.insert(file_id, reasons.join("; "));
+        }
+        self.update_fingerprint(file_id);
+        Ok(())
+    }
+
+    /// Recompute and store one file's semantic fingerprint and graph hashes
+    pub fn update_fingerprint(&mut self, file_id: FileId) -> &str {
+        let (fingerprint, hashes) = self.fingerprint_with_hashes(file_id);
+        self.graph_hashes.insert(file_id, hashes);
+        self.fingerprints.insert(file_id, fingerprint);
+        &self.fingerprints[&file_id]
+    }
+
+    /// Semantic fingerprint of one file's current CFGs, DFGs and symbols
+    ///
+    /// **Deterministic**: Independent of StringIds, so equal across epochs.
+    pub fn compute_fingerprint(&self, file_id: FileId) -> String {
+        self.fingerprint_with_hashes(file_id).0
+    }
+
+    /// Fingerprint of one file, and the CFG and DFG hashes it covers
+    fn fingerprint_with_hashes(&self, file_id: FileId) -> (String, (Vec<String>, Vec<String>)) {
+        fn range(hasher: &mut Sha256, range: ByteRange) {
+            hasher.update((range.start as u64).to_be_bytes());
+            hasher.update((range.end as u64).to_be_bytes());
+        }
+
+        let mut hasher = Sha256::new();
+        let (mut cfg_hashes, mut dfg_hashes) = (Vec::new(), Vec::new());
+
+        for cfg in self.cfgs.get(&file_id).into_iter().flatten() {
+            let hash = cfg.compute_hash();
+            hasher.update(hash.as_bytes());
+            cfg_hashes.push(hash);
+            range(&mut hasher, cfg.signature_range);
+            for node in &cfg.nodes {
+                range(&mut hasher, node.source_range);
+                let statement = node.statement.map_or("", |id| self.strings.resolve(id));
+                hasher.update((statement.len() as u64).to_be_bytes());
+                hasher.update(statement.as_bytes());
+            }
+        }
+        for dfg in self.dfgs.get(&file_id).into_iter().flatten() {
+            let hash = dfg.compute_hash(&self.strings);
+            hasher.update(hash.as_bytes());
+            dfg_hashes.push(hash);
+            for value in &dfg.values {
+                range(&mut hasher, value.source_range);
+            }
+        }
+        if let Some(symbols) = self.symbols.get(&file_id) {
+            hasher.update(symbols.compute_hash().as_bytes());
+        }
+        (format!("{:x}", hasher.finalize()), (cfg_hashes, dfg_hashes))
+    }
+
+    /// Budgets `add_parsed` builds within
+    pub fn limits(&self) -> &LimitsConfig {
+        &self.limits
+    }
+
+    /// Build later `add_parsed` files within `limits`
+    pub fn set_limits(&mut self, limits: LimitsConfig) {
+        self.limits = limits;
+    }
+
+    /// Whether a file's graphs are complete or were cut short by a budget
+    pub fn status(&self, file_id: FileId) -> SemanticStatus {
+        match self.degraded.get(&file_id) {
+            Some(reason) => SemanticStatus::Degraded { reason: reason.clone() },
+            None => SemanticStatus::Complete,
+        }
+    }
+
+    /// Stored semantic fingerprint of a file (None if not built by `add_parsed`)
+    pub fn fingerprint(&self, file_id: FileId) -> Option<&str> {
+        self.fingerprints.get(&file_id).map(String::as_str)
+    }
+
+    /// Every stored fingerprint
+    pub fn fingerprints(&self) -> &HashMap<FileId, String> {
+        &self.fingerprints
+    }
+
+    /// CFG and DFG hashes of a file, in function order, if still current
+    ///
+    /// Kept by `update_fingerprint`; None once `add_cfg` or `add_dfg` has
+    /// changed the file since.
+    pub fn graph_hashes(&self, file_id: FileId) -> Option<(&[String], &[String])> {
+        self.graph_hashes.get(&file_id).map(|(cfgs, dfgs)| (cfgs.as_slice(), dfgs.as_slice()))
+    }
+
+    /// Copy one file's CFGs, DFGs, symbols and dependencies from another epoch
+    ///
+    /// Used by incremental runs for files whose content did not change.
+    /// Strings are re-interned into this epoch's arena.
+    pub fn carry_over(&mut self, previous: &SemanticEpoch, file_id: FileId) {
+        let strings = &mut self.strings;
+        let mut remap = |id| strings.intern(previous.strings.resolve(id));
+
+        if let Some(cfgs) = previous.cfgs.get(&file_id) {
+            let mut cfgs = cfgs.clone();
+            for cfg in &mut cfgs {
+                cfg.remap_strings(&mut remap);
+            }
+            self.cfgs.insert(file_id, cfgs);
+        }
+        if let Some(dfgs) = previous.dfgs.get(&file_id) {
+            let mut dfgs = dfgs.clone();
+            for dfg in &mut dfgs {
+                dfg.remap_strings(&mut remap);
+            }
+            self.dfgs.insert(file_id, dfgs);
+        }
+        if let Some(symbols) = previous.symbols.get(&file_id) {
+            self.symbols.insert(file_id, symbols.clone());
+        }
+        if let Some(language) = previous.languages.get(&file_id) {
+            self.languages.insert(file_id, *language);
+        }
+        if let Some(fingerprint) = previous.fingerprints.get(&file_id) {
+            self.fingerprints.insert(file_id, fingerprint.clone());
+        }
+        if let Some(hashes) = previous.graph_hashes.get(&file_id) {
+            self.graph_hashes.insert(file_id, hashes.clone());
+        }
+        if let Some(reason) = previous.degraded.get(&file_id) {
+            self.degraded.insert(file_id, reason.clone());
+        }
+        self.invalidation.carry_over(&previous.invalidation, file_id);
+    }
+
     /// Add a CFG for a file
+    ///
+    /// Its StringIds must come from `strings_mut()`.
     pub fn add_cfg(&mut self, file_id: FileId, cfg: CFG) {
+        self.graph_hashes.remove(&file_id);
         self.cfgs
             .entry(file_id)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(cfg);
     }
 
     /// Add a DFG for a file
+    ///
+    /// Its StringIds must come from `strings_mut()`.
     pub fn add_dfg(&mut self, file_id: FileId, dfg: DFG) {
+        self.graph_hashes.remove(&file_id);
         self.dfgs
             .entry(file_id)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(dfg);
     }
 
@@ -86,6 +376,16 @@ impl SemanticEpoch {
         self.symbols.insert(file_id, table);
     }
 
+    /// Record a file's source language (CPGBuilder labels its File node with it)
+    pub fn set_language(&mut self, file_id: FileId, language: Language) {
+        self.languages.insert(file_id, language);
+    }
+
+    /// Source language of a file, if recorded
+    pub fn language(&self, file_id: FileId) -> Option<Language> {
+        self.languages.get(&file_id).copied()
+    }
+
     /// Get CFGs for a file
     pub fn get_cfgs(&self, file_id: FileId) -> Option<&Vec<CFG>> {
         self.cfgs.get(&file_id)
@@ -101,16 +401,56 @@ impl SemanticEpoch {
         self.symbols.get(&file_id)
     }
 
+    /// Function symbols named `name`, by FileId then SymbolId
+    pub fn functions_named(&self, name: &str) -> Vec<(FileId, &Symbol)> {
+        let mut functions: Vec<(FileId, &Symbol)> = self.symbols.iter()
+            .flat_map(|(file_id, table)| {
+                table.symbols()
+                    .filter(|symbol| symbol.kind == SymbolKind::Function && symbol.name == name)
+                    .map(|symbol| (*file_id, symbol))
+            })
+            .collect();
+        functions.sort_by_key(|(file_id, symbol)| (*file_id, symbol.id));
+        functions
+    }
+
+    /// Get the invalidation tracker
+    pub fn invalidation(&self) -> &InvalidationTracker {
+        &self.invalidation
+    }
+
     /// Get mutable access to invalidation tracker
     pub fn invalidation_mut(&mut self) -> &mut InvalidationTracker {
         &mut self.invalidation
     }
 
+    /// Strings referenced by this epoch's CFGs and DFGs
+    pub fn strings(&self) -> &StringArena {
+        &self.strings
+    }
+
+    /// Arena to intern into before `add_cfg` / `add_dfg`
+    pub fn strings_mut(&mut self) -> &mut StringArena {
+        &mut self.strings
+    }
+
+    /// Approximate heap usage of CFG and DFG storage, including their text
+    pub fn estimated_bytes(&self) -> usize {
+        let cfgs: usize = self.cfgs.values().flatten().map(CFG::estimated_bytes).sum();
+        let dfgs: usize = self.dfgs.values().flatten().map(DFG::estimated_bytes).sum();
+        cfgs + dfgs + self.strings.estimated_bytes()
+    }
+
     /// Get epoch ID
     pub fn epoch_id(&self) -> u64 {
         self.epoch_id
     }
 
+    /// Parent parse epoch (None for detached epochs)
+    pub fn parse_epoch(&self) -> Option<&ParseEpoch> {
+        self.parse_epoch.as_deref()
+    }
+
     /// Get statistics about this epoch
     pub fn stats(&self) -> SemanticEpochStats {
         SemanticEpochStats {
@@ -122,6 +462,11 @@ impl SemanticEpoch {
         }
     }
 
+    /// Per-file counts and function hashes (see `semantic::report`)
+    pub fn report(&self) -> crate::semantic::report::SemanticEpochReport {
+        crate::semantic::report::SemanticEpochReport::build(self)
+    }
+
     /// Get all file IDs in this epoch
     pub fn get_all_file_ids(&self) -> Vec<FileId> {
         let mut file_ids: std::collections::HashSet<_> = std::collections::HashSet::new();
@@ -145,6 +490,64 @@ impl Drop for SemanticEpoch {
     }
 }
 
+/// Whether a file's semantic graphs are complete
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SemanticStatus {
+    /// Every function was built
+    #[default]
+    Complete,
+
+    /// Some functions were left out to stay within `[limits]`
+    Degraded {
+        /// Which budgets were exceeded
+        reason: String,
+    },
+}
+
+impl SemanticStatus {
+    /// Whether nothing was left out
+    pub fn is_complete(&self) -> bool {
+        *self == SemanticStatus::Complete
+    }
+}
+
+/// Chained construction of a SemanticEpoch
+pub struct SemanticEpochBuilder {
+    epoch: SemanticEpoch,
+}
+
+impl SemanticEpochBuilder {
+    /// Add a CFG for a file
+    pub fn add_cfg(mut self, file_id: FileId, cfg: CFG) -> Self {
+        self.epoch.add_cfg(file_id, cfg);
+        self
+    }
+
+    /// Add a DFG for a file
+    pub fn add_dfg(mut self, file_id: FileId, dfg: DFG) -> Self {
+        self.epoch.add_dfg(file_id, dfg);
+        self
+    }
+
+    /// Add a symbol table for a file
+    pub fn add_symbols(mut self, file_id: FileId, table: SymbolTable) -> Self {
+        self.epoch.add_symbols(file_id, table);
+        self
+    }
+
+    /// Record a file's source language
+    pub fn set_language(mut self, file_id: FileId, language: Language) -> Self {
+        self.epoch.set_language(file_id, language);
+        self
+    }
+
+    /// Finish the epoch
+    pub fn build(self) -> SemanticEpoch {
+        self.epoch
+    }
+}
+
 /// Statistics about a semantic epoch
 #[derive(Debug, Clone)]
 pub struct SemanticEpochStats {
@@ -167,37 +570,33 @@ pub struct SemanticEpochStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse::IncrementalParser;
+    use crate::memory::StringId;
+    use crate::semantic::model::{FunctionId, NodeId};
+    use crate::types::{ByteRange, Language};
+    use tempfile::NamedTempFile;
+
+    fn parse(source: &[u8], file_id: FileId) -> ParsedFile {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), source).unwrap();
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap()
+    }
 
     #[test]
     fn test_semantic_epoch_creation() {
-        // Create epoch with fake parse epoch reference
-        let fake_parse_marker = 2;
-        let semantic = SemanticEpoch {
-            _parse_epoch_marker: fake_parse_marker,
-            cfgs: HashMap::new(),
-            dfgs: HashMap::new(),
-            symbols: HashMap::new(),
-            invalidation: InvalidationTracker::new(),
-            epoch_id: 3,
-        };
+        let semantic = SemanticEpoch::builder(3).build();
         
         assert_eq!(semantic.epoch_id(), 3);
+        assert!(semantic.get_all_file_ids().is_empty());
     }
 
     #[test]
     fn test_semantic_epoch_data_management() {
-        let fake_parse_marker = 2;
-        let mut semantic = SemanticEpoch {
-            _parse_epoch_marker: fake_parse_marker,
-            cfgs: HashMap::new(),
-            dfgs: HashMap::new(),
-            symbols: HashMap::new(),
-            invalidation: InvalidationTracker::new(),
-            epoch_id: 3,
-        };
-        
         let file_id = FileId::new(42);
-        semantic.add_symbols(file_id, SymbolTable::new(file_id));
+        let semantic = SemanticEpoch::builder(3)
+            .add_symbols(file_id, SymbolTable::new(file_id))
+            .build();
         
         assert!(semantic.get_symbols(file_id).is_some());
         assert!(semantic.get_cfgs(file_id).is_none());
@@ -205,22 +604,191 @@ mod tests {
 
     #[test]
     fn test_semantic_epoch_stats() {
-        let fake_parse_marker = 2;
-        let mut semantic = SemanticEpoch {
-            _parse_epoch_marker: fake_parse_marker,
-            cfgs: HashMap::new(),
-            dfgs: HashMap::new(),
-            symbols: HashMap::new(),
-            invalidation: InvalidationTracker::new(),
-            epoch_id: 3,
-        };
-        
         let file_id = FileId::new(42);
-        semantic.add_symbols(file_id, SymbolTable::new(file_id));
+        let semantic = SemanticEpoch::builder(3)
+            .add_symbols(file_id, SymbolTable::new(file_id))
+            .add_cfg(file_id, CFG::new(FunctionId(0), file_id, NodeId(0), NodeId(1)))
+            .add_dfg(file_id, DFG::new(FunctionId(0)))
+            .build();
         
         let stats = semantic.stats();
         assert_eq!(stats.epoch_id, 3);
         assert_eq!(stats.files_analyzed, 1);
+        assert_eq!((stats.total_cfgs, stats.total_dfgs), (1, 1));
     }
-}
 
+    #[test]
+    fn test_get_all_file_ids_sorted() {
+        let (a, b, c) = (FileId::new(9), FileId::new(2), FileId::new(5));
+        let semantic = SemanticEpoch::builder(3)
+            .add_symbols(a, SymbolTable::new(a))
+            .add_cfg(b, CFG::new(FunctionId(0), b, NodeId(0), NodeId(1)))
+            .add_dfg(c, DFG::new(FunctionId(0)))
+            .add_symbols(b, SymbolTable::new(b))
+            .build();
+
+        assert_eq!(semantic.get_all_file_ids(), vec![b, c, a]);
+    }
+
+    #[test]
+    fn test_build_from_parsed_is_order_independent() {
+        let (a_src, b_src): (&[u8], &[u8]) = (b"fn a() { let x = 1; }\nfn a2() {}", b"fn b(c: bool) { if c { let y = 2; } }");
+        let (a_id, b_id) = (FileId::new(1), FileId::new(2));
+        let (a, b) = (parse(a_src, a_id), parse(b_src, b_id));
+
+        let forward = SemanticEpoch::build_from_parsed(&[(a_id, &a, a_src), (b_id, &b, b_src)]).unwrap();
+        let reverse = SemanticEpoch::build_from_parsed(&[(b_id, &b, b_src), (a_id, &a, a_src)]).unwrap();
+
+        assert_eq!(forward.epoch_id(), SEMANTIC_EPOCH_ID);
+        assert_eq!(forward.get_all_file_ids(), vec![a_id, b_id]);
+        assert_eq!(forward.get_cfgs(a_id).unwrap().len(), 2);
+        for file_id in [a_id, b_id] {
+            let hashes = |epoch: &SemanticEpoch| -> Vec<String> {
+                epoch.get_cfgs(file_id).unwrap().iter().map(CFG::compute_hash)
+                    .chain(epoch.get_dfgs(file_id).unwrap().iter().map(|d| d.compute_hash(epoch.strings())))
+                    .collect()
+            };
+            assert_eq!(hashes(&forward), hashes(&reverse));
+        }
+    }
+
+    #[test]
+    fn test_carry_over_copies_one_file() {
+        let (a_src, b_src): (&[u8], &[u8]) = (b"fn a() { let x = 1; }", b"fn b() {}");
+        let (a_id, b_id) = (FileId::new(1), FileId::new(2));
+        let (a, b) = (parse(a_src, a_id), parse(b_src, b_id));
+        let previous = SemanticEpoch::build_from_parsed(&[(a_id, &a, a_src), (b_id, &b, b_src)]).unwrap();
+
+        let mut next = SemanticEpoch::builder(3).build();
+        next.carry_over(&previous, a_id);
+
+        assert_eq!(next.get_all_file_ids(), vec![a_id]);
+        assert_eq!(
+            next.get_cfgs(a_id).unwrap()[0].compute_hash(),
+            previous.get_cfgs(a_id).unwrap()[0].compute_hash()
+        );
+        assert!(next.get_symbols(a_id).is_some());
+
+        let whole_file = [ByteRange::new(0, a_src.len())];
+        assert_eq!(
+            next.invalidation().invalidate(a_id, &whole_file).cfg_nodes,
+            previous.invalidation().invalidate(a_id, &whole_file).cfg_nodes
+        );
+        assert!(next.invalidation().invalidate(b_id, &[ByteRange::new(0, b_src.len())]).is_empty());
+    }
+
+    #[test]
+    fn test_graph_hashes_kept_with_fingerprint() {
+        let source: &[u8] = b"fn a() { let x = 1; }\nfn b(c: bool) { if c { let y = 2; } }";
+        let file_id = FileId::new(1);
+        let parsed = parse(source, file_id);
+        let epoch = SemanticEpoch::build_from_parsed(&[(file_id, &parsed, source)]).unwrap();
+
+        let computed: (Vec<String>, Vec<String>) = (
+            epoch.get_cfgs(file_id).unwrap().iter().map(CFG::compute_hash).collect(),
+            epoch.get_dfgs(file_id).unwrap().iter().map(|d| d.compute_hash(epoch.strings())).collect(),
+        );
+        let (cfgs, dfgs) = epoch.graph_hashes(file_id).unwrap();
+        assert_eq!((cfgs.to_vec(), dfgs.to_vec()), computed);
+
+        let mut next = SemanticEpoch::builder(3).build();
+        next.carry_over(&epoch, file_id);
+        assert_eq!(next.graph_hashes(file_id), epoch.graph_hashes(file_id));
+
+        // A graph added after fingerprinting makes the kept hashes stale
+        next.add_cfg(file_id, CFG::new(FunctionId(9), file_id, NodeId(0), NodeId(1)));
+        assert!(next.graph_hashes(file_id).is_none());
+    }
+
+    #[test]
+    fn test_carry_over_reinterns_strings() {
+        let (a_src, b_src): (&[u8], &[u8]) = (b"fn a() { let x = 1; }", b"fn b() { let y = 2; }");
+        let (a_id, b_id) = (FileId::new(1), FileId::new(2));
+        let (a, b) = (parse(a_src, a_id), parse(b_src, b_id));
+        let previous = SemanticEpoch::build_from_parsed(&[(a_id, &a, a_src), (b_id, &b, b_src)]).unwrap();
+
+        // Only b: its strings get different IDs than in `previous`
+        let mut next = SemanticEpoch::builder(3).build();
+        next.carry_over(&previous, b_id);
+
+        let statements = |epoch: &SemanticEpoch| -> Vec<String> {
+            epoch.get_cfgs(b_id).unwrap()[0].nodes.iter()
+                .filter_map(|n| n.statement)
+                .map(|id| epoch.strings().resolve(id).to_string())
+                .collect()
+        };
+        assert_eq!(statements(&next), statements(&previous));
+        assert!(statements(&next).contains(&"let y = 2;".to_string()));
+        assert!(next.strings().len() < previous.strings().len());
+        assert_eq!(
+            next.get_dfgs(b_id).unwrap()[0].compute_hash(next.strings()),
+            previous.get_dfgs(b_id).unwrap()[0].compute_hash(previous.strings())
+        );
+    }
+
+    #[test]
+    fn test_repeated_statements_share_storage() {
+        let body = "let total = total + 1;\n".repeat(40);
+        let source: String = (0..50).map(|i| format!("fn f{}() {{\n{}}}\n", i, body)).collect();
+        let file_id = FileId::new(1);
+        let parsed = parse(source.as_bytes(), file_id);
+        let epoch = SemanticEpoch::build_from_parsed(&[(file_id, &parsed, source.as_bytes())]).unwrap();
+
+        // Before interning: every statement and name was its own String
+        let strings = epoch.strings();
+        let occurrences = epoch.get_cfgs(file_id).unwrap().iter()
+            .flat_map(|cfg| &cfg.nodes)
+            .filter(|n| n.statement.is_some())
+            .count()
+            + epoch.get_dfgs(file_id).unwrap().iter().map(|dfg| dfg.values.len()).sum::<usize>();
+        let inline_growth = std::mem::size_of::<Option<String>>() - std::mem::size_of::<Option<StringId>>();
+        let interned = epoch.estimated_bytes();
+        let without_interning = interned - strings.estimated_bytes()
+            + strings.requested_bytes()
+            + occurrences * inline_growth;
+
+        assert!(strings.len() < 10, "{} distinct strings", strings.len());
+        assert!(strings.text_bytes() * 100 < strings.requested_bytes());
+        assert!(interned < without_interning, "{} vs {} bytes", interned, without_interning);
+        assert!(without_interning - interned >= strings.requested_bytes() - strings.estimated_bytes());
+    }
+
+    #[test]
+    fn test_inverted_node_ranges_degrade_instead_of_panicking() {
+        // Error recovery inserts a MISSING `;`; an edit the tree was never
+        // reparsed after leaves the block and `let` ending before they start
+        let source = b"fn f() { let x = 1 }\nfn g() {}\n";
+        let file_id = FileId::new(1);
+        let mut parsed = parse(source, file_id);
+        parsed.tree.edit(&tree_sitter::InputEdit {
+            start_byte: 12,
+            old_end_byte: 30,
+            new_end_byte: 2,
+            start_position: tree_sitter::Point::new(0, 12),
+            old_end_position: tree_sitter::Point::new(1, 9),
+            new_end_position: tree_sitter::Point::new(0, 2),
+        });
+
+        let quality = parsed.quality();
+        assert!(!quality.clean);
+        assert!(!quality.inverted_ranges.is_empty());
+
+        let mut semantic = SemanticEpoch::builder(3).build();
+        semantic.add_parsed(file_id, &parsed, source).unwrap();
+        match semantic.status(file_id) {
+            SemanticStatus::Degraded { reason } => assert!(reason.contains("inverted"), "{}", reason),
+            SemanticStatus::Complete => panic!("inverted ranges must degrade the file"),
+        }
+        assert!(semantic.get_cfgs(file_id).is_some());
+    }
+
+    #[test]
+    fn test_build_from_parsed_rejects_duplicates() {
+        let source: &[u8] = b"fn a() {}";
+        let file_id = FileId::new(1);
+        let parsed = parse(source, file_id);
+
+        let err = SemanticEpoch::build_from_parsed(&[(file_id, &parsed, source), (file_id, &parsed, source)]);
+        assert!(err.is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Canonical CFG and DFG hashing
+//!
+//! `CFG::compute_hash` and `DFG::compute_hash` feed explicit bytes to
+//! SHA-256, never `Debug` output: every enum variant has a fixed tag byte
+//! (`CanonicalHash`) and every variable-length field is length-prefixed.
+//! Renaming, reordering or re-deriving a variant leaves hashes unchanged;
+//! only changing the tables below does. No step allocates.
+//!
+//! ## Encoding (version 2)
+//!
+//! Integers are big-endian `u64` unless noted; strings are their length in
+//! bytes followed by their UTF-8 text.
+//!
+//! - CFG: function id, name, node count, node records, edge count, edge
+//!   records. Node record: id, kind tag, then `0x00` for no AST node or
+//!   `0x01` and its id, then the block value flag (1 byte). Edge record:
+//!   from, to, kind tag.
+//! - DFG: function id, value count, value records, edge count, edge
+//!   records. Value record: id, kind (tag, then its fields in declaration
+//!   order, names resolved), occurrence count, then each occurrence's start
+//!   relative to the value's `source_range` and its length. Edge record:
+//!   from, to, kind tag.
+//!
+//! Tags (1 byte; 0 is never used):
+//!
+//! | Enum          | Tags                                                                  |
+//! |---------------|-----------------------------------------------------------------------|
+//! | `CFGNodeKind` | Entry 1, Exit 2, Statement 3, Branch 4, Merge 5, LoopHeader 6         |
+//! | `CFGEdgeKind` | Normal 1, True 2, False 3, Break 4, Continue 5                        |
+//! | `ValueKind`   | Variable 1 (name), Constant 2 (value), Parameter 3 (name, position), Temporary 4 |
+//! | `DFGEdgeKind` | Definition 1, Use 2, PhiLike 3                                        |
+//!
+//! Version 1 hashed each kind's `Debug` text. Its digests, recorded in
+//! snapshots before storage version 10 (`functions`,
+//! `semantic_fingerprints`), differ from version 2 for the same graph.
+
+use crate::semantic::model::{
+    CFGEdgeKind, CFGNodeKind, DFGEdgeKind, ResolvedValueKind, StringArena, CFG, DFG,
+};
+use sha2::{Digest, Sha256};
+
+/// Canonical bytes of a graph element (see the module docs)
+pub trait CanonicalHash {
+    /// Feed the encoding of `self` to `hasher`
+    fn canonical_hash(&self, hasher: &mut Sha256);
+}
+
+impl CanonicalHash for CFGNodeKind {
+    fn canonical_hash(&self, hasher: &mut Sha256) {
+        hasher.update([match self {
+            CFGNodeKind::Entry => 1,
+            CFGNodeKind::Exit => 2,
+            CFGNodeKind::Statement => 3,
+            CFGNodeKind::Branch => 4,
+            CFGNodeKind::Merge => 5,
+            CFGNodeKind::LoopHeader => 6,
+        }]);
+    }
+}
+
+impl CanonicalHash for CFGEdgeKind {
+    fn canonical_hash(&self, hasher: &mut Sha256) {
+        hasher.update([match self {
+            CFGEdgeKind::Normal => 1,
+            CFGEdgeKind::True => 2,
+            CFGEdgeKind::False => 3,
+            CFGEdgeKind::Break => 4,
+            CFGEdgeKind::Continue => 5,
+        }]);
+    }
+}
+
+impl CanonicalHash for ResolvedValueKind<'_> {
+    fn canonical_hash(&self, hasher: &mut Sha256) {
+        match self {
+            ResolvedValueKind::Variable { name } => {
+                hasher.update([1]);
+                name.canonical_hash(hasher);
+            }
+            ResolvedValueKind::Constant { value } => {
+                hasher.update([2]);
+                value.canonical_hash(hasher);
+            }
+            ResolvedValueKind::Parameter { name, position } => {
+                hasher.update([3]);
+                name.canonical_hash(hasher);
+                (*position as u64).canonical_hash(hasher);
+            }
+            ResolvedValueKind::Temporary => hasher.update([4]),
+        }
+    }
+}
+
+impl CanonicalHash for DFGEdgeKind {
+    fn canonical_hash(&self, hasher: &mut Sha256) {
+        hasher.update([match self {
+            DFGEdgeKind::Definition => 1,
+            DFGEdgeKind::Use => 2,
+            DFGEdgeKind::PhiLike => 3,
+        }]);
+    }
+}
+
+impl CanonicalHash for u64 {
+    fn canonical_hash(&self, hasher: &mut Sha256) {
+        hasher.update(self.to_be_bytes());
+    }
+}
+
+impl CanonicalHash for str {
+    fn canonical_hash(&self, hasher: &mut Sha256) {
+        (self.len() as u64).canonical_hash(hasher);
+        hasher.update(self.as_bytes());
+    }
+}
+
+impl CFG {
+    /// Compute hash for determinism testing
+    ///
+    /// Covers IDs, kinds, AST node IDs and the name; not positions or
+    /// statement text.
+    pub fn compute_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        self.function_id.0.canonical_hash(&mut hasher);
+        self.name.canonical_hash(&mut hasher);
+
+        (self.nodes.len() as u64).canonical_hash(&mut hasher);
+        for node in &self.nodes {
+            node.id.0.canonical_hash(&mut hasher);
+            node.kind.canonical_hash(&mut hasher);
+            match node.ast_node_id {
+                Some(ast_id) => {
+                    hasher.update([1]);
+                    (ast_id.0 as u64).canonical_hash(&mut hasher);
+                }
+                None => hasher.update([0]),
+            }
+            hasher.update([node.block_value as u8]);
+        }
+
+        (self.edges.len() as u64).canonical_hash(&mut hasher);
+        for edge in &self.edges {
+            edge.from.0.canonical_hash(&mut hasher);
+            edge.to.0.canonical_hash(&mut hasher);
+            edge.kind.canonical_hash(&mut hasher);
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+impl DFG {
+    /// Compute hash for determinism testing
+    ///
+    /// `strings` must be the arena the DFG's names were interned in. Covers
+    /// the resolved names, never their IDs, and occurrences relative to the
+    /// value's first, so not positions.
+    pub fn compute_hash(&self, strings: &StringArena) -> String {
+        let mut hasher = Sha256::new();
+        self.function_id.0.canonical_hash(&mut hasher);
+
+        (self.values.len() as u64).canonical_hash(&mut hasher);
+        for value in &self.values {
+            value.id.0.canonical_hash(&mut hasher);
+            value.kind.resolve(strings).canonical_hash(&mut hasher);
+            (value.occurrences.len() as u64).canonical_hash(&mut hasher);
+            for occurrence in &value.occurrences {
+                (occurrence.start.wrapping_sub(value.source_range.start) as u64).canonical_hash(&mut hasher);
+                (occurrence.len() as u64).canonical_hash(&mut hasher);
+            }
+        }
+
+        (self.edges.len() as u64).canonical_hash(&mut hasher);
+        for edge in &self.edges {
+            edge.from.0.canonical_hash(&mut hasher);
+            edge.to.0.canonical_hash(&mut hasher);
+            edge.kind.canonical_hash(&mut hasher);
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic::model::{CFGEdge, CFGNode, DFGEdge, DFGValue, FunctionId, NodeId, ValueId, ValueKind};
+    use crate::types::{AstNodeId, ByteRange, FileId};
+
+    /// A CFG with every node and edge kind
+    fn every_cfg_kind() -> CFG {
+        let kinds = [
+            CFGNodeKind::Entry, CFGNodeKind::Exit, CFGNodeKind::Statement,
+            CFGNodeKind::Branch, CFGNodeKind::Merge, CFGNodeKind::LoopHeader,
+        ];
+        let mut cfg = CFG::new(FunctionId(7), FileId::new(1), NodeId(0), NodeId(1)).with_name("f", ByteRange::new(3, 4));
+        for (i, kind) in kinds.into_iter().enumerate() {
+            cfg.add_node(CFGNode {
+                id: NodeId(i as u64),
+                kind,
+                source_range: ByteRange::new(i, i + 1),
+                statement: None,
+                ast_node_id: (i % 2 == 0).then_some(AstNodeId(i as u32 + 10)),
+                block_value: i == 2,
+                statement_chars: 0,
+            });
+        }
+        for (i, kind) in [CFGEdgeKind::Normal, CFGEdgeKind::True, CFGEdgeKind::False, CFGEdgeKind::Break, CFGEdgeKind::Continue]
+            .into_iter()
+            .enumerate()
+        {
+            cfg.add_edge(CFGEdge { from: NodeId(i as u64), to: NodeId(i as u64 + 1), kind });
+        }
+        cfg
+    }
+
+    /// A DFG with every value and edge kind
+    fn every_dfg_kind(strings: &mut StringArena) -> DFG {
+        let kinds = [
+            ValueKind::Variable { name: strings.intern("x") },
+            ValueKind::Constant { value: strings.intern("1") },
+            ValueKind::Parameter { name: strings.intern("p"), position: 2 },
+            ValueKind::Temporary,
+        ];
+        let mut dfg = DFG::new(FunctionId(7));
+        for (i, kind) in kinds.into_iter().enumerate() {
+            let source_range = ByteRange::new(10 * i, 10 * i + 1);
+            dfg.add_value(DFGValue {
+                id: ValueId(i as u64),
+                occurrences: if i == 1 { vec![source_range, ByteRange::new(40, 41)] } else { Vec::new() },
+                kind,
+                source_range,
+                origin: None,
+            });
+        }
+        for (i, kind) in [DFGEdgeKind::Definition, DFGEdgeKind::Use, DFGEdgeKind::PhiLike].into_iter().enumerate() {
+            dfg.add_edge(DFGEdge { from: ValueId(i as u64), to: ValueId(i as u64 + 1), kind });
+        }
+        dfg
+    }
+
+    #[test]
+    fn test_encoding_is_pinned() {
+        // Changing a tag or the framing changes these; bump the encoding
+        // version in the module docs (and the storage version) if so
+        let mut strings = StringArena::new();
+        assert_eq!(every_cfg_kind().compute_hash(), "4df3aa1540562f6fabed40ac890cd03beb0a12a9bf7748ba0976f9028fcd074e");
+        assert_eq!(every_dfg_kind(&mut strings).compute_hash(&strings), "dda6e41ed9a52de5094c12186fc504df76b6885d6d3302585e4b6e0530d961ce");
+    }
+
+    #[test]
+    fn test_hash_ignores_declaration_order() {
+        /// CFGEdgeKind declared back to front, tagged by the same table
+        #[derive(Clone, Copy)]
+        enum Reordered {
+            Continue,
+            Break,
+            False,
+            True,
+            Normal,
+        }
+
+        impl CanonicalHash for Reordered {
+            fn canonical_hash(&self, hasher: &mut Sha256) {
+                hasher.update([match self {
+                    Reordered::Normal => 1,
+                    Reordered::True => 2,
+                    Reordered::False => 3,
+                    Reordered::Break => 4,
+                    Reordered::Continue => 5,
+                }]);
+            }
+        }
+
+        let digest = |kinds: &[&dyn CanonicalHash]| {
+            let mut hasher = Sha256::new();
+            for kind in kinds {
+                kind.canonical_hash(&mut hasher);
+            }
+            hasher.finalize()
+        };
+        let ours = [CFGEdgeKind::Normal, CFGEdgeKind::True, CFGEdgeKind::False, CFGEdgeKind::Break, CFGEdgeKind::Continue];
+        let theirs = [Reordered::Normal, Reordered::True, Reordered::False, Reordered::Break, Reordered::Continue];
+
+        // Declaration discriminants differ, hashes do not
+        assert_ne!(ours[0] as u8, theirs[0] as u8);
+        assert_eq!(
+            digest(&ours.iter().map(|kind| kind as &dyn CanonicalHash).collect::<Vec<_>>()),
+            digest(&theirs.iter().map(|kind| kind as &dyn CanonicalHash).collect::<Vec<_>>()),
+        );
+    }
+
+    #[test]
+    fn test_fields_are_length_prefixed() {
+        let hash = |kinds: &[ResolvedValueKind]| {
+            let mut hasher = Sha256::new();
+            for kind in kinds {
+                kind.canonical_hash(&mut hasher);
+            }
+            hasher.finalize()
+        };
+        assert_ne!(hash(&[ResolvedValueKind::Variable { name: "x" }]), hash(&[ResolvedValueKind::Constant { value: "x" }]));
+
+        // Unframed, both would be the bytes 1, 'x', 4
+        assert_ne!(
+            hash(&[ResolvedValueKind::Variable { name: "x" }, ResolvedValueKind::Temporary]),
+            hash(&[ResolvedValueKind::Variable { name: "x\u{4}" }]),
+        );
+    }
+}
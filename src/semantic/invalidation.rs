@@ -5,14 +5,22 @@
 //! Tracks dependencies between:
 //! - AST byte ranges → CFG nodes
 //! - CFG nodes → DFG edges  
-//! - DFG edges → dependent facts
+//! - CFG nodes → CPG nodes (populated by CPGBuilder)
+//! - DFG values → CPG nodes (populated by CPGBuilder)
 //!
 //! Enables precise incremental updates:
-//! When AST changes, we can determine exactly which semantic facts to rebuild.
+//! When AST changes, we can determine exactly which semantic facts to rebuild,
+//! and which part of the fused graph they produced.
+//!
+//! DFGs are built per function (ValueIds restart at 0 in each), so DFG value
+//! CPG nodes are invalidated per function: any invalidated CFG node
+//! invalidates every DFG value CPG node of its function.
 
-use crate::semantic::model::{EdgeId, NodeId};
-use crate::types::ByteRange;
+use crate::cpg::model::CPGNodeId;
+use crate::semantic::model::{EdgeId, FunctionId, NodeId, ValueId};
+use crate::types::{ByteRange, FileId};
 use std::collections::HashMap;
+use std::sync::OnceLock;
 
 /// Invalidation result - what needs to be rebuilt
 #[derive(Debug, Clone)]
@@ -22,6 +30,9 @@ pub struct InvalidationSet {
     
     /// DFG edges that need rebuilding
     pub dfg_edges: Vec<EdgeId>,
+    
+    /// CPG nodes fused from invalidated CFG nodes and DFG values
+    pub cpg_nodes: Vec<CPGNodeId>,
 }
 
 impl InvalidationSet {
@@ -30,25 +41,45 @@ impl InvalidationSet {
         Self {
             cfg_nodes: Vec::new(),
             dfg_edges: Vec::new(),
+            cpg_nodes: Vec::new(),
         }
     }
 
     /// Check if anything needs invalidation
     pub fn is_empty(&self) -> bool {
-        self.cfg_nodes.is_empty() && self.dfg_edges.is_empty()
+        self.cfg_nodes.is_empty() && self.dfg_edges.is_empty() && self.cpg_nodes.is_empty()
+    }
+}
+
+impl Default for InvalidationSet {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 /// Tracks dependencies for incremental updates
 ///
+/// Everything is keyed by FileId: byte ranges and CFG node IDs are only
+/// unique within one file.
+///
 /// **Determinism guarantee:** All lookups are deterministic.
 /// HashMaps used only for fast lookup, not iteration order.
+#[derive(Clone)]
 pub struct InvalidationTracker {
-    /// AST byte range → CFG nodes affected by that range
-    ast_to_cfg: HashMap<ByteRange, Vec<NodeId>>,
+    /// AST byte ranges → CFG nodes affected by that range, per file
+    ast_to_cfg: HashMap<FileId, RangeIndex>,
     
     /// CFG node → DFG edges that depend on it
-    cfg_to_dfg: HashMap<NodeId, Vec<EdgeId>>,
+    cfg_to_dfg: HashMap<(FileId, NodeId), Vec<EdgeId>>,
+    
+    /// CFG node → CPG nodes fused from it
+    cfg_to_cpg: HashMap<(FileId, NodeId), Vec<CPGNodeId>>,
+    
+    /// CFG node → function it belongs to
+    cfg_function: HashMap<(FileId, NodeId), FunctionId>,
+    
+    /// Function → CPG nodes fused from its DFG values
+    dfg_to_cpg: HashMap<(FileId, FunctionId), Vec<(ValueId, CPGNodeId)>>,
 }
 
 impl InvalidationTracker {
@@ -57,46 +88,84 @@ impl InvalidationTracker {
         Self {
             ast_to_cfg: HashMap::new(),
             cfg_to_dfg: HashMap::new(),
+            cfg_to_cpg: HashMap::new(),
+            cfg_function: HashMap::new(),
+            dfg_to_cpg: HashMap::new(),
         }
     }
 
     /// Register that a CFG node depends on an AST range
-    pub fn track_ast_to_cfg(&mut self, range: ByteRange, node: NodeId) {
+    pub fn track_ast_to_cfg(&mut self, file_id: FileId, range: ByteRange, node: NodeId) {
         self.ast_to_cfg
-            .entry(range)
-            .or_insert_with(Vec::new)
-            .push(node);
+            .entry(file_id)
+            .or_default()
+            .insert(range, node);
     }
 
     /// Register that a DFG edge depends on a CFG node
-    pub fn track_cfg_to_dfg(&mut self, node: NodeId, edge: EdgeId) {
+    pub fn track_cfg_to_dfg(&mut self, file_id: FileId, node: NodeId, edge: EdgeId) {
         self.cfg_to_dfg
-            .entry(node)
-            .or_insert_with(Vec::new)
+            .entry((file_id, node))
+            .or_default()
             .push(edge);
     }
 
-    /// Determine what to invalidate given changed AST ranges
+    /// Register that a CPG node was fused from a CFG node of `function_id`
+    pub fn track_cfg_to_cpg(&mut self, file_id: FileId, function_id: FunctionId, node: NodeId, cpg_node: CPGNodeId) {
+        self.cfg_function.insert((file_id, node), function_id);
+        self.cfg_to_cpg
+            .entry((file_id, node))
+            .or_default()
+            .push(cpg_node);
+    }
+
+    /// Register that a CPG node was fused from a DFG value of `function_id`
+    pub fn track_dfg_to_cpg(&mut self, file_id: FileId, function_id: FunctionId, value: ValueId, cpg_node: CPGNodeId) {
+        self.dfg_to_cpg
+            .entry((file_id, function_id))
+            .or_default()
+            .push((value, cpg_node));
+    }
+
+    /// Forget every CPG mapping (before re-fusing the whole graph)
+    pub fn clear_cpg(&mut self) {
+        self.cfg_to_cpg.clear();
+        self.cfg_function.clear();
+        self.dfg_to_cpg.clear();
+    }
+
+    /// Copy one file's AST → CFG → DFG dependencies from another tracker
+    ///
+    /// CPG mappings are not copied: they are rebuilt by the next fusion.
+    pub fn carry_over(&mut self, previous: &InvalidationTracker, file_id: FileId) {
+        if let Some(index) = previous.ast_to_cfg.get(&file_id) {
+            self.ast_to_cfg.insert(file_id, index.clone());
+        }
+        for (key, edges) in &previous.cfg_to_dfg {
+            if key.0 == file_id {
+                self.cfg_to_dfg.insert(*key, edges.clone());
+            }
+        }
+    }
+
+    /// Determine what to invalidate given changed AST ranges in one file
     ///
     /// **Algorithm:**
-    /// 1. Find all CFG nodes overlapping changed ranges
+    /// 1. Find all CFG nodes overlapping changed ranges (interval lookup)
     /// 2. Find all DFG edges depending on those nodes
-    /// 3. Return invalidation set
-    pub fn invalidate(&self, changed_ranges: &[ByteRange]) -> InvalidationSet {
+    /// 3. Find CPG nodes fused from those CFG nodes and from the DFG values
+    ///    of their functions
+    /// 4. Return invalidation set
+    ///
+    /// A tracked range is hit if it overlaps a changed range or equals it
+    /// (so empty ranges are matched exactly).
+    pub fn invalidate(&self, file_id: FileId, changed_ranges: &[ByteRange]) -> InvalidationSet {
         let mut result = InvalidationSet::new();
 
         // Step 1: Find affected CFG nodes
-        for changed_range in changed_ranges {
-            // Check for exact matches
-            if let Some(nodes) = self.ast_to_cfg.get(changed_range) {
-                result.cfg_nodes.extend(nodes);
-            }
-
-            // Check for overlaps (conservative)
-            for (range, nodes) in &self.ast_to_cfg {
-                if ranges_overlap(*range, *changed_range) {
-                    result.cfg_nodes.extend(nodes);
-                }
+        if let Some(index) = self.ast_to_cfg.get(&file_id) {
+            for changed_range in changed_ranges {
+                index.for_each_hit(*changed_range, |nodes| result.cfg_nodes.extend(nodes));
             }
         }
 
@@ -106,7 +175,7 @@ impl InvalidationTracker {
 
         // Step 2: Propagate to DFG
         for &node_id in &result.cfg_nodes {
-            if let Some(edges) = self.cfg_to_dfg.get(&node_id) {
+            if let Some(edges) = self.cfg_to_dfg.get(&(file_id, node_id)) {
                 result.dfg_edges.extend(edges);
             }
         }
@@ -115,19 +184,152 @@ impl InvalidationTracker {
         result.dfg_edges.sort();
         result.dfg_edges.dedup();
 
+        // Step 3: Propagate to CPG
+        let mut functions: Vec<FunctionId> = Vec::new();
+        for &node_id in &result.cfg_nodes {
+            if let Some(cpg_nodes) = self.cfg_to_cpg.get(&(file_id, node_id)) {
+                result.cpg_nodes.extend(cpg_nodes);
+            }
+            functions.extend(self.cfg_function.get(&(file_id, node_id)).copied());
+        }
+        functions.sort();
+        functions.dedup();
+        for function_id in functions {
+            if let Some(values) = self.dfg_to_cpg.get(&(file_id, function_id)) {
+                result.cpg_nodes.extend(values.iter().map(|(_, cpg_node)| cpg_node));
+            }
+        }
+
+        // Deduplicate
+        result.cpg_nodes.sort();
+        result.cpg_nodes.dedup();
+
         result
     }
 
     /// Get statistics for debugging
     pub fn stats(&self) -> InvalidationStats {
         InvalidationStats {
-            ast_ranges: self.ast_to_cfg.len(),
-            cfg_nodes: self.ast_to_cfg.values().map(|v| v.len()).sum(),
+            ast_ranges: self.ast_to_cfg.values().map(|index| index.entries.len()).sum(),
+            cfg_nodes: self.ast_to_cfg.values()
+                .flat_map(|index| &index.entries)
+                .map(|(_, nodes)| nodes.len())
+                .sum(),
             dfg_edges: self.cfg_to_dfg.values().map(|v| v.len()).sum(),
+            cpg_nodes: self.cfg_to_cpg.values().map(|v| v.len()).sum::<usize>()
+                + self.dfg_to_cpg.values().map(|v| v.len()).sum::<usize>(),
         }
     }
 }
 
+impl Default for InvalidationTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Byte ranges of one file, with an interval index for overlap lookup
+///
+/// Ranges are appended in tracking order; the index is built on the first
+/// lookup after a change and dropped by the next insert.
+#[derive(Clone, Default)]
+struct RangeIndex {
+    /// (range, nodes) in first-tracked order
+    entries: Vec<(ByteRange, Vec<NodeId>)>,
+
+    /// Range → position in `entries`
+    positions: HashMap<ByteRange, usize>,
+
+    /// Lazily built interval tree
+    tree: OnceLock<IntervalTree>,
+}
+
+impl RangeIndex {
+    fn insert(&mut self, range: ByteRange, node: NodeId) {
+        let next = self.entries.len();
+        let position = *self.positions.entry(range).or_insert(next);
+        if position == next {
+            self.entries.push((range, Vec::new()));
+        }
+        self.entries[position].1.push(node);
+        self.tree.take();
+    }
+
+    /// Call `hit` with the nodes of every range hit by `changed`
+    fn for_each_hit(&self, changed: ByteRange, mut hit: impl FnMut(&[NodeId])) {
+        let tree = self.tree.get_or_init(|| IntervalTree::build(&self.entries));
+        tree.query(0, tree.order.len(), &self.entries, changed, &mut hit);
+    }
+}
+
+/// Implicit interval tree over entries sorted by (start, end)
+///
+/// The subtree over `order[lo..hi]` is rooted at `mid = (lo + hi) / 2`;
+/// `max_end[mid]` is the largest end in that subtree. Lookup is
+/// O(log n + hits).
+#[derive(Clone)]
+struct IntervalTree {
+    /// Entry positions sorted by (start, end)
+    order: Vec<usize>,
+
+    /// Largest end in the subtree rooted at each slot
+    max_end: Vec<usize>,
+}
+
+impl IntervalTree {
+    fn build(entries: &[(ByteRange, Vec<NodeId>)]) -> Self {
+        let mut order: Vec<usize> = (0..entries.len()).collect();
+        order.sort_by_key(|&i| (entries[i].0.start, entries[i].0.end));
+
+        let mut tree = Self { max_end: vec![0; order.len()], order };
+        tree.fill_max_end(0, entries.len(), entries);
+        tree
+    }
+
+    fn fill_max_end(&mut self, lo: usize, hi: usize, entries: &[(ByteRange, Vec<NodeId>)]) -> usize {
+        if lo >= hi {
+            return 0;
+        }
+        let mid = (lo + hi) / 2;
+        let left = self.fill_max_end(lo, mid, entries);
+        let right = self.fill_max_end(mid + 1, hi, entries);
+        self.max_end[mid] = entries[self.order[mid]].0.end.max(left).max(right);
+        self.max_end[mid]
+    }
+
+    fn query(
+        &self,
+        lo: usize,
+        hi: usize,
+        entries: &[(ByteRange, Vec<NodeId>)],
+        changed: ByteRange,
+        hit: &mut impl FnMut(&[NodeId]),
+    ) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+
+        // Every range in this subtree ends before the change
+        if self.max_end[mid] < changed.start {
+            return;
+        }
+
+        self.query(lo, mid, entries, changed, hit);
+
+        // This range and everything to its right start after the change
+        let (range, nodes) = &entries[self.order[mid]];
+        if range.start > changed.end {
+            return;
+        }
+
+        if ranges_overlap(*range, changed) || *range == changed {
+            hit(nodes);
+        }
+        self.query(mid + 1, hi, entries, changed, hit);
+    }
+}
+
 /// Statistics about invalidation tracking
 #[derive(Debug, Clone)]
 pub struct InvalidationStats {
@@ -139,6 +341,9 @@ pub struct InvalidationStats {
     
     /// Total DFG edges tracked
     pub dfg_edges: usize,
+    
+    /// Total CPG nodes tracked (from CFG nodes and DFG values)
+    pub cpg_nodes: usize,
 }
 
 /// Check if two byte ranges overlap
@@ -150,6 +355,37 @@ fn ranges_overlap(a: ByteRange, b: ByteRange) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, Instant};
+
+    const FILE: FileId = FileId::new(1);
+
+    /// Deterministic xorshift, no external RNG
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+
+        fn range(&mut self, len: usize, max_width: usize) -> ByteRange {
+            let start = self.next(len);
+            ByteRange::new(start, start + self.next(max_width))
+        }
+    }
+
+    /// The pre-index implementation: compare every tracked range
+    fn brute_force(tracked: &[(ByteRange, NodeId)], changed: &[ByteRange]) -> Vec<NodeId> {
+        let mut nodes: Vec<NodeId> = tracked.iter()
+            .filter(|(range, _)| changed.iter().any(|c| ranges_overlap(*range, *c) || range == c))
+            .map(|(_, node)| *node)
+            .collect();
+        nodes.sort();
+        nodes.dedup();
+        nodes
+    }
 
     #[test]
     fn test_invalidation_tracking() {
@@ -159,15 +395,15 @@ mod tests {
         let range1 = ByteRange::new(0, 10);
         let range2 = ByteRange::new(20, 30);
         
-        tracker.track_ast_to_cfg(range1, NodeId(1));
-        tracker.track_ast_to_cfg(range1, NodeId(2));
-        tracker.track_ast_to_cfg(range2, NodeId(3));
+        tracker.track_ast_to_cfg(FILE, range1, NodeId(1));
+        tracker.track_ast_to_cfg(FILE, range1, NodeId(2));
+        tracker.track_ast_to_cfg(FILE, range2, NodeId(3));
 
-        tracker.track_cfg_to_dfg(NodeId(1), EdgeId(10));
-        tracker.track_cfg_to_dfg(NodeId(2), EdgeId(11));
+        tracker.track_cfg_to_dfg(FILE, NodeId(1), EdgeId(10));
+        tracker.track_cfg_to_dfg(FILE, NodeId(2), EdgeId(11));
 
         // Change range1 → should invalidate nodes 1, 2 and edges 10, 11
-        let inv = tracker.invalidate(&[range1]);
+        let inv = tracker.invalidate(FILE, &[range1]);
         
         assert!(inv.cfg_nodes.contains(&NodeId(1)));
         assert!(inv.cfg_nodes.contains(&NodeId(2)));
@@ -198,7 +434,7 @@ mod tests {
     #[test]
     fn test_empty_invalidation() {
         let tracker = InvalidationTracker::new();
-        let inv = tracker.invalidate(&[ByteRange::new(0, 10)]);
+        let inv = tracker.invalidate(FILE, &[ByteRange::new(0, 10)]);
         
         assert!(inv.is_empty());
     }
@@ -207,13 +443,138 @@ mod tests {
     fn test_stats() {
         let mut tracker = InvalidationTracker::new();
         
-        tracker.track_ast_to_cfg(ByteRange::new(0, 10), NodeId(1));
-        tracker.track_ast_to_cfg(ByteRange::new(0, 10), NodeId(2));
-        tracker.track_cfg_to_dfg(NodeId(1), EdgeId(10));
+        tracker.track_ast_to_cfg(FILE, ByteRange::new(0, 10), NodeId(1));
+        tracker.track_ast_to_cfg(FILE, ByteRange::new(0, 10), NodeId(2));
+        tracker.track_cfg_to_dfg(FILE, NodeId(1), EdgeId(10));
+
+        tracker.track_cfg_to_cpg(FILE, FunctionId(0), NodeId(1), CPGNodeId(5));
+        tracker.track_dfg_to_cpg(FILE, FunctionId(0), ValueId(0), CPGNodeId(6));
 
         let stats = tracker.stats();
         assert_eq!(stats.ast_ranges, 1);
         assert_eq!(stats.cfg_nodes, 2);
         assert_eq!(stats.dfg_edges, 1);
+        assert_eq!(stats.cpg_nodes, 2);
+
+        tracker.clear_cpg();
+        assert_eq!(tracker.stats().cpg_nodes, 0);
+        assert_eq!(tracker.stats().cfg_nodes, 2);
+    }
+
+    #[test]
+    fn test_cpg_propagation() {
+        let mut tracker = InvalidationTracker::new();
+
+        // f0: nodes 0 (entry, 0..50), 1 (10..20); f1: nodes 2 (entry, 60..90), 3 (70..80)
+        for (range, node) in [((0, 50), 0), ((10, 20), 1), ((60, 90), 2), ((70, 80), 3)] {
+            tracker.track_ast_to_cfg(FILE, ByteRange::new(range.0, range.1), NodeId(node));
+        }
+        for (function, node, cpg) in [(0, 0, 100), (0, 0, 101), (0, 1, 102), (1, 2, 200), (1, 2, 201), (1, 3, 202)] {
+            tracker.track_cfg_to_cpg(FILE, FunctionId(function), NodeId(node), CPGNodeId(cpg));
+        }
+        for (function, value, cpg) in [(0, 0, 110), (0, 1, 111), (1, 0, 210)] {
+            tracker.track_dfg_to_cpg(FILE, FunctionId(function), ValueId(value), CPGNodeId(cpg));
+        }
+
+        // Only the entry of f0 is hit: its CPG nodes plus all of f0's DFG values
+        let inv = tracker.invalidate(FILE, &[ByteRange::new(30, 31)]);
+        assert_eq!(inv.cfg_nodes, vec![NodeId(0)]);
+        assert_eq!(inv.cpg_nodes, vec![CPGNodeId(100), CPGNodeId(101), CPGNodeId(110), CPGNodeId(111)]);
+
+        let inv = tracker.invalidate(FILE, &[ByteRange::new(75, 76)]);
+        assert_eq!(inv.cpg_nodes, vec![CPGNodeId(200), CPGNodeId(201), CPGNodeId(202), CPGNodeId(210)]);
+
+        // Another file with the same IDs is unaffected
+        assert!(tracker.invalidate(FileId::new(2), &[ByteRange::new(75, 76)]).is_empty());
+    }
+
+    #[test]
+    fn test_files_do_not_collide() {
+        let mut tracker = InvalidationTracker::new();
+        let other = FileId::new(2);
+
+        tracker.track_ast_to_cfg(FILE, ByteRange::new(0, 10), NodeId(1));
+        tracker.track_ast_to_cfg(other, ByteRange::new(0, 10), NodeId(7));
+        tracker.track_cfg_to_dfg(FILE, NodeId(1), EdgeId(10));
+        tracker.track_cfg_to_dfg(other, NodeId(1), EdgeId(99));
+
+        let inv = tracker.invalidate(FILE, &[ByteRange::new(5, 6)]);
+        assert_eq!(inv.cfg_nodes, vec![NodeId(1)]);
+        assert_eq!(inv.dfg_edges, vec![EdgeId(10)]);
+
+        assert!(tracker.invalidate(FileId::new(3), &[ByteRange::new(5, 6)]).is_empty());
+    }
+
+    #[test]
+    fn test_empty_ranges_match_exactly() {
+        let mut tracker = InvalidationTracker::new();
+        tracker.track_ast_to_cfg(FILE, ByteRange::new(5, 5), NodeId(1));
+        tracker.track_ast_to_cfg(FILE, ByteRange::new(0, 10), NodeId(2));
+
+        assert_eq!(tracker.invalidate(FILE, &[ByteRange::new(5, 5)]).cfg_nodes, vec![NodeId(1), NodeId(2)]);
+        assert_eq!(tracker.invalidate(FILE, &[ByteRange::new(10, 10)]).cfg_nodes, vec![]);
+    }
+
+    #[test]
+    fn test_index_matches_brute_force() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+        for round in 0..50 {
+            let mut tracker = InvalidationTracker::new();
+            let mut tracked = Vec::new();
+            for i in 0..(round * 20 + 1) {
+                // Mix of short statements, long functions and empty ranges
+                let range = rng.range(2000, if i % 10 == 0 { 800 } else { 40 });
+                let node = NodeId(rng.next(500) as u64);
+                tracker.track_ast_to_cfg(FILE, range, node);
+                tracked.push((range, node));
+            }
+
+            for _ in 0..20 {
+                let changed: Vec<ByteRange> = (0..rng.next(4)).map(|_| rng.range(2100, 60)).collect();
+                assert_eq!(
+                    tracker.invalidate(FILE, &changed).cfg_nodes,
+                    brute_force(&tracked, &changed),
+                    "round {} changed {:?}",
+                    round,
+                    changed
+                );
+            }
+
+            // Inserting after a lookup rebuilds the index
+            let late = rng.range(2000, 40);
+            tracker.track_ast_to_cfg(FILE, late, NodeId(1000));
+            tracked.push((late, NodeId(1000)));
+            assert_eq!(tracker.invalidate(FILE, &[late]).cfg_nodes, brute_force(&tracked, &[late]));
+        }
+    }
+
+    #[test]
+    fn test_invalidation_with_50k_ranges_is_sub_millisecond() {
+        let mut tracker = InvalidationTracker::new();
+        let mut rng = Rng(42);
+
+        // 500 functions of 100 statements each, nested inside a whole-file range
+        tracker.track_ast_to_cfg(FILE, ByteRange::new(0, 50_000 * 20), NodeId(0));
+        for i in 1..50_000u64 {
+            let start = i as usize * 20;
+            let range = if i % 100 == 0 { ByteRange::new(start, start + 2000) } else { ByteRange::new(start, start + 15) };
+            tracker.track_ast_to_cfg(FILE, range, NodeId(i));
+        }
+        tracker.invalidate(FILE, &[]); // build the index
+
+        let changed: Vec<ByteRange> = (0..4).map(|_| rng.range(1_000_000, 30)).collect();
+        let fastest = (0..10)
+            .map(|_| {
+                let start = Instant::now();
+                let inv = tracker.invalidate(FILE, &changed);
+                let elapsed = start.elapsed();
+                assert!(inv.cfg_nodes.contains(&NodeId(0)));
+                elapsed
+            })
+            .min()
+            .unwrap();
+
+        assert!(fastest < Duration::from_millis(1), "invalidate took {:?}", fastest);
     }
 }
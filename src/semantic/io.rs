@@ -0,0 +1,301 @@
+//! Standalone CFG/DFG files
+//!
+//! Persists the graphs of some functions without the rest of the CPG, for
+//! lightweight per-function analysis and for tools outside this crate.
+//!
+//! A graph file is JSON:
+//!
+//! ```json
+//! {
+//!   "header": {"kind": "cfg", "version": 1, "tool_version": "0.1.0", "functions": 2},
+//!   "strings": ["let a = 1;", "a"],
+//!   "functions": [{"function_id": 0, ...}, {"function_id": 1, ...}]
+//! }
+//! ```
+//!
+//! `functions` holds one record per function in FunctionId order. Graphs
+//! refer to text by StringId, so the file carries its own string table
+//! with only the strings its graphs use. Loading interns them into the
+//! caller's arena and rewrites the IDs, so `compute_hash` is unchanged.
+//!
+//! **Versioned**: The header is read first. A file from a newer version, or
+//! of the other graph kind, fails cleanly before its graphs are parsed.
+//!
+//! **Atomic**: Files are written to a temporary path and renamed into
+//! place, like the snapshot store's index.
+
+use crate::memory::arena::{StringArena, StringId};
+use crate::semantic::model::{FunctionId, CFG, DFG};
+use crate::storage::store::write_atomic;
+use crate::storage::TOOL_VERSION;
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Graph file version
+pub const GRAPH_FILE_VERSION: u32 = 1;
+
+/// Which graphs a file holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphKind {
+    Cfg,
+    Dfg,
+}
+
+/// First field of every graph file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphFileHeader {
+    pub kind: GraphKind,
+    pub version: u32,
+
+    /// Version of the tool that wrote the file
+    pub tool_version: String,
+
+    /// Number of function records
+    pub functions: usize,
+}
+
+/// A graph that can be stored in a graph file
+pub trait PersistedGraph: Clone + Serialize + DeserializeOwned {
+    const KIND: GraphKind;
+
+    fn function_id(&self) -> FunctionId;
+
+    /// Rewrite every StringId (moving the graph to another arena)
+    fn remap_string_ids(&mut self, remap: &mut dyn FnMut(StringId) -> StringId);
+}
+
+impl PersistedGraph for CFG {
+    const KIND: GraphKind = GraphKind::Cfg;
+
+    fn function_id(&self) -> FunctionId {
+        self.function_id
+    }
+
+    fn remap_string_ids(&mut self, remap: &mut dyn FnMut(StringId) -> StringId) {
+        self.remap_strings(remap);
+    }
+}
+
+impl PersistedGraph for DFG {
+    const KIND: GraphKind = GraphKind::Dfg;
+
+    fn function_id(&self) -> FunctionId {
+        self.function_id
+    }
+
+    fn remap_string_ids(&mut self, remap: &mut dyn FnMut(StringId) -> StringId) {
+        self.remap_strings(remap);
+    }
+}
+
+/// Contents of a graph file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphFile<G> {
+    pub header: GraphFileHeader,
+
+    /// Text the graphs' StringIds refer to
+    pub strings: StringArena,
+
+    /// One record per function, in FunctionId order
+    pub functions: Vec<G>,
+}
+
+/// Just the header, parsed before anything else
+#[derive(Deserialize)]
+struct HeaderOnly {
+    header: GraphFileHeader,
+}
+
+impl<G: PersistedGraph> GraphFile<G> {
+    /// Copy `graphs` (whose text is in `strings`) into a file in FunctionId order
+    pub fn new(graphs: &[G], strings: &StringArena) -> Self {
+        let mut functions = graphs.to_vec();
+        functions.sort_by_key(PersistedGraph::function_id);
+
+        let mut local = StringArena::new();
+        for graph in &mut functions {
+            graph.remap_string_ids(&mut |id| local.intern(strings.resolve(id)));
+        }
+
+        Self {
+            header: GraphFileHeader {
+                kind: G::KIND,
+                version: GRAPH_FILE_VERSION,
+                tool_version: TOOL_VERSION.to_string(),
+                functions: functions.len(),
+            },
+            strings: local,
+            functions,
+        }
+    }
+
+    /// Parse a graph file, checking its header first
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        let HeaderOnly { header } = serde_json::from_slice(bytes).context("Failed to parse graph file header")?;
+        if header.version > GRAPH_FILE_VERSION {
+            bail!(
+                "Graph file version {} is newer than this build supports ({}); written by {}",
+                header.version,
+                GRAPH_FILE_VERSION,
+                header.tool_version
+            );
+        }
+        if header.kind != G::KIND {
+            bail!("Graph file holds {:?} graphs, expected {:?}", header.kind, G::KIND);
+        }
+
+        let file: Self = serde_json::from_slice(bytes).context("Failed to parse graph file")?;
+        if file.functions.len() != file.header.functions {
+            bail!(
+                "Graph file header lists {} function(s) but holds {}",
+                file.header.functions,
+                file.functions.len()
+            );
+        }
+        Ok(file)
+    }
+
+    /// The graphs, with their text interned into `strings`
+    ///
+    /// Errors if a graph refers to a string the file does not have.
+    pub fn into_graphs(self, strings: &mut StringArena) -> Result<Vec<G>> {
+        let Self { strings: local, mut functions, .. } = self;
+        let mut dangling = None;
+        for graph in &mut functions {
+            graph.remap_string_ids(&mut |id| {
+                if (id.0 as usize) < local.len() {
+                    strings.intern(local.resolve(id))
+                } else {
+                    dangling = Some(id);
+                    id
+                }
+            });
+        }
+
+        match dangling {
+            Some(id) => bail!("Graph file refers to string {} but has {} string(s)", id.0, local.len()),
+            None => Ok(functions),
+        }
+    }
+}
+
+/// Write CFGs to `path` (see module docs)
+pub fn save_cfgs(cfgs: &[CFG], strings: &StringArena, path: &Path) -> Result<()> {
+    save(&GraphFile::new(cfgs, strings), path)
+}
+
+/// Read CFGs from `path`, interning their text into `strings`
+pub fn load_cfgs(path: &Path, strings: &mut StringArena) -> Result<Vec<CFG>> {
+    load::<CFG>(path)?.into_graphs(strings)
+}
+
+/// Write DFGs to `path` (see module docs)
+pub fn save_dfgs(dfgs: &[DFG], strings: &StringArena, path: &Path) -> Result<()> {
+    save(&GraphFile::new(dfgs, strings), path)
+}
+
+/// Read DFGs from `path`, interning their text into `strings`
+pub fn load_dfgs(path: &Path, strings: &mut StringArena) -> Result<Vec<DFG>> {
+    load::<DFG>(path)?.into_graphs(strings)
+}
+
+fn save<G: PersistedGraph>(file: &GraphFile<G>, path: &Path) -> Result<()> {
+    let serialized = serde_json::to_vec(file)?;
+    write_atomic(path, &serialized).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn load<G: PersistedGraph>(path: &Path) -> Result<GraphFile<G>> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    GraphFile::from_slice(&bytes).with_context(|| format!("Invalid graph file {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{Pipeline, PipelineOutput};
+    use crate::semantic::SemanticEpoch;
+    use crate::types::FileId;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    /// `tests/golden/calls`: src/main.rs and src/util.rs
+    fn calls_fixture() -> PipelineOutput {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/calls");
+        Pipeline::default().run(&root).unwrap()
+    }
+
+    /// The file with the most functions
+    fn busiest_file(semantic: &SemanticEpoch, output: &PipelineOutput) -> FileId {
+        *output.snapshot.files.keys()
+            .max_by_key(|id| semantic.get_cfgs(**id).map_or(0, Vec::len))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cfg_round_trip_preserves_hashes() {
+        let output = calls_fixture();
+        let semantic = &output.semantic;
+        let cfgs = semantic.get_cfgs(busiest_file(semantic, &output)).unwrap();
+        assert!(cfgs.len() > 1);
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cfgs.json");
+
+        // Reversed input still lands in FunctionId order
+        let reversed: Vec<CFG> = cfgs.iter().rev().cloned().collect();
+        save_cfgs(&reversed, semantic.strings(), &path).unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let mut strings = StringArena::new();
+        strings.intern("shifts every loaded StringId");
+        let loaded = load_cfgs(&path, &mut strings).unwrap();
+
+        assert_eq!(loaded.len(), cfgs.len());
+        for (original, loaded) in cfgs.iter().zip(&loaded) {
+            assert_eq!(loaded.function_id, original.function_id);
+            assert_eq!(loaded.compute_hash(), original.compute_hash());
+            let text = |cfg: &CFG, strings: &StringArena| -> Vec<Option<String>> {
+                cfg.nodes.iter().map(|n| n.statement.map(|id| strings.resolve(id).to_string())).collect()
+            };
+            assert_eq!(text(loaded, &strings), text(original, semantic.strings()));
+        }
+    }
+
+    #[test]
+    fn test_dfg_round_trip_preserves_hashes() {
+        let output = calls_fixture();
+        let semantic = &output.semantic;
+        let dfgs = semantic.get_dfgs(busiest_file(semantic, &output)).unwrap();
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("dfgs.json");
+
+        save_dfgs(dfgs, semantic.strings(), &path).unwrap();
+        let mut strings = StringArena::new();
+        strings.intern("shifts every loaded StringId");
+        let loaded = load_dfgs(&path, &mut strings).unwrap();
+
+        assert_eq!(loaded.len(), dfgs.len());
+        for (original, loaded) in dfgs.iter().zip(&loaded) {
+            assert_eq!(loaded.compute_hash(&strings), original.compute_hash(semantic.strings()));
+        }
+        assert!(load_cfgs(&path, &mut strings).unwrap_err().to_string().contains("Invalid graph file"));
+    }
+
+    #[test]
+    fn test_future_version_fails_cleanly() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cfgs.json");
+        save_cfgs(&[], &StringArena::new(), &path).unwrap();
+
+        // A future layout need not parse as today's graphs
+        let mut file: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        file["header"]["version"] = (GRAPH_FILE_VERSION + 1).into();
+        file["functions"] = serde_json::json!({"layout": "unknown"});
+        std::fs::write(&path, file.to_string()).unwrap();
+
+        let err = load_cfgs(&path, &mut StringArena::new()).unwrap_err();
+        assert!(format!("{:#}", err).contains("is newer than this build supports"), "{:#}", err);
+    }
+}
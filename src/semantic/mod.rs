@@ -13,7 +13,11 @@ pub mod epoch;
 pub mod cfg;
 pub mod dfg;
 pub mod symbols;
+pub mod profile;
 pub mod invalidation;
+pub mod report;
+pub mod io;
+pub mod hash;
 
 // Re-export public API
 pub use model::{
@@ -22,8 +26,11 @@ pub use model::{
     FunctionId, NodeId, ValueId, EdgeId, SymbolId, ScopeId,
 };
 
-pub use epoch::SemanticEpoch;
+pub use epoch::{SemanticEpoch, SemanticEpochBuilder, SemanticStatus};
 pub use cfg::CFGBuilder;
 pub use dfg::DFGBuilder;
 pub use symbols::SymbolTable;
+pub use profile::{profile_for, LanguageProfile, RustProfile, StatementClass};
 pub use invalidation::InvalidationTracker;
+pub use hash::CanonicalHash;
+pub use report::{FileSemanticReport, FunctionHashes, SemanticEpochReport};
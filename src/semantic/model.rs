@@ -8,10 +8,34 @@
 //! - Stable identifiers
 //!
 //! All collections use Vec for deterministic ordering.
+//!
+//! Statement text and value names are `StringId`s into the owning
+//! SemanticEpoch's StringArena. Hashes cover the resolved text, never IDs
+//! (see `semantic::hash` for `compute_hash`).
 
-use crate::types::{ByteRange, FileId};
+pub use crate::memory::arena::{StringArena, StringId};
+use crate::types::{AstNodeId, ByteRange, FileId};
 use serde::{Deserialize, Serialize};
 
+/// Serialized CFG schema version
+///
+/// - 1: original schema
+/// - 2: `CFGNode::ast_node_id`
+/// - 3: `CFGNode::statement` is a StringId into the epoch's StringArena
+/// - 4: `CFG::name` and `CFG::signature_range`
+/// - 5: `CFGNode::block_value`
+/// - 6: `CFGNode::statement_chars`; statement text keeps token separation
+///   and ends in "…" when truncated
+pub const CFG_SCHEMA_VERSION: u32 = 6;
+
+/// Serialized DFG schema version
+///
+/// - 1: original schema
+/// - 2: `DFGValue::origin`
+/// - 3: `DFGValue::occurrences`; one Constant per literal text, numbered
+///   after every other value
+pub const DFG_SCHEMA_VERSION: u32 = 3;
+
 // ============================================================================
 // Identifiers (opaque, deterministic)
 // ============================================================================
@@ -78,8 +102,22 @@ pub struct CFGNode {
     /// Source location
     pub source_range: ByteRange,
     
-    /// Optional AST snippet for debugging
-    pub statement: Option<String>,
+    /// Optional AST snippet for debugging (in the epoch's StringArena)
+    pub statement: Option<StringId>,
+
+    /// Tree-sitter node this CFG node was built from (None for synthetic
+    /// nodes and CFGs serialized before schema version 2)
+    #[serde(default)]
+    pub ast_node_id: Option<AstNodeId>,
+
+    /// Statement is the trailing expression of its block (the block's value)
+    #[serde(default)]
+    pub block_value: bool,
+
+    /// Characters in the statement text before truncation (0 for synthetic
+    /// nodes). Not hashed, like `statement`
+    #[serde(default)]
+    pub statement_chars: usize,
 }
 
 /// CFG edge kind (control flow semantics)
@@ -124,6 +162,14 @@ pub struct CFG {
     
     /// File containing this function
     pub file_id: FileId,
+
+    /// Function name (empty for CFGs serialized before schema version 4)
+    #[serde(default)]
+    pub name: String,
+
+    /// Name through the closing parenthesis of the parameter list
+    #[serde(default)]
+    pub signature_range: ByteRange,
     
     /// All nodes in deterministic order
     pub nodes: Vec<CFGNode>,
@@ -136,6 +182,15 @@ pub struct CFG {
     
     /// Exit node ID
     pub exit: NodeId,
+
+    /// Schema version this CFG was serialized with (absent = 1)
+    #[serde(default = "legacy_cfg_schema")]
+    pub schema_version: u32,
+}
+
+/// Schema version of CFGs serialized without one
+fn legacy_cfg_schema() -> u32 {
+    1
 }
 
 impl CFG {
@@ -144,13 +199,28 @@ impl CFG {
         Self {
             function_id,
             file_id,
+            name: String::new(),
+            signature_range: ByteRange::default(),
             nodes: Vec::new(),
             edges: Vec::new(),
             entry,
             exit,
+            schema_version: CFG_SCHEMA_VERSION,
         }
     }
 
+    /// Set the function name and signature range
+    pub fn with_name(mut self, name: impl Into<String>, signature_range: ByteRange) -> Self {
+        self.name = name.into();
+        self.signature_range = signature_range;
+        self
+    }
+
+    /// Source range of the whole function (the entry node's range)
+    pub fn span(&self) -> ByteRange {
+        self.get_node(self.entry).map(|n| n.source_range).unwrap_or_default()
+    }
+
     /// Add a node to the CFG
     pub fn add_node(&mut self, node: CFGNode) {
         self.nodes.push(node);
@@ -166,28 +236,18 @@ impl CFG {
         self.nodes.iter().find(|n| n.id == id)
     }
 
-    /// Compute hash for determinism testing
-    pub fn compute_hash(&self) -> String {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        
-        // Hash function ID
-        hasher.update(self.function_id.0.to_be_bytes());
-        
-        // Hash all nodes in order
-        for node in &self.nodes {
-            hasher.update(node.id.0.to_be_bytes());
-            hasher.update(format!("{:?}", node.kind).as_bytes());
-        }
-        
-        // Hash all edges in order
-        for edge in &self.edges {
-            hasher.update(edge.from.0.to_be_bytes());
-            hasher.update(edge.to.0.to_be_bytes());
-            hasher.update(format!("{:?}", edge.kind).as_bytes());
+    /// Rewrite every StringId (moving the CFG to another arena)
+    pub fn remap_strings(&mut self, mut remap: impl FnMut(StringId) -> StringId) {
+        for node in &mut self.nodes {
+            node.statement = node.statement.map(&mut remap);
         }
-        
-        format!("{:x}", hasher.finalize())
+    }
+
+    /// Approximate heap usage (excluding arena text)
+    pub fn estimated_bytes(&self) -> usize {
+        self.name.capacity()
+            + self.nodes.capacity() * std::mem::size_of::<CFGNode>()
+            + self.edges.capacity() * std::mem::size_of::<CFGEdge>()
     }
 }
 
@@ -201,19 +261,19 @@ pub enum ValueKind {
     /// Variable (mutable or immutable)
     Variable {
         /// Variable name
-        name: String
+        name: StringId
     },
     
     /// Constant literal
     Constant {
         /// Constant value representation
-        value: String
+        value: StringId
     },
     
     /// Function parameter
     Parameter {
         /// Parameter name
-        name: String,
+        name: StringId,
         /// Parameter position in function signature
         position: usize
     },
@@ -222,6 +282,43 @@ pub enum ValueKind {
     Temporary,
 }
 
+impl ValueKind {
+    /// Resolve names against the arena that issued them
+    pub fn resolve<'s>(&self, strings: &'s StringArena) -> ResolvedValueKind<'s> {
+        match self {
+            ValueKind::Variable { name } => ResolvedValueKind::Variable { name: strings.resolve(*name) },
+            ValueKind::Constant { value } => ResolvedValueKind::Constant { value: strings.resolve(*value) },
+            ValueKind::Parameter { name, position } => ResolvedValueKind::Parameter {
+                name: strings.resolve(*name),
+                position: *position,
+            },
+            ValueKind::Temporary => ResolvedValueKind::Temporary,
+        }
+    }
+
+    /// Rewrite the StringId, if any
+    fn remap_strings(&mut self, remap: &mut impl FnMut(StringId) -> StringId) {
+        match self {
+            ValueKind::Variable { name } | ValueKind::Parameter { name, .. } => *name = remap(*name),
+            ValueKind::Constant { value } => *value = remap(*value),
+            ValueKind::Temporary => {}
+        }
+    }
+}
+
+/// ValueKind with names resolved
+///
+/// Variant and field names mirror ValueKind, so the `Debug` output is the
+/// CPG label. `DFG::compute_hash` hashes its canonical encoding instead
+/// (see `semantic::hash`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolvedValueKind<'s> {
+    Variable { name: &'s str },
+    Constant { value: &'s str },
+    Parameter { name: &'s str, position: usize },
+    Temporary,
+}
+
 /// DFG value (variable, constant, or temporary)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DFGValue {
@@ -233,6 +330,18 @@ pub struct DFGValue {
     
     /// Source location
     pub source_range: ByteRange,
+
+    /// CFG node the value was built for: its statement, or the merge of a
+    /// phi-like value (None for DFGs serialized before schema version 2).
+    /// Not hashed
+    #[serde(default)]
+    pub origin: Option<NodeId>,
+
+    /// Every source range of a Constant's literal, in occurrence order (the
+    /// first is `source_range`); empty for other kinds and for DFGs
+    /// serialized before schema version 3
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub occurrences: Vec<ByteRange>,
 }
 
 /// DFG edge type
@@ -275,6 +384,15 @@ pub struct DFG {
     
     /// All edges in deterministic order
     pub edges: Vec<DFGEdge>,
+
+    /// Schema version this DFG was serialized with (absent = 1)
+    #[serde(default = "legacy_dfg_schema")]
+    pub schema_version: u32,
+}
+
+/// Schema version of DFGs serialized without one
+fn legacy_dfg_schema() -> u32 {
+    1
 }
 
 impl DFG {
@@ -284,6 +402,7 @@ impl DFG {
             function_id,
             values: Vec::new(),
             edges: Vec::new(),
+            schema_version: DFG_SCHEMA_VERSION,
         }
     }
 
@@ -302,28 +421,17 @@ impl DFG {
         self.values.iter().find(|v| v.id == id)
     }
 
-    /// Compute hash for determinism testing
-    pub fn compute_hash(&self) -> String {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        
-        // Hash function ID
-        hasher.update(self.function_id.0.to_be_bytes());
-        
-        // Hash all values in order
-        for value in &self.values {
-            hasher.update(value.id.0.to_be_bytes());
-            hasher.update(format!("{:?}", value.kind).as_bytes());
+    /// Rewrite every StringId (moving the DFG to another arena)
+    pub fn remap_strings(&mut self, mut remap: impl FnMut(StringId) -> StringId) {
+        for value in &mut self.values {
+            value.kind.remap_strings(&mut remap);
         }
-        
-        // Hash all edges in order
-        for edge in &self.edges {
-            hasher.update(edge.from.0.to_be_bytes());
-            hasher.update(edge.to.0.to_be_bytes());
-            hasher.update(format!("{:?}", edge.kind).as_bytes());
-        }
-        
-        format!("{:x}", hasher.finalize())
+    }
+
+    /// Approximate heap usage (excluding arena text)
+    pub fn estimated_bytes(&self) -> usize {
+        self.values.capacity() * std::mem::size_of::<DFGValue>()
+            + self.edges.capacity() * std::mem::size_of::<DFGEdge>()
     }
 }
 
@@ -400,6 +508,9 @@ mod tests {
             kind: CFGNodeKind::Entry,
             source_range: ByteRange::new(0, 1),
             statement: None,
+            ast_node_id: None,
+            block_value: false,
+            statement_chars: 0,
         });
         
         cfg1.add_edge(CFGEdge {
@@ -412,21 +523,88 @@ mod tests {
         let hash2 = cfg1.compute_hash();
 
         assert_eq!(hash1, hash2, "CFG hash must be deterministic");
+
+        cfg1.nodes[0].ast_node_id = Some(AstNodeId(3));
+        assert_ne!(cfg1.compute_hash(), hash1, "CFG hash must cover ast_node_id");
+
+        let unnamed = cfg1.compute_hash();
+        let named = cfg1.clone().with_name("main", ByteRange::new(3, 9));
+        assert_ne!(named.compute_hash(), unnamed, "CFG hash must cover the name");
+        assert_eq!(
+            named.compute_hash(),
+            named.clone().with_name("main", ByteRange::new(40, 46)).compute_hash(),
+            "CFG hash must not cover positions"
+        );
+    }
+
+    #[test]
+    fn test_cfg_schema_v1_deserializes() {
+        let v1 = r#"{
+            "function_id": 0, "file_id": 1, "entry": 0, "exit": 0, "edges": [],
+            "nodes": [{"id": 0, "kind": "Entry", "source_range": {"start": 0, "end": 4}, "statement": null}]
+        }"#;
+        let cfg: CFG = serde_json::from_str(v1).unwrap();
+
+        assert_eq!(cfg.schema_version, 1);
+        assert_eq!(cfg.nodes[0].ast_node_id, None);
+        assert_eq!(cfg.name, "");
+        assert_eq!(cfg.span(), ByteRange::new(0, 4));
+        assert_eq!(CFG::new(FunctionId(0), FileId::new(1), NodeId(0), NodeId(0)).schema_version, CFG_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_dfg_schema_v1_deserializes() {
+        let v1 = r#"{
+            "function_id": 0, "edges": [],
+            "values": [{"id": 0, "kind": "Temporary", "source_range": {"start": 0, "end": 4}}]
+        }"#;
+        let dfg: DFG = serde_json::from_str(v1).unwrap();
+
+        assert_eq!(dfg.schema_version, 1);
+        assert_eq!(dfg.values[0].origin, None);
+        assert!(dfg.values[0].occurrences.is_empty());
+        assert_eq!(DFG::new(FunctionId(0)).schema_version, DFG_SCHEMA_VERSION);
     }
 
     #[test]
     fn test_dfg_hash_determinism() {
+        let mut strings = StringArena::new();
         let mut dfg1 = DFG::new(FunctionId(1));
         
         dfg1.add_value(DFGValue {
             id: ValueId(0),
-            kind: ValueKind::Variable { name: "x".to_string() },
+            kind: ValueKind::Variable { name: strings.intern("x") },
             source_range: ByteRange::new(0, 1),
+            origin: None,
+            occurrences: Vec::new(),
         });
 
-        let hash1 = dfg1.compute_hash();
-        let hash2 = dfg1.compute_hash();
+        let hash1 = dfg1.compute_hash(&strings);
+        let hash2 = dfg1.compute_hash(&strings);
 
         assert_eq!(hash1, hash2, "DFG hash must be deterministic");
     }
+
+    #[test]
+    fn test_dfg_hash_covers_text_not_ids() {
+        let dfg = |strings: &mut StringArena| {
+            let mut dfg = DFG::new(FunctionId(1));
+            dfg.add_value(DFGValue {
+                id: ValueId(0),
+                kind: ValueKind::Variable { name: strings.intern("x") },
+                source_range: ByteRange::new(0, 1),
+                origin: None,
+                occurrences: Vec::new(),
+            });
+            dfg
+        };
+
+        let mut padded = StringArena::new();
+        padded.intern("padding");
+        let mut plain = StringArena::new();
+        let (a, b) = (dfg(&mut padded), dfg(&mut plain));
+
+        assert_ne!(a.values[0].kind, b.values[0].kind);
+        assert_eq!(a.compute_hash(&padded), b.compute_hash(&plain));
+    }
 }
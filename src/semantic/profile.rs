@@ -0,0 +1,351 @@
+//! Language profiles - grammar node kinds behind CFG and symbol construction
+//!
+//! `CFGBuilder` and `SymbolTable` walk Tree-sitter trees by node kind and
+//! field name. A `LanguageProfile` answers every grammar question they ask
+//! (is this a function, which statement shape is this, which field holds the
+//! body), so supporting a language means writing a profile rather than
+//! editing the builders.
+//!
+//! Profiles are chosen from `FileMetadata.language` with `profile_for`.
+//! Files without a supported language get `UnsupportedProfile`, which fails
+//! the build instead of producing empty graphs.
+
+use crate::types::Language;
+use anyhow::{bail, Result};
+
+/// Control-flow shape of a statement node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementClass {
+    /// Wraps one expression (its first child); classified by that child
+    Expression,
+    /// Two-way branch with consequence and optional alternative
+    If,
+    /// Loop; `has_condition` adds the header's exit edge
+    Loop { has_condition: bool },
+    /// Multi-way branch over arms
+    Match,
+    /// Local binding, possibly initialized by an `If` or `Match`
+    Let,
+    /// Anything else: one Statement node
+    Simple,
+}
+
+/// How a pattern node binds names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternClass {
+    /// A plain name
+    Identifier,
+    /// The receiver (`self`)
+    Receiver,
+    /// Binds the names of its named children
+    Nested,
+    /// Binds the names of its named children after the first, the
+    /// constructor path (`Some(x)`)
+    Constructor,
+    /// Binds nothing (wildcards, literals, unsupported shapes)
+    Ignored,
+}
+
+/// Field of a grammar node the builders look up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldRole {
+    /// Function name
+    Name,
+    /// Function parameter list
+    Parameters,
+    /// Function, loop or match body
+    Body,
+    /// Branch or loop condition
+    Condition,
+    /// Branch taken when the condition holds
+    Consequence,
+    /// Branch taken otherwise
+    Alternative,
+    /// Let initializer or match arm value
+    Value,
+    /// Let or parameter pattern
+    Pattern,
+}
+
+/// Grammar node kinds and field names of one language
+pub trait LanguageProfile: Send + Sync {
+    /// Lowercase language name
+    fn name(&self) -> &'static str;
+
+    /// Fail if graphs cannot be built for this language
+    fn ensure_supported(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether a node declares a function (one CFG each)
+    fn is_function(&self, kind: &str) -> bool;
+
+    /// Whether a node is a braced block (its own scope)
+    fn is_block(&self, kind: &str) -> bool;
+
+    /// Whether a block child is never a statement (delimiters, comments)
+    fn is_skipped(&self, kind: &str) -> bool;
+
+    /// Control-flow shape of a statement
+    fn classify_statement(&self, kind: &str) -> StatementClass;
+
+    /// Whether a block's last child, bare, is the block's value
+    fn is_value_expression(&self, kind: &str) -> bool;
+
+    /// Whether an alternative wraps its body (walked via its first named child)
+    fn is_else_clause(&self, kind: &str) -> bool;
+
+    /// Whether a child of a match body is an arm
+    fn is_match_arm(&self, kind: &str) -> bool;
+
+    /// Whether a parameter is the receiver (binds the `Receiver` children)
+    fn is_receiver_parameter(&self, kind: &str) -> bool;
+
+    /// Whether a parameter binds the names of its `Pattern` field
+    fn is_parameter(&self, kind: &str) -> bool;
+
+    /// How a pattern binds names
+    fn classify_pattern(&self, kind: &str) -> PatternClass;
+
+    /// Whether a branch or loop condition binds its `Pattern` field to its
+    /// `Value` field (`if let`, `while let`)
+    fn is_let_condition(&self, kind: &str) -> bool;
+
+    /// Whether a condition joins several conditions, some of which may be
+    /// let conditions (`if let Some(x) = a && x > 0`)
+    fn is_condition_chain(&self, kind: &str) -> bool;
+
+    /// Whether a node is a plain name; outside patterns, a reference
+    fn is_identifier(&self, kind: &str) -> bool;
+
+    /// Grammar field name for a role
+    fn field(&self, role: FieldRole) -> &'static str;
+}
+
+/// Profile for tree-sitter-rust
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RustProfile;
+
+impl LanguageProfile for RustProfile {
+    fn name(&self) -> &'static str {
+        Language::Rust.name()
+    }
+
+    fn is_function(&self, kind: &str) -> bool {
+        kind == "function_item"
+    }
+
+    fn is_block(&self, kind: &str) -> bool {
+        kind == "block"
+    }
+
+    fn is_skipped(&self, kind: &str) -> bool {
+        matches!(kind, "line_comment" | "block_comment" | "{" | "}" | "(" | ")" | "," | ";")
+    }
+
+    fn classify_statement(&self, kind: &str) -> StatementClass {
+        match kind {
+            "expression_statement" => StatementClass::Expression,
+            "if_expression" => StatementClass::If,
+            "while_expression" => StatementClass::Loop { has_condition: true },
+            "loop_expression" => StatementClass::Loop { has_condition: false },
+            "match_expression" => StatementClass::Match,
+            "let_declaration" => StatementClass::Let,
+            _ => StatementClass::Simple,
+        }
+    }
+
+    /// Expressions followed by `;` are wrapped in `expression_statement`, so
+    /// only the trailing one appears bare. Control flow keeps its own shape
+    /// (the values are its arms' trailing expressions); assignments and jumps
+    /// produce no value.
+    fn is_value_expression(&self, kind: &str) -> bool {
+        !matches!(kind, "expression_statement" | "let_declaration" | "empty_statement" | "attribute_item"
+            | "if_expression" | "while_expression" | "loop_expression" | "for_expression" | "match_expression"
+            | "assignment_expression" | "compound_assignment_expr"
+            | "return_expression" | "break_expression" | "continue_expression")
+            && !kind.ends_with("_item")
+            && !kind.ends_with("_declaration")
+    }
+
+    fn is_else_clause(&self, kind: &str) -> bool {
+        kind == "else_clause"
+    }
+
+    fn is_match_arm(&self, kind: &str) -> bool {
+        kind == "match_arm"
+    }
+
+    fn is_receiver_parameter(&self, kind: &str) -> bool {
+        kind == "self_parameter"
+    }
+
+    fn is_parameter(&self, kind: &str) -> bool {
+        kind == "parameter"
+    }
+
+    /// Struct patterns are not handled yet.
+    fn classify_pattern(&self, kind: &str) -> PatternClass {
+        match kind {
+            "identifier" => PatternClass::Identifier,
+            "self" => PatternClass::Receiver,
+            "mut_pattern" | "ref_pattern" | "reference_pattern" | "tuple_pattern" | "slice_pattern"
+            | "captured_pattern" => PatternClass::Nested,
+            "tuple_struct_pattern" => PatternClass::Constructor,
+            // `_`, literals, ranges
+            _ => PatternClass::Ignored,
+        }
+    }
+
+    fn is_let_condition(&self, kind: &str) -> bool {
+        kind == "let_condition"
+    }
+
+    fn is_condition_chain(&self, kind: &str) -> bool {
+        kind == "let_chain"
+    }
+
+    fn is_identifier(&self, kind: &str) -> bool {
+        kind == "identifier"
+    }
+
+    fn field(&self, role: FieldRole) -> &'static str {
+        match role {
+            FieldRole::Name => "name",
+            FieldRole::Parameters => "parameters",
+            FieldRole::Body => "body",
+            FieldRole::Condition => "condition",
+            FieldRole::Consequence => "consequence",
+            FieldRole::Alternative => "alternative",
+            FieldRole::Value => "value",
+            FieldRole::Pattern => "pattern",
+        }
+    }
+}
+
+/// Stub for files without a supported language
+///
+/// Matches nothing; `ensure_supported` fails so builders reject the file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnsupportedProfile;
+
+impl LanguageProfile for UnsupportedProfile {
+    fn name(&self) -> &'static str {
+        "unsupported"
+    }
+
+    fn ensure_supported(&self) -> Result<()> {
+        bail!("No language profile: unsupported language")
+    }
+
+    fn is_function(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_block(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_skipped(&self, _kind: &str) -> bool {
+        true
+    }
+
+    fn classify_statement(&self, _kind: &str) -> StatementClass {
+        StatementClass::Simple
+    }
+
+    fn is_value_expression(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_else_clause(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_match_arm(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_receiver_parameter(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_parameter(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn classify_pattern(&self, _kind: &str) -> PatternClass {
+        PatternClass::Ignored
+    }
+
+    fn is_let_condition(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_condition_chain(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn is_identifier(&self, _kind: &str) -> bool {
+        false
+    }
+
+    fn field(&self, _role: FieldRole) -> &'static str {
+        ""
+    }
+}
+
+/// Profile for a file's detected language (`FileMetadata.language`)
+pub fn profile_for(language: Option<Language>) -> &'static dyn LanguageProfile {
+    match language {
+        Some(Language::Rust) => &RustProfile,
+        None => &UnsupportedProfile,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::BufferedFile;
+    use crate::memory::arena::StringArena;
+    use crate::parse::IncrementalParser;
+    use crate::semantic::{CFGBuilder, SymbolTable};
+    use crate::types::FileId;
+
+    #[test]
+    fn test_profile_for_language() {
+        assert_eq!(profile_for(Some(Language::Rust)).name(), "rust");
+        assert!(profile_for(Some(Language::Rust)).ensure_supported().is_ok());
+        assert_eq!(profile_for(None).name(), "unsupported");
+    }
+
+    #[test]
+    fn test_unsupported_profile_fails_builds() {
+        let source = b"fn f() { let x = 1; }";
+        let file_id = FileId::new(1);
+        let file = BufferedFile::new(source.to_vec(), file_id);
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&file, None).unwrap();
+
+        let err = CFGBuilder::new(file_id, source)
+            .with_profile(profile_for(None))
+            .build_all(&parsed, &mut StringArena::new())
+            .unwrap_err();
+        assert!(err.to_string().contains("unsupported language"), "{}", err);
+
+        let mut symbols = SymbolTable::new(file_id).with_profile(profile_for(None));
+        assert!(symbols.build(&parsed, source).is_err());
+    }
+
+    #[test]
+    fn test_rust_statement_classes() {
+        let rust = RustProfile;
+        assert_eq!(rust.classify_statement("while_expression"), StatementClass::Loop { has_condition: true });
+        assert_eq!(rust.classify_statement("loop_expression"), StatementClass::Loop { has_condition: false });
+        assert_eq!(rust.classify_statement("for_expression"), StatementClass::Simple);
+        assert!(rust.is_value_expression("binary_expression"));
+        assert!(!rust.is_value_expression("struct_item"));
+        assert!(rust.is_skipped("line_comment") && !rust.is_skipped("return_expression"));
+        assert!(rust.is_let_condition("let_condition") && rust.is_condition_chain("let_chain"));
+        assert_eq!(rust.classify_pattern("tuple_struct_pattern"), PatternClass::Constructor);
+    }
+}
@@ -0,0 +1,159 @@
+//! Per-file breakdown of a semantic epoch
+//!
+//! `SemanticEpoch::stats` only totals the epoch. `SemanticEpochReport`
+//! lists, per file: function symbols, CFG node/edge and DFG value/edge
+//! totals, symbols, and each function's CFG and DFG hash. Files are ordered
+//! by FileId and functions by FunctionId, so equal epochs serialize to equal
+//! reports; diffing two reports shows which functions an incremental run
+//! rebuilt differently.
+
+use crate::semantic::epoch::SemanticEpoch;
+use crate::semantic::model::FunctionId;
+use crate::semantic::symbols::SymbolKind;
+use crate::types::FileId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Per-file counts and per-function hashes of one epoch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SemanticEpochReport {
+    pub epoch_id: u64,
+
+    /// One entry per analyzed file, by FileId
+    pub files: Vec<FileSemanticReport>,
+}
+
+/// Counts and function hashes of one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSemanticReport {
+    pub file_id: FileId,
+
+    /// Function symbols
+    pub functions: usize,
+
+    pub cfg_nodes: usize,
+    pub cfg_edges: usize,
+    pub dfg_values: usize,
+    pub dfg_edges: usize,
+    pub symbols: usize,
+
+    /// One entry per function with a CFG or DFG, by FunctionId
+    pub function_hashes: Vec<FunctionHashes>,
+}
+
+/// CFG and DFG hash of one function (None if that graph was not built)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionHashes {
+    pub function_id: FunctionId,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfg_hash: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dfg_hash: Option<String>,
+}
+
+impl SemanticEpochReport {
+    /// Aggregate `epoch`'s CFGs, DFGs and symbol tables
+    pub fn build(epoch: &SemanticEpoch) -> Self {
+        let files = epoch.get_all_file_ids().into_iter()
+            .map(|file_id| file_report(epoch, file_id))
+            .collect();
+        Self { epoch_id: epoch.epoch_id(), files }
+    }
+
+    /// Entry of `file_id` (None if the epoch did not analyze it)
+    pub fn file(&self, file_id: FileId) -> Option<&FileSemanticReport> {
+        self.files.binary_search_by_key(&file_id, |file| file.file_id).ok().map(|i| &self.files[i])
+    }
+}
+
+impl FileSemanticReport {
+    /// CFG hashes, by FunctionId
+    pub fn cfg_hashes(&self) -> Vec<String> {
+        self.function_hashes.iter().filter_map(|f| f.cfg_hash.clone()).collect()
+    }
+
+    /// DFG hashes, by FunctionId
+    pub fn dfg_hashes(&self) -> Vec<String> {
+        self.function_hashes.iter().filter_map(|f| f.dfg_hash.clone()).collect()
+    }
+}
+
+fn file_report(epoch: &SemanticEpoch, file_id: FileId) -> FileSemanticReport {
+    let cfgs = epoch.get_cfgs(file_id).map_or(&[][..], Vec::as_slice);
+    let dfgs = epoch.get_dfgs(file_id).map_or(&[][..], Vec::as_slice);
+    let symbols = epoch.get_symbols(file_id);
+
+    let mut hashes = BTreeMap::new();
+    for cfg in cfgs {
+        function_entry(&mut hashes, cfg.function_id).cfg_hash = Some(cfg.compute_hash());
+    }
+    for dfg in dfgs {
+        function_entry(&mut hashes, dfg.function_id).dfg_hash = Some(dfg.compute_hash(epoch.strings()));
+    }
+
+    FileSemanticReport {
+        file_id,
+        functions: symbols.map_or(0, |table| table.symbols().filter(|s| s.kind == SymbolKind::Function).count()),
+        cfg_nodes: cfgs.iter().map(|cfg| cfg.nodes.len()).sum(),
+        cfg_edges: cfgs.iter().map(|cfg| cfg.edges.len()).sum(),
+        dfg_values: dfgs.iter().map(|dfg| dfg.values.len()).sum(),
+        dfg_edges: dfgs.iter().map(|dfg| dfg.edges.len()).sum(),
+        symbols: symbols.map_or(0, |table| table.symbols().count()),
+        function_hashes: hashes.into_values().collect(),
+    }
+}
+
+fn function_entry(hashes: &mut BTreeMap<FunctionId, FunctionHashes>, function_id: FunctionId) -> &mut FunctionHashes {
+    hashes.entry(function_id).or_insert(FunctionHashes { function_id, cfg_hash: None, dfg_hash: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use crate::verify::file_hashes;
+    use std::path::PathBuf;
+
+    /// `tests/golden/calls`: src/main.rs and src/util.rs
+    fn calls_fixture() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/calls")
+    }
+
+    /// (functions, CFG nodes, CFG edges, DFG values, DFG edges, symbols)
+    const MAIN_COUNTS: (usize, usize, usize, usize, usize, usize) = (2, 8, 6, 6, 4, 5);
+    const UTIL_COUNTS: (usize, usize, usize, usize, usize, usize) = (2, 8, 6, 6, 1, 5);
+
+    #[test]
+    fn test_counts_on_calls_fixture() {
+        let output = Pipeline::default().run(&calls_fixture()).unwrap();
+        let report = output.semantic.report();
+
+        let by_path = |path: &str| {
+            let (file_id, _) = output.snapshot.files.iter()
+                .find(|(_, meta)| meta.path == std::path::Path::new(path))
+                .unwrap();
+            report.file(*file_id).unwrap()
+        };
+        let counts = |file: &FileSemanticReport| {
+            (file.functions, file.cfg_nodes, file.cfg_edges, file.dfg_values, file.dfg_edges, file.symbols)
+        };
+        assert_eq!(counts(by_path("src/main.rs")), MAIN_COUNTS);
+        assert_eq!(counts(by_path("src/util.rs")), UTIL_COUNTS);
+
+        let ids: Vec<FileId> = report.files.iter().map(|f| f.file_id).collect();
+        assert_eq!(ids, output.semantic.get_all_file_ids());
+        for file in &report.files {
+            assert_eq!(file.function_hashes.len(), file.functions);
+            assert!(file.function_hashes.windows(2).all(|w| w[0].function_id < w[1].function_id));
+            assert_eq!((file.cfg_hashes(), file.dfg_hashes()), file_hashes(&output.semantic, file.file_id));
+        }
+    }
+
+    #[test]
+    fn test_report_is_deterministic() {
+        let build = || serde_json::to_string(&Pipeline::default().run(&calls_fixture()).unwrap().semantic.report()).unwrap();
+        assert_eq!(build(), build());
+    }
+}
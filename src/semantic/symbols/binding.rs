@@ -1,11 +1,15 @@
 //! Symbol bindings and scopes
+//!
+//! Scopes serialize their bindings as `[name, symbol]` pairs sorted by
+//! name, so equal scopes always serialize to the same bytes.
 
 use crate::semantic::model::{ScopeId, SymbolId};
 use crate::types::ByteRange;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// A symbol binding (variable, parameter, function)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Symbol {
     /// Unique symbol identifier
     pub id: SymbolId,
@@ -21,10 +25,28 @@ pub struct Symbol {
     
     /// Symbol kind
     pub kind: SymbolKind,
+
+    /// Parameters: index in the parameter list, in declaration order
+    /// (`self` is 0; every name of a destructured parameter shares its index)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<usize>,
+}
+
+/// A name resolved to a symbol where it is read, called or assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolReference {
+    /// Symbol the name resolved to
+    pub symbol: SymbolId,
+
+    /// Source location of the name
+    pub range: ByteRange,
+
+    /// Scope the name appears in
+    pub scope: ScopeId,
 }
 
 /// Kind of symbol
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SymbolKind {
     /// Function definition
     Function,
@@ -40,7 +62,7 @@ pub enum SymbolKind {
 }
 
 /// Lexical scope (file, function, or block)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Scope {
     /// Unique scope identifier
     pub id: ScopeId,
@@ -52,11 +74,12 @@ pub struct Scope {
     pub kind: ScopeKind,
     
     /// Symbol name → Symbol ID
+    #[serde(with = "sorted_bindings")]
     bindings: HashMap<String, SymbolId>,
 }
 
 /// Kind of scope
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScopeKind {
     /// File/module scope
     File,
@@ -94,3 +117,20 @@ impl Scope {
         &self.bindings
     }
 }
+
+/// Bindings as `[name, symbol]` pairs sorted by name
+mod sorted_bindings {
+    use super::SymbolId;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(bindings: &HashMap<String, SymbolId>, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut pairs: Vec<(&String, &SymbolId)> = bindings.iter().collect();
+        pairs.sort();
+        pairs.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<String, SymbolId>, D::Error> {
+        Ok(Vec::<(String, SymbolId)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
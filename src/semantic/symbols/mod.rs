@@ -9,6 +9,12 @@
 //! - Function scope: function parameters
 //! - Block scope: local variables within blocks
 //!
+//! ## References
+//!
+//! Names outside patterns are resolved against the scopes declared so far
+//! and recorded as `SymbolReference`s; unresolved names (macros, paths,
+//! names bound by untracked patterns) are dropped.
+//!
 //! ## Immutability
 //!
 //! All bindings are immutable within a SemanticEpoch.
@@ -18,4 +24,4 @@ pub mod table;
 pub mod binding;
 
 pub use table::SymbolTable;
-pub use binding::{Symbol, Scope, SymbolKind, ScopeKind};
+pub use binding::{Symbol, SymbolReference, Scope, SymbolKind, ScopeKind};
@@ -1,13 +1,21 @@
 //! Symbol table implementation
+//!
+//! Tables serialize with scopes and symbols in ID order (bindings sorted,
+//! see `binding`), so snapshots holding them are byte-for-byte stable. The
+//! language profile is not serialized: a restored table answers lookups
+//! but is never rebuilt, and gets the default `RustProfile`.
 
 use crate::semantic::model::{FunctionId, ScopeId, SymbolId};
-use crate::semantic::symbols::binding::{Scope, ScopeKind, Symbol, SymbolKind};
+use crate::semantic::profile::{FieldRole, LanguageProfile, PatternClass, RustProfile, StatementClass};
+use crate::semantic::symbols::binding::{Scope, ScopeKind, Symbol, SymbolKind, SymbolReference};
 use crate::types::{ByteRange, FileId, ParsedFile};
 use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 use tree_sitter::Node;
 
 /// Symbol table tracks all symbols and their scopes
+#[derive(Clone)]
 pub struct SymbolTable {
     /// File being analyzed
     _file_id: FileId,
@@ -17,6 +25,9 @@ pub struct SymbolTable {
     
     /// All symbols
     symbols: HashMap<SymbolId, Symbol>,
+
+    /// Resolved names, in source order
+    references: Vec<SymbolReference>,
     
     /// File-level scope
     file_scope: ScopeId,
@@ -27,6 +38,9 @@ pub struct SymbolTable {
     /// Counters for ID generation
     next_scope_id: u64,
     next_symbol_id: u64,
+
+    /// Grammar node kinds and field names
+    profile: &'static dyn LanguageProfile,
 }
 
 impl SymbolTable {
@@ -43,15 +57,27 @@ impl SymbolTable {
             _file_id: file_id,
             scopes,
             symbols: HashMap::new(),
+            references: Vec::new(),
             file_scope: file_scope_id,
             _function_scopes: HashMap::new(),
             next_scope_id: 1,
             next_symbol_id: 0,
+            profile: &RustProfile,
         }
     }
 
+    /// Use `profile` for the file's grammar (see `semantic::profile::profile_for`)
+    pub fn with_profile(mut self, profile: &'static dyn LanguageProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
     /// Build symbol table from parsed file
+    ///
+    /// Fails if the profile's language is unsupported.
     pub fn build(&mut self, parsed: &ParsedFile, source: &[u8]) -> Result<()> {
+        let _span = tracing::debug_span!("symbols", file_id = parsed.file_id.as_u64()).entered();
+        self.profile.ensure_supported()?;
         let root = parsed.tree.root_node();
         self.visit_node(&root, self.file_scope, source)?;
         Ok(())
@@ -59,41 +85,46 @@ impl SymbolTable {
 
     /// Visit a node and extract symbols
     fn visit_node(&mut self, node: &Node, current_scope: ScopeId, source: &[u8]) -> Result<()> {
-        match node.kind() {
-            "function_item" => {
-                self.visit_function(node, current_scope, source)?;
-            }
-            "let_declaration" => {
-                self.visit_let_declaration(node, current_scope, source)?;
-            }
-            "block" => {
-                // Create block scope
-                let block_scope = self.new_scope(ScopeKind::Block, Some(current_scope));
-                
-                // Visit children in block scope
-                let mut cursor = node.walk();
-                if cursor.goto_first_child() {
-                    loop {
-                        let child = cursor.node();
-                        if child.kind() != "{" && child.kind() != "}" {
-                            self.visit_node(&child, block_scope, source)?;
-                        }
-                        if !cursor.goto_next_sibling() {
-                            break;
-                        }
+        let kind = node.kind();
+        if self.profile.is_function(kind) {
+            self.visit_function(node, current_scope, source)?;
+        } else if self.profile.classify_statement(kind) == StatementClass::Let {
+            self.visit_let_declaration(node, current_scope, source)?;
+        } else if let Some(condition) = self.let_condition(node) {
+            self.visit_let_condition(node, condition, current_scope, source)?;
+        } else if self.profile.is_identifier(kind) {
+            self.record_reference(node, current_scope, source);
+        } else if self.profile.is_block(kind) {
+            // Create block scope
+            let block_scope = self.new_scope(ScopeKind::Block, Some(current_scope));
+            
+            // Visit children in block scope
+            let mut cursor = node.walk();
+            if cursor.goto_first_child() {
+                loop {
+                    let child = cursor.node();
+                    if !self.profile.is_skipped(child.kind()) {
+                        self.visit_node(&child, block_scope, source)?;
+                    }
+                    if !cursor.goto_next_sibling() {
+                        break;
                     }
                 }
             }
-            _ => {
-                // Recursively visit children
-                let mut cursor = node.walk();
-                if cursor.goto_first_child() {
-                    loop {
-                        let child = cursor.node();
+        } else {
+            // Recursively visit children; names in patterns (match arms,
+            // `if let`, closure parameters) bind rather than reference
+            let pattern = self.profile.field(FieldRole::Pattern);
+            let parameters = self.profile.field(FieldRole::Parameters);
+            let mut cursor = node.walk();
+            if cursor.goto_first_child() {
+                loop {
+                    let child = cursor.node();
+                    if !matches!(cursor.field_name(), Some(field) if field == pattern || field == parameters) {
                         self.visit_node(&child, current_scope, source)?;
-                        if !cursor.goto_next_sibling() {
-                            break;
-                        }
+                    }
+                    if !cursor.goto_next_sibling() {
+                        break;
                     }
                 }
             }
@@ -105,7 +136,7 @@ impl SymbolTable {
     /// Visit a function declaration
     fn visit_function(&mut self, node: &Node, parent_scope: ScopeId, source: &[u8]) -> Result<()> {
         // Extract function name
-        let name = if let Some(name_node) = node.child_by_field_name("name") {
+        let name = if let Some(name_node) = node.child_by_field_name(self.profile.field(FieldRole::Name)) {
             self.node_text(&name_node, source)
         } else {
             return Ok(());
@@ -119,6 +150,7 @@ impl SymbolTable {
             source_range: self.node_range(node),
             scope: parent_scope,
             kind: SymbolKind::Function,
+            position: None,
         };
 
         self.symbols.insert(symbol_id, function_symbol);
@@ -130,12 +162,12 @@ impl SymbolTable {
         let function_scope = self.new_scope(ScopeKind::Function, Some(parent_scope));
         
         // Process parameters
-        if let Some(params) = node.child_by_field_name("parameters") {
+        if let Some(params) = node.child_by_field_name(self.profile.field(FieldRole::Parameters)) {
             self.visit_parameters(&params, function_scope, source)?;
         }
 
         // Process function body
-        if let Some(body) = node.child_by_field_name("body") {
+        if let Some(body) = node.child_by_field_name(self.profile.field(FieldRole::Body)) {
             self.visit_node(&body, function_scope, source)?;
         }
 
@@ -143,37 +175,42 @@ impl SymbolTable {
     }
 
     /// Visit function parameters
+    ///
+    /// `self`, `&self` and `&mut self` bind "self". Typed parameters bind
+    /// every identifier in their pattern (`mut x`, `ref x`, `(a, b)`); `_`
+    /// binds nothing. Each symbol records its parameter's index (comments
+    /// and attributes are not counted).
     fn visit_parameters(&mut self, params_node: &Node, scope: ScopeId, source: &[u8]) -> Result<()> {
         let mut cursor = params_node.walk();
-        if cursor.goto_first_child() {
-            loop {
-                let child = cursor.node();
-                
-                if child.kind() == "parameter" {
-                    // Extract parameter name
-                    if let Some(pattern) = child.child_by_field_name("pattern") {
-                        if let Some(name_node) = pattern.child_by_field_name("name").or(Some(pattern)) {
-                            let name = self.node_text(&name_node, source);
-                            
-                            let symbol_id = self.new_symbol_id();
-                            let param_symbol = Symbol {
-                                id: symbol_id,
-                                name: name.clone(),
-                                source_range: self.node_range(&pattern),
-                                scope,
-                                kind: SymbolKind::Parameter,
-                            };
-
-                            self.symbols.insert(symbol_id, param_symbol);
-                            if let Some(scope_ref) = self.scopes.get_mut(&scope) {
-                                scope_ref.add_binding(name, symbol_id);
-                            }
-                        }
-                    }
+        let parameters = params_node.named_children(&mut cursor)
+            .filter(|child| self.profile.is_receiver_parameter(child.kind()) || self.profile.is_parameter(child.kind()));
+        for (position, child) in parameters.enumerate() {
+            let mut names = Vec::new();
+            if self.profile.is_receiver_parameter(child.kind()) {
+                let mut self_cursor = child.walk();
+                names.extend(child.named_children(&mut self_cursor)
+                    .filter(|n| self.profile.classify_pattern(n.kind()) == PatternClass::Receiver));
+            } else if self.profile.is_parameter(child.kind()) {
+                if let Some(pattern) = child.child_by_field_name(self.profile.field(FieldRole::Pattern)) {
+                    pattern_bindings(self.profile, pattern, &mut names);
                 }
+            }
 
-                if !cursor.goto_next_sibling() {
-                    break;
+            for name_node in names {
+                let name = self.node_text(&name_node, source);
+                let symbol_id = self.new_symbol_id();
+                let param_symbol = Symbol {
+                    id: symbol_id,
+                    name: name.clone(),
+                    source_range: self.node_range(&name_node),
+                    scope,
+                    kind: SymbolKind::Parameter,
+                    position: Some(position),
+                };
+
+                self.symbols.insert(symbol_id, param_symbol);
+                if let Some(scope_ref) = self.scopes.get_mut(&scope) {
+                    scope_ref.add_binding(name, symbol_id);
                 }
             }
         }
@@ -182,16 +219,79 @@ impl SymbolTable {
     }
 
     /// Visit a let declaration
+    ///
+    /// The initializer (and `else` block) is visited first, in the
+    /// enclosing bindings: `let x = x + 1;` reads the outer `x`. Every name
+    /// the pattern binds gets a symbol with the declaration's range.
     fn visit_let_declaration(&mut self, node: &Node, scope: ScopeId, source: &[u8]) -> Result<()> {
-        // Extract variable name
-        if let Some(pattern) = node.child_by_field_name("pattern") {
-            let name = if pattern.kind() == "identifier" {
-                self.node_text(&pattern, source)
-            } else {
-                // Handle more complex patterns later
-                return Ok(());
-            };
+        for role in [FieldRole::Value, FieldRole::Alternative] {
+            if let Some(child) = node.child_by_field_name(self.profile.field(role)) {
+                self.visit_node(&child, scope, source)?;
+            }
+        }
 
+        self.bind_pattern(node, scope, source);
+        Ok(())
+    }
+
+    /// Condition of an `if` or `while` that binds names: a let condition,
+    /// or a chain holding one
+    fn let_condition<'t>(&self, node: &Node<'t>) -> Option<Node<'t>> {
+        if !matches!(self.profile.classify_statement(node.kind()), StatementClass::If | StatementClass::Loop { .. }) {
+            return None;
+        }
+        let condition = node.child_by_field_name(self.profile.field(FieldRole::Condition))?;
+        let mut cursor = condition.walk();
+        let binds = self.profile.is_let_condition(condition.kind())
+            || (self.profile.is_condition_chain(condition.kind())
+                && condition.named_children(&mut cursor).any(|c| self.profile.is_let_condition(c.kind())));
+        binds.then_some(condition)
+    }
+
+    /// Visit an `if let` or `while let`
+    ///
+    /// The names each let condition binds get symbols, with the condition's
+    /// range, in a block scope holding the consequence (or loop body); the
+    /// `else` branch is visited in the enclosing scope and does not see
+    /// them. A condition's value is visited before its names are bound, so
+    /// later conditions of a chain see earlier ones.
+    fn visit_let_condition(&mut self, node: &Node, condition: Node, scope: ScopeId, source: &[u8]) -> Result<()> {
+        let bound = self.new_scope(ScopeKind::Block, Some(scope));
+        let conditions = match self.profile.is_condition_chain(condition.kind()) {
+            true => {
+                let mut cursor = condition.walk();
+                condition.named_children(&mut cursor).collect()
+            }
+            false => vec![condition],
+        };
+        for condition in conditions {
+            if !self.profile.is_let_condition(condition.kind()) {
+                self.visit_node(&condition, bound, source)?;
+                continue;
+            }
+            if let Some(value) = condition.child_by_field_name(self.profile.field(FieldRole::Value)) {
+                self.visit_node(&value, bound, source)?;
+            }
+            self.bind_pattern(&condition, bound, source);
+        }
+
+        for (role, scope) in [(FieldRole::Consequence, bound), (FieldRole::Body, bound), (FieldRole::Alternative, scope)] {
+            if let Some(child) = node.child_by_field_name(self.profile.field(role)) {
+                self.visit_node(&child, scope, source)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bind every name in a binding node's `Pattern` field as a variable
+    /// with the binding node's range
+    fn bind_pattern(&mut self, node: &Node, scope: ScopeId, source: &[u8]) {
+        let mut names = Vec::new();
+        if let Some(pattern) = node.child_by_field_name(self.profile.field(FieldRole::Pattern)) {
+            pattern_bindings(self.profile, pattern, &mut names);
+        }
+        for name_node in names {
+            let name = self.node_text(&name_node, source);
             let symbol_id = self.new_symbol_id();
             let var_symbol = Symbol {
                 id: symbol_id,
@@ -199,6 +299,7 @@ impl SymbolTable {
                 source_range: self.node_range(node),
                 scope,
                 kind: SymbolKind::Variable,
+                position: None,
             };
 
             self.symbols.insert(symbol_id, var_symbol);
@@ -206,8 +307,14 @@ impl SymbolTable {
                 scope_ref.add_binding(name, symbol_id);
             }
         }
+    }
 
-        Ok(())
+    /// Record a name that resolves to a symbol declared so far
+    fn record_reference(&mut self, node: &Node, scope: ScopeId, source: &[u8]) {
+        let name = self.node_text(node, source);
+        if let Some(symbol) = self.lookup(&name, scope) {
+            self.references.push(SymbolReference { symbol: symbol.id, range: self.node_range(node), scope });
+        }
     }
 
     /// Look up a symbol by name in the given scope (walks up parent scopes)
@@ -229,18 +336,52 @@ impl SymbolTable {
     }
 
     /// Get all symbols in a scope
+    ///
+    /// **Deterministic**: Sorted by SymbolId (bindings are a HashMap)
     pub fn symbols_in_scope(&self, scope: ScopeId) -> Vec<&Symbol> {
         if let Some(scope_ref) = self.scopes.get(&scope) {
-            scope_ref
+            let mut symbols: Vec<&Symbol> = scope_ref
                 .bindings()
                 .values()
                 .filter_map(|id| self.symbols.get(id))
-                .collect()
+                .collect();
+            symbols.sort_by_key(|s| s.id);
+            symbols
         } else {
             Vec::new()
         }
     }
 
+    /// Function symbol declared exactly at a source range
+    ///
+    /// CFG entry nodes carry the range of their `function_item`, so this maps
+    /// a CFG back to its function's name.
+    pub fn function_at(&self, range: ByteRange) -> Option<&Symbol> {
+        self.symbols.values()
+            .filter(|s| s.kind == SymbolKind::Function && s.source_range == range)
+            .min_by_key(|s| s.id)
+    }
+
+    /// Get a symbol by ID
+    pub fn get_symbol(&self, symbol_id: SymbolId) -> Option<&Symbol> {
+        self.symbols.get(&symbol_id)
+    }
+
+    /// Every symbol, in no particular order
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.values()
+    }
+
+    /// Every resolved name, in source order
+    pub fn references(&self) -> &[SymbolReference] {
+        &self.references
+    }
+
+    /// Every scope, in no particular order
+    pub fn scopes(&self) -> impl Iterator<Item = &Scope> {
+        self.scopes.values()
+    }
+
     /// Get a scope by ID
     pub fn get_scope(&self, scope_id: ScopeId) -> Option<&Scope> {
         self.scopes.get(&scope_id)
@@ -251,6 +392,52 @@ impl SymbolTable {
         self.file_scope
     }
 
+    /// Compute hash for determinism testing
+    ///
+    /// Scopes, their bindings and symbols in ID order, including each
+    /// symbol's source range, then references in source order.
+    pub fn compute_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+
+        let mut scopes: Vec<&Scope> = self.scopes.values().collect();
+        scopes.sort_by_key(|s| s.id);
+        for scope in scopes {
+            hasher.update(scope.id.0.to_be_bytes());
+            hasher.update(scope.parent.map_or(u64::MAX, |p| p.0).to_be_bytes());
+            hasher.update(format!("{:?}", scope.kind).as_bytes());
+
+            let mut bindings: Vec<(&String, &SymbolId)> = scope.bindings().iter().collect();
+            bindings.sort();
+            for (name, symbol_id) in bindings {
+                hasher.update((name.len() as u64).to_be_bytes());
+                hasher.update(name.as_bytes());
+                hasher.update(symbol_id.0.to_be_bytes());
+            }
+        }
+
+        let mut symbols: Vec<&Symbol> = self.symbols.values().collect();
+        symbols.sort_by_key(|s| s.id);
+        for symbol in symbols {
+            hasher.update(symbol.id.0.to_be_bytes());
+            hasher.update((symbol.name.len() as u64).to_be_bytes());
+            hasher.update(symbol.name.as_bytes());
+            hasher.update((symbol.source_range.start as u64).to_be_bytes());
+            hasher.update((symbol.source_range.end as u64).to_be_bytes());
+            hasher.update(symbol.scope.0.to_be_bytes());
+            hasher.update(format!("{:?}", symbol.kind).as_bytes());
+        }
+
+        for reference in &self.references {
+            hasher.update(reference.symbol.0.to_be_bytes());
+            hasher.update((reference.range.start as u64).to_be_bytes());
+            hasher.update((reference.range.end as u64).to_be_bytes());
+            hasher.update(reference.scope.0.to_be_bytes());
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
+
     /// Create a new scope
     fn new_scope(&mut self, kind: ScopeKind, parent: Option<ScopeId>) -> ScopeId {
         let scope_id = ScopeId(self.next_scope_id);
@@ -271,15 +458,90 @@ impl SymbolTable {
 
     /// Get byte range for a node
     fn node_range(&self, node: &Node) -> ByteRange {
-        ByteRange::new(node.start_byte(), node.end_byte())
+        ByteRange::of_node(node)
     }
 
     /// Get text content of a node
     fn node_text(&self, node: &Node, source: &[u8]) -> String {
-        let start = node.start_byte();
-        let end = node.end_byte();
-        let bytes = &source[start..end];
-        String::from_utf8_lossy(bytes).to_string()
+        let range = self.node_range(node);
+        String::from_utf8_lossy(&source[range.start..range.end]).to_string()
+    }
+}
+
+/// Serialized form of a `SymbolTable`, borrowed for writing
+#[derive(Serialize)]
+struct SymbolTableRef<'a> {
+    file_id: FileId,
+    file_scope: ScopeId,
+    scopes: Vec<&'a Scope>,
+    symbols: Vec<&'a Symbol>,
+    references: &'a [SymbolReference],
+    next_scope_id: u64,
+    next_symbol_id: u64,
+}
+
+/// Serialized form of a `SymbolTable`, as read back
+#[derive(Deserialize)]
+struct SymbolTableRecord {
+    file_id: FileId,
+    file_scope: ScopeId,
+    scopes: Vec<Scope>,
+    symbols: Vec<Symbol>,
+    #[serde(default)]
+    references: Vec<SymbolReference>,
+    next_scope_id: u64,
+    next_symbol_id: u64,
+}
+
+impl Serialize for SymbolTable {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut scopes: Vec<&Scope> = self.scopes.values().collect();
+        scopes.sort_by_key(|s| s.id);
+        let mut symbols: Vec<&Symbol> = self.symbols.values().collect();
+        symbols.sort_by_key(|s| s.id);
+        SymbolTableRef {
+            file_id: self._file_id,
+            file_scope: self.file_scope,
+            scopes,
+            symbols,
+            references: &self.references,
+            next_scope_id: self.next_scope_id,
+            next_symbol_id: self.next_symbol_id,
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SymbolTable {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let record = SymbolTableRecord::deserialize(deserializer)?;
+        Ok(Self {
+            _file_id: record.file_id,
+            scopes: record.scopes.into_iter().map(|scope| (scope.id, scope)).collect(),
+            symbols: record.symbols.into_iter().map(|symbol| (symbol.id, symbol)).collect(),
+            references: record.references,
+            file_scope: record.file_scope,
+            _function_scopes: HashMap::new(),
+            next_scope_id: record.next_scope_id,
+            next_symbol_id: record.next_symbol_id,
+            profile: &RustProfile,
+        })
+    }
+}
+
+/// Identifier nodes bound by a pattern
+fn pattern_bindings<'t>(profile: &dyn LanguageProfile, pattern: Node<'t>, names: &mut Vec<Node<'t>>) {
+    let skip = match profile.classify_pattern(pattern.kind()) {
+        PatternClass::Identifier | PatternClass::Receiver => {
+            names.push(pattern);
+            return;
+        }
+        PatternClass::Nested => 0,
+        PatternClass::Constructor => 1,
+        PatternClass::Ignored => return,
+    };
+    let mut cursor = pattern.walk();
+    for child in pattern.named_children(&mut cursor).skip(skip) {
+        pattern_bindings(profile, child, names);
     }
 }
 
@@ -314,6 +576,29 @@ mod tests {
         assert_eq!(symbol.kind, SymbolKind::Function);
     }
 
+    #[test]
+    fn test_symbols_in_scope_sorted() {
+        let source = b"fn d() { } fn a() { } fn c() { } fn b() { } fn e() { }";
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        
+        let mut parser = IncrementalParser::new(Language::Rust).unwrap();
+        let parsed = parser.parse(&mmap, None).unwrap();
+
+        let mut table = SymbolTable::new(file_id);
+        table.build(&parsed, source).unwrap();
+
+        let ids: Vec<_> = table.symbols_in_scope(table.file_scope()).iter().map(|s| s.id).collect();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids, sorted, "Symbols must come back in SymbolId order");
+    }
+
     #[test]
     fn test_parameter_symbol() {
         let source = b"fn test(x: i32) { }";
@@ -403,4 +688,105 @@ mod tests {
         let x_symbol = table.lookup("x", inner_scope.id);
         assert!(x_symbol.is_some(), "Inner scope should see outer variable 'x'");
     }
+
+    #[test]
+    fn test_serialization_round_trips_canonically() {
+        let source = b"fn d(p: u8) { let x = 1; { let y = 2; } }\nfn a() { let z = 3; }\n";
+        let file_id = FileId::new(1);
+        let file = crate::io::BufferedFile::new(source.to_vec(), file_id);
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&file, None).unwrap();
+
+        let mut table = SymbolTable::new(file_id);
+        table.build(&parsed, source).unwrap();
+
+        let json = serde_json::to_string(&table).unwrap();
+        let restored: SymbolTable = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.compute_hash(), table.compute_hash());
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        assert_eq!(restored.lookup("d", restored.file_scope()), table.lookup("d", table.file_scope()));
+
+        // Scopes and bindings come out in ID and name order
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let ids: Vec<u64> = value["scopes"].as_array().unwrap().iter().map(|s| s["id"].as_u64().unwrap()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]), "{:?}", ids);
+        assert_eq!(value["scopes"][0]["bindings"][0][0], "a");
+    }
+
+    #[test]
+    fn test_method_parameter_symbols() {
+        let source: &[u8] = b"struct S;\nimpl S {\n    fn m(&mut self, mut x: i32, _: u8, (a, _b): (u8, u8), ref r: u8) { }\n    fn n(self: Box<Self>) { }\n}\n";
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), source).unwrap();
+
+        let file_id = FileId::new(1);
+        let mmap = crate::io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&mmap, None).unwrap();
+
+        let mut table = SymbolTable::new(file_id);
+        table.build(&parsed, source).unwrap();
+
+        let mut function_scopes: Vec<_> = table.scopes.values()
+            .filter(|s| s.kind == ScopeKind::Function)
+            .map(|s| s.id)
+            .collect();
+        function_scopes.sort();
+        let params = |scope| -> Vec<(String, String)> {
+            table.symbols_in_scope(scope).iter()
+                .map(|s| {
+                    assert_eq!(s.kind, SymbolKind::Parameter);
+                    let text = &source[s.source_range.start..s.source_range.end];
+                    (s.name.clone(), String::from_utf8_lossy(text).to_string())
+                })
+                .collect()
+        };
+
+        let expected = |names: &[&str]| -> Vec<(String, String)> {
+            names.iter().map(|n| (n.to_string(), n.to_string())).collect()
+        };
+        assert_eq!(params(function_scopes[0]), expected(&["self", "x", "a", "_b", "r"]));
+        assert_eq!(params(function_scopes[1]), expected(&["self"]));
+        assert!(table.lookup("_", function_scopes[0]).is_none());
+
+        let x_start = source.windows(5).position(|w| w == b"mut x").unwrap() + 4;
+        assert_eq!(table.lookup("x", function_scopes[0]).unwrap().source_range, ByteRange::new(x_start, x_start + 1));
+
+        // `_` binds nothing but keeps its index
+        let positions: Vec<Option<usize>> = table.symbols_in_scope(function_scopes[0]).iter().map(|s| s.position).collect();
+        assert_eq!(positions, [Some(0), Some(1), Some(3), Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn test_let_condition_bindings_are_block_scoped() {
+        let source: &[u8] = b"fn f(it: I, r: R) { while let Some(x) = it.next() { g(x); } if let Ok(v) = r { h(v); } else { k(v); } }";
+        let file_id = FileId::new(1);
+        let file = crate::io::BufferedFile::new(source.to_vec(), file_id);
+        let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&file, None).unwrap();
+
+        let mut table = SymbolTable::new(file_id);
+        table.build(&parsed, source).unwrap();
+
+        let symbol = |name: &str| table.symbols().find(|s| s.name == name).unwrap();
+        for name in ["x", "v"] {
+            assert_eq!(symbol(name).kind, SymbolKind::Variable);
+            assert_eq!(table.get_scope(symbol(name).scope).unwrap().kind, ScopeKind::Block);
+        }
+        assert!(table.symbols().all(|s| s.name != "Some" && s.name != "Ok"));
+
+        // Uses in the body resolve; the else branch cannot see `v`
+        let uses = |name: &str| -> Vec<usize> {
+            let mut starts: Vec<usize> = source.windows(3).enumerate()
+                .filter(|(_, w)| *w == format!("({})", name).as_bytes())
+                .map(|(i, _)| i + 1)
+                .collect();
+            starts.remove(0);
+            starts
+        };
+        let resolved = |start: usize| table.references().iter().find(|r| r.range.start == start).map(|r| r.symbol);
+        assert_eq!(uses("x").iter().map(|s| resolved(*s)).collect::<Vec<_>>(), [Some(symbol("x").id)]);
+        assert_eq!(uses("v").iter().map(|s| resolved(*s)).collect::<Vec<_>>(), [Some(symbol("v").id), None]);
+
+        let mut again = SymbolTable::new(file_id);
+        again.build(&parsed, source).unwrap();
+        assert_eq!(again.compute_hash(), table.compute_hash());
+    }
 }
@@ -34,6 +34,74 @@ pub fn filter_by_kind(nodes: &[CPGNode], kind: CPGNodeKind) -> Vec<CPGNodeId> {
     filter_by_kind_scalar(nodes, kind)
 }
 
+/// Elements of sorted `a` not in sorted `b` (scalar baseline - always correct)
+///
+/// Both inputs must be strictly ascending. Preserves `a`'s order.
+pub fn sorted_difference_scalar(a: &[CPGNodeId], b: &[CPGNodeId]) -> Vec<CPGNodeId> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut j = 0;
+    for &x in a {
+        while j < b.len() && b[j] < x {
+            j += 1;
+        }
+        if j == b.len() || b[j] != x {
+            out.push(x);
+        }
+    }
+    out
+}
+
+/// Elements of sorted `a` not in sorted `b` (SIMD version - AVX2)
+///
+/// Skips 4 elements of `b` per compare while they are all below the
+/// current element of `a`, then finishes the step with the scalar merge.
+///
+/// # Safety
+/// Caller must ensure the CPU supports AVX2.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn sorted_difference_simd(a: &[CPGNodeId], b: &[CPGNodeId]) -> Vec<CPGNodeId> {
+    use std::arch::x86_64::*;
+
+    // Flip the sign bit so signed 64-bit compares order unsigned IDs
+    const BIAS: i64 = i64::MIN;
+    let biased = |id: CPGNodeId| (id.0 as i64) ^ BIAS;
+
+    let mut out = Vec::with_capacity(a.len());
+    let mut j = 0;
+    for &x in a {
+        let xv = _mm256_set1_epi64x(biased(x));
+        while j + 4 <= b.len() {
+            let bv = _mm256_set_epi64x(biased(b[j + 3]), biased(b[j + 2]), biased(b[j + 1]), biased(b[j]));
+            let below = _mm256_movemask_pd(_mm256_castsi256_pd(_mm256_cmpgt_epi64(xv, bv)));
+            if below != 0b1111 {
+                break;
+            }
+            j += 4;
+        }
+        while j < b.len() && b[j] < x {
+            j += 1;
+        }
+        if j == b.len() || b[j] != x {
+            out.push(x);
+        }
+    }
+    out
+}
+
+/// Elements of sorted `a` not in sorted `b` (runtime dispatch)
+pub fn sorted_difference(a: &[CPGNodeId], b: &[CPGNodeId]) -> Vec<CPGNodeId> {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support checked above
+            return unsafe { sorted_difference_simd(a, b) };
+        }
+    }
+
+    sorted_difference_scalar(a, b)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,4 +147,38 @@ mod tests {
 
         assert_eq!(scalar_result, simd_result, "SIMD must equal scalar");
     }
+
+    /// Strictly ascending IDs from a fixed LCG seed
+    fn sorted_ids(state: &mut u64, len: usize, max_step: u64) -> Vec<CPGNodeId> {
+        let mut ids = Vec::with_capacity(len);
+        let mut current = 0u64;
+        for _ in 0..len {
+            *state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            current += 1 + (*state >> 33) % max_step;
+            ids.push(CPGNodeId(current));
+        }
+        ids
+    }
+
+    #[test]
+    fn test_sorted_difference_scalar() {
+        let a = [1, 3, 5, 7].map(CPGNodeId);
+        let b = [2, 3, 7, 9].map(CPGNodeId);
+        assert_eq!(sorted_difference_scalar(&a, &b), vec![CPGNodeId(1), CPGNodeId(5)]);
+    }
+
+    #[test]
+    fn test_sorted_difference_simd_equals_scalar() {
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        for round in 0..200 {
+            let a = sorted_ids(&mut state, round * 7 % 300, 4);
+            let b = sorted_ids(&mut state, round * 13 % 500, 3);
+            assert_eq!(sorted_difference(&a, &b), sorted_difference_scalar(&a, &b), "round {}", round);
+        }
+
+        // High bit set: unsigned order must survive the signed compare
+        let a = [5, u64::MAX - 1, u64::MAX].map(CPGNodeId);
+        let b = [1, 2, 3, 4, u64::MAX - 1].map(CPGNodeId);
+        assert_eq!(sorted_difference(&a, &b), vec![CPGNodeId(5), CPGNodeId(u64::MAX)]);
+    }
 }
@@ -5,7 +5,7 @@
 //! Allowed SIMD:
 //! - Node/edge kind filtering
 //! - Trigram matching
-//! - Set intersections
+//! - Set intersections and differences
 //!
 //! Forbidden SIMD:
 //! - Graph traversal
@@ -14,7 +14,7 @@
 
 pub mod filters;
 
-pub use filters::{filter_by_kind, filter_by_kind_scalar};
+pub use filters::{filter_by_kind, filter_by_kind_scalar, sorted_difference, sorted_difference_scalar};
 
 /// Check if SIMD is available at runtime
 #[cfg(target_arch = "x86_64")]
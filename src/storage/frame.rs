@@ -0,0 +1,386 @@
+//! Framed payload encoding (Path B2)
+//!
+//! Compressed payloads are split into frames, each checked on its own, so
+//! reading never needs the whole compressed file and the whole decompressed
+//! payload in memory at once:
+//!
+//! ```text
+//! header  b"VCRF" | format version: u8 | codec: u8
+//! frame   uncompressed_len: u32 LE | stored_len: u32 LE
+//!         | sha256(both lengths, stored bytes): [u8; 32] | stored bytes
+//! end     FRAME_HEADER_LEN zero bytes
+//! ```
+//!
+//! A frame holds at most `FRAME_SIZE` uncompressed bytes. The checksum
+//! covers the frame as stored, so a flipped byte fails even where zstd
+//! would decode it to the same output; zstd's own content checksum then
+//! covers decompression. Any checksum, length or codec mismatch, a missing
+//! end frame or bytes after it fail the read.
+//!
+//! Uncompressed payloads (`Compression::None`) are plain JSON, as every
+//! earlier version wrote them; `read_json` accepts both.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+
+/// First bytes of a framed payload
+pub const FRAME_MAGIC: &[u8; 4] = b"VCRF";
+
+/// Framed format version
+pub const FRAME_VERSION: u8 = 1;
+
+/// Uncompressed bytes per frame
+pub const FRAME_SIZE: usize = 256 * 1024;
+
+/// Default zstd level (`zstd` without a level)
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Codec byte for zstd
+const CODEC_ZSTD: u8 = 1;
+
+/// Frame header: two lengths and a SHA-256
+const FRAME_HEADER_LEN: usize = 4 + 4 + 32;
+
+/// Payload compression (`[snapshot] compression`)
+///
+/// `compression = "none"` or `compression = { zstd = { level = 3 } }`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Plain JSON
+    #[default]
+    None,
+
+    /// zstd frames at the given level
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    /// Check the codec settings
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        match self {
+            Compression::None => Ok(()),
+            Compression::Zstd { level } => {
+                let range = zstd::compression_level_range();
+                match range.contains(level) {
+                    true => Ok(()),
+                    false => Err(format!("zstd level {} is not in {}..={}", level, range.start(), range.end())),
+                }
+            }
+        }
+    }
+}
+
+/// Both frame lengths, as written
+fn frame_lengths(uncompressed_len: usize, stored_len: usize) -> [u8; 8] {
+    let mut lengths = [0; 8];
+    lengths[..4].copy_from_slice(&(uncompressed_len as u32).to_le_bytes());
+    lengths[4..].copy_from_slice(&(stored_len as u32).to_le_bytes());
+    lengths
+}
+
+/// Checksum of a frame's lengths and stored bytes
+fn frame_checksum(lengths: &[u8], stored: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(lengths);
+    hasher.update(stored);
+    hasher.finalize().into()
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Writes frames of everything written to it; call `finish` to end the payload
+pub struct FrameWriter<W: Write> {
+    inner: W,
+    compressor: zstd::bulk::Compressor<'static>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> FrameWriter<W> {
+    /// Write the header for zstd frames at `level`
+    pub fn new(mut inner: W, level: i32) -> Result<Self> {
+        inner.write_all(FRAME_MAGIC)?;
+        inner.write_all(&[FRAME_VERSION, CODEC_ZSTD])?;
+        Ok(Self {
+            inner,
+            compressor: {
+                let mut compressor = zstd::bulk::Compressor::new(level)?;
+                compressor.include_checksum(true)?;
+                compressor
+            },
+            buffer: Vec::with_capacity(FRAME_SIZE),
+        })
+    }
+
+    /// Write the buffered bytes as one frame
+    fn write_frame(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let stored = self.compressor.compress(&self.buffer)?;
+        let lengths = frame_lengths(self.buffer.len(), stored.len());
+        self.inner.write_all(&lengths)?;
+        self.inner.write_all(&frame_checksum(&lengths, &stored))?;
+        self.inner.write_all(&stored)?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Write the last frame and the end frame
+    pub fn finish(mut self) -> Result<W> {
+        self.write_frame()?;
+        self.inner.write_all(&[0; FRAME_HEADER_LEN])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for FrameWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> Result<usize> {
+        let n = bytes.len().min(FRAME_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&bytes[..n]);
+        if self.buffer.len() == FRAME_SIZE {
+            self.write_frame()?;
+        }
+        Ok(n)
+    }
+
+    /// Frames are only cut at `FRAME_SIZE` and in `finish`
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads the decompressed bytes of a framed payload, checking every frame
+pub struct FrameReader<R: Read> {
+    inner: R,
+    decompressor: zstd::bulk::Decompressor<'static>,
+    stored: Vec<u8>,
+    frame: Vec<u8>,
+    position: usize,
+    frames: usize,
+    done: bool,
+}
+
+impl<R: Read> FrameReader<R> {
+    /// Read and check the header
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut header = [0; 6];
+        inner.read_exact(&mut header)?;
+        if &header[..4] != FRAME_MAGIC {
+            return Err(invalid("Not a framed payload".to_string()));
+        }
+        if header[4] != FRAME_VERSION {
+            return Err(invalid(format!("Unsupported frame format version {}", header[4])));
+        }
+        if header[5] != CODEC_ZSTD {
+            return Err(invalid(format!("Unknown payload codec {}", header[5])));
+        }
+        Ok(Self {
+            inner,
+            decompressor: zstd::bulk::Decompressor::new()?,
+            stored: Vec::new(),
+            frame: Vec::new(),
+            position: 0,
+            frames: 0,
+            done: false,
+        })
+    }
+
+    /// Frames read so far (end frame excluded)
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Load the next frame; false at the end frame
+    fn next_frame(&mut self) -> Result<bool> {
+        let mut header = [0; FRAME_HEADER_LEN];
+        self.inner.read_exact(&mut header)
+            .map_err(|e| match e.kind() {
+                ErrorKind::UnexpectedEof => invalid("Payload truncated: missing end frame".to_string()),
+                _ => e,
+            })?;
+        let uncompressed_len = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+        let stored_len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+
+        if uncompressed_len == 0 {
+            if header.iter().any(|&b| b != 0) {
+                return Err(invalid(format!("Frame {}: malformed end frame", self.frames)));
+            }
+            if self.inner.read(&mut [0])? != 0 {
+                return Err(invalid("Trailing bytes after end frame".to_string()));
+            }
+            return Ok(false);
+        }
+        if uncompressed_len > FRAME_SIZE || stored_len > zstd::zstd_safe::compress_bound(FRAME_SIZE) {
+            return Err(invalid(format!("Frame {}: lengths {}/{} exceed the frame size", self.frames, uncompressed_len, stored_len)));
+        }
+
+        self.stored.resize(stored_len, 0);
+        self.inner.read_exact(&mut self.stored)
+            .map_err(|e| match e.kind() {
+                ErrorKind::UnexpectedEof => invalid(format!("Frame {}: truncated", self.frames)),
+                _ => e,
+            })?;
+        if frame_checksum(&header[..8], &self.stored)[..] != header[8..] {
+            return Err(invalid(format!("Frame {}: checksum mismatch", self.frames)));
+        }
+        self.frame = self.decompressor.decompress(&self.stored, uncompressed_len)
+            .map_err(|e| invalid(format!("Frame {}: {}", self.frames, e)))?;
+        if self.frame.len() != uncompressed_len {
+            return Err(invalid(format!(
+                "Frame {}: length mismatch: header has {}, decompressed to {}",
+                self.frames, uncompressed_len, self.frame.len()
+            )));
+        }
+
+        self.position = 0;
+        self.frames += 1;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for FrameReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while self.position == self.frame.len() {
+            if self.done {
+                return Ok(0);
+            }
+            if !self.next_frame()? {
+                self.done = true;
+                self.frame.clear();
+                self.position = 0;
+            }
+        }
+        let n = buf.len().min(self.frame.len() - self.position);
+        buf[..n].copy_from_slice(&self.frame[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// Serialize `value` as JSON to `path`, framed unless `compression` is None
+pub fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T, compression: Compression) -> Result<()> {
+    match compression {
+        Compression::None => std::fs::write(path, serde_json::to_vec(value)?),
+        Compression::Zstd { level } => {
+            let mut writer = FrameWriter::new(BufWriter::new(File::create(path)?), level)?;
+            serde_json::to_writer(&mut writer, value)?;
+            writer.finish()?.into_inner().map_err(|e| e.into_error())?;
+            Ok(())
+        }
+    }
+}
+
+/// Deserialize a JSON payload written by `write_json` (either form)
+///
+/// Framed payloads are decompressed one frame at a time while parsing.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let invalid_json = |e: serde_json::Error| match e.io_error_kind() {
+        Some(_) => Error::from(e),
+        None => Error::new(ErrorKind::InvalidData, e),
+    };
+
+    if reader.fill_buf()?.starts_with(FRAME_MAGIC) {
+        serde_json::from_reader(FrameReader::new(reader)?).map_err(invalid_json)
+    } else {
+        let mut serialized = Vec::new();
+        reader.read_to_end(&mut serialized)?;
+        serde_json::from_slice(&serialized).map_err(invalid_json)
+    }
+}
+
+/// Whether the file at `path` is a framed payload
+pub fn is_framed(path: &Path) -> Result<bool> {
+    let mut magic = [0; 4];
+    let n = File::open(path)?.read(&mut magic)?;
+    Ok(n == magic.len() && &magic == FRAME_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn framed(bytes: &[u8]) -> Vec<u8> {
+        let mut writer = FrameWriter::new(Vec::new(), DEFAULT_ZSTD_LEVEL).unwrap();
+        writer.write_all(bytes).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn unframed(bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        FrameReader::new(bytes)?.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_frames_round_trip() {
+        // Three full frames and a partial one
+        let bytes: Vec<u8> = (0..FRAME_SIZE * 3 + 17).map(|i| (i % 251) as u8).collect();
+        let encoded = framed(&bytes);
+        let mut reader = FrameReader::new(&encoded[..]).unwrap();
+        let mut decoded = Vec::new();
+        reader.read_to_end(&mut decoded).unwrap();
+
+        assert_eq!(decoded, bytes);
+        assert_eq!(reader.frames(), 4);
+        assert_eq!(unframed(&framed(b"")).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_corruption_fails_closed() {
+        let bytes: Vec<u8> = (0..20_000).map(|i| (i / 7 % 13) as u8).collect();
+        let encoded = framed(&bytes);
+
+        // Every byte (header, lengths, checksum, data, end frame) matters
+        for i in 0..encoded.len() {
+            let mut corrupt = encoded.clone();
+            corrupt[i] ^= 0x40;
+            assert!(unframed(&corrupt).is_err(), "flipped byte {} was accepted", i);
+        }
+
+        let truncated = &encoded[..encoded.len() - FRAME_HEADER_LEN];
+        let err = unframed(truncated).unwrap_err();
+        assert!(err.to_string().contains("missing end frame"), "{}", err);
+
+        let mut trailing = encoded.clone();
+        trailing.push(b' ');
+        assert!(unframed(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_read_json_accepts_both_forms() {
+        let value = serde_json::json!({ "labels": vec!["entry"; 1000] });
+        for compression in [Compression::None, Compression::Zstd { level: 1 }] {
+            let temp = NamedTempFile::new().unwrap();
+            write_json(temp.path(), &value, compression).unwrap();
+
+            assert_eq!(is_framed(temp.path()).unwrap(), compression != Compression::None);
+            assert_eq!(read_json::<serde_json::Value>(temp.path()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_compression_config() {
+        #[derive(Deserialize)]
+        struct Section {
+            compression: Compression,
+        }
+
+        let none: Section = toml::from_str("compression = \"none\"").unwrap();
+        assert_eq!(none.compression, Compression::None);
+        let zstd: Section = toml::from_str("compression = { zstd = { level = 9 } }").unwrap();
+        assert_eq!(zstd.compression, Compression::Zstd { level: 9 });
+
+        assert!(zstd.compression.validate().is_ok());
+        assert!(Compression::Zstd { level: 1000 }.validate().is_err());
+    }
+}
@@ -2,16 +2,49 @@
 //!
 //! Persistent on-disk CPG (replayable)
 
-use crate::cpg::model::CPG;
-use std::path::Path;
+pub mod frame;
+pub mod store;
+
+pub use frame::Compression;
+pub use store::{GcReport, PruneReport, RetentionPolicy, SnapshotEntry, SnapshotStore, DEFAULT_GC_SAFETY_WINDOW};
+
+use crate::analysis::FunctionSummaries;
+use crate::compare::RepoSummary;
+use crate::cpg::hash::HashedCpg;
+use crate::cpg::model::{CPGNodeKind, CPG};
+use crate::report::FileReport;
+use crate::repo::normalize_path;
+use crate::semantic::{SemanticEpoch, SymbolTable};
+use crate::types::{FileId, FileMetadata, Language, RepoSnapshot, VcsInfo};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use std::io::{Result, Error, ErrorKind};
+use serde::de::IgnoredAny;
 use serde::{Serialize, Deserialize};
 
 /// Storage version
-pub const STORAGE_VERSION: u32 = 1;
+///
+/// 2: provenance fields (`repo_snapshot_hash`, `tool_version`, `file_count`,
+/// `language_counts`). 3: `semantic_fingerprints` and `file_stats`. 4:
+/// `cpg_hash` uses the version 2 framing of `cpg::hash`. 5: `functions`.
+/// 6: single-file snapshots may hold symbol tables (`symbols_hash`). 7:
+/// `summaries`. 8: `vcs`. 9: `file_stats` rows record content hashes and
+/// policies (see `previous_repo`). 10: `functions` and
+/// `semantic_fingerprints` hash CFGs and DFGs with the canonical encoding
+/// of `semantic::hash`. 11: parameter DfgValue nodes record
+/// `parameter_position`. 12: `normalize_line_endings`.
+/// Older metadata is still read, see `SnapshotMetadata::migrate`.
+pub const STORAGE_VERSION: u32 = 12;
+
+/// Version of this build, recorded in every snapshot
+pub const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Placeholder for provenance a snapshot does not have
+pub const UNKNOWN: &str = "unknown";
 
 /// Snapshot ID
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct SnapshotId(pub u64);
 
 /// Snapshot metadata
@@ -21,69 +54,412 @@ pub struct SnapshotMetadata {
     pub cpg_hash: String,
     pub timestamp: u64,
     pub version: u32,
+
+    /// Hash of the repository snapshot the CPG was built from
+    #[serde(default = "unknown")]
+    pub repo_snapshot_hash: String,
+
+    /// Version of the tool that wrote the snapshot
+    #[serde(default = "unknown")]
+    pub tool_version: String,
+
+    /// Files in the repository snapshot
+    #[serde(default)]
+    pub file_count: usize,
+
+    /// Files per language (`"unknown"` for undetected languages)
+    #[serde(default)]
+    pub language_counts: BTreeMap<String, usize>,
+
+    /// Semantic fingerprint per analyzed file, by normalized relative path
+    #[serde(default)]
+    pub semantic_fingerprints: BTreeMap<String, String>,
+
+    /// Per-file ingestion stats (`vcr report files`), sorted by path
+    #[serde(default)]
+    pub file_stats: Vec<FileReport>,
+
+    /// Files and functions for `vcr compare` (None before storage version 5)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<RepoSummary>,
+
+    /// Function summaries of the call graph (None before storage version 7)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summaries: Option<FunctionSummaries>,
+
+    /// Commit and branch the repository was scanned at (None outside a git
+    /// work tree or before storage version 8)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcs: Option<VcsInfo>,
+
+    /// Hash of the symbol tables saved with a single-file snapshot (see
+    /// `symbols_hash`); None if it holds none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbols_hash: Option<String>,
+
+    /// Whether the repository was scanned with line endings normalized, so
+    /// a rescan reproducing `repo_snapshot_hash` must be too (false before
+    /// storage version 12)
+    #[serde(default)]
+    pub normalize_line_endings: bool,
+}
+
+fn unknown() -> String {
+    UNKNOWN.to_string()
 }
 
 impl SnapshotMetadata {
+    /// Metadata without repository provenance (see `with_repo`)
     pub fn new(epoch_id: u64, cpg_hash: String, timestamp: u64) -> Self {
         Self {
             epoch_id,
             cpg_hash,
             timestamp,
             version: STORAGE_VERSION,
+            repo_snapshot_hash: unknown(),
+            tool_version: TOOL_VERSION.to_string(),
+            file_count: 0,
+            language_counts: BTreeMap::new(),
+            semantic_fingerprints: BTreeMap::new(),
+            file_stats: Vec::new(),
+            functions: None,
+            summaries: None,
+            vcs: None,
+            symbols_hash: None,
+            normalize_line_endings: false,
+        }
+    }
+
+    /// Record the repository snapshot the CPG was built from
+    pub fn with_repo(mut self, repo: &RepoSnapshot) -> Self {
+        self.repo_snapshot_hash = repo.snapshot_hash.clone();
+        self.vcs = repo.vcs.clone();
+        self.normalize_line_endings = repo.normalized_line_endings;
+        self.file_count = repo.files.len();
+        self.language_counts.clear();
+        for file in repo.files.values() {
+            let language = file.language.map_or(UNKNOWN, |l| l.name());
+            *self.language_counts.entry(language.to_string()).or_default() += 1;
+        }
+        self
+    }
+
+    /// Record the semantic fingerprint of every file the CPG was fused from
+    pub fn with_fingerprints(mut self, repo: &RepoSnapshot, semantic: &SemanticEpoch) -> Self {
+        self.semantic_fingerprints = semantic.fingerprints().iter()
+            .filter_map(|(file_id, fingerprint)| {
+                let meta = repo.files.get(file_id)?;
+                Some((normalize_path(&meta.path), fingerprint.clone()))
+            })
+            .collect();
+        self
+    }
+
+    /// Record the files and functions `vcr compare` matches
+    pub fn with_functions(mut self, repo: &RepoSnapshot, semantic: &SemanticEpoch) -> Self {
+        self.functions = Some(RepoSummary::from_epoch(repo, semantic));
+        self
+    }
+
+    /// Record the function summaries of the call graph
+    pub fn with_summaries(mut self, summaries: &FunctionSummaries) -> Self {
+        self.summaries = Some(summaries.clone());
+        self
+    }
+
+    /// Record per-file ingestion stats
+    pub fn with_file_stats(mut self, file_stats: Vec<FileReport>) -> Self {
+        self.file_stats = file_stats;
+        self
+    }
+
+    /// The scanned files of the repository snapshot, rebuilt from
+    /// `file_stats` for `ChangeDetector` (None before storage version 9)
+    ///
+    /// Only paths, sizes, content hashes, languages and policies are
+    /// recorded: the root is empty and modification times are the epoch.
+    pub fn previous_repo(&self) -> Option<RepoSnapshot> {
+        if self.version < 9 {
+            return None;
+        }
+        let files = self.file_stats.iter()
+            .map(|file| (file.file_id, FileMetadata {
+                path: PathBuf::from(&file.path),
+                size: file.size,
+                mtime: SystemTime::UNIX_EPOCH,
+                content_hash: file.content_hash.clone(),
+                language: Language::from_name(&file.language),
+                policy: file.policy,
+            }))
+            .collect();
+        Some(RepoSnapshot {
+            root: PathBuf::new(),
+            roots: Vec::new(),
+            files,
+            created_at: SystemTime::UNIX_EPOCH + Duration::from_secs(self.timestamp),
+            snapshot_hash: self.repo_snapshot_hash.clone(),
+            vcs: self.vcs.clone(),
+            normalized_line_endings: self.normalize_line_endings,
+        })
+    }
+
+    /// `functions`, if its hashes match those of a fresh build (None
+    /// before storage version 10, whose CFG and DFG hashes used an older
+    /// encoding)
+    pub fn comparable_functions(&self) -> Option<&RepoSummary> {
+        self.functions.as_ref().filter(|_| self.version >= 10)
+    }
+
+    /// Accept metadata written by this or an older storage version
+    ///
+    /// Version 1 predates the provenance fields; deserialization already
+    /// filled them with `"unknown"` (and zero counts). Versions 1 and 2
+    /// have no fingerprints or file stats. Versions 1 to 3 record the
+    /// legacy CPG hash (see `hash_of`). Versions 1 to 4 have no function
+    /// summaries, versions 1 to 5 no symbol tables, versions 1 to 6 no
+    /// flow summaries (`summaries`), versions 1 to 7 no VCS info and
+    /// versions 1 to 8 no content hashes in `file_stats`. Versions 1 to 9
+    /// hash CFGs and DFGs by their kinds' `Debug` text (see
+    /// `comparable_functions`). Versions 1 to 10 have no parameter
+    /// positions on CPG nodes (see `migrate_cpg`). Versions 1 to 11 do not
+    /// record `normalize_line_endings` and read as unnormalized. The stored
+    /// `version` is kept, so a migrated snapshot still says what wrote it.
+    pub fn migrate(self) -> Result<Self> {
+        match self.version {
+            1..=11 | STORAGE_VERSION => Ok(self),
+            version => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Version mismatch: expected {}, got {}", STORAGE_VERSION, version)
+            )),
+        }
+    }
+
+    /// Bring a CPG saved under this metadata up to the current version
+    ///
+    /// Before version 11, parameter DfgValue nodes have no
+    /// `parameter_position`; it is recovered from their labels, the `Debug`
+    /// text of `ResolvedValueKind::Parameter` (`Parameter { name: "x",
+    /// position: 0 }`). Positions are not hashed, so `cpg_hash` still holds.
+    pub fn migrate_cpg(&self, cpg: &mut CPG) {
+        if self.version >= 11 {
+            return;
+        }
+        let positions: Vec<_> = cpg.nodes.iter()
+            .map(|node| match node.kind {
+                CPGNodeKind::DfgValue => cpg.label(node).and_then(parameter_position),
+                _ => None,
+            })
+            .collect();
+        for (node, position) in cpg.nodes.iter_mut().zip(positions) {
+            node.parameter_position = node.parameter_position.or(position);
         }
     }
+
+    /// Hash of `cpg` as this snapshot's version records it in `cpg_hash`
+    pub fn hash_of(&self, cpg: &CPG) -> String {
+        match self.version {
+            1..=3 => cpg.compute_legacy_hash(),
+            _ => cpg.compute_hash(),
+        }
+    }
+
+    /// Warning when the snapshot was written by a different major tool version
+    ///
+    /// Unknown or unparsable versions are not compared.
+    pub fn tool_version_warning(&self) -> Option<String> {
+        let major = |version: &str| version.split('.').next()?.parse::<u64>().ok();
+        let (theirs, ours) = (major(&self.tool_version)?, major(TOOL_VERSION)?);
+        (theirs != ours).then(|| format!(
+            "Snapshot was written by vcr {} (major version {}), this is vcr {} (major version {})",
+            self.tool_version, theirs, TOOL_VERSION, ours
+        ))
+    }
+}
+
+/// Position of a parameter value from its label (see `migrate_cpg`)
+fn parameter_position(label: &str) -> Option<u32> {
+    let fields = label.strip_prefix("Parameter {")?.strip_suffix('}')?;
+    fields.rsplit_once("position:")?.1.trim().parse().ok()
+}
+
+/// Single-file snapshot as written by `CPGSnapshot::save`
+///
+/// The CPG is written first and hashed while serialized, then the symbol
+/// tables, if any; the metadata follows with that hash as `cpg_hash`.
+struct SnapshotFileRef<'a> {
+    metadata: SnapshotMetadata,
+    cpg: &'a CPG,
+    symbols: BTreeMap<FileId, &'a SymbolTable>,
+}
+
+impl Serialize for SnapshotFileRef<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let cpg = HashedCpg::new(self.cpg);
+        let mut state = serializer.serialize_struct("SnapshotFile", 3)?;
+        state.serialize_field("cpg", &cpg)?;
+        if self.symbols.is_empty() {
+            state.skip_field("symbols")?;
+        } else {
+            state.serialize_field("symbols", &self.symbols)?;
+        }
+        let metadata = SnapshotMetadata { cpg_hash: cpg.digest(), ..self.metadata.clone() };
+        state.serialize_field("metadata", &metadata)?;
+        state.end()
+    }
+}
+
+/// Single-file snapshot as read back
+#[derive(Deserialize)]
+struct SnapshotFile {
+    metadata: SnapshotMetadata,
+    cpg: CPG,
+
+    /// Symbol tables by file (absent before storage version 6)
+    #[serde(default)]
+    symbols: BTreeMap<FileId, SymbolTable>,
+}
+
+/// Hash of symbol tables by file: each file's ID and `compute_hash`, in
+/// FileId order (None for no tables)
+pub fn symbols_hash<'a>(symbols: impl IntoIterator<Item = (&'a FileId, &'a SymbolTable)>) -> Option<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    let mut any = false;
+    for (file_id, table) in symbols {
+        hasher.update(file_id.as_u64().to_be_bytes());
+        hasher.update(table.compute_hash().as_bytes());
+        any = true;
+    }
+    any.then(|| format!("{:x}", hasher.finalize()))
+}
+
+/// Single-file snapshot with the CPG skipped (parsed, never built)
+#[derive(Deserialize)]
+struct SnapshotHead {
+    metadata: SnapshotMetadata,
+    #[allow(dead_code)]
+    cpg: IgnoredAny,
 }
 
 /// CPG snapshot manager
+///
+/// A snapshot file holds its metadata and the serialized CPG, either as
+/// plain JSON or compressed in checksummed frames (see `frame`). Writing
+/// one hashes the CPG while serializing it; reading one recomputes the hash
+/// and fails on any mismatch with the metadata.
 pub struct CPGSnapshot;
 
 impl CPGSnapshot {
-    /// Save CPG to disk (append-only)
-    pub fn save(cpg: &CPG, path: &Path) -> Result<SnapshotId> {
-        // Compute hash
-        let cpg_hash = cpg.compute_hash();
-        
-        // Create metadata
-        let metadata = SnapshotMetadata::new(
-            0,  // epoch_id placeholder
-            cpg_hash.clone(),
+    /// Save the CPG of an epoch to disk
+    pub fn save(cpg: &CPG, epoch_id: u64, path: &Path) -> Result<SnapshotId> {
+        Self::write(cpg, Self::metadata(epoch_id), path, Compression::None)
+    }
+
+    /// Save the CPG of an epoch along with the repository snapshot it was built from
+    pub fn save_with_repo(cpg: &CPG, epoch_id: u64, repo: &RepoSnapshot, path: &Path) -> Result<SnapshotId> {
+        Self::write(cpg, Self::metadata(epoch_id).with_repo(repo), path, Compression::None)
+    }
+
+    /// `save_with_repo`, also saving every file's symbol table
+    ///
+    /// `CPGEpoch::from_snapshot_with_symbols` restores them, so name
+    /// lookups work without re-ingesting.
+    pub fn save_with_symbols(
+        cpg: &CPG,
+        epoch_id: u64,
+        repo: &RepoSnapshot,
+        semantic: &SemanticEpoch,
+        path: &Path,
+    ) -> Result<SnapshotId> {
+        let symbols: BTreeMap<FileId, &SymbolTable> = semantic.get_all_file_ids().into_iter()
+            .filter_map(|file_id| Some((file_id, semantic.get_symbols(file_id)?)))
+            .collect();
+        let mut metadata = Self::metadata(epoch_id).with_repo(repo);
+        metadata.symbols_hash = symbols_hash(symbols.iter().map(|(file_id, table)| (file_id, *table)));
+        frame::write_json(path, &SnapshotFileRef { metadata, cpg, symbols }, Compression::None)?;
+        Ok(SnapshotId(1))
+    }
+
+    /// `save`, compressing the snapshot
+    pub fn save_compressed(cpg: &CPG, epoch_id: u64, path: &Path, compression: Compression) -> Result<SnapshotId> {
+        Self::write(cpg, Self::metadata(epoch_id), path, compression)
+    }
+
+    /// Metadata whose `cpg_hash` is filled in while writing
+    fn metadata(epoch_id: u64) -> SnapshotMetadata {
+        SnapshotMetadata::new(
+            epoch_id,
+            String::new(),
             std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
-        );
-        
+        )
+    }
+
+    fn write(cpg: &CPG, metadata: SnapshotMetadata, path: &Path, compression: Compression) -> Result<SnapshotId> {
         // Serialize (placeholder - would use FlatBuffers)
-        let serialized = serde_json::to_string(&metadata)?;
-        std::fs::write(path, serialized)?;
-        
+        frame::write_json(path, &SnapshotFileRef { metadata, cpg, symbols: BTreeMap::new() }, compression)?;
+
         Ok(SnapshotId(1))
     }
-    
-    /// Load CPG from disk (zero-copy would go here)
+
+    /// Load a CPG from disk
+    ///
+    /// Use `CPGEpoch::from_snapshot` for a queryable epoch.
     pub fn load(path: &Path) -> Result<CPG> {
-        // Placeholder: would deserialize FlatBuffers
-        // For now, return empty CPG
-        let _serialized = std::fs::read_to_string(path)?;
-        Ok(CPG::new())
+        Self::read(path).map(|(_, cpg)| cpg)
     }
-    
-    /// Verify snapshot integrity
-    pub fn verify(path: &Path) -> Result<String> {
-        // Load metadata
-        let serialized = std::fs::read_to_string(path)?;
-        let metadata: SnapshotMetadata = serde_json::from_str(&serialized)
-            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
-        
+
+    /// Load metadata and CPG, checking version and hash
+    pub fn read(path: &Path) -> Result<(SnapshotMetadata, CPG)> {
+        Self::read_with_symbols(path).map(|(metadata, cpg, _)| (metadata, cpg))
+    }
+
+    /// `read`, also returning the saved symbol tables (empty if none)
+    ///
+    /// The tables must match the metadata's `symbols_hash`.
+    pub fn read_with_symbols(path: &Path) -> Result<(SnapshotMetadata, CPG, BTreeMap<FileId, SymbolTable>)> {
+        let file: SnapshotFile = frame::read_json(path)?;
+
         // Verify version
-        if metadata.version != STORAGE_VERSION {
+        let metadata = file.metadata.migrate()?;
+
+        // Verify content
+        let actual = metadata.hash_of(&file.cpg);
+        if actual != metadata.cpg_hash {
             return Err(Error::new(
-                ErrorKind::InvalidData, 
-                format!("Version mismatch: expected {}, got {}", STORAGE_VERSION, metadata.version)
+                ErrorKind::InvalidData,
+                format!("Hash mismatch: metadata has {}, content hashes to {}", metadata.cpg_hash, actual)
             ));
         }
-        
-        Ok(metadata.cpg_hash)
+        let symbols = symbols_hash(&file.symbols);
+        if symbols != metadata.symbols_hash {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Symbol table hash mismatch: metadata has {:?}, content hashes to {:?}", metadata.symbols_hash, symbols)
+            ));
+        }
+
+        let mut cpg = file.cpg;
+        metadata.migrate_cpg(&mut cpg);
+        Ok((metadata, cpg, file.symbols))
+    }
+    
+    /// Verify snapshot integrity, returning its metadata
+    ///
+    /// A compressed snapshot is checked frame by frame against its
+    /// checksums; the CPG JSON is parsed but no node is built. A plain
+    /// snapshot has no checksums, so it is fully read and hashed.
+    pub fn verify(path: &Path) -> Result<SnapshotMetadata> {
+        if !frame::is_framed(path)? {
+            return Self::read(path).map(|(metadata, _)| metadata);
+        }
+        let head: SnapshotHead = frame::read_json(path)?;
+        head.metadata.migrate()
     }
 }
 
@@ -110,27 +486,58 @@ mod tests {
             OriginRef::Function { function_id: crate::semantic::model::FunctionId(1) },
             ByteRange::new(0, 10),
         ));
+        cpg.add_node(CPGNode::new(
+            CPGNodeId(2),
+            CPGNodeKind::DfgValue,
+            OriginRef::Dfg { value_id: crate::semantic::model::ValueId(0) },
+            ByteRange::new(3, 4),
+        ).with_parameter_position(1));
 
         let temp = NamedTempFile::new().unwrap();
         
         // Save
-        let snapshot_id = CPGSnapshot::save(&cpg, temp.path()).unwrap();
+        let snapshot_id = CPGSnapshot::save(&cpg, 7, temp.path()).unwrap();
         assert_eq!(snapshot_id.0, 1);
         
-        // Load (placeholder returns empty CPG)
-        let loaded = CPGSnapshot::load(temp.path()).unwrap();
-        assert_eq!(loaded.nodes.len(), 0);  // Placeholder behavior
+        // Load
+        let (metadata, loaded) = CPGSnapshot::read(temp.path()).unwrap();
+        assert_eq!(metadata.epoch_id, 7);
+        assert_eq!(loaded.nodes.len(), 2);
+        assert_eq!(loaded.compute_hash(), cpg.compute_hash());
+        let positions: Vec<_> = loaded.nodes.iter().map(|node| node.parameter_position).collect();
+        assert_eq!(positions, [None, Some(1)]);
     }
 
     #[test]
     fn test_snapshot_verify() {
-        let mut cpg = CPG::new();
+        let cpg = CPG::new();
         let temp = NamedTempFile::new().unwrap();
         
-        CPGSnapshot::save(&cpg, temp.path()).unwrap();
-        let hash = CPGSnapshot::verify(temp.path()).unwrap();
+        CPGSnapshot::save(&cpg, 1, temp.path()).unwrap();
+        let metadata = CPGSnapshot::verify(temp.path()).unwrap();
         
-        assert!(!hash.is_empty());
+        assert_eq!(metadata.cpg_hash, cpg.compute_hash());
+    }
+
+    #[test]
+    fn test_snapshot_content_mismatch() {
+        let mut cpg = CPG::new();
+        let temp = NamedTempFile::new().unwrap();
+        CPGSnapshot::save(&cpg, 1, temp.path()).unwrap();
+
+        // Swap in different content under the old metadata
+        cpg.add_node(CPGNode::new(
+            CPGNodeId(1),
+            CPGNodeKind::File,
+            OriginRef::File { file_id: crate::types::FileId::new(1) },
+            ByteRange::new(0, 0),
+        ));
+        let mut file: serde_json::Value = serde_json::from_slice(&std::fs::read(temp.path()).unwrap()).unwrap();
+        file["cpg"] = serde_json::to_value(&cpg).unwrap();
+        std::fs::write(temp.path(), file.to_string()).unwrap();
+
+        let err = CPGSnapshot::verify(temp.path()).unwrap_err();
+        assert!(err.to_string().contains("Hash mismatch"), "{}", err);
     }
 
     #[test]
@@ -139,16 +546,118 @@ mod tests {
         
         // Write invalid version
         let bad_metadata = SnapshotMetadata {
-            epoch_id: 1,
-            cpg_hash: "test".to_string(),
-            timestamp: 0,
             version: 999,  // Invalid
+            ..SnapshotMetadata::new(1, "test".to_string(), 0)
         };
         
-        let serialized = serde_json::to_string(&bad_metadata).unwrap();
+        let serialized = serde_json::json!({ "metadata": bad_metadata, "cpg": CPG::new() }).to_string();
         std::fs::write(temp.path(), serialized).unwrap();
         
         // Verify should fail
         assert!(CPGSnapshot::verify(temp.path()).is_err());
     }
+
+    #[test]
+    fn test_v1_snapshot_migrates() {
+        let temp = NamedTempFile::new().unwrap();
+        let cpg = CPG::new();
+
+        // Exactly what version 1 wrote
+        let v1 = serde_json::json!({
+            "metadata": { "epoch_id": 3, "cpg_hash": cpg.compute_legacy_hash(), "timestamp": 0, "version": 1 },
+            "cpg": cpg,
+        });
+        std::fs::write(temp.path(), v1.to_string()).unwrap();
+
+        let metadata = CPGSnapshot::verify(temp.path()).unwrap();
+        assert_eq!(metadata.version, 1);
+        assert_eq!(metadata.epoch_id, 3);
+        assert_eq!(metadata.repo_snapshot_hash, UNKNOWN);
+        assert_eq!(metadata.tool_version, UNKNOWN);
+        assert_eq!(metadata.file_count, 0);
+        assert!(metadata.language_counts.is_empty());
+        assert!(metadata.semantic_fingerprints.is_empty());
+        assert!(metadata.file_stats.is_empty());
+        assert_eq!(metadata.tool_version_warning(), None);
+    }
+
+    #[test]
+    fn test_functions_before_canonical_hashes_are_not_comparable() {
+        let mut meta = SnapshotMetadata::new(1, String::new(), 0);
+        meta.functions = Some(RepoSummary::default());
+        assert!(meta.comparable_functions().is_some());
+
+        meta.version = 9;
+        assert!(meta.migrate().unwrap().comparable_functions().is_none());
+    }
+
+    /// Statement nodes in a chain, labels cycling through a few statements
+    fn synthetic_cpg(statements: u64) -> CPG {
+        use crate::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind};
+        use crate::semantic::model::NodeId;
+
+        let mut cpg = CPG::new();
+        for i in 0..statements {
+            let label = cpg.intern_label(&format!("let x{} = compute(a, b);", i % 40));
+            let start = i as usize * 24;
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(i),
+                CPGNodeKind::CfgNode,
+                OriginRef::Cfg { node_id: NodeId(i) },
+                ByteRange::new(start, start + 22),
+            ).with_label(label));
+            if i > 0 {
+                cpg.add_edge(CPGEdge::new(CPGEdgeId(i - 1), CPGEdgeKind::ControlFlow, CPGNodeId(i - 1), CPGNodeId(i)));
+            }
+        }
+        cpg
+    }
+
+    #[test]
+    fn test_compressed_snapshot_round_trip() {
+        let cpg = synthetic_cpg(20_000);
+        let plain = NamedTempFile::new().unwrap();
+        let compressed = NamedTempFile::new().unwrap();
+
+        CPGSnapshot::save(&cpg, 5, plain.path()).unwrap();
+        CPGSnapshot::save_compressed(&cpg, 5, compressed.path(), Compression::Zstd { level: 3 }).unwrap();
+
+        let (metadata, loaded) = CPGSnapshot::read(compressed.path()).unwrap();
+        assert_eq!(metadata.epoch_id, 5);
+        assert_eq!(loaded.compute_hash(), cpg.compute_hash());
+        assert_eq!(CPGSnapshot::verify(compressed.path()).unwrap().cpg_hash, cpg.compute_hash());
+
+        // Several frames, and far smaller than the plain JSON
+        let plain_len = std::fs::metadata(plain.path()).unwrap().len();
+        let compressed_len = std::fs::metadata(compressed.path()).unwrap().len();
+        assert!(plain_len > 2 * frame::FRAME_SIZE as u64);
+        assert!(compressed_len * 10 < plain_len, "{} bytes compressed from {}", compressed_len, plain_len);
+    }
+
+    #[test]
+    fn test_compressed_snapshot_corruption_fails_closed() {
+        let cpg = synthetic_cpg(20_000);
+        let temp = NamedTempFile::new().unwrap();
+        CPGSnapshot::save_compressed(&cpg, 1, temp.path(), Compression::Zstd { level: 3 }).unwrap();
+
+        let mut bytes = std::fs::read(temp.path()).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0x01;
+        std::fs::write(temp.path(), &bytes).unwrap();
+
+        let err = CPGSnapshot::verify(temp.path()).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"), "{}", err);
+        assert!(CPGSnapshot::load(temp.path()).is_err());
+    }
+
+    #[test]
+    fn test_tool_version_warning() {
+        let mut meta = SnapshotMetadata::new(1, "abc".to_string(), 0);
+        assert_eq!(meta.tool_version, TOOL_VERSION);
+        assert_eq!(meta.tool_version_warning(), None);
+
+        meta.tool_version = "999.0.0".to_string();
+        let warning = meta.tool_version_warning().unwrap();
+        assert!(warning.contains("999.0.0"), "{}", warning);
+    }
 }
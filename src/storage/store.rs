@@ -0,0 +1,784 @@
+//! Snapshot store (Path B2)
+//!
+//! Directory of snapshots with a single index:
+//!
+//! ```text
+//! <dir>/index.json               - entries + pending deletes
+//! <dir>/payloads/<cpg_hash>.cpg  - serialized CPG, shared by equal hashes
+//! <dir>/payloads/<cpg_hash>.pts  - points-to map of that CPG (optional)
+//! <dir>/operations/*.op          - markers of saves in flight
+//! ```
+//!
+//! Payloads are written with the store's `Compression` (plain JSON by
+//! default); reads accept either form, so changing the setting never
+//! invalidates existing payloads. A payload is hashed while it is
+//! serialized and must match the hash it is named after.
+//! `save_with_semantics` takes that hash from the `FrozenCPGEpoch`, which fusion
+//! already computed, so saving never walks the graph twice.
+//!
+//! **Points-to maps**: `save_points_to` stores a `PersistedPointsTo` next to
+//! the payload of the CPG it was computed on. Being keyed by the hash, it is
+//! never served for a different graph; it is deleted along with its payload
+//! and counts as referenced while the payload does.
+//!
+//! **Crash safety**: The index is always replaced atomically (write temp,
+//! rename). Pruning records payloads to delete in the index *before*
+//! touching files, so an interrupted prune is finished on the next open.
+//! Every save holds a `RecoveryManager` operation marker naming its payload
+//! from before the payload is written until the index references it.
+//!
+//! **Garbage collection**: `gc` deletes payload files no index entry
+//! references (left by a crash mid-save, or by a prune that lost its
+//! pending deletes). It never touches a payload named by an operation
+//! marker, nor any file modified within the safety window, so it cannot
+//! race a save in another process.
+
+use crate::analysis::{FunctionSummaries, PersistedPointsTo, PointerAnalysis};
+use crate::cpg::hash::HashedCpg;
+use crate::cpg::model::CPG;
+use crate::cpg::FrozenCPGEpoch;
+use crate::recovery::RecoveryManager;
+use crate::report::FileReport;
+use crate::semantic::SemanticEpoch;
+use crate::storage::frame::{self, Compression};
+use crate::storage::{SnapshotId, SnapshotMetadata};
+use crate::types::RepoSnapshot;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Index file name
+const INDEX_FILE: &str = "index.json";
+
+/// Payload directory name
+const PAYLOAD_DIR: &str = "payloads";
+
+/// Extension of a payload's points-to map
+const POINTS_TO_EXTENSION: &str = "pts";
+
+/// Operation marker prefix of a save (followed by its payload name)
+const SAVE_OPERATION: &str = "save-";
+
+/// Files modified more recently than this are never collected by `gc`
+pub const DEFAULT_GC_SAFETY_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// One snapshot in the index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// Snapshot ID (monotonic)
+    pub id: SnapshotId,
+
+    /// Snapshot metadata
+    pub metadata: SnapshotMetadata,
+
+    /// Payload file name (relative to the payload directory)
+    pub payload: String,
+}
+
+/// On-disk index
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct StoreIndex {
+    /// Next snapshot ID
+    next_id: u64,
+
+    /// Entries, oldest first
+    entries: Vec<SnapshotEntry>,
+
+    /// Payloads scheduled for deletion by an unfinished prune
+    #[serde(default)]
+    pending_deletes: Vec<String>,
+}
+
+/// Retention limits (None = unlimited)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep at most this many snapshots
+    pub max_snapshots: Option<usize>,
+
+    /// Drop snapshots older than this many seconds
+    pub max_age_secs: Option<u64>,
+}
+
+/// Result of a prune
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// Snapshots removed from the index
+    pub removed: Vec<SnapshotId>,
+
+    /// Payload files deleted (CPGs and their points-to maps)
+    pub payloads_deleted: Vec<String>,
+}
+
+/// Result of a gc
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GcReport {
+    /// Files in the payload directory
+    pub scanned: usize,
+
+    /// Files an index entry references
+    pub referenced: usize,
+
+    /// Unreferenced files old enough to collect, sorted
+    pub orphaned: Vec<String>,
+
+    /// Unreferenced files left alone (in-flight save or safety window)
+    pub protected: usize,
+
+    /// Total size of the orphaned files
+    pub bytes_reclaimable: u64,
+
+    /// Files deleted (none for a dry run)
+    pub deleted: Vec<String>,
+}
+
+/// Snapshot store
+pub struct SnapshotStore {
+    /// Store directory
+    dir: PathBuf,
+
+    /// Loaded index
+    index: StoreIndex,
+
+    /// Compression for new payloads
+    compression: Compression,
+
+    /// Operation markers of saves
+    recovery: RecoveryManager,
+
+    /// Minimum age of a file `gc` may delete
+    gc_safety_window: Duration,
+}
+
+impl SnapshotStore {
+    /// Open (or create) a store, finishing any interrupted prune
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(dir.join(PAYLOAD_DIR))?;
+
+        let mut store = Self {
+            index: read_index(&dir)?,
+            recovery: RecoveryManager::new(dir.clone()),
+            dir,
+            compression: Compression::None,
+            gc_safety_window: DEFAULT_GC_SAFETY_WINDOW,
+        };
+        store.finish_pending_deletes()?;
+        Ok(store)
+    }
+
+    /// Compress payloads written from now on
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Let `gc` delete files modified less than `window` ago
+    pub fn with_gc_safety_window(mut self, window: Duration) -> Self {
+        self.gc_safety_window = window;
+        self
+    }
+
+    /// Save a CPG (payload is shared with any snapshot of the same hash)
+    pub fn save(&mut self, cpg: &CPG, epoch_id: u64) -> Result<SnapshotId> {
+        self.save_at(cpg, epoch_id, now_secs())
+    }
+
+    /// Save a CPG along with the repository snapshot it was built from
+    pub fn save_with_repo(&mut self, cpg: &CPG, epoch_id: u64, repo: &RepoSnapshot) -> Result<SnapshotId> {
+        let metadata = SnapshotMetadata::new(epoch_id, cpg.compute_hash(), now_secs()).with_repo(repo);
+        self.save_metadata(cpg, metadata)
+    }
+
+    /// `save_with_repo`, also recording per-file semantic fingerprints,
+    /// ingestion stats, the function summaries of `vcr compare` and the
+    /// call graph's flow summaries
+    pub fn save_with_semantics(
+        &mut self,
+        cpg_epoch: &FrozenCPGEpoch,
+        repo: &RepoSnapshot,
+        semantic: &SemanticEpoch,
+        summaries: &FunctionSummaries,
+        file_stats: Vec<FileReport>,
+    ) -> Result<SnapshotId> {
+        let metadata = SnapshotMetadata::new(cpg_epoch.epoch_id(), cpg_epoch.cpg_hash().to_string(), now_secs())
+            .with_repo(repo)
+            .with_fingerprints(repo, semantic)
+            .with_functions(repo, semantic)
+            .with_summaries(summaries)
+            .with_file_stats(file_stats);
+        self.save_metadata(cpg_epoch.cpg(), metadata)
+    }
+
+    /// Save a CPG with an explicit timestamp
+    pub fn save_at(&mut self, cpg: &CPG, epoch_id: u64, timestamp: u64) -> Result<SnapshotId> {
+        self.save_metadata(cpg, SnapshotMetadata::new(epoch_id, cpg.compute_hash(), timestamp))
+    }
+
+    /// Save under an operation marker naming the payload
+    ///
+    /// The marker is left behind only if the process dies mid-save.
+    fn save_metadata(&mut self, cpg: &CPG, metadata: SnapshotMetadata) -> Result<SnapshotId> {
+        let payload = format!("{}.cpg", metadata.cpg_hash);
+        let operation = format!("{}{}", SAVE_OPERATION, payload);
+        self.recovery.mark_operation_start(&operation)?;
+        let saved = self.save_payload(cpg, metadata, payload);
+        self.recovery.mark_operation_complete(&operation)?;
+        saved
+    }
+
+    fn save_payload(&mut self, cpg: &CPG, metadata: SnapshotMetadata, payload: String) -> Result<SnapshotId> {
+        let payload_path = self.payload_path(&payload);
+
+        if payload_path.exists() {
+            // Reusing a payload (possibly an orphan): restart its safety window
+            std::fs::File::options().append(true).open(&payload_path)?.set_modified(SystemTime::now())?;
+        } else {
+            let tmp = payload_path.with_extension("tmp");
+            let hashed = HashedCpg::new(cpg);
+            frame::write_json(&tmp, &hashed, self.compression)?;
+            let written = hashed.digest();
+            if written != metadata.cpg_hash {
+                std::fs::remove_file(&tmp)?;
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Hash mismatch: saving {}, content hashes to {}", metadata.cpg_hash, written)
+                ));
+            }
+            std::fs::rename(&tmp, &payload_path)?;
+        }
+
+        let id = SnapshotId(self.index.next_id);
+        self.index.next_id += 1;
+        self.index.entries.push(SnapshotEntry {
+            id,
+            metadata,
+            payload,
+        });
+        self.write_index()?;
+
+        Ok(id)
+    }
+
+    /// All snapshots, oldest first
+    pub fn entries(&self) -> &[SnapshotEntry] {
+        &self.index.entries
+    }
+
+    /// Latest snapshot
+    pub fn latest(&self) -> Option<&SnapshotEntry> {
+        self.index.entries.last()
+    }
+
+    /// Get a snapshot entry
+    pub fn get(&self, id: SnapshotId) -> Option<&SnapshotEntry> {
+        self.index.entries.iter().find(|e| e.id == id)
+    }
+
+    /// Payload path for an entry
+    pub fn payload_path(&self, payload: &str) -> PathBuf {
+        self.dir.join(PAYLOAD_DIR).join(payload)
+    }
+
+    /// Load a snapshot's CPG, checking it against the recorded hash
+    pub fn load(&self, id: SnapshotId) -> Result<CPG> {
+        let entry = self.entry(id)?;
+        let mut cpg: CPG = frame::read_json(&self.payload_path(&entry.payload))?;
+        let actual = entry.metadata.hash_of(&cpg);
+        if actual != entry.metadata.cpg_hash {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Hash mismatch: metadata has {}, content hashes to {}", entry.metadata.cpg_hash, actual)
+            ));
+        }
+        entry.metadata.migrate_cpg(&mut cpg);
+        Ok(cpg)
+    }
+
+    /// Check a snapshot's payload
+    ///
+    /// Compressed payloads are checked against their frame checksums without
+    /// building the CPG; plain payloads are loaded and hashed.
+    pub fn verify(&self, id: SnapshotId) -> Result<()> {
+        let path = self.payload_path(&self.entry(id)?.payload);
+        if frame::is_framed(&path)? {
+            frame::read_json::<IgnoredAny>(&path).map(|_| ())
+        } else {
+            self.load(id).map(|_| ())
+        }
+    }
+
+    /// Store the points-to map of the CPG hashing to `cpg_hash`
+    ///
+    /// Fails with `NotFound` unless a snapshot of that CPG is in the index.
+    pub fn save_points_to(&self, cpg_hash: &str, analysis: &PointerAnalysis) -> Result<()> {
+        if !self.index.entries.iter().any(|e| e.metadata.cpg_hash == cpg_hash) {
+            return Err(Error::new(ErrorKind::NotFound, format!("No snapshot of CPG {}", cpg_hash)));
+        }
+        let path = self.points_to_path(cpg_hash);
+        let tmp = path.with_extension("tmp");
+        frame::write_json(&tmp, &analysis.persist(cpg_hash), self.compression)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    /// Points-to map of the CPG hashing to `cpg_hash` (None if none was saved)
+    pub fn load_points_to(&self, cpg_hash: &str) -> Result<Option<PointerAnalysis>> {
+        let path = self.points_to_path(cpg_hash);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let persisted: PersistedPointsTo = frame::read_json(&path)?;
+        if persisted.cpg_hash != cpg_hash {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Points-to map {} was computed on CPG {}", path.display(), persisted.cpg_hash)
+            ));
+        }
+        Ok(Some(persisted.into()))
+    }
+
+    fn points_to_path(&self, cpg_hash: &str) -> PathBuf {
+        self.payload_path(&format!("{}.{}", cpg_hash, POINTS_TO_EXTENSION))
+    }
+
+    fn entry(&self, id: SnapshotId) -> Result<&SnapshotEntry> {
+        self.get(id).ok_or_else(|| Error::new(ErrorKind::NotFound, format!("No snapshot {}", id.0)))
+    }
+
+    /// Prune using the current time
+    pub fn prune(&mut self, policy: &RetentionPolicy) -> Result<PruneReport> {
+        self.prune_at(policy, now_secs())
+    }
+
+    /// Prune snapshots beyond the policy limits
+    ///
+    /// The latest snapshot is never pruned. A payload is only deleted if
+    /// no retained entry still references it.
+    pub fn prune_at(&mut self, policy: &RetentionPolicy, now: u64) -> Result<PruneReport> {
+        let total = self.index.entries.len();
+        if total == 0 {
+            return Ok(PruneReport::default());
+        }
+
+        let excess = policy.max_snapshots.map(|max| total.saturating_sub(max)).unwrap_or(0);
+
+        let mut retained = Vec::new();
+        let mut removed = Vec::new();
+        for (position, entry) in self.index.entries.iter().enumerate() {
+            let is_latest = position == total - 1;
+            let over_count = position < excess;
+            let over_age = policy.max_age_secs
+                .is_some_and(|max| now.saturating_sub(entry.metadata.timestamp) > max);
+
+            if !is_latest && (over_count || over_age) {
+                removed.push(entry.clone());
+            } else {
+                retained.push(entry.clone());
+            }
+        }
+
+        if removed.is_empty() {
+            return Ok(PruneReport::default());
+        }
+
+        let referenced: HashSet<&str> = retained.iter().map(|e| e.payload.as_str()).collect();
+        let mut orphaned: Vec<String> = removed.iter()
+            .map(|e| e.payload.clone())
+            .filter(|p| !referenced.contains(p.as_str()))
+            .collect();
+        orphaned.sort();
+        orphaned.dedup();
+        let points_to: Vec<String> = orphaned.iter()
+            .map(|payload| format!("{}.{}", payload_stem(payload), POINTS_TO_EXTENSION))
+            .filter(|name| self.payload_path(name).exists())
+            .collect();
+        orphaned.extend(points_to);
+
+        // Commit the new index (with pending deletes) before deleting anything
+        self.index.entries = retained;
+        self.index.pending_deletes.extend(orphaned);
+        self.write_index()?;
+
+        let payloads_deleted = self.finish_pending_deletes()?;
+
+        Ok(PruneReport {
+            removed: removed.iter().map(|e| e.id).collect(),
+            payloads_deleted,
+        })
+    }
+
+    /// Delete (or with `dry_run`, only report) unreferenced payload files
+    ///
+    /// Re-reads the index first, so entries saved by another process count.
+    /// Operation markers are read before the index: a save that starts
+    /// later writes a file younger than the safety window.
+    pub fn gc(&mut self, dry_run: bool) -> Result<GcReport> {
+        let in_flight: HashSet<String> = self.recovery.operations_in_progress()?.into_iter()
+            .filter_map(|operation| operation.strip_prefix(SAVE_OPERATION).map(payload_stem))
+            .collect();
+        self.index = read_index(&self.dir)?;
+        let referenced: HashSet<&str> = self.index.entries.iter().map(|e| e.payload.as_str()).collect();
+        let referenced_stems: HashSet<String> = referenced.iter().map(|payload| payload_stem(payload)).collect();
+
+        let mut report = GcReport::default();
+        let now = SystemTime::now();
+        for entry in std::fs::read_dir(self.dir.join(PAYLOAD_DIR))? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            report.scanned += 1;
+
+            let points_to = name.ends_with(&format!(".{}", POINTS_TO_EXTENSION));
+            if referenced.contains(name.as_str()) || (points_to && referenced_stems.contains(&payload_stem(&name))) {
+                report.referenced += 1;
+                continue;
+            }
+            // A future modification time counts as fresh
+            let fresh = now.duration_since(metadata.modified()?)
+                .map_or(true, |age| age < self.gc_safety_window);
+            if fresh || in_flight.contains(&payload_stem(&name)) {
+                report.protected += 1;
+                continue;
+            }
+            report.bytes_reclaimable += metadata.len();
+            report.orphaned.push(name);
+        }
+        report.orphaned.sort();
+
+        if !dry_run {
+            for payload in &report.orphaned {
+                match std::fs::remove_file(self.payload_path(payload)) {
+                    Ok(()) => report.deleted.push(payload.clone()),
+                    // Deleted concurrently
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Delete payloads recorded as pending, then clear the list
+    fn finish_pending_deletes(&mut self) -> Result<Vec<String>> {
+        if self.index.pending_deletes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pending = std::mem::take(&mut self.index.pending_deletes);
+        for payload in &pending {
+            match std::fs::remove_file(self.payload_path(payload)) {
+                Ok(()) => {}
+                // Already deleted before a crash
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => {
+                    self.index.pending_deletes = pending.clone();
+                    return Err(e);
+                }
+            }
+        }
+
+        self.write_index()?;
+        Ok(pending)
+    }
+
+    /// Atomically replace the index
+    fn write_index(&self) -> Result<()> {
+        let serialized = serde_json::to_vec_pretty(&self.index)?;
+        write_atomic(&self.dir.join(INDEX_FILE), &serialized)
+    }
+}
+
+/// Read a store's index (a new index if there is none)
+fn read_index(dir: &Path) -> Result<StoreIndex> {
+    let index_path = dir.join(INDEX_FILE);
+    if !index_path.exists() {
+        return Ok(StoreIndex { next_id: 1, ..Default::default() });
+    }
+    let serialized = std::fs::read_to_string(&index_path)?;
+    serde_json::from_str(&serialized).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Payload name without its extension, shared by the payload and its
+/// temp file
+fn payload_stem(name: &str) -> String {
+    name.split_once('.').map_or(name, |(stem, _)| stem).to_string()
+}
+
+/// Write a file via temp + rename
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+/// Current time in seconds since the Unix epoch
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpg::model::{CPGNode, CPGNodeId, CPGNodeKind, OriginRef};
+    use crate::semantic::model::FunctionId;
+    use crate::types::ByteRange;
+    use tempfile::TempDir;
+
+    fn cpg_with(functions: u64) -> CPG {
+        let mut cpg = CPG::new();
+        for i in 0..functions {
+            cpg.add_node(CPGNode::new(
+                CPGNodeId(i),
+                CPGNodeKind::Function,
+                OriginRef::Function { function_id: FunctionId(i) },
+                ByteRange::new(0, 10),
+            ));
+        }
+        cpg
+    }
+
+    fn payload_exists(store: &SnapshotStore, id: SnapshotId) -> bool {
+        store.payload_path(&store.get(id).unwrap().payload).exists()
+    }
+
+    #[test]
+    fn test_save_dedups_payloads() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+
+        let a = store.save(&cpg_with(1), 1).unwrap();
+        let b = store.save(&cpg_with(1), 2).unwrap();
+
+        assert_ne!(a, b);
+        assert_eq!(store.get(a).unwrap().payload, store.get(b).unwrap().payload);
+        assert_eq!(std::fs::read_dir(dir.path().join(PAYLOAD_DIR)).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_compressed_payloads_load_and_verify() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap()
+            .with_compression(Compression::Zstd { level: 1 });
+        let cpg = cpg_with(3000);
+
+        let id = store.save(&cpg, 1).unwrap();
+        let payload = store.payload_path(&store.get(id).unwrap().payload);
+        assert!(frame::is_framed(&payload).unwrap());
+        assert_eq!(store.load(id).unwrap().compute_hash(), cpg.compute_hash());
+        store.verify(id).unwrap();
+
+        // A plain payload saved earlier still loads
+        let plain = SnapshotStore::open(dir.path()).unwrap().save(&cpg_with(2), 2).unwrap();
+        let reopened = SnapshotStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.load(plain).unwrap().nodes.len(), 2);
+        reopened.verify(plain).unwrap();
+
+        let mut bytes = std::fs::read(&payload).unwrap();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0x01;
+        std::fs::write(&payload, &bytes).unwrap();
+        assert!(reopened.verify(id).is_err());
+        assert!(reopened.load(id).is_err());
+    }
+
+    #[test]
+    fn test_saved_fingerprints_and_stats_survive_reopen() {
+        let repo = TempDir::new().unwrap();
+        std::fs::write(repo.path().join("lib.rs"), "fn f() { let x = 1; }\n").unwrap();
+        let output = crate::pipeline::Pipeline::default().run(repo.path()).unwrap();
+        let file_id = output.snapshot.file_ids()[0];
+
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+        let stats = crate::report::ReportBuilder::from_output(&output).build();
+        let id = store.save_with_semantics(&output.cpg_epoch, &output.snapshot, &output.semantic, &output.summaries, stats.clone()).unwrap();
+
+        let reopened = SnapshotStore::open(dir.path()).unwrap();
+        let metadata = &reopened.get(id).unwrap().metadata;
+        assert_eq!(metadata.semantic_fingerprints.len(), 1);
+        assert_eq!(metadata.semantic_fingerprints.get("lib.rs").map(String::as_str), output.semantic.fingerprint(file_id));
+        assert_eq!(metadata.file_stats, stats);
+        assert_eq!(metadata.summaries.as_ref().unwrap().summaries(), output.summaries.summaries());
+    }
+
+    #[test]
+    fn test_prune_by_count() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+
+        let ids: Vec<_> = (1..=4).map(|n| store.save(&cpg_with(n), n).unwrap()).collect();
+        let policy = RetentionPolicy { max_snapshots: Some(2), max_age_secs: None };
+
+        let report = store.prune(&policy).unwrap();
+
+        assert_eq!(report.removed, vec![ids[0], ids[1]]);
+        assert_eq!(report.payloads_deleted.len(), 2);
+        assert_eq!(store.entries().len(), 2);
+        assert!(payload_exists(&store, ids[2]));
+        assert!(payload_exists(&store, ids[3]));
+    }
+
+    #[test]
+    fn test_prune_by_age_keeps_latest() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+
+        let old = store.save_at(&cpg_with(1), 1, 100).unwrap();
+        let recent = store.save_at(&cpg_with(2), 2, 900).unwrap();
+        let latest = store.save_at(&cpg_with(3), 3, 100).unwrap();
+        let policy = RetentionPolicy { max_snapshots: None, max_age_secs: Some(500) };
+
+        let report = store.prune_at(&policy, 1000).unwrap();
+
+        // `latest` is also too old but is never pruned
+        assert_eq!(report.removed, vec![old]);
+        let remaining: Vec<_> = store.entries().iter().map(|e| e.id).collect();
+        assert_eq!(remaining, vec![recent, latest]);
+    }
+
+    #[test]
+    fn test_prune_keeps_shared_payload() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+
+        let shared = cpg_with(1);
+        let old = store.save_at(&shared, 1, 100).unwrap();
+        store.save_at(&cpg_with(2), 2, 200).unwrap();
+        let kept = store.save_at(&shared, 3, 300).unwrap();
+        let policy = RetentionPolicy { max_snapshots: Some(1), max_age_secs: None };
+
+        let report = store.prune_at(&policy, 300).unwrap();
+
+        assert_eq!(report.removed.len(), 2);
+        assert!(report.removed.contains(&old));
+        // `old` shares its payload with `kept`, so only one payload goes
+        assert_eq!(report.payloads_deleted.len(), 1);
+        assert!(payload_exists(&store, kept));
+    }
+
+    #[test]
+    fn test_reopen_finishes_interrupted_prune() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+
+        let old = store.save(&cpg_with(1), 1).unwrap();
+        store.save(&cpg_with(2), 2).unwrap();
+        let old_payload_name = store.get(old).unwrap().payload.clone();
+        let old_payload = store.payload_path(&old_payload_name);
+
+        // Simulate a crash after the index commit but before file deletion
+        store.index.entries.remove(0);
+        store.index.pending_deletes.push(old_payload_name);
+        store.write_index().unwrap();
+        drop(store);
+        assert!(old_payload.exists());
+
+        let store = SnapshotStore::open(dir.path()).unwrap();
+        assert!(!old_payload.exists());
+        assert!(store.index.pending_deletes.is_empty());
+        assert_eq!(store.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_gc_reports_and_deletes_orphans() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+        let kept = store.save(&cpg_with(1), 1).unwrap();
+        let orphan = store.payload_path("orphan.cpg");
+        std::fs::write(&orphan, b"{}").unwrap();
+
+        // Both files are newer than the default window
+        let report = store.gc(false).unwrap();
+        assert_eq!((report.scanned, report.referenced, report.protected), (2, 1, 1));
+        assert!(report.orphaned.is_empty() && orphan.exists());
+
+        let mut store = store.with_gc_safety_window(Duration::ZERO);
+        let dry = store.gc(true).unwrap();
+        assert_eq!(dry.orphaned, vec!["orphan.cpg"]);
+        assert_eq!(dry.bytes_reclaimable, 2);
+        assert!(dry.deleted.is_empty() && orphan.exists());
+
+        let report = store.gc(false).unwrap();
+        assert_eq!(report.deleted, vec!["orphan.cpg"]);
+        assert!(!orphan.exists());
+        assert!(payload_exists(&store, kept));
+        store.load(kept).unwrap();
+    }
+
+    #[test]
+    fn test_gc_spares_payload_of_in_flight_save() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap().with_gc_safety_window(Duration::ZERO);
+        store.save(&cpg_with(1), 1).unwrap();
+
+        // Simulate a crash after writing the payload, before the index commit
+        let cpg = cpg_with(2);
+        let payload = format!("{}.cpg", cpg.compute_hash());
+        let operation = format!("{}{}", SAVE_OPERATION, payload);
+        store.recovery.mark_operation_start(&operation).unwrap();
+        let tmp = store.payload_path(&payload).with_extension("tmp");
+        frame::write_json(&tmp, &cpg, Compression::None).unwrap();
+        std::fs::copy(&tmp, store.payload_path(&payload)).unwrap();
+
+        let mut reopened = SnapshotStore::open(dir.path()).unwrap().with_gc_safety_window(Duration::ZERO);
+        let report = reopened.gc(false).unwrap();
+        assert_eq!((report.scanned, report.referenced, report.protected), (3, 1, 2));
+        assert!(report.deleted.is_empty());
+        assert!(store.payload_path(&payload).exists() && tmp.exists());
+
+        // Once the marker is gone, both are orphans
+        store.recovery.mark_operation_complete(&operation).unwrap();
+        let report = reopened.gc(false).unwrap();
+        assert_eq!(report.deleted.len(), 2);
+        assert!(!store.payload_path(&payload).exists());
+    }
+
+    #[test]
+    fn test_points_to_maps_follow_their_payload() {
+        use crate::analysis::PointerAnalysis;
+
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap().with_gc_safety_window(Duration::ZERO);
+        let (old, new) = (cpg_with(1), cpg_with(2));
+        store.save_at(&old, 1, 100).unwrap();
+        store.save_at(&new, 2, 200).unwrap();
+        for cpg in [&old, &new] {
+            store.save_points_to(&cpg.compute_hash(), &PointerAnalysis::analyze_from_roots(cpg)).unwrap();
+        }
+
+        // Referenced along with its payload
+        let report = store.gc(true).unwrap();
+        assert_eq!((report.scanned, report.referenced), (4, 4));
+        assert!(report.orphaned.is_empty());
+
+        // Deleted along with its payload
+        let policy = RetentionPolicy { max_snapshots: Some(1), max_age_secs: None };
+        let report = store.prune_at(&policy, 300).unwrap();
+        let old_map = format!("{}.{}", old.compute_hash(), POINTS_TO_EXTENSION);
+        assert_eq!(report.payloads_deleted, vec![format!("{}.cpg", old.compute_hash()), old_map]);
+        assert_eq!(store.load_points_to(&old.compute_hash()).unwrap(), None);
+        assert!(store.load_points_to(&new.compute_hash()).unwrap().is_some());
+
+        // Never served for another CPG
+        let new_map = store.points_to_path(&new.compute_hash());
+        std::fs::copy(&new_map, store.points_to_path(&old.compute_hash())).unwrap();
+        assert!(store.load_points_to(&old.compute_hash()).is_err());
+    }
+
+    #[test]
+    fn test_prune_empty_store() {
+        let dir = TempDir::new().unwrap();
+        let mut store = SnapshotStore::open(dir.path()).unwrap();
+        let policy = RetentionPolicy { max_snapshots: Some(0), max_age_secs: Some(0) };
+
+        assert_eq!(store.prune(&policy).unwrap(), PruneReport::default());
+    }
+}
@@ -0,0 +1,232 @@
+//! Synthetic repositories (`bench-helpers` feature)
+//!
+//! `generate_repo` writes a seeded Rust repository for benchmarks, so runs
+//! compare like with like: the same arguments always produce byte-identical
+//! files. Functions mix `let`, `if`/`else`, `while` and calls into earlier
+//! functions (possibly in other files), so every pipeline stage has work.
+//!
+//! `IncrementalSession` drives incremental builds one edit at a time and
+//! compares each against a from-scratch build of the same contents, for
+//! property tests over random edit sequences.
+
+use crate::config::ParseErrorPolicy;
+use crate::io::MemoryBackend;
+use crate::pipeline::{Pipeline, PipelineOutput};
+use crate::verify::{Divergence, StageHashes};
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Files per generated module directory
+const FILES_PER_MODULE: usize = 16;
+
+/// Write `files` Rust files of about `avg_size` bytes each under `dir/src`.
+///
+/// Returns the written paths in sorted order.
+pub fn generate_repo(dir: &Path, files: usize, avg_size: usize, seed: u64) -> Result<Vec<PathBuf>> {
+    let mut rng = SplitMix64(seed);
+    let mut functions: Vec<String> = Vec::new();
+    let mut paths = Vec::with_capacity(files);
+
+    for file in 0..files {
+        let module = dir.join("src").join(format!("m{:03}", file / FILES_PER_MODULE));
+        fs::create_dir_all(&module)?;
+
+        // 0.5x..1.5x the average, at least one function
+        let target = avg_size / 2 + rng.below(avg_size.max(1));
+        let mut source = String::new();
+        let mut index = 0;
+        while index == 0 || source.len() < target {
+            let name = format!("f{}_{}", file, index);
+            write_function(&mut source, &name, &functions, &mut rng);
+            functions.push(name);
+            index += 1;
+        }
+
+        let path = module.join(format!("f{:04}.rs", file));
+        fs::write(&path, source)?;
+        paths.push(path);
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+/// Append one generated function to a file (a deterministic single-file edit).
+pub fn edit_file(path: &Path, seed: u64) -> Result<()> {
+    let mut source = fs::read_to_string(path)?;
+    write_function(&mut source, &format!("edited_{}", seed), &[], &mut SplitMix64(seed));
+    fs::write(path, source)
+}
+
+/// A single generated file of `functions` functions (at least one), each
+/// calling only earlier ones
+pub fn generate_source(functions: usize, seed: u64) -> String {
+    let mut rng = SplitMix64(seed);
+    let mut names: Vec<String> = Vec::new();
+    let mut source = String::new();
+    for index in 0..functions.max(1) {
+        let name = format!("f{}", index);
+        write_function(&mut source, &name, &names, &mut rng);
+        names.push(name);
+    }
+    source
+}
+
+/// Text random edits insert: tokens, statements and whole items, so edited
+/// files stay close to Rust (and sometimes break it)
+pub const EDIT_FRAGMENTS: &[&str] = &[
+    "x", "1", " ", "\n", ";", "{", "}", "(", ")", ",", "// note",
+    "x = x + 1;", "let y = 2;", "if b { x = 3; }", "while x < 5 { x = x + 1; }",
+    "f0(x, b);", "return x;", "fn extra(a: i32) -> i32 { a }\n",
+];
+
+/// Replace `delete` bytes at `offset` with `insert`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteEdit {
+    pub offset: usize,
+    pub delete: usize,
+    pub insert: String,
+}
+
+impl ByteEdit {
+    /// Apply to `source`, clamping the range into it and onto character
+    /// boundaries, so any edit applies to any source
+    pub fn apply(&self, source: &mut String) {
+        let start = char_boundary(source, self.offset);
+        let end = char_boundary(source, start.saturating_add(self.delete));
+        source.replace_range(start..end, &self.insert);
+    }
+}
+
+/// Largest character boundary of `text` at or before `offset`
+fn char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// Stage divergences after one edit
+#[derive(Debug, Clone)]
+pub struct StepMismatch {
+    /// Edits applied so far, this one included
+    pub step: usize,
+
+    /// File contents both builds saw
+    pub source: String,
+
+    pub divergences: Vec<Divergence>,
+}
+
+/// Step-by-step incremental builds of an in-memory repository
+///
+/// Holds one edited file (`src/lib.rs`) beside one file that never
+/// changes (`src/main.rs`, calling into it), so every step mixes rebuilt
+/// and carried-over files. Each `apply` rebuilds incrementally from the
+/// previous step's output and from scratch, and compares stage hashes.
+pub struct IncrementalSession {
+    pipeline: Pipeline,
+    source: String,
+    previous: PipelineOutput,
+    steps: usize,
+}
+
+impl IncrementalSession {
+    /// Root of the in-memory repository
+    pub const ROOT: &'static str = "/session";
+
+    /// Build `source` from scratch as the first step
+    pub fn new(pipeline: Pipeline, source: String) -> anyhow::Result<Self> {
+        let previous = Self::build(&pipeline, &source, None)?;
+        Ok(Self { pipeline, source, previous, steps: 0 })
+    }
+
+    /// Session with a pipeline that analyzes files despite syntax errors
+    pub fn best_effort(source: String) -> anyhow::Result<Self> {
+        Self::new(Pipeline::default().with_parse_error_policy(ParseErrorPolicy::IncludeBestEffort), source)
+    }
+
+    /// Current contents of the edited file
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Output of the last incremental build
+    pub fn output(&self) -> &PipelineOutput {
+        &self.previous
+    }
+
+    /// Apply an edit, then build incrementally and from scratch
+    ///
+    /// Errors if either build fails; the session keeps the incremental output.
+    pub fn apply(&mut self, edit: &ByteEdit) -> anyhow::Result<Option<StepMismatch>> {
+        edit.apply(&mut self.source);
+        self.steps += 1;
+
+        let incremental = Self::build(&self.pipeline, &self.source, Some(&self.previous))?;
+        let fresh = Self::build(&self.pipeline, &self.source, None)?;
+        let divergences = StageHashes::from_output(&fresh).divergences(&StageHashes::from_output(&incremental));
+        self.previous = incremental;
+
+        Ok((!divergences.is_empty()).then(|| StepMismatch {
+            step: self.steps,
+            source: self.source.clone(),
+            divergences,
+        }))
+    }
+
+    fn build(pipeline: &Pipeline, source: &str, previous: Option<&PipelineOutput>) -> anyhow::Result<PipelineOutput> {
+        let root = Path::new(Self::ROOT);
+        let mut backend = MemoryBackend::default();
+        backend.insert(root.join("src/lib.rs"), source);
+        backend.insert(root.join("src/main.rs"), "fn main() { let x = f0(1, true); }\n");
+        let pipeline = pipeline.clone().with_backend(Arc::new(backend));
+        match previous {
+            Some(previous) => pipeline.run_incremental(previous),
+            None => pipeline.run(root),
+        }
+    }
+}
+
+/// One function with a random body over `a: i32, b: bool`
+fn write_function(out: &mut String, name: &str, callees: &[String], rng: &mut SplitMix64) {
+    out.push_str(&format!("fn {}(a: i32, b: bool) -> i32 {{\n    let mut x = a;\n", name));
+
+    for i in 0..2 + rng.below(6) {
+        match rng.below(4) {
+            0 => out.push_str(&format!("    let v{} = x + {};\n", i, rng.below(100))),
+            1 => out.push_str(&format!(
+                "    if b {{\n        x = x + {};\n    }} else {{\n        x = x - 1;\n    }}\n",
+                rng.below(10)
+            )),
+            2 => out.push_str(&format!("    while x < {} {{\n        x = x + 1;\n    }}\n", rng.below(50))),
+            _ => match callees.len() {
+                0 => out.push_str("    x = x * 2;\n"),
+                n => out.push_str(&format!("    x = {}(x, b);\n", callees[rng.below(n)])),
+            },
+        }
+    }
+
+    out.push_str("    x\n}\n\n");
+}
+
+/// Small seeded PRNG (no dependency, stable across platforms)
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform-ish value in `0..n` (`n` > 0)
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
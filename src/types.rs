@@ -6,7 +6,7 @@
 //! - Immutable snapshots
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::time::SystemTime;
 
@@ -16,7 +16,7 @@ pub struct FileId(u64);
 
 impl FileId {
     /// Create a new FileId from a hash.
-    pub fn new(hash: u64) -> Self {
+    pub const fn new(hash: u64) -> Self {
         Self(hash)
     }
 
@@ -34,25 +34,63 @@ impl FileId {
 /// - Serializable: can be persisted and restored
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoSnapshot {
-    /// Root directory of the repository
+    /// Root directory of the repository (common ancestor of a workspace)
     pub root: PathBuf,
+
+    /// Scanned directories relative to `root` ("" = `root` itself)
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
     
     /// Map from FileId to file metadata
-    pub files: HashMap<FileId, FileMetadata>,
+    ///
+    /// Ordered by FileId, so iteration and serialization are deterministic.
+    pub files: BTreeMap<FileId, FileMetadata>,
     
     /// When this snapshot was created
     pub created_at: SystemTime,
     
     /// SHA256 hash of the entire snapshot (for verification)
     pub snapshot_hash: String,
+
+    /// Commit the scanned work tree was on (informational; not part of
+    /// `snapshot_hash`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcs: Option<VcsInfo>,
+
+    /// Whether contents were hashed as LF views (the scanner's
+    /// `with_normalized_line_endings`)
+    #[serde(default)]
+    pub normalized_line_endings: bool,
+}
+
+/// Version control state of a scanned work tree (see `repo::vcs`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VcsInfo {
+    /// Commit HEAD resolves to (None on an unborn branch)
+    pub commit: Option<String>,
+
+    /// Branch HEAD names (None when detached)
+    pub branch: Option<String>,
+
+    /// Whether the work tree differs from the commit (not computed yet)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dirty: Option<bool>,
 }
 
 impl RepoSnapshot {
     /// Get all file IDs in deterministic order.
     pub fn file_ids(&self) -> Vec<FileId> {
-        let mut ids: Vec<_> = self.files.keys().copied().collect();
-        ids.sort();
-        ids
+        self.files.keys().copied().collect()
+    }
+
+    /// Absolute scanned directories, sorted
+    pub fn root_paths(&self) -> Vec<PathBuf> {
+        if self.roots.is_empty() {
+            return vec![self.root.clone()];
+        }
+        self.roots.iter()
+            .map(|label| if label.as_os_str().is_empty() { self.root.clone() } else { self.root.join(label) })
+            .collect()
     }
 }
 
@@ -73,6 +111,33 @@ pub struct FileMetadata {
     
     /// Detected language (for parser selection)
     pub language: Option<Language>,
+
+    /// Effective `.vcr.toml` settings for the file
+    #[serde(default, skip_serializing_if = "FilePolicy::is_default")]
+    pub policy: FilePolicy,
+}
+
+/// Per-file settings from `.vcr.toml` overrides (see `repo::policy`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilePolicy {
+    /// Hash the file but build no semantics for it
+    #[serde(default)]
+    pub skip_semantics: bool,
+
+    /// Hash but do not analyze files larger than this many bytes
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+}
+
+impl FilePolicy {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether a file of `size` bytes is parsed and analyzed
+    pub fn analyzes(&self, size: u64) -> bool {
+        !self.skip_semantics && self.max_file_size.is_none_or(|max| size <= max)
+    }
 }
 
 /// Supported languages for parsing.
@@ -84,6 +149,13 @@ pub enum Language {
 }
 
 impl Language {
+    /// Lowercase language name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+        }
+    }
+
     /// Get file extension associated with this language.
     pub fn extension(&self) -> &'static str {
         match self {
@@ -91,6 +163,14 @@ impl Language {
         }
     }
 
+    /// Language with a lowercase `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rust" => Some(Language::Rust),
+            _ => None,
+        }
+    }
+
     /// Detect language from file extension.
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext {
@@ -116,8 +196,167 @@ pub struct ParsedFile {
     pub parse_time_us: u64,
 }
 
+impl ParsedFile {
+    /// Index every node of the parse tree by preorder position
+    pub fn preorder_index(&self) -> PreorderIndex<'_> {
+        let mut nodes = Vec::new();
+        walk_preorder(&self.tree, |node| {
+            nodes.push(node);
+            true
+        });
+
+        let ids = nodes.iter()
+            .enumerate()
+            .map(|(i, node)| (node.id(), AstNodeId(i as u32)))
+            .collect();
+        PreorderIndex { nodes, ids }
+    }
+
+    /// Node at a preorder position
+    ///
+    /// Walks the tree (O(n)); build a `PreorderIndex` for repeated lookups.
+    pub fn node_by_preorder_index(&self, id: AstNodeId) -> Option<tree_sitter::Node<'_>> {
+        let mut found = None;
+        let mut position = 0;
+        walk_preorder(&self.tree, |node| {
+            if position == id.0 {
+                found = Some(node);
+                return false;
+            }
+            position += 1;
+            true
+        });
+        found
+    }
+}
+
+impl ParsedFile {
+    /// Ranges of ERROR and MISSING nodes, in preorder
+    ///
+    /// Nodes inside an ERROR node are not reported separately.
+    pub fn error_ranges(&self) -> Vec<ByteRange> {
+        let mut ranges = Vec::new();
+        let mut stack = vec![self.tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if node.is_error() || node.is_missing() {
+                ranges.push(ByteRange::of_node(&node));
+            } else if node.has_error() {
+                let mut cursor = node.walk();
+                let children: Vec<_> = node.children(&mut cursor).collect();
+                stack.extend(children.into_iter().rev());
+            }
+        }
+        ranges
+    }
+
+    /// Nodes whose range is inverted, in preorder
+    pub fn inverted_ranges(&self) -> Vec<InvertedRange> {
+        let mut inverted = Vec::new();
+        walk_preorder(&self.tree, |node| {
+            inverted.extend(ByteRange::try_new(node.start_byte(), node.end_byte()).err());
+            true
+        });
+        inverted
+    }
+
+    /// Whether the tree is free of syntax errors, and where it is not
+    pub fn quality(&self) -> ParseQuality {
+        let error_ranges = self.error_ranges();
+        let inverted_ranges = self.inverted_ranges();
+        ParseQuality {
+            clean: error_ranges.is_empty() && inverted_ranges.is_empty(),
+            error_count: error_ranges.len(),
+            error_ranges,
+            inverted_ranges,
+        }
+    }
+}
+
+/// Syntax errors Tree-sitter recovered from in one file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseQuality {
+    /// No ERROR or MISSING nodes and no inverted ranges
+    pub clean: bool,
+
+    /// Number of ERROR and MISSING nodes (outermost only)
+    pub error_count: usize,
+
+    /// Their byte ranges, in preorder
+    pub error_ranges: Vec<ByteRange>,
+
+    /// Nodes whose range is inverted (clamped by the builders), in preorder
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inverted_ranges: Vec<InvertedRange>,
+}
+
+/// Stable per-file AST node identifier: the node's position in a preorder
+/// walk of the parse tree (anonymous nodes included, root = 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AstNodeId(pub u32);
+
+/// Preorder node table for one parse tree
+pub struct PreorderIndex<'tree> {
+    /// Nodes by AstNodeId
+    nodes: Vec<tree_sitter::Node<'tree>>,
+
+    /// Tree-sitter node id → AstNodeId
+    ids: HashMap<usize, AstNodeId>,
+}
+
+impl<'tree> PreorderIndex<'tree> {
+    /// Node for an id
+    pub fn node(&self, id: AstNodeId) -> Option<tree_sitter::Node<'tree>> {
+        self.nodes.get(id.0 as usize).copied()
+    }
+
+    /// Id of a node from the same tree
+    pub fn id_of(&self, node: &tree_sitter::Node) -> Option<AstNodeId> {
+        self.ids.get(&node.id()).copied()
+    }
+
+    /// Smallest node spanning a byte range
+    pub fn node_for_range(&self, range: ByteRange) -> Option<tree_sitter::Node<'tree>> {
+        self.nodes.first()?.descendant_for_byte_range(range.start, range.end)
+    }
+
+    /// Nodes whose range is inverted, in preorder
+    pub fn inverted_ranges(&self) -> Vec<InvertedRange> {
+        self.nodes.iter()
+            .filter_map(|node| ByteRange::try_new(node.start_byte(), node.end_byte()).err())
+            .collect()
+    }
+
+    /// Number of nodes in the tree
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree has no nodes
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+/// Visit nodes in preorder until the visitor returns false
+fn walk_preorder<'tree>(tree: &'tree tree_sitter::Tree, mut visit: impl FnMut(tree_sitter::Node<'tree>) -> bool) {
+    let mut cursor = tree.walk();
+    loop {
+        if !visit(cursor.node()) {
+            return;
+        }
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return;
+            }
+        }
+    }
+}
+
 /// A byte range in a source file.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ByteRange {
     /// Start byte offset (inclusive)
     pub start: usize,
@@ -128,11 +367,28 @@ pub struct ByteRange {
 
 impl ByteRange {
     /// Create a new byte range.
+    ///
+    /// Panics if `start > end`: only for offsets the caller computed itself.
+    /// Ranges read from a parse tree go through `try_new` or `of_node`.
     pub fn new(start: usize, end: usize) -> Self {
         assert!(start <= end, "Invalid byte range");
         Self { start, end }
     }
 
+    /// Create a byte range, failing if it is inverted.
+    pub fn try_new(start: usize, end: usize) -> Result<Self, InvertedRange> {
+        match start <= end {
+            true => Ok(Self { start, end }),
+            false => Err(InvertedRange { start, end }),
+        }
+    }
+
+    /// Range of a parse tree node, clamped to an empty range at its end if
+    /// inverted (see `InvertedRange`)
+    pub fn of_node(node: &tree_sitter::Node) -> Self {
+        Self::try_new(node.start_byte(), node.end_byte()).unwrap_or_else(|inverted| inverted.clamped())
+    }
+
     /// Get the length of this range.
     pub fn len(&self) -> usize {
         self.end - self.start
@@ -144,8 +400,30 @@ impl ByteRange {
     }
 }
 
+/// A byte range whose start is past its end
+///
+/// Tree-sitter can report one for a node of a tree edited but not yet
+/// reparsed, around nodes error recovery inserted. Builders clamp such a node
+/// (`ByteRange::of_node`), `ParseQuality` lists it, and the file's semantics
+/// are marked degraded instead of the ingest panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[error("Inverted byte range {start}..{end}")]
+pub struct InvertedRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl InvertedRange {
+    /// Empty range at `end`, the smaller offset
+    pub fn clamped(&self) -> ByteRange {
+        ByteRange { start: self.end, end: self.end }
+    }
+}
+
 /// Epoch marker for type-safe epoch tracking.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Markers are ordered: a child epoch always has a later marker than its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct EpochMarker(u64);
 
 impl EpochMarker {
@@ -158,4 +436,9 @@ impl EpochMarker {
     pub fn next(&self) -> Self {
         Self(self.0 + 1)
     }
+
+    /// Get the raw epoch ID.
+    pub fn id(&self) -> u64 {
+        self.0
+    }
 }
@@ -0,0 +1,508 @@
+//! Glob matching over normalized paths
+//!
+//! One pattern syntax for everything that filters paths (scanner
+//! extensions, `.vcrignore`) or names (query `function_matches`), matched
+//! against forward-slash paths without leading, trailing or doubled
+//! separators:
+//!
+//! - `*` matches any run of characters within one component, `?` exactly
+//!   one character other than `/`
+//! - `[abc]`, `[a-z]` match one listed character; `[!abc]` / `[^abc]` one
+//!   character not listed. A `]` right after the opening `[` (or `[!`) is
+//!   literal. Classes never match `/`
+//! - `**` as a whole component matches any number of components: `**/b`
+//!   matches `b` and `a/b`, `a/**/b` matches `a/b` and `a/x/y/b`, a
+//!   trailing `a/**` matches everything below `a` (not `a` itself) and `**`
+//!   alone matches every path. `**` inside a component (`a**`) is rejected
+//! - `\` makes the next character literal; a `]` outside a class must be
+//!   escaped
+//!
+//! Case sensitivity is chosen per glob (`with_case_sensitive`); folding is
+//! per character.
+//!
+//! ## Bound
+//!
+//! A pattern compiles to a small automaton (`Automaton`) that is run on all
+//! its states at once instead of backtracking. Each input character
+//! advances every live state at most once, so matching a path of `n`
+//! characters against a pattern of `m` instructions takes `O(n * m)` time
+//! and `O(m)` memory, whatever the pattern: `*a*a*a*b` cannot blow up the
+//! way a backtracking matcher does. The automaton is shared with the query
+//! name matcher (`query::NamePattern`).
+
+use anyhow::{bail, Result};
+
+/// What one input character is tested against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CharMatch {
+    Literal(char),
+
+    /// Any character but `/`
+    NotSlash,
+
+    /// Any character
+    Any,
+
+    /// `[...]`: inclusive ranges, never `/`
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+/// One automaton instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Inst {
+    /// Consume a matching character and continue at the next instruction
+    Step(CharMatch),
+
+    /// Continue at both targets without consuming
+    Split(usize, usize),
+
+    /// Continue at the target without consuming
+    Jump(usize),
+
+    /// The whole input matched
+    Accept,
+}
+
+/// Non-backtracking matcher built instruction by instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Automaton {
+    insts: Vec<Inst>,
+    case_sensitive: bool,
+}
+
+impl Automaton {
+    pub(crate) fn new() -> Self {
+        Self { insts: Vec::new(), case_sensitive: true }
+    }
+
+    /// Exactly one matching character
+    pub(crate) fn step(&mut self, m: CharMatch) {
+        self.insts.push(Inst::Step(m));
+    }
+
+    /// Zero or more matching characters
+    pub(crate) fn repeat(&mut self, m: CharMatch) {
+        let split = self.insts.len();
+        self.insts.push(Inst::Split(split + 1, split + 3));
+        self.insts.push(Inst::Step(m));
+        self.insts.push(Inst::Jump(split));
+    }
+
+    /// Nothing, or any characters ending in `/` (a `**/` component)
+    fn any_dirs(&mut self) {
+        let skip = self.insts.len();
+        let end = skip + 5;
+        self.insts.push(Inst::Split(skip + 1, end));
+        self.insts.push(Inst::Split(skip + 2, skip + 4));
+        self.insts.push(Inst::Step(CharMatch::Any));
+        self.insts.push(Inst::Jump(skip + 1));
+        self.insts.push(Inst::Step(CharMatch::Literal('/')));
+    }
+
+    /// Complete the automaton; nothing can be added afterwards
+    pub(crate) fn finish(mut self) -> Self {
+        self.insts.push(Inst::Accept);
+        self
+    }
+
+    pub(crate) fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Whether the whole of `text` matches
+    pub(crate) fn matches(&self, text: &str) -> bool {
+        self.run(text).0
+    }
+
+    /// Match, also returning the number of states visited (at most
+    /// `insts.len()` per character, plus one set for the start)
+    fn run(&self, text: &str) -> (bool, usize) {
+        let mut current = StateSet::new(self.insts.len());
+        let mut next = StateSet::new(self.insts.len());
+        let mut visited = 0;
+        self.add(&mut current, 0, &mut visited);
+
+        for c in text.chars() {
+            if current.list.is_empty() {
+                return (false, visited);
+            }
+            next.clear();
+            for &pc in &current.list {
+                if let Inst::Step(m) = &self.insts[pc] {
+                    if self.accepts(m, c) {
+                        self.add(&mut next, pc + 1, &mut visited);
+                    }
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+
+        let accepted = current.list.iter().any(|&pc| self.insts[pc] == Inst::Accept);
+        (accepted, visited)
+    }
+
+    /// Add a state and everything reachable from it without consuming
+    fn add(&self, set: &mut StateSet, pc: usize, visited: &mut usize) {
+        if set.member[pc] {
+            return;
+        }
+        set.member[pc] = true;
+        set.list.push(pc);
+        *visited += 1;
+        match self.insts[pc] {
+            Inst::Jump(target) => self.add(set, target, visited),
+            Inst::Split(first, second) => {
+                self.add(set, first, visited);
+                self.add(set, second, visited);
+            }
+            Inst::Step(_) | Inst::Accept => {}
+        }
+    }
+
+    fn accepts(&self, m: &CharMatch, c: char) -> bool {
+        match m {
+            CharMatch::Literal(expected) if self.case_sensitive => *expected == c,
+            CharMatch::Literal(expected) => fold(*expected) == fold(c),
+            CharMatch::NotSlash => c != '/',
+            CharMatch::Any => true,
+            CharMatch::Class { negated, ranges } => {
+                let in_class = |c: char| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+                let listed = match self.case_sensitive {
+                    true => in_class(c),
+                    false => in_class(c) || in_class(fold(c)) || in_class(upper(c)),
+                };
+                c != '/' && listed != *negated
+            }
+        }
+    }
+}
+
+/// Live automaton states, in insertion order
+struct StateSet {
+    list: Vec<usize>,
+    member: Vec<bool>,
+}
+
+impl StateSet {
+    fn new(size: usize) -> Self {
+        Self { list: Vec::with_capacity(size), member: vec![false; size] }
+    }
+
+    fn clear(&mut self) {
+        for &pc in &self.list {
+            self.member[pc] = false;
+        }
+        self.list.clear();
+    }
+}
+
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn upper(c: char) -> char {
+    c.to_uppercase().next().unwrap_or(c)
+}
+
+/// One parsed element of a component
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Char(CharMatch),
+
+    /// `*`
+    Star,
+}
+
+/// One parsed path component
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Component {
+    Tokens(Vec<Token>),
+
+    /// `**`
+    AnyComponents,
+}
+
+/// Compiled glob
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+    automaton: Automaton,
+}
+
+impl Glob {
+    /// Parse a pattern (case-sensitive)
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let components = parse_components(pattern)?;
+        let mut automaton = Automaton::new();
+        let last = components.len() - 1;
+        for (i, component) in components.iter().enumerate() {
+            match component {
+                Component::AnyComponents if last == 0 => automaton.repeat(CharMatch::Any),
+                Component::AnyComponents if i == last => {
+                    automaton.step(CharMatch::Any);
+                    automaton.repeat(CharMatch::Any);
+                }
+                // Includes the separator
+                Component::AnyComponents => automaton.any_dirs(),
+                Component::Tokens(tokens) => {
+                    for token in tokens {
+                        match token {
+                            Token::Char(m) => automaton.step(m.clone()),
+                            Token::Star => automaton.repeat(CharMatch::NotSlash),
+                        }
+                    }
+                    if i != last {
+                        automaton.step(CharMatch::Literal('/'));
+                    }
+                }
+            }
+        }
+        Ok(Self { pattern: pattern.to_string(), automaton: automaton.finish() })
+    }
+
+    /// `*.<ext>` with `ext` taken literally
+    pub fn extension(ext: &str) -> Self {
+        let mut automaton = Automaton::new();
+        automaton.repeat(CharMatch::NotSlash);
+        automaton.step(CharMatch::Literal('.'));
+        for c in ext.chars() {
+            automaton.step(CharMatch::Literal(c));
+        }
+        Self { pattern: format!("*.{}", escape(ext)), automaton: automaton.finish() }
+    }
+
+    /// Match letters regardless of case (default: case-sensitive)
+    pub fn with_case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.automaton = self.automaton.with_case_sensitive(case_sensitive);
+        self
+    }
+
+    /// Whether a whole normalized path matches
+    pub fn matches(&self, path: &str) -> bool {
+        self.automaton.matches(path)
+    }
+
+    /// Pattern text
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+/// Escape glob metacharacters so `text` matches only itself
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn parse_components(pattern: &str) -> Result<Vec<Component>> {
+    if pattern.is_empty() {
+        bail!("Invalid pattern: empty");
+    }
+
+    let mut components = Vec::new();
+    let mut tokens = Vec::new();
+    // Length of the current run of `*`, and whether this component had `**`
+    let mut run = 0;
+    let mut doubled = false;
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '/' => {
+                components.push(finish_component(pattern, std::mem::take(&mut tokens), doubled)?);
+                run = 0;
+                doubled = false;
+                continue;
+            }
+            '*' if run > 0 => {
+                run += 1;
+                if run > 2 {
+                    bail!("Invalid pattern {:?}: '***' is not supported", pattern);
+                }
+                doubled = true;
+                continue;
+            }
+            '*' => Token::Star,
+            '?' => Token::Char(CharMatch::NotSlash),
+            '[' => Token::Char(parse_class(pattern, &mut chars)?),
+            ']' => bail!("Invalid pattern {:?}: unmatched ']'", pattern),
+            '\\' => match chars.next() {
+                Some('/') => bail!("Invalid pattern {:?}: '/' cannot be escaped", pattern),
+                Some(escaped) => Token::Char(CharMatch::Literal(escaped)),
+                None => bail!("Invalid pattern {:?}: trailing '\\'", pattern),
+            },
+            c => Token::Char(CharMatch::Literal(c)),
+        };
+        run = usize::from(token == Token::Star);
+        tokens.push(token);
+    }
+    components.push(finish_component(pattern, tokens, doubled)?);
+    Ok(components)
+}
+
+/// Check a component; `doubled` if it contained `**`
+fn finish_component(pattern: &str, tokens: Vec<Token>, doubled: bool) -> Result<Component> {
+    if tokens.is_empty() {
+        bail!("Invalid pattern {:?}: empty component", pattern);
+    }
+    match doubled {
+        true if tokens.len() == 1 => Ok(Component::AnyComponents),
+        true => bail!("Invalid pattern {:?}: '**' must be a whole path component", pattern),
+        false => Ok(Component::Tokens(tokens)),
+    }
+}
+
+/// Parse a class after its `[`
+fn parse_class(pattern: &str, chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<CharMatch> {
+    let negated = matches!(chars.peek(), Some('!' | '^'));
+    if negated {
+        chars.next();
+    }
+
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let lo = match chars.next() {
+            None => bail!("Invalid pattern {:?}: unclosed '['", pattern),
+            Some(']') if !first => break,
+            Some('\\') => chars.next().ok_or_else(|| anyhow::anyhow!("Invalid pattern {:?}: unclosed '['", pattern))?,
+            Some(c) => c,
+        };
+        first = false;
+
+        let hi = match chars.peek() {
+            Some('-') => {
+                chars.next();
+                match chars.next() {
+                    // `-` right before `]` is literal
+                    Some(']') => {
+                        ranges.push((lo, lo));
+                        ranges.push(('-', '-'));
+                        break;
+                    }
+                    Some('\\') => chars.next().ok_or_else(|| anyhow::anyhow!("Invalid pattern {:?}: unclosed '['", pattern))?,
+                    Some(c) => c,
+                    None => bail!("Invalid pattern {:?}: unclosed '['", pattern),
+                }
+            }
+            _ => lo,
+        };
+        if lo > hi {
+            bail!("Invalid pattern {:?}: range {}-{} is reversed", pattern, lo, hi);
+        }
+        if lo <= '/' && '/' <= hi {
+            bail!("Invalid pattern {:?}: a class cannot match '/'", pattern);
+        }
+        ranges.push((lo, hi));
+    }
+    Ok(CharMatch::Class { negated, ranges })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Glob::parse(pattern).unwrap().matches(path)
+    }
+
+    #[test]
+    fn test_component_wildcards() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(matches("src/*.rs", "src/main.rs"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "a/c"));
+        assert!(matches("*", "anything"));
+        assert!(!matches("*", "a/b"));
+        assert!(matches("a*b*c", "abc"));
+        assert!(matches("a*b*c", "axxbyyc"));
+        assert!(!matches("a*b*c", "axxbyy"));
+    }
+
+    #[test]
+    fn test_globstar_components() {
+        assert!(matches("**/b", "b"));
+        assert!(matches("**/b", "a/b"));
+        assert!(matches("**/b", "a/x/b"));
+        assert!(!matches("**/b", "ab"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(!matches("a/**/b", "ab"));
+        assert!(!matches("a/**/b", "a/xb"));
+        assert!(matches("a/**", "a/x"));
+        assert!(matches("a/**", "a/x/y"));
+        assert!(!matches("a/**", "a"));
+        assert!(!matches("a/**", "ab/x"));
+        assert!(matches("**", "a/b/c"));
+        assert!(matches("**/*.rs", "main.rs"));
+        assert!(matches("**/*.rs", "src/deep/main.rs"));
+        assert!(matches("**/**/b", "b"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(matches("[abc].rs", "b.rs"));
+        assert!(!matches("[abc].rs", "d.rs"));
+        assert!(matches("[a-c]x", "bx"));
+        assert!(matches("[!a-c]x", "dx"));
+        assert!(matches("[^a-c]x", "dx"));
+        assert!(!matches("[!a-c]x", "ax"));
+        assert!(!matches("a[!x]b", "a/b"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[!]]", "a"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("[\\]]", "]"));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(matches("\\*.rs", "*.rs"));
+        assert!(!matches("\\*.rs", "a.rs"));
+        assert!(matches("\\[a\\]", "[a]"));
+        let literal = escape("we*ird?[1].rs");
+        assert!(matches(&literal, "we*ird?[1].rs"));
+        assert!(!matches(&literal, "weXirdY1.rs"));
+        assert!(Glob::extension("r*").matches("a.r*"));
+        assert!(!Glob::extension("r*").matches("a.rs"));
+    }
+
+    #[test]
+    fn test_case_sensitivity() {
+        assert!(!matches("*.RS", "main.rs"));
+        let glob = Glob::parse("src/*.RS").unwrap().with_case_sensitive(false);
+        assert!(glob.matches("SRC/main.rs"));
+        assert!(glob.matches("src/MAIN.Rs"));
+        let class = Glob::parse("[a-c]").unwrap().with_case_sensitive(false);
+        assert!(class.matches("B"));
+        assert!(!class.matches("D"));
+        let negated = Glob::parse("[!a-c]").unwrap().with_case_sensitive(false);
+        assert!(!negated.matches("B"));
+        assert!(Glob::extension("rs").with_case_sensitive(false).matches("lib.RS"));
+    }
+
+    #[test]
+    fn test_rejects_invalid_patterns() {
+        for pattern in ["", "/a", "a/", "a//b", "a**", "**a", "a/***/b", "[ab", "[b-a]", "[.-0]", "a]", "a\\", "a\\/b"] {
+            assert!(Glob::parse(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_matching_is_linear() {
+        // Exponential for a backtracking matcher
+        let pattern = "*a*a*a*a*a*a*a*a*a*a*a*a*b";
+        let glob = Glob::parse(pattern).unwrap();
+        let path = "a".repeat(200);
+        let (matched, visited) = glob.automaton.run(&path);
+        assert!(!matched);
+        assert!(visited <= glob.automaton.insts.len() * (path.len() + 1), "{}", visited);
+        assert!(glob.matches(&format!("{}b", path)));
+    }
+}
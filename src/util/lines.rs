@@ -0,0 +1,89 @@
+//! Byte offset → line and column
+//!
+//! Lines and columns are 1-based; columns count characters, not bytes. A
+//! source that is not valid UTF-8 counts every byte that does not continue
+//! a multibyte sequence as one character.
+
+/// Start offset of every line of one source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    /// Index the line starts of `source` (`\n` ends a line)
+    pub fn new(source: &[u8]) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.iter().enumerate().filter(|(_, b)| **b == b'\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { starts }
+    }
+
+    /// Number of lines (a trailing newline starts an empty last line)
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// (line, column) of `offset` in `source`, the source the index was
+    /// built from; offsets past the end are clamped to it
+    pub fn position(&self, source: &[u8], offset: usize) -> (usize, usize) {
+        let offset = floor_char_boundary(source, offset.min(source.len()));
+        let line = self.starts.partition_point(|start| *start <= offset) - 1;
+        let column = source[self.starts[line]..offset].iter().filter(|b| !is_continuation(**b)).count();
+        (line + 1, column + 1)
+    }
+}
+
+/// Whether `byte` continues a UTF-8 multibyte sequence
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// The greatest char boundary at or before `offset`
+pub fn floor_char_boundary(source: &[u8], offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while offset > 0 && offset < source.len() && is_continuation(source[offset]) {
+        offset -= 1;
+    }
+    offset
+}
+
+/// The least char boundary at or after `offset`
+pub fn ceil_char_boundary(source: &[u8], offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while offset < source.len() && is_continuation(source[offset]) {
+        offset += 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_count_characters() {
+        let source = "fn a() {}\nlet é = \"ü\";\n".as_bytes();
+        let index = LineIndex::new(source);
+
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.position(source, 0), (1, 1));
+        assert_eq!(index.position(source, 10), (2, 1));
+        // `=` follows the two-byte é
+        let equals = source.iter().position(|b| *b == b'=').unwrap();
+        assert_eq!(index.position(source, equals), (2, 7));
+        // Inside ü: the position of ü itself
+        let u = equals + 3;
+        assert_eq!(index.position(source, u + 1), index.position(source, u));
+        assert_eq!(index.position(source, 10_000), (3, 1));
+    }
+
+    #[test]
+    fn test_char_boundaries() {
+        let source = "aé".as_bytes();
+        assert_eq!(floor_char_boundary(source, 2), 1);
+        assert_eq!(ceil_char_boundary(source, 2), 3);
+        assert_eq!((floor_char_boundary(source, 1), ceil_char_boundary(source, 1)), (1, 1));
+        assert_eq!(ceil_char_boundary(source, 9), 3);
+    }
+}
@@ -0,0 +1,7 @@
+//! Small utilities shared across modules
+
+pub mod glob;
+pub mod lines;
+
+pub use glob::Glob;
+pub use lines::LineIndex;
@@ -0,0 +1,234 @@
+//! Golden hashes (Path B7)
+//!
+//! **Goal**: Catch drift across versions, not just within one process
+//!
+//! A golden directory holds fixture repositories (one subdirectory each)
+//! and a `manifest.toml` pinning every stage hash of each fixture: snapshot,
+//! per-file CFG and DFG hashes (by FunctionId, read from the epoch's
+//! `SemanticEpochReport`) and the CPG. `check` rebuilds every fixture and
+//! reports each artifact that no longer matches; `bless` rewrites the
+//! manifest and reports what changed.
+
+use crate::pipeline::Pipeline;
+use crate::repo::normalize_path;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Manifest file name inside a golden directory
+pub const MANIFEST_FILE: &str = "manifest.toml";
+
+/// Pinned hashes of every fixture, by fixture directory name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldenManifest {
+    #[serde(default)]
+    pub fixtures: BTreeMap<String, GoldenEntry>,
+}
+
+/// Pinned hashes of one fixture
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GoldenEntry {
+    pub snapshot_hash: String,
+    pub cpg_hash: String,
+
+    /// Per-file hashes, by normalized relative path
+    #[serde(default)]
+    pub files: BTreeMap<String, FileHashes>,
+}
+
+/// CFG and DFG hashes of one file, by FunctionId
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileHashes {
+    pub cfg: Vec<String>,
+    pub dfg: Vec<String>,
+}
+
+impl FileHashes {
+    /// Hashes of one stage ("cfg" or "dfg")
+    fn stage(&self, stage: &str) -> &[String] {
+        if stage == "cfg" { &self.cfg } else { &self.dfg }
+    }
+}
+
+/// One artifact whose hash differs from the manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenMismatch {
+    /// Fixture directory name
+    pub fixture: String,
+
+    /// Artifact path, e.g. `cpg` or `files/src/lib.rs/cfg/2`
+    pub artifact: String,
+
+    /// Pinned hash ("<missing>" if the manifest has none)
+    pub expected: String,
+
+    /// Rebuilt hash ("<missing>" if the artifact disappeared)
+    pub actual: String,
+}
+
+impl fmt::Display for GoldenMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {} -> {}", self.fixture, self.artifact, self.expected, self.actual)
+    }
+}
+
+const MISSING: &str = "<missing>";
+
+impl GoldenManifest {
+    /// Read `manifest.toml` from a golden directory (empty if absent)
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Write `manifest.toml` into a golden directory
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let text = toml::to_string(self).context("Failed to serialize golden manifest")?;
+        fs::write(dir.join(MANIFEST_FILE), text).context("Failed to write golden manifest")
+    }
+
+    /// Rebuild every fixture subdirectory of `dir` (sorted by name)
+    pub fn build(dir: &Path) -> Result<Self> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .with_context(|| format!("Failed to read golden directory {}", dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+
+        let pipeline = Pipeline::default();
+        let mut fixtures = BTreeMap::new();
+        for name in names {
+            let output = pipeline.run(&dir.join(&name))
+                .with_context(|| format!("Failed to build golden fixture {}", name))?;
+            let files: BTreeMap<String, FileHashes> = output.semantic.report().files.iter()
+                .map(|file| {
+                    let path = normalize_path(&output.snapshot.files[&file.file_id].path);
+                    (path, FileHashes { cfg: file.cfg_hashes(), dfg: file.dfg_hashes() })
+                })
+                .collect();
+            fixtures.insert(name, GoldenEntry {
+                snapshot_hash: output.snapshot.snapshot_hash.clone(),
+                cpg_hash: output.cpg_epoch.cpg_hash().to_string(),
+                files,
+            });
+        }
+        Ok(Self { fixtures })
+    }
+
+    /// Every artifact of `actual` that differs from `self`, in manifest order
+    pub fn diff(&self, actual: &GoldenManifest) -> Vec<GoldenMismatch> {
+        let mut mismatches = Vec::new();
+        let empty = GoldenEntry::default();
+
+        for fixture in union_keys(&self.fixtures, &actual.fixtures) {
+            let expected = self.fixtures.get(fixture).unwrap_or(&empty);
+            let rebuilt = actual.fixtures.get(fixture).unwrap_or(&empty);
+            let mut push = |artifact: String, old: Option<&String>, new: Option<&String>| {
+                if old != new {
+                    mismatches.push(GoldenMismatch {
+                        fixture: fixture.clone(),
+                        artifact,
+                        expected: old.map_or(MISSING, String::as_str).to_string(),
+                        actual: new.map_or(MISSING, String::as_str).to_string(),
+                    });
+                }
+            };
+            let present = |entry: &GoldenEntry, hash| (entry != &empty).then_some(hash);
+
+            push("snapshot".into(), present(expected, &expected.snapshot_hash), present(rebuilt, &rebuilt.snapshot_hash));
+            for file in union_keys(&expected.files, &rebuilt.files) {
+                let (old, new) = (expected.files.get(file), rebuilt.files.get(file));
+                for stage in ["cfg", "dfg"] {
+                    let (old, new) = (old.map(|h| h.stage(stage)), new.map(|h| h.stage(stage)));
+                    let count = old.map_or(0, <[_]>::len).max(new.map_or(0, <[_]>::len));
+                    for i in 0..count {
+                        push(
+                            format!("files/{}/{}/{}", file, stage, i),
+                            old.and_then(|hashes| hashes.get(i)),
+                            new.and_then(|hashes| hashes.get(i)),
+                        );
+                    }
+                }
+            }
+            push("cpg".into(), present(expected, &expected.cpg_hash), present(rebuilt, &rebuilt.cpg_hash));
+        }
+        mismatches
+    }
+}
+
+/// Rebuild the fixtures in `dir` and compare them with its manifest
+pub fn check(dir: &Path) -> Result<Vec<GoldenMismatch>> {
+    let pinned = GoldenManifest::load(dir)?;
+    Ok(pinned.diff(&GoldenManifest::build(dir)?))
+}
+
+/// Rebuild the fixtures in `dir`, rewrite its manifest, and return what changed
+pub fn bless(dir: &Path) -> Result<Vec<GoldenMismatch>> {
+    let pinned = GoldenManifest::load(dir)?;
+    let rebuilt = GoldenManifest::build(dir)?;
+    let changes = pinned.diff(&rebuilt);
+    rebuilt.save(dir)?;
+    Ok(changes)
+}
+
+/// Keys of either map, ascending
+fn union_keys<'a, V>(a: &'a BTreeMap<String, V>, b: &'a BTreeMap<String, V>) -> Vec<&'a String> {
+    let mut keys: Vec<_> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn golden_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let fixture = dir.path().join("basic");
+        fs::create_dir_all(&fixture).unwrap();
+        fs::write(fixture.join("lib.rs"), "fn a() { let x = 1; if x > 0 { b(); } }\nfn b() {}\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_bless_then_check_is_clean() {
+        let dir = golden_dir();
+
+        let first = bless(dir.path()).unwrap();
+        assert!(first.iter().any(|m| m.artifact == "cpg" && m.expected == MISSING));
+        assert!(check(dir.path()).unwrap().is_empty());
+        assert!(bless(dir.path()).unwrap().is_empty());
+
+        let manifest = GoldenManifest::load(dir.path()).unwrap();
+        assert_eq!(manifest.fixtures["basic"].files["lib.rs"].cfg.len(), 2);
+    }
+
+    #[test]
+    fn test_check_names_the_diverged_artifact() {
+        let dir = golden_dir();
+        bless(dir.path()).unwrap();
+        let mut manifest = GoldenManifest::load(dir.path()).unwrap();
+        let entry = manifest.fixtures.get_mut("basic").unwrap();
+        entry.files.get_mut("lib.rs").unwrap().dfg[1] = "stale".into();
+        manifest.save(dir.path()).unwrap();
+
+        let mismatches = check(dir.path()).unwrap();
+
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].artifact, "files/lib.rs/dfg/1");
+        assert_eq!(mismatches[0].expected, "stale");
+        assert!(mismatches[0].to_string().starts_with("basic: files/lib.rs/dfg/1: stale -> "));
+    }
+}
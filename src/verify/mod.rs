@@ -0,0 +1,255 @@
+//! Determinism self-check (Path B7)
+//!
+//! **Goal**: Catch divergence at runtime, not in a bug report
+//!
+//! The whole pipeline runs twice in one process. Every stage hash is
+//! compared; any mismatch fails closed.
+//!
+//! `audit` does the same for incremental runs, one sampled file at a time.
+//! `golden` compares against hashes pinned in a manifest, across versions.
+
+pub mod audit;
+pub mod golden;
+
+pub use audit::Auditor;
+pub use golden::{GoldenManifest, GoldenMismatch};
+
+use crate::pipeline::{Pipeline, PipelineOutput};
+use crate::semantic::SemanticEpoch;
+use crate::types::FileId;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Hashes of every pipeline stage for one run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageHashes {
+    /// Repository snapshot hash (from the scanner)
+    pub snapshot_hash: String,
+
+    /// CFG hashes per file, in file order
+    pub cfg_hashes: Vec<(FileId, Vec<String>)>,
+
+    /// DFG hashes per file, in file order
+    pub dfg_hashes: Vec<(FileId, Vec<String>)>,
+
+    /// Final CPG hash
+    pub cpg_hash: String,
+}
+
+impl StageHashes {
+    /// Collect stage hashes from a finished pipeline run
+    pub fn from_output(build: &PipelineOutput) -> Self {
+        let (cfg_hashes, dfg_hashes) = build.semantic.get_all_file_ids().into_iter()
+            .map(|id| {
+                let (cfgs, dfgs) = file_hashes(&build.semantic, id);
+                ((id, cfgs), (id, dfgs))
+            })
+            .unzip();
+
+        Self {
+            snapshot_hash: build.snapshot.snapshot_hash.clone(),
+            cfg_hashes,
+            dfg_hashes,
+            cpg_hash: build.cpg_epoch.cpg_hash().to_string(),
+        }
+    }
+
+    /// Stages where `other` differs from these hashes (empty = equal)
+    pub fn divergences(&self, other: &StageHashes) -> Vec<Divergence> {
+        compare(self, other)
+    }
+}
+
+/// CFG and DFG hashes of one file, in function order
+///
+/// Reuses the hashes kept with the file's fingerprint when they are
+/// current; hashes the graphs only when they are not.
+pub fn file_hashes(semantic: &SemanticEpoch, file_id: FileId) -> (Vec<String>, Vec<String>) {
+    if let Some((cfgs, dfgs)) = semantic.graph_hashes(file_id) {
+        return (cfgs.to_vec(), dfgs.to_vec());
+    }
+
+    let cfgs = semantic.get_cfgs(file_id)
+        .map(|cfgs| cfgs.iter().map(|c| c.compute_hash()).collect())
+        .unwrap_or_default();
+    let dfgs = semantic.get_dfgs(file_id)
+        .map(|dfgs| dfgs.iter().map(|d| d.compute_hash(semantic.strings())).collect())
+        .unwrap_or_default();
+    (cfgs, dfgs)
+}
+
+/// A stage whose hash differed between runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Stage name ("snapshot", "cfg", "dfg", "cpg")
+    pub stage: &'static str,
+
+    /// What differed
+    pub detail: String,
+}
+
+/// Result of a determinism check
+#[derive(Debug, Clone)]
+pub struct DeterminismReport {
+    /// Hashes from the first run
+    pub first: StageHashes,
+
+    /// All divergences (empty = deterministic)
+    pub divergences: Vec<Divergence>,
+}
+
+impl DeterminismReport {
+    /// True if both runs matched at every stage
+    pub fn is_deterministic(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    /// Fail closed if any stage diverged
+    pub fn into_result(self) -> Result<StageHashes> {
+        if let Some(first) = self.divergences.first() {
+            bail!(
+                "Determinism check failed at stage '{}': {} ({} divergence(s) total)",
+                first.stage, first.detail, self.divergences.len()
+            );
+        }
+        Ok(self.first)
+    }
+}
+
+/// Run a pipeline twice and compare stage hashes
+pub fn check_determinism<F>(mut run: F) -> Result<DeterminismReport>
+where
+    F: FnMut() -> Result<StageHashes>,
+{
+    let first = run()?;
+    let second = run()?;
+    let divergences = compare(&first, &second);
+
+    Ok(DeterminismReport { first, divergences })
+}
+
+/// Build a repository twice and compare stage hashes
+pub fn verify_repo(root: &Path) -> Result<DeterminismReport> {
+    let pipeline = Pipeline::default();
+    check_determinism(|| pipeline.run(root).map(|output| StageHashes::from_output(&output)))
+}
+
+/// Compare two runs stage by stage
+fn compare(a: &StageHashes, b: &StageHashes) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+
+    if a.snapshot_hash != b.snapshot_hash {
+        divergences.push(Divergence {
+            stage: "snapshot",
+            detail: format!("{} != {}", a.snapshot_hash, b.snapshot_hash),
+        });
+    }
+
+    compare_per_file("cfg", &a.cfg_hashes, &b.cfg_hashes, &mut divergences);
+    compare_per_file("dfg", &a.dfg_hashes, &b.dfg_hashes, &mut divergences);
+
+    if a.cpg_hash != b.cpg_hash {
+        divergences.push(Divergence {
+            stage: "cpg",
+            detail: format!("{} != {}", a.cpg_hash, b.cpg_hash),
+        });
+    }
+
+    divergences
+}
+
+/// Compare per-file hash lists
+fn compare_per_file(
+    stage: &'static str,
+    a: &[(FileId, Vec<String>)],
+    b: &[(FileId, Vec<String>)],
+    divergences: &mut Vec<Divergence>,
+) {
+    if a.len() != b.len() {
+        divergences.push(Divergence {
+            stage,
+            detail: format!("file count {} != {}", a.len(), b.len()),
+        });
+        return;
+    }
+
+    for ((file_a, hashes_a), (file_b, hashes_b)) in a.iter().zip(b) {
+        if file_a != file_b {
+            divergences.push(Divergence {
+                stage,
+                detail: format!("file order {:?} != {:?}", file_a, file_b),
+            });
+        } else if hashes_a != hashes_b {
+            divergences.push(Divergence {
+                stage,
+                detail: format!("{:?} hashes differ", file_a),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn temp_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "fn a() { let x = 1; if x > 0 { return; } }\n").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "fn b(y: i32) -> i32 { y + 1 }\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_real_pipeline_is_deterministic() {
+        let dir = temp_repo();
+        let report = verify_repo(dir.path()).unwrap();
+
+        assert!(report.is_deterministic(), "{:?}", report.divergences);
+        assert!(report.into_result().is_ok());
+    }
+
+    /// CFG stage that hashes nodes in HashMap iteration order
+    fn nondeterministic_run() -> Result<StageHashes> {
+        let nodes: HashMap<u64, u64> = (0..64).map(|i| (i, i * 31)).collect();
+
+        let mut hasher = Sha256::new();
+        for (id, value) in &nodes {
+            hasher.update(id.to_le_bytes());
+            hasher.update(value.to_le_bytes());
+        }
+
+        Ok(StageHashes {
+            snapshot_hash: "snapshot".to_string(),
+            cfg_hashes: vec![(FileId::new(1), vec![format!("{:x}", hasher.finalize())])],
+            dfg_hashes: vec![(FileId::new(1), vec![])],
+            cpg_hash: "cpg".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_nondeterministic_stage_is_caught() {
+        let report = check_determinism(nondeterministic_run).unwrap();
+
+        assert!(!report.is_deterministic());
+        assert_eq!(report.divergences[0].stage, "cfg");
+
+        let err = report.into_result().unwrap_err().to_string();
+        assert!(err.contains("stage 'cfg'"), "{}", err);
+    }
+
+    #[test]
+    fn test_compare_reports_every_stage() {
+        let a = nondeterministic_run().unwrap();
+        let b = StageHashes {
+            snapshot_hash: "other".to_string(),
+            cpg_hash: "other".to_string(),
+            ..a.clone()
+        };
+
+        let stages: Vec<_> = compare(&a, &b).iter().map(|d| d.stage).collect();
+        assert_eq!(stages, vec!["snapshot", "cpg"]);
+    }
+}
@@ -0,0 +1,69 @@
+//! Benchmark smoke tests (Path B4)
+//!
+//! Runs every path `benches/ingest.rs` measures once, on a CI-sized
+//! generated repository, so the benchmarks cannot silently rot.
+
+use tempfile::TempDir;
+use vcr::cpg::builder::CPGBuilder;
+use vcr::cpg::CPGEpoch;
+use vcr::io::{create_backend, IOMode};
+use vcr::pipeline::Pipeline;
+use vcr::query::{QueryEngine, QuerySpec};
+use vcr::repo::RepoScanner;
+use vcr::testing::{edit_file, generate_repo};
+
+const FILES: usize = 20;
+const AVG_SIZE: usize = 512;
+const SEED: u64 = 7;
+
+#[test]
+fn test_generator_is_reproducible() {
+    let (a, b, c) = (TempDir::new().unwrap(), TempDir::new().unwrap(), TempDir::new().unwrap());
+    let paths_a = generate_repo(a.path(), FILES, AVG_SIZE, SEED).unwrap();
+    let paths_b = generate_repo(b.path(), FILES, AVG_SIZE, SEED).unwrap();
+    generate_repo(c.path(), FILES, AVG_SIZE, SEED + 1).unwrap();
+
+    assert_eq!(paths_a.len(), FILES);
+    for (pa, pb) in paths_a.iter().zip(&paths_b) {
+        assert_eq!(pa.strip_prefix(a.path()).unwrap(), pb.strip_prefix(b.path()).unwrap());
+        assert_eq!(std::fs::read(pa).unwrap(), std::fs::read(pb).unwrap());
+    }
+
+    let scan = |dir: &TempDir| RepoScanner::new(dir.path()).unwrap().with_extension("rs").scan().unwrap().snapshot_hash;
+    assert_eq!(scan(&a), scan(&b));
+    assert_ne!(scan(&a), scan(&c));
+}
+
+#[test]
+fn test_benchmarked_paths_run() {
+    let dir = TempDir::new().unwrap();
+    let paths = generate_repo(dir.path(), FILES, AVG_SIZE, SEED).unwrap();
+
+    // IO: hot and cold read the same bytes
+    let hot = create_backend(IOMode::Hot);
+    let cold = create_backend(IOMode::Cold);
+    assert_eq!(hot.read_file(&paths[0]).unwrap(), cold.read_file(&paths[0]).unwrap());
+
+    // Scan: serial and parallel agree
+    let scan = |threads| RepoScanner::new(dir.path()).unwrap().with_extension("rs").with_threads(threads).scan().unwrap();
+    assert_eq!(scan(1).snapshot_hash, scan(4).snapshot_hash);
+
+    // Full ingest
+    let pipeline = Pipeline::default();
+    let output = pipeline.run(dir.path()).unwrap();
+    assert_eq!(output.rebuilt.len(), FILES);
+
+    // Fusion reproduces the pipeline's CPG
+    let mut epoch = CPGEpoch::new(output.semantic.epoch_id(), 0);
+    CPGBuilder::new().build(&output.semantic, &mut epoch).unwrap();
+    assert_eq!(epoch.cpg().compute_hash(), output.cpg_epoch.cpg().compute_hash());
+
+    // Query
+    let spec = QuerySpec::from_json(r#"{"pipeline": [{"find": "CfgNode"}, {"follow": "ControlFlow"}]}"#).unwrap();
+    assert!(QueryEngine::new().execute(output.cpg_epoch.cpg(), &spec).unwrap().total > 0);
+
+    // Incremental after one edit rebuilds one file
+    edit_file(&paths[FILES / 2], SEED).unwrap();
+    let incremental = pipeline.run_incremental(&output).unwrap();
+    assert_eq!(incremental.rebuilt.len(), 1);
+}
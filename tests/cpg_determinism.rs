@@ -6,11 +6,9 @@
 //! - Queries that "sometimes" work = broken
 
 use vcr::*;
-use vcr::cpg::{CPGEpoch, model::CPGNodeKind};
+use vcr::cpg::CPGEpoch;
 use vcr::cpg::builder::CPGBuilder;
 use vcr::query::primitives::QueryPrimitives;
-use vcr::semantic::cfg::CFGBuilder;
-use vcr::semantic::symbols::SymbolTable;
 use std::fs;
 use tempfile::NamedTempFile;
 
@@ -28,20 +26,7 @@ fn test_cpg_hash_stability() {
     let mut parser = parse::IncrementalParser::new(types::Language::Rust).unwrap();
     let parsed = parser.parse(&mmap, None).unwrap();
 
-    let mut cfg_builder = CFGBuilder::new(file_id, source);
-    let cfgs = cfg_builder.build_all(&parsed).unwrap();
-
-    let mut symbols = SymbolTable::new(file_id);
-    symbols.build(&parsed, source).unwrap();
-
-    let semantic = semantic::SemanticEpoch {
-        _parse_epoch_marker: 2,
-        cfgs: [(file_id, cfgs)].into_iter().collect(),
-        dfgs: std::collections::HashMap::new(),
-        symbols: [(file_id, symbols)].into_iter().collect(),
-        invalidation: semantic::invalidation::InvalidationTracker::new(),
-        epoch_id: 3,
-    };
+    let semantic = semantic::SemanticEpoch::build_from_parsed(&[(file_id, &parsed, source)]).unwrap();
 
     // Build CPG twice
     let mut cpg_epoch1 = CPGEpoch::new(3, 4);
@@ -62,7 +47,6 @@ fn test_cpg_hash_stability() {
 #[test]
 fn test_query_determinism() {
     // Same query → same result order (ALWAYS)
-    // Simplified to test CPG directly without private semantic fields
     use vcr::cpg::model::*;
     use vcr::types::ByteRange;
     
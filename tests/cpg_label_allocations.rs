@@ -0,0 +1,69 @@
+//! CPG label memory tests
+//!
+//! Interned labels must not cost an allocation per node. A counting global
+//! allocator (this test binary only) measures it.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use vcr::cpg::model::{CPGNode, CPGNodeId, CPGNodeKind, OriginRef, CPG};
+use vcr::types::ByteRange;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const NODES: u64 = 100_000;
+const LABELS: [&str; 6] = ["Entry", "Exit", "Statement", "Branch", "Merge", "LoopHeader"];
+
+fn node(i: u64) -> CPGNode {
+    CPGNode::new(
+        CPGNodeId(i),
+        CPGNodeKind::CfgNode,
+        OriginRef::Ast { range: ByteRange::new(0, 0) },
+        ByteRange::new(i as usize, i as usize + 1),
+    )
+}
+
+#[test]
+fn test_interned_labels_allocate_sublinearly() {
+    // Baseline: one owned String per node, as labels used to be stored
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let owned: Vec<(CPGNode, String)> = (0..NODES)
+        .map(|i| (node(i), LABELS[i as usize % LABELS.len()].to_string()))
+        .collect();
+    let per_node_strings = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(owned);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let mut cpg = CPG::new();
+    for i in 0..NODES {
+        let label = cpg.intern_label(LABELS[i as usize % LABELS.len()]);
+        cpg.add_node(node(i).with_label(label));
+    }
+    let interned = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    assert_eq!(cpg.nodes.len(), NODES as usize);
+    assert_eq!(cpg.label_count(), LABELS.len());
+    assert_eq!(cpg.label(&cpg.nodes[3]), Some("Branch"));
+    assert!(per_node_strings >= NODES as usize);
+    assert!(interned * 100 < per_node_strings, "{} allocations interned vs {} with owned labels", interned, per_node_strings);
+}
@@ -0,0 +1,144 @@
+//! CPG validation tests (Step 3.2)
+//!
+//! Fused graphs must validate; deliberately corrupted graphs must report
+//! the matching ValidationError.
+
+use vcr::cpg::model::*;
+use vcr::cpg::ValidationError;
+use vcr::pipeline::{Pipeline, PipelineOutput};
+use vcr::semantic::model::{NodeId, SymbolId, ValueId};
+use tempfile::TempDir;
+
+const SOURCE: &str = "fn a(c: bool) -> i32 { let mut x = 1; if c { x = 2; } let y = x; y }\n";
+
+fn fused() -> (TempDir, PipelineOutput) {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("a.rs"), SOURCE).unwrap();
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    (dir, output)
+}
+
+fn errors(cpg: &CPG, output: &PipelineOutput) -> Vec<ValidationError> {
+    cpg.validate_against(&output.semantic).unwrap_err()
+}
+
+fn first_edge(cpg: &CPG, kind: CPGEdgeKind) -> usize {
+    cpg.edges.iter().position(|e| e.kind == kind).unwrap()
+}
+
+#[test]
+fn test_fused_graph_is_valid() {
+    let (_dir, output) = fused();
+    let cpg = output.cpg_epoch.cpg();
+
+    assert!(cpg.validate().is_ok());
+    assert!(cpg.validate_against(&output.semantic).is_ok());
+    assert!(!cpg.get_edges_of_kind(CPGEdgeKind::ControlFlow).is_empty());
+    assert!(!cpg.get_edges_of_kind(CPGEdgeKind::DataFlow).is_empty());
+}
+
+#[test]
+fn test_flow_edges_connect_their_own_nodes() {
+    let (_dir, output) = fused();
+    let cpg = output.cpg_epoch.cpg();
+
+    for edge in cpg.get_edges_of_kind(CPGEdgeKind::ControlFlow) {
+        for id in [edge.from, edge.to] {
+            assert!(matches!(cpg.get_node(id).unwrap().origin, OriginRef::Cfg { .. }));
+        }
+    }
+    for edge in cpg.get_edges_of_kind(CPGEdgeKind::DataFlow) {
+        for id in [edge.from, edge.to] {
+            assert!(matches!(cpg.get_node(id).unwrap().origin, OriginRef::Dfg { .. }));
+        }
+    }
+}
+
+#[test]
+fn test_raw_cfg_ids_as_endpoints_are_reported() {
+    let (_dir, output) = fused();
+    let mut cpg = output.cpg_epoch.cpg().clone();
+
+    // What fusion used to do: reuse the CFG NodeId as the CPG node ID
+    let i = first_edge(&cpg, CPGEdgeKind::ControlFlow);
+    let cfg_from = match cpg.get_node(cpg.edges[i].from).unwrap().origin {
+        OriginRef::Cfg { node_id } => node_id,
+        origin => panic!("unexpected origin {:?}", origin),
+    };
+    cpg.edges[i].from = CPGNodeId(cfg_from.0);
+
+    let errors = cpg.validate().unwrap_err();
+    assert!(matches!(errors[..], [ValidationError::EndpointKind {
+        kind: CPGEdgeKind::ControlFlow,
+        expected: CPGNodeKind::CfgNode,
+        ..
+    }]), "{:?}", errors);
+}
+
+#[test]
+fn test_dangling_edge_is_reported() {
+    let (_dir, output) = fused();
+    let mut cpg = output.cpg_epoch.cpg().clone();
+    let missing = CPGNodeId(cpg.nodes.len() as u64 + 100);
+    let i = first_edge(&cpg, CPGEdgeKind::DataFlow);
+    cpg.edges[i].to = missing;
+
+    let edge = cpg.edges[i].id;
+    assert_eq!(errors(&cpg, &output), vec![ValidationError::DanglingEdge { edge, node: missing }]);
+}
+
+#[test]
+fn test_duplicate_edge_id_is_reported() {
+    let (_dir, output) = fused();
+    let mut cpg = output.cpg_epoch.cpg().clone();
+    let duplicate = cpg.edges[0].id;
+    cpg.edges[1].id = duplicate;
+
+    assert_eq!(errors(&cpg, &output), vec![ValidationError::DuplicateEdge(duplicate)]);
+}
+
+#[test]
+fn test_out_of_order_nodes_are_reported() {
+    let (_dir, output) = fused();
+    let mut cpg = output.cpg_epoch.cpg().clone();
+    cpg.nodes.swap(1, 2);
+    let (first, second) = (cpg.nodes[1].id, cpg.nodes[2].id);
+
+    assert_eq!(
+        cpg.validate().unwrap_err(),
+        vec![ValidationError::NodeOrder { index: 2, id: second, previous: first }]
+    );
+}
+
+#[test]
+fn test_missing_origins_are_reported() {
+    let (_dir, output) = fused();
+    let mut cpg = output.cpg_epoch.cpg().clone();
+
+    let mut corrupted = Vec::new();
+    for (kind, origin) in [
+        (CPGNodeKind::CfgNode, OriginRef::Cfg { node_id: NodeId(9_999) }),
+        (CPGNodeKind::DfgValue, OriginRef::Dfg { value_id: ValueId(9_999) }),
+        (CPGNodeKind::Symbol, OriginRef::Symbol { symbol_id: SymbolId(9_999) }),
+    ] {
+        let node = cpg.nodes.iter_mut().find(|n| n.kind == kind).unwrap();
+        node.origin = origin;
+        corrupted.push(ValidationError::MissingOrigin { node: node.id, origin });
+    }
+
+    // Structure is intact; only the semantic cross-check fails
+    assert!(cpg.validate().is_ok());
+    assert_eq!(errors(&cpg, &output), corrupted);
+}
+
+#[test]
+fn test_strict_build_matches_plain_build() {
+    let (_dir, output) = fused();
+    let mut epoch = vcr::cpg::CPGEpoch::new(output.semantic.epoch_id(), output.semantic.epoch_id() + 1);
+    vcr::cpg::builder::CPGBuilder::new()
+        .with_strict_validation(true)
+        .build(&output.semantic, &mut epoch)
+        .unwrap();
+
+    assert_eq!(epoch.cpg().compute_hash(), output.cpg_epoch.cpg().compute_hash());
+}
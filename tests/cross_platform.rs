@@ -0,0 +1,73 @@
+//! Cross-platform determinism tests
+//!
+//! - CRLF and LF checkouts → same hashes with `normalize_line_endings`,
+//!   different ones without
+//! - `\` path separators → same FileIds and snapshot as `/`
+
+use vcr::config::ValoriConfig;
+use vcr::io::MemoryBackend;
+use vcr::pipeline::{Pipeline, PipelineOutput};
+use vcr::repo::normalize_path;
+use vcr::verify::StageHashes;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const FILES: &[(&str, &str)] = &[
+    ("src/main.rs", "fn main() {\n    let x = helper(1);\n    if x > 0 {\n        log(x);\n    }\n}\n"),
+    ("src/helper.rs", "pub fn helper(n: i32) -> i32 {\n    let mut y = n;\n    while y < 10 { y = y + 1; }\n    y\n}\n"),
+];
+
+/// Build `FILES` from memory, with `separator` between path components and
+/// `newline` ending lines
+fn build(separator: &str, newline: &str, normalize: bool) -> PipelineOutput {
+    let mut backend = MemoryBackend::new(HashMap::new());
+    for (path, source) in FILES {
+        backend.insert(format!("/repo/{}", path.replace('/', separator)), source.replace('\n', newline));
+    }
+    Pipeline::default()
+        .with_backend(Arc::new(backend))
+        .with_normalized_line_endings(normalize)
+        .run(Path::new("/repo"))
+        .unwrap()
+}
+
+#[test]
+fn test_crlf_and_lf_hash_identically_when_normalized() {
+    let lf = build("/", "\n", true);
+    let crlf = build("/", "\r\n", true);
+
+    assert_eq!(lf.snapshot.snapshot_hash, crlf.snapshot.snapshot_hash);
+    assert_eq!(StageHashes::from_output(&lf), StageHashes::from_output(&crlf));
+    assert_eq!(lf.cpg_epoch.cpg_hash(), crlf.cpg_epoch.cpg_hash());
+}
+
+#[test]
+fn test_crlf_and_lf_differ_without_normalization() {
+    let lf = build("/", "\n", false);
+    let crlf = build("/", "\r\n", false);
+
+    assert_ne!(lf.snapshot.snapshot_hash, crlf.snapshot.snapshot_hash);
+    assert_ne!(lf.cpg_epoch.cpg_hash(), crlf.cpg_epoch.cpg_hash());
+    // Normalization leaves LF files as they are
+    assert_eq!(StageHashes::from_output(&lf), StageHashes::from_output(&build("/", "\n", true)));
+}
+
+#[test]
+fn test_config_enables_normalization() {
+    let mut config = ValoriConfig::default();
+    assert!(!Pipeline::new(&config).normalizes_line_endings());
+    config.parse.normalize_line_endings = true;
+    assert!(Pipeline::new(&config).normalizes_line_endings());
+}
+
+#[test]
+fn test_backslash_paths_normalize_to_slashes() {
+    assert_eq!(normalize_path(Path::new("src\\nested\\lib.rs")), "src/nested/lib.rs");
+    assert_eq!(normalize_path(Path::new(".\\src\\\\lib.rs")), "src/lib.rs");
+
+    let unix = build("/", "\n", true);
+    let windows = build("\\", "\r\n", true);
+    assert_eq!(unix.snapshot.file_ids(), windows.snapshot.file_ids());
+    assert_eq!(StageHashes::from_output(&unix), StageHashes::from_output(&windows));
+}
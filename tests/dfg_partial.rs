@@ -0,0 +1,191 @@
+//! Partial DFG rebuilds equal full rebuilds
+//!
+//! A 50-statement function (straight-line code, both arms of an `if`, a
+//! loop body and a `let` initialized by `if`) takes random single-statement
+//! edits. After each one the DFG is rebuilt partially from the previous
+//! (itself partial) DFG and must serialize exactly like a full rebuild.
+
+use proptest::prelude::*;
+use vcr::io::BufferedFile;
+use vcr::memory::arena::StringArena;
+use vcr::metrics::MetricsCollector;
+use vcr::parse::IncrementalParser;
+use vcr::semantic::{CFGBuilder, DFGBuilder, NodeId, SymbolTable, CFG, DFG};
+use vcr::types::{FileId, Language};
+
+/// Statements a slot may hold; none changes the CFG's shape
+const STATEMENTS: &[&str] = &[
+    "let a = 1;",
+    "a = b * 3;",
+    "let b = a + 2;",
+    "b = -c;",
+    "let c = (a, b);",
+    "c = foo(a, 1);",
+    "d = a;",
+    "let d = \"s\";",
+    "let e = a.len() + p;",
+    "e = p;",
+    "foo(a, b);",
+    "a += 1;",
+    "let (x, y) = (1, 2);",
+];
+
+/// Statements defining the same variable (or none)
+const GROUPS: &[&[usize]] = &[&[0, 1], &[2, 3], &[4, 5], &[6, 7], &[8, 9], &[10, 11, 12]];
+
+/// A statement defining the same variable as `statement`
+fn same_definition(statement: usize, choice: usize) -> usize {
+    let group = GROUPS.iter().find(|group| group.contains(&statement)).unwrap();
+    group[choice % group.len()]
+}
+
+/// Slots before the `if`, in each arm, before the loop, in the loop, after it
+const LAYOUT: [usize; 6] = [10, 5, 5, 10, 5, 15];
+
+fn render(slots: &[usize]) -> (String, Vec<usize>) {
+    let mut source = String::from("fn f(p: i32) -> i32 {\n");
+    let mut offsets = Vec::new();
+    let mut slots = slots.iter();
+    let mut push = |source: &mut String, n: usize, indent: &str| {
+        for slot in slots.by_ref().take(n) {
+            source.push_str(indent);
+            offsets.push(source.len());
+            source.push_str(STATEMENTS[*slot]);
+            source.push('\n');
+        }
+    };
+    push(&mut source, LAYOUT[0], "    ");
+    source.push_str("    if p > 0 {\n");
+    push(&mut source, LAYOUT[1], "        ");
+    source.push_str("    } else {\n");
+    push(&mut source, LAYOUT[2], "        ");
+    source.push_str("    }\n");
+    push(&mut source, LAYOUT[3], "    ");
+    source.push_str("    while a < 3 {\n");
+    push(&mut source, LAYOUT[4], "        ");
+    source.push_str("    }\n");
+    push(&mut source, LAYOUT[5], "    ");
+    source.push_str("    let r = if p > 1 { a } else { b + 1 };\n    r + a\n}\n");
+    (source, offsets)
+}
+
+/// Build the function's CFG and hand it, with the builder inputs, to `f`
+fn with_builder<T>(source: &str, f: impl FnOnce(&CFG, DFGBuilder) -> T) -> T {
+    let file_id = FileId::new(1);
+    let file = BufferedFile::new(source.as_bytes().to_vec(), file_id);
+    let parsed = IncrementalParser::new(Language::Rust).unwrap().parse(&file, None).unwrap();
+    let cfgs = CFGBuilder::new(file_id, source.as_bytes()).build_all(&parsed, &mut StringArena::new()).unwrap();
+    let mut symbols = SymbolTable::new(file_id);
+    symbols.build(&parsed, source.as_bytes()).unwrap();
+    let index = parsed.preorder_index();
+    f(&cfgs[0], DFGBuilder::new(&cfgs[0], &symbols, &index, source.as_bytes()))
+}
+
+fn full(source: &str, strings: &mut StringArena) -> DFG {
+    with_builder(source, |_, builder| builder.build(strings).unwrap())
+}
+
+/// Partial rebuild invalidating the statement starting at `offset`
+fn partial(source: &str, prev: &DFG, offset: usize, strings: &mut StringArena, metrics: &MetricsCollector) -> DFG {
+    with_builder(source, |cfg, builder| {
+        let invalidated: Vec<NodeId> = cfg.nodes.iter()
+            .filter(|node| node.source_range.start == offset)
+            .map(|node| node.id)
+            .collect();
+        assert_eq!(invalidated.len(), 1, "offset {}", offset);
+        builder.rebuild_partial_with_metrics(prev, &invalidated, strings, metrics).unwrap()
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn prop_partial_rebuild_matches_full(
+        initial in prop::collection::vec(0..STATEMENTS.len(), 50),
+        edits in prop::collection::vec((0usize..50, 0..STATEMENTS.len(), any::<bool>()), 1..=6),
+    ) {
+        let mut strings = StringArena::new();
+        let metrics = MetricsCollector::new();
+        let mut slots = initial;
+        let mut prev = full(&render(&slots).0, &mut strings);
+        let mut total_values = 0;
+
+        for (slot, choice, keep_definition) in edits {
+            slots[slot] = match keep_definition {
+                true => same_definition(slots[slot], choice),
+                false => choice,
+            };
+            let (source, offsets) = render(&slots);
+            let reused = metrics.dfg_values_reused();
+            let rebuilt = partial(&source, &prev, offsets[slot], &mut strings, &metrics);
+            let expected = full(&source, &mut strings);
+
+            prop_assert_eq!(rebuilt.compute_hash(&strings), expected.compute_hash(&strings));
+            prop_assert_eq!(serde_json::to_value(&rebuilt).unwrap(), serde_json::to_value(&expected).unwrap(), "{}", source);
+            if keep_definition {
+                prop_assert!(metrics.dfg_values_reused() > reused, "{}", source);
+            }
+            total_values += expected.values.len();
+            prev = rebuilt;
+        }
+
+        prop_assert_eq!(metrics.dfg_values_reused() + metrics.dfg_values_rebuilt(), total_values);
+    }
+}
+
+#[test]
+fn test_unchanged_definitions_reuse_most_values() {
+    let mut strings = StringArena::new();
+    let metrics = MetricsCollector::new();
+    let mut slots: Vec<usize> = (0..50).map(|i| i % STATEMENTS.len()).collect();
+    let prev = full(&render(&slots).0, &mut strings);
+
+    // `let b = a + 2;` -> `b = -c;`: still defines b
+    assert_eq!(slots[15], 2);
+    slots[15] = 3;
+    let (source, offsets) = render(&slots);
+    let rebuilt = partial(&source, &prev, offsets[15], &mut strings, &metrics);
+    let expected = full(&source, &mut strings);
+
+    assert_eq!(rebuilt.compute_hash(&strings), expected.compute_hash(&strings));
+    assert!(metrics.dfg_values_reused() > 0);
+    assert!(
+        metrics.dfg_values_rebuilt() < expected.values.len() / 4,
+        "{} of {} values rebuilt", metrics.dfg_values_rebuilt(), expected.values.len()
+    );
+}
+
+#[test]
+fn test_changed_definition_rebuilds_everything() {
+    let mut strings = StringArena::new();
+    let metrics = MetricsCollector::new();
+    let mut slots = vec![0; 50];
+    let prev = full(&render(&slots).0, &mut strings);
+
+    // `let a = 1;` -> `foo(a, b);` changes which definition reaches later uses
+    slots[3] = 10;
+    let (source, offsets) = render(&slots);
+    let rebuilt = partial(&source, &prev, offsets[3], &mut strings, &metrics);
+
+    assert_eq!(rebuilt.compute_hash(&strings), full(&source, &mut strings).compute_hash(&strings));
+    assert_eq!(metrics.dfg_values_reused(), 0);
+}
+
+#[test]
+fn test_let_condition_definitions_survive_partial_rebuild() {
+    let mut strings = StringArena::new();
+    let metrics = MetricsCollector::new();
+    let render = |n: u8| format!(
+        "fn f(it: I, r: R) -> i32 {{\n    while let Some(q) = it.next() {{\n        let a = q + {};\n    }}\n    if let Ok(v) = r {{ v }} else {{ 0 }}\n}}\n",
+        n
+    );
+    let prev = full(&render(1), &mut strings);
+
+    let source = render(2);
+    let offset = source.find("let a").unwrap();
+    let rebuilt = partial(&source, &prev, offset, &mut strings, &metrics);
+
+    assert_eq!(rebuilt.compute_hash(&strings), full(&source, &mut strings).compute_hash(&strings));
+    assert!(metrics.dfg_values_reused() > 0);
+}
@@ -0,0 +1,66 @@
+//! C ABI smoke test (Phase 4 Step 4.6, `ffi` feature)
+//!
+//! Loads the built cdylib with libloading and drives a temp repository
+//! through the exported functions, as a C caller would.
+
+#![cfg(feature = "ffi")]
+
+use libloading::{Library, Symbol};
+use std::ffi::{c_char, CStr, CString};
+use std::path::PathBuf;
+use tempfile::TempDir;
+use vcr::api::ffi::{VCR_ERR_INVALID_QUERY, VCR_ERR_UNKNOWN_RESULT, VCR_OK};
+
+type LoadRepo = unsafe extern "C" fn(*const c_char, *mut u64) -> i32;
+type RunQuery = unsafe extern "C" fn(u64, *const c_char, *mut u64) -> i32;
+type FetchResult = unsafe extern "C" fn(u64, *mut *mut c_char) -> i32;
+type UpdateFiles = unsafe extern "C" fn(u64, *const u64, usize) -> i32;
+type FreeString = unsafe extern "C" fn(*mut c_char);
+
+/// The cdylib sits next to this test binary (deps/) and in the profile dir
+fn cdylib_path() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let name = libloading::library_filename("vcr");
+
+    [deps.join(&name), deps.parent().unwrap().join(&name)]
+        .into_iter()
+        .find(|path| path.exists())
+        .expect("cdylib not built; run with --features ffi")
+}
+
+#[test]
+fn test_query_through_c_abi() {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("lib.rs"), "fn a() { let x = 1; }\nfn b() {}\n").unwrap();
+
+    unsafe {
+        let lib = Library::new(cdylib_path()).unwrap();
+        let load_repo: Symbol<LoadRepo> = lib.get(b"vcr_load_repo").unwrap();
+        let run_query: Symbol<RunQuery> = lib.get(b"vcr_run_query").unwrap();
+        let fetch_result: Symbol<FetchResult> = lib.get(b"vcr_fetch_result").unwrap();
+        let update_files: Symbol<UpdateFiles> = lib.get(b"vcr_update_files").unwrap();
+        let free_string: Symbol<FreeString> = lib.get(b"vcr_free_string").unwrap();
+
+        let path = CString::new(repo.path().to_str().unwrap()).unwrap();
+        let mut handle = 0u64;
+        assert_eq!(load_repo(path.as_ptr(), &mut handle), VCR_OK);
+
+        let query = CString::new(r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+        let mut result_id = 0u64;
+        assert_eq!(run_query(handle, query.as_ptr(), &mut result_id), VCR_OK);
+
+        let mut json = std::ptr::null_mut();
+        assert_eq!(fetch_result(result_id, &mut json), VCR_OK);
+        let nodes: Vec<String> = serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+        free_string(json);
+        assert_eq!(nodes.len(), 2);
+
+        assert_eq!(update_files(handle, [0u64].as_ptr(), 1), VCR_OK);
+
+        // Typed errors come back as their codes
+        let bad = CString::new("not json").unwrap();
+        assert_eq!(run_query(handle, bad.as_ptr(), &mut result_id), VCR_ERR_INVALID_QUERY);
+        assert_eq!(fetch_result(u64::MAX, &mut json), VCR_ERR_UNKNOWN_RESULT);
+    }
+}
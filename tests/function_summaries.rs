@@ -0,0 +1,229 @@
+//! Function summaries and interprocedural taint
+//!
+//! - Summaries record parameter flows to returns, callee arguments and sinks
+//! - Recursive components reach a fixpoint
+//! - Incremental runs recompute only functions whose fingerprint changed
+//!   (and callers of functions whose summary changed)
+//! - Summary and inline taint report the same flows on acyclic fixtures
+//! - Summaries are saved with snapshots
+
+use vcr::analysis::{find_taint_flows, CallGraph, CallRef, FunctionSummaries, TaintFlow, TaintSpec};
+use vcr::config::{TaintMode, ValoriConfig};
+use vcr::pipeline::{Pipeline, PipelineOutput};
+use vcr::storage::SnapshotStore;
+use std::collections::BTreeSet;
+use tempfile::TempDir;
+
+const CHAIN: &str = "\
+fn main() { let x = read_line(); let y = wrap(x); run(y); }
+fn run(c: String) { exec(c); }
+fn wrap(v: String) -> String { v }
+";
+
+fn ingest(files: &[(&str, &str)]) -> (TempDir, PipelineOutput) {
+    let dir = TempDir::new().unwrap();
+    for (path, source) in files {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    (dir, output)
+}
+
+fn edit(dir: &TempDir, previous: &PipelineOutput, source: &str) -> PipelineOutput {
+    std::fs::write(dir.path().join("lib.rs"), source).unwrap();
+    Pipeline::default().run_incremental(previous).unwrap()
+}
+
+fn key_of(graph: &CallGraph, name: &str) -> (vcr::types::FileId, vcr::semantic::model::FunctionId) {
+    let function = graph.functions().find(|f| f.name == name).unwrap();
+    (function.file_id, function.function_id)
+}
+
+/// (source function, source callee, sink function, sink callee)
+fn named(graph: &CallGraph, flows: &[TaintFlow]) -> BTreeSet<(String, String, String, String)> {
+    let site = |at: CallRef| {
+        let function = graph.function(at.function()).unwrap().name.clone();
+        (function, graph.flows(at.function()).unwrap().calls[at.call].callee.clone())
+    };
+    flows.iter()
+        .map(|flow| {
+            let (source_fn, source) = site(flow.source);
+            let (sink_fn, sink) = site(flow.sink);
+            (source_fn, source, sink_fn, sink)
+        })
+        .collect()
+}
+
+/// Files of one fixture and the flows it must report
+type Fixture<'a> = (&'a [(&'a str, &'a str)], &'a [(&'a str, &'a str, &'a str, &'a str)]);
+
+fn flow(source_fn: &str, source: &str, sink_fn: &str, sink: &str) -> (String, String, String, String) {
+    (source_fn.to_string(), source.to_string(), sink_fn.to_string(), sink.to_string())
+}
+
+#[test]
+fn test_summaries_record_parameter_flows() {
+    let (_dir, output) = ingest(&[("lib.rs", CHAIN)]);
+    let graph = &output.call_graph;
+    let summary = |name| output.summaries.get(key_of(graph, name)).unwrap();
+
+    assert_eq!(output.summaries.len(), 3);
+    assert_eq!(summary("wrap").params_to_return, [0].into());
+    assert!(summary("run").params_to_return.is_empty());
+
+    let exec = CallRef::new(key_of(graph, "run"), 0);
+    assert_eq!(summary("run").params_to_sinks.get(&0), Some(&[exec].into()));
+    // `main` has no parameters; its source reaches no return
+    assert!(summary("main").params_to_sinks.is_empty());
+    assert!(summary("main").return_sources.is_empty());
+}
+
+#[test]
+fn test_return_sources_and_callee_arguments() {
+    let (_dir, output) = ingest(&[("lib.rs", "\
+fn input() -> String { let s = read_line(); s }
+fn forward(a: String, b: String) { sink_here(b, a); }
+fn sink_here(x: String, y: String) { exec(y); }
+")]);
+    let graph = &output.call_graph;
+    let summary = |name| output.summaries.get(key_of(graph, name)).unwrap();
+
+    assert_eq!(summary("input").return_sources, [CallRef::new(key_of(graph, "input"), 0)].into());
+
+    let forward = summary("forward");
+    let (sink_here, exec) = (key_of(graph, "sink_here"), CallRef::new(key_of(graph, "sink_here"), 0));
+    let args = |param| forward.params_to_callees[&param].iter().map(|a| ((a.file_id, a.function_id), a.index)).collect::<Vec<_>>();
+    assert_eq!(args(0), vec![(sink_here, 1)]);
+    assert_eq!(args(1), vec![(sink_here, 0)]);
+    // Only `a` lands in the argument `sink_here` passes to `exec`
+    assert_eq!(forward.params_to_sinks.keys().copied().collect::<Vec<_>>(), vec![0]);
+    assert_eq!(forward.params_to_sinks[&0], [exec].into());
+}
+
+#[test]
+fn test_recursion_reaches_fixpoint() {
+    let (_dir, output) = ingest(&[("lib.rs", "\
+fn main() { let s = read_line(); down(3, String::new(), s); }
+fn down(n: u32, a: String, b: String) { if n > 0 { down(n - 1, b, a) } else { exec(a) } }
+")]);
+    let graph = &output.call_graph;
+    let down = output.summaries.get(key_of(graph, "down")).unwrap();
+
+    // `b` reaches `exec` only after one more round of the recursion
+    assert_eq!(down.params_to_sinks.keys().copied().collect::<Vec<_>>(), vec![1, 2]);
+
+    let summarized = find_taint_flows(graph, &output.summaries, TaintMode::Summary);
+    assert_eq!(named(graph, &summarized), [flow("main", "read_line", "down", "exec")].into());
+    // Inline mode does not enter `down` again from inside `down`
+    assert!(find_taint_flows(graph, &output.summaries, TaintMode::Inline).is_empty());
+}
+
+#[test]
+fn test_summary_and_inline_modes_agree() {
+    let fixtures: &[Fixture] = &[
+        // Direct
+        (&[("lib.rs", "fn main() { let x = read_line(); exec(x); }\n")], &[("main", "read_line", "main", "exec")]),
+        // Through a returning helper and a sinking callee
+        (&[("lib.rs", CHAIN)], &[("main", "read_line", "run", "exec")]),
+        // Source returned from a callee, sink three calls down, across files
+        (
+            &[
+                ("src/main.rs", "fn main() { let c = input(); a(c); }\nfn input() -> String { read_line() }\n"),
+                ("src/deep.rs", "fn a(x: String) { b(x) }\nfn b(y: String) { let z = format(y); exec(z); }\n"),
+            ],
+            &[("input", "read_line", "b", "exec")],
+        ),
+        // `&mut` out-parameter and a method sink
+        (
+            &[("lib.rs", "\
+fn main() { let mut buf = String::new(); stdin().read_line(&mut buf); let db = Db::new(); db.lookup(buf); }
+impl Db { fn lookup(&self, q: String) { self.conn.query(q); } }
+")],
+            &[("main", "read_line", "lookup", "query")],
+        ),
+        // Callee drops its argument: no flow
+        (&[("lib.rs", "fn main() { exec(clean(read_line())); }\nfn clean(s: String) -> String { String::new() }\n")], &[]),
+        // Two sources, one sink each way round
+        (
+            &[("lib.rs", "\
+fn main() { let a = var(\"A\"); let b = read_line(); pick(a, b); }
+fn pick(x: String, y: String) { system(x); let t = twice(y); exec(t); }
+fn twice(s: String) -> String { let u = s.clone(); u }
+")],
+            &[("main", "var", "pick", "system"), ("main", "read_line", "pick", "exec")],
+        ),
+    ];
+
+    for (files, expected) in fixtures {
+        let (_dir, output) = ingest(files);
+        let graph = &output.call_graph;
+        let summarized = find_taint_flows(graph, &output.summaries, TaintMode::Summary);
+        let inlined = find_taint_flows(graph, &output.summaries, TaintMode::Inline);
+
+        assert_eq!(summarized, inlined, "{:?}", files);
+        let expected: BTreeSet<_> = expected.iter().map(|(a, b, c, d)| flow(a, b, c, d)).collect();
+        assert_eq!(named(graph, &summarized), expected, "{:?}", files);
+    }
+}
+
+#[test]
+fn test_incremental_recomputes_changed_functions() {
+    let (dir, before) = ingest(&[("lib.rs", CHAIN)]);
+    let graph = &before.call_graph;
+    let (main, run, wrap) = (key_of(graph, "main"), key_of(graph, "run"), key_of(graph, "wrap"));
+    assert_eq!(before.summaries.recomputed(), &[main, run, wrap]);
+
+    // Nothing changed
+    let again = FunctionSummaries::update(Some(&before.summaries), graph, &before.semantic, before.summaries.spec());
+    assert!(again.recomputed().is_empty());
+    assert_eq!(again.summaries(), before.summaries.summaries());
+
+    // `wrap` changes but summarizes the same: its callers are kept
+    let same = edit(&dir, &before, &CHAIN.replace("{ v }", "{ let w = v; w }"));
+    assert_eq!(same.summaries.recomputed(), &[wrap]);
+    assert_eq!(same.summaries.get(wrap).unwrap().params_to_return, [0].into());
+    assert_ne!(same.summaries.get(wrap).unwrap().fingerprint, before.summaries.get(wrap).unwrap().fingerprint);
+
+    // `wrap` no longer returns its parameter: `main` is summarized again
+    let changed = edit(&dir, &same, &CHAIN.replace("{ v }", "{ String::new() }"));
+    assert_eq!(changed.summaries.recomputed(), &[main, wrap]);
+    assert!(changed.summaries.get(wrap).unwrap().params_to_return.is_empty());
+    assert!(find_taint_flows(&changed.call_graph, &changed.summaries, TaintMode::Summary).is_empty());
+
+    // Matches a from-scratch build
+    let fresh = FunctionSummaries::compute(&changed.call_graph, &changed.semantic, changed.summaries.spec());
+    assert_eq!(fresh.summaries(), changed.summaries.summaries());
+}
+
+#[test]
+fn test_spec_change_recomputes_everything() {
+    let (_dir, output) = ingest(&[("lib.rs", CHAIN)]);
+    let spec = TaintSpec { sources: ["read_line".to_string()].into(), sinks: ["wrap".to_string()].into() };
+    let updated = FunctionSummaries::update(Some(&output.summaries), &output.call_graph, &output.semantic, &spec);
+
+    assert_eq!(updated.recomputed().len(), 3);
+    let flows = find_taint_flows(&output.call_graph, &updated, TaintMode::Summary);
+    assert_eq!(named(&output.call_graph, &flows), [flow("main", "read_line", "main", "wrap")].into());
+}
+
+#[test]
+fn test_summaries_are_deterministic_and_saved() {
+    let (dir, output) = ingest(&[("lib.rs", CHAIN)]);
+    let rebuilt = Pipeline::default().run(dir.path()).unwrap();
+    assert_eq!(
+        serde_json::to_string(&output.summaries).unwrap(),
+        serde_json::to_string(&rebuilt.summaries).unwrap()
+    );
+
+    let mut config = ValoriConfig::default();
+    config.snapshot.path = dir.path().join("snapshots");
+    let mut store = SnapshotStore::open(&config.snapshot.path).unwrap();
+    let id = store.save_with_semantics(&output.cpg_epoch, &output.snapshot, &output.semantic, &output.summaries, Vec::new()).unwrap();
+
+    let reopened = SnapshotStore::open(&config.snapshot.path).unwrap();
+    let saved = reopened.get(id).unwrap().metadata.summaries.clone().unwrap();
+    assert_eq!(saved.summaries(), output.summaries.summaries());
+    assert_eq!(saved.spec(), output.summaries.spec());
+}
@@ -0,0 +1,149 @@
+//! `util::glob` against a reference matcher on random patterns and paths
+//!
+//! Patterns are generated as component lists and rendered to glob text; the
+//! reference matches the component list directly by backtracking, which is
+//! exponential in the worst case but obviously follows the documented
+//! semantics. Paths are normalized (non-empty components).
+
+use proptest::prelude::*;
+use vcr::util::Glob;
+
+const ALPHABET: &[char] = &['a', 'b', 'A', '.', '-'];
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(char),
+    Any,
+    Star,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+#[derive(Debug, Clone)]
+enum Component {
+    Tokens(Vec<Token>),
+    AnyComponents,
+}
+
+fn token() -> impl Strategy<Value = Token> {
+    let range = prop::sample::select(&['a', 'b', 'c', 'A', 'B'][..])
+        .prop_flat_map(|lo| (Just(lo), prop::sample::select(&['a', 'b', 'c', 'A', 'B'][..])))
+        .prop_map(|(lo, hi)| if lo <= hi { (lo, hi) } else { (hi, lo) });
+    prop_oneof![
+        4 => prop::sample::select(ALPHABET).prop_map(Token::Literal),
+        1 => Just(Token::Any),
+        2 => Just(Token::Star),
+        1 => (any::<bool>(), prop::collection::vec(range, 1..=2))
+            .prop_map(|(negated, ranges)| Token::Class { negated, ranges }),
+    ]
+}
+
+fn component() -> impl Strategy<Value = Component> {
+    prop_oneof![
+        4 => prop::collection::vec(token(), 1..=4).prop_map(|mut tokens| {
+            // `**` inside a component is invalid
+            tokens.dedup_by(|a, b| matches!((a, b), (Token::Star, Token::Star)));
+            Component::Tokens(tokens)
+        }),
+        1 => Just(Component::AnyComponents),
+    ]
+}
+
+fn path() -> impl Strategy<Value = String> {
+    let component = prop::collection::vec(prop::sample::select(ALPHABET), 1..=3)
+        .prop_map(|chars| chars.into_iter().collect::<String>());
+    prop::collection::vec(component, 1..=4).prop_map(|components| components.join("/"))
+}
+
+fn render(pattern: &[Component]) -> String {
+    let components: Vec<String> = pattern.iter()
+        .map(|component| match component {
+            Component::AnyComponents => "**".to_string(),
+            Component::Tokens(tokens) => tokens.iter()
+                .map(|token| match token {
+                    Token::Literal(c) => c.to_string(),
+                    Token::Any => "?".to_string(),
+                    Token::Star => "*".to_string(),
+                    Token::Class { negated, ranges } => {
+                        let items: String = ranges.iter().map(|(lo, hi)| format!("{}-{}", lo, hi)).collect();
+                        format!("[{}{}]", if *negated { "!" } else { "" }, items)
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+    components.join("/")
+}
+
+fn same(a: char, b: char, case_sensitive: bool) -> bool {
+    match case_sensitive {
+        true => a == b,
+        false => a.to_lowercase().eq(b.to_lowercase()),
+    }
+}
+
+fn accepts(token: &Token, c: char, case_sensitive: bool) -> bool {
+    match token {
+        Token::Literal(expected) => same(*expected, c, case_sensitive),
+        Token::Any | Token::Star => true,
+        Token::Class { negated, ranges } => {
+            let listed = ranges.iter()
+                .any(|&(lo, hi)| (lo..=hi).any(|member| same(member, c, case_sensitive)));
+            listed != *negated
+        }
+    }
+}
+
+fn reference_component(tokens: &[Token], text: &[char], case_sensitive: bool) -> bool {
+    match tokens.split_first() {
+        None => text.is_empty(),
+        Some((Token::Star, rest)) => (0..=text.len()).any(|skip| reference_component(rest, &text[skip..], case_sensitive)),
+        Some((token, rest)) => text.split_first().is_some_and(|(c, tail)| {
+            accepts(token, *c, case_sensitive) && reference_component(rest, tail, case_sensitive)
+        }),
+    }
+}
+
+fn reference(pattern: &[Component], path: &[&str], case_sensitive: bool) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        // Trailing (or lone) `**`: one or more components
+        Some((Component::AnyComponents, [])) => !path.is_empty(),
+        Some((Component::AnyComponents, rest)) => (0..=path.len()).any(|skip| reference(rest, &path[skip..], case_sensitive)),
+        Some((Component::Tokens(tokens), rest)) => path.split_first().is_some_and(|(first, tail)| {
+            let text: Vec<char> = first.chars().collect();
+            reference_component(tokens, &text, case_sensitive) && reference(rest, tail, case_sensitive)
+        }),
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn prop_glob_matches_reference(
+        pattern in prop::collection::vec(component(), 1..=4),
+        paths in prop::collection::vec(path(), 1..=8),
+        case_sensitive in any::<bool>(),
+    ) {
+        let text = render(&pattern);
+        let glob = Glob::parse(&text).unwrap().with_case_sensitive(case_sensitive);
+        for path in &paths {
+            let components: Vec<&str> = path.split('/').collect();
+            prop_assert_eq!(
+                glob.matches(path),
+                reference(&pattern, &components, case_sensitive),
+                "pattern {:?} path {:?} case_sensitive {}", text, path, case_sensitive
+            );
+        }
+    }
+
+    #[test]
+    fn prop_escaped_text_matches_only_itself(
+        text in "[a-b*?\\[\\]\\\\.]{1,8}",
+        other in "[a-b*?\\[\\]\\\\.]{1,8}",
+    ) {
+        let glob = Glob::parse(&vcr::util::glob::escape(&text)).unwrap();
+        prop_assert!(glob.matches(&text));
+        prop_assert_eq!(glob.matches(&other), other == text);
+    }
+}
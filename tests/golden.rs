@@ -0,0 +1,36 @@
+//! Golden hash regression tests (Path B7)
+//!
+//! Rebuilds every fixture under `tests/golden/` and compares each stage
+//! hash with `tests/golden/manifest.toml`.
+//!
+//! After an intentional change, regenerate the manifest and review the
+//! printed diff:
+//!
+//! ```text
+//! VCR_BLESS=1 cargo test --test golden
+//! cargo run --bin vcr -- golden bless
+//! ```
+
+use std::path::Path;
+use vcr::verify::golden;
+
+#[test]
+fn test_golden_hashes() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+
+    if std::env::var_os("VCR_BLESS").is_some() {
+        for change in golden::bless(&dir).unwrap() {
+            println!("blessed {}", change);
+        }
+        return;
+    }
+
+    let mismatches = golden::check(&dir).unwrap();
+    let report: Vec<String> = mismatches.iter().map(ToString::to_string).collect();
+    assert!(
+        mismatches.is_empty(),
+        "{} golden artifact(s) diverged (fixture: artifact: pinned -> rebuilt):\n{}\nRun `VCR_BLESS=1 cargo test --test golden` if the change is intended.",
+        mismatches.len(),
+        report.join("\n"),
+    );
+}
@@ -0,0 +1,20 @@
+pub fn classify(n: i32) -> i32 {
+    if n < 0 {
+        return -1;
+    }
+    let mut label = 0;
+    if n > 100 {
+        label = 2;
+    } else if n > 10 {
+        label = 1;
+    }
+    label
+}
+
+pub fn sign(x: i32) -> i32 {
+    match x {
+        0 => 0,
+        _ if x > 0 => 1,
+        _ => -1,
+    }
+}
@@ -0,0 +1,11 @@
+mod util;
+
+fn main() {
+    let total = util::sum(3);
+    report(total);
+}
+
+fn report(value: i32) {
+    let doubled = util::double(value);
+    println!("{}", doubled);
+}
@@ -0,0 +1,11 @@
+pub fn sum(n: i32) -> i32 {
+    let mut acc = 0;
+    for i in 0..n {
+        acc = acc + i;
+    }
+    acc
+}
+
+pub fn double(v: i32) -> i32 {
+    v * 2
+}
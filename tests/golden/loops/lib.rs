@@ -0,0 +1,25 @@
+pub fn collatz(mut n: u64) -> u32 {
+    let mut steps = 0;
+    while n != 1 {
+        if n % 2 == 0 {
+            n = n / 2;
+        } else {
+            n = 3 * n + 1;
+        }
+        steps = steps + 1;
+    }
+    steps
+}
+
+pub fn first_even(values: &[u64]) -> Option<u64> {
+    let mut i = 0;
+    loop {
+        if i >= values.len() {
+            break None;
+        }
+        if values[i] % 2 == 0 {
+            break Some(values[i]);
+        }
+        i = i + 1;
+    }
+}
@@ -0,0 +1,65 @@
+//! Incremental precision: one edited function, one rebuild
+//!
+//! Acceptance gate for incremental updates. A function-body edit in one file
+//! of a four-file repository re-analyzes exactly that file and rebuilds
+//! exactly its one CFG, and the refreshed CPG equals a cold build's.
+
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use vcr::metrics::MetricsCollector;
+use vcr::pipeline::Pipeline;
+
+fn create_test_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("src/core")).unwrap();
+    fs::create_dir_all(dir.path().join("src/utils")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn main() { println!(\"Hello\"); }").unwrap();
+    fs::write(dir.path().join("src/core/mod.rs"), "pub mod engine;").unwrap();
+    fs::write(dir.path().join("src/core/engine.rs"), "pub fn run() { println!(\"Engine\"); }").unwrap();
+    fs::write(dir.path().join("src/utils/mod.rs"), "pub fn helper() -> i32 { 42 }").unwrap();
+    dir
+}
+
+#[test]
+fn test_single_function_edit_rebuilds_one_cfg() {
+    let dir = create_test_repo();
+    let pipeline = Pipeline::default();
+    let first = pipeline.run(dir.path()).unwrap();
+
+    fs::write(dir.path().join("src/utils/mod.rs"), "pub fn helper() -> i32 { let x = 41; x + 1 }").unwrap();
+    let metrics = MetricsCollector::new();
+    let refreshed = pipeline.run_incremental_with_metrics(&first, &metrics).unwrap();
+    let cold = pipeline.run(dir.path()).unwrap();
+
+    let helper = refreshed.snapshot.files.iter()
+        .find(|(_, meta)| meta.path == Path::new("src/utils/mod.rs"))
+        .map(|(id, _)| *id)
+        .unwrap();
+    assert_eq!(refreshed.rebuilt, vec![helper]);
+    assert_eq!(metrics.reparse_count(), 1);
+    assert_eq!(metrics.reuse_count(), 3);
+    assert_eq!(metrics.files_semantically_rebuilt(), 1);
+    assert_eq!(metrics.cfg_rebuilds(), 1);
+    assert_eq!(metrics.dfg_rebuilds(), 1);
+    assert_eq!(metrics.cpg_nodes_reused(), 0);
+    assert_eq!(metrics.to_json()["incremental"]["cfg_rebuilds"], 1);
+
+    assert_eq!(refreshed.cpg_epoch.cpg_hash(), cold.cpg_epoch.cpg_hash());
+    assert_eq!(refreshed.cpg_epoch.cpg().compute_hash(), cold.cpg_epoch.cpg().compute_hash());
+}
+
+#[test]
+fn test_refresh_without_edits_rebuilds_nothing() {
+    let dir = create_test_repo();
+    let pipeline = Pipeline::default();
+    let first = pipeline.run(dir.path()).unwrap();
+
+    let metrics = MetricsCollector::new();
+    let refreshed = pipeline.run_incremental_with_metrics(&first, &metrics).unwrap();
+
+    assert!(refreshed.rebuilt.is_empty());
+    assert_eq!((metrics.files_semantically_rebuilt(), metrics.cfg_rebuilds(), metrics.dfg_rebuilds()), (0, 0, 0));
+    assert_eq!(metrics.cpg_nodes_reused(), first.cpg_epoch.cpg().nodes.len());
+    assert_eq!(refreshed.cpg_epoch.cpg_hash(), first.cpg_epoch.cpg_hash());
+}
@@ -0,0 +1,98 @@
+//! Incremental == from-scratch under random edit sequences
+//!
+//! Each case generates a small file, applies a random sequence of byte
+//! edits (insertions, deletions, replacements) and, after every edit,
+//! compares the incremental build's stage hashes with a fresh build's. A
+//! failing case shrinks to a minimal edit sequence; proptest prints it and
+//! records its seed in `incremental_prop.proptest-regressions`, which
+//! replays it first on later runs.
+//!
+//! Work per case is capped (4 functions, 8 edits); `PROPTEST_CASES`
+//! raises the case count for longer local runs.
+
+use proptest::prelude::*;
+use vcr::config::ParseErrorPolicy;
+use vcr::pipeline::Pipeline;
+use vcr::testing::{generate_source, ByteEdit, IncrementalSession, EDIT_FRAGMENTS};
+
+fn edit() -> impl Strategy<Value = ByteEdit> {
+    let insert = prop_oneof![Just(String::new()), prop::sample::select(EDIT_FRAGMENTS).prop_map(str::to_string)];
+    (0usize..1024, 0usize..24, insert).prop_map(|(offset, delete, insert)| ByteEdit { offset, delete, insert })
+}
+
+fn check(pipeline: Pipeline, functions: usize, seed: u64, edits: &[ByteEdit]) -> Result<(), TestCaseError> {
+    let mut session = IncrementalSession::new(pipeline, generate_source(functions, seed))
+        .map_err(|e| TestCaseError::fail(format!("seed {}: initial build failed: {:#}", seed, e)))?;
+    for edit in edits {
+        let mismatch = session.apply(edit)
+            .map_err(|e| TestCaseError::fail(format!("seed {}: build failed after {:?}: {:#}", seed, edit, e)))?;
+        if let Some(mismatch) = mismatch {
+            return Err(TestCaseError::fail(format!(
+                "seed {}: incremental build diverged after edit {} ({:?})\n{:?}\n--- source ---\n{}",
+                seed, mismatch.step, edit, mismatch.divergences, mismatch.source
+            )));
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn prop_best_effort_edits_match_fresh_build(
+        functions in 1usize..=4,
+        seed in any::<u64>(),
+        edits in prop::collection::vec(edit(), 1..=8),
+    ) {
+        let pipeline = Pipeline::default().with_parse_error_policy(ParseErrorPolicy::IncludeBestEffort);
+        check(pipeline, functions, seed, &edits)?;
+    }
+
+    #[test]
+    fn prop_skipping_edits_match_fresh_build(
+        functions in 1usize..=4,
+        seed in any::<u64>(),
+        edits in prop::collection::vec(edit(), 1..=8),
+    ) {
+        let pipeline = Pipeline::default().with_parse_error_policy(ParseErrorPolicy::Skip);
+        check(pipeline, functions, seed, &edits)?;
+    }
+}
+
+#[test]
+fn test_edits_apply_at_any_offset() {
+    let mut source = "fn a() {}".to_string();
+    ByteEdit { offset: 3, delete: 1, insert: "b".into() }.apply(&mut source);
+    assert_eq!(source, "fn b() {}");
+    ByteEdit { offset: 100, delete: 5, insert: "\n".into() }.apply(&mut source);
+    assert_eq!(source, "fn b() {}\n");
+    ByteEdit { offset: 0, delete: 100, insert: String::new() }.apply(&mut source);
+    assert_eq!(source, "");
+
+    let mut wide = "é".to_string();
+    ByteEdit { offset: 1, delete: 0, insert: "x".into() }.apply(&mut wide);
+    assert_eq!(wide, "xé");
+}
+
+#[test]
+fn test_session_tracks_edits() {
+    let mut session = IncrementalSession::best_effort(generate_source(2, 7)).unwrap();
+    let before = session.source().to_string();
+    let edit = ByteEdit { offset: 0, delete: 0, insert: "fn extra(a: i32) -> i32 { a }\n".into() };
+    assert!(session.apply(&edit).unwrap().is_none());
+    assert_eq!(session.source(), format!("fn extra(a: i32) -> i32 {{ a }}\n{}", before));
+    assert_eq!(session.output().rebuilt.len(), 1);
+}
+
+#[test]
+fn test_session_reports_divergence() {
+    // Rebuilt files get no semantics, unlike a fresh build
+    let pipeline = Pipeline::default().with_incremental_analyzer(|_, _, _, _| Ok(()));
+    let mut session = IncrementalSession::new(pipeline, generate_source(2, 7)).unwrap();
+    let mismatch = session.apply(&ByteEdit { offset: 0, delete: 0, insert: "fn extra(a: i32) -> i32 { a }\n".into() })
+        .unwrap()
+        .unwrap();
+    assert_eq!(mismatch.step, 1);
+    assert!(mismatch.divergences.iter().any(|d| d.stage == "cfg"), "{:?}", mismatch.divergences);
+}
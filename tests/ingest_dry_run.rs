@@ -0,0 +1,114 @@
+//! `vcr ingest --dry-run` (`Pipeline::plan`, `cli::ingest_plan`)
+//!
+//! - ignored, foreign, oversized and skip_semantics files are listed with
+//!   their reasons, in path order
+//! - nothing is parsed, analyzed or fused
+//! - changes are reported against the latest stored snapshot
+
+use std::fs;
+use tempfile::TempDir;
+use vcr::cli;
+use vcr::cli::output::SnapshotResult;
+use vcr::config::ValoriConfig;
+use vcr::metrics::MetricsCollector;
+use vcr::pipeline::{ParseLoad, Pipeline, PlannedRename, SkipReason};
+
+fn fixture() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for sub in ["src", "target/debug", "generated", "vendor"] {
+        fs::create_dir_all(dir.path().join(sub)).unwrap();
+    }
+    fs::write(dir.path().join(".vcrignore"), "/target/\n").unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "fn a() { let x = 1; }\n").unwrap();
+    fs::write(dir.path().join("src/util.rs"), "fn b() {}\n").unwrap();
+    fs::write(dir.path().join("README.md"), "# readme\n").unwrap();
+    fs::write(dir.path().join("target/debug/build.rs"), "fn built() {}\n").unwrap();
+    fs::write(dir.path().join("generated/.vcr.toml"), "max_file_size = 16\n").unwrap();
+    fs::write(dir.path().join("generated/huge.rs"), "fn huge() { let y = [0u8; 1024]; }\n").unwrap();
+    fs::write(dir.path().join("generated/tiny.rs"), "fn t() {}\n").unwrap();
+    fs::write(dir.path().join("vendor/.vcr.toml"), "skip_semantics = true\n").unwrap();
+    fs::write(dir.path().join("vendor/dep.rs"), "fn dep() {}\n").unwrap();
+    dir
+}
+
+/// Config whose snapshot store lives in `store`
+fn config(store: &TempDir) -> ValoriConfig {
+    let mut config = ValoriConfig::default();
+    config.snapshot.path = store.path().join("snapshots");
+    config
+}
+
+#[test]
+fn test_plan_lists_skip_reasons() {
+    let dir = fixture();
+    let plan = Pipeline::default().plan(dir.path()).unwrap();
+
+    let parsed: Vec<_> = plan.parsed.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(parsed, ["generated/tiny.rs", "src/lib.rs", "src/util.rs"]);
+    let skipped: Vec<_> = plan.skipped.iter().map(|file| (file.path.as_str(), file.reason)).collect();
+    assert_eq!(skipped, [
+        (".vcrignore", SkipReason::Extension),
+        ("README.md", SkipReason::Extension),
+        ("generated/.vcr.toml", SkipReason::Extension),
+        ("generated/huge.rs", SkipReason::TooLarge { size: 35, max_file_size: 16 }),
+        ("target", SkipReason::Ignored),
+        ("vendor/.vcr.toml", SkipReason::Extension),
+        ("vendor/dep.rs", SkipReason::SkipSemantics),
+    ]);
+
+    assert_eq!(plan.scanned, 5);
+    assert_eq!(plan.scanned_bytes, 10 + 35 + 22 + 10 + 12);
+    assert_eq!(plan.languages["rust"], ParseLoad { files: 3, bytes: 10 + 22 + 10 });
+    assert_eq!(plan.snapshot_hash, Pipeline::default().scan(&[dir.path().to_path_buf()]).unwrap().snapshot_hash);
+    assert_eq!(plan, Pipeline::default().plan(dir.path()).unwrap());
+    assert!(plan.changes.is_none());
+}
+
+#[test]
+fn test_dry_run_builds_nothing() {
+    let dir = fixture();
+    let store = TempDir::new().unwrap();
+    let mut metrics = MetricsCollector::new();
+    let output = cli::ingest_plan(&[dir.path().to_path_buf()], &config(&store), &mut metrics).unwrap();
+
+    assert_eq!(output.plan.parsed.len(), 3);
+    assert_eq!(metrics.parse_time_stats().count, 0);
+    assert_eq!(metrics.reparse_count(), 0);
+    assert!(metrics.cpg_stats().is_none());
+    assert!(metrics.semantic_report().is_none());
+    assert!(metrics.scan_duration().is_some());
+
+    // No store was created, so no changes either
+    assert!(!store.path().join("snapshots").exists());
+    assert_eq!(output.since_snapshot, None);
+
+    let json = serde_json::to_value(&output).unwrap();
+    assert_eq!(json["skipped"][3], serde_json::json!({
+        "path": "generated/huge.rs", "reason": "too_large", "size": 35, "max_file_size": 16,
+    }));
+    assert_eq!(json["skipped"][4], serde_json::json!({"path": "target", "reason": "ignored"}));
+}
+
+#[test]
+fn test_dry_run_reports_changes_since_latest_snapshot() {
+    let dir = fixture();
+    let store = TempDir::new().unwrap();
+    let config = config(&store);
+    let SnapshotResult::Saved { snapshot_id, .. } = cli::snapshot_save(&config, Some(dir.path())).unwrap().result else {
+        panic!("not saved");
+    };
+
+    fs::write(dir.path().join("src/lib.rs"), "fn a() { let x = 2; }\n").unwrap();
+    fs::rename(dir.path().join("src/util.rs"), dir.path().join("src/helpers.rs")).unwrap();
+    fs::write(dir.path().join("src/new.rs"), "fn n() {}\n").unwrap();
+    fs::remove_file(dir.path().join("generated/tiny.rs")).unwrap();
+
+    let output = cli::ingest_plan(&[dir.path().to_path_buf()], &config, &mut MetricsCollector::new()).unwrap();
+    assert_eq!(output.since_snapshot, Some(snapshot_id));
+    let changes = output.plan.changes.unwrap();
+    assert_eq!(changes.added, ["src/new.rs"]);
+    assert_eq!(changes.modified, ["src/lib.rs"]);
+    assert_eq!(changes.deleted, ["generated/tiny.rs"]);
+    assert_eq!(changes.renamed, [PlannedRename { from: "src/util.rs".into(), to: "src/helpers.rs".into() }]);
+    assert_eq!(changes.unchanged, 2);
+}
@@ -0,0 +1,70 @@
+//! `vcr ingest --stdin` tests
+//!
+//! Pipes a fixture into the real binary and checks the JSON document and
+//! exit code.
+
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const FIXTURE: &str = "fn main() {\n    let x = helper(1);\n    if x > 0 {\n        log(x);\n    }\n}\n\nfn helper(n: i32) -> i32 {\n    let unused = 0;\n    let mut y = n;\n    while y < 10 { y = y + 1; }\n    y\n}\n";
+
+/// Run `vcr ingest --stdin --language rust <args>` on `source`
+fn ingest(source: &str, args: &[&str]) -> (Value, Option<i32>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vcr"))
+        .args(["ingest", "--stdin", "--language", "rust"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(source.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (serde_json::from_slice(&output.stdout).unwrap(), output.status.code())
+}
+
+#[test]
+fn test_stdin_buffer_report() {
+    let (out, code) = ingest(FIXTURE, &[]);
+    assert_eq!(code, Some(0));
+    assert_eq!(out["status"], "success");
+    assert_eq!(out["language"], "rust");
+    assert_eq!(out["size"], FIXTURE.len());
+    assert_eq!(out["parse"]["clean"], true);
+    assert_eq!(out["analyzed"], true);
+    assert!(out.get("lint").is_none());
+
+    let functions = out["functions"].as_array().unwrap();
+    let names: Vec<_> = functions.iter().map(|f| f["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["main", "helper"]);
+    assert_eq!(functions[0]["cyclomatic_complexity"], 2);
+    assert_eq!(functions[1]["max_loop_nesting"], 1);
+    for function in functions {
+        assert_eq!(function["cfg_hash"].as_str().unwrap().len(), 64);
+        assert_eq!(function["dfg_hash"].as_str().unwrap().len(), 64);
+    }
+
+    // Same buffer, same document
+    assert_eq!(ingest(FIXTURE, &[]).0, out);
+}
+
+#[test]
+fn test_stdin_lint_and_deny() {
+    let (out, code) = ingest(FIXTURE, &["--lint"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(out["lint"]["unused"][0]["name"], "unused");
+    assert_eq!(out["lint"]["unused"][0]["file"], "<stdin>");
+
+    let (out, code) = ingest(FIXTURE, &["--deny", "unused", "--deny", "shadowing"]);
+    assert_eq!(code, Some(1));
+    assert_eq!(out["denied"], serde_json::json!(["unused"]));
+}
+
+#[test]
+fn test_stdin_syntax_error_exits_2() {
+    let (out, code) = ingest("fn broken( {\n", &["--deny", "unused"]);
+    assert_eq!(code, Some(2));
+    assert_eq!(out["parse"]["clean"], false);
+    assert_eq!(out["parse"]["error_count"], 1);
+    assert_eq!(out["analyzed"], false);
+}
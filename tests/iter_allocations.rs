@@ -0,0 +1,71 @@
+//! Zero-allocation CPG iterators
+//!
+//! A counting global allocator checks that `iter_nodes_of_kind`,
+//! `iter_edges_of_kind` and `iter_edges_from` never allocate, where the
+//! collecting `get_*` accessors allocate per call, and that both agree.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs;
+use tempfile::TempDir;
+use vcr::cpg::{CPGEdgeKind, CPGNodeKind};
+use vcr::pipeline::Pipeline;
+
+/// Counts allocations made by the current thread
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Allocations made by `f` on this thread
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    std::hint::black_box(f());
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_iterators_do_not_allocate() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("lib.rs"), "fn a(x: i32) -> i32 { let y = x + 1; if y > 2 { y } else { x } }\nfn b() { let z = a(3); }\n").unwrap();
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    let (cpg, indices) = (output.cpg_epoch.cpg(), output.cpg_epoch.indices());
+    assert!(!cpg.edges.is_empty());
+
+    let walked = allocations(|| {
+        let mut seen = 0;
+        for node in &cpg.nodes {
+            seen += cpg.iter_edges_from(indices, node.id).count();
+            seen += cpg.iter_edges_from(indices, node.id).of_kind(CPGEdgeKind::DataFlow).count();
+        }
+        seen + cpg.iter_nodes_of_kind(CPGNodeKind::CfgNode).count() + cpg.iter_edges_of_kind(CPGEdgeKind::ControlFlow).count()
+    });
+    assert_eq!(walked, 0);
+
+    let collected = allocations(|| {
+        cpg.nodes.iter().map(|node| cpg.get_edges_from(node.id).len()).sum::<usize>()
+    });
+    assert!(collected > 0);
+
+    // Same edges, same order
+    for node in &cpg.nodes {
+        let indexed: Vec<_> = cpg.iter_edges_from(indices, node.id).map(|edge| edge.id).collect();
+        let scanned: Vec<_> = cpg.get_edges_from(node.id).iter().map(|edge| edge.id).collect();
+        assert_eq!(indexed, scanned);
+    }
+}
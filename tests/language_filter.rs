@@ -0,0 +1,79 @@
+//! Language-labelled File nodes and the `language` query stage
+//!
+//! A mixed repository: Rust files are fused with File nodes labelled
+//! `rust`; files of languages the pipeline does not analyze never reach the
+//! CPG, so no language query selects them. The label survives incremental
+//! runs and a snapshot round-trip.
+
+use std::fs;
+use tempfile::TempDir;
+use vcr::api::{RepoHandle, ValoriAPI};
+use vcr::cpg::{CPGEpoch, CPGNodeKind};
+use vcr::pipeline::Pipeline;
+use vcr::storage::CPGSnapshot;
+
+fn mixed_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "fn a() {}\nfn b() { let x = 1; }\n").unwrap();
+    fs::write(dir.path().join("src/util.rs"), "fn c() {}\n").unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(dir.path().join("tool.py"), "def helper():\n    return 1\n").unwrap();
+    fs::write(dir.path().join("app.js"), "function run() {}\n").unwrap();
+    dir
+}
+
+fn count(api: &mut ValoriAPI, handle: RepoHandle, pipeline: &str) -> usize {
+    let query = format!(r#"{{"pipeline": {}}}"#, pipeline);
+    let result_id = api.run_query(handle, &query).unwrap();
+    api.fetch_result(result_id).unwrap().len()
+}
+
+#[test]
+fn test_counts_per_language() {
+    let dir = mixed_repo();
+    let mut api = ValoriAPI::default();
+    let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    assert_eq!(count(&mut api, handle, r#"[{"language": "rust"}, {"filter": "File"}]"#), 3);
+    assert_eq!(count(&mut api, handle, r#"[{"language": "rust"}, {"filter": "Function"}]"#), 4);
+    assert_eq!(count(&mut api, handle, r#"[{"find": "Function"}, {"language": "rust"}]"#), 4);
+    assert_eq!(count(&mut api, handle, r#"[{"language": "python"}]"#), 0);
+    assert_eq!(count(&mut api, handle, r#"[{"language": "javascript"}]"#), 0);
+
+    // Composes with in_file either way round
+    assert_eq!(count(&mut api, handle, r#"[{"language": "rust"}, {"in_file": "src"}, {"filter": "Function"}]"#), 3);
+    assert_eq!(count(&mut api, handle, r#"[{"in_file": "main.rs"}, {"language": "rust"}, {"filter": "Function"}]"#), 1);
+    assert_eq!(count(&mut api, handle, r#"[{"in_file": "src"}, {"language": "python"}]"#), 0);
+}
+
+#[test]
+fn test_incremental_run_keeps_languages() {
+    let dir = mixed_repo();
+    let pipeline = Pipeline::default();
+    let first = pipeline.run(dir.path()).unwrap();
+
+    fs::write(dir.path().join("src/util.rs"), "fn c() { let y = 2; }\n").unwrap();
+    let refreshed = pipeline.run_incremental(&first).unwrap();
+
+    let indices = refreshed.cpg_epoch.indices();
+    assert_eq!(indices.files_in_language("rust").len(), 3);
+    assert_eq!(indices.languages().len(), 1);
+    assert_eq!(refreshed.cpg_epoch.cpg_hash(), pipeline.run(dir.path()).unwrap().cpg_epoch.cpg_hash());
+}
+
+#[test]
+fn test_snapshot_round_trip_preserves_language() {
+    let dir = mixed_repo();
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    let snapshot = TempDir::new().unwrap();
+    let path = snapshot.path().join("cpg.snap");
+    CPGSnapshot::save(output.cpg_epoch.cpg(), output.cpg_epoch.epoch_id(), &path).unwrap();
+
+    let restored = CPGEpoch::from_snapshot(&path, Some(output.cpg_epoch.cpg_hash())).unwrap();
+    let cpg = restored.cpg();
+    let files = cpg.get_nodes_of_kind(CPGNodeKind::File);
+    assert_eq!(files.len(), 3);
+    assert!(files.iter().all(|file| cpg.label(file) == Some("rust")));
+    assert_eq!(restored.indices().languages(), output.cpg_epoch.indices().languages());
+}
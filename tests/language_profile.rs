@@ -0,0 +1,51 @@
+//! The Rust language profile reproduces the pinned golden hashes
+//!
+//! Every golden fixture file is rebuilt with the profile chosen from its
+//! `FileMetadata.language`, outside the pipeline, and each CFG and DFG hash
+//! must equal the one pinned in `tests/golden/manifest.toml`.
+
+use std::path::Path;
+use vcr::io::{MmappedFile, SourceFile};
+use vcr::memory::arena::StringArena;
+use vcr::parse::IncrementalParser;
+use vcr::repo::{normalize_path, RepoScanner};
+use vcr::semantic::{profile_for, CFGBuilder, DFGBuilder, SymbolTable};
+use vcr::verify::GoldenManifest;
+
+#[test]
+fn test_rust_profile_reproduces_golden_hashes() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let manifest = GoldenManifest::load(&dir).unwrap();
+    let mut checked = 0;
+
+    for (name, entry) in &manifest.fixtures {
+        let snapshot = RepoScanner::new(dir.join(name)).unwrap().with_extension("rs").scan().unwrap();
+        for (file_id, meta) in &snapshot.files {
+            let pinned = &entry.files[&normalize_path(&meta.path)];
+            let profile = profile_for(meta.language);
+            assert_eq!(profile.name(), "rust");
+
+            let file = MmappedFile::open(snapshot.root.join(&meta.path), *file_id).unwrap();
+            let parsed = IncrementalParser::new(meta.language.unwrap()).unwrap().parse(&file, None).unwrap();
+            let mut strings = StringArena::new();
+            let cfgs = CFGBuilder::new(*file_id, file.bytes())
+                .with_profile(profile)
+                .build_all(&parsed, &mut strings)
+                .unwrap();
+            let mut symbols = SymbolTable::new(*file_id).with_profile(profile);
+            symbols.build(&parsed, file.bytes()).unwrap();
+            let index = parsed.preorder_index();
+
+            let cfg_hashes: Vec<String> = cfgs.iter().map(|cfg| cfg.compute_hash()).collect();
+            let dfgs: Vec<_> = cfgs.iter()
+                .map(|cfg| DFGBuilder::new(cfg, &symbols, &index, file.bytes()).build(&mut strings).unwrap())
+                .collect();
+            let dfg_hashes: Vec<String> = dfgs.iter().map(|dfg| dfg.compute_hash(&strings)).collect();
+            assert_eq!(cfg_hashes, pinned.cfg, "{}: {}", name, meta.path.display());
+            assert_eq!(dfg_hashes, pinned.dfg, "{}: {}", name, meta.path.display());
+            checked += 1;
+        }
+    }
+
+    assert_eq!(checked, 4);
+}
@@ -0,0 +1,111 @@
+//! Parameter positions from signatures to queries
+//!
+//! - Each parameter is a DFG value with its declaration-order position
+//! - The position is on the DfgValue node (`parameter_position`) and in its label
+//! - `parameter` selects a function's parameter by position, as a query and
+//!   as a taint source
+//! - Snapshots saved before positions were recorded recover them on load
+
+use vcr::analysis::{TaintAnalysis, TaintSink, TaintSource};
+use vcr::cpg::model::CPGNodeId;
+use vcr::pipeline::{Pipeline, PipelineOutput};
+use vcr::query::{QueryEngine, QuerySpec, TaintQuery};
+use vcr::storage::CPGSnapshot;
+use tempfile::TempDir;
+
+const SOURCE: &str = "\
+fn handle(user: String, table: String, query: String) { let sql = query; run(sql); }
+fn other(only: i32) { let x = only; }
+";
+
+fn ingest() -> (TempDir, PipelineOutput) {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("lib.rs"), SOURCE).unwrap();
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    (dir, output)
+}
+
+fn positions(output: &PipelineOutput, nodes: &[CPGNodeId]) -> Vec<Option<u32>> {
+    let cpg = output.cpg_epoch.cpg();
+    nodes.iter().map(|id| cpg.get_node(*id).unwrap().parameter_position).collect()
+}
+
+fn labels(output: &PipelineOutput, nodes: &[CPGNodeId]) -> Vec<String> {
+    let cpg = output.cpg_epoch.cpg();
+    nodes.iter().map(|id| cpg.label(cpg.get_node(*id).unwrap()).unwrap().to_string()).collect()
+}
+
+fn query(output: &PipelineOutput, pipeline: &str) -> Vec<CPGNodeId> {
+    let spec = QuerySpec::from_json(&format!(r#"{{"pipeline": {}}}"#, pipeline)).unwrap();
+    QueryEngine::new().compute(output.cpg_epoch.cpg(), &spec).unwrap()
+}
+
+#[test]
+fn test_positions_reach_query_results() {
+    let (_dir, output) = ingest();
+
+    for (position, name) in ["user", "table", "query"].into_iter().enumerate() {
+        let nodes = query(&output, &format!(r#"[{{"function": "handle"}}, {{"parameter": {}}}]"#, position));
+        assert_eq!(labels(&output, &nodes), [format!(r#"Parameter {{ name: "{}", position: {} }}"#, name, position)]);
+        assert_eq!(positions(&output, &nodes), [Some(position as u32)]);
+    }
+    assert!(query(&output, r#"[{"function": "handle"}, {"parameter": 3}]"#).is_empty());
+
+    // First stage: every function's
+    let firsts = query(&output, r#"[{"parameter": 0}]"#);
+    assert_eq!(labels(&output, &firsts), [r#"Parameter { name: "user", position: 0 }"#, r#"Parameter { name: "only", position: 0 }"#]);
+    assert_eq!(positions(&output, &firsts), [Some(0), Some(0)]);
+    // Only parameter values have a position
+    assert_eq!(positions(&output, &query(&output, r#"[{"find": "DfgValue"}]"#)).iter().flatten().count(), 4);
+    // Non-function nodes select nothing
+    assert!(query(&output, r#"[{"find": "File"}, {"parameter": 0}]"#).is_empty());
+}
+
+#[test]
+fn test_parameter_as_taint_source() {
+    let (_dir, output) = ingest();
+    let cpg = output.cpg_epoch.cpg();
+    let taint = TaintQuery::from_json(r#"{
+        "sources": {"pipeline": [{"function": "handle"}, {"parameter": 2}]},
+        "sinks": {"pipeline": [{"function": "handle"}, {"parameter": 2}, {"follow": "DataFlow"}]}
+    }"#).unwrap();
+
+    let engine = QueryEngine::new();
+    let sources = engine.compute(cpg, &taint.sources).unwrap();
+    let sinks = engine.compute(cpg, &taint.sinks).unwrap();
+    assert_eq!(labels(&output, &sinks), [r#"Variable { name: "sql" }"#]);
+
+    let analysis = TaintAnalysis::analyze_bounded(
+        cpg,
+        sources.iter().copied().map(TaintSource::Parameter).collect(),
+        sinks.iter().copied().map(TaintSink::FunctionCall).collect(),
+        taint.max_depth,
+    );
+    let paths: Vec<&[CPGNodeId]> = analysis.paths().iter().map(|path| path.path.as_slice()).collect();
+    assert_eq!(paths, [&[sources[0], sinks[0]][..]]);
+}
+
+#[test]
+fn test_version_10_snapshot_recovers_positions() {
+    let (dir, output) = ingest();
+    let path = dir.path().join("snapshot.json");
+    CPGSnapshot::save(output.cpg_epoch.cpg(), 1, &path).unwrap();
+
+    // What version 10 wrote: no positions on the nodes
+    let mut file: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    file["metadata"]["version"] = 10.into();
+    for node in file["cpg"]["nodes"].as_array_mut().unwrap() {
+        node.as_object_mut().unwrap().remove("parameter_position");
+    }
+    std::fs::write(&path, file.to_string()).unwrap();
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("parameter_position"));
+
+    let (metadata, cpg) = CPGSnapshot::read(&path).unwrap();
+    assert_eq!(metadata.version, 10);
+    let spec = QuerySpec::from_json(r#"{"pipeline": [{"function": "handle"}, {"parameter": 2}]}"#).unwrap();
+    let nodes = QueryEngine::new().compute(&cpg, &spec).unwrap();
+    assert_eq!(labels(&output, &nodes), [r#"Parameter { name: "query", position: 2 }"#]);
+    let recovered: Vec<_> = cpg.nodes.iter().map(|node| node.parameter_position).collect();
+    let saved: Vec<_> = output.cpg_epoch.cpg().nodes.iter().map(|node| node.parameter_position).collect();
+    assert_eq!(recovered, saved);
+}
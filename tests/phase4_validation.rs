@@ -4,13 +4,8 @@
 
 use vcr::*;
 use vcr::execution::{ExecutionPlan, Stage, Task, TaskId, WorkFragment, Scheduler, DeterministicOrder};
-use vcr::cpg::{CPGEpoch, model::{CPG, CPGNode, CPGNodeId, CPGNodeKind, OriginRef}};
-use vcr::cpg::builder::CPGBuilder;
-use vcr::semantic::cfg::CFGBuilder;
-use vcr::semantic::symbols::SymbolTable;
+use vcr::cpg::model::{CPG, CPGNode, CPGNodeId, CPGNodeKind, OriginRef};
 use vcr::types::ByteRange;
-use std::fs;
-use tempfile::NamedTempFile;
 
 #[test]
 fn test_parallel_execution_determinism() {
@@ -23,7 +18,7 @@ fn test_parallel_execution_determinism() {
             CPGNodeId(i),
             CPGNodeKind::Function,
             OriginRef::Function { function_id: semantic::model::FunctionId(i) },
-            ByteRange::new((i as u32 - 1) * 10, i as u32 * 10),
+            ByteRange::new((i as usize - 1) * 10, i as usize * 10),
         ));
     }
 
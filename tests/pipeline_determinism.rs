@@ -0,0 +1,96 @@
+//! Pipeline determinism tests (Path B8)
+//!
+//! - Same directory → same CPG hash across runs
+//! - Incremental run after an edit → same stage hashes as a fresh run
+
+use vcr::pipeline::{Pipeline, PipelineOutput};
+use vcr::verify::StageHashes;
+use vcr::FileId;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn temp_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), "fn main() { let x = helper(1); if x > 0 { log(x); } }\n").unwrap();
+    fs::write(dir.path().join("src/helper.rs"), "pub fn helper(n: i32) -> i32 { let mut y = n; while y < 10 { y = y + 1; } y }\n").unwrap();
+    fs::write(dir.path().join("src/log.rs"), "pub fn log(v: i32) { let s = v; }\n").unwrap();
+    dir
+}
+
+fn file_id(output: &PipelineOutput, relative: &str) -> FileId {
+    output.snapshot.files.iter()
+        .find(|(_, meta)| meta.path == Path::new(relative))
+        .map(|(id, _)| *id)
+        .unwrap()
+}
+
+#[test]
+fn test_pipeline_cpg_hash_stable() {
+    let dir = temp_repo();
+    let pipeline = Pipeline::default();
+
+    let first = pipeline.run(dir.path()).unwrap();
+    let second = pipeline.run(dir.path()).unwrap();
+
+    assert_eq!(first.cpg_epoch.cpg().compute_hash(), second.cpg_epoch.cpg().compute_hash());
+    assert_eq!(StageHashes::from_output(&first), StageHashes::from_output(&second));
+}
+
+#[test]
+fn test_incremental_edit_matches_fresh_run() {
+    let dir = temp_repo();
+    let pipeline = Pipeline::default();
+    let first = pipeline.run(dir.path()).unwrap();
+
+    fs::write(dir.path().join("src/helper.rs"), "pub fn helper(n: i32) -> i32 { if n > 0 { n } else { 0 } }\n").unwrap();
+
+    let incremental = pipeline.run_incremental(&first).unwrap();
+    let fresh = pipeline.run(dir.path()).unwrap();
+
+    assert_eq!(incremental.rebuilt, vec![file_id(&fresh, "src/helper.rs")]);
+    assert_eq!(StageHashes::from_output(&incremental), StageHashes::from_output(&fresh));
+    assert_eq!(incremental.metrics, fresh.metrics);
+    assert!(incremental.cpg_epoch.indices().verify_against(incremental.cpg_epoch.cpg()).is_ok());
+
+    let (inc, new) = (incremental.semantic.invalidation().stats(), fresh.semantic.invalidation().stats());
+    assert_eq!((inc.ast_ranges, inc.cpg_nodes), (new.ast_ranges, new.cpg_nodes));
+}
+
+#[test]
+fn test_incremental_add_and_delete_match_fresh_run() {
+    let dir = temp_repo();
+    let pipeline = Pipeline::default();
+    let first = pipeline.run(dir.path()).unwrap();
+
+    fs::remove_file(dir.path().join("src/log.rs")).unwrap();
+    fs::write(dir.path().join("src/extra.rs"), "fn extra() { let z = 3; }\n").unwrap();
+
+    let incremental = pipeline.run_incremental(&first).unwrap();
+    let fresh = pipeline.run(dir.path()).unwrap();
+
+    assert_eq!(incremental.rebuilt, vec![file_id(&fresh, "src/extra.rs")]);
+    assert_eq!(StageHashes::from_output(&incremental), StageHashes::from_output(&fresh));
+    assert_eq!(incremental.call_graph.len(), fresh.call_graph.len());
+    assert!(incremental.cpg_epoch.indices().verify_against(incremental.cpg_epoch.cpg()).is_ok());
+}
+
+#[test]
+fn test_invalidation_sets_stable_across_runs() {
+    use vcr::types::ByteRange;
+
+    let dir = temp_repo();
+    let pipeline = Pipeline::default();
+    let first = pipeline.run(dir.path()).unwrap();
+    let second = pipeline.run(dir.path()).unwrap();
+
+    let helper = file_id(&first, "src/helper.rs");
+    let edit = [ByteRange::new(40, 50)];
+    let a = first.semantic.invalidation().invalidate(helper, &edit);
+    let b = second.semantic.invalidation().invalidate(helper, &edit);
+
+    assert!(!a.cpg_nodes.is_empty());
+    assert_eq!(a.cfg_nodes, b.cfg_nodes);
+    assert_eq!(a.cpg_nodes, b.cpg_nodes);
+}
@@ -0,0 +1,104 @@
+//! Persisted and incremental points-to maps
+//!
+//! - An additive edit updates the previous map incrementally, seeded from
+//!   the values invalidation reports, to exactly the full recompute
+//! - A deleting edit is detected and recomputed from scratch
+//! - Maps round-trip through the snapshot store, keyed by CPG hash
+
+use vcr::analysis::{changed_values, AliasResult, PointerAnalysis, PointsToUpdate};
+use vcr::pipeline::{Pipeline, PipelineOutput};
+use vcr::semantic::model::ValueId;
+use vcr::storage::SnapshotStore;
+use vcr::types::ByteRange;
+use tempfile::TempDir;
+
+const BEFORE: &str = "fn f(a: i32) {\n    let b = a;\n}\n";
+const AFTER: &str = "fn f(a: i32) {\n    let b = a;\n    let c = b;\n}\n";
+
+/// Offset in `BEFORE` where `AFTER` inserts `let c = b;`
+const INSERTED_AT: usize = 30;
+
+fn ingest(source: &str) -> (TempDir, PipelineOutput) {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("lib.rs"), source).unwrap();
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    (dir, output)
+}
+
+fn edit(dir: &TempDir, previous: &PipelineOutput, source: &str) -> PipelineOutput {
+    std::fs::write(dir.path().join("lib.rs"), source).unwrap();
+    Pipeline::default().run_incremental(previous).unwrap()
+}
+
+#[test]
+fn test_incremental_matches_full_after_addition() {
+    assert_eq!(&AFTER[INSERTED_AT..INSERTED_AT + 14], "    let c = b;");
+    let (dir, before) = ingest(BEFORE);
+    let previous = PointerAnalysis::analyze_from_roots(before.cpg_epoch.cpg());
+
+    let file_id = before.snapshot.file_ids()[0];
+    let invalidated = before.semantic.invalidation().invalidate(file_id, &[ByteRange::new(INSERTED_AT, INSERTED_AT)]);
+    let changed = changed_values(before.cpg_epoch.cpg(), &invalidated.cpg_nodes);
+    assert!(!changed.is_empty());
+
+    let after = edit(&dir, &before, AFTER);
+    let (updated, how) = PointerAnalysis::update_from_roots(&previous, after.cpg_epoch.cpg(), &changed);
+    let full = PointerAnalysis::analyze_from_roots(after.cpg_epoch.cpg());
+
+    assert!(matches!(how, PointsToUpdate::Incremental { .. }));
+    assert_eq!(updated, full);
+    assert_ne!(updated, previous);
+    // `c` (value 2) is a copy of `b` (value 1), itself a copy of parameter `a` (value 0)
+    assert_eq!(updated.may_alias(ValueId(1), ValueId(2)), AliasResult::MayAlias { witnesses: vec![ValueId(0)] });
+}
+
+#[test]
+fn test_deletion_forces_full_recompute() {
+    let (dir, before) = ingest(AFTER);
+    let previous = PointerAnalysis::analyze_from_roots(before.cpg_epoch.cpg());
+    assert!(previous.points_to(ValueId(2)).is_some());
+
+    let after = edit(&dir, &before, BEFORE);
+    let (updated, how) = PointerAnalysis::update_from_roots(&previous, after.cpg_epoch.cpg(), &[]);
+
+    assert_eq!(how, PointsToUpdate::Recomputed);
+    assert!(!updated.graph().extends(previous.graph()));
+    assert_eq!(updated, PointerAnalysis::analyze_from_roots(after.cpg_epoch.cpg()));
+    // Resuming from the previous sets would have kept the deleted value
+    assert_eq!(updated.points_to(ValueId(2)), None);
+}
+
+#[test]
+fn test_unchanged_graph_reuses_previous() {
+    let (dir, before) = ingest(AFTER);
+    let previous = PointerAnalysis::analyze_from_roots(before.cpg_epoch.cpg());
+
+    // A comment moves no value or edge
+    let after = edit(&dir, &before, &format!("// lib\n{}", AFTER));
+    let (updated, how) = PointerAnalysis::update_from_roots(&previous, after.cpg_epoch.cpg(), &[]);
+    assert_eq!(how, PointsToUpdate::Unchanged);
+    assert_eq!(updated, previous);
+}
+
+#[test]
+fn test_store_keys_maps_by_cpg_hash() {
+    let (_dir, output) = ingest(AFTER);
+    let analysis = PointerAnalysis::analyze_from_roots(output.cpg_epoch.cpg());
+    let hash = output.cpg_epoch.cpg_hash();
+
+    let store_dir = TempDir::new().unwrap();
+    let mut store = SnapshotStore::open(store_dir.path()).unwrap();
+    // Only kept alongside a saved snapshot
+    assert!(store.save_points_to(hash, &analysis).is_err());
+    store.save_with_repo(output.cpg_epoch.cpg(), 1, &output.snapshot).unwrap();
+    store.save_points_to(hash, &analysis).unwrap();
+
+    let reopened = SnapshotStore::open(store_dir.path()).unwrap();
+    assert_eq!(reopened.load_points_to(hash).unwrap(), Some(analysis.clone()));
+    assert_eq!(reopened.load_points_to(&"0".repeat(64)).unwrap(), None);
+
+    // Sorted serialization: equal maps write equal bytes
+    let persisted = serde_json::to_string(&analysis.persist(hash)).unwrap();
+    let recomputed = PointerAnalysis::analyze_from_roots(output.cpg_epoch.cpg());
+    assert_eq!(serde_json::to_string(&recomputed.persist(hash)).unwrap(), persisted);
+}
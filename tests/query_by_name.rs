@@ -0,0 +1,75 @@
+//! Name-based function lookup (Step 3.6)
+//!
+//! - `function` finds every same-named function, in file order
+//! - `function_matches` takes a glob over the whole name
+//! - Both compose with the other stages through the API
+
+use tempfile::TempDir;
+use vcr::api::ValoriAPI;
+use vcr::pipeline::Pipeline;
+use vcr::query::{QueryEngine, QuerySpec};
+
+/// Example query shipped for `vcr query`
+const HANDLERS: &str = include_str!("../examples/queries/handlers.json");
+
+fn repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let src = dir.path().join("src");
+    std::fs::create_dir_all(src.join("admin")).unwrap();
+    std::fs::write(src.join("lib.rs"), "fn main() { handle_login(); }\nfn handle_login() {}\n").unwrap();
+    std::fs::write(src.join("admin/auth.rs"), "fn handle_login() { let x = 1; }\nfn handle_logout() {}\nfn rehandle_() {}\n").unwrap();
+    dir
+}
+
+/// Labels of the result, in result order
+fn names(query: &str, dir: &TempDir) -> Vec<String> {
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    let cpg = output.cpg_epoch.cpg();
+    let nodes = QueryEngine::new().compute(cpg, &QuerySpec::from_json(query).unwrap()).unwrap();
+    nodes.iter().map(|id| cpg.label(cpg.get_node(*id).unwrap()).unwrap().to_string()).collect()
+}
+
+#[test]
+fn test_function_returns_every_file_in_order() {
+    let dir = repo();
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    let cpg = output.cpg_epoch.cpg();
+
+    let spec = QuerySpec::from_json(r#"{"pipeline": [{"function": "handle_login"}]}"#).unwrap();
+    let nodes = QueryEngine::new().compute(cpg, &spec).unwrap();
+    assert_eq!(nodes.len(), 2);
+
+    // Ordered by FileId, matching fusion order
+    let files: Vec<_> = nodes.iter()
+        .map(|id| output.cpg_epoch.indices().function_names()["handle_login"].iter().find(|f| f.2 == *id).unwrap().0)
+        .collect();
+    let mut sorted = files.clone();
+    sorted.sort();
+    assert_eq!(files, sorted);
+
+    assert!(names(r#"{"pipeline": [{"function": "missing"}]}"#, &dir).is_empty());
+}
+
+#[test]
+fn test_function_matches_example_query() {
+    let dir = repo();
+    let mut found = names(HANDLERS, &dir);
+    found.sort();
+    assert_eq!(found, ["handle_login", "handle_login", "handle_logout"]);
+
+    assert_eq!(names(r#"{"pipeline": [{"function_matches": "*_logout"}]}"#, &dir), ["handle_logout"]);
+    assert_eq!(names(r#"{"pipeline": [{"function_matches": "*handle_*"}]}"#, &dir).len(), 4);
+}
+
+#[test]
+fn test_function_lookup_through_api() {
+    let dir = repo();
+    let mut api = ValoriAPI::default();
+    let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    let scoped = api.run_query(handle, r#"{"pipeline": [{"in_file": "src/admin"}, {"function": "handle_login"}]}"#).unwrap();
+    assert_eq!(api.fetch_result(scoped).unwrap().len(), 1);
+
+    let err = api.run_query(handle, r#"{"pipeline": [{"function_matches": "handle_[login"}]}"#).unwrap_err();
+    assert!(err.to_string().contains("unclosed"), "{}", err);
+}
@@ -0,0 +1,69 @@
+//! Query result cache validation (Step 3.6)
+//!
+//! - Same query + same CPG → served from cache
+//! - Cache hits are identical to recomputation
+
+use vcr::api::ValoriAPI;
+use vcr::config::ValoriConfig;
+use std::fs;
+use tempfile::TempDir;
+
+fn temp_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("main.rs"), "fn main() { let x = 1; helper(x); }\n").unwrap();
+    fs::write(dir.path().join("helper.rs"), "fn helper(y: i32) { if y > 0 { return; } }\n").unwrap();
+    dir
+}
+
+#[test]
+fn test_second_query_served_from_cache() {
+    let dir = temp_repo();
+    let mut api = ValoriAPI::default();
+    let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    let first = api.run_query(handle, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+    assert_eq!(api.metrics().query_cache_misses(), 1);
+    assert_eq!(api.metrics().query_cache_hits(), 0);
+
+    // Same query, different formatting and paging
+    let second = api.run_query(handle, r#"{ "limit": 1, "pipeline": [ {"find": "Function"} ] }"#).unwrap();
+    assert_eq!(api.metrics().query_cache_misses(), 1);
+    assert_eq!(api.metrics().query_cache_hits(), 1);
+
+    assert_ne!(first, second, "Each run gets its own result ID");
+    assert_eq!(api.fetch_result(first).unwrap(), api.fetch_result(second).unwrap());
+}
+
+#[test]
+fn test_paranoid_hits_match_recomputation() {
+    let dir = temp_repo();
+    let mut config = ValoriConfig::default();
+    config.query.cache_paranoid = true;
+
+    let mut api = ValoriAPI::new(&config);
+    let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    let query = r#"{"pipeline": [{"find": "CfgNode"}], "order_by": "source_range"}"#;
+    let first = api.run_query(handle, query).unwrap();
+    let second = api.run_query(handle, query).unwrap();
+
+    assert_eq!(api.metrics().query_cache_hits(), 1);
+    assert_eq!(api.fetch_result(first).unwrap(), api.fetch_result(second).unwrap());
+}
+
+#[test]
+fn test_cache_disabled() {
+    let dir = temp_repo();
+    let mut config = ValoriConfig::default();
+    config.query.cache_capacity = 0;
+
+    let mut api = ValoriAPI::new(&config);
+    let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    let query = r#"{"pipeline": [{"find": "Function"}]}"#;
+    api.run_query(handle, query).unwrap();
+    api.run_query(handle, query).unwrap();
+
+    assert_eq!(api.metrics().query_cache_hits(), 0);
+    assert_eq!(api.metrics().query_cache_misses(), 2);
+}
@@ -0,0 +1,55 @@
+//! Reverse traversal validation (Step 3.6)
+//!
+//! - Callers-of via `follow_reverse` finds every call site
+//! - Other edge kinds are not followed
+
+use vcr::cpg::model::{CPGEdge, CPGEdgeId, CPGEdgeKind, CPGNode, CPGNodeId, CPGNodeKind, OriginRef, CPG};
+use vcr::query::{QueryEngine, QuerySpec};
+use vcr::semantic::model::{FunctionId, NodeId};
+use vcr::types::ByteRange;
+
+/// Example query shipped for `vcr query`
+const CALLERS_OF: &str = include_str!("../examples/queries/callers_of.json");
+
+/// Two functions; `helper` is called from three sites, `main` from none
+fn call_graph() -> CPG {
+    let mut cpg = CPG::new();
+    let (main, helper) = (cpg.intern_label("main"), cpg.intern_label("helper"));
+    cpg.add_node(CPGNode::new(CPGNodeId(0), CPGNodeKind::Function,
+        OriginRef::Function { function_id: FunctionId(0) }, ByteRange::new(0, 50)).with_label(main));
+    cpg.add_node(CPGNode::new(CPGNodeId(1), CPGNodeKind::Function,
+        OriginRef::Function { function_id: FunctionId(1) }, ByteRange::new(60, 90)).with_label(helper));
+
+    for (i, site) in [4u64, 2, 3].into_iter().enumerate() {
+        cpg.add_node(CPGNode::new(CPGNodeId(site), CPGNodeKind::CfgNode,
+            OriginRef::Cfg { node_id: NodeId(site) }, ByteRange::new(site as usize * 10, site as usize * 10 + 5)));
+        cpg.add_edge(CPGEdge::new(CPGEdgeId(i as u64), CPGEdgeKind::Calls, CPGNodeId(site), CPGNodeId(1)));
+    }
+    cpg.add_edge(CPGEdge::new(CPGEdgeId(3), CPGEdgeKind::ControlFlow, CPGNodeId(2), CPGNodeId(0)));
+
+    cpg
+}
+
+#[test]
+fn test_callers_of_example_query() {
+    let cpg = call_graph();
+    let spec = QuerySpec::from_json(CALLERS_OF).unwrap();
+
+    let mut engine = QueryEngine::new();
+    let page = engine.execute(&cpg, &spec).unwrap();
+
+    // Only Calls edges count; ordered by node ID
+    assert_eq!(page.nodes, vec![CPGNodeId(2), CPGNodeId(3), CPGNodeId(4)]);
+    assert_eq!(page.total, 3);
+}
+
+#[test]
+fn test_follow_reverse_only_follows_requested_kind() {
+    let cpg = call_graph();
+    let spec = QuerySpec::from_json(
+        r#"{"pipeline": [{"find": "Function"}, {"follow_reverse": "ControlFlow"}]}"#,
+    ).unwrap();
+
+    let engine = QueryEngine::new();
+    assert_eq!(engine.compute(&cpg, &spec).unwrap(), vec![CPGNodeId(2)]);
+}
@@ -0,0 +1,52 @@
+//! Query set operation validation (Step 3.6)
+//!
+//! - `union` of per-file results equals the whole-repo result
+//! - `difference` removes exactly the nested pipeline's nodes
+
+use vcr::api::{RepoHandle, ValoriAPI};
+use std::fs;
+use tempfile::TempDir;
+
+fn temp_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.rs"), "fn a1() {}\nfn a2() { let x = 1; }\n").unwrap();
+    fs::write(dir.path().join("b.rs"), "fn b1(y: i32) { if y > 0 { return; } }\n").unwrap();
+    dir
+}
+
+fn query(api: &mut ValoriAPI, handle: RepoHandle, query: &str) -> Vec<String> {
+    let result_id = api.run_query(handle, query).unwrap();
+    api.fetch_result(result_id).unwrap()
+}
+
+#[test]
+fn test_union_of_files_is_whole_repo() {
+    let dir = temp_repo();
+    let mut api = ValoriAPI::default();
+    let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    let all = query(&mut api, handle, r#"{"pipeline": [{"find": "Function"}]}"#);
+    let union = query(&mut api, handle, r#"{"pipeline": [
+        {"in_file": "a.rs"}, {"filter": "Function"},
+        {"union": [{"in_file": "b.rs"}, {"filter": "Function"}]}
+    ]}"#);
+
+    assert_eq!(all.len(), 3);
+    assert_eq!(union, all);
+}
+
+#[test]
+fn test_difference_removes_nested_result() {
+    let dir = temp_repo();
+    let mut api = ValoriAPI::default();
+    let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    let in_b = query(&mut api, handle, r#"{"pipeline": [{"in_file": "b.rs"}, {"filter": "Function"}]}"#);
+    let not_in_a = query(&mut api, handle, r#"{"pipeline": [
+        {"find": "Function"},
+        {"difference": [{"in_file": "a.rs"}]}
+    ]}"#);
+
+    assert_eq!(in_b.len(), 1);
+    assert_eq!(not_in_a, in_b);
+}
@@ -0,0 +1,94 @@
+//! `vcr query --watch` tests
+//!
+//! Runs the watch loop on a thread against a temp repository, edits files
+//! between refreshes and reads the printed lines back as they arrive.
+
+use serde_json::{json, Value};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+use vcr::cli;
+use vcr::config::ValoriConfig;
+
+/// Sends each completed line to the test thread
+struct LineWriter {
+    buffer: Vec<u8>,
+    lines: Sender<Value>,
+}
+
+impl Write for LineWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            self.lines.send(serde_json::from_slice(&line).unwrap()).ok();
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn next_line(lines: &Receiver<Value>) -> Value {
+    lines.recv_timeout(Duration::from_secs(30)).expect("watch printed no line")
+}
+
+#[test]
+fn test_watch_prints_deltas_for_two_edits() {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    let query_dir = TempDir::new().unwrap();
+    let query_file = query_dir.path().join("functions.json");
+    std::fs::write(&query_file, json!({"pipeline": [{"find": "Function"}]}).to_string()).unwrap();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, lines) = mpsc::channel();
+    let watcher = {
+        let (stop, root) = (Arc::clone(&stop), repo.path().to_path_buf());
+        std::thread::spawn(move || {
+            let output = LineWriter { buffer: Vec::new(), lines: sender };
+            cli::query_watch(&ValoriConfig::default(), &query_file, &root, Duration::from_millis(20), &stop, output)
+        })
+    };
+
+    let initial = next_line(&lines);
+    assert_eq!(initial["event"], "initial");
+    assert_eq!(initial["epoch_id"], 1);
+    assert_eq!(initial["total"], 2);
+    let initial_keys: Vec<Value> = initial["results"].as_array().unwrap().clone();
+
+    // First edit adds a function: only it is new
+    std::fs::write(repo.path().join("lib.rs"), "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+    let first = next_line(&lines);
+    assert_eq!(first["event"], "delta");
+    assert_eq!(first["changed_files"], json!(["lib.rs"]));
+    assert_eq!(first["added"].as_array().unwrap().len(), 1);
+    assert_eq!(first["removed"], json!([]));
+    assert_eq!(first["total"], 3);
+    assert_eq!(first["cached"], false);
+    assert!(!initial_keys.contains(&first["added"][0]));
+
+    // Second edit drops the first function: only it is gone
+    std::fs::write(repo.path().join("lib.rs"), "fn b() {}\nfn c() {}\n").unwrap();
+    let second = next_line(&lines);
+    assert_eq!(second["event"], "delta");
+    assert!(second["epoch_id"].as_u64().unwrap() > first["epoch_id"].as_u64().unwrap());
+    assert_eq!(second["added"], json!([]));
+    assert_eq!(second["removed"].as_array().unwrap().len(), 1);
+    assert!(initial_keys.contains(&second["removed"][0]));
+    assert_eq!(second["total"], 2);
+
+    stop.store(true, Ordering::Relaxed);
+    watcher.join().unwrap().unwrap();
+    let summary = next_line(&lines);
+    assert_eq!(summary["event"], "summary");
+    assert_eq!(summary["reruns"], 2);
+    assert_eq!(summary["added"], 1);
+    assert_eq!(summary["removed"], 1);
+    assert_eq!(summary["total"], 2);
+}
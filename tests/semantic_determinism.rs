@@ -9,8 +9,9 @@
 //! - Local edits → local invalidation only
 
 use std::fs;
-use tempfile::{NamedTempFile, TempDir};
+use tempfile::NamedTempFile;
 use vcr::*;
+use vcr::memory::StringArena;
 use vcr::semantic::cfg::CFGBuilder;
 use vcr::semantic::symbols::SymbolTable;
 
@@ -29,14 +30,14 @@ fn test_cfg_determinism_across_runs() {
     let parsed1 = parser1.parse(&mmap, None).unwrap();
     
     let mut builder1 = CFGBuilder::new(file_id, source);
-    let cfgs1 = builder1.build_all(&parsed1).unwrap();
+    let cfgs1 = builder1.build_all(&parsed1, &mut StringArena::new()).unwrap();
 
     // Second parse
     let mut parser2 = parse::IncrementalParser::new(types::Language::Rust).unwrap();
     let parsed2 = parser2.parse(&mmap, None).unwrap();
     
     let mut builder2 = CFGBuilder::new(file_id, source);
-    let cfgs2 = builder2.build_all(&parsed2).unwrap();
+    let cfgs2 = builder2.build_all(&parsed2, &mut StringArena::new()).unwrap();
 
     // CFG hashes must match
     assert_eq!(cfgs1.len(), cfgs2.len(), "Same number of functions");
@@ -69,7 +70,7 @@ fn test_whitespace_has_no_semantic_effect() {
     let parsed1 = parser1.parse(&mmap1, None).unwrap();
     
     let mut builder1 = CFGBuilder::new(file_id, source1);
-    let cfgs1 = builder1.build_all(&parsed1).unwrap();
+    let cfgs1 = builder1.build_all(&parsed1, &mut StringArena::new()).unwrap();
 
     // Parse file 2
     let mmap2 = io::MmappedFile::open(temp2.path(), file_id).unwrap();
@@ -77,7 +78,7 @@ fn test_whitespace_has_no_semantic_effect() {
     let parsed2 = parser2.parse(&mmap2, None).unwrap();
     
     let mut builder2 = CFGBuilder::new(file_id, source2);
-    let cfgs2 = builder2.build_all(&parsed2).unwrap();
+    let cfgs2 = builder2.build_all(&parsed2, &mut StringArena::new()).unwrap();
 
     // Semantic structure should be identical
     assert_eq!(cfgs1.len(), cfgs2.len());
@@ -103,17 +104,19 @@ fn test_function_order_is_deterministic() {
     let parsed = parser.parse(&mmap, None).unwrap();
     
     let mut builder = CFGBuilder::new(file_id, source);
-    let cfgs = builder.build_all(&parsed).unwrap();
+    let cfgs = builder.build_all(&parsed, &mut StringArena::new()).unwrap();
 
     // Should have 3 CFGs in lexical order (third, first, second)
     assert_eq!(cfgs.len(), 3, "Should have 3 functions");
+    let names: Vec<_> = cfgs.iter().map(|cfg| cfg.name.as_str()).collect();
+    assert_eq!(names, ["third", "first", "second"]);
     
     // Parse again
     let mut parser2 = parse::IncrementalParser::new(types::Language::Rust).unwrap();
     let parsed2 = parser2.parse(&mmap, None).unwrap();
     
     let mut builder2 = CFGBuilder::new(file_id, source);
-    let cfgs2 = builder2.build_all(&parsed2).unwrap();
+    let cfgs2 = builder2.build_all(&parsed2, &mut StringArena::new()).unwrap();
 
     // Order must be identical
     for (i, (cfg1, cfg2)) in cfgs.iter().zip(cfgs2.iter()).enumerate() {
@@ -122,6 +125,7 @@ fn test_function_order_is_deterministic() {
             "Function {} ID must match",
             i
         );
+        assert_eq!(cfg1.compute_hash(), cfg2.compute_hash(), "Function {} hash must match", i);
     }
 }
 
@@ -179,7 +183,7 @@ fn test_local_edit_local_invalidation() {
     let parsed1 = parser1.parse(&mmap1, None).unwrap();
     
     let mut builder1 = CFGBuilder::new(file_id, source1);
-    let cfgs1 = builder1.build_all(&parsed1).unwrap();
+    let cfgs1 = builder1.build_all(&parsed1, &mut StringArena::new()).unwrap();
 
     // Parse version 2
     let mmap2 = io::MmappedFile::open(temp2.path(), file_id).unwrap();
@@ -187,10 +191,40 @@ fn test_local_edit_local_invalidation() {
     let parsed2 = parser2.parse(&mmap2, None).unwrap();
     
     let mut builder2 = CFGBuilder::new(file_id, source2);
-    let cfgs2 = builder2.build_all(&parsed2).unwrap();
+    let cfgs2 = builder2.build_all(&parsed2, &mut StringArena::new()).unwrap();
 
     // foo() changed, bar() didn't
     // Both versions should have 2 functions
     assert_eq!(cfgs1.len(), 2);
     assert_eq!(cfgs2.len(), 2);
 }
+
+#[test]
+fn test_dfg_phi_placement_determinism() {
+    // Branches and loops exercise dominance-frontier phi placement
+    let source = b"fn test(c: bool) { let mut x = 1; if c { x = 2; } else { x = 3; } while x < 9 { x = x + 1; } }";
+
+    let temp_file = NamedTempFile::new().unwrap();
+    fs::write(temp_file.path(), source).unwrap();
+
+    let file_id = FileId::new(1);
+    let mmap = io::MmappedFile::open(temp_file.path(), file_id).unwrap();
+
+    let build = || {
+        let mut parser = parse::IncrementalParser::new(types::Language::Rust).unwrap();
+        let parsed = parser.parse(&mmap, None).unwrap();
+        let cfgs = CFGBuilder::new(file_id, source).build_all(&parsed, &mut StringArena::new()).unwrap();
+        let mut symbols = SymbolTable::new(file_id);
+        symbols.build(&parsed, source).unwrap();
+        let index = parsed.preorder_index();
+        let mut strings = StringArena::new();
+        let dfg = semantic::dfg::DFGBuilder::new(&cfgs[0], &symbols, &index, source).build(&mut strings).unwrap();
+        (dfg, strings)
+    };
+
+    let (dfg1, strings1) = build();
+    let (dfg2, strings2) = build();
+
+    assert_eq!(dfg1.compute_hash(&strings1), dfg2.compute_hash(&strings2), "DFG hashes must be identical across runs");
+    assert!(dfg1.edges.iter().any(|e| e.kind == semantic::model::DFGEdgeKind::PhiLike));
+}
@@ -0,0 +1,61 @@
+//! `vcr serve` protocol tests (Path B9)
+//!
+//! Drives the real binary over stdin/stdout: one request per line, one
+//! response per line, in order.
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+#[test]
+fn test_serve_load_query_update_query() {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("lib.rs"), "fn a() { let x = 1; }\nfn b() {}\n").unwrap();
+
+    let functions = json!({"pipeline": [{"find": "Function"}]});
+    let requests = [
+        json!({"id": 1, "op": "load_repo", "path": repo.path()}),
+        json!({"id": 2, "op": "run_query", "handle": 1, "query": functions}),
+        json!({"id": 3, "op": "fetch_result", "result_id": 1}),
+        json!({"id": 4, "op": "update_files", "handle": 1, "files": [0]}),
+        json!({"id": 5, "op": "run_query", "handle": 1, "query": functions}),
+        json!({"id": 6, "op": "fetch_result", "result_id": 2}),
+        json!({"id": 7, "op": "run_query", "handle": 9, "query": functions}),
+        json!({"id": 8, "op": "shutdown"}),
+    ];
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_vcr"))
+        .arg("serve")
+        .current_dir(repo.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // One line at a time: each response arrives before the next request is sent
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut responses = Vec::new();
+    for request in &requests {
+        writeln!(stdin, "{}", request).unwrap();
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        responses.push(serde_json::from_str::<Value>(&line).unwrap());
+    }
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    let ids: Vec<_> = responses.iter().map(|r| r["id"].as_u64().unwrap()).collect();
+    assert_eq!(ids, (1..=8).collect::<Vec<_>>());
+
+    assert_eq!(responses[0]["handle"], 1);
+    assert_eq!(responses[1]["result_id"], 1);
+    assert_eq!(responses[2]["count"], 2);
+    assert_eq!(responses[3]["status"], "success");
+    assert_eq!(responses[5]["results"], responses[2]["results"]);
+
+    assert_eq!(responses[6]["status"], "error");
+    assert_eq!(responses[6]["code"], "not_found");
+    assert_eq!(responses[7]["status"], "success");
+}
@@ -0,0 +1,116 @@
+//! Snapshot restore tests (Path B2)
+//!
+//! A CPG restored from a snapshot must answer queries exactly like the
+//! epoch it was saved from.
+
+use vcr::cpg::{CPGEdgeKind, CPGEpoch, CPGNodeId, FrozenCPGEpoch};
+use vcr::pipeline::Pipeline;
+use vcr::query::{QueryEngine, QuerySpec};
+use vcr::storage::CPGSnapshot;
+use tempfile::TempDir;
+
+const FUNCTIONS: &str = r#"{"pipeline": [{"find": "Function"}], "order_by": "label"}"#;
+const CALLERS: &str = r#"{"pipeline": [{"find": "CfgNode"}, {"follow_reverse": "ControlFlow"}]}"#;
+
+fn run(epoch: &FrozenCPGEpoch, query: &str) -> Vec<u64> {
+    let spec = QuerySpec::from_json(query).unwrap();
+    let page = QueryEngine::new().execute(epoch.cpg(), &spec).unwrap();
+    page.nodes.iter().map(|id| id.0).collect()
+}
+
+/// Control-flow predecessors of every node, from the epoch's indices
+fn predecessors(epoch: &FrozenCPGEpoch) -> Vec<Vec<CPGNodeId>> {
+    epoch.cpg().nodes.iter()
+        .map(|n| epoch.indices().get_sources_to(n.id, CPGEdgeKind::ControlFlow).to_vec())
+        .collect()
+}
+
+#[test]
+fn test_restored_snapshot_answers_like_the_live_epoch() {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("a.rs"), "fn a() { let x = 1; if x > 0 { b(); } }\n").unwrap();
+    std::fs::write(repo.path().join("b.rs"), "fn b() {}\nfn c() { b(); }\n").unwrap();
+    let snapshot = repo.path().join("snapshot.cpg");
+
+    // Ingest and save
+    let output = Pipeline::default().run(repo.path()).unwrap();
+    let live = &output.cpg_epoch;
+    CPGSnapshot::save(live.cpg(), live.epoch_id(), &snapshot).unwrap();
+    let expected_hash = live.cpg().compute_hash();
+    let expected = (run(live, FUNCTIONS), run(live, CALLERS), predecessors(live));
+    let saved_epoch = live.epoch_id();
+    drop(output);
+
+    // Load with nothing else alive
+    let restored = CPGEpoch::from_snapshot(&snapshot, Some(&expected_hash)).unwrap();
+
+    assert_eq!(expected.0.len(), 3);
+    assert_eq!((run(&restored, FUNCTIONS), run(&restored, CALLERS), predecessors(&restored)), expected);
+    assert_eq!(restored.restored_from(), Some(saved_epoch));
+    assert!(restored.epoch_id() > saved_epoch);
+}
+
+#[test]
+fn test_tampered_snapshot_fails_closed() {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("a.rs"), "fn a() {}\n").unwrap();
+    let snapshot = repo.path().join("snapshot.cpg");
+
+    let output = Pipeline::default().run(repo.path()).unwrap();
+    CPGSnapshot::save(output.cpg_epoch.cpg(), output.cpg_epoch.epoch_id(), &snapshot).unwrap();
+
+    let tampered = std::fs::read_to_string(&snapshot).unwrap().replacen("\"Function\"", "\"Symbol\"", 1);
+    std::fs::write(&snapshot, tampered).unwrap();
+
+    let err = CPGEpoch::from_snapshot(&snapshot, None).err().unwrap();
+    assert!(format!("{:#}", err).contains("Hash mismatch"), "{:#}", err);
+}
+
+#[test]
+fn test_restored_symbols_answer_name_queries_like_the_live_ingest() {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("a.rs"), "fn a() { fn inner() {} b(); }\nfn b() {}\n").unwrap();
+    std::fs::write(repo.path().join("b.rs"), "fn b() {}\nfn c(x: u8) { let y = x; }\n").unwrap();
+    let snapshot = repo.path().join("snapshot.cpg");
+
+    let output = Pipeline::default().run(repo.path()).unwrap();
+    let live = &output.cpg_epoch;
+    CPGSnapshot::save_with_symbols(live.cpg(), live.epoch_id(), &output.snapshot, &output.semantic, &snapshot).unwrap();
+    let (restored, semantic) = CPGEpoch::from_snapshot_with_symbols(&snapshot, None).unwrap();
+
+    assert_eq!(semantic.get_all_file_ids(), output.semantic.get_all_file_ids());
+    for name in ["a", "b", "c", "inner", "x", "missing"] {
+        assert_eq!(semantic.functions_named(name), output.semantic.functions_named(name), "{}", name);
+        let query = format!(r#"{{"pipeline": [{{"function": "{}"}}]}}"#, name);
+        assert_eq!(run(&restored, &query), run(live, &query), "{}", name);
+    }
+    assert_eq!(semantic.functions_named("b").len(), 2);
+    assert!(semantic.functions_named("x").is_empty());
+
+    // Lookups through every restored scope resolve like the live tables
+    for file_id in output.semantic.get_all_file_ids() {
+        let (before, after) = (output.semantic.get_symbols(file_id).unwrap(), semantic.get_symbols(file_id).unwrap());
+        assert_eq!(after.compute_hash(), before.compute_hash());
+        for symbol in before.symbols() {
+            assert_eq!(after.lookup(&symbol.name, symbol.scope), before.lookup(&symbol.name, symbol.scope));
+        }
+    }
+}
+
+#[test]
+fn test_tampered_symbol_tables_fail_closed() {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("a.rs"), "fn a() {}\n").unwrap();
+    let snapshot = repo.path().join("snapshot.cpg");
+
+    let output = Pipeline::default().run(repo.path()).unwrap();
+    CPGSnapshot::save_with_symbols(output.cpg_epoch.cpg(), 1, &output.snapshot, &output.semantic, &snapshot).unwrap();
+
+    let mut file: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&snapshot).unwrap()).unwrap();
+    let table = file["symbols"].as_object_mut().unwrap().values_mut().next().unwrap();
+    table["symbols"][0]["name"] = "renamed".into();
+    std::fs::write(&snapshot, file.to_string()).unwrap();
+
+    let err = CPGEpoch::from_snapshot_with_symbols(&snapshot, None).err().unwrap();
+    assert!(format!("{:#}", err).contains("Symbol table hash mismatch"), "{:#}", err);
+}
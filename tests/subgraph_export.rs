@@ -0,0 +1,117 @@
+//! Subgraph extraction (`CPG::extract_subgraph`, `vcr export --subgraph`)
+//!
+//! - the same repository and root always extract the same subgraph
+//! - anonymization gives the same identifier the same pseudonym
+//! - a query over the extracted snapshot answers like the same query over
+//!   the full graph, restricted to the extracted nodes
+
+use std::fs;
+use tempfile::TempDir;
+use vcr::analysis::StableKeys;
+use vcr::cli::{self, QuerySnapshot};
+use vcr::config::ValoriConfig;
+use vcr::cpg::model::{CPGEdgeKind, CPGNodeId, CPGNodeKind, CPG};
+use vcr::cpg::{pseudonym, CPGEpoch};
+use vcr::pipeline::Pipeline;
+use vcr::query::{QueryEngine, QuerySpec};
+
+fn temp_repo() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.rs"), "fn handle_login(x: i32) -> i32 { let y = x + 1; if y > 2 { return y; } y }\n").unwrap();
+    fs::write(dir.path().join("b.rs"), "fn other() { let z = 3; }\n").unwrap();
+    dir
+}
+
+/// Full CPG and the CFG nodes fused for `handle_login`, in order
+fn ingest(dir: &TempDir) -> (CPG, Vec<CPGNodeId>, String) {
+    let output = Pipeline::default().run(dir.path()).unwrap();
+    let cpg = output.cpg_epoch.cpg().clone();
+    let function = cpg.nodes.iter().position(|node| cpg.label(node) == Some("handle_login")).unwrap();
+    let cfg_nodes: Vec<CPGNodeId> = cpg.nodes[function + 1..].iter()
+        .take_while(|node| node.kind == CPGNodeKind::CfgNode)
+        .map(|node| node.id)
+        .collect();
+    let key = StableKeys::build(output.cpg_epoch.cpg(), output.cpg_epoch.indices()).key(cfg_nodes[0]);
+    (cpg, cfg_nodes, key)
+}
+
+fn query(cpg: &CPG, pipeline: &str) -> Vec<CPGNodeId> {
+    let spec = QuerySpec::from_json(&format!(r#"{{"pipeline": {}, "limit": 1000}}"#, pipeline)).unwrap();
+    let mut nodes = QueryEngine::new().execute(cpg, &spec).unwrap().nodes;
+    nodes.sort();
+    nodes
+}
+
+#[test]
+fn test_extraction_is_deterministic() {
+    let dir = temp_repo();
+    let (first, cfg_nodes, _) = ingest(&dir);
+    let (second, _, _) = ingest(&dir);
+
+    let a = first.extract_subgraph(&[cfg_nodes[0]], 3, None);
+    let b = second.extract_subgraph(&[cfg_nodes[0]], 3, None);
+    assert!(!a.edges.is_empty());
+    assert_eq!(a.compute_hash(), b.compute_hash());
+    assert!(a.validate().is_ok());
+
+    let ids: Vec<u64> = a.nodes.iter().map(|node| node.id.0).collect();
+    assert_eq!(ids, (0..a.nodes.len() as u64).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_anonymization_is_stable() {
+    let dir = temp_repo();
+    let (cpg, _, _) = ingest(&dir);
+    let whole: Vec<CPGNodeId> = cpg.nodes.iter().map(|node| node.id).collect();
+
+    let a = cpg.extract_subgraph(&whole, 0, None).anonymized();
+    let b = ingest(&dir).0.extract_subgraph(&whole, 0, None).anonymized();
+    assert_eq!(a.compute_hash(), b.compute_hash());
+
+    let labels: Vec<&str> = a.nodes.iter().filter_map(|node| a.label(node)).collect();
+    assert!(labels.contains(&pseudonym("handle_login").as_str()));
+    assert!(labels.iter().all(|label| !label.contains("handle_login") && !label.contains("\"x\"")));
+}
+
+#[test]
+fn test_subgraph_queries_match_restricted_full_queries() {
+    let dir = temp_repo();
+    let (cpg, cfg_nodes, key) = ingest(&dir);
+    let snapshot_dir = TempDir::new().unwrap();
+    let snapshot = snapshot_dir.path().join("subgraph.snap");
+
+    // The whole control flow of handle_login, saved and restored
+    let exported = cli::export_subgraph(&ValoriConfig::default(), dir.path(), &key, 100, false, &snapshot).unwrap();
+    let restored = CPGEpoch::from_snapshot(&snapshot, Some(&exported.hash)).unwrap();
+    let subgraph = restored.cpg();
+    assert_eq!(subgraph.compute_hash(), cpg.extract_subgraph(&[cfg_nodes[0]], 100, Some(&[CPGEdgeKind::ControlFlow])).compute_hash());
+
+    // Extracted CFG nodes keep their order: map full IDs to subgraph IDs
+    let extracted: Vec<CPGNodeId> = subgraph.get_nodes_of_kind(CPGNodeKind::CfgNode).iter().map(|node| node.id).collect();
+    assert_eq!(extracted.len(), cfg_nodes.len());
+    let restrict = |full: Vec<CPGNodeId>| -> Vec<CPGNodeId> {
+        full.iter().filter_map(|id| cfg_nodes.iter().position(|node| node == id)).map(|i| extracted[i]).collect()
+    };
+
+    for pipeline in [
+        r#"[{"find": "CfgNode"}]"#,
+        r#"[{"find": "CfgNode"}, {"follow": "ControlFlow"}]"#,
+        r#"[{"find": "CfgNode"}, {"follow_reverse": "ControlFlow"}]"#,
+    ] {
+        assert_eq!(query(subgraph, pipeline), restrict(query(&cpg, pipeline)), "{}", pipeline);
+    }
+
+    // The CLI queries the exported file like any snapshot
+    let query_file = snapshot_dir.path().join("cfg.json");
+    fs::write(&query_file, r#"{"pipeline": [{"find": "CfgNode"}]}"#).unwrap();
+    let output = cli::query(&query_file, Some(QuerySnapshot::File(&snapshot)), false, None, None).unwrap();
+    assert_eq!(output.total, cfg_nodes.len());
+}
+
+#[test]
+fn test_export_rejects_unknown_key() {
+    let dir = temp_repo();
+    let out = TempDir::new().unwrap();
+    let err = cli::export_subgraph(&ValoriConfig::default(), dir.path(), "nope", 3, false, &out.path().join("s.snap")).unwrap_err();
+    assert_eq!(err.code, vcr::cli::output::ErrorCode::NotFound);
+}
@@ -0,0 +1,86 @@
+//! Time-travel queries
+//!
+//! A query run against snapshot N of the store answers from the CPG as it
+//! was saved, whatever the repo looks like now.
+
+use vcr::api::{Aggregate, ValoriAPI, ValoriError};
+use vcr::cli::{self, QuerySnapshot};
+use vcr::config::ValoriConfig;
+use vcr::storage::SnapshotId;
+use tempfile::TempDir;
+
+const COUNT_FUNCTIONS: &str = r#"{"pipeline": [{"find": "Function"}, {"count": true}]}"#;
+
+/// Save the repo, add a function, refresh and save again
+fn two_snapshots(api: &mut ValoriAPI, repo: &TempDir) -> (SnapshotId, SnapshotId) {
+    let handle = api.load_repo(repo.path().to_str().unwrap()).unwrap();
+    let before = api.auto_save(handle).unwrap().unwrap();
+    std::fs::write(repo.path().join("lib.rs"), "fn a() {}\nfn b() {}\nfn added() {}\n").unwrap();
+    api.refresh(handle).unwrap();
+    let after = api.auto_save(handle).unwrap().unwrap();
+    (before, after)
+}
+
+fn setup() -> (TempDir, TempDir, ValoriConfig) {
+    let repo = TempDir::new().unwrap();
+    std::fs::write(repo.path().join("lib.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+    let store = TempDir::new().unwrap();
+    let mut config = ValoriConfig::default();
+    config.snapshot.path = store.path().to_path_buf();
+    (repo, store, config)
+}
+
+#[test]
+fn test_query_at_each_snapshot() {
+    let (repo, _store, config) = setup();
+    let mut api = ValoriAPI::new(&config);
+    let (before, after) = two_snapshots(&mut api, &repo);
+
+    let mut count_at = |id| {
+        let result_id = api.run_query_at(id, COUNT_FUNCTIONS).unwrap();
+        let explanation = api.explain_result(result_id).unwrap();
+        assert!(explanation.ends_with(&format!("(snapshot {})", id.0)), "{}", explanation);
+        match api.fetch_aggregate(result_id).unwrap() {
+            Some(Aggregate::Count(count)) => count,
+            other => panic!("expected a count, got {:?}", other),
+        }
+    };
+    let (old, new) = (count_at(before), count_at(after));
+    assert_eq!(new, old + 1);
+    assert_eq!(count_at(before), old);
+
+    // Node results are identical across instances sharing the store
+    let functions = r#"{"pipeline": [{"find": "Function"}], "order_by": "label"}"#;
+    let fetch = |api: &mut ValoriAPI| {
+        let result_id = api.run_query_at(before, functions).unwrap();
+        api.fetch_result(result_id).unwrap()
+    };
+    assert_eq!(fetch(&mut api), fetch(&mut ValoriAPI::new(&config)));
+
+    assert_eq!(api.run_query_at(SnapshotId(99), COUNT_FUNCTIONS), Err(ValoriError::UnknownSnapshot(99)));
+    assert!(matches!(api.run_query_at(before, "{"), Err(ValoriError::InvalidQuery(_))));
+}
+
+#[test]
+fn test_cli_query_snapshot_id() {
+    let (repo, store, config) = setup();
+    let (before, after) = two_snapshots(&mut ValoriAPI::new(&config), &repo);
+    let query_file = repo.path().join("count.json");
+    std::fs::write(&query_file, COUNT_FUNCTIONS).unwrap();
+
+    let run = |id: SnapshotId| {
+        let snapshot = QuerySnapshot::Stored { dir: store.path(), id: id.0 };
+        cli::query(&query_file, Some(snapshot), false, None, None).unwrap()
+    };
+    let (old, new) = (run(before), run(after));
+    assert_eq!((old.snapshot_id, new.snapshot_id), (Some(before.0), Some(after.0)));
+    let count = |output: &cli::output::QueryOutput| match output.aggregate {
+        Some(Aggregate::Count(count)) => count,
+        ref other => panic!("expected a count, got {:?}", other),
+    };
+    assert_eq!(count(&new), count(&old) + 1);
+
+    let missing = QuerySnapshot::Stored { dir: store.path(), id: 99 };
+    let error = cli::query(&query_file, Some(missing), false, None, None).unwrap_err();
+    assert_eq!(error.code, cli::output::ErrorCode::NotFound);
+}
@@ -0,0 +1,118 @@
+//! Tracing span validation
+//!
+//! - Every pipeline stage opens a named span
+//! - Spans carry IDs, never raw paths
+//! - The library never installs a global subscriber
+
+use std::fs;
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+use vcr::api::ValoriAPI;
+
+/// Captured span: name + (field, value) pairs
+type CapturedSpan = (String, Vec<(String, String)>);
+
+/// Layer recording every span and its fields
+#[derive(Clone, Default)]
+struct CaptureLayer {
+    spans: Arc<Mutex<Vec<CapturedSpan>>>,
+}
+
+struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.push((field.name().to_string(), format!("{:?}", value)));
+    }
+}
+
+impl<S> Layer<S> for CaptureLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        let mut fields = Vec::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        self.spans.lock().unwrap().push((attrs.metadata().name().to_string(), fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let name = ctx.span(id).unwrap().name();
+        let mut spans = self.spans.lock().unwrap();
+        if let Some((_, fields)) = spans.iter_mut().rev().find(|(n, _)| n == name) {
+            values.record(&mut FieldVisitor(fields));
+        }
+    }
+}
+
+fn field<'a>(span: &'a CapturedSpan, name: &str) -> Option<&'a str> {
+    span.1.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+}
+
+#[test]
+fn test_ingest_and_query_emit_stage_spans() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("secret_name.rs"), "fn a() { let x = 1; }\nfn b() {}\n").unwrap();
+
+    let layer = CaptureLayer::default();
+    let subscriber = tracing_subscriber::registry().with(layer.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        let mut api = ValoriAPI::default();
+        let handle = api.load_repo(dir.path().to_str().unwrap()).unwrap();
+        api.run_query(handle, r#"{"pipeline": [{"find": "Function"}]}"#).unwrap();
+    });
+
+    let spans = layer.spans.lock().unwrap().clone();
+    let names: Vec<&str> = spans.iter().map(|(n, _)| n.as_str()).collect();
+
+    for expected in ["scan", "parse", "cfg", "symbols", "dfg", "fusion", "query"] {
+        assert!(names.contains(&expected), "Missing span '{}' in {:?}", expected, names);
+    }
+
+    let scan = spans.iter().find(|(n, _)| n == "scan").unwrap();
+    assert_eq!(field(scan, "files"), Some("1"));
+
+    let parse = spans.iter().find(|(n, _)| n == "parse").unwrap();
+    assert!(field(parse, "file_id").is_some());
+
+    let cfgs: Vec<_> = spans.iter().filter(|(n, _)| n == "cfg").collect();
+    assert_eq!(cfgs.len(), 2, "One cfg span per function");
+    assert_eq!(field(cfgs[0], "function_id"), Some("0"));
+    assert_eq!(field(cfgs[1], "function_id"), Some("1"));
+
+    let fusion = spans.iter().find(|(n, _)| n == "fusion").unwrap();
+    assert!(field(fusion, "epoch_id").is_some());
+    assert!(field(fusion, "nodes").is_some());
+
+    let query = spans.iter().find(|(n, _)| n == "query").unwrap();
+    assert_eq!(field(query, "stages"), Some("1"));
+
+    // FileId design principle: no raw paths in span fields
+    let tmp = dir.path().to_string_lossy().to_string();
+    for (name, fields) in &spans {
+        for (key, value) in fields {
+            assert!(!value.contains("secret_name") && !value.contains(&tmp),
+                "Span '{}' field '{}' leaks a path: {}", name, key, value);
+        }
+    }
+}
+
+#[test]
+fn test_library_sets_no_global_subscriber() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+
+    let mut api = ValoriAPI::default();
+    api.load_repo(dir.path().to_str().unwrap()).unwrap();
+
+    // Installing our own global default must still succeed
+    let subscriber = tracing_subscriber::registry().with(CaptureLayer::default());
+    assert!(tracing::subscriber::set_global_default(subscriber).is_ok());
+}